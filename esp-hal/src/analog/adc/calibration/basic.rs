@@ -24,6 +24,9 @@ pub struct AdcCalBasic<ADCI> {
     /// Calibration value to set to ADC unit
     cal_val: u16,
 
+    /// Whether the calibration value was read from efuse
+    from_efuse: bool,
+
     _phantom: PhantomData<ADCI>,
 }
 
@@ -36,13 +39,16 @@ where
     fn new_cal(atten: Attenuation) -> Self {
         // Try to get init code (Dout0) from efuse
         // Dout0 means mean raw ADC value when zero voltage applied to input.
-        let cal_val = ADCI::init_code(atten).unwrap_or_else(|| {
+        let init_code = ADCI::init_code(atten);
+        let from_efuse = init_code.is_some();
+        let cal_val = init_code.unwrap_or_else(|| {
             // As a fallback try to calibrate via connecting input to ground internally.
             AdcConfig::<ADCI>::adc_calibrate(atten, AdcCalSource::Gnd)
         });
 
         Self {
             cal_val,
+            from_efuse,
             _phantom: PhantomData,
         }
     }
@@ -50,4 +56,8 @@ where
     fn adc_cal(&self) -> u16 {
         self.cal_val
     }
+
+    fn has_efuse_calibration(&self) -> bool {
+        self.from_efuse
+    }
 }
@@ -3,6 +3,7 @@ use core::marker::PhantomData;
 use crate::analog::adc::{
     AdcCalEfuse,
     AdcCalLine,
+    AdcCalMillivolts,
     AdcCalScheme,
     AdcHasLineCal,
     Attenuation,
@@ -38,7 +39,7 @@ pub trait AdcHasCurveCal {
 /// Curve fitting ADC calibration scheme
 ///
 /// This scheme implements polynomial error correction using predefined
-/// coefficient sets for each attenuation. It returns readings in mV.
+/// coefficient sets for each attenuation, on top of the conversion to mV.
 ///
 /// This scheme also includes basic calibration ([`super::AdcCalBasic`]) and
 /// line fitting ([`AdcCalLine`]).
@@ -87,7 +88,20 @@ where
     }
 
     fn adc_val(&self, val: u16) -> u16 {
-        let val = self.line.adc_val(val);
+        self.adc_mv(val)
+    }
+
+    fn has_efuse_calibration(&self) -> bool {
+        self.line.has_efuse_calibration()
+    }
+}
+
+impl<ADCI> AdcCalMillivolts<ADCI> for AdcCalCurve<ADCI>
+where
+    ADCI: AdcCalEfuse + AdcHasLineCal + AdcHasCurveCal + CalibrationAccess,
+{
+    fn adc_mv(&self, raw: u16) -> u16 {
+        let val = self.line.adc_mv(raw);
 
         let err = if val == 0 {
             0
@@ -0,0 +1,133 @@
+use core::marker::PhantomData;
+
+use crate::{
+    analog::adc::{AdcCalMillivolts, AdcCalScheme, Attenuation},
+    efuse::Efuse,
+};
+
+/// The reference voltage assumed when it isn't stored in efuse.
+const DEFAULT_VREF: u32 = 1100;
+
+/// We store the gain as a u32, but it's really a fixed-point number.
+const GAIN_SCALE: u32 = 1 << 16;
+
+/// Characterization data of an ADC unit.
+///
+/// The constants are taken from <https://github.com/espressif/esp-idf/blob/903af13e8/components/esp_adc/esp32/adc_cali_line_fitting.c>
+trait AdcLineCoeffs {
+    /// Gain of each attenuation relative to the two-point line, scaled by
+    /// [`GAIN_SCALE`]
+    const TP_SCALES: [u32; 4];
+    /// Offset of each attenuation relative to the two-point line, in mV
+    const TP_OFFSETS: [u32; 4];
+    /// Gain of each attenuation per mV of the reference voltage, scaled by
+    /// [`GAIN_SCALE`]
+    const VREF_SCALES: [u32; 4];
+    /// Offset of each attenuation in mV, when using the reference voltage
+    const VREF_OFFSETS: [u32; 4];
+
+    /// Raw readings at 150 mV and 850 mV, if they're stored in efuse
+    fn two_point() -> Option<(u16, u16)>;
+}
+
+impl AdcLineCoeffs for crate::peripherals::ADC1 {
+    const TP_SCALES: [u32; 4] = [65504, 86975, 120389, 224310];
+    const TP_OFFSETS: [u32; 4] = [0, 1, 27, 54];
+    const VREF_SCALES: [u32; 4] = [57431, 76236, 105481, 196602];
+    const VREF_OFFSETS: [u32; 4] = [75, 78, 107, 142];
+
+    fn two_point() -> Option<(u16, u16)> {
+        Efuse::adc_two_point(1)
+    }
+}
+
+impl AdcLineCoeffs for crate::peripherals::ADC2 {
+    const TP_SCALES: [u32; 4] = [65467, 86861, 120416, 224708];
+    const TP_OFFSETS: [u32; 4] = [0, 9, 26, 66];
+    const VREF_SCALES: [u32; 4] = [57236, 76175, 105678, 197170];
+    const VREF_OFFSETS: [u32; 4] = [63, 66, 89, 128];
+
+    fn two_point() -> Option<(u16, u16)> {
+        Efuse::adc_two_point(2)
+    }
+}
+
+/// Line fitting ADC calibration scheme
+///
+/// This scheme converts readings to mV along a line, which is characterized
+/// by the calibration data in efuse. Chips either store the readings of two
+/// reference voltages (150 mV and 850 mV) for each ADC unit, or the actual
+/// voltage of the ADC's internal reference. Two-point data is preferred when
+/// both are present. If neither is, the reference voltage is assumed to be
+/// 1100 mV, which is typical but may be off by up to 100 mV.
+///
+/// The ESP32 doesn't calibrate its ADC bias, so the scheme leaves raw readings
+/// unchanged.
+///
+/// Readings at 11 dB attenuation above about 2.5 V deviate from the line. The
+/// lookup table that ESP-IDF uses to correct them is not applied.
+#[derive(Clone, Copy)]
+pub struct AdcCalLine<ADCI> {
+    /// Slope of the line, a fixed-point number with 16 fractional bits
+    gain: u32,
+
+    /// Voltage of a zero reading, in mV
+    offset: u32,
+
+    /// Whether the line was characterized with data from efuse
+    from_efuse: bool,
+
+    _phantom: PhantomData<ADCI>,
+}
+
+impl<ADCI> crate::private::Sealed for AdcCalLine<ADCI> {}
+
+impl<ADCI> AdcCalScheme<ADCI> for AdcCalLine<ADCI>
+where
+    ADCI: AdcLineCoeffs,
+{
+    fn new_cal(atten: Attenuation) -> Self {
+        let atten = atten as usize;
+
+        let (gain, offset, from_efuse) = if let Some((low, high)) = ADCI::two_point() {
+            // Fit the line through the two reference points, then adjust it for the
+            // attenuation. The `+ dx / 2` terms round to the nearest integer.
+            let (low, high) = (low as u32, high as u32);
+            let dx = high - low;
+            let dv = 850 - 150;
+
+            let gain = (dv * ADCI::TP_SCALES[atten] + dx / 2) / dx;
+            let offset = 850 - (dv * high + dx / 2) / dx + ADCI::TP_OFFSETS[atten];
+
+            (gain, offset, true)
+        } else {
+            let vref = Efuse::adc_vref();
+            let from_efuse = vref.is_some();
+            let vref = vref.map_or(DEFAULT_VREF, u32::from);
+
+            let gain = vref * ADCI::VREF_SCALES[atten] / 4096;
+
+            (gain, ADCI::VREF_OFFSETS[atten], from_efuse)
+        };
+
+        Self {
+            gain,
+            offset,
+            from_efuse,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn has_efuse_calibration(&self) -> bool {
+        self.from_efuse
+    }
+}
+
+impl<ADCI> AdcCalMillivolts<ADCI> for AdcCalLine<ADCI>
+where
+    ADCI: AdcLineCoeffs,
+{
+    fn adc_mv(&self, raw: u16) -> u16 {
+        ((raw as u32 * self.gain + GAIN_SCALE / 2) / GAIN_SCALE + self.offset) as u16
+    }
+}
@@ -3,6 +3,7 @@ use core::marker::PhantomData;
 use crate::analog::adc::{
     AdcCalBasic,
     AdcCalEfuse,
+    AdcCalMillivolts,
     AdcCalScheme,
     AdcCalSource,
     AdcConfig,
@@ -21,8 +22,8 @@ const GAIN_SCALE: u32 = 1 << 16;
 
 /// Line fitting ADC calibration scheme
 ///
-/// This scheme implements gain correction based on reference points, and
-/// returns readings in mV.
+/// This scheme implements gain correction based on reference points, which
+/// converts readings to mV.
 ///
 /// A reference point is a pair of a reference voltage and the corresponding
 /// mean raw digital ADC value. Such values are usually stored in efuse bit
@@ -45,6 +46,9 @@ pub struct AdcCalLine<ADCI> {
     /// number with 16 fractional bits.
     gain: u32,
 
+    /// Whether the reference point was read from efuse
+    from_efuse: bool,
+
     _phantom: PhantomData<ADCI>,
 }
 
@@ -59,9 +63,9 @@ where
 
         // Try get the reference point (Dout, Vin) from efuse
         // Dout means mean raw ADC value when specified Vin applied to input.
-        let (code, mv) = ADCI::cal_code(atten)
-            .map(|code| (code, ADCI::cal_mv(atten)))
-            .unwrap_or_else(|| {
+        let reference = ADCI::cal_code(atten).map(|code| (code, ADCI::cal_mv(atten)));
+        let from_efuse = basic.has_efuse_calibration() && reference.is_some();
+        let (code, mv) = reference.unwrap_or_else(|| {
                 // As a fallback try to calibrate using reference voltage source.
                 // This method is not too good because actual reference voltage may varies
                 // in range 1000..=1200 mV and this value currently cannot be read from efuse.
@@ -82,6 +86,7 @@ where
         Self {
             basic,
             gain,
+            from_efuse,
             _phantom: PhantomData,
         }
     }
@@ -91,13 +96,24 @@ where
     }
 
     fn adc_val(&self, val: u16) -> u16 {
-        let val = self.basic.adc_val(val);
+        self.adc_mv(val)
+    }
+
+    fn has_efuse_calibration(&self) -> bool {
+        self.from_efuse
+    }
+}
 
-        (val as u32 * self.gain / GAIN_SCALE) as u16
+impl<ADCI> AdcCalMillivolts<ADCI> for AdcCalLine<ADCI>
+where
+    ADCI: AdcCalEfuse + AdcHasLineCal + CalibrationAccess,
+{
+    fn adc_mv(&self, raw: u16) -> u16 {
+        (raw as u32 * self.gain / GAIN_SCALE) as u16
     }
 }
 
-#[cfg(any(esp32c2, esp32c3, esp32c6, esp32s3))]
+#[cfg(any(esp32c2, esp32c3, esp32c6, esp32h2, esp32s3))]
 impl AdcHasLineCal for crate::peripherals::ADC1 {}
 
 #[cfg(any(esp32c3, esp32s3))]
@@ -1,14 +1,18 @@
 #[cfg(any(esp32c3, esp32c6, esp32s3))]
 pub use self::curve::{AdcCalCurve, AdcHasCurveCal};
-#[cfg(any(esp32c2, esp32c3, esp32c6, esp32s3))]
+#[cfg(esp32)]
+pub use self::esp32::AdcCalLine;
+#[cfg(any(esp32c2, esp32c3, esp32c6, esp32h2, esp32s3))]
 pub use self::{
     basic::AdcCalBasic,
     line::{AdcCalLine, AdcHasLineCal},
 };
 
-#[cfg(any(esp32c2, esp32c3, esp32c6, esp32s3))]
+#[cfg(any(esp32c2, esp32c3, esp32c6, esp32h2, esp32s3))]
 mod basic;
 #[cfg(any(esp32c3, esp32c6, esp32s3))]
 mod curve;
-#[cfg(any(esp32c2, esp32c3, esp32c6, esp32s3))]
+#[cfg(esp32)]
+mod esp32;
+#[cfg(any(esp32c2, esp32c3, esp32c6, esp32h2, esp32s3))]
 mod line;
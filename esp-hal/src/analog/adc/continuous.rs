@@ -0,0 +1,608 @@
+//! Continuous conversions by the ADC's digital controller, moved by DMA.
+
+use core::{future::poll_fn, mem::ManuallyDrop, task::Poll};
+
+use fugit::HertzU32;
+
+use super::{Adc, Resolution, NUM_ATTENS};
+use crate::{
+    analog::adc::{AdcChannel, AdcPin, Attenuation},
+    clock::Clocks,
+    dma::{
+        ChannelRx,
+        DmaPeripheral,
+        DmaRxBuffer,
+        DmaRxInterrupt,
+        DmaRxStreamBuf,
+        DmaRxStreamBufView,
+        PeripheralRxChannel,
+        Rx,
+        RxChannelFor,
+    },
+    peripheral::Peripheral,
+    peripherals::{ADC1, APB_SARADC},
+    Async,
+    Blocking,
+    DriverMode,
+};
+
+// The digital controller's clock is its source clock divided by 16, and each
+// conversion takes two of its timer's periods.
+const CLKM_DIV_NUM: u32 = 15;
+
+// The sample frequencies the SAR ADC supports in continuous mode.
+const MIN_SAMPLE_FREQUENCY: u32 = 611;
+const MAX_SAMPLE_FREQUENCY: u32 = 83_333;
+
+cfg_if::cfg_if! {
+    if #[cfg(esp32s3)] {
+        const MAX_PATTERN_LEN: usize = 16;
+    } else {
+        const MAX_PATTERN_LEN: usize = 8;
+    }
+}
+
+/// An entry of the pattern table, i.e. one conversion of a round of
+/// conversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PatternEntry {
+    channel: u8,
+    attenuation: Attenuation,
+    resolution: Resolution,
+}
+
+impl PatternEntry {
+    /// Converts the voltage at `pin`, with the given attenuation, at 12 bits.
+    pub fn new<PIN, CS>(_pin: &AdcPin<PIN, ADC1, CS>, attenuation: Attenuation) -> Self
+    where
+        PIN: AdcChannel,
+    {
+        Self {
+            channel: PIN::CHANNEL,
+            attenuation,
+            resolution: Resolution::Resolution12Bit,
+        }
+    }
+
+    /// Sets the resolution of the conversion.
+    ///
+    /// Continuous mode only supports 12 bits on the chips it's available on.
+    pub fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
+    }
+
+    // An item of the pattern table: the attenuation in bits 0..2 and the
+    // channel above it, in bits 2..5 followed by the ADC unit on the ESP32-C3,
+    // ESP32-C6 and ESP32-H2, and in bits 2..6 on the ESP32-S3, which has a
+    // table for each unit.
+    fn bits(&self) -> u32 {
+        (self.attenuation as u32) | ((self.channel as u32) << 2)
+    }
+}
+
+/// Continuous mode configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, procmacros::BuilderLite)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct ContinuousConfig {
+    /// The number of conversions per second, across all entries of the pattern
+    /// table.
+    ///
+    /// It has to be between 611 Hz and 83.333 kHz.
+    sample_frequency: HertzU32,
+}
+
+impl Default for ContinuousConfig {
+    fn default() -> Self {
+        Self {
+            sample_frequency: HertzU32::kHz(20),
+        }
+    }
+}
+
+/// Continuous mode configuration error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+#[allow(
+    clippy::enum_variant_names,
+    reason = "named like the configuration errors of the other drivers"
+)]
+pub enum ConfigError {
+    /// The pattern table is empty, or has more than 8 entries, 16 on the
+    /// ESP32-S3.
+    UnsupportedPatternLength,
+    /// An entry of the pattern table selects a resolution that continuous
+    /// mode doesn't support.
+    UnsupportedResolution,
+    /// The sample frequency is out of range.
+    UnsupportedSampleFrequency,
+}
+
+impl core::error::Error for ConfigError {}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::UnsupportedPatternLength => {
+                write!(
+                    f,
+                    "The pattern table must have between 1 and {} entries",
+                    MAX_PATTERN_LEN
+                )
+            }
+            ConfigError::UnsupportedResolution => {
+                write!(f, "The resolution isn't supported in continuous mode")
+            }
+            ConfigError::UnsupportedSampleFrequency => {
+                write!(f, "The sample frequency is out of range")
+            }
+        }
+    }
+}
+
+/// Continuous mode error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The DMA ran out of descriptors, because samples weren't read fast
+    /// enough. The samples taken in the meantime were lost, and the
+    /// conversions started over with the first entry of the pattern table.
+    Overrun,
+}
+
+impl core::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Overrun => write!(f, "Samples were lost because they weren't read in time"),
+        }
+    }
+}
+
+/// A conversion result of continuous mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AdcSample {
+    /// The channel that was converted, i.e. the [`AdcChannel::CHANNEL`] of its
+    /// pin
+    pub channel: u8,
+    /// The raw conversion result
+    pub value: u16,
+}
+
+impl AdcSample {
+    // The DMA receives each result in the type-2 format, a little-endian word
+    // with the value in bits 0..12. The channel follows in bits 13..16 and the
+    // ADC unit in bit 16, except on the ESP32-S3, whose channel takes bits
+    // 13..17 and moves the unit to bit 17. Results of other units, or of
+    // channels that don't exist, are invalid.
+    fn parse(word: [u8; 4]) -> Option<Self> {
+        let word = u32::from_le_bytes(word);
+        cfg_if::cfg_if! {
+            if #[cfg(esp32s3)] {
+                let channel = ((word >> 13) & 0xf) as u8;
+                let unit = (word >> 17) & 0x1;
+            } else {
+                let channel = ((word >> 13) & 0x7) as u8;
+                let unit = (word >> 16) & 0x1;
+            }
+        }
+        if unit != 0 || channel as usize >= NUM_ATTENS {
+            return None;
+        }
+
+        Some(Self {
+            channel,
+            value: (word & 0xfff) as u16,
+        })
+    }
+}
+
+impl<'d> Adc<'d, ADC1, Blocking> {
+    /// Converts the pattern table continuously, and streams the samples into
+    /// `buffer` with DMA.
+    ///
+    /// The conversions start right away. See the
+    /// [module documentation](crate::analog::adc#continuous-mode) for how samples flow.
+    ///
+    /// ## Example
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::analog::adc::{
+    /// #     Adc, AdcConfig, Attenuation, ContinuousConfig, PatternEntry,
+    /// # };
+    /// # use esp_hal::dma_rx_stream_buffer;
+    #[cfg_attr(esp32s3, doc = "let analog_pin = peripherals.GPIO3;")]
+    #[cfg_attr(not(esp32s3), doc = "let analog_pin = peripherals.GPIO2;")]
+    /// let mut adc1_config = AdcConfig::new();
+    /// let pin = adc1_config.enable_pin(analog_pin, Attenuation::_11dB);
+    /// let adc1 = Adc::new(peripherals.ADC1, adc1_config);
+    ///
+    /// let mut adc1 = adc1.into_continuous(
+    ///     peripherals.DMA_CH0,
+    ///     &[PatternEntry::new(&pin, Attenuation::_11dB)],
+    ///     ContinuousConfig::default().with_sample_frequency(40.kHz()),
+    ///     dma_rx_stream_buffer!(4096, 512),
+    /// )?;
+    ///
+    /// let mut values = [0u16; 256];
+    /// let count = adc1.read(&mut values)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_continuous<CH>(
+        self,
+        channel: impl Peripheral<P = CH> + 'd,
+        pattern: &[PatternEntry],
+        config: ContinuousConfig,
+        buffer: DmaRxStreamBuf,
+    ) -> Result<AdcDma<'d, Blocking>, ConfigError>
+    where
+        CH: RxChannelFor<ADC1>,
+    {
+        if pattern.is_empty() || pattern.len() > MAX_PATTERN_LEN {
+            return Err(ConfigError::UnsupportedPatternLength);
+        }
+        if pattern
+            .iter()
+            .any(|entry| entry.resolution != Resolution::Resolution12Bit)
+        {
+            return Err(ConfigError::UnsupportedResolution);
+        }
+        let sample_frequency = config.sample_frequency.to_Hz();
+        if !(MIN_SAMPLE_FREQUENCY..=MAX_SAMPLE_FREQUENCY).contains(&sample_frequency) {
+            return Err(ConfigError::UnsupportedSampleFrequency);
+        }
+
+        configure(pattern, sample_frequency);
+
+        let mut channel = ChannelRx::new(channel.map(|ch| ch.degrade()));
+        let rx_view = start(&mut channel, buffer);
+
+        Ok(AdcDma {
+            controller: DigitalController { _adc: self },
+            channel,
+            rx_view: ManuallyDrop::new(rx_view),
+        })
+    }
+}
+
+fn configure(pattern: &[PatternEntry], sample_frequency: u32) {
+    let regs = APB_SARADC::regs();
+
+    cfg_if::cfg_if! {
+        if #[cfg(esp32s3)] {
+            // Hand ADC1 over from the RTC controller of the oneshot reads.
+            crate::peripherals::SENS::regs()
+                .sar_meas1_mux()
+                .modify(|_, w| w.sar1_dig_force().set_bit());
+        } else {
+            // The oneshot reads share the SAR ADC.
+            regs.onetime_sample().modify(|_, w| {
+                w.saradc1_onetime_sample().clear_bit();
+                w.onetime_start().clear_bit()
+            });
+        }
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(esp32c3, esp32s3))] {
+            let source = Clocks::get().apb_clock.to_Hz();
+            regs.clkm_conf().modify(|_, w| unsafe {
+                w.clkm_div_num().bits(CLKM_DIV_NUM as u8);
+                w.clkm_div_b().bits(1);
+                w.clkm_div_a().bits(0);
+                // APB_CLK
+                w.clk_sel().bits(2);
+                w.clk_en().set_bit()
+            });
+        } else {
+            let source = Clocks::get().xtal_clock.to_Hz();
+            crate::peripherals::PCR::regs()
+                .saradc_clkm_conf()
+                .modify(|_, w| unsafe {
+                    w.saradc_clkm_div_num().bits(CLKM_DIV_NUM as u8);
+                    w.saradc_clkm_div_b().bits(1);
+                    w.saradc_clkm_div_a().bits(0);
+                    // XTAL_CLK
+                    w.saradc_clkm_sel().bits(0);
+                    w.saradc_clkm_en().set_bit()
+                });
+        }
+    }
+    let interval = source / (CLKM_DIV_NUM + 1) / 2 / sample_frequency;
+
+    // Values taken from ESP-IDF.
+    regs.fsm_wait().modify(|_, w| unsafe {
+        w.rstb_wait().bits(8);
+        w.xpd_wait().bits(5);
+        w.standby_wait().bits(100)
+    });
+
+    // Each register holds four 6-bit items, the first one in the top bits.
+    let mut table = [0u32; MAX_PATTERN_LEN / 4];
+    for (i, entry) in pattern.iter().enumerate() {
+        table[i / 4] |= entry.bits() << (18 - (i % 4) * 6);
+    }
+
+    regs.ctrl().modify(|_, w| unsafe {
+        // Let the timer start the conversions.
+        w.start_force().clear_bit();
+        w.start().clear_bit();
+        w.sar_clk_div().bits(1)
+    });
+
+    cfg_if::cfg_if! {
+        if #[cfg(esp32s3)] {
+            regs.sar1_patt_tab1()
+                .write(|w| unsafe { w.sar1_patt_tab1().bits(table[0]) });
+            regs.sar1_patt_tab2()
+                .write(|w| unsafe { w.sar1_patt_tab2().bits(table[1]) });
+            regs.sar1_patt_tab3()
+                .write(|w| unsafe { w.sar1_patt_tab3().bits(table[2]) });
+            regs.sar1_patt_tab4()
+                .write(|w| unsafe { w.sar1_patt_tab4().bits(table[3]) });
+
+            regs.ctrl().modify(|_, w| unsafe {
+                // Only convert with ADC1.
+                w.work_mode().bits(0);
+                w.sar_sel().clear_bit();
+                w.sar1_patt_len().bits(pattern.len() as u8 - 1);
+                w.sar1_patt_p_clear().set_bit()
+            });
+            regs.ctrl().modify(|_, w| w.sar1_patt_p_clear().clear_bit());
+
+            regs.ctrl2().modify(|_, w| unsafe {
+                w.meas_num_limit().clear_bit();
+                w.sar1_inv().clear_bit();
+                // The SAR ADC timer, not the I2S word select.
+                w.timer_sel().set_bit();
+                w.timer_target().bits(interval as u16)
+            });
+        } else {
+            regs.sar_patt_tab1()
+                .write(|w| unsafe { w.sar_patt_tab1().bits(table[0]) });
+            regs.sar_patt_tab2()
+                .write(|w| unsafe { w.sar_patt_tab2().bits(table[1]) });
+
+            regs.ctrl().modify(|_, w| unsafe {
+                w.sar_patt_len().bits(pattern.len() as u8 - 1);
+                w.sar_patt_p_clear().set_bit()
+            });
+            regs.ctrl().modify(|_, w| w.sar_patt_p_clear().clear_bit());
+
+            regs.ctrl2().modify(|_, w| unsafe {
+                w.meas_num_limit().clear_bit();
+                w.timer_target().bits(interval as u16)
+            });
+        }
+    }
+
+    // Samples are handed over when a descriptor is full, so the DMA doesn't
+    // need end-of-frame markers.
+    regs.dma_conf()
+        .modify(|_, w| unsafe { w.adc_eof_num().bits(u16::MAX) });
+}
+
+// Starts the conversions from the first entry of the pattern table.
+fn start(channel: &mut impl Rx, mut buffer: DmaRxStreamBuf) -> DmaRxStreamBufView {
+    let regs = APB_SARADC::regs();
+
+    unwrap!(unsafe { channel.prepare_transfer(DmaPeripheral::Adc, &mut buffer) });
+    unwrap!(channel.start_transfer());
+
+    // Resetting the FSM also resets the pattern table pointer.
+    regs.dma_conf().modify(|_, w| w.adc_reset_fsm().set_bit());
+    regs.dma_conf().modify(|_, w| w.adc_reset_fsm().clear_bit());
+    regs.dma_conf().modify(|_, w| w.adc_trans().set_bit());
+    regs.ctrl2().modify(|_, w| w.timer_en().set_bit());
+
+    buffer.into_view()
+}
+
+fn stop() {
+    let regs = APB_SARADC::regs();
+    regs.ctrl2().modify(|_, w| w.timer_en().clear_bit());
+    regs.dma_conf().modify(|_, w| w.adc_trans().clear_bit());
+}
+
+/// The ADC, converting continuously.
+struct DigitalController<'d> {
+    _adc: Adc<'d, ADC1, Blocking>,
+}
+
+impl Drop for DigitalController<'_> {
+    fn drop(&mut self) {
+        stop();
+    }
+}
+
+/// An ADC driver that converts continuously, and moves the samples with DMA.
+///
+/// Created by [`Adc::into_continuous`].
+pub struct AdcDma<'d, Dm: DriverMode> {
+    // Dropped first, so that the conversions stop before the DMA channel is
+    // released.
+    controller: DigitalController<'d>,
+    channel: ChannelRx<'d, Dm, PeripheralRxChannel<ADC1>>,
+    rx_view: ManuallyDrop<DmaRxStreamBufView>,
+}
+
+impl<'d> AdcDma<'d, Blocking> {
+    /// Converts the driver to [`Async`] mode.
+    pub fn into_async(self) -> AdcDma<'d, Async> {
+        AdcDma {
+            controller: self.controller,
+            channel: self.channel.into_async(),
+            rx_view: self.rx_view,
+        }
+    }
+
+    /// Reads the values of samples, waiting until at least one has been
+    /// taken.
+    ///
+    /// The values follow the order of the pattern table, which starts over
+    /// after an overrun.
+    ///
+    /// Returns the number of values read, or [`Error::Overrun`] once after
+    /// samples were lost.
+    pub fn read(&mut self, values: &mut [u16]) -> Result<usize, Error> {
+        self.read_with(values, |sample| sample.value)
+    }
+
+    /// Reads samples, with the channels they were taken on, waiting until at
+    /// least one has been taken.
+    ///
+    /// Returns the number of samples read, or [`Error::Overrun`] once after
+    /// samples were lost.
+    pub fn read_samples(&mut self, samples: &mut [AdcSample]) -> Result<usize, Error> {
+        self.read_with(samples, |sample| sample)
+    }
+
+    fn read_with<T>(
+        &mut self,
+        out: &mut [T],
+        convert: impl Fn(AdcSample) -> T,
+    ) -> Result<usize, Error> {
+        loop {
+            let count = self.read_available_with(out, &convert)?;
+            if count > 0 || out.is_empty() {
+                return Ok(count);
+            }
+        }
+    }
+}
+
+impl<'d> AdcDma<'d, Async> {
+    /// Converts the driver to [`Blocking`] mode.
+    pub fn into_blocking(self) -> AdcDma<'d, Blocking> {
+        AdcDma {
+            controller: self.controller,
+            channel: self.channel.into_blocking(),
+            rx_view: self.rx_view,
+        }
+    }
+
+    /// Reads the values of samples, waiting until at least one has been
+    /// taken.
+    ///
+    /// See [`AdcDma::read`].
+    pub async fn read_async(&mut self, values: &mut [u16]) -> Result<usize, Error> {
+        self.read_async_with(values, |sample| sample.value).await
+    }
+
+    /// Reads samples, with the channels they were taken on, waiting until at
+    /// least one has been taken.
+    ///
+    /// See [`AdcDma::read_samples`].
+    pub async fn read_samples_async(&mut self, samples: &mut [AdcSample]) -> Result<usize, Error> {
+        self.read_async_with(samples, |sample| sample).await
+    }
+
+    async fn read_async_with<T>(
+        &mut self,
+        out: &mut [T],
+        convert: impl Fn(AdcSample) -> T,
+    ) -> Result<usize, Error> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+
+        poll_fn(|cx| {
+            self.channel.waker().register(cx.waker());
+            self.channel.clear_in(DmaRxInterrupt::Done);
+            match self.read_available_with(out, &convert) {
+                Ok(0) => {}
+                result => return Poll::Ready(result),
+            }
+            self.channel
+                .listen_in(DmaRxInterrupt::Done | DmaRxInterrupt::DescriptorEmpty);
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<Dm> AdcDma<'_, Dm>
+where
+    Dm: DriverMode,
+{
+    /// Reads the values of the samples that have been taken, without waiting.
+    ///
+    /// See [`AdcDma::read`].
+    pub fn read_available(&mut self, values: &mut [u16]) -> Result<usize, Error> {
+        self.read_available_with(values, |sample| sample.value)
+    }
+
+    /// Reads the samples that have been taken, with the channels they were
+    /// taken on, without waiting.
+    ///
+    /// Returns the number of samples read, or [`Error::Overrun`] once after
+    /// samples were lost.
+    pub fn read_available_samples(&mut self, samples: &mut [AdcSample]) -> Result<usize, Error> {
+        self.read_available_with(samples, |sample| sample)
+    }
+
+    fn read_available_with<T>(
+        &mut self,
+        out: &mut [T],
+        convert: impl Fn(AdcSample) -> T,
+    ) -> Result<usize, Error> {
+        let mut count = 0;
+        while count < out.len() {
+            let data = self.rx_view.peek();
+            let words = data.len() / 4;
+            if words > 0 {
+                let words = words.min(out.len() - count);
+                for word in data[..words * 4].chunks_exact(4) {
+                    if let Some(sample) = AdcSample::parse([word[0], word[1], word[2], word[3]]) {
+                        out[count] = convert(sample);
+                        count += 1;
+                    }
+                }
+                self.rx_view.consume(words * 4);
+            } else if !data.is_empty() && self.rx_view.available_bytes() >= 4 {
+                // The word wraps around the end of the buffer.
+                let mut word = [0; 4];
+                self.rx_view.pop(&mut word);
+                if let Some(sample) = AdcSample::parse(word) {
+                    out[count] = convert(sample);
+                    count += 1;
+                }
+            } else {
+                break;
+            }
+        }
+
+        if count == 0 {
+            self.restart_if_stalled()?;
+        }
+
+        Ok(count)
+    }
+
+    // The DMA stops when it runs out of descriptors. Restarting resets every
+    // descriptor, so it has to wait until all the samples have been read.
+    fn restart_if_stalled(&mut self) -> Result<(), Error> {
+        let stalled = self
+            .channel
+            .pending_in_interrupts()
+            .contains(DmaRxInterrupt::DescriptorEmpty);
+        if !stalled || self.rx_view.available_bytes() >= 4 {
+            return Ok(());
+        }
+
+        stop();
+        self.channel.stop_transfer();
+        let view = unsafe { ManuallyDrop::take(&mut self.rx_view) };
+        let view = start(&mut self.channel, DmaRxStreamBuf::from_view(view));
+        self.rx_view = ManuallyDrop::new(view);
+
+        Err(Error::Overrun)
+    }
+}
@@ -1,11 +1,14 @@
 use core::marker::PhantomData;
 
-use super::{AdcConfig, Attenuation};
+pub use self::calibration::*;
+use super::{AdcCalMillivolts, AdcCalScheme, AdcChannel, AdcConfig, AdcPin, Attenuation};
 use crate::{
     peripheral::PeripheralRef,
     peripherals::{ADC1, ADC2, RTC_IO, SENS},
 };
 
+mod calibration;
+
 pub(super) const NUM_ATTENS: usize = 10;
 
 /// The sampling/readout resolution of the ADC.
@@ -202,6 +205,7 @@ impl RegisterAccess for ADC2 {
 /// Analog-to-Digital Converter peripheral driver.
 pub struct Adc<'d, ADC, Dm: crate::DriverMode> {
     _adc: PeripheralRef<'d, ADC>,
+    resolution: Resolution,
     attenuations: [Option<Attenuation>; NUM_ATTENS],
     active_channel: Option<u8>,
     _phantom: PhantomData<Dm>,
@@ -230,7 +234,7 @@ where
 
         for (channel, attentuation) in attenuations.iter().enumerate() {
             if let Some(attenuation) = attentuation {
-                ADC1::set_attenuation(channel, *attenuation as u8);
+                ADCI::set_attenuation(channel, *attenuation as u8);
             }
         }
 
@@ -281,6 +285,7 @@ where
 
         Adc {
             _adc: adc_instance.into_ref(),
+            resolution: config.resolution,
             attenuations: config.attenuations,
             active_channel: None,
             _phantom: PhantomData,
@@ -292,9 +297,16 @@ where
     /// This method takes an [AdcPin](super::AdcPin) reference, as it is
     /// expected that the ADC will be able to sample whatever channel
     /// underlies the pin.
-    pub fn read_oneshot<PIN>(&mut self, _pin: &mut super::AdcPin<PIN, ADCI>) -> nb::Result<u16, ()>
+    ///
+    /// Unlike on the other chips, readings aren't converted by the pin's
+    /// calibration scheme, so this works like [Adc::read_raw]. Use
+    /// [Adc::read_mv] for millivolts.
+    pub fn read_oneshot<PIN, CS>(
+        &mut self,
+        _pin: &mut AdcPin<PIN, ADCI, CS>,
+    ) -> nb::Result<u16, ()>
     where
-        PIN: super::AdcChannel,
+        PIN: AdcChannel,
     {
         if self.attenuations[PIN::CHANNEL as usize].is_none() {
             panic!("Channel {} is not configured reading!", PIN::CHANNEL);
@@ -331,6 +343,50 @@ where
 
         Ok(converted_value)
     }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// return the reading without converting it
+    pub fn read_raw<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> nb::Result<u16, ()>
+    where
+        PIN: AdcChannel,
+    {
+        self.read_oneshot(pin)
+    }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// convert the result to millivolts
+    ///
+    /// This works like [Adc::read_raw], but the reading is converted by the
+    /// pin's calibration scheme.
+    pub fn read_mv<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> nb::Result<u16, ()>
+    where
+        PIN: AdcChannel,
+        CS: AdcCalMillivolts<ADCI>,
+    {
+        let raw = self.read_raw(pin)?;
+
+        // The calibration data is characterized for 12-bit readings
+        let raw = raw << (Resolution::Resolution12Bit as u8 - self.resolution as u8);
+
+        Ok(pin.cal_scheme.adc_mv(raw))
+    }
+
+    /// Change the attenuation of the specified pin
+    ///
+    /// The pin's calibration scheme is set up again for the new attenuation.
+    pub fn set_attenuation<PIN, CS>(
+        &mut self,
+        pin: &mut AdcPin<PIN, ADCI, CS>,
+        attenuation: Attenuation,
+    ) where
+        PIN: AdcChannel,
+        CS: AdcCalScheme<ADCI>,
+    {
+        self.attenuations[PIN::CHANNEL as usize] = Some(attenuation);
+        ADCI::set_attenuation(PIN::CHANNEL as usize, attenuation as u8);
+
+        pin.cal_scheme = CS::new_cal(attenuation);
+    }
 }
 
 impl<ADC1> Adc<'_, ADC1, crate::Blocking> {
@@ -50,9 +50,94 @@
 //! # }
 //! ```
 //! 
+//! ## Calibration
+//!
+//! A pin enabled with `enable_pin_with_cal` is read through its calibration
+//! scheme. `Adc::read_mv` converts readings to millivolts, and `Adc::read_raw`
+//! returns them unconverted, only with the bias correction of the scheme
+//! applied. `Adc::read_oneshot` postprocesses readings with the scheme, so it
+//! returns millivolts for `AdcCalLine` and `AdcCalCurve`, except on the ESP32,
+//! where it returns raw readings.
+//! The schemes are built from calibration data that is measured for each chip
+//! during manufacturing and burned into eFuse. When it's missing, they fall
+//! back to less accurate runtime measurements or typical values, which
+//! [`AdcPin::has_efuse_calibration`] reports. Changing the attenuation of a
+//! pin with `Adc::set_attenuation` sets up its scheme again.
+//!
+//! | Chip                         | Schemes                                          |
+//! |------------------------------|--------------------------------------------------|
+//! | ESP32                        | `AdcCalLine`, from two-point or Vref data        |
+//! | ESP32-S2                     | None                                             |
+//! | ESP32-C2                     | `AdcCalBasic`, `AdcCalLine`                      |
+//! | ESP32-C3, ESP32-C6, ESP32-S3 | `AdcCalBasic`, `AdcCalLine`, `AdcCalCurve`       |
+//! | ESP32-H2                     | `AdcCalBasic`, `AdcCalLine`, measured at runtime |
+//!
+#![cfg_attr(esp32s2, doc = "```rust, ignore")]
+#![cfg_attr(not(esp32s2), doc = "```rust, no_run")]
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::analog::adc::{Adc, AdcCalLine, AdcConfig, Attenuation};
+//! # use esp_hal::peripherals::ADC1;
+#![cfg_attr(esp32, doc = "let analog_pin = peripherals.GPIO32;")]
+#![cfg_attr(any(esp32s2, esp32s3), doc = "let analog_pin = peripherals.GPIO3;")]
+#![cfg_attr(
+    not(any(esp32, esp32s2, esp32s3)),
+    doc = "let analog_pin = peripherals.GPIO2;"
+)]
+//! let mut adc1_config = AdcConfig::new();
+//! let mut pin = adc1_config.enable_pin_with_cal::<_, AdcCalLine<ADC1>>(
+//!     analog_pin,
+//!     Attenuation::_11dB,
+//! );
+//! let mut adc1 = Adc::new(peripherals.ADC1, adc1_config);
+//!
+//! let millivolts: u16 = nb::block!(adc1.read_mv(&mut pin))?;
+//!
+//! adc1.set_attenuation(&mut pin, Attenuation::_0dB);
+//! let millivolts: u16 = nb::block!(adc1.read_mv(&mut pin))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Continuous mode
+//!
+//! In continuous mode, a timer of the ADC's digital controller starts
+//! conversions at a fixed sample frequency. The controller walks through a
+//! pattern table of up to 8 entries, 16 on the ESP32-S3, which select the
+//! channel, attenuation and resolution of each conversion, and the DMA streams
+//! the results into a [`DmaRxStreamBuf`] without any CPU involvement. This
+//! reaches sample rates of tens of kilosamples per second, which oneshot reads
+//! can't. `read` returns the values of the samples, in the order of the
+//! pattern table, while `read_samples` also returns the channel each sample was
+//! taken on.
+//!
+//! The DMA hands samples over to the driver one descriptor at a time, when the
+//! descriptor is full, so smaller descriptors make samples available sooner.
+//! If the samples aren't read fast enough, the DMA runs out of descriptors and
+//! stops. The next read reports the overrun, and the conversions start over
+//! with the first entry of the pattern table.
+//!
+//! Continuous mode is available through `Adc::into_continuous`:
+//!
+//! | Chip                                   | Continuous mode               |
+//! |----------------------------------------|-------------------------------|
+//! | ESP32, ESP32-S2                        | Not implemented               |
+//! | ESP32-C2                               | Not supported by the hardware |
+//! | ESP32-C3, ESP32-C6, ESP32-H2, ESP32-S3 | ADC1, at 12 bits              |
+//!
+//! [`DmaRxStreamBuf`]: crate::dma::DmaRxStreamBuf
+//!
 //! ## Implementation State
 //!
-//!  - [ADC calibration is not implemented for all targets].
+//!  - [ADC calibration is not implemented for all targets]. The ESP32-S2 has
+//!    no calibration schemes, as neither its eFuse calibration table nor its
+//!    internal calibration sources are supported yet, and the ESP32-H2
+//!    doesn't read its calibration data from eFuse yet.
+//!  - The ESP32's `AdcCalLine` doesn't correct readings above about 2.5 V at
+//!    11 dB attenuation with the lookup table ESP-IDF uses.
+//!  - Continuous mode isn't implemented on the ESP32 and ESP32-S2, which move
+//!    their samples with the I2S and SPI DMA, in the type-1 data format, and
+//!    are the only chips converting at other resolutions than 12 bits in
+//!    continuous mode.
 //!
 //! [ADC calibration is not implemented for all targets]: https://github.com/esp-rs/esp-hal/issues/326
 use core::marker::PhantomData;
@@ -102,11 +187,26 @@ pub struct AdcPin<PIN, ADCI, CS = ()> {
     /// The underlying GPIO pin
     pub pin: PIN,
     /// Calibration scheme used for the configured ADC pin
-    #[cfg_attr(esp32, allow(unused))]
     pub cal_scheme: CS,
     _phantom: PhantomData<ADCI>,
 }
 
+impl<PIN, ADCI, CS> AdcPin<PIN, ADCI, CS>
+where
+    CS: AdcCalScheme<ADCI>,
+{
+    /// Returns whether the pin's calibration scheme uses the calibration data
+    /// of the chip, which is measured for each chip during manufacturing and
+    /// burned into eFuse.
+    ///
+    /// Schemes fall back to less accurate measurements at runtime, or to
+    /// typical values, when the data is missing. Pins without a calibration
+    /// scheme always return `false`.
+    pub fn has_efuse_calibration(&self) -> bool {
+        self.cal_scheme.has_efuse_calibration()
+    }
+}
+
 /// Configuration for the ADC.
 pub struct AdcConfig<ADCI> {
     #[cfg_attr(not(esp32), allow(unused))]
@@ -139,14 +239,12 @@ impl<ADCI> AdcConfig<ADCI> {
 
     /// Enable the specified pin with the given attenuation and calibration
     /// scheme
-    #[cfg(not(esp32))]
     pub fn enable_pin_with_cal<PIN, CS>(
         &mut self,
         pin: PIN,
         attenuation: Attenuation,
     ) -> AdcPin<PIN, ADCI, CS>
     where
-        ADCI: CalibrationAccess,
         PIN: AdcChannel + AnalogPin,
         CS: AdcCalScheme<ADCI>,
     {
@@ -209,6 +307,19 @@ pub trait AdcCalScheme<ADCI>: Sized + crate::private::Sealed {
     fn adc_val(&self, val: u16) -> u16 {
         val
     }
+
+    /// Return whether the scheme was set up with calibration data from eFuse.
+    fn has_efuse_calibration(&self) -> bool {
+        false
+    }
+}
+
+/// A calibration scheme which can convert readings to millivolts.
+///
+/// Pins using such a scheme can be read with `Adc::read_mv`.
+pub trait AdcCalMillivolts<ADCI>: AdcCalScheme<ADCI> {
+    /// Convert a raw ADC reading to millivolts.
+    fn adc_mv(&self, raw: u16) -> u16;
 }
 
 impl crate::private::Sealed for () {}
@@ -218,7 +329,7 @@ impl<ADCI> AdcCalScheme<ADCI> for () {
 }
 
 /// A helper trait to get access to ADC calibration efuses.
-#[cfg(not(any(esp32, esp32s2, esp32h2)))]
+#[cfg(not(any(esp32, esp32s2)))]
 trait AdcCalEfuse {
     /// Get ADC calibration init code
     ///
@@ -8,9 +8,10 @@ cfg_if::cfg_if! {
     }
 }
 
-#[cfg(not(esp32h2))]
 pub use self::calibration::*;
-use super::{AdcCalSource, AdcConfig, Attenuation};
+#[cfg(any(esp32c3, esp32c6, esp32h2))]
+pub use self::continuous::*;
+use super::{AdcCalMillivolts, AdcCalScheme, AdcCalSource, AdcChannel, AdcConfig, AdcPin, Attenuation};
 #[cfg(any(esp32c6, esp32h2))]
 use crate::clock::clocks_ll::regi2c_write_mask;
 #[cfg(any(esp32c2, esp32c3, esp32c6))]
@@ -26,6 +27,8 @@ use crate::{
 };
 
 mod calibration;
+#[cfg(any(esp32c3, esp32c6, esp32h2))]
+mod continuous;
 
 // polyfill for c2 and c3
 #[cfg(any(esp32c2, esp32c3))]
@@ -460,6 +463,10 @@ where
     /// This method takes an [AdcPin](super::AdcPin) reference, as it is
     /// expected that the ADC will be able to sample whatever channel
     /// underlies the pin.
+    ///
+    /// The reading is postprocessed by the pin's calibration scheme, so pins
+    /// using `AdcCalLine` or `AdcCalCurve` return millivolts. Use
+    /// [Adc::read_raw] for the reading itself.
     pub fn read_oneshot<PIN, CS>(
         &mut self,
         pin: &mut super::AdcPin<PIN, ADCI, CS>,
@@ -467,6 +474,22 @@ where
     where
         PIN: super::AdcChannel,
         CS: super::AdcCalScheme<ADCI>,
+    {
+        let raw = self.read_raw(pin)?;
+
+        // Postprocess converted value according to calibration scheme used for pin
+        Ok(pin.cal_scheme.adc_val(raw))
+    }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// return the reading without converting it
+    ///
+    /// The pin's calibration scheme still corrects the ADC's bias, but
+    /// readings aren't converted to millivolts. Use [Adc::read_mv] for that.
+    pub fn read_raw<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> nb::Result<u16, ()>
+    where
+        PIN: AdcChannel,
+        CS: AdcCalScheme<ADCI>,
     {
         if self.attenuations[PIN::CHANNEL as usize].is_none() {
             panic!("Channel {} is not configured reading!", PIN::CHANNEL);
@@ -510,9 +533,6 @@ where
         let converted_value = ADCI::read_data();
         ADCI::reset();
 
-        // Postprocess converted value according to calibration scheme used for pin
-        let converted_value = pin.cal_scheme.adc_val(converted_value);
-
         // There is a hardware limitation. If the APB clock frequency is high, the step
         // of this reg signal: ``onetime_start`` may not be captured by the
         // ADC digital controller (when its clock frequency is too slow). A rough
@@ -528,6 +548,45 @@ where
 
         Ok(converted_value)
     }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// convert the result to millivolts
+    ///
+    /// This works like [Adc::read_raw], but the reading is converted by the
+    /// pin's calibration scheme.
+    pub fn read_mv<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> nb::Result<u16, ()>
+    where
+        PIN: AdcChannel,
+        CS: AdcCalMillivolts<ADCI>,
+    {
+        let raw = self.read_raw(pin)?;
+
+        Ok(pin.cal_scheme.adc_mv(raw))
+    }
+}
+
+impl<ADCI, Dm> Adc<'_, ADCI, Dm>
+where
+    ADCI: RegisterAccess,
+    Dm: crate::DriverMode,
+{
+    /// Change the attenuation of the specified pin
+    ///
+    /// The pin's calibration scheme is set up again for the new attenuation,
+    /// which may take a few conversions if it measures the internal
+    /// references.
+    pub fn set_attenuation<PIN, CS>(
+        &mut self,
+        pin: &mut AdcPin<PIN, ADCI, CS>,
+        attenuation: Attenuation,
+    ) where
+        PIN: AdcChannel,
+        CS: AdcCalScheme<ADCI>,
+    {
+        self.attenuations[PIN::CHANNEL as usize] = Some(attenuation);
+
+        pin.cal_scheme = CS::new_cal(attenuation);
+    }
 }
 
 impl<ADCI> crate::private::Sealed for Adc<'_, ADCI, Blocking> {}
@@ -560,6 +619,23 @@ impl super::AdcCalEfuse for crate::peripherals::ADC1 {
     }
 }
 
+// The calibration data of the ESP32-H2 isn't decoded from efuse yet, so the
+// schemes measure the internal references at runtime instead.
+#[cfg(esp32h2)]
+impl super::AdcCalEfuse for crate::peripherals::ADC1 {
+    fn init_code(_atten: Attenuation) -> Option<u16> {
+        None
+    }
+
+    fn cal_mv(_atten: Attenuation) -> u16 {
+        1100
+    }
+
+    fn cal_code(_atten: Attenuation) -> Option<u16> {
+        None
+    }
+}
+
 #[cfg(adc2)]
 impl super::AdcCalEfuse for crate::peripherals::ADC2 {
     fn init_code(atten: Attenuation) -> Option<u16> {
@@ -661,11 +737,32 @@ where
     /// This method takes an [AdcPin](super::AdcPin) reference, as it is
     /// expected that the ADC will be able to sample whatever channel
     /// underlies the pin.
+    ///
+    /// The reading is postprocessed by the pin's calibration scheme, so pins
+    /// using `AdcCalLine` or `AdcCalCurve` return millivolts. Use
+    /// [Adc::read_raw] for the reading itself.
     pub async fn read_oneshot<PIN, CS>(&mut self, pin: &mut super::AdcPin<PIN, ADCI, CS>) -> u16
     where
         ADCI: asynch::AsyncAccess,
         PIN: super::AdcChannel,
         CS: super::AdcCalScheme<ADCI>,
+    {
+        let raw = self.read_raw(pin).await;
+
+        // Postprocess converted value according to calibration scheme used for pin
+        pin.cal_scheme.adc_val(raw)
+    }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// return the reading without converting it
+    ///
+    /// The pin's calibration scheme still corrects the ADC's bias, but
+    /// readings aren't converted to millivolts. Use [Adc::read_mv] for that.
+    pub async fn read_raw<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> u16
+    where
+        ADCI: asynch::AsyncAccess,
+        PIN: AdcChannel,
+        CS: AdcCalScheme<ADCI>,
     {
         let channel = PIN::CHANNEL;
         if self.attenuations[channel as usize].is_none() {
@@ -696,8 +793,23 @@ where
 
         ADCI::reset();
 
-        // Postprocess converted value according to calibration scheme used for pin
-        pin.cal_scheme.adc_val(converted_value)
+        converted_value
+    }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// convert the result to millivolts
+    ///
+    /// This works like [Adc::read_raw], but the reading is converted by the
+    /// pin's calibration scheme.
+    pub async fn read_mv<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> u16
+    where
+        ADCI: asynch::AsyncAccess,
+        PIN: AdcChannel,
+        CS: AdcCalMillivolts<ADCI>,
+    {
+        let raw = self.read_raw(pin).await;
+
+        pin.cal_scheme.adc_mv(raw)
     }
 }
 
@@ -2,7 +2,17 @@ use core::marker::PhantomData;
 
 #[cfg(esp32s3)]
 pub use self::calibration::*;
-use super::{AdcCalScheme, AdcCalSource, AdcChannel, AdcConfig, AdcPin, Attenuation};
+#[cfg(esp32s3)]
+pub use self::continuous::*;
+use super::{
+    AdcCalMillivolts,
+    AdcCalScheme,
+    AdcCalSource,
+    AdcChannel,
+    AdcConfig,
+    AdcPin,
+    Attenuation,
+};
 #[cfg(esp32s3)]
 use crate::efuse::Efuse;
 use crate::{
@@ -12,6 +22,8 @@ use crate::{
 };
 
 mod calibration;
+#[cfg(esp32s3)]
+mod continuous;
 
 pub(super) const NUM_ATTENS: usize = 10;
 
@@ -82,6 +94,9 @@ pub enum Resolution {
     /// 13-bit resolution
     #[default]
     Resolution13Bit,
+    /// 12-bit resolution, which continuous mode converts at
+    #[cfg(esp32s3)]
+    Resolution12Bit,
 }
 
 impl<ADCI> AdcConfig<ADCI>
@@ -499,6 +514,10 @@ where
     /// This method takes an [AdcPin](super::AdcPin) reference, as it is
     /// expected that the ADC will be able to sample whatever channel
     /// underlies the pin.
+    ///
+    /// The reading is postprocessed by the pin's calibration scheme, so pins
+    /// using `AdcCalLine` or `AdcCalCurve` return millivolts. Use
+    /// [Adc::read_raw] for the reading itself.
     pub fn read_oneshot<PIN, CS>(
         &mut self,
         pin: &mut super::AdcPin<PIN, ADCI, CS>,
@@ -506,6 +525,22 @@ where
     where
         PIN: super::AdcChannel,
         CS: super::AdcCalScheme<ADCI>,
+    {
+        let raw = self.read_raw(pin)?;
+
+        // Postprocess converted value according to calibration scheme used for pin
+        Ok(pin.cal_scheme.adc_val(raw))
+    }
+
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// return the reading without converting it
+    ///
+    /// The pin's calibration scheme still corrects the ADC's bias, but
+    /// readings aren't converted to millivolts. Use [Adc::read_mv] for that.
+    pub fn read_raw<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> nb::Result<u16, ()>
+    where
+        PIN: AdcChannel,
+        CS: AdcCalScheme<ADCI>,
     {
         if let Some(active_channel) = self.active_channel {
             // There is conversion in progress:
@@ -531,15 +566,45 @@ where
         let converted_value = ADCI::read_data();
         ADCI::reset();
 
-        // Postprocess converted value according to calibration scheme used for pin
-        let converted_value = pin.cal_scheme.adc_val(converted_value);
-
         // Mark that no conversions are currently in progress
         self.active_channel = None;
 
         Ok(converted_value)
     }
 
+    /// Request that the ADC begin a conversion on the specified pin, and
+    /// convert the result to millivolts
+    ///
+    /// This works like [Adc::read_raw], but the reading is converted by the
+    /// pin's calibration scheme.
+    pub fn read_mv<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>) -> nb::Result<u16, ()>
+    where
+        PIN: AdcChannel,
+        CS: AdcCalMillivolts<ADCI>,
+    {
+        let raw = self.read_raw(pin)?;
+
+        Ok(pin.cal_scheme.adc_mv(raw))
+    }
+
+    /// Change the attenuation of the specified pin
+    ///
+    /// The pin's calibration scheme is set up again for the new attenuation,
+    /// which may take a few conversions if it measures the internal
+    /// references.
+    pub fn set_attenuation<PIN, CS>(
+        &mut self,
+        pin: &mut AdcPin<PIN, ADCI, CS>,
+        attenuation: Attenuation,
+    ) where
+        PIN: AdcChannel,
+        CS: AdcCalScheme<ADCI>,
+    {
+        pin.cal_scheme = CS::new_cal(attenuation);
+
+        ADCI::set_attenuation(PIN::CHANNEL as usize, attenuation as u8);
+    }
+
     fn start_sample<PIN, CS>(&mut self, pin: &mut AdcPin<PIN, ADCI, CS>)
     where
         PIN: AdcChannel,
@@ -1309,19 +1309,36 @@ pub(crate) struct TxCircularState {
     buffer_len: usize,
 
     first_desc_ptr: *mut DmaDescriptor,
+
+    /// Whether the DMA hands the descriptors it finished back to the CPU.
+    write_back: bool,
 }
 
 impl TxCircularState {
     pub(crate) fn new(chain: &mut DescriptorChain) -> Self {
+        Self::with_write_back(chain, true)
+    }
+
+    /// Creates the state of a ring whose descriptors the DMA may not write
+    /// back. Their owner bit never changes then, so an underrun can only be
+    /// detected by the whole ring having been transmitted.
+    pub(crate) fn with_write_back(chain: &mut DescriptorChain, write_back: bool) -> Self {
         Self {
             write_offset: 0,
             write_descr_ptr: chain.first_mut(),
             available: 0,
-            last_seen_handled_descriptor_ptr: chain.first_mut(),
+            last_seen_handled_descriptor_ptr: if write_back {
+                chain.first_mut()
+            } else {
+                // The DMA starts with the first descriptor, so the one before it is the
+                // last one we've "seen".
+                chain.last_mut()
+            },
             buffer_start: chain.descriptors[0].buffer as _,
             buffer_len: chain.descriptors.iter().map(|d| d.len()).sum(),
 
             first_desc_ptr: chain.first_mut(),
+            write_back,
         }
     }
 
@@ -1354,59 +1371,94 @@ impl TxCircularState {
 
             let descr_address = channel.last_out_dscr_address() as *mut DmaDescriptor;
 
-            let mut ptr = self.last_seen_handled_descriptor_ptr;
-            if descr_address >= self.last_seen_handled_descriptor_ptr {
-                unsafe {
-                    while ptr < descr_address {
-                        let dw0 = ptr.read_volatile();
-                        self.available += dw0.len();
-                        ptr = ptr.offset(1);
-                    }
-                }
+            if self.write_back {
+                self.update_written_back(descr_address);
             } else {
-                unsafe {
-                    while !((*ptr).next.is_null() || (*ptr).next == self.first_desc_ptr) {
-                        let dw0 = ptr.read_volatile();
-                        self.available += dw0.len();
-                        ptr = ptr.offset(1);
-                    }
+                self.update_not_written_back(descr_address);
+            }
+
+            self.last_seen_handled_descriptor_ptr = descr_address;
+        }
 
-                    // add bytes pointed to by the last descriptor
+        // Without write-back, the owner check above can't detect an underrun,
+        // but the whole buffer being available means the DMA has started over
+        // with data we didn't push.
+        if !self.write_back && self.available >= self.buffer_len {
+            return Err(DmaError::Late);
+        }
+
+        Ok(())
+    }
+
+    fn update_written_back(&mut self, descr_address: *mut DmaDescriptor) {
+        let mut ptr = self.last_seen_handled_descriptor_ptr;
+        if descr_address >= self.last_seen_handled_descriptor_ptr {
+            unsafe {
+                while ptr < descr_address {
                     let dw0 = ptr.read_volatile();
                     self.available += dw0.len();
-
-                    // in circular mode we need to honor the now available bytes at start
-                    if (*ptr).next == self.first_desc_ptr {
-                        ptr = self.first_desc_ptr;
-                        while ptr < descr_address {
-                            let dw0 = ptr.read_volatile();
-                            self.available += dw0.len();
-                            ptr = ptr.offset(1);
-                        }
-                    }
+                    ptr = ptr.offset(1);
                 }
             }
+        } else {
+            unsafe {
+                while !((*ptr).next.is_null() || (*ptr).next == self.first_desc_ptr) {
+                    let dw0 = ptr.read_volatile();
+                    self.available += dw0.len();
+                    ptr = ptr.offset(1);
+                }
 
-            if self.available >= self.buffer_len {
-                unsafe {
-                    let dw0 = self.write_descr_ptr.read_volatile();
-                    let segment_len = dw0.len();
-                    let next_descriptor = dw0.next;
-                    self.available -= segment_len;
-                    self.write_offset = (self.write_offset + segment_len) % self.buffer_len;
-
-                    self.write_descr_ptr = if next_descriptor.is_null() {
-                        self.first_desc_ptr
-                    } else {
-                        next_descriptor
+                // add bytes pointed to by the last descriptor
+                let dw0 = ptr.read_volatile();
+                self.available += dw0.len();
+
+                // in circular mode we need to honor the now available bytes at start
+                if (*ptr).next == self.first_desc_ptr {
+                    ptr = self.first_desc_ptr;
+                    while ptr < descr_address {
+                        let dw0 = ptr.read_volatile();
+                        self.available += dw0.len();
+                        ptr = ptr.offset(1);
                     }
                 }
             }
+        }
 
-            self.last_seen_handled_descriptor_ptr = descr_address;
+        if self.available >= self.buffer_len {
+            unsafe {
+                let dw0 = self.write_descr_ptr.read_volatile();
+                let segment_len = dw0.len();
+                let next_descriptor = dw0.next;
+                self.available -= segment_len;
+                self.write_offset = (self.write_offset + segment_len) % self.buffer_len;
+
+                self.write_descr_ptr = if next_descriptor.is_null() {
+                    self.first_desc_ptr
+                } else {
+                    next_descriptor
+                }
+            }
         }
+    }
 
-        Ok(())
+    fn update_not_written_back(&mut self, descr_address: *mut DmaDescriptor) {
+        // Every descriptor after the last one we've seen, up to and including the
+        // one the DMA has just finished, can be refilled. Following the links
+        // (instead of comparing addresses) keeps the count exact when the DMA
+        // has wrapped around the end of the ring since the last update.
+        let mut ptr = self.last_seen_handled_descriptor_ptr;
+        while ptr != descr_address {
+            ptr = match unsafe { ptr.read_volatile() }.next {
+                next if next.is_null() => self.first_desc_ptr,
+                next => next,
+            };
+            self.available += unsafe { ptr.read_volatile() }.len();
+
+            if ptr == self.last_seen_handled_descriptor_ptr {
+                // The address isn't part of our ring, don't loop forever.
+                break;
+            }
+        }
     }
 
     pub(crate) fn push(&mut self, data: &[u8]) -> Result<usize, DmaError> {
@@ -1519,13 +1571,21 @@ impl RxCircularState {
     }
 
     pub(crate) fn pop(&mut self, data: &mut [u8]) -> Result<usize, DmaError> {
-        let len = data.len();
-        let mut avail = self.available;
-
-        if avail > len {
+        if self.available > data.len() {
             return Err(DmaError::BufferTooSmall);
         }
 
+        self.pop_descriptors(data)
+    }
+
+    /// Pops as many whole descriptors as fit into `data`.
+    ///
+    /// Fails only if there is data available, but the next descriptor doesn't
+    /// fit.
+    pub(crate) fn pop_descriptors(&mut self, data: &mut [u8]) -> Result<usize, DmaError> {
+        let len = data.len();
+        let mut avail = self.available;
+
         let mut remaining_buffer = data;
         let mut descr_ptr = self.read_descr_ptr;
 
@@ -1535,6 +1595,10 @@ impl RxCircularState {
 
         let mut descr = unsafe { descr_ptr.read_volatile() };
 
+        if avail > 0 && remaining_buffer.len() < descr.len() {
+            return Err(DmaError::BufferTooSmall);
+        }
+
         while avail > 0 && !remaining_buffer.is_empty() && remaining_buffer.len() >= descr.len() {
             unsafe {
                 let dst = remaining_buffer.as_mut_ptr();
@@ -2809,6 +2873,15 @@ where
         Self { instance, state }
     }
 
+    /// Creates a transfer for a DMA channel that doesn't write its
+    /// descriptors back, which reports an underrun once the DMA has
+    /// transmitted the whole ring.
+    #[allow(unused)] // currently used by peripherals not available on all chips
+    pub(crate) fn new_without_write_back(instance: &'a mut I) -> Self {
+        let state = TxCircularState::with_write_back(instance.chain(), false);
+        Self { instance, state }
+    }
+
     /// Amount of bytes which can be pushed.
     pub fn available(&mut self) -> Result<usize, DmaError> {
         self.state.update(self.instance.tx())?;
@@ -3039,7 +3112,7 @@ pub(crate) mod asynch {
     }
 
     #[cfg(any(i2s0, i2s1))]
-    pub struct DmaTxDoneChFuture<'a, TX>
+    pub struct DmaTxEofChFuture<'a, TX>
     where
         TX: Tx,
     {
@@ -3048,7 +3121,7 @@ pub(crate) mod asynch {
     }
 
     #[cfg(any(i2s0, i2s1))]
-    impl<'a, TX> DmaTxDoneChFuture<'a, TX>
+    impl<'a, TX> DmaTxEofChFuture<'a, TX>
     where
         TX: Tx,
     {
@@ -3058,7 +3131,7 @@ pub(crate) mod asynch {
     }
 
     #[cfg(any(i2s0, i2s1))]
-    impl<TX> core::future::Future for DmaTxDoneChFuture<'_, TX>
+    impl<TX> core::future::Future for DmaTxEofChFuture<'_, TX>
     where
         TX: Tx,
     {
@@ -3068,12 +3141,12 @@ pub(crate) mod asynch {
             self: core::pin::Pin<&mut Self>,
             cx: &mut core::task::Context<'_>,
         ) -> Poll<Self::Output> {
+            // The interrupt is left pending, it's consumed by `TxCircularState::update`.
             if self
                 .tx
                 .pending_out_interrupts()
-                .contains(DmaTxInterrupt::Done)
+                .contains(DmaTxInterrupt::Eof)
             {
-                self.tx.clear_out(DmaTxInterrupt::Done);
                 Poll::Ready(Ok(()))
             } else if self
                 .tx
@@ -3085,20 +3158,20 @@ pub(crate) mod asynch {
             } else {
                 self.tx.waker().register(cx.waker());
                 self.tx
-                    .listen_out(DmaTxInterrupt::Done | DmaTxInterrupt::DescriptorError);
+                    .listen_out(DmaTxInterrupt::Eof | DmaTxInterrupt::DescriptorError);
                 Poll::Pending
             }
         }
     }
 
     #[cfg(any(i2s0, i2s1))]
-    impl<TX> Drop for DmaTxDoneChFuture<'_, TX>
+    impl<TX> Drop for DmaTxEofChFuture<'_, TX>
     where
         TX: Tx,
     {
         fn drop(&mut self) {
             self.tx
-                .unlisten_out(DmaTxInterrupt::Done | DmaTxInterrupt::DescriptorError);
+                .unlisten_out(DmaTxInterrupt::Eof | DmaTxInterrupt::DescriptorError);
         }
     }
 
@@ -3222,7 +3295,10 @@ pub(crate) mod asynch {
             .contains(DmaTxInterrupt::DescriptorError)
         {
             tx.unlisten(
-                DmaTxInterrupt::DescriptorError | DmaTxInterrupt::TotalEof | DmaTxInterrupt::Done,
+                DmaTxInterrupt::DescriptorError
+                    | DmaTxInterrupt::TotalEof
+                    | DmaTxInterrupt::Eof
+                    | DmaTxInterrupt::Done,
             );
             tx.waker().wake()
         }
@@ -3234,6 +3310,13 @@ pub(crate) mod asynch {
             tx.waker().wake()
         }
 
+        if tx.pending_interrupts().contains(DmaTxInterrupt::Eof)
+            && tx.is_listening().contains(DmaTxInterrupt::Eof)
+        {
+            tx.unlisten(DmaTxInterrupt::Eof);
+            tx.waker().wake()
+        }
+
         if tx.pending_interrupts().contains(DmaTxInterrupt::Done) {
             tx.unlisten(DmaTxInterrupt::Done);
             tx.waker().wake()
@@ -128,9 +128,9 @@ impl TxRegisterAccess for AnySpiDmaTxChannel {
         }
     }
 
-    fn set_auto_write_back(&self, enable: bool) {
-        // there is no `auto_wrback` for SPI
-        assert!(!enable);
+    fn set_auto_write_back(&self, _enable: bool) {
+        // there is no `auto_wrback` for SPI, the descriptors of circular
+        // transfers stay owned by the DMA
     }
 
     fn last_dscr_address(&self) -> usize {
@@ -0,0 +1,438 @@
+//! # Dedicated GPIO
+//!
+//! ## Overview
+//! Dedicated GPIO lets the CPU read and write up to 8 output and 8 input
+//! pins directly, without going through the peripheral bus. The outputs can be
+//! updated in a single instruction, which makes it possible to bit-bang
+//! protocols at several MHz with predictable timing.
+//!
+//! Pins are bound to the CPU channels via the GPIO matrix. Bit `n` of the
+//! values written and read by [DedicatedGpio] corresponds to the pin bound to
+//! channel `n`.
+//!
+//! ## Implementation State
+//! - On the RISC-V chips, the channels are accessed via CPU control and status
+//!   registers.
+//! - On the ESP32-S2, the channels are accessed via the peripheral registers by
+//!   default. Enable the `dedicated-gpio-instructions` feature to use the
+//!   dedicated GPIO CPU instructions instead.
+//! - On the ESP32-S3, the channels are always accessed via CPU instructions.
+//!   The channels belong to the first core, so the driver must be used from
+//!   it.
+//!
+//! ## Examples
+//! ### Pulse a Pin While Another One Is High
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::gpio::dedicated::DedicatedGpio;
+//! let mut gpio = DedicatedGpio::new(peripherals.DEDICATED_GPIO);
+//! let output = gpio.add_output(peripherals.GPIO1)?;
+//! let input = gpio.add_input(peripherals.GPIO2)?;
+//!
+//! loop {
+//!     if gpio.read_bits() & (1 << input) != 0 {
+//!         gpio.write_bits(1 << output);
+//!         gpio.write_bits(0);
+//!     }
+//! }
+//! # }
+//! ```
+
+use core::fmt::Display;
+
+#[cfg(not(any(esp32c6, esp32h2)))]
+use crate::system::{Peripheral as PeripheralEnable, PeripheralGuard};
+use crate::{
+    gpio::{
+        interconnect::{OutputConnection, PeripheralInput, PeripheralOutput},
+        InputSignal,
+        Level,
+        OutputSignal,
+        PinGuard,
+        Pull,
+    },
+    peripheral::{Peripheral, PeripheralRef},
+    peripherals::DEDICATED_GPIO,
+};
+
+/// The number of output and of input channels.
+const CHANNEL_COUNT: usize = 8;
+
+cfg_if::cfg_if! {
+    if #[cfg(any(esp32c2, esp32c3))] {
+        const INPUT_SIGNALS: [InputSignal; CHANNEL_COUNT] = [
+            InputSignal::CPU_GPIO_0,
+            InputSignal::CPU_GPIO_1,
+            InputSignal::CPU_GPIO_2,
+            InputSignal::CPU_GPIO_3,
+            InputSignal::CPU_GPIO_4,
+            InputSignal::CPU_GPIO_5,
+            InputSignal::CPU_GPIO_6,
+            InputSignal::CPU_GPIO_7,
+        ];
+        const OUTPUT_SIGNALS: [OutputSignal; CHANNEL_COUNT] = [
+            OutputSignal::CPU_GPIO_0,
+            OutputSignal::CPU_GPIO_1,
+            OutputSignal::CPU_GPIO_2,
+            OutputSignal::CPU_GPIO_3,
+            OutputSignal::CPU_GPIO_4,
+            OutputSignal::CPU_GPIO_5,
+            OutputSignal::CPU_GPIO_6,
+            OutputSignal::CPU_GPIO_7,
+        ];
+    } else if #[cfg(any(esp32c6, esp32h2))] {
+        #[cfg(esp32c6)]
+        const INPUT_SIGNALS: [InputSignal; CHANNEL_COUNT] = [
+            InputSignal::CPU_GPIO_IN0,
+            InputSignal::CPU_GPIO_IN1,
+            InputSignal::CPU_GPIO_IN2,
+            InputSignal::CPU_GPIO_IN3,
+            InputSignal::CPU_GPIO_IN4,
+            InputSignal::CPU_GPIO_IN5,
+            InputSignal::CPU_GPIO_IN6,
+            InputSignal::CPU_GPIO_IN7,
+        ];
+        #[cfg(esp32h2)]
+        const INPUT_SIGNALS: [InputSignal; CHANNEL_COUNT] = [
+            InputSignal::CPU_GPIO0,
+            InputSignal::CPU_GPIO1,
+            InputSignal::CPU_GPIO2,
+            InputSignal::CPU_GPIO3,
+            InputSignal::CPU_GPIO4,
+            InputSignal::CPU_GPIO5,
+            InputSignal::CPU_GPIO6,
+            InputSignal::CPU_GPIO7,
+        ];
+        const OUTPUT_SIGNALS: [OutputSignal; CHANNEL_COUNT] = [
+            OutputSignal::CPU_GPIO_OUT0,
+            OutputSignal::CPU_GPIO_OUT1,
+            OutputSignal::CPU_GPIO_OUT2,
+            OutputSignal::CPU_GPIO_OUT3,
+            OutputSignal::CPU_GPIO_OUT4,
+            OutputSignal::CPU_GPIO_OUT5,
+            OutputSignal::CPU_GPIO_OUT6,
+            OutputSignal::CPU_GPIO_OUT7,
+        ];
+    } else {
+        const INPUT_SIGNALS: [InputSignal; CHANNEL_COUNT] = [
+            InputSignal::PRO_ALONEGPIO_IN0,
+            InputSignal::PRO_ALONEGPIO_IN1,
+            InputSignal::PRO_ALONEGPIO_IN2,
+            InputSignal::PRO_ALONEGPIO_IN3,
+            InputSignal::PRO_ALONEGPIO_IN4,
+            InputSignal::PRO_ALONEGPIO_IN5,
+            InputSignal::PRO_ALONEGPIO_IN6,
+            InputSignal::PRO_ALONEGPIO_IN7,
+        ];
+        const OUTPUT_SIGNALS: [OutputSignal; CHANNEL_COUNT] = [
+            OutputSignal::PRO_ALONEGPIO_OUT0,
+            OutputSignal::PRO_ALONEGPIO_OUT1,
+            OutputSignal::PRO_ALONEGPIO_OUT2,
+            OutputSignal::PRO_ALONEGPIO_OUT3,
+            OutputSignal::PRO_ALONEGPIO_OUT4,
+            OutputSignal::PRO_ALONEGPIO_OUT5,
+            OutputSignal::PRO_ALONEGPIO_OUT6,
+            OutputSignal::PRO_ALONEGPIO_OUT7,
+        ];
+    }
+}
+
+/// Errors that can occur when binding a pin to a dedicated GPIO channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// All channels of the requested direction are already in use.
+    NoChannelAvailable,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::NoChannelAvailable => write!(f, "All dedicated GPIO channels are in use"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Dedicated GPIO driver.
+///
+/// Output pins are bound to the output channels with
+/// [`add_output`][Self::add_output], input pins to the input channels with
+/// [`add_input`][Self::add_input]. The channels are allocated in ascending
+/// order. Dropping the driver releases the pins back to the GPIO matrix.
+#[derive(Debug)]
+pub struct DedicatedGpio<'d> {
+    _peripheral: PeripheralRef<'d, DEDICATED_GPIO>,
+    outputs: [PinGuard; CHANNEL_COUNT],
+    output_channels: u8,
+    input_channels: u8,
+    #[cfg(not(any(esp32c6, esp32h2)))]
+    _guard: PeripheralGuard,
+}
+
+impl<'d> DedicatedGpio<'d> {
+    /// Creates a new dedicated GPIO driver with no pins bound to it.
+    ///
+    /// # Panics
+    ///
+    /// On the ESP32-S3, panics if not called on the first core.
+    pub fn new(peripheral: impl Peripheral<P = DEDICATED_GPIO> + 'd) -> Self {
+        crate::into_ref!(peripheral);
+
+        #[cfg(esp32s3)]
+        assert!(
+            crate::Cpu::current() == crate::Cpu::ProCpu,
+            "Dedicated GPIO can only be used on the first core"
+        );
+
+        #[cfg(not(any(esp32c6, esp32h2)))]
+        let guard = PeripheralGuard::new(PeripheralEnable::DedicatedGpio);
+
+        ll::init();
+
+        Self {
+            _peripheral: peripheral,
+            outputs: core::array::from_fn(|ch| PinGuard::new_unconnected(OUTPUT_SIGNALS[ch])),
+            output_channels: 0,
+            input_channels: 0,
+            #[cfg(not(any(esp32c6, esp32h2)))]
+            _guard: guard,
+        }
+    }
+
+    /// Binds an output pin to the next free output channel.
+    ///
+    /// The pin is configured as a push-pull output. Returns the channel the
+    /// pin was bound to, which is the bit that controls the pin in
+    /// [`Self::write_bits`].
+    pub fn add_output(
+        &mut self,
+        pin: impl Peripheral<P = impl PeripheralOutput> + 'd,
+    ) -> Result<u8, Error> {
+        let channel = Self::next_channel(self.output_channels)?;
+
+        crate::into_mapped_ref!(pin);
+        pin.set_to_push_pull_output();
+        self.outputs[channel as usize] =
+            OutputConnection::connect_with_guard(pin, OUTPUT_SIGNALS[channel as usize]);
+
+        self.output_channels |= 1 << channel;
+        ll::enable_outputs(self.output_channels);
+
+        Ok(channel)
+    }
+
+    /// Binds an input pin to the next free input channel.
+    ///
+    /// Returns the channel the pin was bound to, which is the bit that
+    /// reflects the pin in [`Self::read_bits`].
+    pub fn add_input(
+        &mut self,
+        pin: impl Peripheral<P = impl PeripheralInput> + 'd,
+    ) -> Result<u8, Error> {
+        let channel = Self::next_channel(self.input_channels)?;
+
+        crate::into_mapped_ref!(pin);
+        pin.init_input(Pull::None);
+        INPUT_SIGNALS[channel as usize].connect_to(pin);
+
+        self.input_channels |= 1 << channel;
+
+        Ok(channel)
+    }
+
+    fn next_channel(allocated: u8) -> Result<u8, Error> {
+        let channel = allocated.trailing_ones() as u8;
+        if channel as usize >= CHANNEL_COUNT {
+            return Err(Error::NoChannelAvailable);
+        }
+        Ok(channel)
+    }
+
+    /// Sets the level of every output channel.
+    ///
+    /// Bit `n` sets the level of the pin bound to output channel `n`.
+    #[inline(always)]
+    pub fn write_bits(&mut self, bits: u8) {
+        ll::write_all(bits);
+    }
+
+    /// Sets the level of the output channels selected by `mask`.
+    ///
+    /// Channels whose bit is clear in `mask` keep their level.
+    #[inline(always)]
+    pub fn write_masked(&mut self, mask: u8, bits: u8) {
+        ll::write_masked(mask, bits);
+    }
+
+    /// Returns the level the output channels are set to.
+    #[inline(always)]
+    pub fn output_bits(&self) -> u8 {
+        ll::read_output()
+    }
+
+    /// Returns the level of every input channel.
+    ///
+    /// Bit `n` is set if the pin bound to input channel `n` is high. Bits of
+    /// unused channels are undefined.
+    #[inline(always)]
+    pub fn read_bits(&self) -> u8 {
+        ll::read_input()
+    }
+}
+
+impl Drop for DedicatedGpio<'_> {
+    fn drop(&mut self) {
+        // The output pins are released by their guards.
+        ll::enable_outputs(0);
+
+        for (channel, signal) in INPUT_SIGNALS.iter().enumerate() {
+            if self.input_channels & (1 << channel) != 0 {
+                signal.connect_to(Level::Low);
+            }
+        }
+    }
+}
+
+#[cfg(riscv)]
+mod ll {
+    use core::arch::asm;
+
+    // The control and status registers of the dedicated GPIO channels.
+    const CSR_GPIO_OEN_USER: u16 = 0x803;
+    const CSR_GPIO_IN_USER: u16 = 0x804;
+    const CSR_GPIO_OUT_USER: u16 = 0x805;
+
+    pub(super) fn init() {}
+
+    pub(super) fn enable_outputs(channels: u8) {
+        unsafe { asm!("csrw {csr}, {0}", in(reg) channels as u32, csr = const CSR_GPIO_OEN_USER) };
+    }
+
+    #[inline(always)]
+    pub(super) fn write_all(bits: u8) {
+        unsafe { asm!("csrw {csr}, {0}", in(reg) bits as u32, csr = const CSR_GPIO_OUT_USER) };
+    }
+
+    #[inline(always)]
+    pub(super) fn write_masked(mask: u8, bits: u8) {
+        let set = (mask & bits) as u32;
+        let clear = (mask & !bits) as u32;
+        unsafe {
+            asm!(
+                "csrs {csr}, {set}",
+                "csrc {csr}, {clear}",
+                set = in(reg) set,
+                clear = in(reg) clear,
+                csr = const CSR_GPIO_OUT_USER,
+            )
+        };
+    }
+
+    #[inline(always)]
+    pub(super) fn read_output() -> u8 {
+        let bits: u32;
+        unsafe { asm!("csrr {0}, {csr}", out(reg) bits, csr = const CSR_GPIO_OUT_USER) };
+        bits as u8
+    }
+
+    #[inline(always)]
+    pub(super) fn read_input() -> u8 {
+        let bits: u32;
+        unsafe { asm!("csrr {0}, {csr}", out(reg) bits, csr = const CSR_GPIO_IN_USER) };
+        bits as u8
+    }
+}
+
+#[cfg(all(esp32s2, not(feature = "dedicated-gpio-instructions")))]
+mod ll {
+    use crate::peripherals::DEDICATED_GPIO;
+
+    pub(super) fn init() {
+        // Drive every channel from the output registers.
+        DEDICATED_GPIO::regs()
+            .out_cpu()
+            .write(|w| unsafe { w.bits(0) });
+    }
+
+    pub(super) fn enable_outputs(_channels: u8) {}
+
+    #[inline(always)]
+    pub(super) fn write_all(bits: u8) {
+        DEDICATED_GPIO::regs()
+            .out_drt()
+            .write(|w| unsafe { w.value().bits(bits) });
+    }
+
+    #[inline(always)]
+    pub(super) fn write_masked(mask: u8, bits: u8) {
+        DEDICATED_GPIO::regs()
+            .out_msk()
+            .write(|w| unsafe { w.out_msk().bits(mask).out_value().bits(bits) });
+    }
+
+    #[inline(always)]
+    pub(super) fn read_output() -> u8 {
+        DEDICATED_GPIO::regs().out_scan().read().out_status().bits()
+    }
+
+    #[inline(always)]
+    pub(super) fn read_input() -> u8 {
+        DEDICATED_GPIO::regs().in_scan().read().in_status().bits()
+    }
+}
+
+#[cfg(any(esp32s3, all(esp32s2, feature = "dedicated-gpio-instructions")))]
+mod ll {
+    use core::arch::asm;
+
+    pub(super) fn init() {
+        // Drive every channel from the CPU instructions.
+        #[cfg(esp32s2)]
+        crate::peripherals::DEDICATED_GPIO::regs()
+            .out_cpu()
+            .write(|w| unsafe { w.bits(0xFF) });
+    }
+
+    pub(super) fn enable_outputs(_channels: u8) {}
+
+    #[inline(always)]
+    pub(super) fn write_all(bits: u8) {
+        unsafe { asm!("wur.gpio_out {0}", in(reg) bits as u32) };
+    }
+
+    #[inline(always)]
+    pub(super) fn write_masked(mask: u8, bits: u8) {
+        #[cfg(esp32s2)]
+        unsafe {
+            asm!("wr_mask_gpio_out {0}, {1}", in(reg) bits as u32, in(reg) mask as u32)
+        };
+        #[cfg(esp32s3)]
+        unsafe {
+            asm!("ee.wr_mask_gpio_out {0}, {1}", in(reg) bits as u32, in(reg) mask as u32)
+        };
+    }
+
+    #[inline(always)]
+    pub(super) fn read_output() -> u8 {
+        let bits: u32;
+        unsafe { asm!("rur.gpio_out {0}", out(reg) bits) };
+        bits as u8
+    }
+
+    #[inline(always)]
+    pub(super) fn read_input() -> u8 {
+        let bits: u32;
+        #[cfg(esp32s2)]
+        unsafe {
+            asm!("get_gpio_in {0}", out(reg) bits)
+        };
+        #[cfg(esp32s3)]
+        unsafe {
+            asm!("ee.get_gpio_in {0}", out(reg) bits)
+        };
+        bits as u8
+    }
+}
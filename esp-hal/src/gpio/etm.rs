@@ -21,6 +21,10 @@
 //! - ANY_EDGE: Indicates that the output signal of the corresponding GPIO is
 //!   reversed
 //!
+//! GPIO events are independent of the GPIO interrupt, so a pin can be used as
+//! an ETM event source while interrupts are enabled for it. Dropping an
+//! [Event] or a [Task] releases the pin from its channel again.
+//!
 //! ## Examples
 //! ### Toggle an LED When a Button is Pressed
 //! ```rust, no_run
@@ -187,6 +191,7 @@ impl<const C: u8> EventChannel<C> {
         enable_event_channel(C, pin.number());
         Event {
             id: kind.id() + C,
+            channel: C,
             _pin: PhantomData,
         }
     }
@@ -214,10 +219,17 @@ impl EventKind {
 pub struct Event<'d> {
     _pin: PhantomData<&'d mut ()>,
     id: u8,
+    channel: u8,
 }
 
 impl private::Sealed for Event<'_> {}
 
+impl Drop for Event<'_> {
+    fn drop(&mut self) {
+        disable_event_channel(self.channel);
+    }
+}
+
 impl crate::etm::EtmEvent for Event<'_> {
     fn id(&self) -> u8 {
         self.id
@@ -301,6 +313,7 @@ impl<const C: u8> TaskChannel<C> {
         enable_task_channel(C, pin.number());
         Task {
             id: kind.id() + C,
+            pin: pin.number(),
             _pin: PhantomData,
         }
     }
@@ -328,10 +341,18 @@ impl TaskKind {
 pub struct Task<'d> {
     _pin: PhantomData<&'d mut ()>,
     id: u8,
+    pin: u8,
 }
 
 impl private::Sealed for Task<'_> {}
 
+impl Drop for Task<'_> {
+    fn drop(&mut self) {
+        // The pin keeps its current level and stays an output
+        disable_task_channel(self.pin);
+    }
+}
+
 impl crate::etm::EtmTask for Task<'_> {
     fn id(&self) -> u8 {
         self.id
@@ -339,17 +360,21 @@ impl crate::etm::EtmTask for Task<'_> {
 }
 
 fn enable_task_channel(channel: u8, pin: u8) {
+    // bit 0 = en, bit 1-3 = channel
+    write_task_config(pin, 1 | (channel << 1));
+}
+
+fn disable_task_channel(pin: u8) {
+    write_task_config(pin, 0);
+}
+
+fn write_task_config(pin: u8, config: u8) {
     let gpio_sd = GPIO_SD::regs();
     let ptr = unsafe { gpio_sd.etm_task_p0_cfg().as_ptr().add(pin as usize / 4) };
     let shift = 8 * (pin as usize % 4);
-    // bit 0 = en, bit 1-3 = channel
-    unsafe {
-        ptr.write_volatile(
-            ptr.read_volatile() & !(0xf << shift)
-                | (1 << shift)
-                | ((channel as u32) << (shift + 1)),
-        );
-    }
+    critical_section::with(|_| unsafe {
+        ptr.write_volatile(ptr.read_volatile() & !(0xf << shift) | ((config as u32) << shift));
+    });
 }
 
 fn enable_event_channel(channel: u8, pin: u8) {
@@ -364,3 +389,9 @@ fn enable_event_channel(channel: u8, pin: u8) {
         .etm_event_ch_cfg(channel as usize)
         .modify(|_, w| w.event_en().set_bit());
 }
+
+fn disable_event_channel(channel: u8) {
+    GPIO_SD::regs()
+        .etm_event_ch_cfg(channel as usize)
+        .modify(|_, w| w.event_en().clear_bit());
+}
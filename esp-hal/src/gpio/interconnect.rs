@@ -1,4 +1,24 @@
 //! Peripheral signal interconnect using IOMUX or GPIOMUX.
+//!
+//! Peripheral drivers take their pins as [`PeripheralInput`] and
+//! [`PeripheralOutput`], which are implemented for pins as well as for the
+//! signals in this module. Splitting a pin into an [`InputSignal`] and an
+//! [`OutputSignal`] allows more than one peripheral to use it, and both can be
+//! inverted in the GPIO matrix. An input signal can be cloned to connect a pin
+//! to several peripheral inputs, and [`Level`] connects a peripheral input to
+//! a constant level.
+//!
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::uart::{Config, Uart};
+//! // Half-duplex UART on a single wire
+//! let (rx, tx) = peripherals.GPIO2.split();
+//! let uart = Uart::new(peripherals.UART1, Config::default())?
+//!     .with_rx(rx)
+//!     .with_tx(tx);
+//! # Ok(())
+//! # }
+//! ```
 
 // FIXME: https://github.com/esp-rs/esp-hal/issues/2954 The GPIO implementation does not contain any
 // locking. This is okay there, because the implementation uses either W1TS/W1TC
@@ -220,9 +240,15 @@ fn connect_peripheral_to_output(
 fn disconnect_peripheral_output_from_pin(pin: &AnyPin, signal: gpio::OutputSignal) {
     pin.set_alternate_function(GPIO_FUNCTION);
 
-    GPIO::regs()
-        .func_in_sel_cfg(signal as usize - FUNC_IN_SEL_OFFSET)
-        .write(|w| w.sel().clear_bit());
+    // Only disconnect the pin if it's still driven by this signal, so the pin
+    // can be handed over to a different signal before the old one is released
+    let out_sel = GPIO::regs().func_out_sel_cfg(pin.number() as usize);
+    if out_sel.read().out_sel().bits() == signal as OutputSignalType {
+        out_sel.write(|w| unsafe {
+            w.out_sel()
+                .bits(gpio::OutputSignal::GPIO as OutputSignalType)
+        });
+    }
 }
 
 /// A configurable input signal between a peripheral and a GPIO pin.
@@ -293,6 +319,18 @@ impl InputSignal {
         self.is_inverted = !self.is_inverted;
     }
 
+    /// Sets whether the peripheral's input signal is inverted.
+    ///
+    /// This only takes effect when the signal is connected.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.is_inverted = inverted;
+    }
+
+    /// Returns whether the peripheral's input signal is inverted.
+    pub fn is_inverted(&self) -> bool {
+        self.is_inverted
+    }
+
     /// Consumes the signal and returns a new one that inverts the peripheral's
     /// input signal.
     ///
@@ -418,6 +456,18 @@ impl OutputSignal {
         self.is_inverted = !self.is_inverted;
     }
 
+    /// Sets whether the peripheral's output signal is inverted.
+    ///
+    /// This only takes effect when the signal is connected.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.is_inverted = inverted;
+    }
+
+    /// Returns whether the peripheral's output signal is inverted.
+    pub fn is_inverted(&self) -> bool {
+        self.is_inverted
+    }
+
     /// Consumes the signal and returns a new one that inverts the peripheral's
     /// output signal.
     ///
@@ -594,6 +644,20 @@ impl From<Flex<'static>> for InputConnection {
 impl Sealed for InputConnection {}
 
 impl InputConnection {
+    /// Returns the pin this connection reads, and whether the signal is
+    /// inverted. Constant levels aren't read from a pin.
+    pub(crate) fn pin(&self) -> Option<(AnyPin, bool)> {
+        match &self.0 {
+            InputConnectionInner::Input(signal) => {
+                Some((unsafe { signal.pin.clone_unchecked() }, signal.is_inverted))
+            }
+            InputConnectionInner::DirectInput(signal) => {
+                Some((unsafe { signal.pin.clone_unchecked() }, false))
+            }
+            InputConnectionInner::Constant(_) => None,
+        }
+    }
+
     delegate::delegate! {
         #[instability::unstable]
         to match &self.0 {
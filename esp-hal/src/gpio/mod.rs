@@ -78,6 +78,9 @@ pub use placeholder::NoPin;
 crate::unstable_module! {
     pub mod interconnect;
 
+    #[cfg(dedicated_gpio)]
+    pub mod dedicated;
+
     #[cfg(soc_etm)]
     pub mod etm;
 
@@ -113,6 +116,34 @@ mod user_irq {
         }
     }
 
+    /// Handlers of single pins, indexed by GPIO number.
+    #[ram]
+    pub(super) static PIN_INTERRUPT_HANDLERS: [FnPtr; super::NUM_PINS] =
+        [const { FnPtr::new() }; super::NUM_PINS];
+
+    pub(super) struct FnPtr(AtomicPtr<()>);
+    impl FnPtr {
+        pub const fn new() -> Self {
+            Self(AtomicPtr::new(core::ptr::null_mut()))
+        }
+
+        pub fn store(&self, f: Option<fn()>) {
+            let ptr = f.map_or(core::ptr::null_mut(), |f| f as *mut ());
+            self.0.store(ptr, Ordering::Relaxed);
+        }
+
+        pub fn is_set(&self) -> bool {
+            !self.0.load(Ordering::Relaxed).is_null()
+        }
+
+        pub fn call(&self) {
+            let ptr = self.0.load(Ordering::Relaxed);
+            if !ptr.is_null() {
+                unsafe { (core::mem::transmute::<*mut (), fn()>(ptr))() };
+            }
+        }
+    }
+
     #[ram]
     pub(super) extern "C" fn user_gpio_interrupt_handler() {
         super::handle_pin_interrupts(|| USER_INTERRUPT_HANDLER.call());
@@ -158,6 +189,11 @@ impl PinGuard {
             signal,
         }
     }
+
+    /// Returns the number of the connected pin, if there is one.
+    pub(crate) fn pin_number(&self) -> Option<u8> {
+        (self.pin != u8::MAX).then_some(self.pin)
+    }
 }
 
 impl Drop for PinGuard {
@@ -280,6 +316,134 @@ impl Display for WakeConfigError {
 
 impl core::error::Error for WakeConfigError {}
 
+/// Glitch filter of an input pin.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(not(esp32))]
+#[instability::unstable]
+#[non_exhaustive]
+pub enum GlitchFilter {
+    /// The input signal isn't filtered.
+    Disabled,
+    /// Pulses shorter than two IO MUX clock cycles are filtered out.
+    FixedTwoClocks,
+    /// A level is only passed on if the input signal stays at it for at least
+    /// `threshold_ns` nanoseconds within a window of `window_ns`
+    /// nanoseconds.
+    ///
+    /// This uses one of the eight flexible glitch filter channels, which are
+    /// clocked from the crystal. Both durations must be at most 64 cycles of
+    /// it, and `threshold_ns` must not be larger than `window_ns`.
+    #[cfg(any(esp32c6, esp32h2))]
+    Flex {
+        /// Length of the window in nanoseconds.
+        window_ns: u32,
+        /// Time the signal has to be stable within the window in nanoseconds.
+        threshold_ns: u32,
+    },
+}
+
+/// Errors that can occur when configuring the glitch filter of a pin.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(not(esp32))]
+#[instability::unstable]
+#[non_exhaustive]
+pub enum GlitchFilterError {
+    /// All flexible glitch filter channels are in use by other pins.
+    NoChannelAvailable,
+    /// The window or the threshold is zero, too long, or the threshold is
+    /// longer than the window.
+    InvalidTiming,
+}
+
+#[cfg(not(esp32))]
+impl Display for GlitchFilterError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GlitchFilterError::NoChannelAvailable => {
+                write!(f, "All glitch filter channels are in use")
+            }
+            GlitchFilterError::InvalidTiming => {
+                write!(f, "The glitch filter window or threshold is out of range")
+            }
+        }
+    }
+}
+
+#[cfg(not(esp32))]
+impl core::error::Error for GlitchFilterError {}
+
+#[cfg(any(esp32c6, esp32h2))]
+mod flex_glitch_filter {
+    use super::GlitchFilterError;
+    use crate::peripherals::{GPIO_SD, PCR};
+
+    const CHANNELS: usize = 8;
+    const MAX_CYCLES: u32 = 64;
+
+    fn nanos_to_cycles(ns: u32) -> u32 {
+        let freq = crate::clock::Clocks::xtal_freq().to_Hz() as u64;
+        (ns as u64 * freq / 1_000_000_000) as u32
+    }
+
+    /// Connects a free channel, or the one it already uses, to `pin`.
+    pub(super) fn attach(
+        pin: u8,
+        window_ns: u32,
+        threshold_ns: u32,
+    ) -> Result<(), GlitchFilterError> {
+        let window = nanos_to_cycles(window_ns);
+        let threshold = nanos_to_cycles(threshold_ns);
+        if threshold == 0 || threshold > window || window > MAX_CYCLES {
+            return Err(GlitchFilterError::InvalidTiming);
+        }
+
+        // The filters count cycles of the IO MUX function clock
+        PCR::regs().iomux_clk_conf().modify(|_, w| unsafe {
+            w.iomux_func_clk_sel().bits(3); // XTAL
+            w.iomux_func_clk_en().set_bit()
+        });
+
+        critical_section::with(|_| {
+            let regs = GPIO_SD::regs();
+            let channel = regs
+                .glitch_filter_ch_iter()
+                .find(|ch| {
+                    let ch = ch.read();
+                    ch.en().bit_is_set() && ch.input_io_num().bits() == pin
+                })
+                .or_else(|| {
+                    regs.glitch_filter_ch_iter()
+                        .find(|ch| ch.read().en().bit_is_clear())
+                })
+                .ok_or(GlitchFilterError::NoChannelAvailable)?;
+
+            channel.write(|w| unsafe {
+                w.input_io_num().bits(pin);
+                w.window_width().bits(window as u8 - 1);
+                w.window_thres().bits(threshold as u8 - 1);
+                w.en().set_bit()
+            });
+
+            Ok(())
+        })
+    }
+
+    /// Frees the channel used by `pin`, if any.
+    pub(super) fn detach(pin: u8) {
+        critical_section::with(|_| {
+            for index in 0..CHANNELS {
+                let channel = GPIO_SD::regs().glitch_filter_ch(index);
+                let ch = channel.read();
+                if ch.en().bit_is_set() && ch.input_io_num().bits() == pin {
+                    channel.write(|w| w.en().clear_bit());
+                }
+            }
+        });
+    }
+}
+
 /// Pull setting for a GPIO.
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -306,6 +470,17 @@ pub enum DriveStrength {
     _40mA = 3,
 }
 
+impl DriveStrength {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0 => Self::_5mA,
+            1 => Self::_10mA,
+            2 => Self::_20mA,
+            _ => Self::_40mA,
+        }
+    }
+}
+
 /// Alternate functions
 ///
 /// GPIO pins can be configured for various functions, such as GPIO
@@ -374,8 +549,12 @@ pub trait RtcPin: Pin {
     #[doc(hidden)]
     fn rtc_set_config(&self, input_enable: bool, mux: bool, func: RtcFunction);
 
-    /// Enable or disable PAD_HOLD
-    #[doc(hidden)]
+    /// Enable or disable the hold of the pad.
+    ///
+    /// A held pad keeps its configuration and output level, even through deep
+    /// sleep and the reset when waking up from it. Changes to the pad's
+    /// configuration only take effect once the hold is disabled again, so
+    /// release it before reconfiguring the pin after waking up.
     fn rtcio_pad_hold(&self, enable: bool);
 
     /// # Safety
@@ -393,10 +572,13 @@ pub trait RtcPin: Pin {
 #[cfg(any(lp_io, rtc_cntl))]
 pub trait RtcPinWithResistors: RtcPin {
     /// Enable/disable the internal pull-up resistor
-    #[doc(hidden)]
+    ///
+    /// Combined with [`RtcPin::rtcio_pad_hold`], the resistor stays active in
+    /// deep sleep, for example to keep a wake-up pin from floating.
     fn rtcio_pullup(&self, enable: bool);
     /// Enable/disable the internal pull-down resistor
-    #[doc(hidden)]
+    ///
+    /// See [`Self::rtcio_pullup`] for using it in deep sleep.
     fn rtcio_pulldown(&self, enable: bool);
 }
 
@@ -569,6 +751,14 @@ impl GpioBank {
         }
     }
 
+    fn read_out_en(self) -> u32 {
+        match self {
+            Self::_0 => GPIO::regs().enable().read().bits(),
+            #[cfg(gpio_bank_1)]
+            Self::_1 => GPIO::regs().enable1().read().bits(),
+        }
+    }
+
     fn read_interrupt_status(self) -> u32 {
         match self {
             Self::_0 => GPIO::regs().status().read().bits(),
@@ -831,6 +1021,17 @@ fn handle_pin_interrupts(user_handler: fn()) {
             });
         }
 
+        // Call the handlers of single pins, which can't be awaited at the
+        // same time
+        let mut intr_bits = intrs & !async_pins;
+        while intr_bits != 0 {
+            let pin_pos = intr_bits.trailing_zeros();
+            intr_bits -= 1 << pin_pos;
+
+            let pin_nr = pin_pos as u8 + bank.offset();
+            user_irq::PIN_INTERRUPT_HANDLERS[pin_nr as usize].call();
+        }
+
         bank.write_interrupt_status_clear(intrs);
     }
 }
@@ -1197,6 +1398,8 @@ impl<'d> Output<'d> {
         };
         this.set_level(initial_level);
         this.apply_config(&config);
+        // Keep the pad readable, so that `level` reports the actual line
+        this.pin.pin.enable_input(true);
         this.pin.pin.enable_output(true);
 
         this
@@ -1268,6 +1471,82 @@ impl<'d> Output<'d> {
         self.pin.apply_output_config(config)
     }
 
+    /// Changes the [DriveStrength] of the pin.
+    ///
+    /// Unlike [`Self::apply_config`], this leaves the drive mode and the pull
+    /// resistors untouched.
+    #[inline]
+    #[instability::unstable]
+    pub fn set_drive_strength(&mut self, strength: DriveStrength) {
+        self.pin.set_drive_strength(strength)
+    }
+
+    /// Returns the [DriveStrength] of the pin.
+    #[inline]
+    #[instability::unstable]
+    pub fn drive_strength(&self) -> DriveStrength {
+        self.pin.drive_strength()
+    }
+
+    /// Enables the pull-up or pull-down resistor, or disables both.
+    ///
+    /// This is mostly useful in open-drain mode, for example to pull a line up
+    /// without an external resistor.
+    #[inline]
+    #[instability::unstable]
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pin.pull_direction(pull)
+    }
+
+    /// Returns the pull-up/pull-down resistor configuration.
+    #[inline]
+    #[instability::unstable]
+    pub fn pull(&self) -> Pull {
+        self.pin.pull()
+    }
+
+    /// Holds the current configuration and level of the pin.
+    ///
+    /// The pad keeps driving its current level, ignoring [`Self::set_level`]
+    /// and configuration changes, until [`Self::disable_hold`] is called. The
+    /// hold survives dropping the driver, light sleep and resets other than
+    /// power-on, which makes it useful for keeping an enable line asserted.
+    ///
+    /// RTC and LP pads keep the hold through deep sleep and the reset when
+    /// waking up. Other pads are powered down in deep sleep on some chips.
+    /// After waking up, the pin stays held until the hold is disabled, so do
+    /// that before reconfiguring it.
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::gpio::{Level, Output, OutputConfig};
+    /// let config = OutputConfig::default();
+    /// let mut enable = Output::new(peripherals.GPIO1, Level::High, config);
+    /// enable.enable_hold();
+    ///
+    /// // The pin keeps driving high, even though the driver sets it low.
+    /// enable.set_low();
+    ///
+    /// enable.disable_hold();
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    #[instability::unstable]
+    pub fn enable_hold(&mut self) {
+        self.pin.enable_hold()
+    }
+
+    /// Releases the hold of the pin.
+    ///
+    /// The pad returns to the configuration and level which are currently set
+    /// in the registers.
+    #[inline]
+    #[instability::unstable]
+    pub fn disable_hold(&mut self) {
+        self.pin.disable_hold()
+    }
+
     /// Set the output as high.
     #[inline]
     pub fn set_high(&mut self) {
@@ -1313,6 +1592,36 @@ impl<'d> Output<'d> {
         self.pin.output_level()
     }
 
+    /// Returns whether the pad is at high level.
+    ///
+    /// See [`Self::level`].
+    #[inline]
+    #[instability::unstable]
+    pub fn is_high(&self) -> bool {
+        self.level() == Level::High
+    }
+
+    /// Returns whether the pad is at low level.
+    ///
+    /// See [`Self::level`].
+    #[inline]
+    #[instability::unstable]
+    pub fn is_low(&self) -> bool {
+        self.level() == Level::Low
+    }
+
+    /// Returns the level of the pad.
+    ///
+    /// Unlike [`Self::output_level`], this reads the actual line, for example
+    /// to check whether another device is pulling a released open-drain line
+    /// low. The input stage of the pad stays enabled while it drives the
+    /// line, so this works in both drive modes.
+    #[inline]
+    #[instability::unstable]
+    pub fn level(&self) -> Level {
+        self.pin.level()
+    }
+
     /// Toggles the pin output.
     ///
     /// If the pin was previously set to high, it will be set to low, and vice
@@ -1456,6 +1765,20 @@ impl<'d> Input<'d> {
         self.pin.apply_input_config(config)
     }
 
+    /// Enables the pull-up or pull-down resistor, or disables both.
+    #[inline]
+    #[instability::unstable]
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pin.pull_direction(pull)
+    }
+
+    /// Returns the pull-up/pull-down resistor configuration.
+    #[inline]
+    #[instability::unstable]
+    pub fn pull(&self) -> Pull {
+        self.pin.pull()
+    }
+
     /// Listen for interrupts.
     ///
     /// The interrupts will be handled by the handler set using
@@ -1540,6 +1863,45 @@ impl<'d> Input<'d> {
         self.pin.unlisten();
     }
 
+    /// Check if the pin is listening for interrupts.
+    #[inline]
+    #[instability::unstable]
+    pub fn is_listening(&self) -> bool {
+        self.pin.is_listening()
+    }
+
+    /// Registers an interrupt handler for this pin.
+    ///
+    /// The handler is called from the GPIO interrupt whenever this pin's
+    /// interrupt status bit is set, after calling the handler registered with
+    /// [`Io::set_interrupt_handler`], if any. The status bit is cleared
+    /// afterwards, so the handler doesn't need to do that. Use
+    /// [`Self::listen`] to select the event which triggers the interrupt.
+    ///
+    /// The handler is removed, and the pin stops listening, when the pin
+    /// driver is dropped, including when it's converted into a peripheral
+    /// signal. The priority of all GPIO interrupts is set with
+    /// [`Io::set_interrupt_priority`].
+    ///
+    /// A pin can either have an interrupt handler or be awaited, see
+    /// [`Self::wait_for`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pin is being awaited.
+    #[inline]
+    #[instability::unstable]
+    pub fn set_interrupt_handler(&mut self, handler: fn()) {
+        self.pin.set_interrupt_handler(handler);
+    }
+
+    /// Removes the interrupt handler of this pin.
+    #[inline]
+    #[instability::unstable]
+    pub fn remove_interrupt_handler(&mut self) {
+        self.pin.remove_interrupt_handler();
+    }
+
     /// Clear the interrupt status bit for this Pin
     #[inline]
     #[instability::unstable]
@@ -1558,6 +1920,12 @@ impl<'d> Input<'d> {
     ///
     /// This will unlisten for interrupts
     ///
+    /// Any GPIO can wake the chip from light sleep, including pins that are
+    /// not RTC or LP capable. Pass a `GpioWakeupSource` to
+    /// `Rtc::sleep_light` to let the configured pins end the sleep. Once
+    /// woken up, [`wakeup_cause`][crate::rtc_cntl::wakeup_cause] returns
+    /// [`SleepSource::Gpio`][crate::reset::SleepSource::Gpio].
+    ///
     /// # Error
     /// Configuring pin to wake up from light sleep on an edge
     /// trigger is currently not supported, corresponding variant of
@@ -1568,6 +1936,37 @@ impl<'d> Input<'d> {
         self.pin.wakeup_enable(enable, event)
     }
 
+    /// Configures the glitch filter of the input.
+    ///
+    /// [`GlitchFilter::FixedTwoClocks`] uses the filter built into the IO MUX
+    /// of every pin. [`GlitchFilter::Flex`] allocates one of the flexible
+    /// filter channels, which is released again when the filter is changed or
+    /// disabled.
+    ///
+    /// The filter stays configured when the pin driver is dropped.
+    ///
+    /// # Errors
+    ///
+    /// [`GlitchFilterError::NoChannelAvailable`] is returned if all flexible
+    /// channels are used by other pins, and
+    /// [`GlitchFilterError::InvalidTiming`] if the window or threshold can't
+    /// be represented. The previous filter is kept in both cases.
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::gpio::{GlitchFilter, Input, InputConfig};
+    /// let mut button = Input::new(peripherals.GPIO1, InputConfig::default());
+    /// button.set_glitch_filter(GlitchFilter::FixedTwoClocks)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(esp32))]
+    #[inline]
+    #[instability::unstable]
+    pub fn set_glitch_filter(&mut self, filter: GlitchFilter) -> Result<(), GlitchFilterError> {
+        self.pin.set_glitch_filter(filter)
+    }
+
     /// Split the pin into an input and output signal.
     ///
     /// Peripheral signals allow connecting peripherals together without using
@@ -1627,6 +2026,19 @@ pub struct Flex<'d> {
 
 impl private::Sealed for Flex<'_> {}
 
+impl Drop for Flex<'_> {
+    fn drop(&mut self) {
+        // The handler belongs to this driver. Leaving it registered would block
+        // later drivers of the pin from awaiting it, or call it for their
+        // interrupts.
+        let handler = &user_irq::PIN_INTERRUPT_HANDLERS[self.number() as usize];
+        if handler.is_set() {
+            set_int_enable(self.number(), Some(0), 0, false);
+            handler.store(None);
+        }
+    }
+}
+
 impl Peripheral for Flex<'_> {
     type P = Self;
     unsafe fn clone_unchecked(&self) -> Self::P {
@@ -1756,6 +2168,30 @@ impl<'d> Flex<'d> {
         is_int_enabled(self.pin.number())
     }
 
+    /// Registers an interrupt handler for this pin.
+    ///
+    /// See [`Input::set_interrupt_handler`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pin is being awaited.
+    #[inline]
+    #[instability::unstable]
+    pub fn set_interrupt_handler(&mut self, handler: fn()) {
+        assert!(
+            self.pin.bank().async_operations().load(Ordering::Relaxed) & self.pin.mask() == 0,
+            "A pin which is being awaited can't have an interrupt handler"
+        );
+        user_irq::PIN_INTERRUPT_HANDLERS[self.number() as usize].store(Some(handler));
+    }
+
+    /// Removes the interrupt handler of this pin.
+    #[inline]
+    #[instability::unstable]
+    pub fn remove_interrupt_handler(&mut self) {
+        user_irq::PIN_INTERRUPT_HANDLERS[self.number() as usize].store(None);
+    }
+
     /// Clear the interrupt status bit for this Pin
     #[inline]
     #[instability::unstable]
@@ -1776,6 +2212,8 @@ impl<'d> Flex<'d> {
     ///
     /// This will unlisten for interrupts
     ///
+    /// See [`Input::wakeup_enable`] for more information.
+    ///
     /// # Error
     /// Configuring pin to wake up from light sleep on an edge
     /// trigger is currently not supported, corresponding variant of
@@ -1786,7 +2224,48 @@ impl<'d> Flex<'d> {
         self.listen_with_options(event.into(), false, false, enable)
     }
 
+    /// Configures the glitch filter of the input.
+    ///
+    /// See [`Input::set_glitch_filter`] for more information.
+    #[cfg(not(esp32))]
+    #[instability::unstable]
+    pub fn set_glitch_filter(&mut self, filter: GlitchFilter) -> Result<(), GlitchFilterError> {
+        let number = self.number();
+
+        #[cfg(any(esp32c6, esp32h2))]
+        if let GlitchFilter::Flex {
+            window_ns,
+            threshold_ns,
+        } = filter
+        {
+            flex_glitch_filter::attach(number, window_ns, threshold_ns)?;
+            io_mux_reg(number).modify(|_, w| w.filter_en().clear_bit());
+            return Ok(());
+        }
+
+        #[cfg(any(esp32c6, esp32h2))]
+        flex_glitch_filter::detach(number);
+
+        io_mux_reg(number).modify(|_, w| w.filter_en().bit(filter == GlitchFilter::FixedTwoClocks));
+
+        Ok(())
+    }
+
+    /// Set the GPIO to input mode with the given pull resistor.
+    ///
+    /// The output is disabled, but the level set with [`Self::set_level`] is
+    /// kept and driven again once the pin is switched back to an output.
+    #[inline]
+    #[instability::unstable]
+    pub fn set_as_input(&mut self, pull: Pull) {
+        self.pin.pull_direction(pull);
+        self.pin.enable_output(false);
+        self.pin.enable_input(true);
+    }
+
     /// Set the GPIO to output mode.
+    ///
+    /// The pin starts driving the level last set with [`Self::set_level`].
     #[inline]
     #[instability::unstable]
     pub fn set_as_output(&mut self) {
@@ -1850,12 +2329,19 @@ impl<'d> Flex<'d> {
         self.pin.set_drive_strength(strength);
     }
 
+    /// Returns the [DriveStrength] of the pin.
+    #[inline]
+    #[instability::unstable]
+    pub fn drive_strength(&self) -> DriveStrength {
+        self.pin.drive_strength()
+    }
+
     /// Set the GPIO to open-drain mode.
     #[inline]
     #[instability::unstable]
     pub fn set_as_open_drain(&mut self, pull: Pull) {
-        self.pin.set_to_open_drain_output();
         self.pin.pull_direction(pull);
+        self.pin.set_to_open_drain_output();
     }
 
     /// Configure pullup/pulldown resistors.
@@ -1865,6 +2351,29 @@ impl<'d> Flex<'d> {
         self.pin.pull_direction(pull);
     }
 
+    /// Returns the pull-up/pull-down resistor configuration.
+    #[inline]
+    #[instability::unstable]
+    pub fn pull(&self) -> Pull {
+        self.pin.pull()
+    }
+
+    /// Holds the current configuration and level of the pin.
+    ///
+    /// See [`Output::enable_hold`] for more information.
+    #[inline]
+    #[instability::unstable]
+    pub fn enable_hold(&mut self) {
+        self.pin.set_hold(true);
+    }
+
+    /// Releases the hold of the pin.
+    #[inline]
+    #[instability::unstable]
+    pub fn disable_hold(&mut self) {
+        self.pin.set_hold(false);
+    }
+
     /// Enable or disable the GPIO pin input buffer.
     #[inline]
     #[instability::unstable]
@@ -1880,7 +2389,10 @@ impl<'d> Flex<'d> {
         let pull_down = config.pull == Pull::Down;
 
         #[cfg(esp32)]
-        crate::soc::gpio::errata36(AnyPin(self.pin.0), pull_up, pull_down);
+        {
+            crate::soc::gpio::errata36(AnyPin(self.pin.0), pull_up, pull_down);
+            crate::soc::gpio::rtcio_drive_strength(AnyPin(self.pin.0), config.drive_strength);
+        }
 
         io_mux_reg(self.number()).modify(|_, w| {
             unsafe { w.fun_drv().bits(config.drive_strength as u8) };
@@ -1942,6 +2454,154 @@ impl<'d> Flex<'d> {
     }
 }
 
+/// A group of pins that are read and written together.
+///
+/// Setting the pins of a parallel bus one by one skews the bits by the time it
+/// takes to write a register for each of them. A `PinGroup` writes the set
+/// register of a GPIO bank once for all pins that go high, then its clear
+/// register once for all pins that go low. Pins that change in the same
+/// direction change at the same time, but the pins that go high do so a few
+/// clock cycles before the ones that go low.
+///
+/// The update is therefore not glitch-free: between the two writes, the bus
+/// can show a value that's neither the old nor the new one. Latch the bus with
+/// a separate strobe or enable line once [`Self::write`] returns, if the
+/// receiver must not see intermediate values.
+///
+/// Bit `n` of the masks and values passed to the group refers to the `n`-th
+/// pin of the array the group was created from, regardless of the pin's GPIO
+/// number. A group can hold up to 32 pins.
+///
+/// The group takes ownership of its pins, so they can't be driven
+/// individually while they are part of the group. Use [`Self::into_inner`] to
+/// get them back.
+///
+/// On the ESP32, pins 32 and above are in a second bank, which is written
+/// after the first one.
+///
+/// ```rust, no_run
+#[doc = crate::before_snippet!()]
+/// use esp_hal::gpio::{Level, Output, OutputConfig, PinGroup};
+///
+/// let config = OutputConfig::default();
+/// let mut bus = PinGroup::new([
+///     Output::new(peripherals.GPIO0, Level::Low, config).into_flex(),
+///     Output::new(peripherals.GPIO1, Level::Low, config).into_flex(),
+///     Output::new(peripherals.GPIO2, Level::Low, config).into_flex(),
+///     Output::new(peripherals.GPIO3, Level::Low, config).into_flex(),
+/// ]);
+///
+/// // Drive 0b1010 to the bus.
+/// bus.write(0b1111, 0b1010);
+/// // Flip the lowest two bits, the bus now reads 0b1001.
+/// bus.toggle(0b0011);
+/// # Ok(())
+/// # }
+/// ```
+#[instability::unstable]
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PinGroup<'d, const N: usize> {
+    pins: [Flex<'d>; N],
+}
+
+impl<'d, const N: usize> PinGroup<'d, N> {
+    const BANKS: [GpioBank; GpioBank::COUNT] = [
+        GpioBank::_0,
+        #[cfg(gpio_bank_1)]
+        GpioBank::_1,
+    ];
+
+    /// Creates a group from the given pins.
+    ///
+    /// The pins keep their configuration. Pins that are written to need to be
+    /// configured as outputs, pins that are read need their input stage
+    /// enabled.
+    #[instability::unstable]
+    pub fn new(pins: [Flex<'d>; N]) -> Self {
+        const { ::core::assert!(N <= 32, "a pin group can hold at most 32 pins") };
+
+        Self { pins }
+    }
+
+    /// Releases the pins of the group.
+    #[instability::unstable]
+    pub fn into_inner(self) -> [Flex<'d>; N] {
+        self.pins
+    }
+
+    /// Sets the pins selected by `mask` to the corresponding bits of `value`.
+    ///
+    /// Pins whose bit is clear in `mask` are not changed. Pins that go high
+    /// change before the ones that go low, see the [type-level
+    /// documentation](Self) for details.
+    #[inline]
+    #[instability::unstable]
+    pub fn write(&mut self, mask: u32, value: u32) {
+        let set = self.bank_masks(mask & value);
+        let clear = self.bank_masks(mask & !value);
+
+        for bank in Self::BANKS {
+            if set[bank as usize] != 0 {
+                bank.write_output_set(set[bank as usize]);
+            }
+            if clear[bank as usize] != 0 {
+                bank.write_output_clear(clear[bank as usize]);
+            }
+        }
+    }
+
+    /// Inverts the output level of the pins selected by `mask`.
+    #[inline]
+    #[instability::unstable]
+    pub fn toggle(&mut self, mask: u32) {
+        let selected = self.bank_masks(mask);
+
+        for bank in Self::BANKS {
+            let selected = selected[bank as usize];
+            if selected == 0 {
+                continue;
+            }
+            let output = bank.read_output();
+
+            bank.write_output_set(selected & !output);
+            bank.write_output_clear(selected & output);
+        }
+    }
+
+    /// Returns the input level of every pin in the group.
+    ///
+    /// Bit `n` is set if the `n`-th pin is high. Each GPIO bank is sampled
+    /// once, so the pins of a bank are read at the same time.
+    #[inline]
+    #[instability::unstable]
+    pub fn read(&self) -> u32 {
+        let mut input = [0; GpioBank::COUNT];
+        for bank in Self::BANKS {
+            input[bank as usize] = bank.read_input();
+        }
+
+        let mut value = 0;
+        for (i, pin) in self.pins.iter().enumerate() {
+            if input[pin.pin.bank() as usize] & pin.pin.mask() != 0 {
+                value |= 1 << i;
+            }
+        }
+        value
+    }
+
+    /// Translates a group bit mask to per-bank register words.
+    fn bank_masks(&self, bits: u32) -> [u32; GpioBank::COUNT] {
+        let mut words = [0; GpioBank::COUNT];
+        for (i, pin) in self.pins.iter().enumerate() {
+            if bits & (1 << i) != 0 {
+                words[pin.pin.bank() as usize] |= pin.pin.mask();
+            }
+        }
+        words
+    }
+}
+
 impl private::Sealed for AnyPin {}
 
 impl AnyPin {
@@ -2029,6 +2689,18 @@ impl AnyPin {
         });
     }
 
+    #[inline]
+    pub(crate) fn pull(&self) -> Pull {
+        let reg = io_mux_reg(self.number()).read();
+        if reg.fun_wpu().bit_is_set() {
+            Pull::Up
+        } else if reg.fun_wpd().bit_is_set() {
+            Pull::Down
+        } else {
+            Pull::None
+        }
+    }
+
     #[inline]
     fn mask(&self) -> u32 {
         1 << (self.number() % 32)
@@ -2048,8 +2720,6 @@ impl AnyPin {
         open_drain: bool,
         input_enable: Option<bool>,
     ) {
-        self.enable_output(true);
-
         let gpio = GPIO::regs();
 
         gpio.pin(self.number() as usize)
@@ -2069,6 +2739,10 @@ impl AnyPin {
             w.fun_drv().bits(DriveStrength::_20mA as u8);
             w.slp_sel().clear_bit()
         });
+
+        // Only drive the pad once the driver mode and the signal are set up, so
+        // switching directions doesn't glitch the line
+        self.enable_output(true);
     }
 
     /// Configure open-drain mode
@@ -2092,9 +2766,51 @@ impl AnyPin {
     /// Configure the [DriveStrength] of the pin
     #[inline]
     pub(crate) fn set_drive_strength(&self, strength: DriveStrength) {
+        #[cfg(esp32)]
+        crate::soc::gpio::rtcio_drive_strength(Self(self.0), strength);
+
         io_mux_reg(self.number()).modify(|_, w| unsafe { w.fun_drv().bits(strength as u8) });
     }
 
+    /// The [DriveStrength] of the pin
+    #[inline]
+    pub(crate) fn drive_strength(&self) -> DriveStrength {
+        DriveStrength::from_bits(io_mux_reg(self.number()).read().fun_drv().bits())
+    }
+
+    /// Enable/disable the hold of the pad
+    #[inline]
+    pub(crate) fn set_hold(&self, enable: bool) {
+        crate::soc::gpio::set_pad_hold(self.number(), enable);
+    }
+
+    /// Sets the event that sets the interrupt status bit of the pin, and
+    /// whether it interrupts the CPU. The interrupt handler of the pin is not
+    /// changed.
+    pub(crate) fn set_interrupt_event(&self, event: Option<Event>, enable: bool) {
+        let int_ena = if enable {
+            gpio_intr_enable(true, false)
+        } else {
+            0
+        };
+        set_int_enable(
+            self.number(),
+            Some(int_ena),
+            event.map_or(0, |e| e as u8),
+            false,
+        );
+    }
+
+    /// Returns whether the interrupt status bit of the pin is set.
+    pub(crate) fn is_interrupt_set(&self) -> bool {
+        self.bank().read_interrupt_status() & self.mask() != 0
+    }
+
+    /// Clears the interrupt status bit of the pin.
+    pub(crate) fn clear_interrupt(&self) {
+        self.bank().write_interrupt_status_clear(self.mask());
+    }
+
     /// Enable/disable open-drain mode
     #[inline]
     pub(crate) fn enable_open_drain(&self, on: bool) {
@@ -2108,6 +2824,43 @@ impl AnyPin {
     pub(crate) fn is_set_high(&self) -> bool {
         self.bank().read_output() & self.mask() != 0
     }
+
+    /// Saves the output routing, driver mode and IO MUX settings of the pin,
+    /// so that a driver can temporarily take it over as a GPIO.
+    pub(crate) fn save_output_config(&self) -> SavedOutputConfig {
+        let gpio = GPIO::regs();
+        SavedOutputConfig {
+            io_mux: io_mux_reg(self.number()).read().bits(),
+            pin: gpio.pin(self.number() as usize).read().bits(),
+            out_sel: gpio.func_out_sel_cfg(self.number() as usize).read().bits(),
+            output_enabled: self.bank().read_out_en() & self.mask() != 0,
+            output_high: self.is_set_high(),
+        }
+    }
+
+    /// Restores a configuration saved by [`Self::save_output_config`].
+    pub(crate) fn restore_output_config(&self, config: &SavedOutputConfig) {
+        let gpio = GPIO::regs();
+
+        // Disconnect the pad while it is being reconfigured.
+        self.enable_output(false);
+        self.set_output_high(config.output_high);
+        gpio.pin(self.number() as usize)
+            .write(|w| unsafe { w.bits(config.pin) });
+        gpio.func_out_sel_cfg(self.number() as usize)
+            .write(|w| unsafe { w.bits(config.out_sel) });
+        io_mux_reg(self.number()).write(|w| unsafe { w.bits(config.io_mux) });
+        self.enable_output(config.output_enabled);
+    }
+}
+
+/// Pin configuration saved by [`AnyPin::save_output_config`].
+pub(crate) struct SavedOutputConfig {
+    io_mux: u32,
+    pin: u32,
+    out_sel: u32,
+    output_enabled: bool,
+    output_high: bool,
 }
 
 impl Pin for AnyPin {
@@ -2217,9 +2970,19 @@ mod asynch {
         ///
         /// Note that calling this function will overwrite previous
         /// [`listen`][Self::listen] operations for this pin.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the pin has an interrupt handler, see
+        /// [`Self::set_interrupt_handler`].
         #[inline]
         #[instability::unstable]
         pub async fn wait_for(&mut self, event: Event) {
+            assert!(
+                !user_irq::PIN_INTERRUPT_HANDLERS[self.number() as usize].is_set(),
+                "A pin with an interrupt handler can't be awaited"
+            );
+
             // We construct the Future first, because its `Drop` implementation
             // is load-bearing if `wait_for` is dropped during the initialization.
             let mut future = PinFuture {
@@ -2316,6 +3079,11 @@ mod asynch {
         ///
         /// Note that calling this function will overwrite previous
         /// [`listen`][Self::listen] operations for this pin.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the pin has an interrupt handler, see
+        /// [`Self::set_interrupt_handler`].
         #[inline]
         pub async fn wait_for(&mut self, event: Event) {
             self.pin.wait_for(event).await
@@ -2473,6 +3241,17 @@ mod embedded_hal_impls {
         }
     }
 
+    #[instability::unstable]
+    impl digital::InputPin for Output<'_> {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(Self::is_high(self))
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(Self::is_low(self))
+        }
+    }
+
     #[instability::unstable]
     impl digital::InputPin for Flex<'_> {
         fn is_high(&mut self) -> Result<bool, Self::Error> {
@@ -12,17 +12,67 @@
 //! setting such as frequency, timeout, and SDA/SCL pins can easily be
 //! configured.
 //!
+//! ## Transfer length
+//!
+//! Reads and writes aren't limited by the size of the FIFO. The driver keeps
+//! refilling the TX FIFO and draining the RX FIFO while a transfer runs, and
+//! spreads long transfers over several command lists. The bus is paused, but
+//! not released, between them, so a single `write` or `read` of any length is
+//! still one transaction on the bus.
+//!
 //! ## Usage
 //!
 //! The I2C driver implements a number of third-party traits, with the
 //! intention of making the HAL inter-compatible with various device drivers
 //! from the community, including the [embedded-hal].
 //!
+//! ### Devices with different speeds on a shared bus
+//!
+//! [`I2c::apply_config`] can change the bus frequency at any time between
+//! transactions. With the `unstable` feature, the driver also implements
+//! `SetConfig` from [`embassy-embedded-hal`], so the `I2cDeviceWithConfig`
+//! wrappers of its blocking and async shared buses can apply each device's
+//! [`Config`] before the transactions of that device. The wrappers don't
+//! restore the previous configuration afterwards, so every device on the bus
+//! should be wrapped with its own configuration. A device that uses the plain
+//! `I2cDevice` wrapper runs at whichever frequency was applied last.
+//!
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use core::cell::RefCell;
+//! # use embassy_embedded_hal::shared_bus::blocking::i2c::I2cDeviceWithConfig;
+//! # use embassy_sync::blocking_mutex::{raw::NoopRawMutex, Mutex};
+//! # use embedded_hal::i2c::I2c as _;
+//! # use esp_hal::{
+//! #     i2c::master::{Config, I2c},
+//! #     time::RateExtU32,
+//! # };
+//! let i2c = I2c::new(peripherals.I2C0, Config::default())?
+//!     .with_sda(peripherals.GPIO1)
+//!     .with_scl(peripherals.GPIO2);
+//! let bus = Mutex::<NoopRawMutex, _>::new(RefCell::new(i2c));
+//!
+//! let mut sensor = I2cDeviceWithConfig::new(
+//!     &bus,
+//!     Config::default().with_frequency(100.kHz()),
+//! );
+//! let mut eeprom = I2cDeviceWithConfig::new(
+//!     &bus,
+//!     Config::default().with_frequency(1.MHz()),
+//! );
+//!
+//! let mut calibration = [0u8; 22];
+//! sensor.write_read(0x77, &[0xaa], &mut calibration)?;
+//! eeprom.write(0x50, &[0x00, 0x00, 0x42])?;
+//! # Ok(())
+//! # }
+//! ```
+//! 
 //! [embedded-hal]: embedded_hal
+//! [`embassy-embedded-hal`]: https://docs.rs/embassy-embedded-hal/latest/embassy_embedded_hal/shared_bus/index.html
 
-use core::marker::PhantomData;
-#[cfg(not(esp32))]
 use core::{
+    marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -30,12 +80,15 @@ use core::{
 use embedded_hal::i2c::Operation as EhalOperation;
 use enumset::{EnumSet, EnumSetType};
 use fugit::HertzU32;
+use portable_atomic::{AtomicU32, Ordering};
 
 use crate::{
     asynch::AtomicWaker,
     clock::Clocks,
+    delay::Delay,
     gpio::{
         interconnect::{OutputConnection, PeripheralOutput},
+        AnyPin,
         InputSignal,
         OutputSignal,
         PinGuard,
@@ -47,11 +100,16 @@ use crate::{
     peripherals::Interrupt,
     private,
     system::{PeripheralClockControl, PeripheralGuard},
+    time::{Duration, Instant},
     Async,
     Blocking,
     DriverMode,
 };
 
+crate::unstable_module! {
+    pub mod smbus;
+}
+
 cfg_if::cfg_if! {
     if #[cfg(esp32s2)] {
         const I2C_LL_INTR_MASK: u32 = 0x1ffff;
@@ -60,15 +118,34 @@ cfg_if::cfg_if! {
     }
 }
 
-// Chunk writes/reads by this size
+// Chunk reads by this size
 #[cfg(any(esp32, esp32s2))]
 const I2C_CHUNK_SIZE: usize = 32;
 
 #[cfg(not(any(esp32, esp32s2)))]
 const I2C_CHUNK_SIZE: usize = 254;
 
-// on ESP32 there is a chance to get trapped in `wait_for_completion` forever
-const MAX_ITERATIONS: u32 = 1_000_000;
+// Size of the TX and RX FIFOs
+const I2C_FIFO_SIZE: usize = 32;
+
+// Longest write, address included, that a single command list can send
+#[cfg(any(esp32, esp32s2))]
+const I2C_MAX_WRITE_LEN: usize = I2C_FIFO_SIZE;
+
+#[cfg(not(any(esp32, esp32s2)))]
+const I2C_MAX_WRITE_LEN: usize = 255;
+
+// Largest value of the timeout register, in cycles on the ESP32 and ESP32-S2,
+// as a power of two of cycles on the others
+cfg_if::cfg_if! {
+    if #[cfg(esp32)] {
+        const MAX_TIMEOUT: u32 = 0xF_FFFF;
+    } else if #[cfg(esp32s2)] {
+        const MAX_TIMEOUT: u32 = 0xFF_FFFF;
+    } else {
+        const MAX_TIMEOUT: u32 = 0x1F;
+    }
+}
 
 /// Representation of I2C address.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -85,7 +162,18 @@ pub enum I2cAddress {
     ///
     /// * `0b0110010_0` or `0x64` for *writes*
     /// * `0b0110010_1` or `0x65` for *reads*
+    ///
+    /// Addresses above `0x7F` and the reserved addresses `0x01..=0x07` and
+    /// `0x78..=0x7F` are rejected with [`Error::AddressInvalid`]. The general
+    /// call address `0x00` is accepted.
     SevenBit(u8),
+
+    /// 10-bit address mode type.
+    ///
+    /// The address is in the range `0x000..=0x3FF`. It is sent as two bytes,
+    /// `0b11110_A9_A8_0` followed by the lower eight bits. Reads repeat the
+    /// first byte with the read bit set after a repeated START condition.
+    TenBit(u16),
 }
 
 impl From<u8> for I2cAddress {
@@ -94,18 +182,68 @@ impl From<u8> for I2cAddress {
     }
 }
 
+impl I2cAddress {
+    fn validate(&self) -> Result<(), Error> {
+        let valid = match *self {
+            I2cAddress::SevenBit(address) => address == 0x00 || (0x08..=0x77).contains(&address),
+            I2cAddress::TenBit(address) => address <= 0x3FF,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::AddressInvalid(*self))
+        }
+    }
+
+    /// Returns the first byte of the 10-bit address preamble, without the R/W
+    /// bit.
+    fn ten_bit_header(address: u16) -> u8 {
+        0b1111_0000 | ((address >> 7) as u8 & 0b0110)
+    }
+
+    /// The number of bytes the address takes at the start of a write.
+    fn write_len(&self) -> usize {
+        match self {
+            I2cAddress::SevenBit(_) => 1,
+            I2cAddress::TenBit(_) => 2,
+        }
+    }
+}
+
 /// I2C SCL timeout period.
 ///
 /// When the level of SCL remains unchanged for more than `timeout` bus
-/// clock cycles, the bus goes to idle state.
+/// clock cycles, the bus goes to idle state and the transfer fails with
+/// [`Error::Timeout`].
 ///
 /// Default value is `BusCycles(10)`.
+///
+/// The longest timeout the hardware can represent depends on the chip:
+#[cfg_attr(esp32, doc = "`0xF_FFFF` cycles of the 80 MHz APB clock, about 13 ms.")]
+#[cfg_attr(
+    esp32s2,
+    doc = "`0xFF_FFFF` cycles of the 80 MHz APB clock, about 209 ms."
+)]
+#[cfg_attr(
+    not(any(esp32, esp32s2)),
+    doc = "2<sup>31</sup> cycles of the crystal clock, about 53 s with a 40 MHz crystal. The crystal clock is divided for bus frequencies below 40 kHz, which lengthens the maximum accordingly."
+)]
 #[doc = ""]
 #[cfg_attr(
-    not(esp32),
-    doc = "Note that the effective timeout may be longer than the value configured here."
+    not(any(esp32, esp32s2)),
+    doc = "Timeouts are rounded up to a power of two crystal clock cycles, so the effective timeout may be longer than the value configured here."
 )]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, strum::Display)]
+#[doc = ""]
+/// The hardware timeout doesn't cover every phase of a transfer, so the driver
+/// also stops waiting for the controller once the timeout, plus the time
+/// needed to move a full FIFO over the bus, has passed without progress. Async
+/// operations check this bound when they are woken, and otherwise rely on the
+/// timeout interrupt. Disabling the hardware timeout doesn't remove this
+/// bound, which then uses the longest hardware timeout instead. Waits for the
+/// controller, including the abort that stops a dropped async transfer,
+/// therefore always end.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum::Display)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 // TODO: when supporting interrupts, document that SCL = high also triggers an
@@ -120,30 +258,67 @@ pub enum BusTimeout {
 
     /// Timeout in bus clock cycles.
     BusCycles(u32),
+
+    /// Timeout as a duration.
+    ///
+    /// Durations longer than the hardware can represent are rejected with
+    /// [`ConfigError::TimeoutInvalid`].
+    Duration(Duration),
 }
 
-impl BusTimeout {
-    fn cycles(&self) -> u32 {
+impl core::hash::Hash for BusTimeout {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
         match self {
-            #[cfg(esp32)]
-            BusTimeout::Maximum => 0xF_FFFF,
-
-            #[cfg(esp32s2)]
-            BusTimeout::Maximum => 0xFF_FFFF,
-
+            BusTimeout::Maximum => {}
             #[cfg(not(any(esp32, esp32s2)))]
-            BusTimeout::Maximum => 0x1F,
+            BusTimeout::Disabled => {}
+            BusTimeout::BusCycles(cycles) => cycles.hash(state),
+            // `Duration` doesn't implement `Hash`
+            BusTimeout::Duration(duration) => duration.to_micros().hash(state),
+        }
+    }
+}
 
+impl BusTimeout {
+    /// Converts the timeout into the value of the timeout register.
+    ///
+    /// `clock_hz` is the frequency of the clock the hardware counts the
+    /// timeout in, and `half_cycle` the number of its cycles per half SCL
+    /// period. `None` means that the timeout is disabled.
+    fn register_value(&self, half_cycle: u32, clock_hz: u32) -> Result<Option<u32>, ConfigError> {
+        let cycles = match *self {
+            BusTimeout::Maximum => return Ok(Some(MAX_TIMEOUT)),
             #[cfg(not(any(esp32, esp32s2)))]
-            BusTimeout::Disabled => 1,
+            BusTimeout::Disabled => return Ok(None),
+            BusTimeout::BusCycles(cycles) => cycles.saturating_mul(2 * half_cycle),
+            BusTimeout::Duration(duration) => duration_to_cycles(duration, clock_hz),
+        };
 
-            BusTimeout::BusCycles(cycles) => *cycles,
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32, esp32s2))] {
+                check_timeout(cycles, MAX_TIMEOUT).map(Some)
+            } else {
+                // The timeout is configured as a power of two of cycles.
+                let cycles = cycles.max(1);
+                let log2 = cycles.ilog2();
+                // Round up so that we don't shorten timeouts.
+                let raw = if cycles != 1 << log2 { log2 + 1 } else { log2 };
+                check_timeout(raw, MAX_TIMEOUT).map(Some)
+            }
         }
     }
 
-    #[cfg(not(esp32))]
-    fn is_set(&self) -> bool {
-        matches!(self, BusTimeout::BusCycles(_) | BusTimeout::Maximum)
+    /// Returns how long the timeout configured by the register value `raw`
+    /// lasts, inverting [`Self::register_value`].
+    fn register_duration(raw: u32, clock_hz: u32) -> Duration {
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32, esp32s2))] {
+                cycles_to_duration(raw as u64, clock_hz)
+            } else {
+                cycles_to_duration(1 << raw, clock_hz)
+            }
+        }
     }
 }
 
@@ -166,6 +341,10 @@ pub enum Error {
     CommandNumberExceeded,
     /// Zero length read or write operation.
     ZeroLengthInvalid,
+    /// A device kept holding SDA or SCL low while the bus was being cleared.
+    BusStuck,
+    /// The address is out of range or reserved.
+    AddressInvalid(I2cAddress),
 }
 
 /// I2C no acknowledge error reason.
@@ -229,6 +408,10 @@ impl core::fmt::Display for Error {
                 write!(f, "The number of commands issued exceeded the limit")
             }
             Error::ZeroLengthInvalid => write!(f, "Zero length read or write operation"),
+            Error::BusStuck => write!(f, "A device kept holding the bus lines low"),
+            Error::AddressInvalid(address) => {
+                write!(f, "The address {:?} is out of range or reserved", address)
+            }
         }
     }
 }
@@ -240,6 +423,9 @@ impl core::fmt::Display for Error {
 pub enum ConfigError {
     /// Provided bus frequency is invalid for the current configuration.
     FrequencyInvalid,
+    /// The clock dividers can't produce a bus frequency within 5% of the
+    /// provided one. The closest frequency they can produce is contained.
+    FrequencyUnreachable(HertzU32),
     /// Provided timeout is invalid for the current configuration.
     TimeoutInvalid,
 }
@@ -253,6 +439,11 @@ impl core::fmt::Display for ConfigError {
                 f,
                 "Provided bus frequency is invalid for the current configuration"
             ),
+            ConfigError::FrequencyUnreachable(closest) => write!(
+                f,
+                "Provided bus frequency can't be reached, the closest achievable frequency is {}",
+                closest
+            ),
             ConfigError::TimeoutInvalid => write!(
                 f,
                 "Provided timeout is invalid for the current configuration"
@@ -475,6 +666,20 @@ impl<Dm: DriverMode> embedded_hal::i2c::I2c for I2c<'_, Dm> {
     }
 }
 
+impl<Dm: DriverMode> embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2c<'_, Dm> {
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_impl(
+            I2cAddress::TenBit(address),
+            operations.iter_mut().map(Operation::from),
+        )
+        .inspect_err(|_| self.internal_recover())
+    }
+}
+
 impl<'d, Dm: DriverMode> I2c<'d, Dm> {
     fn driver(&self) -> Driver<'_> {
         Driver {
@@ -494,16 +699,117 @@ impl<'d, Dm: DriverMode> I2c<'d, Dm> {
 
     /// Applies a new configuration.
     ///
+    /// This reprograms the SCL periods, the setup and hold times and the
+    /// timeout, so it can be used to switch the bus frequency between
+    /// transactions.
+    ///
     /// # Errors
     ///
     /// A [`ConfigError`] variant will be returned if bus frequency or timeout
-    /// passed in config is invalid.
+    /// passed in config is invalid. The previous configuration stays in effect
+    /// in that case.
     pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
-        self.driver().setup(config)?;
+        self.driver().setup(config).inspect_err(|_| {
+            // The previous configuration was accepted before.
+            _ = self.driver().setup(&self.config);
+        })?;
         self.config = *config;
         Ok(())
     }
 
+    /// Recovers the bus after a device was left holding SDA low, for example
+    /// because it was reset in the middle of a transfer.
+    ///
+    /// This temporarily drives SDA and SCL as GPIOs and pulses SCL up to nine
+    /// times, until the device releases SDA. It then generates a STOP
+    /// condition, resets the controller and hands the pins back to it with
+    /// their previous configuration, whether or not the recovery succeeded.
+    ///
+    /// Nothing is done if either pin isn't connected to a GPIO.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::BusStuck`] is returned if SDA is still low after the clock
+    /// pulses, or if a device holds SCL low for longer than the configured
+    /// timeout.
+    #[instability::unstable]
+    pub fn clear_bus(&mut self) -> Result<(), Error> {
+        let (Some(sda), Some(scl)) = (self.sda_pin.pin_number(), self.scl_pin.pin_number()) else {
+            return Ok(());
+        };
+        let sda = unsafe { AnyPin::steal(sda) };
+        let scl = unsafe { AnyPin::steal(scl) };
+
+        let sda_config = sda.save_output_config();
+        let scl_config = scl.save_output_config();
+
+        let result = self.clock_out_stuck_device(&sda, &scl);
+
+        scl.restore_output_config(&scl_config);
+        sda.restore_output_config(&sda_config);
+
+        self.internal_recover();
+
+        result
+    }
+
+    fn clock_out_stuck_device(&self, sda: &AnyPin, scl: &AnyPin) -> Result<(), Error> {
+        let delay = Delay::new();
+        let half_period = (500_000 / self.config.frequency.raw()).max(1);
+        let state = self.driver().state;
+
+        let release_scl = || {
+            scl.set_output_high(true);
+
+            // The device may stretch the clock.
+            let deadline = state.deadline();
+            while !scl.is_input_high() {
+                if crate::time::now() > deadline {
+                    return Err(Error::BusStuck);
+                }
+            }
+
+            delay.delay_micros(half_period);
+            Ok(())
+        };
+
+        // Release both lines before taking them over from the controller.
+        for pin in [sda, scl] {
+            pin.set_output_high(true);
+            pin.set_to_open_drain_output();
+        }
+        release_scl()?;
+
+        for _ in 0..9 {
+            if sda.is_input_high() {
+                break;
+            }
+
+            scl.set_output_high(false);
+            delay.delay_micros(half_period);
+            release_scl()?;
+        }
+
+        if !sda.is_input_high() {
+            return Err(Error::BusStuck);
+        }
+
+        // STOP condition: SDA rises while SCL is high.
+        scl.set_output_high(false);
+        delay.delay_micros(half_period);
+        sda.set_output_high(false);
+        delay.delay_micros(half_period);
+        release_scl()?;
+        sda.set_output_high(true);
+        delay.delay_micros(half_period);
+
+        if sda.is_input_high() {
+            Ok(())
+        } else {
+            Err(Error::BusStuck)
+        }
+    }
+
     fn transaction_impl<'a>(
         &mut self,
         address: I2cAddress,
@@ -808,6 +1114,54 @@ impl<'d> I2c<'d, Blocking> {
         self.i2c.info().clear_interrupts(interrupts)
     }
 
+    /// Starts writing `bytes` to the device at `address`, and returns without
+    /// waiting for the write to complete.
+    ///
+    /// The write makes progress each time [`I2cTransfer::poll`] is called.
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::i2c::master::{Config, I2c};
+    /// # let mut i2c = I2c::new(
+    /// #   peripherals.I2C0,
+    /// #   Config::default(),
+    /// # )?;
+    /// # const DEVICE_ADDR: u8 = 0x77;
+    /// let mut transfer = i2c.start_write(DEVICE_ADDR, &[0xaa, 0x55])?;
+    /// loop {
+    ///     match transfer.poll() {
+    ///         Ok(()) => break,
+    ///         Err(nb::Error::WouldBlock) => {
+    ///             // Do something else in the meantime.
+    ///         }
+    ///         Err(nb::Error::Other(error)) => return Err(error.into()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instability::unstable]
+    pub fn start_write<'a, A: Into<I2cAddress>>(
+        &'a mut self,
+        address: A,
+        bytes: &'a [u8],
+    ) -> Result<I2cTransfer<'a, 'd>, Error> {
+        I2cTransfer::start(self, address.into(), TransferBuffer::Write(bytes))
+    }
+
+    /// Starts reading enough bytes from the device at `address` to fill
+    /// `buffer`, and returns without waiting for the read to complete.
+    ///
+    /// The read makes progress each time [`I2cTransfer::poll`] is called.
+    #[instability::unstable]
+    pub fn start_read<'a, A: Into<I2cAddress>>(
+        &'a mut self,
+        address: A,
+        buffer: &'a mut [u8],
+    ) -> Result<I2cTransfer<'a, 'd>, Error> {
+        I2cTransfer::start(self, address.into(), TransferBuffer::Read(buffer))
+    }
+
     /// Configures the I2C peripheral to operate in asynchronous mode.
     pub fn into_async(mut self) -> I2c<'d, Async> {
         self.set_interrupt_handler(self.driver().info.async_handler);
@@ -832,7 +1186,260 @@ impl crate::interrupt::InterruptConfigurable for I2c<'_, Blocking> {
     }
 }
 
-#[cfg_attr(esp32, allow(dead_code))]
+enum TransferBuffer<'a> {
+    Write(&'a [u8]),
+    Read(&'a mut [u8]),
+}
+
+impl TransferBuffer<'_> {
+    fn len(&self) -> usize {
+        match self {
+            TransferBuffer::Write(bytes) => bytes.len(),
+            TransferBuffer::Read(buffer) => buffer.len(),
+        }
+    }
+}
+
+/// A write or read that makes progress when it's polled, created by
+/// [`I2c::start_write`] or [`I2c::start_read`].
+///
+/// Each call to [`poll`](Self::poll) moves data between the buffer and the
+/// FIFOs and starts the next command list once the current one is done, so
+/// long transfers are still sent as a single transaction. The transfer has to
+/// be polled often enough for the FIFOs not to run empty or full, otherwise
+/// the controller holds SCL low until it's polled again, which may trigger the
+/// bus timeout.
+///
+/// To poll from an interrupt handler, [`listen`](I2c::listen) for
+/// [`Event::EndDetect`], [`Event::TxComplete`] and, where available, the FIFO
+/// watermark events. `poll` clears the flags it acts on.
+///
+/// Dropping a transfer before it's done ends it with a STOP condition and
+/// resets the controller, which also disables all interrupts.
+#[instability::unstable]
+pub struct I2cTransfer<'a, 'd> {
+    i2c: &'a mut I2c<'d, Blocking>,
+    address: I2cAddress,
+    buffer: TransferBuffer<'a>,
+    // The part of `buffer` that the current command list transfers.
+    chunk: Option<core::ops::Range<usize>>,
+    // The next byte of `buffer` to move to or from the FIFO.
+    index: usize,
+    deadline: Instant,
+    result: Option<Result<(), Error>>,
+}
+
+#[cfg_attr(not(feature = "unstable"), allow(dead_code))]
+impl<'a, 'd> I2cTransfer<'a, 'd> {
+    fn start(
+        i2c: &'a mut I2c<'d, Blocking>,
+        address: I2cAddress,
+        buffer: TransferBuffer<'a>,
+    ) -> Result<Self, Error> {
+        let deadline = i2c.driver().state.deadline();
+        let mut transfer = Self {
+            i2c,
+            address,
+            buffer,
+            chunk: None,
+            index: 0,
+            deadline,
+            result: None,
+        };
+
+        // Like `I2c::read`, an empty read doesn't access the bus.
+        if matches!(transfer.buffer, TransferBuffer::Read(ref buffer) if buffer.is_empty()) {
+            transfer.result = Some(Ok(()));
+            return Ok(transfer);
+        }
+
+        if let Err(error) = transfer.start_chunk() {
+            transfer.result = Some(Err(error));
+            transfer.i2c.internal_recover();
+            return Err(error);
+        }
+
+        Ok(transfer)
+    }
+
+    /// Moves the transfer forward without blocking.
+    ///
+    /// Returns `Ok(())` once the transfer is complete, and
+    /// [`nb::Error::WouldBlock`] while it's still running. Errors, e.g. a
+    /// missing acknowledgment or lost arbitration, end the transfer and reset
+    /// the controller. Polling a finished transfer returns the same result
+    /// again.
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        if let Some(result) = self.result {
+            return result.map_err(nb::Error::Other);
+        }
+
+        match self.poll_impl() {
+            Ok(false) => Err(nb::Error::WouldBlock),
+            Ok(true) => {
+                self.result = Some(Ok(()));
+                Ok(())
+            }
+            Err(error) => {
+                self.result = Some(Err(error));
+                self.i2c.internal_recover();
+                Err(nb::Error::Other(error))
+            }
+        }
+    }
+
+    /// Returns whether the transfer has ended, successfully or not.
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Polls the transfer until it's complete.
+    pub fn wait(mut self) -> Result<(), Error> {
+        nb::block!(self.poll())
+    }
+
+    // Sets up the command list for the next part of the buffer.
+    fn start_chunk(&mut self) -> Result<(), Error> {
+        let start = self.chunk.is_none();
+        let offset = self.chunk.as_ref().map_or(0, |chunk| chunk.end);
+        let max_len = match self.buffer {
+            TransferBuffer::Write(_) if start => I2C_MAX_WRITE_LEN - self.address.write_len(),
+            TransferBuffer::Write(_) => I2C_MAX_WRITE_LEN,
+            TransferBuffer::Read(_) => I2C_CHUNK_SIZE,
+        };
+        let end = (offset + max_len).min(self.buffer.len());
+        let stop = end == self.buffer.len();
+
+        let driver = self.i2c.driver();
+        driver.clear_all_interrupts();
+        match &mut self.buffer {
+            TransferBuffer::Write(bytes) => {
+                let written =
+                    driver.start_write_operation(self.address, &bytes[offset..end], start, stop)?;
+                self.index = offset + written;
+            }
+            TransferBuffer::Read(buffer) => {
+                driver.start_read_operation(
+                    self.address,
+                    &mut buffer[offset..end],
+                    start,
+                    stop,
+                    !stop,
+                )?;
+                self.index = offset;
+            }
+        }
+
+        self.chunk = Some(offset..end);
+        self.deadline = self.i2c.driver().state.deadline();
+        Ok(())
+    }
+
+    // Returns whether the transfer is complete.
+    fn poll_impl(&mut self) -> Result<bool, Error> {
+        let Some(chunk) = self.chunk.clone() else {
+            return Ok(true);
+        };
+        let stop = chunk.end == self.buffer.len();
+
+        self.i2c.driver().check_errors()?;
+        let mut progress = self.move_data(chunk.end)?;
+
+        let interrupts = self.i2c.driver().regs().int_raw().read();
+        if interrupts.end_detect().bit_is_set()
+            || (stop && interrupts.trans_complete().bit_is_set())
+        {
+            let driver = self.i2c.driver();
+            driver.check_errors()?;
+            driver.check_all_commands_done()?;
+
+            // The last bytes of a read may have arrived with the end of the
+            // command list.
+            self.move_data(chunk.end)?;
+            if self.index != chunk.end {
+                return Err(Error::ExecutionIncomplete);
+            }
+
+            if stop {
+                return Ok(true);
+            }
+
+            self.start_chunk()?;
+            progress = true;
+        }
+
+        if progress {
+            self.deadline = self.i2c.driver().state.deadline();
+        } else {
+            self.i2c.driver().check_deadline(self.deadline)?;
+        }
+
+        Ok(false)
+    }
+
+    // Moves as much of the current chunk as possible between the buffer and
+    // the FIFO, and returns whether anything was moved.
+    #[cfg(not(any(esp32, esp32s2)))]
+    fn move_data(&mut self, end: usize) -> Result<bool, Error> {
+        let driver = self.i2c.driver();
+        let start = self.index;
+
+        match &mut self.buffer {
+            TransferBuffer::Write(bytes) => {
+                if self.index < end && driver.regs().int_raw().read().txfifo_wm().bit_is_set() {
+                    driver
+                        .regs()
+                        .int_clr()
+                        .write(|w| w.txfifo_wm().clear_bit_by_one());
+                    self.index += driver.fill_tx_fifo(&bytes[self.index..end])?;
+                }
+            }
+            TransferBuffer::Read(buffer) => {
+                let available = driver.regs().sr().read().rxfifo_cnt().bits() as usize;
+                let count = available.min(end - self.index);
+                for byte in &mut buffer[self.index..][..count] {
+                    *byte = read_fifo(driver.regs());
+                }
+                self.index += count;
+            }
+        }
+
+        Ok(self.index != start)
+    }
+
+    // The whole chunk fits the FIFO. Writes are filled in by
+    // `start_write_operation`, reads are collected once the chunk is done.
+    #[cfg(any(esp32, esp32s2))]
+    fn move_data(&mut self, end: usize) -> Result<bool, Error> {
+        let driver = self.i2c.driver();
+
+        if let TransferBuffer::Read(buffer) = &mut self.buffer {
+            let interrupts = driver.regs().int_raw().read();
+            if self.index < end
+                && (interrupts.end_detect().bit_is_set()
+                    || interrupts.trans_complete().bit_is_set())
+            {
+                for byte in &mut buffer[self.index..end] {
+                    *byte = read_fifo(driver.regs());
+                }
+                self.index = end;
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+impl Drop for I2cTransfer<'_, '_> {
+    fn drop(&mut self) {
+        if self.result.is_none() {
+            self.i2c.driver().abort_transaction();
+            self.i2c.internal_recover();
+        }
+    }
+}
+
 #[derive(Debug, EnumSetType)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
@@ -849,17 +1456,21 @@ pub enum Event {
     /// falls below the configured watermark.
     #[cfg(not(any(esp32, esp32s2)))]
     TxFifoWatermark,
+
+    /// Triggered when the RX FIFO watermark check is enabled and the RX fifo
+    /// reaches the configured watermark.
+    #[cfg(not(any(esp32, esp32s2)))]
+    RxFifoWatermark,
 }
 
-#[cfg(not(esp32))]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 struct I2cFuture<'a> {
     event: Event,
     info: &'a Info,
     state: &'a State,
+    deadline: Instant,
 }
 
-#[cfg(not(esp32))]
 impl<'a> I2cFuture<'a> {
     pub fn new(event: Event, info: &'a Info, state: &'a State) -> Self {
         info.regs().int_ena().modify(|_, w| {
@@ -868,6 +1479,8 @@ impl<'a> I2cFuture<'a> {
                 Event::TxComplete => w.trans_complete().set_bit(),
                 #[cfg(not(any(esp32, esp32s2)))]
                 Event::TxFifoWatermark => w.txfifo_wm().set_bit(),
+                #[cfg(not(any(esp32, esp32s2)))]
+                Event::RxFifoWatermark => w.rxfifo_wm().set_bit(),
             };
 
             w.arbitration_lost().set_bit();
@@ -877,7 +1490,12 @@ impl<'a> I2cFuture<'a> {
             w
         });
 
-        Self { event, state, info }
+        Self {
+            event,
+            state,
+            info,
+            deadline: state.deadline(),
+        }
     }
 
     fn event_bit_is_clear(&self) -> bool {
@@ -888,6 +1506,8 @@ impl<'a> I2cFuture<'a> {
             Event::TxComplete => r.trans_complete().bit_is_clear(),
             #[cfg(not(any(esp32, esp32s2)))]
             Event::TxFifoWatermark => r.txfifo_wm().bit_is_clear(),
+            #[cfg(not(any(esp32, esp32s2)))]
+            Event::RxFifoWatermark => r.rxfifo_wm().bit_is_clear(),
         }
     }
 
@@ -920,7 +1540,6 @@ impl<'a> I2cFuture<'a> {
     }
 }
 
-#[cfg(not(esp32))]
 impl core::future::Future for I2cFuture<'_> {
     type Output = Result<(), Error>;
 
@@ -935,12 +1554,42 @@ impl core::future::Future for I2cFuture<'_> {
 
         if self.event_bit_is_clear() {
             Poll::Ready(Ok(()))
+        } else if crate::time::now() > self.deadline {
+            Poll::Ready(Err(Error::Timeout))
         } else {
             Poll::Pending
         }
     }
 }
 
+/// Releases the bus if an async operation is dropped before it completes.
+struct AbortGuard<'a, 'd> {
+    i2c: &'a I2c<'d, Async>,
+    defused: bool,
+}
+
+impl<'a, 'd> AbortGuard<'a, 'd> {
+    fn new(i2c: &'a I2c<'d, Async>) -> Self {
+        Self {
+            i2c,
+            defused: false,
+        }
+    }
+
+    fn defuse(mut self) {
+        self.defused = true;
+    }
+}
+
+impl Drop for AbortGuard<'_, '_> {
+    fn drop(&mut self) {
+        if !self.defused {
+            self.i2c.driver().abort_transaction();
+            self.i2c.internal_recover();
+        }
+    }
+}
+
 impl<'d> I2c<'d, Async> {
     /// Configure the I2C peripheral to operate in blocking mode.
     pub fn into_blocking(self) -> I2c<'d, Blocking> {
@@ -957,15 +1606,22 @@ impl<'d> I2c<'d, Async> {
     }
 
     /// Writes bytes to slave with address `address`
+    ///
+    /// If the returned future is dropped before it completes, the transaction
+    /// is ended with a STOP condition.
     pub async fn write_async<A: Into<I2cAddress>>(
         &mut self,
         address: A,
         buffer: &[u8],
     ) -> Result<(), Error> {
-        self.driver()
+        let guard = AbortGuard::new(self);
+        let result = self
+            .driver()
             .write(address.into(), buffer, true, true)
-            .await
-            .inspect_err(|_| self.internal_recover())
+            .await;
+        guard.defuse();
+
+        result.inspect_err(|_| self.internal_recover())
     }
 
     /// Reads enough bytes from slave with `address` to fill `buffer`
@@ -974,15 +1630,22 @@ impl<'d> I2c<'d, Async> {
     ///
     /// The corresponding error variant from [`Error`] will be returned if the
     /// passed buffer has zero length.
+    ///
+    /// If the returned future is dropped before it completes, the transaction
+    /// is ended with a STOP condition.
     pub async fn read_async<A: Into<I2cAddress>>(
         &mut self,
         address: A,
         buffer: &mut [u8],
     ) -> Result<(), Error> {
-        self.driver()
+        let guard = AbortGuard::new(self);
+        let result = self
+            .driver()
             .read(address.into(), buffer, true, true, false)
-            .await
-            .inspect_err(|_| self.internal_recover())
+            .await;
+        guard.defuse();
+
+        result.inspect_err(|_| self.internal_recover())
     }
 
     /// Writes bytes to slave with address `address` and then reads enough
@@ -992,6 +1655,9 @@ impl<'d> I2c<'d, Async> {
     ///
     /// The corresponding error variant from [`Error`] will be returned if the
     /// passed buffer has zero length.
+    ///
+    /// If the returned future is dropped before it completes, the transaction
+    /// is ended with a STOP condition.
     pub async fn write_read_async<A: Into<I2cAddress>>(
         &mut self,
         address: A,
@@ -1000,17 +1666,19 @@ impl<'d> I2c<'d, Async> {
     ) -> Result<(), Error> {
         let address = address.into();
 
-        self.driver()
-            .write(address, write_buffer, true, read_buffer.is_empty())
-            .await
-            .inspect_err(|_| self.internal_recover())?;
-
-        self.driver()
-            .read(address, read_buffer, true, true, false)
-            .await
-            .inspect_err(|_| self.internal_recover())?;
+        let guard = AbortGuard::new(self);
+        let result = async {
+            self.driver()
+                .write(address, write_buffer, true, read_buffer.is_empty())
+                .await?;
+            self.driver()
+                .read(address, read_buffer, true, true, false)
+                .await
+        }
+        .await;
+        guard.defuse();
 
-        Ok(())
+        result.inspect_err(|_| self.internal_recover())
     }
 
     /// Execute the provided operations on the I2C bus as a single
@@ -1037,6 +1705,9 @@ impl<'d> I2c<'d, Async> {
     ///
     /// The corresponding error variant from [`Error`] will be returned if the
     /// buffer passed to an [`Operation`] has zero length.
+    ///
+    /// If the returned future is dropped before it completes, the transaction
+    /// is ended with a STOP condition.
     pub async fn transaction_async<'a, A: Into<I2cAddress>>(
         &mut self,
         address: A,
@@ -1048,7 +1719,19 @@ impl<'d> I2c<'d, Async> {
     }
 
     async fn transaction_impl_async<'a>(
-        &mut self,
+        &self,
+        address: I2cAddress,
+        operations: impl Iterator<Item = Operation<'a>>,
+    ) -> Result<(), Error> {
+        let guard = AbortGuard::new(self);
+        let result = self.transaction_operations_async(address, operations).await;
+        guard.defuse();
+
+        result
+    }
+
+    async fn transaction_operations_async<'a>(
+        &self,
         address: I2cAddress,
         operations: impl Iterator<Item = Operation<'a>>,
     ) -> Result<(), Error> {
@@ -1111,6 +1794,21 @@ impl embedded_hal_async::i2c::I2c for I2c<'_, Async> {
     }
 }
 
+impl embedded_hal_async::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2c<'_, Async> {
+    async fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [EhalOperation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_impl_async(
+            I2cAddress::TenBit(address),
+            operations.iter_mut().map(Operation::from),
+        )
+        .await
+        .inspect_err(|_| self.internal_recover())
+    }
+}
+
 fn async_handler(info: &Info, state: &State) {
     let regs = info.regs();
     regs.int_ena().modify(|_, w| {
@@ -1120,7 +1818,10 @@ fn async_handler(info: &Info, state: &State) {
         w.time_out().clear_bit();
 
         #[cfg(not(any(esp32, esp32s2)))]
-        w.txfifo_wm().clear_bit();
+        {
+            w.txfifo_wm().clear_bit();
+            w.rxfifo_wm().clear_bit();
+        }
 
         w.nack().clear_bit()
     });
@@ -1130,7 +1831,7 @@ fn async_handler(info: &Info, state: &State) {
 
 /// Sets the filter with a supplied threshold in clock cycles for which a
 /// pulse must be present to pass the filter
-fn set_filter(
+pub(super) fn set_filter(
     register_block: &RegisterBlock,
     sda_threshold: Option<u8>,
     scl_threshold: Option<u8>,
@@ -1178,7 +1879,7 @@ fn configure_clock(
     scl_stop_setup_time: u32,
     scl_start_hold_time: u32,
     scl_stop_hold_time: u32,
-    timeout: BusTimeout,
+    timeout: Option<u32>,
 ) -> Result<(), ConfigError> {
     unsafe {
         // divider
@@ -1234,13 +1935,13 @@ fn configure_clock(
             if #[cfg(esp32)] {
                 register_block
                     .to()
-                    .write(|w| w.time_out().bits(timeout.cycles()));
+                    .write(|w| w.time_out().bits(timeout.unwrap_or(MAX_TIMEOUT)));
             } else {
                 register_block
                     .to()
-                    .write(|w| w.time_out_en().bit(timeout.is_set())
+                    .write(|w| w.time_out_en().bit(timeout.is_some())
                     .time_out_value()
-                    .bits(timeout.cycles() as _)
+                    .bits(timeout.unwrap_or(MAX_TIMEOUT) as _)
                 );
             }
         }
@@ -1264,6 +1965,11 @@ pub struct Info {
     /// Interrupt handler for the asynchronous operations of this I2C instance.
     pub async_handler: InterruptHandler,
 
+    /// Interrupt handler for the asynchronous operations of this I2C instance
+    /// in slave mode.
+    #[cfg(i2c_support_slave)]
+    pub slave_async_handler: InterruptHandler,
+
     /// Interrupt for this I2C instance.
     pub interrupt: Interrupt,
 
@@ -1297,6 +2003,8 @@ impl Info {
                     Event::TxComplete => w.trans_complete().bit(enable),
                     #[cfg(not(any(esp32, esp32s2)))]
                     Event::TxFifoWatermark => w.txfifo_wm().bit(enable),
+                    #[cfg(not(any(esp32, esp32s2)))]
+                    Event::RxFifoWatermark => w.rxfifo_wm().bit(enable),
                 };
             }
             w
@@ -1319,6 +2027,10 @@ impl Info {
         if ints.txfifo_wm().bit_is_set() {
             res.insert(Event::TxFifoWatermark);
         }
+        #[cfg(not(any(esp32, esp32s2)))]
+        if ints.rxfifo_wm().bit_is_set() {
+            res.insert(Event::RxFifoWatermark);
+        }
 
         res
     }
@@ -1333,13 +2045,15 @@ impl Info {
                     Event::TxComplete => w.trans_complete().clear_bit_by_one(),
                     #[cfg(not(any(esp32, esp32s2)))]
                     Event::TxFifoWatermark => w.txfifo_wm().clear_bit_by_one(),
+                    #[cfg(not(any(esp32, esp32s2)))]
+                    Event::RxFifoWatermark => w.rxfifo_wm().clear_bit_by_one(),
                 };
             }
             w
         });
     }
 
-    fn set_interrupt_handler(&self, handler: InterruptHandler) {
+    pub(super) fn set_interrupt_handler(&self, handler: InterruptHandler) {
         for core in crate::Cpu::other() {
             crate::interrupt::disable(core, self.interrupt);
         }
@@ -1349,7 +2063,7 @@ impl Info {
         unwrap!(crate::interrupt::enable(self.interrupt, handler.priority()));
     }
 
-    fn disable_interrupts(&self) {
+    pub(super) fn disable_interrupts(&self) {
         crate::interrupt::disable(crate::Cpu::current(), self.interrupt);
     }
 }
@@ -1427,6 +2141,22 @@ impl Driver<'_> {
         self.reset_command_list();
     }
 
+    /// Stops the transaction in progress by issuing a STOP condition, so that
+    /// neither the controller nor the devices on the bus are left waiting for
+    /// the rest of it.
+    fn abort_transaction(&self) {
+        self.regs().int_ena().write(|w| unsafe { w.bits(0) });
+
+        self.reset();
+
+        // A STOP on its own is always accepted by an empty command list.
+        _ = add_cmd(&mut self.regs().comd_iter(), Command::Stop);
+        self.start_transmission();
+        _ = self.wait_for_completion_blocking(false);
+
+        self.reset();
+    }
+
     /// Resets the I2C peripheral's command registers
     fn reset_command_list(&self) {
         // Confirm that all commands that were configured were actually executed
@@ -1435,6 +2165,30 @@ impl Driver<'_> {
         }
     }
 
+    /// Bounds the waits for the controller, given the value of the timeout
+    /// register, counted in cycles of a `clock_hz` clock.
+    ///
+    /// The waits stay bounded if the hardware timeout is disabled, as if it
+    /// was set to the maximum.
+    fn set_software_timeout(&self, timeout: Option<u32>, clock_hz: u32, bus_freq: u32) {
+        let timeout = BusTimeout::register_duration(timeout.unwrap_or(MAX_TIMEOUT), clock_hz);
+        // A full FIFO and the address, 9 bits per byte including the ACK.
+        let transfer = ((I2C_FIFO_SIZE as u64 + 1) * 9 * 1_000_000).div_ceil(bus_freq as u64);
+        let micros = (timeout.to_micros() + transfer).min(u32::MAX as u64) as u32;
+        self.state.software_timeout.store(micros, Ordering::Relaxed);
+    }
+
+    /// Fails with [`Error::Timeout`] once `deadline` has passed, resetting the
+    /// controller like [`Self::check_errors`] does.
+    fn check_deadline(&self, deadline: Instant) -> Result<(), Error> {
+        if crate::time::now() > deadline {
+            self.reset();
+            return Err(Error::Timeout);
+        }
+
+        Ok(())
+    }
+
     #[cfg(esp32)]
     /// Sets the frequency of the I2C interface by calculating and applying the
     /// associated timings - corresponds to i2c_ll_cal_bus_clk and
@@ -1444,17 +2198,16 @@ impl Driver<'_> {
         let source_clk = clocks.i2c_clock.raw();
         let bus_freq = clock_config.frequency.raw();
 
-        let half_cycle: u32 = source_clk / bus_freq / 2;
+        // SCL high needs room for the filter compensation below.
+        let half_cycle: u32 = half_cycles(source_clk, bus_freq, 14..=0x4000)?;
         let scl_low = half_cycle;
         let scl_high = half_cycle;
         let sda_hold = half_cycle / 2;
         let sda_sample = scl_high / 2;
         let setup = half_cycle;
         let hold = half_cycle;
-        let timeout = BusTimeout::BusCycles(match timeout {
-            BusTimeout::Maximum => 0xF_FFFF,
-            BusTimeout::BusCycles(cycles) => check_timeout(cycles * 2 * half_cycle, 0xF_FFFF)?,
-        });
+        let timeout = timeout.register_value(half_cycle, source_clk)?;
+        self.set_software_timeout(timeout, source_clk, bus_freq);
 
         // SCL period. According to the TRM, we should always subtract 1 to SCL low
         // period
@@ -1522,7 +2275,7 @@ impl Driver<'_> {
         let source_clk = clocks.apb_clock.raw();
         let bus_freq = clock_config.frequency.raw();
 
-        let half_cycle: u32 = source_clk / bus_freq / 2;
+        let half_cycle: u32 = half_cycles(source_clk, bus_freq, 4..=0x4000)?;
         // SCL
         let scl_low = half_cycle;
         // default, scl_wait_high < scl_high
@@ -1548,10 +2301,8 @@ impl Driver<'_> {
         let scl_start_hold_time = hold - 1;
         let scl_stop_hold_time = hold;
 
-        let timeout = BusTimeout::BusCycles(match timeout {
-            BusTimeout::Maximum => 0xFF_FFFF,
-            BusTimeout::BusCycles(cycles) => check_timeout(cycles * 2 * half_cycle, 0xFF_FFFF)?,
-        });
+        let timeout = timeout.register_value(half_cycle, source_clk)?;
+        self.set_software_timeout(timeout, source_clk, bus_freq);
 
         configure_clock(
             self.regs(),
@@ -1580,9 +2331,13 @@ impl Driver<'_> {
         let source_clk = clocks.xtal_clock.raw();
         let bus_freq = clock_config.frequency.raw();
 
-        let clkm_div: u32 = source_clk / (bus_freq * 1024) + 1;
+        if bus_freq == 0 {
+            return Err(ConfigError::FrequencyInvalid);
+        }
+
+        let clkm_div: u32 = (source_clk / (bus_freq.saturating_mul(1024)) + 1).min(256);
         let sclk_freq: u32 = source_clk / clkm_div;
-        let half_cycle: u32 = sclk_freq / bus_freq / 2;
+        let half_cycle: u32 = half_cycles(sclk_freq, bus_freq, 4..=512)?;
         // SCL
         let scl_low = half_cycle;
         // default, scl_wait_high < scl_high
@@ -1620,17 +2375,8 @@ impl Driver<'_> {
         let scl_start_hold_time = hold - 1;
         let scl_stop_hold_time = hold - 1;
 
-        let timeout = match timeout {
-            BusTimeout::Maximum => BusTimeout::BusCycles(0x1F),
-            BusTimeout::Disabled => BusTimeout::Disabled,
-            BusTimeout::BusCycles(cycles) => {
-                let to_peri = (cycles * 2 * half_cycle).max(1);
-                let log2 = to_peri.ilog2();
-                // Round up so that we don't shorten timeouts.
-                let raw = if to_peri != 1 << log2 { log2 + 1 } else { log2 };
-                BusTimeout::BusCycles(check_timeout(raw, 0x1F)?)
-            }
-        };
+        let timeout = timeout.register_value(half_cycle, sclk_freq)?;
+        self.set_software_timeout(timeout, sclk_freq, bus_freq);
 
         configure_clock(
             self.regs(),
@@ -1667,7 +2413,28 @@ impl Driver<'_> {
 
     #[cfg(not(any(esp32, esp32s2)))]
     async fn read_all_from_fifo(&self, buffer: &mut [u8]) -> Result<(), Error> {
-        self.read_all_from_fifo_blocking(buffer)
+        for byte in buffer.iter_mut() {
+            loop {
+                self.check_errors()?;
+
+                // Clear the watermark before checking the FIFO, so that a byte that arrives
+                // in between re-raises it.
+                self.regs()
+                    .int_clr()
+                    .write(|w| w.rxfifo_wm().clear_bit_by_one());
+
+                let reg = self.regs().fifo_st().read();
+                if reg.rxfifo_raddr().bits() != reg.rxfifo_waddr().bits() {
+                    break;
+                }
+
+                I2cFuture::new(Event::RxFifoWatermark, self.info, self.state).await?;
+            }
+
+            *byte = read_fifo(self.regs());
+        }
+
+        Ok(())
     }
 
     /// Configures the I2C peripheral for a write operation.
@@ -1686,15 +2453,19 @@ impl Driver<'_> {
     where
         I: Iterator<Item = &'a COMD>,
     {
-        // if start is true we can only send 254 additional bytes with the address as
-        // the first
-        let max_len = if start { 254usize } else { 255usize };
-        if bytes.len() > max_len {
-            // we could support more by adding multiple write operations
+        // if start is true the address is sent as part of the write
+        let address_len = if start {
+            addr.validate()?;
+            addr.write_len()
+        } else {
+            0
+        };
+        if bytes.len() + address_len > I2C_MAX_WRITE_LEN {
+            // longer writes are split into several operations by `write_chunks`
             return Err(Error::FifoExceeded);
         }
 
-        let write_len = if start { bytes.len() + 1 } else { bytes.len() };
+        let write_len = bytes.len() + address_len;
         // don't issue write if there is no data to write
         if write_len > 0 {
             // WRITE command
@@ -1716,6 +2487,13 @@ impl Driver<'_> {
                 I2cAddress::SevenBit(addr) => {
                     write_fifo(self.regs(), (addr << 1) | OperationType::Write as u8);
                 }
+                I2cAddress::TenBit(addr) => {
+                    write_fifo(
+                        self.regs(),
+                        I2cAddress::ten_bit_header(addr) | OperationType::Write as u8,
+                    );
+                    write_fifo(self.regs(), addr as u8);
+                }
             }
         }
         Ok(())
@@ -1754,6 +2532,22 @@ impl Driver<'_> {
         }
 
         if start {
+            addr.validate()?;
+
+            if let I2cAddress::TenBit(_) = addr {
+                // The full address is written first, then the read is started
+                // by repeating the header with the read bit set.
+                add_cmd(
+                    cmd_iterator,
+                    Command::Write {
+                        ack_exp: Ack::Ack,
+                        ack_check_en: true,
+                        length: 2,
+                    },
+                )?;
+                add_cmd(cmd_iterator, Command::Start)?;
+            }
+
             // WRITE command
             add_cmd(
                 cmd_iterator,
@@ -1796,6 +2590,12 @@ impl Driver<'_> {
                 I2cAddress::SevenBit(addr) => {
                     write_fifo(self.regs(), (addr << 1) | OperationType::Read as u8);
                 }
+                I2cAddress::TenBit(addr) => {
+                    let header = I2cAddress::ten_bit_header(addr);
+                    write_fifo(self.regs(), header | OperationType::Write as u8);
+                    write_fifo(self.regs(), addr as u8);
+                    write_fifo(self.regs(), header | OperationType::Read as u8);
+                }
             }
         }
         Ok(())
@@ -1808,8 +2608,10 @@ impl Driver<'_> {
         // FIXME: Handle case where less data has been provided by the slave than
         // requested? Or is this prevented from a protocol perspective?
         for byte in buffer.iter_mut() {
+            let deadline = self.state.deadline();
             loop {
                 self.check_errors()?;
+                self.check_deadline(deadline)?;
 
                 let reg = self.regs().fifo_st().read();
                 if reg.rxfifo_raddr().bits() != reg.rxfifo_waddr().bits() {
@@ -1873,27 +2675,23 @@ impl Driver<'_> {
     #[cfg(not(any(esp32, esp32s2)))]
     async fn write_remaining_tx_fifo(&self, start_index: usize, bytes: &[u8]) -> Result<(), Error> {
         let mut index = start_index;
-        loop {
+        while index < bytes.len() {
             self.check_errors()?;
 
-            I2cFuture::new(Event::TxFifoWatermark, self.info, self.state).await?;
-
+            // The watermark stays raised while the FIFO is below the threshold, so a
+            // stale flag from filling the FIFO is cleared before waiting.
             self.regs()
                 .int_clr()
                 .write(|w| w.txfifo_wm().clear_bit_by_one());
 
             I2cFuture::new(Event::TxFifoWatermark, self.info, self.state).await?;
 
-            if index >= bytes.len() {
-                break Ok(());
-            }
-
-            write_fifo(self.regs(), bytes[index]);
-            index += 1;
+            index += self.fill_tx_fifo(&bytes[index..])?;
         }
+
+        Ok(())
     }
 
-    #[cfg(not(esp32))]
     async fn wait_for_completion(&self, end_only: bool) -> Result<(), Error> {
         self.check_errors()?;
 
@@ -1916,40 +2714,9 @@ impl Driver<'_> {
         Ok(())
     }
 
-    #[cfg(esp32)]
-    async fn wait_for_completion(&self, end_only: bool) -> Result<(), Error> {
-        // for ESP32 we need a timeout here but wasting a timer seems unnecessary
-        // given the short time we spend here
-
-        let mut tout = MAX_ITERATIONS / 10; // adjust the timeout because we are yielding in the loop
-        loop {
-            let interrupts = self.regs().int_raw().read();
-
-            self.check_errors()?;
-
-            // Handle completion cases
-            // A full transmission was completed (either a STOP condition or END was
-            // processed)
-            if (!end_only && interrupts.trans_complete().bit_is_set())
-                || interrupts.end_detect().bit_is_set()
-            {
-                break;
-            }
-
-            tout -= 1;
-            if tout == 0 {
-                return Err(Error::Timeout);
-            }
-
-            embassy_futures::yield_now().await;
-        }
-        self.check_all_commands_done()?;
-        Ok(())
-    }
-
     /// Waits for the completion of an I2C transaction.
     fn wait_for_completion_blocking(&self, end_only: bool) -> Result<(), Error> {
-        let mut tout = MAX_ITERATIONS;
+        let deadline = self.state.deadline();
         loop {
             let interrupts = self.regs().int_raw().read();
 
@@ -1964,10 +2731,7 @@ impl Driver<'_> {
                 break;
             }
 
-            tout -= 1;
-            if tout == 0 {
-                return Err(Error::Timeout);
-            }
+            self.check_deadline(deadline)?;
         }
         self.check_all_commands_done()?;
         Ok(())
@@ -2087,16 +2851,20 @@ impl Driver<'_> {
         loop {
             self.check_errors()?;
 
+            let deadline = self.state.deadline();
             while !self.regs().int_raw().read().txfifo_wm().bit_is_set() {
                 self.check_errors()?;
+                self.check_deadline(deadline)?;
             }
 
             self.regs()
                 .int_clr()
                 .write(|w| w.txfifo_wm().clear_bit_by_one());
 
+            let deadline = self.state.deadline();
             while !self.regs().int_raw().read().txfifo_wm().bit_is_set() {
                 self.check_errors()?;
+                self.check_deadline(deadline)?;
             }
 
             if index >= bytes.len() {
@@ -2114,10 +2882,7 @@ impl Driver<'_> {
         // on ESP32/ESP32-S2 we currently don't support I2C transactions larger than the
         // FIFO apparently it would be possible by using non-fifo mode
         // see  https://github.com/espressif/arduino-esp32/blob/7e9afe8c5ed7b5bf29624a5cd6e07d431c027b97/cores/esp32/esp32-hal-i2c.c#L615
-
-        if bytes.len() > 31 {
-            return Err(Error::FifoExceeded);
-        }
+        // `setup_write` has made sure that the address and the data fit.
 
         for b in bytes {
             write_fifo(self.regs(), *b);
@@ -2417,11 +3182,8 @@ impl Driver<'_> {
         start: bool,
         stop: bool,
     ) -> Result<(), Error> {
-        if buffer.is_empty() {
-            return self.write_operation_blocking(address, &[], start, stop);
-        }
-        let chunk_count = buffer.len().div_ceil(I2C_CHUNK_SIZE);
-        for (idx, chunk) in buffer.chunks(I2C_CHUNK_SIZE).enumerate() {
+        let (chunk_count, chunks) = write_chunks(address, buffer, start);
+        for (idx, chunk) in chunks.enumerate() {
             self.write_operation_blocking(
                 address,
                 chunk,
@@ -2463,11 +3225,8 @@ impl Driver<'_> {
         start: bool,
         stop: bool,
     ) -> Result<(), Error> {
-        if buffer.is_empty() {
-            return self.write_operation(address, &[], start, stop).await;
-        }
-        let chunk_count = buffer.len().div_ceil(I2C_CHUNK_SIZE);
-        for (idx, chunk) in buffer.chunks(I2C_CHUNK_SIZE).enumerate() {
+        let (chunk_count, chunks) = write_chunks(address, buffer, start);
+        for (idx, chunk) in chunks.enumerate() {
             self.write_operation(
                 address,
                 chunk,
@@ -2481,6 +3240,63 @@ impl Driver<'_> {
     }
 }
 
+/// Splits a write into the chunks that are sent by one command list each, and
+/// returns their number. The first chunk leaves room for the address if
+/// `start` is set. An empty write results in a single empty chunk.
+fn write_chunks(
+    address: I2cAddress,
+    buffer: &[u8],
+    start: bool,
+) -> (usize, impl Iterator<Item = &[u8]>) {
+    let first_len = if start {
+        I2C_MAX_WRITE_LEN - address.write_len()
+    } else {
+        I2C_MAX_WRITE_LEN
+    };
+    let (first, rest) = buffer.split_at(first_len.min(buffer.len()));
+    let chunk_count = 1 + rest.len().div_ceil(I2C_MAX_WRITE_LEN);
+
+    (
+        chunk_count,
+        core::iter::once(first).chain(rest.chunks(I2C_MAX_WRITE_LEN)),
+    )
+}
+
+/// Returns the number of `clock_hz` cycles in half an SCL period at
+/// `bus_freq`, limited to `range`.
+///
+/// If the resulting frequency is more than 5% off, the closest frequency that
+/// `range` allows is returned in the error instead.
+fn half_cycles(
+    clock_hz: u32,
+    bus_freq: u32,
+    range: core::ops::RangeInclusive<u32>,
+) -> Result<u32, ConfigError> {
+    if bus_freq == 0 {
+        return Err(ConfigError::FrequencyInvalid);
+    }
+
+    let freq = |half_cycles: u32| clock_hz / (2 * half_cycles);
+    let deviation = |half_cycles: u32| freq(half_cycles).abs_diff(bus_freq);
+
+    let half_cycles = (clock_hz / bus_freq / 2).clamp(*range.start(), *range.end());
+    if deviation(half_cycles) as u64 * 20 <= bus_freq as u64 {
+        return Ok(half_cycles);
+    }
+
+    // The division rounds down, so the next longer period may be closer.
+    let closest =
+        if half_cycles < *range.end() && deviation(half_cycles + 1) < deviation(half_cycles) {
+            half_cycles + 1
+        } else {
+            half_cycles
+        };
+
+    Err(ConfigError::FrequencyUnreachable(HertzU32::from_raw(freq(
+        closest,
+    ))))
+}
+
 fn check_timeout(v: u32, max: u32) -> Result<u32, ConfigError> {
     if v <= max {
         Ok(v)
@@ -2489,12 +3305,37 @@ fn check_timeout(v: u32, max: u32) -> Result<u32, ConfigError> {
     }
 }
 
+/// Converts a duration into cycles of a `clock_hz` clock, saturating at
+/// `u32::MAX`.
+fn duration_to_cycles(duration: Duration, clock_hz: u32) -> u32 {
+    let cycles = duration.to_micros().saturating_mul(clock_hz as u64) / 1_000_000;
+    cycles.try_into().unwrap_or(u32::MAX)
+}
+
+/// Converts cycles of a `clock_hz` clock into a duration, rounding up.
+fn cycles_to_duration(cycles: u64, clock_hz: u32) -> Duration {
+    Duration::micros((cycles * 1_000_000).div_ceil(clock_hz as u64))
+}
+
 /// Peripheral state for an I2C instance.
 #[doc(hidden)]
 #[non_exhaustive]
 pub struct State {
     /// Waker for the asynchronous operations.
     pub waker: AtomicWaker,
+
+    /// How long, in microseconds, a phase of a transfer may wait for the
+    /// controller.
+    pub software_timeout: AtomicU32,
+}
+
+impl State {
+    /// Returns the point in time after which a phase of a transfer that
+    /// starts now is considered stuck.
+    fn deadline(&self) -> Instant {
+        let micros = self.software_timeout.load(Ordering::Relaxed);
+        crate::time::now() + Duration::micros(micros as u64)
+    }
 }
 
 /// I2C Peripheral Instance
@@ -2550,19 +3391,19 @@ where
 }
 
 #[cfg(not(esp32s2))]
-fn read_fifo(register_block: &RegisterBlock) -> u8 {
+pub(super) fn read_fifo(register_block: &RegisterBlock) -> u8 {
     register_block.data().read().fifo_rdata().bits()
 }
 
 #[cfg(not(esp32))]
-fn write_fifo(register_block: &RegisterBlock, data: u8) {
+pub(super) fn write_fifo(register_block: &RegisterBlock, data: u8) {
     register_block
         .data()
         .write(|w| unsafe { w.fifo_rdata().bits(data) });
 }
 
 #[cfg(esp32s2)]
-fn read_fifo(register_block: &RegisterBlock) -> u8 {
+pub(super) fn read_fifo(register_block: &RegisterBlock) -> u8 {
     let base_addr = register_block.scl_low_period().as_ptr();
     let fifo_ptr = (if base_addr as u32 == 0x3f413000 {
         0x6001301c
@@ -2573,7 +3414,7 @@ fn read_fifo(register_block: &RegisterBlock) -> u8 {
 }
 
 #[cfg(esp32)]
-fn write_fifo(register_block: &RegisterBlock, data: u8) {
+pub(super) fn write_fifo(register_block: &RegisterBlock, data: u8) {
     let base_addr = register_block.scl_low_period().as_ptr();
     let fifo_ptr = (if base_addr as u32 == 0x3FF53000 {
         0x6001301c
@@ -2611,14 +3452,23 @@ macro_rules! instance {
                     async_handler(&PERIPHERAL, &STATE);
                 }
 
+                #[cfg(i2c_support_slave)]
+                #[crate::handler]
+                pub(super) fn slave_irq_handler() {
+                    crate::i2c::slave::async_handler(&PERIPHERAL, &STATE);
+                }
+
                 static STATE: State = State {
                     waker: AtomicWaker::new(),
+                    software_timeout: AtomicU32::new(0),
                 };
 
                 static PERIPHERAL: Info = Info {
                     register_block: crate::peripherals::$inst::ptr(),
                     peripheral: crate::system::Peripheral::$peri,
                     async_handler: irq_handler,
+                    #[cfg(i2c_support_slave)]
+                    slave_async_handler: slave_irq_handler,
                     interrupt: Interrupt::$interrupt,
                     scl_output: OutputSignal::$scl,
                     scl_input: InputSignal::$scl,
@@ -0,0 +1,270 @@
+//! # System Management Bus (SMBus)
+//!
+//! ## Overview
+//!
+//! [`Smbus`] wraps an I2C master and implements the SMBus word and block
+//! protocols on top of it. Optionally, a Packet Error Code (PEC) is appended
+//! to every write and checked at the end of every read.
+//!
+//! ## Configuration
+//!
+//! The bus timing is taken from the [`Config`](super::Config) of the wrapped
+//! driver. SMBus devices expect a clock of at most 100 kHz, and reset their
+//! interface if the clock is held low for 25 ms or more. The
+//! [`BusTimeout`](super::BusTimeout) of the driver is used as is.
+//!
+//! ## Usage
+//!
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::i2c::master::{Config, I2c, smbus::Smbus};
+//! # use esp_hal::time::RateExtU32;
+//! let i2c = I2c::new(
+//!     peripherals.I2C0,
+//!     Config::default().with_frequency(100.kHz()),
+//! )?
+//! .with_sda(peripherals.GPIO4)
+//! .with_scl(peripherals.GPIO5);
+//!
+//! let mut smbus = Smbus::new(i2c).with_pec(true);
+//!
+//! const GAUGE_ADDR: u8 = 0x0B;
+//! let voltage = smbus.read_word(GAUGE_ADDR, 0x09)?;
+//!
+//! let mut name = [0u8; 32];
+//! let len = smbus.block_read(GAUGE_ADDR, 0x21, &mut name)?;
+//! let name = &name[..len];
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{I2c, I2cAddress};
+use crate::{rom::crc::crc8_be, DriverMode};
+
+/// The longest block the block protocols can transfer.
+pub const MAX_BLOCK_LEN: usize = 32;
+
+/// SMBus-specific errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The underlying I2C transfer failed.
+    I2c(super::Error),
+    /// The Packet Error Code sent by the device doesn't match the received
+    /// data.
+    PecMismatch,
+    /// The block passed to [`Smbus::block_write`] is longer than
+    /// [`MAX_BLOCK_LEN`].
+    BlockTooLong,
+    /// The device announced a block of the contained length, which is longer
+    /// than [`MAX_BLOCK_LEN`] or the buffer passed to [`Smbus::block_read`].
+    BlockLengthInvalid(u8),
+}
+
+impl From<super::Error> for Error {
+    fn from(error: super::Error) -> Self {
+        Error::I2c(error)
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::I2c(error) => write!(f, "{}", error),
+            Error::PecMismatch => write!(f, "The Packet Error Code doesn't match the data"),
+            Error::BlockTooLong => write!(f, "The block is longer than {} bytes", MAX_BLOCK_LEN),
+            Error::BlockLengthInvalid(len) => {
+                write!(f, "The device announced a block of invalid length {}", len)
+            }
+        }
+    }
+}
+
+/// SMBus protocols on top of an I2C master.
+pub struct Smbus<'d, Dm: DriverMode> {
+    i2c: I2c<'d, Dm>,
+    pec: bool,
+}
+
+impl<'d, Dm: DriverMode> Smbus<'d, Dm> {
+    /// Wraps an I2C master. Packet error checking is disabled.
+    pub fn new(i2c: I2c<'d, Dm>) -> Self {
+        Self { i2c, pec: false }
+    }
+
+    /// Enables or disables packet error checking.
+    ///
+    /// When enabled, a PEC byte is appended to every write, and the device is
+    /// expected to send one after the data of every read.
+    pub fn with_pec(mut self, enabled: bool) -> Self {
+        self.set_pec(enabled);
+        self
+    }
+
+    /// Enables or disables packet error checking.
+    ///
+    /// See [`Self::with_pec`].
+    pub fn set_pec(&mut self, enabled: bool) {
+        self.pec = enabled;
+    }
+
+    /// Returns the wrapped I2C master.
+    pub fn into_inner(self) -> I2c<'d, Dm> {
+        self.i2c
+    }
+
+    /// Reads the 16-bit register `command` of the device at `address`.
+    ///
+    /// The word is transferred least significant byte first.
+    pub fn read_word(&mut self, address: u8, command: u8) -> Result<u16, Error> {
+        let mut data = [0u8; 3];
+        let len = 2 + self.pec as usize;
+        self.i2c.write_read(address, &[command], &mut data[..len])?;
+
+        if self.pec {
+            let pec = pec(&[&[write_header(address), command, read_header(address)], &data[..2]]);
+            if pec != data[2] {
+                return Err(Error::PecMismatch);
+            }
+        }
+
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    /// Writes `value` to the 16-bit register `command` of the device at
+    /// `address`.
+    ///
+    /// The word is transferred least significant byte first.
+    pub fn write_word(&mut self, address: u8, command: u8, value: u16) -> Result<(), Error> {
+        let [low, high] = value.to_le_bytes();
+        let mut data = [command, low, high, 0];
+        self.write_with_pec(address, &mut data, 3)
+    }
+
+    /// Reads a block from `command` of the device at `address` into `buffer`,
+    /// and returns its length.
+    ///
+    /// The device sends the length of the block before the data. A block of
+    /// length zero is not an error.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::BlockLengthInvalid`] is returned if the announced length is
+    /// longer than [`MAX_BLOCK_LEN`] or `buffer`. The transaction is still
+    /// terminated properly, and `buffer` is left alone.
+    pub fn block_read(
+        &mut self,
+        address: u8,
+        command: u8,
+        buffer: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut count = [0u8; 1];
+        // Room for the longest block and its PEC.
+        let mut data = [0u8; MAX_BLOCK_LEN + 1];
+
+        let len = self
+            .block_read_impl(address, command, buffer.len(), &mut count, &mut data)
+            .inspect_err(|error| {
+                if matches!(error, Error::I2c(_)) {
+                    self.i2c.internal_recover()
+                }
+            })?;
+
+        if self.pec {
+            let pec = pec(&[
+                &[write_header(address), command, read_header(address)],
+                &count,
+                &data[..len],
+            ]);
+            if pec != data[len] {
+                return Err(Error::PecMismatch);
+            }
+        }
+
+        buffer[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    // Returns the length of the block in `data`. The PEC, if enabled, follows.
+    fn block_read_impl(
+        &mut self,
+        address: u8,
+        command: u8,
+        buffer_len: usize,
+        count: &mut [u8; 1],
+        data: &mut [u8; MAX_BLOCK_LEN + 1],
+    ) -> Result<usize, Error> {
+        let address = I2cAddress::SevenBit(address);
+        let driver = self.i2c.driver();
+
+        driver.write_blocking(address, &[command], true, false)?;
+        // Acknowledge the length, the device must not be cut off yet.
+        driver.read_blocking(address, count, true, false, true)?;
+
+        let len = count[0] as usize;
+        if len > MAX_BLOCK_LEN || len > buffer_len {
+            // Read, and reject, one more byte so that the device releases the
+            // bus before the STOP.
+            driver.read_blocking(address, &mut data[..1], false, true, false)?;
+            return Err(Error::BlockLengthInvalid(count[0]));
+        }
+
+        // A transaction can't end right after an acknowledged byte, so a
+        // byte is read, and dropped, after an empty block without PEC.
+        let tail = (len + self.pec as usize).max(1);
+        driver.read_blocking(address, &mut data[..tail], false, true, false)?;
+
+        Ok(len)
+    }
+
+    /// Writes `data` as a block to `command` of the device at `address`.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::BlockTooLong`] is returned if `data` is longer than
+    /// [`MAX_BLOCK_LEN`].
+    pub fn block_write(&mut self, address: u8, command: u8, data: &[u8]) -> Result<(), Error> {
+        if data.len() > MAX_BLOCK_LEN {
+            return Err(Error::BlockTooLong);
+        }
+
+        let mut buffer = [0u8; MAX_BLOCK_LEN + 3];
+        buffer[0] = command;
+        buffer[1] = data.len() as u8;
+        buffer[2..][..data.len()].copy_from_slice(data);
+        self.write_with_pec(address, &mut buffer, data.len() + 2)
+    }
+
+    // Writes the first `len` bytes of `buffer`, followed by the PEC if
+    // enabled. `buffer` must have room for the PEC.
+    fn write_with_pec(&mut self, address: u8, buffer: &mut [u8], len: usize) -> Result<(), Error> {
+        let len = if self.pec {
+            buffer[len] = pec(&[&[write_header(address)], &buffer[..len]]);
+            len + 1
+        } else {
+            len
+        };
+
+        self.i2c.write(address, &buffer[..len])?;
+        Ok(())
+    }
+}
+
+fn write_header(address: u8) -> u8 {
+    address << 1
+}
+
+fn read_header(address: u8) -> u8 {
+    (address << 1) | 1
+}
+
+/// CRC-8 with polynomial 0x07 and no reflection, over the concatenation of
+/// `parts`.
+fn pec(parts: &[&[u8]]) -> u8 {
+    // The ROM function complements its input and output.
+    let crc = parts.iter().fold(!0, |crc, part| crc8_be(crc, part));
+    !crc
+}
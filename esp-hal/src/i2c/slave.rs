@@ -0,0 +1,666 @@
+//! # Inter-Integrated Circuit (I2C) - Slave mode
+//!
+//! ## Overview
+//!
+//! In this mode, the I2C acts as a device on a bus that is driven by another
+//! master. It acknowledges its own 7-bit address, and optionally the general
+//! call address, and exchanges data with the master through the 32-byte RX
+//! and TX FIFOs.
+//!
+//! ## Configuration
+//!
+//! The address the device responds to is set through [`Config`]. With clock
+//! stretching enabled (the default), the driver holds SCL low when the master
+//! starts a read until the firmware has provided the response, and whenever
+//! the RX FIFO is full or the TX FIFO runs empty. Without clock stretching, the
+//! master reads the data queued with [`I2c::preload_response`], and a master
+//! that writes faster than the firmware drains the RX FIFO causes
+//! [`Error::RxFifoOverflow`].
+//!
+//! To stretch the clock, the slave pulls SCL low, so its SCL pin must be
+//! connected to the bus line driven by the master, with a pull-up, just like
+//! SDA. The hardware-in-loop tests of this driver connect a master and a slave
+//! of the same chip through a single SCL pin that only the master drives, so
+//! clock stretching, and the responses that rely on it, aren't covered by
+//! them.
+//!
+//! ## Usage
+//!
+//! [`I2c::wait_for_request`] collects the data written by the master and
+//! returns once the master ends a write, or addresses the device for a read.
+//! A read is answered with [`I2c::respond`].
+//!
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::i2c::slave::{Config, I2c, Request};
+//! let mut i2c = I2c::new(peripherals.I2C0, Config::default().with_address(0x42))?
+//!     .with_sda(peripherals.GPIO1)
+//!     .with_scl(peripherals.GPIO2);
+//!
+//! let registers = [0x12, 0x34, 0x56, 0x78];
+//! let mut register = 0;
+//!
+//! loop {
+//!     let mut buffer = [0u8; 8];
+//!     let (len, read) = match i2c.wait_for_request(&mut buffer)? {
+//!         Request::Write(len) => (len, false),
+//!         Request::Read(len) => (len, true),
+//!         _ => continue,
+//!     };
+//!
+//!     // The first byte written selects the register.
+//!     if len > 0 {
+//!         register = buffer[0] as usize % registers.len();
+//!     }
+//!     if read {
+//!         i2c.respond(&registers[register..]);
+//!     }
+//! }
+//! # }
+//! ```
+
+use core::{marker::PhantomData, task::Poll};
+
+use super::master::{read_fifo, set_filter, write_fifo, AnyI2c, Info, Instance, State};
+use crate::{
+    gpio::{
+        interconnect::{OutputConnection, PeripheralOutput},
+        InputSignal,
+        OutputSignal,
+        PinGuard,
+        Pull,
+    },
+    pac::i2c0::RegisterBlock,
+    peripheral::{Peripheral, PeripheralRef},
+    system::PeripheralGuard,
+    Async,
+    Blocking,
+    DriverMode,
+};
+
+const FIFO_SIZE: usize = 32;
+
+// The RX FIFO is drained once it is half full, the TX FIFO is refilled once
+// fewer than this many bytes are left to send.
+const RX_FIFO_THRESHOLD: u8 = 16;
+const TX_FIFO_THRESHOLD: u8 = 8;
+
+// Module clock cycles to wait after releasing SCL before the next SDA change,
+// the value used by ESP-IDF.
+const STRETCH_PROTECT_NUM: u16 = 0x3FF;
+
+// Values of `SR.STRETCH_CAUSE`
+const STRETCH_CAUSE_MASTER_READ: u8 = 0;
+const STRETCH_CAUSE_TX_FIFO_EMPTY: u8 = 1;
+
+/// I2C slave configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, procmacros::BuilderLite)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Config {
+    /// The 7-bit address the device responds to.
+    ///
+    /// Addresses reserved by the I2C specification (`0x00..=0x07` and
+    /// `0x78..=0x7F`) are rejected. Defaults to `0x08`.
+    address: u8,
+
+    /// Whether the device also responds to the general call address (`0x00`).
+    ///
+    /// Data written to the general call address is reported as
+    /// [`Request::GeneralCall`].
+    general_call: bool,
+
+    /// Whether the device holds SCL low while the firmware prepares a
+    /// response, drains the RX FIFO, or refills the TX FIFO.
+    clock_stretch: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            address: 0x08,
+            general_call: false,
+            clock_stretch: true,
+        }
+    }
+}
+
+/// I2C slave configuration errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The address is not a valid, unreserved 7-bit address.
+    AddressInvalid,
+}
+
+impl core::error::Error for ConfigError {}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::AddressInvalid => write!(
+                f,
+                "The address is not a valid, unreserved 7-bit address"
+            ),
+        }
+    }
+}
+
+/// I2C slave errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The master wrote more data than the RX FIFO could hold before it was
+    /// drained, and data was lost.
+    ///
+    /// Only possible with clock stretching disabled.
+    RxFifoOverflow,
+}
+
+impl core::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::RxFifoOverflow => write!(f, "The RX FIFO overflowed and data was lost"),
+        }
+    }
+}
+
+/// A request of the master, returned by [`I2c::wait_for_request`].
+///
+/// The number of bytes the master wrote before the request is included in
+/// every variant. Bytes that didn't fit into the buffer passed to
+/// [`I2c::wait_for_request`] are discarded and not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Request {
+    /// The master wrote data and ended the transaction with a STOP condition.
+    Write(usize),
+
+    /// The master addressed the device for a read, after writing data in the
+    /// same transaction if the value is non-zero.
+    ///
+    /// SCL is held low until the response is provided with [`I2c::respond`].
+    /// Only reported with clock stretching enabled, otherwise the master
+    /// reads the data queued with [`I2c::preload_response`] and the data it
+    /// wrote before is reported as [`Request::Write`].
+    Read(usize),
+
+    /// The master wrote data to the general call address.
+    GeneralCall(usize),
+}
+
+/// I2C slave driver
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct I2c<'d, Dm: DriverMode> {
+    i2c: PeripheralRef<'d, AnyI2c>,
+    phantom: PhantomData<Dm>,
+    config: Config,
+    guard: PeripheralGuard,
+    sda_pin: PinGuard,
+    scl_pin: PinGuard,
+}
+
+impl<'d> I2c<'d, Blocking> {
+    /// Create a new I2C slave instance.
+    ///
+    /// # Errors
+    ///
+    /// [`ConfigError::AddressInvalid`] is returned if the configured address is
+    /// not a valid, unreserved 7-bit address.
+    pub fn new(
+        i2c: impl Peripheral<P = impl Instance> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        crate::into_mapped_ref!(i2c);
+
+        let guard = PeripheralGuard::new(i2c.info().peripheral);
+
+        let sda_pin = PinGuard::new_unconnected(i2c.info().sda_output);
+        let scl_pin = PinGuard::new_unconnected(i2c.info().scl_output);
+
+        let i2c = I2c {
+            i2c,
+            phantom: PhantomData,
+            config,
+            guard,
+            sda_pin,
+            scl_pin,
+        };
+
+        i2c.setup(&i2c.config)?;
+
+        Ok(i2c)
+    }
+
+    /// Configures the I2C peripheral to operate in asynchronous mode.
+    pub fn into_async(self) -> I2c<'d, Async> {
+        self.info()
+            .set_interrupt_handler(self.info().slave_async_handler);
+
+        I2c {
+            i2c: self.i2c,
+            phantom: PhantomData,
+            config: self.config,
+            guard: self.guard,
+            sda_pin: self.sda_pin,
+            scl_pin: self.scl_pin,
+        }
+    }
+
+    /// Waits for the next request of the master.
+    ///
+    /// The data written by the master is stored in `buffer`. This function
+    /// returns once the master ends a write, or addresses the device for a
+    /// read.
+    pub fn wait_for_request(&mut self, buffer: &mut [u8]) -> Result<Request, Error> {
+        let mut received = RequestState::default();
+        loop {
+            if let Some(result) = self.poll_request(buffer, &mut received) {
+                return result;
+            }
+        }
+    }
+
+    /// Sends `data` to the master in response to a [`Request::Read`].
+    ///
+    /// Anything left in the TX FIFO from a previous response is discarded.
+    /// This function refills the TX FIFO as the master reads, and returns
+    /// once all of `data` has been queued or the master ended the transaction.
+    /// If the master reads more than `data`, it receives undefined data.
+    pub fn respond(&mut self, data: &[u8]) {
+        let mut queued = self.start_response(data);
+        while !self.poll_response(data, &mut queued) {}
+    }
+}
+
+impl<'d> I2c<'d, Async> {
+    /// Configures the I2C peripheral to operate in blocking mode.
+    pub fn into_blocking(self) -> I2c<'d, Blocking> {
+        self.info().disable_interrupts();
+
+        I2c {
+            i2c: self.i2c,
+            phantom: PhantomData,
+            config: self.config,
+            guard: self.guard,
+            sda_pin: self.sda_pin,
+            scl_pin: self.scl_pin,
+        }
+    }
+
+    /// Waits for the next request of the master.
+    ///
+    /// See [`I2c::wait_for_request`]. If the returned future is dropped, the
+    /// data that was already received is lost, and a master that addressed
+    /// the device for a read is held until the next call to
+    /// [`I2c::respond_async`].
+    pub async fn wait_for_request_async(&mut self, buffer: &mut [u8]) -> Result<Request, Error> {
+        let mut received = RequestState::default();
+        let result = core::future::poll_fn(|cx| {
+            self.state().waker.register(cx.waker());
+
+            if let Some(result) = self.poll_request(buffer, &mut received) {
+                return Poll::Ready(result);
+            }
+
+            self.regs().int_ena().modify(|_, w| {
+                w.rxfifo_wm().set_bit();
+                w.rxfifo_ovf().set_bit();
+                w.general_call().set_bit();
+                w.slave_stretch().set_bit();
+                w.trans_complete().set_bit()
+            });
+
+            Poll::Pending
+        })
+        .await;
+
+        self.stop_listening();
+
+        result
+    }
+
+    /// Sends `data` to the master in response to a [`Request::Read`].
+    ///
+    /// See [`I2c::respond`].
+    pub async fn respond_async(&mut self, data: &[u8]) {
+        let mut queued = self.start_response(data);
+        core::future::poll_fn(|cx| {
+            self.state().waker.register(cx.waker());
+
+            if self.poll_response(data, &mut queued) {
+                return Poll::Ready(());
+            }
+
+            self.regs().int_ena().modify(|_, w| {
+                w.txfifo_wm().set_bit();
+                w.slave_stretch().set_bit();
+                w.trans_complete().set_bit()
+            });
+
+            Poll::Pending
+        })
+        .await;
+
+        self.stop_listening();
+    }
+}
+
+/// Progress of a [`I2c::wait_for_request`] call.
+#[derive(Default)]
+struct RequestState {
+    received: usize,
+    general_call: bool,
+}
+
+impl<'d, Dm: DriverMode> I2c<'d, Dm> {
+    fn info(&self) -> &Info {
+        self.i2c.info()
+    }
+
+    fn state(&self) -> &State {
+        self.i2c.state()
+    }
+
+    fn regs(&self) -> &RegisterBlock {
+        self.info().regs()
+    }
+
+    /// Connect a pin to the I2C SDA signal.
+    ///
+    /// This will replace previous pin assignments for this signal.
+    pub fn with_sda(mut self, sda: impl Peripheral<P = impl PeripheralOutput> + 'd) -> Self {
+        let input = self.info().sda_input;
+        let output = self.info().sda_output;
+        Self::connect_pin(sda, input, output, &mut self.sda_pin);
+
+        self
+    }
+
+    /// Connect a pin to the I2C SCL signal.
+    ///
+    /// This will replace previous pin assignments for this signal. The pin
+    /// needs to be an output for the device to stretch the clock.
+    pub fn with_scl(mut self, scl: impl Peripheral<P = impl PeripheralOutput> + 'd) -> Self {
+        let input = self.info().scl_input;
+        let output = self.info().scl_output;
+        Self::connect_pin(scl, input, output, &mut self.scl_pin);
+
+        self
+    }
+
+    fn connect_pin(
+        pin: impl Peripheral<P = impl PeripheralOutput> + 'd,
+        input: InputSignal,
+        output: OutputSignal,
+        guard: &mut PinGuard,
+    ) {
+        crate::into_mapped_ref!(pin);
+        // avoid the pin going low during configuration
+        pin.set_output_high(true);
+
+        pin.set_to_open_drain_output();
+        pin.enable_input(true);
+        pin.pull_direction(Pull::Up);
+
+        input.connect_to(pin.reborrow());
+
+        *guard = OutputConnection::connect_with_guard(pin, output);
+    }
+
+    /// Applies a new configuration.
+    ///
+    /// This also discards any data in the FIFOs.
+    ///
+    /// # Errors
+    ///
+    /// [`ConfigError::AddressInvalid`] is returned if the configured address is
+    /// not a valid, unreserved 7-bit address.
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        self.setup(config)?;
+        self.config = *config;
+        Ok(())
+    }
+
+    /// Queues data for the next read of the master, and returns the number of
+    /// bytes queued.
+    ///
+    /// Anything left in the TX FIFO is discarded first. At most 32 bytes fit
+    /// into the TX FIFO. With clock stretching enabled, the master is still
+    /// held until [`I2c::respond`] is called, which replaces this data.
+    pub fn preload_response(&mut self, data: &[u8]) -> usize {
+        self.reset_fifo(true, false);
+        self.fill_tx_fifo(data)
+    }
+
+    fn setup(&self, config: &Config) -> Result<(), ConfigError> {
+        if !(0x08..=0x77).contains(&config.address) {
+            return Err(ConfigError::AddressInvalid);
+        }
+
+        let regs = self.regs();
+
+        regs.ctr().write(|w| {
+            // Set I2C controller to slave mode
+            w.ms_mode().clear_bit();
+            // Use open drain output for SDA and SCL
+            w.sda_force_out().set_bit();
+            w.scl_force_out().set_bit();
+            // Use Most Significant Bit first for sending and receiving data
+            w.tx_lsb_first().clear_bit();
+            w.rx_lsb_first().clear_bit();
+            // Send the TX FIFO contents once the master starts reading
+            w.slv_tx_auto_start_en().set_bit();
+            w.addr_broadcasting_en().bit(config.general_call);
+            // NACK data that doesn't fit into the RX FIFO
+            w.rx_full_ack_level().set_bit();
+            // Ensure that clock is enabled
+            w.clk_en().set_bit()
+        });
+
+        regs.slave_addr().write(|w| unsafe {
+            w.addr_10bit_en().clear_bit();
+            w.slave_addr().bits(config.address as u16)
+        });
+
+        regs.scl_stretch_conf().write(|w| unsafe {
+            w.stretch_protect_num().bits(STRETCH_PROTECT_NUM);
+            w.slave_scl_stretch_en().bit(config.clock_stretch)
+        });
+
+        // The master drives the bus timing, the module clock only needs to sample
+        // the lines.
+        regs.clk_conf().modify(|_, w| unsafe {
+            w.sclk_sel().clear_bit();
+            w.sclk_div_num().bits(0)
+        });
+        set_filter(regs, Some(7), Some(7));
+        regs.sda_hold().write(|w| unsafe { w.time().bits(10) });
+        regs.sda_sample().write(|w| unsafe { w.time().bits(10) });
+
+        // The slave can't tell a stuck bus from a slow master.
+        regs.to().write(|w| w.time_out_en().clear_bit());
+
+        regs.ctr().modify(|_, w| w.conf_upgate().set_bit());
+
+        regs.ctr().modify(|_, w| w.fsm_rst().set_bit());
+        self.reset_fifo(true, true);
+        regs.int_clr().write(|w| unsafe { w.bits(u32::MAX) });
+
+        Ok(())
+    }
+
+    fn reset_fifo(&self, tx: bool, rx: bool) {
+        self.regs().fifo_conf().modify(|_, w| unsafe {
+            w.tx_fifo_rst().bit(tx);
+            w.rx_fifo_rst().bit(rx);
+            w.nonfifo_en().clear_bit();
+            w.fifo_addr_cfg_en().clear_bit();
+            w.fifo_prt_en().set_bit();
+            w.rxfifo_wm_thrhd().bits(RX_FIFO_THRESHOLD);
+            w.txfifo_wm_thrhd().bits(TX_FIFO_THRESHOLD)
+        });
+
+        self.regs().fifo_conf().modify(|_, w| {
+            w.tx_fifo_rst().clear_bit();
+            w.rx_fifo_rst().clear_bit()
+        });
+
+        self.regs().int_clr().write(|w| {
+            w.rxfifo_wm().clear_bit_by_one();
+            w.rxfifo_ovf().clear_bit_by_one();
+            w.txfifo_wm().clear_bit_by_one()
+        });
+    }
+
+    fn stop_listening(&self) {
+        stop_listening(self.regs());
+    }
+
+    fn release_scl(&self) {
+        self.regs()
+            .scl_stretch_conf()
+            .modify(|_, w| w.slave_scl_stretch_clr().set_bit());
+    }
+
+    /// Moves the contents of the RX FIFO into `buffer`.
+    fn drain_rx_fifo(&self, buffer: &mut [u8], state: &mut RequestState) {
+        let count = self.regs().sr().read().rxfifo_cnt().bits();
+        for _ in 0..count {
+            let byte = read_fifo(self.regs());
+            if let Some(slot) = buffer.get_mut(state.received) {
+                *slot = byte;
+                state.received += 1;
+            }
+        }
+    }
+
+    /// Writes as much of `data` as fits into the TX FIFO, and returns the
+    /// number of bytes written.
+    fn fill_tx_fifo(&self, data: &[u8]) -> usize {
+        let used = self.regs().sr().read().txfifo_cnt().bits() as usize;
+        let count = data.len().min(FIFO_SIZE.saturating_sub(used));
+        for byte in &data[..count] {
+            write_fifo(self.regs(), *byte);
+        }
+        count
+    }
+
+    /// Advances a [`I2c::wait_for_request`] call, returning the result once
+    /// the request is complete.
+    fn poll_request(
+        &self,
+        buffer: &mut [u8],
+        state: &mut RequestState,
+    ) -> Option<Result<Request, Error>> {
+        let regs = self.regs();
+        let interrupts = regs.int_raw().read();
+
+        // Clear the watermark before draining, so that data arriving in between
+        // raises it again.
+        regs.int_clr().write(|w| w.rxfifo_wm().clear_bit_by_one());
+        self.drain_rx_fifo(buffer, state);
+
+        if interrupts.rxfifo_ovf().bit_is_set() {
+            self.reset_fifo(false, true);
+            return Some(Err(Error::RxFifoOverflow));
+        }
+
+        if interrupts.general_call().bit_is_set() {
+            regs.int_clr().write(|w| w.general_call().clear_bit_by_one());
+            state.general_call = true;
+        }
+
+        if interrupts.slave_stretch().bit_is_set() {
+            regs.int_clr().write(|w| w.slave_stretch().clear_bit_by_one());
+            if regs.sr().read().stretch_cause().bits() == STRETCH_CAUSE_MASTER_READ {
+                return Some(Ok(Request::Read(state.received)));
+            }
+
+            // The RX FIFO was full and has just been drained, or the master
+            // reads past the end of a response.
+            self.release_scl();
+        }
+
+        if interrupts.trans_complete().bit_is_set() {
+            regs.int_clr().write(|w| w.trans_complete().clear_bit_by_one());
+            self.drain_rx_fifo(buffer, state);
+
+            if state.general_call {
+                return Some(Ok(Request::GeneralCall(state.received)));
+            }
+
+            // A read served from the preloaded TX FIFO isn't reported.
+            if state.received > 0 || regs.sr().read().slave_rw().bit_is_clear() {
+                return Some(Ok(Request::Write(state.received)));
+            }
+        }
+
+        None
+    }
+
+    /// Discards the previous response, queues the start of `data` and lets
+    /// the master continue. Returns the number of bytes queued.
+    fn start_response(&self, data: &[u8]) -> usize {
+        self.reset_fifo(true, false);
+        let queued = self.fill_tx_fifo(data);
+        self.regs().int_clr().write(|w| {
+            w.slave_stretch().clear_bit_by_one();
+            w.trans_complete().clear_bit_by_one()
+        });
+        self.release_scl();
+        queued
+    }
+
+    /// Advances a [`I2c::respond`] call, returning `true` once it is
+    /// complete.
+    fn poll_response(&self, data: &[u8], queued: &mut usize) -> bool {
+        let regs = self.regs();
+        if *queued >= data.len() {
+            return true;
+        }
+
+        let interrupts = regs.int_raw().read();
+
+        if interrupts.trans_complete().bit_is_set() {
+            regs.int_clr().write(|w| w.trans_complete().clear_bit_by_one());
+            return true;
+        }
+
+        // The watermark stays raised while the FIFO is below the threshold.
+        regs.int_clr().write(|w| w.txfifo_wm().clear_bit_by_one());
+        *queued += self.fill_tx_fifo(&data[*queued..]);
+
+        if interrupts.slave_stretch().bit_is_set()
+            && regs.sr().read().stretch_cause().bits() == STRETCH_CAUSE_TX_FIFO_EMPTY
+        {
+            regs.int_clr().write(|w| w.slave_stretch().clear_bit_by_one());
+            self.release_scl();
+        }
+
+        *queued >= data.len()
+    }
+}
+
+fn stop_listening(regs: &RegisterBlock) {
+    regs.int_ena().modify(|_, w| {
+        w.rxfifo_wm().clear_bit();
+        w.rxfifo_ovf().clear_bit();
+        w.txfifo_wm().clear_bit();
+        w.general_call().clear_bit();
+        w.slave_stretch().clear_bit();
+        w.trans_complete().clear_bit()
+    });
+}
+
+/// Wakes the task waiting for the slave, leaving the events to the task.
+pub(super) fn async_handler(info: &Info, state: &State) {
+    stop_listening(info.regs());
+    state.waker.wake();
+}
@@ -48,7 +48,7 @@
 //!     rx_descriptors,
 //!     tx_descriptors,
 //! );
-#![cfg_attr(not(esp32), doc = "let i2s = i2s.with_mclk(peripherals.GPIO0);")]
+//! let i2s = i2s.with_mclk(peripherals.GPIO0);
 //! let mut i2s_rx = i2s.i2s_rx
 //!     .with_bclk(peripherals.GPIO1)
 //!     .with_ws(peripherals.GPIO2)
@@ -71,6 +71,8 @@
 //! ## Implementation State
 //!
 //! - Only TDM Philips standard is supported.
+//! - Other TDM frame layouts can be set up with `I2s::new_tdm`, except on
+//!   ESP32 and ESP32-S2.
 
 use enumset::{EnumSet, EnumSetType};
 use private::*;
@@ -101,6 +103,7 @@ use crate::{
     interrupt::{InterruptConfigurable, InterruptHandler},
     peripheral::{Peripheral, PeripheralRef},
     system::PeripheralGuard,
+    time::Duration,
     Async,
     Blocking,
     DriverMode,
@@ -130,6 +133,20 @@ pub(crate) const I2S_LL_MCLK_DIVIDER_BIT_WIDTH: usize = 9;
 
 pub(crate) const I2S_LL_MCLK_DIVIDER_MAX: usize = (1 << I2S_LL_MCLK_DIVIDER_BIT_WIDTH) - 1;
 
+// The bit clock divider is stored as is on the ESP32 and ESP32-S2, and minus
+// one on the later chips, in 6 bits.
+#[cfg(any(esp32, esp32s2))]
+pub(crate) const I2S_LL_BCLK_DIVIDER_MAX: u32 = 63;
+
+#[cfg(not(any(esp32, esp32s2)))]
+pub(crate) const I2S_LL_BCLK_DIVIDER_MAX: u32 = 64;
+
+#[cfg(any(esp32c3, esp32c6, esp32s3))]
+const I2S_LL_TDM_FRAME_BITS_MAX: u16 = 128;
+
+#[cfg(esp32h2)]
+const I2S_LL_TDM_FRAME_BITS_MAX: u16 = 512;
+
 /// Data types that the I2S peripheral can work with.
 pub trait AcceptedWord: crate::private::Sealed {}
 impl AcceptedWord for u8 {}
@@ -150,6 +167,9 @@ pub enum Error {
     DmaError(DmaError),
     /// An illegal or invalid argument was passed to an I2S function or method.
     IllegalArgument,
+    /// The transfer didn't finish in time, e.g. because the external master
+    /// stopped clocking.
+    Timeout,
 }
 
 impl From<DmaError> for Error {
@@ -179,6 +199,15 @@ pub enum DataFormat {
     Data32Channel24,
     /// 32-bit data width and 16-bit channel width.
     Data32Channel16,
+    /// 24-bit data width and 32-bit channel width.
+    ///
+    /// The samples take up 3 bytes in memory, and are sent MSB first, padded
+    /// to 32 bits.
+    Data24Channel32,
+    /// 24-bit data width and 24-bit channel width.
+    ///
+    /// The samples take up 3 bytes in memory.
+    Data24Channel24,
     /// 32-bit data width and 8-bit channel width.
     Data32Channel8,
     /// 16-bit data width and 16-bit channel width.
@@ -209,6 +238,8 @@ impl DataFormat {
             DataFormat::Data32Channel24 => 32,
             DataFormat::Data32Channel16 => 32,
             DataFormat::Data32Channel8 => 32,
+            DataFormat::Data24Channel32 => 24,
+            DataFormat::Data24Channel24 => 24,
             DataFormat::Data16Channel16 => 16,
             DataFormat::Data16Channel8 => 16,
             DataFormat::Data8Channel8 => 8,
@@ -222,6 +253,8 @@ impl DataFormat {
             DataFormat::Data32Channel24 => 24,
             DataFormat::Data32Channel16 => 16,
             DataFormat::Data32Channel8 => 8,
+            DataFormat::Data24Channel32 => 32,
+            DataFormat::Data24Channel24 => 24,
             DataFormat::Data16Channel16 => 16,
             DataFormat::Data16Channel8 => 8,
             DataFormat::Data8Channel8 => 8,
@@ -248,6 +281,351 @@ impl DataFormat {
     }
 }
 
+impl DataFormat {
+    /// Returns the number of bytes a sample takes up in memory.
+    ///
+    /// DMA buffers hold the samples of a frame back to back, so e.g. a frame
+    /// of [`DataFormat::Data24Channel32`] takes up 6 bytes.
+    pub fn bytes_per_sample(&self) -> usize {
+        self.data_bits().div_ceil(8) as usize
+    }
+
+    /// Converts `samples` to the memory layout of this data format.
+    ///
+    /// Samples are in the natural range of the data width, e.g. -2^23 to
+    /// 2^23 - 1 for 24-bit data, and the bits above it are ignored. Returns
+    /// the number of bytes written to `buffer`, which holds as many samples as
+    /// fit.
+    pub fn pack_samples(&self, samples: &[i32], buffer: &mut [u8]) -> usize {
+        let sample_bytes = self.bytes_per_sample();
+        let mut written = 0;
+        for (sample, bytes) in samples.iter().zip(buffer.chunks_exact_mut(sample_bytes)) {
+            bytes.copy_from_slice(&sample.to_le_bytes()[..sample_bytes]);
+            written += sample_bytes;
+        }
+        written
+    }
+
+    /// Converts data in the memory layout of this data format to sign-extended
+    /// samples.
+    ///
+    /// Returns the number of samples written to `samples`, ignoring a trailing
+    /// partial sample in `buffer`.
+    pub fn unpack_samples(&self, buffer: &[u8], samples: &mut [i32]) -> usize {
+        let sample_bytes = self.bytes_per_sample();
+        let mut count = 0;
+        for (bytes, sample) in buffer.chunks_exact(sample_bytes).zip(samples.iter_mut()) {
+            *sample = unpack_sample(bytes);
+            count += 1;
+        }
+        count
+    }
+}
+
+fn unpack_sample(bytes: &[u8]) -> i32 {
+    let mut raw = [0; 4];
+    raw[..bytes.len()].copy_from_slice(bytes);
+
+    // Move the sign bit to the top, then shift back to sign-extend.
+    let shift = 32 - 8 * bytes.len() as u32;
+    (i32::from_le_bytes(raw) << shift) >> shift
+}
+
+fn words_as_bytes(words: &mut [i32]) -> &mut [u8] {
+    unsafe {
+        core::slice::from_raw_parts_mut(
+            words.as_mut_ptr().cast::<u8>(),
+            core::mem::size_of_val(words),
+        )
+    }
+}
+
+/// Packs the samples of `words` into the memory layout of `sample_bytes` wide
+/// samples, at the start of the slice.
+fn pack_samples_in_place(words: &mut [i32], sample_bytes: usize) -> &mut [u8] {
+    let count = words.len();
+    let bytes = words_as_bytes(words);
+
+    // Every sample moves towards the start, so going forwards never overwrites
+    // a sample that's yet to be packed.
+    for i in 0..count {
+        let sample = i32::from_le_bytes(bytes[i * 4..][..4].try_into().unwrap());
+        let packed = &sample.to_le_bytes()[..sample_bytes];
+        bytes[i * sample_bytes..][..sample_bytes].copy_from_slice(packed);
+    }
+
+    &mut bytes[..count * sample_bytes]
+}
+
+/// Reverses [`pack_samples_in_place`].
+fn unpack_samples_in_place(words: &mut [i32], sample_bytes: usize) {
+    let count = words.len();
+    let bytes = words_as_bytes(words);
+
+    for i in (0..count).rev() {
+        let sample = unpack_sample(&bytes[i * sample_bytes..][..sample_bytes]);
+        bytes[i * 4..][..4].copy_from_slice(&sample.to_le_bytes());
+    }
+}
+
+/// The frequency of the master clock (MCLK), as a multiple of the sample rate.
+///
+/// Codecs usually expect 256 times the sample rate. The multiple has to be a
+/// multiple of the bits per frame, at least twice as many, so e.g. frames of
+/// two 24-bit channels need one of the multiples of 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MclkMultiple {
+    /// MCLK runs at 128 times the sample rate.
+    _128 = 128,
+    /// MCLK runs at 192 times the sample rate.
+    _192 = 192,
+    /// MCLK runs at 256 times the sample rate.
+    _256 = 256,
+    /// MCLK runs at 384 times the sample rate.
+    _384 = 384,
+    /// MCLK runs at 512 times the sample rate.
+    _512 = 512,
+    /// MCLK runs at 576 times the sample rate.
+    _576 = 576,
+    /// MCLK runs at 768 times the sample rate.
+    _768 = 768,
+    /// MCLK runs at 1024 times the sample rate.
+    _1024 = 1024,
+}
+
+/// TDM frame configuration.
+///
+/// A frame consists of `slots` slots of `slot_bits` bits each. Transfers only
+/// carry the samples of the slots in `active_slot_mask`: every frame is laid out
+/// in memory as the active slots in ascending order, each one taking
+/// `sample_bits / 8` bytes. The remaining slots are skipped by TX and ignored
+/// by RX.
+///
+/// The default configuration matches [`Standard::Philips`] with
+/// [`DataFormat::Data16Channel16`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, procmacros::BuilderLite)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(not(any(esp32, esp32s2)))]
+#[non_exhaustive]
+pub struct TdmConfig {
+    /// The number of slots in a frame, 1 to 16.
+    pub slots: u8,
+
+    /// The width of a slot in bits: 8, 16, 24 or 32.
+    pub slot_bits: u8,
+
+    /// The number of valid bits in a slot: 8, 16, 24 or 32, at most
+    /// `slot_bits`.
+    pub sample_bits: u8,
+
+    /// The slots which carry data, bit `n` standing for slot `n`.
+    pub active_slot_mask: u16,
+
+    /// How many BCLK cycles WS stays high at the start of a frame. `None`
+    /// keeps it high for half of the frame.
+    pub ws_width: Option<u16>,
+
+    /// Places the sample at the start of its slot. Otherwise, samples which
+    /// are shorter than the slot are placed at its end.
+    pub left_align: bool,
+
+    /// Stores the samples in memory with their most significant byte first.
+    pub big_endian: bool,
+}
+
+#[cfg(not(any(esp32, esp32s2)))]
+impl Default for TdmConfig {
+    fn default() -> Self {
+        Self {
+            slots: 2,
+            slot_bits: 16,
+            sample_bits: 16,
+            active_slot_mask: 0b11,
+            ws_width: None,
+            left_align: true,
+            big_endian: false,
+        }
+    }
+}
+
+#[cfg(not(any(esp32, esp32s2)))]
+impl TdmConfig {
+    fn frame_bits(&self) -> u16 {
+        self.slots as u16 * self.slot_bits as u16
+    }
+
+    fn ws_bits(&self) -> u16 {
+        self.ws_width.unwrap_or(self.frame_bits() / 2)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(1..=16).contains(&self.slots) {
+            return Err(ConfigError::UnsupportedSlotCount);
+        }
+        if ![8, 16, 24, 32].contains(&self.slot_bits) {
+            return Err(ConfigError::UnsupportedSlotBits);
+        }
+        if ![8, 16, 24, 32].contains(&self.sample_bits) || self.sample_bits > self.slot_bits {
+            return Err(ConfigError::UnsupportedSampleBits);
+        }
+        if self.frame_bits() > I2S_LL_TDM_FRAME_BITS_MAX {
+            return Err(ConfigError::FrameTooLong);
+        }
+        if self.active_slot_mask == 0 || self.active_slot_mask >> self.slots != 0 {
+            return Err(ConfigError::InvalidSlotMask);
+        }
+        if !(1..=self.frame_bits()).contains(&self.ws_bits()) {
+            return Err(ConfigError::UnsupportedWsWidth);
+        }
+
+        Ok(())
+    }
+}
+
+/// Clock and TDM configuration errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The number of slots is not between 1 and 16.
+    #[cfg(not(any(esp32, esp32s2)))]
+    UnsupportedSlotCount,
+    /// The slot width is not 8, 16, 24 or 32 bits.
+    #[cfg(not(any(esp32, esp32s2)))]
+    UnsupportedSlotBits,
+    /// The sample width is not 8, 16, 24 or 32 bits, or wider than a slot.
+    #[cfg(not(any(esp32, esp32s2)))]
+    UnsupportedSampleBits,
+    /// The frame has more bits than the hardware supports.
+    #[cfg(not(any(esp32, esp32s2)))]
+    FrameTooLong,
+    /// No slot is active, or the mask selects slots past the end of the
+    /// frame.
+    #[cfg(not(any(esp32, esp32s2)))]
+    InvalidSlotMask,
+    /// The WS pulse is empty or longer than a frame.
+    #[cfg(not(any(esp32, esp32s2)))]
+    UnsupportedWsWidth,
+    /// The MCLK multiple can't be divided down to the bit clock.
+    UnsupportedMclkMultiple,
+    /// The clock dividers can't produce MCLK for this sample rate, to within
+    /// 0.1%.
+    UnsupportedSampleRate,
+    /// The clocks come from an external master, and can't be configured.
+    ExternalClock,
+}
+
+impl core::error::Error for ConfigError {}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(not(any(esp32, esp32s2)))]
+            ConfigError::UnsupportedSlotCount => write!(f, "A frame must have 1 to 16 slots"),
+            #[cfg(not(any(esp32, esp32s2)))]
+            ConfigError::UnsupportedSlotBits => {
+                write!(f, "Slots must be 8, 16, 24 or 32 bits wide")
+            }
+            #[cfg(not(any(esp32, esp32s2)))]
+            ConfigError::UnsupportedSampleBits => write!(
+                f,
+                "Samples must be 8, 16, 24 or 32 bits wide and fit into a slot"
+            ),
+            #[cfg(not(any(esp32, esp32s2)))]
+            ConfigError::FrameTooLong => write!(
+                f,
+                "A frame can't be longer than {} bits",
+                I2S_LL_TDM_FRAME_BITS_MAX
+            ),
+            #[cfg(not(any(esp32, esp32s2)))]
+            ConfigError::InvalidSlotMask => {
+                write!(f, "The active slots must be a non-empty subset of the slots")
+            }
+            #[cfg(not(any(esp32, esp32s2)))]
+            ConfigError::UnsupportedWsWidth => {
+                write!(f, "The WS pulse must be between 1 bit and a frame long")
+            }
+            ConfigError::UnsupportedMclkMultiple => write!(
+                f,
+                "MCLK must be an integer multiple of the bit clock, between 2 and 64 times as fast"
+            ),
+            ConfigError::UnsupportedSampleRate => {
+                write!(f, "The clock dividers can't produce the sample rate to within 0.1%")
+            }
+            ConfigError::ExternalClock => {
+                write!(f, "The clocks come from an external master in slave mode")
+            }
+        }
+    }
+}
+
+/// The ratio of the PDM clock to the PCM sample rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(any(esp32, esp32s3))]
+pub enum PdmDownsampling {
+    /// The PDM clock runs at 64 times the sample rate.
+    Ratio64,
+    /// The PDM clock runs at 128 times the sample rate.
+    Ratio128,
+}
+
+#[cfg(any(esp32, esp32s3))]
+impl PdmDownsampling {
+    fn clock_multiple(self) -> u32 {
+        match self {
+            PdmDownsampling::Ratio64 => 64,
+            PdmDownsampling::Ratio128 => 128,
+        }
+    }
+}
+
+/// The microphones read in PDM RX mode.
+///
+/// Two microphones share the data line: the left one is latched on the rising
+/// edge of the PDM clock, the right one on the falling edge. Which edge a
+/// microphone drives is usually selected with one of its pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(any(esp32, esp32s3))]
+pub enum PdmChannels {
+    /// Both microphones are read, and every frame holds a left and a right
+    /// sample, in that order.
+    Stereo,
+    /// Only the microphone latched on the rising edge is read.
+    Left,
+    /// Only the microphone latched on the falling edge is read.
+    Right,
+}
+
+/// PDM receive configuration.
+///
+/// The hardware decimates the PDM stream into 16-bit PCM samples. It has no
+/// gain or shift settings for received data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, procmacros::BuilderLite)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(any(esp32, esp32s3))]
+#[non_exhaustive]
+pub struct PdmRxConfig {
+    /// How many PDM bits make up one PCM sample.
+    pub downsampling: PdmDownsampling,
+
+    /// The microphones to read. Reading a single one keeps the unconnected
+    /// channel out of the received data.
+    pub channels: PdmChannels,
+}
+
+#[cfg(any(esp32, esp32s3))]
+impl Default for PdmRxConfig {
+    fn default() -> Self {
+        Self {
+            downsampling: PdmDownsampling::Ratio64,
+            channels: PdmChannels::Stereo,
+        }
+    }
+}
+
 /// Instance of the I2S peripheral driver
 #[non_exhaustive]
 pub struct I2s<'d, Dm>
@@ -340,8 +718,131 @@ impl<'d> I2s<'d, Blocking> {
     where
         CH: DmaChannelFor<AnyI2s>,
     {
-        crate::into_ref!(i2s);
+        crate::into_mapped_ref!(i2s);
+
+        Self::new_internal(
+            i2s,
+            channel,
+            rx_descriptors,
+            tx_descriptors,
+            ClockConfig::new(sample_rate, 2, data_format.channel_bits()),
+            data_format.bytes_per_sample(),
+            |i2s| i2s.configure(&standard, &data_format),
+        )
+    }
+
+    /// Construct a new I2S peripheral driver instance which transfers TDM
+    /// frames as described by `config`.
+    ///
+    /// The bit clock runs at `sample_rate × slots × slot_bits`.
+    #[cfg(not(any(esp32, esp32s2)))]
+    pub fn new_tdm<CH>(
+        i2s: impl Peripheral<P = impl RegisterAccess> + 'd,
+        config: TdmConfig,
+        sample_rate: impl Into<fugit::HertzU32>,
+        channel: impl Peripheral<P = CH> + 'd,
+        rx_descriptors: &'static mut [DmaDescriptor],
+        tx_descriptors: &'static mut [DmaDescriptor],
+    ) -> Result<Self, ConfigError>
+    where
+        CH: DmaChannelFor<AnyI2s>,
+    {
+        config.validate()?;
+        let clock = ClockConfig::new(sample_rate, config.slots, config.slot_bits);
+        clock.validate()?;
+
+        crate::into_mapped_ref!(i2s);
+
+        Ok(Self::new_internal(
+            i2s,
+            channel,
+            rx_descriptors,
+            tx_descriptors,
+            clock,
+            config.sample_bits as usize / 8,
+            |i2s| i2s.configure_tdm(&config),
+        ))
+    }
+
+    /// Construct a new I2S peripheral driver instance which receives from PDM
+    /// microphones, and delivers 16-bit PCM samples at `sample_rate`.
+    ///
+    /// Connect the PDM clock with `i2s_rx.with_ws` and the data line with
+    /// `i2s_rx.with_din`. The TX half has no descriptors and can't be used.
+    #[cfg(any(esp32, esp32s3))]
+    pub fn new_pdm_rx<CH>(
+        i2s: impl Peripheral<P = crate::peripherals::I2S0> + 'd,
+        config: PdmRxConfig,
+        sample_rate: impl Into<fugit::HertzU32>,
+        channel: impl Peripheral<P = CH> + 'd,
+        rx_descriptors: &'static mut [DmaDescriptor],
+    ) -> Self
+    where
+        CH: DmaChannelFor<AnyI2s>,
+    {
+        crate::into_mapped_ref!(i2s);
+
+        Self::new_internal(
+            i2s,
+            channel,
+            rx_descriptors,
+            &mut [],
+            ClockConfig::pdm_rx(sample_rate, config.downsampling),
+            DataFormat::Data16Channel16.bytes_per_sample(),
+            |i2s| {
+                i2s.configure(&Standard::Philips, &DataFormat::Data16Channel16);
+                configure_pdm_rx(&config);
+            },
+        )
+    }
+
+    /// Construct a new I2S peripheral driver instance in slave mode, where an
+    /// external master generates the bit clock (BCLK) and word select (WS).
+    ///
+    /// `with_bclk` and `with_ws` connect the pins as inputs, for both the TX
+    /// and the RX half. The peripheral's own clock runs at its fastest to
+    /// sample them, which supports bit clocks up to 10 MHz, so there's no
+    /// useful MCLK to output, and the clocks can't be configured.
+    ///
+    /// If the master stops clocking, transfers stop making progress, so
+    /// blocking transfers give up after one second by default (see
+    /// [`I2sRx::set_timeout`] and [`I2sTx::set_timeout`]).
+    pub fn new_slave<CH>(
+        i2s: impl Peripheral<P = impl RegisterAccess> + 'd,
+        standard: Standard,
+        data_format: DataFormat,
+        channel: impl Peripheral<P = CH> + 'd,
+        rx_descriptors: &'static mut [DmaDescriptor],
+        tx_descriptors: &'static mut [DmaDescriptor],
+    ) -> Self
+    where
+        CH: DmaChannelFor<AnyI2s>,
+    {
+        crate::into_mapped_ref!(i2s);
+
+        Self::new_internal(
+            i2s,
+            channel,
+            rx_descriptors,
+            tx_descriptors,
+            ClockConfig::slave(),
+            data_format.bytes_per_sample(),
+            |i2s| i2s.configure(&standard, &data_format),
+        )
+    }
 
+    fn new_internal<CH>(
+        i2s: PeripheralRef<'d, AnyI2s>,
+        channel: impl Peripheral<P = CH> + 'd,
+        rx_descriptors: &'static mut [DmaDescriptor],
+        tx_descriptors: &'static mut [DmaDescriptor],
+        clock: ClockConfig,
+        sample_bytes: usize,
+        configure: impl FnOnce(&AnyI2s),
+    ) -> Self
+    where
+        CH: DmaChannelFor<AnyI2s>,
+    {
         let channel = Channel::new(channel.map(|ch| ch.degrade()));
         channel.runtime_ensure_compatible(&i2s);
 
@@ -354,12 +855,17 @@ impl<'d> I2s<'d, Blocking> {
         let rx_guard = PeripheralGuard::new(peripheral);
         let tx_guard = PeripheralGuard::new(peripheral);
 
-        i2s.set_clock(calculate_clock(sample_rate, 2, data_format.channel_bits()));
-        i2s.configure(&standard, &data_format);
-        i2s.set_master();
+        i2s.set_clock(clock.dividers());
+        configure(&i2s);
+        if clock.slave {
+            i2s.set_slave();
+        } else {
+            i2s.set_master();
+        }
         i2s.update();
 
-        let i2s = i2s.map_into();
+        // The master may stop clocking at any time.
+        let timeout = clock.slave.then_some(SLAVE_TIMEOUT);
 
         Self {
             i2s_rx: RxCreator {
@@ -367,12 +873,18 @@ impl<'d> I2s<'d, Blocking> {
                 rx_channel: channel.rx,
                 descriptors: rx_descriptors,
                 guard: rx_guard,
+                sample_bytes,
+                slave: clock.slave,
+                timeout,
             },
             i2s_tx: TxCreator {
                 i2s,
                 tx_channel: channel.tx,
                 descriptors: tx_descriptors,
                 guard: tx_guard,
+                sample_bytes,
+                clock,
+                timeout,
             },
         }
     }
@@ -385,12 +897,18 @@ impl<'d> I2s<'d, Blocking> {
                 rx_channel: self.i2s_rx.rx_channel.into_async(),
                 descriptors: self.i2s_rx.descriptors,
                 guard: self.i2s_rx.guard,
+                sample_bytes: self.i2s_rx.sample_bytes,
+                slave: self.i2s_rx.slave,
+                timeout: self.i2s_rx.timeout,
             },
             i2s_tx: TxCreator {
                 i2s: self.i2s_tx.i2s,
                 tx_channel: self.i2s_tx.tx_channel.into_async(),
                 descriptors: self.i2s_tx.descriptors,
                 guard: self.i2s_tx.guard,
+                sample_bytes: self.i2s_tx.sample_bytes,
+                clock: self.i2s_tx.clock,
+                timeout: self.i2s_tx.timeout,
             },
         }
     }
@@ -400,14 +918,97 @@ impl<'d, Dm> I2s<'d, Dm>
 where
     Dm: DriverMode,
 {
+    /// Lets the RX half receive with the bit clock (BCLK) and word select (WS)
+    /// signals generated by the TX half.
+    ///
+    /// This is what full-duplex codecs expect: both directions run in
+    /// lock-step, so only the TX half's BCLK and WS need to be routed to pins.
+    /// The RX half only receives while a TX transfer is running, but starting
+    /// and stopping RX transfers leaves the clocks alone.
+    #[instability::unstable]
+    pub fn with_shared_clock(self) -> Self {
+        self.i2s_tx.i2s.share_clock();
+        self.i2s_tx.i2s.update();
+
+        self
+    }
+
     /// Configures the I2S peripheral to use a master clock (MCLK) output pin.
+    ///
+    /// On the ESP32, MCLK can only be output on GPIO0, GPIO1 or GPIO3, and
+    /// other pins cause a panic. The other chips route it to any pin.
     pub fn with_mclk<P: PeripheralOutput>(self, pin: impl Peripheral<P = P> + 'd) -> Self {
         crate::into_mapped_ref!(pin);
         pin.set_to_push_pull_output();
-        self.i2s_tx.i2s.mclk_signal().connect_to(pin);
+
+        #[cfg(esp32)]
+        let signal = {
+            let signal = pin
+                .output_signals(crate::private::Internal)
+                .iter()
+                .map(|(_, signal)| *signal)
+                .find(|signal| {
+                    matches!(
+                        signal,
+                        crate::gpio::OutputSignal::CLK_OUT1
+                            | crate::gpio::OutputSignal::CLK_OUT2
+                            | crate::gpio::OutputSignal::CLK_OUT3
+                    )
+                })
+                .expect("MCLK can only be output on GPIO0, GPIO1 or GPIO3");
+            self.i2s_tx.i2s.select_clk_out(signal);
+            signal
+        };
+        #[cfg(not(esp32))]
+        let signal = self.i2s_tx.i2s.mclk_signal();
+
+        signal.connect_to(pin);
 
         self
     }
+
+    /// Sets MCLK to `multiple` times the sample rate.
+    ///
+    /// The bit clock is divided down from MCLK, so it stays at the same
+    /// frequency.
+    pub fn set_mclk_multiple(&mut self, multiple: MclkMultiple) -> Result<(), ConfigError> {
+        self.apply_clock(ClockConfig {
+            mclk_multiple: multiple as u32,
+            ..self.i2s_tx.clock
+        })
+    }
+
+    /// Changes the sample rate.
+    ///
+    /// MCLK, the bit clock and WS are all recomputed, keeping the MCLK
+    /// multiple.
+    pub fn set_sample_rate(
+        &mut self,
+        sample_rate: impl Into<fugit::HertzU32>,
+    ) -> Result<(), ConfigError> {
+        self.apply_clock(ClockConfig {
+            sample_rate: sample_rate.into().raw(),
+            ..self.i2s_tx.clock
+        })
+    }
+
+    /// Returns the MCLK frequency the clock dividers actually produce.
+    ///
+    /// MCLK is divided down from a fixed clock source with a fractional
+    /// divider, so it may be slightly off the requested frequency.
+    pub fn mclk_frequency(&self) -> fugit::HertzU32 {
+        fugit::HertzU32::from_raw(self.i2s_tx.clock.dividers().mclk_frequency())
+    }
+
+    fn apply_clock(&mut self, clock: ClockConfig) -> Result<(), ConfigError> {
+        clock.validate()?;
+
+        self.i2s_tx.i2s.set_clock(clock.dividers());
+        self.i2s_tx.i2s.update();
+        self.i2s_tx.clock = clock;
+
+        Ok(())
+    }
 }
 
 /// I2S TX channel
@@ -418,6 +1019,9 @@ where
     i2s: PeripheralRef<'d, AnyI2s>,
     tx_channel: ChannelTx<'d, Dm, PeripheralTxChannel<AnyI2s>>,
     tx_chain: DescriptorChain,
+    sample_bytes: usize,
+    clock: ClockConfig,
+    timeout: Option<Duration>,
     _guard: PeripheralGuard,
 }
 
@@ -435,7 +1039,9 @@ where
     Dm: DriverMode,
 {
     fn peripheral_wait_dma(&mut self, _is_rx: bool, _is_tx: bool) {
-        self.i2s.wait_for_tx_done();
+        // `DmaTransferTx` has no way to report the timeout, but at least
+        // doesn't hang.
+        _ = self.wait_for_tx_done();
     }
 
     fn peripheral_dma_stop(&mut self) {
@@ -466,11 +1072,33 @@ where
         self.start_tx_transfer(&data, false)?;
 
         // wait until I2S_TX_IDLE is 1
-        self.i2s.wait_for_tx_done();
+        self.wait_for_tx_done()
+    }
+
+    fn wait_for_tx_done(&mut self) -> Result<(), Error> {
+        let deadline = self.timeout.map(|timeout| crate::time::now() + timeout);
+        while !self.i2s.is_tx_idle() {
+            if deadline.is_some_and(|deadline| crate::time::now() > deadline) {
+                self.i2s.tx_stop();
+                self.tx_channel.stop_transfer();
+                return Err(Error::Timeout);
+            }
+        }
+        self.i2s.tx_stop();
 
         Ok(())
     }
 
+    /// Sets how long blocking writes wait for the transfer to finish, or
+    /// `None` to wait forever.
+    ///
+    /// On timeout, [`Self::write`] and [`Self::write_samples`] stop the
+    /// transfer and return [`Error::Timeout`]. Waiting for one from
+    /// [`Self::write_dma`] stops just the same, but can't report it.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
     fn start_tx_transfer<'t, TXBUF>(
         &'t mut self,
         words: &'t TXBUF,
@@ -510,6 +1138,62 @@ where
         })
     }
 
+    /// Writes samples in the natural range of the configured data width, e.g.
+    /// -2^23 to 2^23 - 1 for 24-bit data.
+    ///
+    /// The samples are converted to the memory layout of the data format in
+    /// place for the duration of the transfer, and converted back afterwards,
+    /// hence the mutable borrow. Bits above the data width don't survive the
+    /// round trip.
+    pub fn write_samples(&mut self, samples: &mut [i32]) -> Result<(), Error> {
+        let sample_bytes = self.sample_bytes;
+        let result = self.write_bytes(pack_samples_in_place(samples, sample_bytes));
+        unpack_samples_in_place(samples, sample_bytes);
+
+        result
+    }
+
+    /// Changes the sample rate, keeping the DMA descriptors and pins.
+    ///
+    /// TX is stopped at the end of the frame that's being sent, and the data
+    /// the DMA has already fetched for the old rate is discarded. The next
+    /// transfer starts at the new rate, with MCLK, the bit clock and WS all
+    /// recomputed for the same MCLK multiple. No frame is cut short, but the
+    /// output pauses from the end of the last transfer (or up to one frame
+    /// after this call, if a circular transfer is still running) until the
+    /// next transfer starts.
+    ///
+    /// The RX half shares the clock dividers, and follows the new rate too.
+    /// Rates the dividers can't produce to within 0.1% are rejected with
+    /// [`ConfigError::UnsupportedSampleRate`], leaving TX untouched.
+    pub fn set_sample_rate(
+        &mut self,
+        sample_rate: impl Into<fugit::HertzU32>,
+    ) -> Result<(), ConfigError> {
+        let clock = ClockConfig {
+            sample_rate: sample_rate.into().raw(),
+            ..self.clock
+        };
+        clock.validate()?;
+
+        // TX only goes idle once it's done with the current frame. Give up
+        // after two frames, as TX never goes idle with a full FIFO on the
+        // ESP32 and ESP32-S2.
+        self.i2s.tx_stop();
+        let frame = 1_000_000u64.div_ceil(self.clock.sample_rate as u64);
+        let deadline = crate::time::now() + crate::time::Duration::micros(2 * frame);
+        while !self.i2s.is_tx_idle() && crate::time::now() < deadline {}
+
+        self.tx_channel.stop_transfer();
+        self.i2s.reset_tx();
+
+        self.i2s.set_clock(clock.dividers());
+        self.i2s.update();
+        self.clock = clock;
+
+        Ok(())
+    }
+
     /// Write I2S.
     /// Returns [DmaTransferTx] which represents the in-progress DMA
     /// transfer
@@ -546,6 +1230,8 @@ where
     i2s: PeripheralRef<'d, AnyI2s>,
     rx_channel: ChannelRx<'d, Dm, PeripheralRxChannel<AnyI2s>>,
     rx_chain: DescriptorChain,
+    sample_bytes: usize,
+    timeout: Option<Duration>,
     _guard: PeripheralGuard,
 }
 
@@ -563,7 +1249,9 @@ where
     Dm: DriverMode,
 {
     fn peripheral_wait_dma(&mut self, _is_rx: bool, _is_tx: bool) {
-        self.i2s.wait_for_rx_done();
+        // `DmaTransferRx` has no way to report the timeout, but at least
+        // doesn't hang.
+        _ = self.wait_for_rx_done();
     }
 
     fn peripheral_dma_stop(&mut self) {
@@ -594,11 +1282,34 @@ where
         self.start_rx_transfer(&mut data, false)?;
 
         // wait until I2S_RX_IDLE is 1
+        self.wait_for_rx_done()
+    }
+
+    fn wait_for_rx_done(&mut self) -> Result<(), Error> {
+        let deadline = self.timeout.map(|timeout| crate::time::now() + timeout);
+        while !self.i2s.is_rx_done() {
+            if deadline.is_some_and(|deadline| crate::time::now() > deadline) {
+                self.rx_channel.stop_transfer();
+                self.i2s.reset_rx();
+                return Err(Error::Timeout);
+            }
+        }
         self.i2s.wait_for_rx_done();
 
         Ok(())
     }
 
+    /// Sets how long blocking reads wait for the transfer to finish, or `None`
+    /// to wait forever.
+    ///
+    /// On timeout, [`Self::read`] and [`Self::read_samples`] stop the transfer
+    /// and return [`Error::Timeout`], leaving the buffer partially filled.
+    /// Waiting for one from [`Self::read_dma`] stops just the same, but can't
+    /// report it.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
     fn start_rx_transfer<'t, RXBUF>(
         &'t mut self,
         words: &'t mut RXBUF,
@@ -646,6 +1357,23 @@ where
         })
     }
 
+    /// Reads samples, sign-extended from the configured data width.
+    ///
+    /// The received data takes up [`DataFormat::bytes_per_sample`] bytes per
+    /// sample, which (like with [`Self::read`]) must be a non-zero multiple
+    /// of 4 bytes, up to 4096 bytes.
+    pub fn read_samples(&mut self, samples: &mut [i32]) -> Result<(), Error> {
+        let len = samples.len() * self.sample_bytes;
+        if len > 4096 || len == 0 {
+            return Err(Error::IllegalArgument);
+        }
+
+        self.read_bytes(&mut words_as_bytes(samples)[..len])?;
+        unpack_samples_in_place(samples, self.sample_bytes);
+
+        Ok(())
+    }
+
     /// Read I2S.
     /// Returns [DmaTransferRx] which represents the in-progress DMA
     /// transfer
@@ -711,6 +1439,9 @@ mod private {
         pub tx_channel: ChannelTx<'d, Dm, PeripheralTxChannel<AnyI2s>>,
         pub descriptors: &'static mut [DmaDescriptor],
         pub(crate) guard: PeripheralGuard,
+        pub(crate) sample_bytes: usize,
+        pub(crate) clock: ClockConfig,
+        pub(crate) timeout: Option<Duration>,
     }
 
     impl<'d, Dm> TxCreator<'d, Dm>
@@ -723,6 +1454,9 @@ mod private {
                 i2s: self.i2s,
                 tx_channel: self.tx_channel,
                 tx_chain: DescriptorChain::new(self.descriptors),
+                sample_bytes: self.sample_bytes,
+                clock: self.clock,
+                timeout: self.timeout,
                 _guard: PeripheralGuard::new(peripheral),
             }
         }
@@ -732,8 +1466,13 @@ mod private {
             P: PeripheralOutput,
         {
             crate::into_mapped_ref!(pin);
-            pin.set_to_push_pull_output();
-            self.i2s.bclk_signal().connect_to(pin);
+            if self.clock.slave {
+                pin.enable_input(true);
+                self.i2s.bclk_in_signal().connect_to(pin);
+            } else {
+                pin.set_to_push_pull_output();
+                self.i2s.bclk_signal().connect_to(pin);
+            }
 
             self
         }
@@ -743,8 +1482,13 @@ mod private {
             P: PeripheralOutput,
         {
             crate::into_mapped_ref!(pin);
-            pin.set_to_push_pull_output();
-            self.i2s.ws_signal().connect_to(pin);
+            if self.clock.slave {
+                pin.enable_input(true);
+                self.i2s.ws_in_signal().connect_to(pin);
+            } else {
+                pin.set_to_push_pull_output();
+                self.i2s.ws_signal().connect_to(pin);
+            }
 
             self
         }
@@ -769,6 +1513,9 @@ mod private {
         pub rx_channel: ChannelRx<'d, Dm, PeripheralRxChannel<AnyI2s>>,
         pub descriptors: &'static mut [DmaDescriptor],
         pub(crate) guard: PeripheralGuard,
+        pub(crate) sample_bytes: usize,
+        pub(crate) slave: bool,
+        pub(crate) timeout: Option<Duration>,
     }
 
     impl<'d, Dm> RxCreator<'d, Dm>
@@ -781,6 +1528,8 @@ mod private {
                 i2s: self.i2s,
                 rx_channel: self.rx_channel,
                 rx_chain: DescriptorChain::new(self.descriptors),
+                sample_bytes: self.sample_bytes,
+                timeout: self.timeout,
                 _guard: PeripheralGuard::new(peripheral),
             }
         }
@@ -790,8 +1539,13 @@ mod private {
             P: PeripheralOutput,
         {
             crate::into_mapped_ref!(pin);
-            pin.set_to_push_pull_output();
-            self.i2s.bclk_rx_signal().connect_to(pin);
+            if self.slave {
+                pin.enable_input(true);
+                self.i2s.bclk_rx_in_signal().connect_to(pin);
+            } else {
+                pin.set_to_push_pull_output();
+                self.i2s.bclk_rx_signal().connect_to(pin);
+            }
 
             self
         }
@@ -801,8 +1555,13 @@ mod private {
             P: PeripheralOutput,
         {
             crate::into_mapped_ref!(pin);
-            pin.set_to_push_pull_output();
-            self.i2s.ws_rx_signal().connect_to(pin);
+            if self.slave {
+                pin.enable_input(true);
+                self.i2s.ws_rx_in_signal().connect_to(pin);
+            } else {
+                pin.set_to_push_pull_output();
+                self.i2s.ws_rx_signal().connect_to(pin);
+            }
 
             self
         }
@@ -825,13 +1584,22 @@ mod private {
     }
 
     pub trait Signals: RegBlock {
+        #[cfg(not(esp32))]
         fn mclk_signal(&self) -> OutputSignal;
+        /// Selects this peripheral's MCLK as the source of one of the
+        /// `CLK_OUTx` signals.
+        #[cfg(esp32)]
+        fn select_clk_out(&self, clk_out: OutputSignal);
         fn bclk_signal(&self) -> OutputSignal;
         fn ws_signal(&self) -> OutputSignal;
         fn dout_signal(&self) -> OutputSignal;
         fn bclk_rx_signal(&self) -> OutputSignal;
         fn ws_rx_signal(&self) -> OutputSignal;
         fn din_signal(&self) -> InputSignal;
+        fn bclk_in_signal(&self) -> InputSignal;
+        fn ws_in_signal(&self) -> InputSignal;
+        fn bclk_rx_in_signal(&self) -> InputSignal;
+        fn ws_rx_in_signal(&self) -> InputSignal;
     }
 
     #[cfg(any(esp32, esp32s2))]
@@ -973,6 +1741,20 @@ mod private {
             });
         }
 
+        fn set_slave(&self) {
+            self.regs().conf().modify(|_, w| {
+                w.rx_slave_mod().set_bit();
+                w.tx_slave_mod().set_bit()
+            });
+        }
+
+        fn share_clock(&self) {
+            self.regs().conf().modify(|_, w| {
+                w.sig_loopback().set_bit();
+                w.rx_slave_mod().set_bit()
+            });
+        }
+
         fn update(&self) {
             // nothing to do
         }
@@ -1008,12 +1790,8 @@ mod private {
             self.regs().conf().modify(|_, w| w.tx_start().clear_bit());
         }
 
-        fn wait_for_tx_done(&self) {
-            while self.regs().state().read().tx_idle().bit_is_clear() {
-                // wait
-            }
-
-            self.regs().conf().modify(|_, w| w.tx_start().clear_bit());
+        fn is_tx_idle(&self) -> bool {
+            self.regs().state().read().tx_idle().bit_is_set()
         }
 
         fn reset_rx(&self) {
@@ -1056,8 +1834,12 @@ mod private {
             self.regs().conf().modify(|_, w| w.rx_start().set_bit());
         }
 
+        fn is_rx_done(&self) -> bool {
+            self.regs().int_raw().read().in_suc_eof().bit_is_set()
+        }
+
         fn wait_for_rx_done(&self) {
-            while self.regs().int_raw().read().in_suc_eof().bit_is_clear() {
+            while !self.is_rx_done() {
                 // wait
             }
 
@@ -1311,13 +2093,27 @@ mod private {
         }
 
         fn configure(&self, _standard: &Standard, data_format: &DataFormat) {
-            #[allow(clippy::useless_conversion)]
+            // Philips is a TDM frame with two active slots.
+            self.configure_tdm(&TdmConfig {
+                slots: 2,
+                slot_bits: data_format.channel_bits(),
+                sample_bits: data_format.data_bits(),
+                active_slot_mask: 0b11,
+                ws_width: None,
+                left_align: true,
+                big_endian: false,
+            });
+        }
+
+        fn configure_tdm(&self, config: &TdmConfig) {
+            let active = |slot: u8| config.active_slot_mask & (1 << slot) != 0;
+
             self.regs().tx_conf1().modify(|_, w| unsafe {
-                w.tx_tdm_ws_width()
-                    .bits((data_format.channel_bits() - 1).into());
-                w.tx_bits_mod().bits(data_format.data_bits() - 1);
-                w.tx_tdm_chan_bits().bits(data_format.channel_bits() - 1);
-                w.tx_half_sample_bits().bits(data_format.channel_bits() - 1)
+                w.tx_tdm_ws_width().bits((config.ws_bits() - 1) as _);
+                w.tx_bits_mod().bits(config.sample_bits - 1);
+                w.tx_tdm_chan_bits().bits(config.slot_bits - 1);
+                w.tx_half_sample_bits()
+                    .bits((config.frame_bits() / 2 - 1) as _)
             });
             #[cfg(not(esp32h2))]
             self.regs()
@@ -1335,38 +2131,40 @@ mod private {
                 w.tx_tdm_en().set_bit();
                 w.tx_pdm_en().clear_bit();
                 w.tx_pcm_bypass().set_bit();
-                w.tx_big_endian().clear_bit();
+                w.tx_big_endian().bit(config.big_endian);
+                w.tx_left_align().bit(config.left_align);
                 w.tx_bit_order().clear_bit();
                 w.tx_chan_mod().bits(0)
             });
 
             self.regs().tx_tdm_ctrl().modify(|_, w| unsafe {
-                w.tx_tdm_tot_chan_num().bits(1);
-                w.tx_tdm_chan0_en().set_bit();
-                w.tx_tdm_chan1_en().set_bit();
-                w.tx_tdm_chan2_en().clear_bit();
-                w.tx_tdm_chan3_en().clear_bit();
-                w.tx_tdm_chan4_en().clear_bit();
-                w.tx_tdm_chan5_en().clear_bit();
-                w.tx_tdm_chan6_en().clear_bit();
-                w.tx_tdm_chan7_en().clear_bit();
-                w.tx_tdm_chan8_en().clear_bit();
-                w.tx_tdm_chan9_en().clear_bit();
-                w.tx_tdm_chan10_en().clear_bit();
-                w.tx_tdm_chan11_en().clear_bit();
-                w.tx_tdm_chan12_en().clear_bit();
-                w.tx_tdm_chan13_en().clear_bit();
-                w.tx_tdm_chan14_en().clear_bit();
-                w.tx_tdm_chan15_en().clear_bit()
+                w.tx_tdm_tot_chan_num().bits(config.slots - 1);
+                // Only the active slots take data from the DMA buffer.
+                w.tx_tdm_skip_msk_en().set_bit();
+                w.tx_tdm_chan0_en().bit(active(0));
+                w.tx_tdm_chan1_en().bit(active(1));
+                w.tx_tdm_chan2_en().bit(active(2));
+                w.tx_tdm_chan3_en().bit(active(3));
+                w.tx_tdm_chan4_en().bit(active(4));
+                w.tx_tdm_chan5_en().bit(active(5));
+                w.tx_tdm_chan6_en().bit(active(6));
+                w.tx_tdm_chan7_en().bit(active(7));
+                w.tx_tdm_chan8_en().bit(active(8));
+                w.tx_tdm_chan9_en().bit(active(9));
+                w.tx_tdm_chan10_en().bit(active(10));
+                w.tx_tdm_chan11_en().bit(active(11));
+                w.tx_tdm_chan12_en().bit(active(12));
+                w.tx_tdm_chan13_en().bit(active(13));
+                w.tx_tdm_chan14_en().bit(active(14));
+                w.tx_tdm_chan15_en().bit(active(15))
             });
 
-            #[allow(clippy::useless_conversion)]
             self.regs().rx_conf1().modify(|_, w| unsafe {
-                w.rx_tdm_ws_width()
-                    .bits((data_format.channel_bits() - 1).into());
-                w.rx_bits_mod().bits(data_format.data_bits() - 1);
-                w.rx_tdm_chan_bits().bits(data_format.channel_bits() - 1);
-                w.rx_half_sample_bits().bits(data_format.channel_bits() - 1)
+                w.rx_tdm_ws_width().bits((config.ws_bits() - 1) as _);
+                w.rx_bits_mod().bits(config.sample_bits - 1);
+                w.rx_tdm_chan_bits().bits(config.slot_bits - 1);
+                w.rx_half_sample_bits()
+                    .bits((config.frame_bits() / 2 - 1) as _)
             });
             #[cfg(not(esp32h2))]
             self.regs()
@@ -1384,28 +2182,29 @@ mod private {
                 w.rx_tdm_en().set_bit();
                 w.rx_pdm_en().clear_bit();
                 w.rx_pcm_bypass().set_bit();
-                w.rx_big_endian().clear_bit();
+                w.rx_big_endian().bit(config.big_endian);
+                w.rx_left_align().bit(config.left_align);
                 w.rx_bit_order().clear_bit()
             });
 
             self.regs().rx_tdm_ctrl().modify(|_, w| unsafe {
-                w.rx_tdm_tot_chan_num().bits(1);
-                w.rx_tdm_pdm_chan0_en().set_bit();
-                w.rx_tdm_pdm_chan1_en().set_bit();
-                w.rx_tdm_pdm_chan2_en().clear_bit();
-                w.rx_tdm_pdm_chan3_en().clear_bit();
-                w.rx_tdm_pdm_chan4_en().clear_bit();
-                w.rx_tdm_pdm_chan5_en().clear_bit();
-                w.rx_tdm_pdm_chan6_en().clear_bit();
-                w.rx_tdm_pdm_chan7_en().clear_bit();
-                w.rx_tdm_chan8_en().clear_bit();
-                w.rx_tdm_chan9_en().clear_bit();
-                w.rx_tdm_chan10_en().clear_bit();
-                w.rx_tdm_chan11_en().clear_bit();
-                w.rx_tdm_chan12_en().clear_bit();
-                w.rx_tdm_chan13_en().clear_bit();
-                w.rx_tdm_chan14_en().clear_bit();
-                w.rx_tdm_chan15_en().clear_bit()
+                w.rx_tdm_tot_chan_num().bits(config.slots - 1);
+                w.rx_tdm_pdm_chan0_en().bit(active(0));
+                w.rx_tdm_pdm_chan1_en().bit(active(1));
+                w.rx_tdm_pdm_chan2_en().bit(active(2));
+                w.rx_tdm_pdm_chan3_en().bit(active(3));
+                w.rx_tdm_pdm_chan4_en().bit(active(4));
+                w.rx_tdm_pdm_chan5_en().bit(active(5));
+                w.rx_tdm_pdm_chan6_en().bit(active(6));
+                w.rx_tdm_pdm_chan7_en().bit(active(7));
+                w.rx_tdm_chan8_en().bit(active(8));
+                w.rx_tdm_chan9_en().bit(active(9));
+                w.rx_tdm_chan10_en().bit(active(10));
+                w.rx_tdm_chan11_en().bit(active(11));
+                w.rx_tdm_chan12_en().bit(active(12));
+                w.rx_tdm_chan13_en().bit(active(13));
+                w.rx_tdm_chan14_en().bit(active(14));
+                w.rx_tdm_chan15_en().bit(active(15))
             });
         }
 
@@ -1418,6 +2217,24 @@ mod private {
                 .modify(|_, w| w.rx_slave_mod().clear_bit());
         }
 
+        fn set_slave(&self) {
+            self.regs()
+                .tx_conf()
+                .modify(|_, w| w.tx_slave_mod().set_bit());
+            self.regs()
+                .rx_conf()
+                .modify(|_, w| w.rx_slave_mod().set_bit());
+        }
+
+        fn share_clock(&self) {
+            self.regs()
+                .tx_conf()
+                .modify(|_, w| w.sig_loopback().set_bit());
+            self.regs()
+                .rx_conf()
+                .modify(|_, w| w.rx_slave_mod().set_bit());
+        }
+
         fn update(&self) {
             self.regs()
                 .tx_conf()
@@ -1456,14 +2273,8 @@ mod private {
                 .modify(|_, w| w.tx_start().clear_bit());
         }
 
-        fn wait_for_tx_done(&self) {
-            while self.regs().state().read().tx_idle().bit_is_clear() {
-                // wait
-            }
-
-            self.regs()
-                .tx_conf()
-                .modify(|_, w| w.tx_start().clear_bit());
+        fn is_tx_idle(&self) -> bool {
+            self.regs().state().read().tx_idle().bit_is_set()
         }
 
         fn reset_rx(&self) {
@@ -1495,8 +2306,12 @@ mod private {
             self.regs().rx_conf().modify(|_, w| w.rx_start().set_bit());
         }
 
+        fn is_rx_done(&self) -> bool {
+            self.regs().int_raw().read().rx_done().bit_is_set()
+        }
+
         fn wait_for_rx_done(&self) {
-            while self.regs().int_raw().read().rx_done().bit_is_clear() {
+            while !self.is_rx_done() {
                 // wait
             }
 
@@ -1530,11 +2345,15 @@ mod private {
     }
 
     impl Signals for crate::peripherals::I2S0 {
+        #[cfg(esp32)]
+        fn select_clk_out(&self, clk_out: OutputSignal) {
+            select_clk_out(clk_out, 0x0);
+        }
+
+        #[cfg(not(esp32))]
         fn mclk_signal(&self) -> OutputSignal {
             cfg_if::cfg_if! {
-                if #[cfg(esp32)] {
-                    panic!("MCLK currently not supported on ESP32");
-                } else if #[cfg(esp32s2)] {
+                if #[cfg(esp32s2)] {
                     OutputSignal::CLK_I2S
                 } else if #[cfg(esp32s3)] {
                     OutputSignal::I2S0_MCLK
@@ -1611,6 +2430,46 @@ mod private {
                 }
             }
         }
+
+        fn bclk_in_signal(&self) -> InputSignal {
+            cfg_if::cfg_if! {
+                if #[cfg(any(esp32, esp32s2, esp32s3))] {
+                    InputSignal::I2S0O_BCK
+                } else {
+                    InputSignal::I2SO_BCK
+                }
+            }
+        }
+
+        fn ws_in_signal(&self) -> InputSignal {
+            cfg_if::cfg_if! {
+                if #[cfg(any(esp32, esp32s2, esp32s3))] {
+                    InputSignal::I2S0O_WS
+                } else {
+                    InputSignal::I2SO_WS
+                }
+            }
+        }
+
+        fn bclk_rx_in_signal(&self) -> InputSignal {
+            cfg_if::cfg_if! {
+                if #[cfg(any(esp32, esp32s2, esp32s3))] {
+                    InputSignal::I2S0I_BCK
+                } else {
+                    InputSignal::I2SI_BCK
+                }
+            }
+        }
+
+        fn ws_rx_in_signal(&self) -> InputSignal {
+            cfg_if::cfg_if! {
+                if #[cfg(any(esp32, esp32s2, esp32s3))] {
+                    InputSignal::I2S0I_WS
+                } else {
+                    InputSignal::I2SI_WS
+                }
+            }
+        }
     }
 
     #[cfg(i2s1)]
@@ -1640,14 +2499,14 @@ mod private {
 
     #[cfg(i2s1)]
     impl Signals for crate::peripherals::I2S1 {
+        #[cfg(esp32)]
+        fn select_clk_out(&self, clk_out: OutputSignal) {
+            select_clk_out(clk_out, 0xF);
+        }
+
+        #[cfg(not(esp32))]
         fn mclk_signal(&self) -> OutputSignal {
-            cfg_if::cfg_if! {
-                if #[cfg(esp32)] {
-                    panic!("MCLK currently not supported on ESP32");
-                } else {
-                    OutputSignal::I2S1_MCLK
-                }
-            }
+            OutputSignal::I2S1_MCLK
         }
 
         fn bclk_signal(&self) -> OutputSignal {
@@ -1685,6 +2544,22 @@ mod private {
                 }
             }
         }
+
+        fn bclk_in_signal(&self) -> InputSignal {
+            InputSignal::I2S1O_BCK
+        }
+
+        fn ws_in_signal(&self) -> InputSignal {
+            InputSignal::I2S1O_WS
+        }
+
+        fn bclk_rx_in_signal(&self) -> InputSignal {
+            InputSignal::I2S1I_BCK
+        }
+
+        fn ws_rx_in_signal(&self) -> InputSignal {
+            InputSignal::I2S1I_WS
+        }
     }
 
     impl RegBlock for super::AnyI2s {
@@ -1726,13 +2601,84 @@ mod private {
                 #[cfg(i2s1)]
                 super::AnyI2sInner::I2s1(i2s) => i2s,
             } {
+                #[cfg(not(esp32))]
                 fn mclk_signal(&self) -> OutputSignal;
+                #[cfg(esp32)]
+                fn select_clk_out(&self, clk_out: OutputSignal);
                 fn bclk_signal(&self) -> OutputSignal;
                 fn ws_signal(&self) -> OutputSignal;
                 fn dout_signal(&self) -> OutputSignal;
                 fn bclk_rx_signal(&self) -> OutputSignal;
                 fn ws_rx_signal(&self) -> OutputSignal;
                 fn din_signal(&self) -> InputSignal;
+                fn bclk_in_signal(&self) -> InputSignal;
+                fn ws_in_signal(&self) -> InputSignal;
+                fn bclk_rx_in_signal(&self) -> InputSignal;
+                fn ws_rx_in_signal(&self) -> InputSignal;
+            }
+        }
+    }
+
+    /// The `CLK_OUTx` signals are selected by the `PIN_CTRL` register. `CLK1`
+    /// selects between the two I2S peripherals (0x0 for I2S0 and 0xF for
+    /// I2S1), and `CLK_OUT2` and `CLK_OUT3` additionally need their own field
+    /// cleared to output that clock.
+    #[cfg(esp32)]
+    fn select_clk_out(clk_out: OutputSignal, clk1: u8) {
+        crate::peripherals::IO_MUX::regs()
+            .pin_ctrl()
+            .modify(|_, w| unsafe {
+                w.clk1().bits(clk1);
+                match clk_out {
+                    OutputSignal::CLK_OUT2 => w.clk2().bits(0x0),
+                    OutputSignal::CLK_OUT3 => w.clk3().bits(0x0),
+                    _ => w,
+                }
+            });
+    }
+
+    #[cfg(any(esp32, esp32s3))]
+    pub fn configure_pdm_rx(config: &PdmRxConfig) {
+        // Only I2S0 has the PDM to PCM converter.
+        let regs = I2S0::regs();
+
+        cfg_if::cfg_if! {
+            if #[cfg(esp32)] {
+                regs.pdm_conf().modify(|_, w| {
+                    w.rx_pdm_en().set_bit();
+                    w.pdm2pcm_conv_en().set_bit();
+                    w.rx_pdm_sinc_dsr_16_en()
+                        .bit(config.downsampling == PdmDownsampling::Ratio128)
+                });
+
+                let (fifo_mod, chan_mod) = match config.channels {
+                    PdmChannels::Stereo => (0, 0),
+                    PdmChannels::Left => (1, 1),
+                    PdmChannels::Right => (1, 2),
+                };
+                regs.fifo_conf()
+                    .modify(|_, w| unsafe { w.rx_fifo_mod().bits(fifo_mod) });
+                regs.conf_chan()
+                    .modify(|_, w| unsafe { w.rx_chan_mod().bits(chan_mod) });
+            } else {
+                regs.rx_conf().modify(|_, w| {
+                    w.rx_tdm_en().clear_bit();
+                    w.rx_pdm_en().set_bit();
+                    w.rx_pdm2pcm_en().set_bit();
+                    w.rx_pdm_sinc_dsr_16_en()
+                        .bit(config.downsampling == PdmDownsampling::Ratio128)
+                });
+
+                // Only the enabled slots are stored.
+                let (left, right) = match config.channels {
+                    PdmChannels::Stereo => (true, true),
+                    PdmChannels::Left => (true, false),
+                    PdmChannels::Right => (false, true),
+                };
+                regs.rx_tdm_ctrl().modify(|_, w| {
+                    w.rx_tdm_pdm_chan0_en().bit(left);
+                    w.rx_tdm_pdm_chan1_en().bit(right)
+                });
             }
         }
     }
@@ -1744,81 +2690,189 @@ mod private {
         numerator: u32,
     }
 
-    pub fn calculate_clock(
-        sample_rate: impl Into<fugit::HertzU32>,
-        channels: u8,
-        data_bits: u8,
-    ) -> I2sClockDividers {
-        // this loosely corresponds to `i2s_std_calculate_clock` and
-        // `i2s_ll_tx_set_mclk` in esp-idf
-        //
-        // main difference is we are using fixed-point arithmetic here
-
-        // If data_bits is a power of two, use 256 as the mclk_multiple
-        // If data_bits is 24, use 192 (24 * 8) as the mclk_multiple
-        let mclk_multiple = if data_bits == 24 { 192 } else { 256 };
-        let sclk = crate::soc::constants::I2S_SCLK; // for now it's fixed 160MHz and 96MHz (just H2)
-
-        let rate_hz: HertzU32 = sample_rate.into();
-        let rate = rate_hz.raw();
-
-        let bclk = rate * channels as u32 * data_bits as u32;
-        let mclk = rate * mclk_multiple;
-        let bclk_divider = mclk / bclk;
-        let mut mclk_divider = sclk / mclk;
-
-        let mut ma: u32;
-        let mut mb: u32;
-        let mut denominator: u32 = 0;
-        let mut numerator: u32 = 0;
-
-        let freq_diff = sclk.abs_diff(mclk * mclk_divider);
-
-        if freq_diff != 0 {
-            let decimal = freq_diff as u64 * 10000 / mclk as u64;
-
-            // Carry bit if the decimal is greater than 1.0 - 1.0 / (63.0 * 2) = 125.0 /
-            // 126.0
-            if decimal > 1250000 / 126 {
-                mclk_divider += 1;
-            } else {
-                let mut min: u32 = !0;
+    impl I2sClockDividers {
+        /// The MCLK frequency these dividers produce.
+        pub fn mclk_frequency(&self) -> u32 {
+            let sclk = crate::soc::constants::I2S_SCLK as u64;
 
-                for a in 2..=I2S_LL_MCLK_DIVIDER_MAX {
-                    let b = (a as u64) * (freq_diff as u64 * 10000u64 / mclk as u64) + 5000;
-                    ma = ((freq_diff as u64 * 10000u64 * a as u64) / 10000) as u32;
-                    mb = (mclk as u64 * (b / 10000)) as u32;
+            // MCLK = SCLK / (N + b / a)
+            let (mut divider, mut scale) = (self.mclk_divider as u64, 1);
+            if self.numerator != 0 && self.denominator != 0 {
+                scale = self.denominator as u64;
+                divider = divider * scale + self.numerator as u64;
+            }
 
-                    if ma == mb {
-                        denominator = a as u32;
-                        numerator = (b / 10000) as u32;
-                        break;
-                    }
+            ((sclk * scale + divider / 2) / divider) as u32
+        }
+    }
+
+    /// How far off the generated sample rate may be, in parts per million.
+    const SAMPLE_RATE_TOLERANCE_PPM: u64 = 1000;
+
+    /// Enough for the longest blocking transfer, 4096 bytes of 8 kHz 8-bit
+    /// mono.
+    pub const SLAVE_TIMEOUT: Duration = Duration::millis(1000);
+
+    /// The clock setup of the driver, kept around so that MCLK can be
+    /// recomputed when either the sample rate or the multiple changes.
+    #[derive(Debug, Clone, Copy)]
+    pub struct ClockConfig {
+        pub sample_rate: u32,
+        /// Bit clock cycles per sample, i.e. per frame, or per PCM sample in
+        /// PDM mode.
+        pub frame_bits: u32,
+        pub mclk_multiple: u32,
+        /// The bit clock and WS come from an external master.
+        pub slave: bool,
+    }
+
+    impl ClockConfig {
+        pub fn new(sample_rate: impl Into<fugit::HertzU32>, channels: u8, data_bits: u8) -> Self {
+            // this loosely corresponds to `i2s_std_calculate_clock` in esp-idf
+            //
+            // If data_bits is a power of two, use 256 as the mclk_multiple
+            // If data_bits is 24, use 192 (24 * 8) as the mclk_multiple
+            let default_multiple = if data_bits == 24 { 192 } else { 256 };
+
+            let frame_bits = channels as u32 * data_bits as u32;
+
+            // Derive MCLK from BCLK, so that TDM frames whose length doesn't divide
+            // the MCLK multiple still get the exact bit clock
+            let bclk_divider = (default_multiple / frame_bits).max(2);
+
+            let rate_hz: HertzU32 = sample_rate.into();
+            Self {
+                sample_rate: rate_hz.raw(),
+                frame_bits,
+                mclk_multiple: frame_bits * bclk_divider,
+                slave: false,
+            }
+        }
+
+        /// Clock setup for slave mode, which runs MCLK at half the source
+        /// clock. Like esp-idf, this keeps the bit clock divider at 8, as MCLK
+        /// has to be at least 8 times as fast as the external bit clock.
+        pub fn slave() -> Self {
+            Self {
+                sample_rate: crate::soc::constants::I2S_SCLK / 16,
+                frame_bits: 1,
+                mclk_multiple: 8,
+                slave: true,
+            }
+        }
 
-                    if mb.abs_diff(ma) < min {
-                        denominator = a as u32;
-                        numerator = b as u32;
-                        min = mb.abs_diff(ma);
+        /// Clock setup for PDM RX, where the bit clock is the PDM clock.
+        #[cfg(any(esp32, esp32s3))]
+        pub fn pdm_rx(
+            sample_rate: impl Into<fugit::HertzU32>,
+            downsampling: PdmDownsampling,
+        ) -> Self {
+            // this corresponds to `i2s_pdm_rx_calculate_clock` in esp-idf
+            let rate_hz: HertzU32 = sample_rate.into();
+            let frame_bits = downsampling.clock_multiple();
+
+            Self {
+                sample_rate: rate_hz.raw(),
+                frame_bits,
+                mclk_multiple: frame_bits * 8,
+                slave: false,
+            }
+        }
+
+        fn mclk(&self) -> u64 {
+            self.sample_rate as u64 * self.mclk_multiple as u64
+        }
+
+        pub fn validate(&self) -> Result<(), ConfigError> {
+            if self.slave {
+                return Err(ConfigError::ExternalClock);
+            }
+
+            let bclk_divider = self.mclk_multiple / self.frame_bits;
+            if self.mclk_multiple % self.frame_bits != 0
+                || !(2..=I2S_LL_BCLK_DIVIDER_MAX).contains(&bclk_divider)
+            {
+                return Err(ConfigError::UnsupportedMclkMultiple);
+            }
+
+            // The fractional part may round the integer divider up.
+            let sclk = crate::soc::constants::I2S_SCLK as u64;
+            let mclk = self.mclk();
+            if mclk == 0 || !(2..=255).contains(&(sclk / mclk)) || sclk.div_ceil(mclk) > 255 {
+                return Err(ConfigError::UnsupportedSampleRate);
+            }
+
+            // The fraction has a limited denominator, so not every rate in range
+            // can be hit.
+            let error = (self.dividers().mclk_frequency() as u64).abs_diff(mclk);
+            if error * 1_000_000 > mclk * SAMPLE_RATE_TOLERANCE_PPM {
+                return Err(ConfigError::UnsupportedSampleRate);
+            }
+
+            Ok(())
+        }
+
+        pub fn dividers(&self) -> I2sClockDividers {
+            // this loosely corresponds to `i2s_ll_tx_set_mclk` in esp-idf
+            //
+            // main difference is we are using integer arithmetic here, and look for
+            // the fraction closest to the target instead of the one with the
+            // smallest absolute error term
+            let sclk = crate::soc::constants::I2S_SCLK as u64;
+            let mclk = self.mclk().max(1);
+
+            let mut mclk_divider = sclk / mclk;
+            let remainder = sclk % mclk;
+
+            // Approximate `remainder / mclk` with `numerator / denominator`.
+            let mut denominator = 0;
+            let mut numerator = 0;
+            if remainder != 0 {
+                // Compare the errors `|b * mclk - a * remainder| / a` without dividing.
+                let mut best_error = u64::MAX;
+                let mut best_a = 1;
+                let mut best_b = 0;
+                for a in 2..=I2S_LL_MCLK_DIVIDER_MAX as u64 {
+                    let b = (2 * a * remainder + mclk) / (2 * mclk);
+                    let error = (b * mclk).abs_diff(a * remainder);
+
+                    if (error as u128) * (best_a as u128) < (best_error as u128) * (a as u128) {
+                        best_error = error;
+                        best_a = a;
+                        best_b = b;
+                        if error == 0 {
+                            break;
+                        }
                     }
                 }
+
+                if best_b == best_a {
+                    // Closest to the next integer divider
+                    mclk_divider += 1;
+                } else if best_b != 0 {
+                    denominator = best_a as u32;
+                    numerator = best_b as u32;
+                }
             }
-        }
 
-        I2sClockDividers {
-            mclk_divider,
-            bclk_divider,
-            denominator,
-            numerator,
+            I2sClockDividers {
+                mclk_divider: mclk_divider as u32,
+                bclk_divider: self.mclk_multiple / self.frame_bits,
+                denominator,
+                numerator,
+            }
         }
     }
 }
 
 /// Async functionality
 pub mod asynch {
+    use embassy_futures::select::{select, Either};
+
     use super::{Error, I2sRx, I2sTx, RegisterAccessPrivate};
     use crate::{
         dma::{
-            asynch::{DmaRxDoneChFuture, DmaRxFuture, DmaTxDoneChFuture, DmaTxFuture},
+            asynch::{DmaRxDoneChFuture, DmaRxFuture, DmaTxEofChFuture, DmaTxFuture},
             DmaEligible,
             ReadBuffer,
             Rx,
@@ -1888,6 +2942,15 @@ pub mod asynch {
     }
 
     /// An in-progress async circular DMA write transfer.
+    ///
+    /// The transfer keeps running while no task is waiting on it, so a
+    /// cancelled future (e.g. one losing a `select`) doesn't interrupt the
+    /// stream. Whatever was pushed before the cancellation stays queued.
+    ///
+    /// If the DMA runs out of pushed data before more is pushed, it starts
+    /// over with stale samples. This underrun is reported as
+    /// [`DmaError::Late`](crate::dma::DmaError::Late) by the next call to
+    /// any of the methods, and can't be recovered from.
     pub struct I2sWriteDmaTransferAsync<'d, BUFFER> {
         i2s_tx: I2sTx<'d, Async>,
         state: TxCircularState,
@@ -1897,6 +2960,8 @@ pub mod asynch {
     impl<BUFFER> I2sWriteDmaTransferAsync<'_, BUFFER> {
         /// How many bytes can be pushed into the DMA transaction.
         /// Will wait for more than 0 bytes available.
+        ///
+        /// The task is woken as the DMA finishes sending each descriptor.
         pub async fn available(&mut self) -> Result<usize, Error> {
             loop {
                 self.state.update(&self.i2s_tx.tx_channel)?;
@@ -1906,7 +2971,7 @@ pub mod asynch {
                     break Ok(res);
                 }
 
-                DmaTxDoneChFuture::new(&mut self.i2s_tx.tx_channel).await?
+                DmaTxEofChFuture::new(&mut self.i2s_tx.tx_channel).await?
             }
         }
 
@@ -1917,6 +2982,19 @@ pub mod asynch {
             Ok(self.state.push(&data[..to_send])?)
         }
 
+        /// Push all of `data` into the DMA transaction, waiting for the DMA to
+        /// free up space as needed.
+        ///
+        /// If the future is dropped before completing, the data pushed so far
+        /// is still sent.
+        pub async fn push_all(&mut self, mut data: &[u8]) -> Result<(), Error> {
+            while !data.is_empty() {
+                let pushed = self.push(data).await?;
+                data = &data[pushed..];
+            }
+            Ok(())
+        }
+
         /// Push bytes into the DMA buffer via the given closure.
         /// The closure *must* return the actual number of bytes written.
         /// The closure *might* get called with a slice which is smaller than
@@ -1926,7 +3004,7 @@ pub mod asynch {
             &mut self,
             f: impl FnOnce(&mut [u8]) -> usize,
         ) -> Result<usize, Error> {
-            let _avail = self.available().await;
+            self.available().await?;
             Ok(self.state.push_with(f)?)
         }
     }
@@ -1962,6 +3040,28 @@ pub mod asynch {
             Ok(())
         }
 
+        /// One-shot read I2S, which gives up with [`Error::Timeout`] once
+        /// `deadline` completes.
+        ///
+        /// `deadline` is usually a timer, e.g. `Timer::after_millis(100)` with
+        /// `embassy-time`. This keeps a slave from waiting forever when the
+        /// master stops clocking. On timeout, `words` is partially filled.
+        pub async fn read_dma_async_until(
+            &mut self,
+            words: &mut [u8],
+            deadline: impl core::future::Future<Output = ()>,
+        ) -> Result<(), Error> {
+            match select(self.read_dma_async(words), deadline).await {
+                Either::First(result) => result,
+                Either::Second(()) => {
+                    self.rx_channel.stop_transfer();
+                    self.i2s.reset_rx();
+
+                    Err(Error::Timeout)
+                }
+            }
+        }
+
         /// Continuously read from I2S. Returns [I2sReadDmaTransferAsync]
         pub fn read_dma_circular_async<RXBUF>(
             mut self,
@@ -2002,6 +3102,13 @@ pub mod asynch {
     }
 
     /// An in-progress async circular DMA read transfer.
+    ///
+    /// Like its write counterpart, the transfer keeps running while no task is
+    /// waiting on it, and dropping a future doesn't lose any data.
+    ///
+    /// If data isn't popped fast enough, the DMA wraps around and overwrites
+    /// samples that haven't been read. This overrun is reported as
+    /// [`DmaError::Late`](crate::dma::DmaError::Late).
     pub struct I2sReadDmaTransferAsync<'d, BUFFER> {
         i2s_rx: I2sRx<'d, Async>,
         state: RxCircularState,
@@ -2021,15 +3128,22 @@ pub mod asynch {
                     break Ok(res);
                 }
 
+                // In circular mode, the successful EOF interrupt only fires once per
+                // lap of the buffer, so wait for a descriptor to be done instead.
                 DmaRxDoneChFuture::new(&mut self.i2s_rx.rx_channel).await?;
             }
         }
 
-        /// Pop bytes from the DMA transaction.
+        /// Pop bytes from the DMA transaction, waiting for at least one
+        /// descriptor's worth of data.
+        ///
+        /// Only whole descriptors are popped, as many as fit into `data`.
+        /// Returns the number of bytes written to `data`, or
+        /// [`DmaError::BufferTooSmall`](crate::dma::DmaError::BufferTooSmall)
+        /// if `data` can't hold a single descriptor.
         pub async fn pop(&mut self, data: &mut [u8]) -> Result<usize, Error> {
-            let avail = self.available().await?;
-            let to_rcv = usize::min(avail, data.len());
-            Ok(self.state.pop(&mut data[..to_rcv])?)
+            self.available().await?;
+            Ok(self.state.pop_descriptors(data)?)
         }
     }
 }
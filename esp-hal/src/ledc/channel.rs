@@ -8,18 +8,60 @@
 //! The module allows precise and flexible control over LED lighting and other
 //! `Pulse-Width Modulation (PWM)` applications by offering configurable duty
 //! cycles and frequencies.
+//!
+//! ## Fades
+//! A channel can change its duty cycle gradually in hardware, see
+//! [`ChannelIFace::start_fade`]. The end of a fade can be waited for with
+//! [`ChannelIFace::wait_fade_end`], or awaited with [`Channel::fade`], which
+//! uses the LEDC interrupt.
+//!
+//! A channel runs a single fade at a time: starting a new fade, or setting the
+//! duty directly, replaces the fade in progress from the next PWM period on.
+//! Fades are not queued.
+//!
+//! ## Grouped updates
+//! A new duty is latched at the end of the PWM period, so setting the duty on
+//! several channels one after the other can take effect in two different
+//! periods, when a period ends in between. Duties staged with
+//! [`ChannelIFace::stage_duty`] take effect together, in the same period, once
+//! the channels are passed to [`Ledc::commit`](super::Ledc::commit).
+//!
+//! ## embedded-hal
+//! [`Channel`] implements [`embedded_hal::pwm::SetDutyCycle`], with the
+//! maximum duty cycle given by the duty resolution of its timer. Fractions are
+//! rounded to the nearest duty the timer can output, and a fraction of 1 keeps
+//! the output fully on for the whole period. [`Channel::fade`] and
+//! [`Channel::wait_for_update`] are the async counterparts of the fade and
+//! update methods of [`ChannelIFace`].
+
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use portable_atomic::{AtomicBool, Ordering};
 
 use super::timer::{TimerIFace, TimerSpeed};
 use crate::{
+    asynch::AtomicWaker,
     gpio::{
         interconnect::{OutputConnection, PeripheralOutput},
         OutputSignal,
     },
+    handler,
     pac::ledc::RegisterBlock,
     peripheral::{Peripheral, PeripheralRef},
-    peripherals::LEDC,
+    peripherals::{Interrupt, LEDC},
+    sync::{lock, RawMutex},
+    time::Duration,
 };
 
+/// The largest value of the step count, the cycles per step and the duty
+/// change per step of a hardware fade.
+const MAX_FADE_FIELD: u32 = 0x3ff;
+
 /// Fade parameter sub-errors
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -44,6 +86,10 @@ pub enum Error {
     Timer,
     /// Channel not configured
     Channel,
+    /// Invalid hpoint or phase value
+    Hpoint,
+    /// Channels committed together don't use the same timer
+    TimerMismatch,
     /// Fade parameters invalid
     Fade(FadeError),
 }
@@ -93,6 +139,10 @@ pub mod config {
         pub timer: &'a dyn TimerIFace<S>,
         /// The duty cycle percentage (0-100).
         pub duty_pct: u8,
+        /// Where the duty window starts in the timer period, in timer ticks.
+        ///
+        /// Must be less than `1 << bit_count` of the timer's duty resolution.
+        pub hpoint: u32,
         /// The pin configuration (PushPull or OpenDrain).
         pub pin_config: PinConfig,
     }
@@ -109,6 +159,18 @@ where
     /// Set channel duty HW
     fn set_duty(&self, duty_pct: u8) -> Result<(), Error>;
 
+    /// Set channel duty HW, to take effect with the next commit
+    fn stage_duty(&self, duty_pct: u8) -> Result<(), Error>;
+
+    /// Check whether the last duty change hasn't taken effect yet
+    fn update_pending(&self) -> bool;
+
+    /// Set where the duty window starts in the timer period, in timer ticks
+    fn set_hpoint(&self, hpoint: u32) -> Result<(), Error>;
+
+    /// Set where the duty window starts in the timer period, in degrees
+    fn set_phase(&self, degrees: u16) -> Result<(), Error>;
+
     /// Start a duty-cycle fade
     fn start_duty_fade(
         &self,
@@ -117,8 +179,14 @@ where
         duration_ms: u16,
     ) -> Result<(), Error>;
 
+    /// Start a duty-cycle fade from `from_pct` to `to_pct`, lasting `duration`
+    fn start_fade(&self, from_pct: u8, to_pct: u8, duration: Duration) -> Result<(), Error>;
+
     /// Check whether a duty-cycle fade is running
     fn is_duty_fade_running(&self) -> bool;
+
+    /// Wait for the running duty-cycle fade to end
+    fn wait_fade_end(&self);
 }
 
 /// Channel HW interface
@@ -133,6 +201,13 @@ pub trait ChannelHW {
     /// Set channel duty HW
     fn set_duty_hw(&self, duty: u32);
 
+    /// Write the channel duty to HW, without applying it
+    fn stage_duty_hw(&self, duty: u32);
+
+    /// Apply the duty written by [`Self::stage_duty_hw`] at the end of the
+    /// period
+    fn commit_duty_hw(&self);
+
     /// Start a duty-cycle fade HW
     fn start_duty_fade_hw(
         &self,
@@ -153,6 +228,10 @@ pub struct Channel<'a, S: TimerSpeed> {
     timer: Option<&'a dyn TimerIFace<S>>,
     number: Number,
     output_pin: PeripheralRef<'a, OutputConnection>,
+    hpoint: Cell<u32>,
+    /// The duty the running fade has to be completed with, if its steps don't
+    /// add up to the exact end duty.
+    fade_end_duty: Cell<Option<u32>>,
 }
 
 impl<'a, S: TimerSpeed> Channel<'a, S> {
@@ -168,6 +247,8 @@ impl<'a, S: TimerSpeed> Channel<'a, S> {
             timer: None,
             number,
             output_pin,
+            hpoint: Cell::new(0),
+            fade_end_duty: Cell::new(None),
         }
     }
 }
@@ -180,6 +261,9 @@ where
     fn configure(&mut self, config: config::Config<'a, S>) -> Result<(), Error> {
         self.timer = Some(config.timer);
 
+        self.check_hpoint(config.hpoint)?;
+        self.hpoint.set(config.hpoint);
+
         self.set_duty(config.duty_pct)?;
         self.configure_hw_with_pin_config(config.pin_config)?;
 
@@ -187,62 +271,126 @@ where
     }
 
     /// Set duty % of channel
+    ///
+    /// The new duty is latched at the end of the current PWM period, so that
+    /// no period is cut short or stretched. Setting the duty again before
+    /// that replaces the value that will be latched, only the latest one is
+    /// output. [`Self::update_pending`] and [`Channel::wait_for_update`] tell
+    /// when the new duty has been output for a full period.
     fn set_duty(&self, duty_pct: u8) -> Result<(), Error> {
-        let duty_exp;
-        if let Some(timer) = self.timer {
-            if let Some(timer_duty) = timer.duty() {
-                duty_exp = timer_duty as u32;
-            } else {
-                return Err(Error::Timer);
-            }
-        } else {
-            return Err(Error::Channel);
-        }
-
-        let duty_range = 2u32.pow(duty_exp);
-        let duty_value = (duty_range * duty_pct as u32) / 100;
+        let duty_value = self.duty_from_pct(duty_pct)?;
+        self.set_duty_hw(duty_value);
 
-        if duty_pct > 100u8 {
-            // duty_pct greater than 100%
-            return Err(Error::Duty);
-        }
+        Ok(())
+    }
 
-        self.set_duty_hw(duty_value);
+    /// Set duty % of channel, without applying it
+    ///
+    /// The channel keeps its current duty until it is passed to
+    /// [`Ledc::commit`](super::Ledc::commit). Setting the duty or starting a
+    /// fade before that drops the staged duty.
+    fn stage_duty(&self, duty_pct: u8) -> Result<(), Error> {
+        let duty_value = self.duty_from_pct(duty_pct)?;
+        self.stage_duty_hw(duty_value);
 
         Ok(())
     }
 
-    /// Start a duty fade from one % to another.
+    /// Check whether the last duty change hasn't taken effect yet.
     ///
-    /// There's a constraint on the combination of timer frequency, timer PWM
-    /// duty resolution (the bit count), the fade "range" (abs(start-end)), and
-    /// the duration:
+    /// A duty change is pending from the moment the duty is set until a full
+    /// period with the new duty has been output. A fade is pending until it
+    /// has ended.
+    fn update_pending(&self) -> bool {
+        self.is_duty_fade_running_hw()
+    }
+
+    /// Set where the duty window starts in the timer period.
+    ///
+    /// The output goes high `hpoint` ticks into the period, and stays high for
+    /// the duty. Channels on the same timer can be shifted in phase against
+    /// each other this way. The hpoint is kept when the duty changes, until it
+    /// is set again or the channel is configured again.
+    ///
+    /// `hpoint` must be less than `1 << bit_count` of the timer's duty
+    /// resolution.
+    fn set_hpoint(&self, hpoint: u32) -> Result<(), Error> {
+        self.check_hpoint(hpoint)?;
+
+        self.hpoint.set(hpoint);
+        self.set_hpoint_hw(hpoint);
+        self.update_channel();
+
+        Ok(())
+    }
+
+    /// Set where the duty window starts in the timer period, in degrees.
     ///
-    /// frequency * duration / ((1<<bit_count) * abs(start-end)) < 1024
+    /// This is [`Self::set_hpoint`] with the hpoint given as a fraction of the
+    /// period, from 0 up to but not including 360 degrees. The hpoint is rounded
+    /// down to the timer's duty resolution.
+    fn set_phase(&self, degrees: u16) -> Result<(), Error> {
+        if degrees >= 360 {
+            return Err(Error::Hpoint);
+        }
+
+        let duty_range = 1u32 << self.duty_exp()?;
+        self.set_hpoint(duty_range * degrees as u32 / 360)
+    }
+
+    /// Start a duty fade from one % to another.
     ///
-    /// Small percentage changes, long durations, coarse PWM resolutions (that
-    /// is, low bit counts), and high timer frequencies will all be more likely
-    /// to fail this requirement.  If it does fail, this function will return
-    /// an error Result.
+    /// This is [`Self::start_fade`] with the duration in milliseconds.
     fn start_duty_fade(
         &self,
         start_duty_pct: u8,
         end_duty_pct: u8,
         duration_ms: u16,
     ) -> Result<(), Error> {
+        self.start_fade(
+            start_duty_pct,
+            end_duty_pct,
+            Duration::millis(duration_ms as u64),
+        )
+    }
+
+    /// Start a duty fade from one % to another.
+    ///
+    /// The fade replaces the one running on the channel, if any, and starts
+    /// from `from_pct` at the next PWM period.
+    ///
+    /// The hardware changes the duty in at most 1023 steps, and a step lasts at
+    /// most 1023 PWM periods. This constrains the combination of timer
+    /// frequency, timer PWM duty resolution (the bit count), the fade "range"
+    /// (abs(from-to)), and the duration:
+    ///
+    /// frequency * duration / min(1023, (1<<bit_count) * abs(from-to) / 100) <
+    /// 1024
+    ///
+    /// Long durations, small percentage changes, coarse PWM resolutions, and
+    /// high timer frequencies will all be more likely to fail this
+    /// requirement. If it does fail, this function will return an error
+    /// Result. Shorter durations are fine, the fade then takes fewer and bigger
+    /// steps.
+    ///
+    /// The steps never take the duty past `to_pct`, so fades to or from 0% and
+    /// 100% never wrap around the PWM range. When the steps don't add up to the
+    /// exact end duty, [`Self::wait_fade_end`] and [`Channel::fade`] set it
+    /// once the fade is over.
+    fn start_fade(&self, from_pct: u8, to_pct: u8, duration: Duration) -> Result<(), Error> {
         let duty_exp;
         let frequency;
-        if start_duty_pct > 100u8 {
+        if from_pct > 100u8 {
             return Err(Error::Fade(FadeError::StartDuty));
         }
-        if end_duty_pct > 100u8 {
+        if to_pct > 100u8 {
             return Err(Error::Fade(FadeError::EndDuty));
         }
         if let Some(timer) = self.timer {
             if let Some(timer_duty) = timer.duty() {
-                if timer.frequency() > 0 {
+                if let Some(timer_frequency) = timer.achieved_frequency() {
                     duty_exp = timer_duty as u32;
-                    frequency = timer.frequency();
+                    frequency = timer_frequency;
                 } else {
                     return Err(Error::Timer);
                 }
@@ -253,52 +401,160 @@ where
             return Err(Error::Channel);
         }
 
-        let duty_range = (1u32 << duty_exp) - 1;
-        let start_duty_value = (duty_range * start_duty_pct as u32) / 100;
-        let end_duty_value = (duty_range * end_duty_pct as u32) / 100;
-
-        // NB: since we do the multiplication first here, there's no loss of
-        // precision from using milliseconds instead of (e.g.) nanoseconds.
-        let pwm_cycles = (duration_ms as u32) * frequency / 1000;
+        // Same scale as `set_duty`, so that 100% is fully on.
+        let duty_range = 1u32 << duty_exp;
+        let start_duty_value = (duty_range * from_pct as u32) / 100;
+        let end_duty_value = (duty_range * to_pct as u32) / 100;
 
         let abs_duty_diff = end_duty_value.abs_diff(start_duty_value);
-        let duty_steps: u32 = u16::try_from(abs_duty_diff).unwrap_or(65535).into();
-        // This conversion may fail if duration_ms is too big, and if either
-        // duty_steps gets truncated, or the fade is over a short range of duty
-        // percentages, so it's too small.  Returning an Err in either case is
-        // fine: shortening the duration_ms will sort things out.
-        let cycles_per_step: u16 = (pwm_cycles / duty_steps)
-            .try_into()
-            .map_err(|_| Error::Fade(FadeError::Duration))
-            .and_then(|res| {
-                if res > 1023 {
-                    Err(Error::Fade(FadeError::Duration))
-                } else {
-                    Ok(res)
-                }
-            })?;
-        // This can't fail unless abs_duty_diff is bigger than 65536*65535-1,
-        // and so duty_steps gets truncated.  But that requires duty_exp to be
-        // at least 32, and the hardware only supports up to 20.  Still, handle
-        // it in case something changes in the future.
-        let duty_per_cycle: u16 = (abs_duty_diff / duty_steps)
-            .try_into()
-            .map_err(|_| Error::Fade(FadeError::DutyRange))?;
+        if abs_duty_diff == 0 {
+            // The hardware can't do a fade without steps.
+            self.set_duty_hw(end_duty_value);
+            return Ok(());
+        }
+
+        // The frequency is in steps of 0.01 Hz.
+        let pwm_cycles = (duration.to_micros() * frequency.raw() as u64 / 100_000_000).max(1);
+
+        // Take as many equal steps as the hardware, the duty range and the
+        // duration allow. Rounding the step size up and the step count down
+        // keeps the duty between the start and the end value.
+        let max_steps = pwm_cycles.min(abs_duty_diff.min(MAX_FADE_FIELD) as u64) as u32;
+        let duty_per_cycle = abs_duty_diff.div_ceil(max_steps).min(MAX_FADE_FIELD);
+        let duty_steps = (abs_duty_diff / duty_per_cycle).min(max_steps);
+        let cycles_per_step = pwm_cycles / duty_steps as u64;
+        if cycles_per_step > MAX_FADE_FIELD as u64 {
+            return Err(Error::Fade(FadeError::Duration));
+        }
 
         self.start_duty_fade_hw(
             start_duty_value,
             end_duty_value > start_duty_value,
-            duty_steps.try_into().unwrap(),
-            cycles_per_step,
-            duty_per_cycle,
+            duty_steps as u16,
+            cycles_per_step as u16,
+            duty_per_cycle as u16,
         );
 
+        let duty_change = duty_steps * duty_per_cycle;
+        let reached_duty_value = if end_duty_value > start_duty_value {
+            start_duty_value + duty_change
+        } else {
+            start_duty_value - duty_change
+        };
+        if reached_duty_value != end_duty_value {
+            self.fade_end_duty.set(Some(end_duty_value));
+        }
+
         Ok(())
     }
 
     fn is_duty_fade_running(&self) -> bool {
         self.is_duty_fade_running_hw()
     }
+
+    /// Wait for the running duty fade to end.
+    ///
+    /// Returns immediately if no fade is running.
+    fn wait_fade_end(&self) {
+        while self.is_duty_fade_running_hw() {}
+        self.complete_fade();
+    }
+}
+
+impl<'a, S: TimerSpeed> Channel<'a, S> {
+    /// Fade the duty from one % to another, and wait for the fade to end.
+    ///
+    /// See [`ChannelIFace::start_fade`] for the constraints on the
+    /// parameters. The first call binds the LEDC interrupt handler, which
+    /// replaces any handler bound to the LEDC interrupt before.
+    ///
+    /// Dropping the future doesn't stop the fade, the hardware carries on.
+    pub async fn fade(&mut self, from_pct: u8, to_pct: u8, duration: Duration) -> Result<(), Error> {
+        bind_interrupt_handler();
+
+        self.start_fade(from_pct, to_pct, duration)?;
+        DutyChangeFuture { channel: self }.await;
+        self.complete_fade();
+
+        Ok(())
+    }
+
+    /// Wait for the last duty change to take effect.
+    ///
+    /// This is the async version of polling [`ChannelIFace::update_pending`].
+    /// The first call binds the LEDC interrupt handler, like [`Self::fade`].
+    pub async fn wait_for_update(&mut self) {
+        bind_interrupt_handler();
+
+        DutyChangeFuture { channel: self }.await;
+    }
+
+    /// Set the exact end duty of the fade that just ended, if its steps fell
+    /// short of it.
+    fn complete_fade(&self) {
+        if let Some(duty) = self.fade_end_duty.take() {
+            self.set_duty_hw(duty);
+        }
+    }
+
+    fn duty_from_pct(&self, duty_pct: u8) -> Result<u32, Error> {
+        let duty_range = 2u32.pow(self.duty_exp()?);
+
+        if duty_pct > 100u8 {
+            // duty_pct greater than 100%
+            return Err(Error::Duty);
+        }
+
+        Ok((duty_range * duty_pct as u32) / 100)
+    }
+
+    fn duty_exp(&self) -> Result<u32, Error> {
+        let timer = self.timer.ok_or(Error::Channel)?;
+        let timer_duty = timer.duty().ok_or(Error::Timer)?;
+
+        Ok(timer_duty as u32)
+    }
+
+    fn check_hpoint(&self, hpoint: u32) -> Result<(), Error> {
+        if hpoint >= 1 << self.duty_exp()? {
+            return Err(Error::Hpoint);
+        }
+
+        Ok(())
+    }
+
+    /// The current count of a timer of the channel's speed, from 0 to the end
+    /// of its period.
+    #[cfg(esp32)]
+    fn timer_count(&self, number: super::timer::Number) -> u32 {
+        if S::IS_HS {
+            self.ledc.hstimer(number as usize).value().read().cnt().bits()
+        } else {
+            self.ledc.lstimer(number as usize).value().read().cnt().bits()
+        }
+    }
+
+    /// The current count of a timer, from 0 to the end of its period.
+    #[cfg(not(esp32))]
+    #[allow(clippy::useless_conversion)]
+    fn timer_count(&self, number: super::timer::Number) -> u32 {
+        self.ledc
+            .timer(number as usize)
+            .value()
+            .read()
+            .cnt()
+            .bits()
+            .into()
+    }
+
+    /// Where the channel is in [`DUTY_CHANGE_WAKERS`].
+    fn waker_index(&self) -> usize {
+        if S::IS_HS {
+            NUM_LS_CHANNELS + self.number as usize
+        } else {
+            self.number as usize
+        }
+    }
 }
 
 mod ehal1 {
@@ -317,28 +573,41 @@ mod ehal1 {
         type Error = Error;
     }
 
+    /// Scales `num / denom` to a duty of `duty_exp` bits, to the nearest
+    /// value.
+    ///
+    /// A fraction of 1 gives `1 << duty_exp`, one more than the largest counter
+    /// value, so the duty window never closes and the output is fully on.
+    fn duty_value(num: u16, denom: u16, duty_exp: u32) -> u32 {
+        let denom = denom as u64;
+        ((((num as u64) << duty_exp) + denom / 2) / denom) as u32
+    }
+
     impl<'a, S: TimerSpeed> SetDutyCycle for Channel<'a, S>
     where
         Channel<'a, S>: ChannelHW,
     {
+        /// The duty resolution of the timer, or `u16::MAX` for resolutions of
+        /// 16 bits and more.
         fn max_duty_cycle(&self) -> u16 {
-            let duty_exp;
-
-            if let Some(timer_duty) = self.timer.and_then(|timer| timer.duty()) {
-                duty_exp = timer_duty as u32;
-            } else {
-                return 0;
+            match self.duty_exp() {
+                Ok(duty_exp) => (1u32 << duty_exp).min(u16::MAX as u32) as u16,
+                Err(_) => 0,
             }
-
-            let duty_range = 2u32.pow(duty_exp);
-
-            duty_range as u16
         }
 
-        fn set_duty_cycle(&mut self, mut duty: u16) -> Result<(), Self::Error> {
+        fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
             let max = self.max_duty_cycle();
-            duty = if duty > max { max } else { duty };
-            self.set_duty_hw(duty.into());
+            self.set_duty_cycle_fraction(duty.min(max), max)
+        }
+
+        fn set_duty_cycle_fraction(&mut self, num: u16, denom: u16) -> Result<(), Self::Error> {
+            let duty_exp = self.duty_exp()?;
+            if denom == 0 || num > denom {
+                return Err(Error::Duty);
+            }
+
+            self.set_duty_hw(duty_value(num, denom, duty_exp));
             Ok(())
         }
     }
@@ -349,12 +618,14 @@ impl<S: crate::ledc::timer::TimerSpeed> Channel<'_, S> {
     fn set_channel(&mut self, timer_number: u8) {
         if S::IS_HS {
             let ch = self.ledc.hsch(self.number as usize);
-            ch.hpoint().write(|w| unsafe { w.hpoint().bits(0x0) });
+            ch.hpoint()
+                .write(|w| unsafe { w.hpoint().bits(self.hpoint.get() as _) });
             ch.conf0()
                 .modify(|_, w| unsafe { w.sig_out_en().set_bit().timer_sel().bits(timer_number) });
         } else {
             let ch = self.ledc.lsch(self.number as usize);
-            ch.hpoint().write(|w| unsafe { w.hpoint().bits(0x0) });
+            ch.hpoint()
+                .write(|w| unsafe { w.hpoint().bits(self.hpoint.get() as _) });
             ch.conf0()
                 .modify(|_, w| unsafe { w.sig_out_en().set_bit().timer_sel().bits(timer_number) });
         }
@@ -364,7 +635,8 @@ impl<S: crate::ledc::timer::TimerSpeed> Channel<'_, S> {
     fn set_channel(&mut self, timer_number: u8) {
         {
             let ch = self.ledc.ch(self.number as usize);
-            ch.hpoint().write(|w| unsafe { w.hpoint().bits(0x0) });
+            ch.hpoint()
+                .write(|w| unsafe { w.hpoint().bits(self.hpoint.get() as _) });
             ch.conf0().modify(|_, w| {
                 w.sig_out_en().set_bit();
                 unsafe { w.timer_sel().bits(timer_number) }
@@ -380,6 +652,28 @@ impl<S: crate::ledc::timer::TimerSpeed> Channel<'_, S> {
         self.start_duty_without_fading();
     }
 
+    #[cfg(esp32)]
+    fn set_hpoint_hw(&self, hpoint: u32) {
+        if S::IS_HS {
+            self.ledc
+                .hsch(self.number as usize)
+                .hpoint()
+                .write(|w| unsafe { w.hpoint().bits(hpoint as _) });
+        } else {
+            self.ledc
+                .lsch(self.number as usize)
+                .hpoint()
+                .write(|w| unsafe { w.hpoint().bits(hpoint as _) });
+        }
+    }
+    #[cfg(not(esp32))]
+    fn set_hpoint_hw(&self, hpoint: u32) {
+        self.ledc
+            .ch(self.number as usize)
+            .hpoint()
+            .write(|w| unsafe { w.hpoint().bits(hpoint as _) });
+    }
+
     #[cfg(esp32)]
     fn start_duty_without_fading(&self) {
         if S::IS_HS {
@@ -421,6 +715,14 @@ impl<S: crate::ledc::timer::TimerSpeed> Channel<'_, S> {
                 w.ch_gamma_scale().bits(0x0)
             }
         });
+        // Writing the address stores the entry, which would otherwise leave the
+        // last fade in place.
+        self.ledc
+            .ch_gamma_wr_addr(cnum)
+            .write(|w| unsafe { w.ch_gamma_wr_addr().bits(0) });
+        self.ledc
+            .ch_gamma_conf(cnum)
+            .write(|w| unsafe { w.ch_gamma_entry_num().bits(0x1) });
     }
     #[cfg(not(any(esp32, esp32c6, esp32h2)))]
     fn start_duty_without_fading(&self) {
@@ -547,6 +849,28 @@ impl<S: crate::ledc::timer::TimerSpeed> Channel<'_, S> {
             .conf0()
             .modify(|_, w| w.para_up().set_bit());
     }
+
+    // The new parameters only take effect at the end of the current period, so
+    // clearing the status after the update can't lose the end of the change
+    // that was just requested, only that of the change it replaces.
+    #[cfg(esp32)]
+    fn clear_duty_change_end(&self) {
+        if S::IS_HS {
+            self.ledc
+                .int_clr()
+                .write(|w| w.duty_chng_end_hsch(self.number as u8).clear_bit_by_one());
+        } else {
+            self.ledc
+                .int_clr()
+                .write(|w| w.duty_chng_end_lsch(self.number as u8).clear_bit_by_one());
+        }
+    }
+    #[cfg(not(esp32))]
+    fn clear_duty_change_end(&self) {
+        self.ledc
+            .int_clr()
+            .write(|w| w.duty_chng_end_ch(self.number as u8).clear_bit_by_one());
+    }
 }
 
 impl<S> ChannelHW for Channel<'_, S>
@@ -623,9 +947,18 @@ where
     }
 
     /// Set duty in channel HW
-    #[cfg(esp32)]
     fn set_duty_hw(&self, duty: u32) {
+        self.stage_duty_hw(duty);
+        self.commit_duty_hw();
+    }
+
+    /// Write duty in channel HW
+    #[cfg(esp32)]
+    fn stage_duty_hw(&self, duty: u32) {
+        self.fade_end_duty.set(None);
         if S::IS_HS {
+            // Setting `duty_start` applies the duty, HS channels have no
+            // `para_up`.
             self.ledc
                 .hsch(self.number as usize)
                 .duty()
@@ -635,20 +968,29 @@ where
                 .lsch(self.number as usize)
                 .duty()
                 .write(|w| unsafe { w.duty().bits(duty << 4) });
+            self.start_duty_without_fading();
         }
-        self.start_duty_without_fading();
-        self.update_channel();
     }
 
-    /// Set duty in channel HW
+    /// Write duty in channel HW
     #[cfg(not(esp32))]
-    fn set_duty_hw(&self, duty: u32) {
+    fn stage_duty_hw(&self, duty: u32) {
+        self.fade_end_duty.set(None);
         self.ledc
             .ch(self.number as usize)
             .duty()
             .write(|w| unsafe { w.duty().bits(duty << 4) });
         self.start_duty_without_fading();
+    }
+
+    /// Apply the written duty in channel HW
+    fn commit_duty_hw(&self) {
+        #[cfg(esp32)]
+        if S::IS_HS {
+            self.start_duty_without_fading();
+        }
         self.update_channel();
+        self.clear_duty_change_end();
     }
 
     /// Start a duty-cycle fade HW
@@ -666,20 +1008,15 @@ where
                 .hsch(self.number as usize)
                 .duty()
                 .write(|w| unsafe { w.duty().bits(start_duty << 4) });
-            self.ledc
-                .int_clr()
-                .write(|w| w.duty_chng_end_hsch(self.number as u8).clear_bit_by_one());
         } else {
             self.ledc
                 .lsch(self.number as usize)
                 .duty()
                 .write(|w| unsafe { w.duty().bits(start_duty << 4) });
-            self.ledc
-                .int_clr()
-                .write(|w| w.duty_chng_end_lsch(self.number as u8).clear_bit_by_one());
         }
         self.start_duty_fade_inner(duty_inc, duty_steps, cycles_per_step, duty_per_cycle);
         self.update_channel();
+        self.clear_duty_change_end();
     }
 
     /// Start a duty-cycle fade HW
@@ -696,11 +1033,9 @@ where
             .ch(self.number as usize)
             .duty()
             .write(|w| unsafe { w.duty().bits(start_duty << 4) });
-        self.ledc
-            .int_clr()
-            .write(|w| w.duty_chng_end_ch(self.number as u8).clear_bit_by_one());
         self.start_duty_fade_inner(duty_inc, duty_steps, cycles_per_step, duty_per_cycle);
         self.update_channel();
+        self.clear_duty_change_end();
     }
 
     #[cfg(esp32)]
@@ -722,3 +1057,160 @@ where
             .bit_is_clear()
     }
 }
+
+/// How long before the end of a period committing channels waits for the next
+/// one, to have the whole commit land in the same period.
+const COMMIT_MARGIN: Duration = Duration::micros(10);
+
+/// Apply the staged duties of `channels` at the end of the same period.
+pub(super) fn commit<S: TimerSpeed>(channels: &[&Channel<'_, S>]) -> Result<(), Error> {
+    let Some(first) = channels.first() else {
+        return Ok(());
+    };
+
+    let timer = first.timer.ok_or(Error::Channel)?;
+    for channel in channels {
+        if channel.timer.ok_or(Error::Channel)?.number() != timer.number() {
+            return Err(Error::TimerMismatch);
+        }
+    }
+
+    let duty_exp = first.duty_exp()?;
+    let frequency = timer.achieved_frequency().ok_or(Error::Timer)?;
+
+    // In timer ticks, capped at half a period for high PWM frequencies.
+    let period = 1u32 << duty_exp;
+    let tick_rate = (frequency.raw() as u64) << duty_exp;
+    let margin = (tick_rate * COMMIT_MARGIN.to_micros() / 100_000_000).clamp(1, period as u64 / 2);
+
+    critical_section::with(|_| {
+        let mut count = first.timer_count(timer.number());
+        if (period.saturating_sub(count) as u64) <= margin {
+            loop {
+                let next = first.timer_count(timer.number());
+                if next < count {
+                    break;
+                }
+                count = next;
+            }
+        }
+
+        for channel in channels {
+            channel.commit_duty_hw();
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(any(esp32c2, esp32c3, esp32c6, esp32h2)))]
+const NUM_LS_CHANNELS: usize = 8;
+#[cfg(any(esp32c2, esp32c3, esp32c6, esp32h2))]
+const NUM_LS_CHANNELS: usize = 6;
+
+/// Low speed channels first, then the high speed channels of the ESP32.
+#[cfg(esp32)]
+const NUM_CHANNELS: usize = 2 * NUM_LS_CHANNELS;
+#[cfg(not(esp32))]
+const NUM_CHANNELS: usize = NUM_LS_CHANNELS;
+
+static DUTY_CHANGE_WAKERS: [AtomicWaker; NUM_CHANNELS] =
+    [const { AtomicWaker::new() }; NUM_CHANNELS];
+
+static INTERRUPT_HANDLER_BOUND: AtomicBool = AtomicBool::new(false);
+
+static INT_ENA_LOCK: RawMutex = RawMutex::new();
+
+fn bind_interrupt_handler() {
+    if INTERRUPT_HANDLER_BOUND.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    unsafe { crate::interrupt::bind_interrupt(Interrupt::LEDC, duty_change_interrupt_handler.handler()) };
+    unwrap!(crate::interrupt::enable(
+        Interrupt::LEDC,
+        duty_change_interrupt_handler.priority()
+    ));
+}
+
+#[cfg(esp32)]
+fn is_duty_change_end_pending(status: &crate::pac::ledc::int_st::R, index: usize) -> bool {
+    if index >= NUM_LS_CHANNELS {
+        status
+            .duty_chng_end_hsch((index - NUM_LS_CHANNELS) as u8)
+            .bit_is_set()
+    } else {
+        status.duty_chng_end_lsch(index as u8).bit_is_set()
+    }
+}
+
+#[cfg(not(esp32))]
+fn is_duty_change_end_pending(status: &crate::pac::ledc::int_st::R, index: usize) -> bool {
+    status.duty_chng_end_ch(index as u8).bit_is_set()
+}
+
+#[cfg(esp32)]
+fn listen_duty_change_end(index: usize, enable: bool) {
+    lock(&INT_ENA_LOCK, || {
+        LEDC::regs().int_ena().modify(|_, w| {
+            if index >= NUM_LS_CHANNELS {
+                w.duty_chng_end_hsch((index - NUM_LS_CHANNELS) as u8)
+                    .bit(enable)
+            } else {
+                w.duty_chng_end_lsch(index as u8).bit(enable)
+            }
+        })
+    });
+}
+
+#[cfg(not(esp32))]
+fn listen_duty_change_end(index: usize, enable: bool) {
+    lock(&INT_ENA_LOCK, || {
+        LEDC::regs()
+            .int_ena()
+            .modify(|_, w| w.duty_chng_end_ch(index as u8).bit(enable))
+    });
+}
+
+#[handler]
+fn duty_change_interrupt_handler() {
+    let status = LEDC::regs().int_st().read();
+
+    for (index, waker) in DUTY_CHANGE_WAKERS.iter().enumerate() {
+        if is_duty_change_end_pending(&status, index) {
+            // The raw status stays set, it tells the future the change is over.
+            listen_duty_change_end(index, false);
+            waker.wake();
+        }
+    }
+}
+
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+struct DutyChangeFuture<'c, 'a, S: TimerSpeed> {
+    channel: &'c Channel<'a, S>,
+}
+
+impl<S: TimerSpeed> Future for DutyChangeFuture<'_, '_, S> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let index = self.channel.waker_index();
+        DUTY_CHANGE_WAKERS[index].register(cx.waker());
+
+        // Listen before looking, so that the end of the change can't slip in
+        // between.
+        listen_duty_change_end(index, true);
+        if self.channel.is_duty_fade_running_hw() {
+            Poll::Pending
+        } else {
+            listen_duty_change_end(index, false);
+            Poll::Ready(())
+        }
+    }
+}
+
+impl<S: TimerSpeed> Drop for DutyChangeFuture<'_, '_, S> {
+    fn drop(&mut self) {
+        listen_duty_change_end(self.channel.waker_index(), false);
+    }
+}
@@ -31,6 +31,7 @@
 //! # use esp_hal::ledc::timer::{self, TimerIFace};
 //! # use esp_hal::ledc::LowSpeed;
 //! # use esp_hal::ledc::channel::{self, ChannelIFace};
+//! # use esp_hal::time::Duration;
 //! # let led = peripherals.GPIO0;
 //!
 //! let mut ledc = Ledc::new(peripherals.LEDC);
@@ -49,23 +50,24 @@
 //!     .configure(channel::config::Config {
 //!         timer: &lstimer0,
 //!         duty_pct: 10,
+//!         hpoint: 0,
 //!         pin_config: channel::config::PinConfig::PushPull,
 //!     })?;
 //!
 //! loop {
 //!     // Set up a breathing LED: fade from off to on over a second, then
 //!     // from on back off over the next second.  Then loop.
-//!     channel0.start_duty_fade(0, 100, 1000)?;
-//!     while channel0.is_duty_fade_running() {}
-//!     channel0.start_duty_fade(100, 0, 1000)?;
-//!     while channel0.is_duty_fade_running() {}
+//!     channel0.start_fade(0, 100, Duration::millis(1000))?;
+//!     channel0.wait_fade_end();
+//!     channel0.start_fade(100, 0, Duration::millis(1000))?;
+//!     channel0.wait_fade_end();
 //! }
 //! # }
 //! ```
 //! 
 //! ## Implementation State
-//! - Source clock selection is not supported
-//! - Interrupts are not supported
+//! - The RC_FAST clock isn't supported as a clock source
+//! - Interrupts are only used to await the end of fades and duty updates
 
 use self::{
     channel::Channel,
@@ -87,6 +89,10 @@ pub mod timer;
 pub enum LSGlobalClkSource {
     /// APB clock.
     APBClk,
+    /// The crystal oscillator, which allows lower frequencies than the APB
+    /// clock.
+    #[cfg(not(esp32))]
+    XTALClk,
 }
 
 /// LEDC (LED PWM Controller)
@@ -162,6 +168,15 @@ impl<'d> Ledc<'d> {
                 pcr.ledc_sclk_conf()
                     .write(|w| unsafe { w.ledc_sclk_sel().bits(0) });
             }
+            LSGlobalClkSource::XTALClk => {
+                #[cfg(not(any(esp32c6, esp32h2)))]
+                self.ledc
+                    .conf()
+                    .write(|w| unsafe { w.apb_clk_sel().bits(3) });
+                #[cfg(any(esp32c6, esp32h2))]
+                pcr.ledc_sclk_conf()
+                    .write(|w| unsafe { w.ledc_sclk_sel().bits(3) });
+            }
         }
         self.ledc
             .timer(0)
@@ -174,6 +189,26 @@ impl<'d> Ledc<'d> {
         Timer::new(self.ledc, number)
     }
 
+    /// Apply the duties staged on `channels` in the same PWM period
+    ///
+    /// Each channel switches to the duty set with
+    /// [`ChannelIFace::stage_duty`](channel::ChannelIFace::stage_duty) at the
+    /// end of the current period, none of them a period later than the others.
+    /// Channels without a staged duty keep their duty.
+    ///
+    /// The channels have to run from the same timer, as channels on different
+    /// timers don't share their periods: this returns
+    /// [`channel::Error::TimerMismatch`] otherwise, without applying any duty.
+    ///
+    /// The duties are applied one channel after the other, with interrupts
+    /// disabled. When the period is about to end, this waits for the next
+    /// one to start first, for up to 10 µs. At PWM frequencies of hundreds of
+    /// kHz, committing many channels can take longer than the half period this
+    /// then leaves, and the last channels can still switch a period later.
+    pub fn commit<S: TimerSpeed>(&self, channels: &[&Channel<'_, S>]) -> Result<(), channel::Error> {
+        channel::commit(channels)
+    }
+
     /// Return a new channel
     pub fn channel<S: TimerSpeed>(
         &self,
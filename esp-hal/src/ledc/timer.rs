@@ -9,7 +9,20 @@
 //! duty cycles and frequencies, making it ideal for Pulse-Width Modulation
 //! (PWM) applications and LED lighting control.
 //!
-//! LEDC uses APB as clock source.
+//! A timer divides its clock source by a divisor between 1 and 1024, with 8
+//! fractional bits, and counts up to `1 << bit_count` of its duty resolution.
+//! The frequency is the closest one this can produce, which can be read back
+//! with [`TimerIFace::achieved_frequency`]. Low frequencies and high
+//! resolutions need a slow clock source:
+//!
+//! - high speed timers of the ESP32 run from the APB clock or REF_TICK
+//! - low speed timers run from the global slow clock, see
+//!   [`Ledc::set_global_slow_clock`](super::Ledc::set_global_slow_clock), or
+//!   REF_TICK on the ESP32 and ESP32-S2
+//!
+//! On the ESP32 and ESP32-S2, timers configured with
+//! [`LSClockSource::APBClk`] or `HSClockSource::APBClk` switch to REF_TICK by
+//! themselves when the frequency is too low for the APB clock.
 
 use fugit::HertzU32;
 
@@ -18,14 +31,32 @@ use super::HighSpeed;
 use super::{LowSpeed, Speed};
 use crate::{clock::Clocks, pac};
 
+/// The largest divisor, 1023 and 255/256.
 const LEDC_TIMER_DIV_NUM_MAX: u64 = 0x3FFFF;
 
+/// The divisor 1, with 8 fractional bits.
+const LEDC_TIMER_DIV_NUM_MIN: u64 = 0x100;
+
+/// REF_TICK is set up to tick at 1 MHz.
+#[cfg(any(esp32, esp32s2))]
+const REF_TICK_FREQUENCY: u32 = 1_000_000;
+
 /// Timer errors
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
-    /// Invalid Divisor
-    Divisor,
+    /// The frequency can't be produced with the requested duty resolution.
+    ///
+    /// The clock source can produce frequencies from `min` to `max` at that
+    /// resolution.
+    Frequency {
+        /// The lowest frequency at the requested duty resolution.
+        min: config::Frequency,
+        /// The highest frequency at the requested duty resolution.
+        max: config::Frequency,
+    },
+    /// The clock source isn't running, or isn't supported.
+    ClockSource,
 }
 
 #[cfg(esp32)]
@@ -35,16 +66,20 @@ pub enum Error {
 pub enum HSClockSource {
     /// APB clock.
     APBClk,
-    // TODO RefTick,
+    /// REF_TICK, at 1 MHz.
+    RefTick,
 }
 
 /// Clock source for LS Timers
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum LSClockSource {
-    /// APB clock.
+    /// The global slow clock, selected with
+    /// [`Ledc::set_global_slow_clock`](super::Ledc::set_global_slow_clock).
     APBClk,
-    // TODO SLOWClk
+    /// REF_TICK, at 1 MHz.
+    #[cfg(any(esp32, esp32s2))]
+    RefTick,
 }
 
 /// Timer number
@@ -63,7 +98,12 @@ pub enum Number {
 
 /// Timer configuration
 pub mod config {
-    use fugit::HertzU32;
+    /// A timer frequency, in steps of 0.01 Hz.
+    ///
+    /// `RateExtU32` shorthands like `24.kHz()` create it directly. Frequencies
+    /// below 1 Hz are created from their raw value, e.g.
+    /// `Frequency::from_raw(50)` for 0.5 Hz.
+    pub type Frequency = fugit::Rate<u32, 1, 100>;
 
     /// Number of bits reserved for duty cycle adjustment
     #[derive(PartialEq, Eq, Copy, Clone, Debug)]
@@ -98,22 +138,22 @@ pub mod config {
         Duty13Bit,
         /// 14-bit resolution for duty cycle adjustment.
         Duty14Bit,
-        #[cfg(esp32)]
+        #[cfg(any(esp32, esp32c6, esp32h2))]
         /// 15-bit resolution for duty cycle adjustment.
         Duty15Bit,
-        #[cfg(esp32)]
+        #[cfg(any(esp32, esp32c6, esp32h2))]
         /// 16-bit resolution for duty cycle adjustment.
         Duty16Bit,
-        #[cfg(esp32)]
+        #[cfg(any(esp32, esp32c6, esp32h2))]
         /// 17-bit resolution for duty cycle adjustment.
         Duty17Bit,
-        #[cfg(esp32)]
+        #[cfg(any(esp32, esp32c6, esp32h2))]
         /// 18-bit resolution for duty cycle adjustment.
         Duty18Bit,
-        #[cfg(esp32)]
+        #[cfg(any(esp32, esp32c6, esp32h2))]
         /// 19-bit resolution for duty cycle adjustment.
         Duty19Bit,
-        #[cfg(esp32)]
+        #[cfg(any(esp32, esp32c6, esp32h2))]
         /// 20-bit resolution for duty cycle adjustment.
         Duty20Bit,
     }
@@ -137,17 +177,17 @@ pub mod config {
                 12 => Self::Duty12Bit,
                 13 => Self::Duty13Bit,
                 14 => Self::Duty14Bit,
-                #[cfg(esp32)]
+                #[cfg(any(esp32, esp32c6, esp32h2))]
                 15 => Self::Duty15Bit,
-                #[cfg(esp32)]
+                #[cfg(any(esp32, esp32c6, esp32h2))]
                 16 => Self::Duty16Bit,
-                #[cfg(esp32)]
+                #[cfg(any(esp32, esp32c6, esp32h2))]
                 17 => Self::Duty17Bit,
-                #[cfg(esp32)]
+                #[cfg(any(esp32, esp32c6, esp32h2))]
                 18 => Self::Duty18Bit,
-                #[cfg(esp32)]
+                #[cfg(any(esp32, esp32c6, esp32h2))]
                 19 => Self::Duty19Bit,
-                #[cfg(esp32)]
+                #[cfg(any(esp32, esp32c6, esp32h2))]
                 20 => Self::Duty20Bit,
                 _ => Err(())?,
             })
@@ -161,8 +201,8 @@ pub mod config {
         pub duty: Duty,
         /// The clock source for the timer.
         pub clock_source: CS,
-        /// The frequency of the PWM signal in Hertz.
-        pub frequency: HertzU32,
+        /// The frequency of the PWM signal.
+        pub frequency: Frequency,
     }
 }
 
@@ -187,7 +227,7 @@ impl TimerSpeed for HighSpeed {
 
 /// Interface for Timers
 pub trait TimerIFace<S: TimerSpeed> {
-    /// Return the frequency of the timer
+    /// Return the frequency of the clock source of the timer
     fn freq(&self) -> Option<HertzU32>;
 
     /// Configure the timer
@@ -202,8 +242,12 @@ pub trait TimerIFace<S: TimerSpeed> {
     /// Return the timer number
     fn number(&self) -> Number;
 
-    /// Return the timer frequency, or 0 if not configured
+    /// Return the requested timer frequency in whole hertz, or 0 if not
+    /// configured
     fn frequency(&self) -> u32;
+
+    /// Return the frequency the timer actually runs at, if configured
+    fn achieved_frequency(&self) -> Option<config::Frequency>;
 }
 
 /// Interface for HW configuration of timer
@@ -211,6 +255,9 @@ pub trait TimerHW<S: TimerSpeed> {
     /// Get the current source timer frequency from the HW
     fn freq_hw(&self) -> Option<HertzU32>;
 
+    /// Whether the timer can fall back to REF_TICK for low frequencies
+    fn can_use_ref_tick(&self) -> bool;
+
     /// Configure the HW for the timer
     fn configure_hw(&self, divisor: u32);
 
@@ -224,6 +271,7 @@ pub struct Timer<'a, S: TimerSpeed> {
     number: Number,
     duty: Option<config::Duty>,
     frequency: u32,
+    achieved_frequency: Option<config::Frequency>,
     configured: bool,
     use_ref_tick: bool,
     clock_source: Option<S::ClockSourceType>,
@@ -239,29 +287,46 @@ where
     }
 
     /// Configure the timer
+    ///
+    /// Fails with [`Error::Frequency`], which holds the range of frequencies
+    /// the clock source can produce at the duty resolution, if the frequency
+    /// is outside of it.
     fn configure(&mut self, config: config::Config<S::ClockSourceType>) -> Result<(), Error> {
         self.duty = Some(config.duty);
         self.clock_source = Some(config.clock_source);
-
-        // TODO: we should return some error here if `unwrap()` fails
-        let src_freq: u32 = self.freq().unwrap().to_Hz();
-        let precision = 1 << config.duty as u32;
-        let frequency: u32 = config.frequency.raw();
-        self.frequency = frequency;
-
-        let mut divisor = ((src_freq as u64) << 8) / frequency as u64 / precision as u64;
-
-        if divisor > LEDC_TIMER_DIV_NUM_MAX {
-            // APB_CLK results in divisor which too high. Try using REF_TICK as clock
-            // source.
-            self.use_ref_tick = true;
-            divisor = (1_000_000u64 << 8) / frequency as u64 / precision as u64;
+        self.use_ref_tick = false;
+
+        let src_freq = self.freq().ok_or(Error::ClockSource)?.to_Hz();
+        let duty_exp = config.duty as u32;
+        self.frequency = config.frequency.to_Hz();
+
+        #[allow(unused_mut)]
+        let (mut min, max) = frequency_range(src_freq, duty_exp);
+        #[allow(unused_mut)]
+        let mut divisor = divisor(src_freq, config.frequency, duty_exp);
+
+        #[cfg(any(esp32, esp32s2))]
+        if self.can_use_ref_tick() {
+            min = frequency_range(REF_TICK_FREQUENCY, duty_exp).0;
+            if divisor > LEDC_TIMER_DIV_NUM_MAX {
+                // The frequency is too low for the APB clock.
+                self.use_ref_tick = true;
+                divisor = self::divisor(REF_TICK_FREQUENCY, config.frequency, duty_exp);
+            }
         }
 
-        if !(256..LEDC_TIMER_DIV_NUM_MAX).contains(&divisor) {
-            return Err(Error::Divisor);
+        if !(LEDC_TIMER_DIV_NUM_MIN..=LEDC_TIMER_DIV_NUM_MAX).contains(&divisor) {
+            return Err(Error::Frequency { min, max });
         }
 
+        #[cfg(any(esp32, esp32s2))]
+        let src_freq = if self.use_ref_tick {
+            REF_TICK_FREQUENCY
+        } else {
+            src_freq
+        };
+        self.achieved_frequency = Some(achieved_frequency(src_freq, divisor, duty_exp));
+
         self.configure_hw(divisor as u32);
         self.update_hw();
 
@@ -289,6 +354,47 @@ where
     fn frequency(&self) -> u32 {
         self.frequency
     }
+
+    /// Return the frequency the timer runs at
+    ///
+    /// This is as close to the configured frequency as the clock source and
+    /// the divisor allow.
+    fn achieved_frequency(&self) -> Option<config::Frequency> {
+        self.achieved_frequency
+    }
+}
+
+/// Returns the divisor, with 8 fractional bits, that gets closest to
+/// `frequency`.
+fn divisor(src_freq: u32, frequency: config::Frequency, duty_exp: u32) -> u64 {
+    // In steps of the frequency, 0.01 Hz.
+    let src_ticks = (src_freq as u64 * 100) << 8;
+    let period_ticks = (frequency.raw() as u64) << duty_exp;
+    if period_ticks == 0 {
+        return u64::MAX;
+    }
+
+    (src_ticks + period_ticks / 2) / period_ticks
+}
+
+/// Returns the frequency the timer runs at with a given divisor.
+fn achieved_frequency(src_freq: u32, divisor: u64, duty_exp: u32) -> config::Frequency {
+    let src_ticks = (src_freq as u64 * 100) << 8;
+    let period_ticks = divisor << duty_exp;
+
+    config::Frequency::from_raw(((src_ticks + period_ticks / 2) / period_ticks) as u32)
+}
+
+/// Returns the lowest and the highest frequency at a duty resolution.
+fn frequency_range(src_freq: u32, duty_exp: u32) -> (config::Frequency, config::Frequency) {
+    let src_ticks = (src_freq as u64 * 100) << 8;
+    let min = src_ticks.div_ceil(LEDC_TIMER_DIV_NUM_MAX << duty_exp);
+    let max = src_ticks / (LEDC_TIMER_DIV_NUM_MIN << duty_exp);
+
+    (
+        config::Frequency::from_raw(min as u32),
+        config::Frequency::from_raw(max.min(u32::MAX as u64) as u32),
+    )
 }
 
 impl<'a, S: TimerSpeed> Timer<'a, S> {
@@ -299,6 +405,7 @@ impl<'a, S: TimerSpeed> Timer<'a, S> {
             number,
             duty: None,
             frequency: 0u32,
+            achieved_frequency: None,
             configured: false,
             use_ref_tick: false,
             clock_source: None,
@@ -310,19 +417,22 @@ impl<'a, S: TimerSpeed> Timer<'a, S> {
 impl TimerHW<LowSpeed> for Timer<'_, LowSpeed> {
     /// Get the current source timer frequency from the HW
     fn freq_hw(&self) -> Option<HertzU32> {
-        self.clock_source.map(|source| match source {
-            LSClockSource::APBClk => {
-                let clocks = Clocks::get();
-                clocks.apb_clock
-            }
-        })
+        match self.clock_source? {
+            LSClockSource::APBClk => self.global_slow_clock_freq(),
+            #[cfg(any(esp32, esp32s2))]
+            LSClockSource::RefTick => Some(HertzU32::Hz(REF_TICK_FREQUENCY)),
+        }
+    }
+
+    fn can_use_ref_tick(&self) -> bool {
+        cfg!(any(esp32, esp32s2)) && self.clock_source == Some(LSClockSource::APBClk)
     }
 
     #[cfg(esp32)]
     /// Configure the HW for the timer
     fn configure_hw(&self, divisor: u32) {
         let duty = unwrap!(self.duty) as u8;
-        let use_apb = !self.use_ref_tick;
+        let use_apb = !self.use_ref_tick && self.clock_source == Some(LSClockSource::APBClk);
 
         self.ledc
             .lstimer(self.number as usize)
@@ -340,7 +450,7 @@ impl TimerHW<LowSpeed> for Timer<'_, LowSpeed> {
     /// Configure the HW for the timer
     fn configure_hw(&self, divisor: u32) {
         let duty = unwrap!(self.duty) as u8;
-        let use_ref_tick = self.use_ref_tick;
+        let use_ref_tick = self.use_ref_tick || self.clock_source != Some(LSClockSource::APBClk);
 
         self.ledc
             .timer(self.number as usize)
@@ -368,6 +478,42 @@ impl TimerHW<LowSpeed> for Timer<'_, LowSpeed> {
     }
 }
 
+impl Timer<'_, LowSpeed> {
+    /// The frequency of the global slow clock, as selected in the HW.
+    #[cfg(esp32)]
+    fn global_slow_clock_freq(&self) -> Option<HertzU32> {
+        // RC_FAST is the alternative, which the HAL doesn't run.
+        if self.ledc.conf().read().apb_clk_sel().bit_is_set() {
+            Some(Clocks::get().apb_clock)
+        } else {
+            None
+        }
+    }
+
+    /// The frequency of the global slow clock, as selected in the HW.
+    #[cfg(not(esp32))]
+    fn global_slow_clock_freq(&self) -> Option<HertzU32> {
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32c6, esp32h2))] {
+                let pcr = unsafe { &*crate::peripherals::PCR::ptr() };
+                let sel = pcr.ledc_sclk_conf().read().ledc_sclk_sel().bits();
+            } else {
+                let sel = self.ledc.conf().read().apb_clk_sel().bits();
+            }
+        }
+
+        // RC_FAST, 2, is the other option, which the HAL doesn't run.
+        match sel {
+            #[cfg(esp32h2)]
+            0 => Some(Clocks::get().apb_clock),
+            #[cfg(not(esp32h2))]
+            1 => Some(Clocks::get().apb_clock),
+            3 => Some(Clocks::get().xtal_clock),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(esp32)]
 /// Timer HW implementation for HighSpeed timers
 impl TimerHW<HighSpeed> for Timer<'_, HighSpeed> {
@@ -378,13 +524,18 @@ impl TimerHW<HighSpeed> for Timer<'_, HighSpeed> {
                 let clocks = Clocks::get();
                 clocks.apb_clock
             }
+            HSClockSource::RefTick => HertzU32::Hz(REF_TICK_FREQUENCY),
         })
     }
 
+    fn can_use_ref_tick(&self) -> bool {
+        self.clock_source == Some(HSClockSource::APBClk)
+    }
+
     /// Configure the HW for the timer
     fn configure_hw(&self, divisor: u32) {
         let duty = unwrap!(self.duty) as u8;
-        let sel_hstimer = self.clock_source == Some(HSClockSource::APBClk);
+        let sel_hstimer = !self.use_ref_tick && self.clock_source == Some(HSClockSource::APBClk);
 
         self.ledc
             .hstimer(self.number as usize)
@@ -0,0 +1,450 @@
+//! # MCPWM Capture Module
+//!
+//! ## Overview
+//! The `capture` module timestamps the edges of external signals. Each of the
+//! three capture channels of a MCPWM peripheral latches the value of the
+//! 32-bit [`CaptureTimer`] when its input changes, which measures pulse
+//! widths and periods with the resolution of the capture timer clock.
+//!
+//! [`CapturePin::capture`] reads a capture when polling or from an interrupt
+//! handler set with [`McPwm::set_interrupt_handler`], and
+//! [`CapturePin::next_capture`] waits for one asynchronously.
+//!
+//! ## Lost edges and timer wrap-around
+//! The hardware only keeps the last capture of each channel, so edges that
+//! come in faster than they are read overwrite each other. [`Capture::missed`]
+//! reports the losses the driver can tell about: captures that were replaced
+//! before [`CapturePin::next_capture`] took them, edges that came in while a
+//! capture was being read and, with [`CaptureEdge::Both`], two captures of the
+//! same edge in a row.
+//!
+//! The difference of the timestamps of two captures is the time between them
+//! as long as it is shorter than a full turn of the capture timer, which is
+//! `2^32` ticks. The driver compares it with the system timer to set
+//! [`Capture::overflow`] when it isn't.
+//!
+//! [`CaptureTimer`]: crate::mcpwm::capture::CaptureTimer
+//! [`CapturePin::capture`]: crate::mcpwm::capture::CapturePin::capture
+//! [`CapturePin::next_capture`]: crate::mcpwm::capture::CapturePin::next_capture
+//! [`Capture::missed`]: crate::mcpwm::capture::Capture::missed
+//! [`Capture::overflow`]: crate::mcpwm::capture::Capture::overflow
+//! [`CaptureEdge::Both`]: crate::mcpwm::capture::CaptureEdge::Both
+//! [`McPwm::set_interrupt_handler`]: crate::mcpwm::McPwm::set_interrupt_handler
+
+use core::{
+    future::poll_fn,
+    marker::PhantomData,
+    task::Poll,
+};
+
+use fugit::HertzU32;
+
+use super::{instance, PeripheralGuard, INT_ENA_LOCK, NUM_INSTANCES};
+use crate::{
+    asynch::AtomicWaker,
+    gpio::interconnect::PeripheralInput,
+    mcpwm::PwmPeripheral,
+    peripheral::Peripheral,
+    sync::{lock, Locked},
+    time,
+};
+
+/// The timer captured by the capture channels of a MCPWM peripheral
+///
+/// It counts up from when it's started, and wraps around after `2^32` ticks.
+#[cfg_attr(
+    any(esp32, esp32s3),
+    doc = "It runs from the APB clock, independently of the peripheral clock."
+)]
+#[cfg_attr(
+    any(esp32c6, esp32h2),
+    doc = "It runs from the peripheral clock, like the PWM timers."
+)]
+pub struct CaptureTimer<PWM> {
+    frequency: HertzU32,
+    phantom: PhantomData<PWM>,
+    _guard: PeripheralGuard,
+}
+
+impl<PWM: PwmPeripheral> CaptureTimer<PWM> {
+    pub(super) fn new(frequency: HertzU32) -> Self {
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        CaptureTimer {
+            frequency,
+            phantom: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    /// Start the capture timer
+    pub fn start(&mut self) {
+        let block = unsafe { &*PWM::block() };
+        block
+            .cap_timer_cfg()
+            .modify(|_, w| w.cap_timer_en().set_bit());
+    }
+
+    /// Stop the capture timer in its current state
+    pub fn stop(&mut self) {
+        let block = unsafe { &*PWM::block() };
+        block
+            .cap_timer_cfg()
+            .modify(|_, w| w.cap_timer_en().clear_bit());
+    }
+
+    /// Get the frequency the capture timer counts at.
+    ///
+    /// ### Note:
+    /// The actual value is rounded down to the nearest `u32` value
+    pub fn frequency(&self) -> HertzU32 {
+        self.frequency
+    }
+}
+
+/// The edges a capture channel captures
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CaptureEdge {
+    /// Capture rising edges.
+    Rising  = 0b10,
+    /// Capture falling edges.
+    Falling = 0b01,
+    /// Capture rising and falling edges.
+    Both    = 0b11,
+}
+
+/// Configuration of a capture channel
+#[derive(Copy, Clone)]
+pub struct CaptureConfig {
+    edge: CaptureEdge,
+    prescaler: u8,
+}
+
+impl CaptureConfig {
+    /// A configuration that captures the given edges of every period of the
+    /// input.
+    pub const fn new(edge: CaptureEdge) -> Self {
+        CaptureConfig { edge, prescaler: 0 }
+    }
+
+    /// Divide the input by `prescaler + 1` before detecting edges.
+    ///
+    /// The prescaler counts rising edges of the input, so that with
+    /// [`CaptureEdge::Rising`] only every `prescaler + 1`-th period is
+    /// captured. This lowers the interrupt rate of fast signals.
+    pub const fn with_prescaler(self, prescaler: u8) -> Self {
+        CaptureConfig { prescaler, ..self }
+    }
+}
+
+/// An edge of an input signal, e.g. the one that triggered a capture
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// A rising edge.
+    Rising,
+    /// A falling edge.
+    Falling,
+}
+
+/// A timestamped edge
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Capture {
+    /// The value of the capture timer when the edge came in.
+    pub timestamp: u32,
+    /// The edge that triggered the capture.
+    pub edge: Edge,
+    /// Whether edges were lost between the previous capture of the channel
+    /// and this one.
+    pub missed: bool,
+    /// Whether the capture timer made a full turn since the previous capture
+    /// of the channel, so that `timestamp.wrapping_sub(previous.timestamp)`
+    /// falls short of the time between them.
+    ///
+    /// This relies on the capture timer running all the time in between.
+    pub overflow: bool,
+}
+
+/// A capture read from the hardware, with the system time it was read at
+#[derive(Copy, Clone)]
+struct RawCapture {
+    timestamp: u32,
+    edge: Edge,
+    read_at: time::Instant,
+    missed: bool,
+}
+
+/// A capture channel
+///
+/// Timestamps edges with the [`CaptureTimer`] of the same
+/// [`MCPWM`](super::McPwm) peripheral, once it's connected to a pin with
+/// [`Self::with_pin`].
+pub struct CaptureChannel<'d, const CAP: u8, PWM> {
+    phantom: PhantomData<&'d PWM>,
+    frequency: HertzU32,
+    _guard: PeripheralGuard,
+}
+
+impl<'d, const CAP: u8, PWM: PwmPeripheral> CaptureChannel<'d, CAP, PWM> {
+    pub(super) fn new(frequency: HertzU32) -> Self {
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        CaptureChannel {
+            phantom: PhantomData,
+            frequency,
+            _guard: guard,
+        }
+    }
+
+    /// Capture the edges of the given pin
+    pub fn with_pin(
+        self,
+        pin: impl Peripheral<P = impl PeripheralInput> + 'd,
+        config: CaptureConfig,
+    ) -> CapturePin<'d, PWM, CAP> {
+        CapturePin::new(pin, config, self.frequency)
+    }
+}
+
+/// A pin whose edges are captured by a capture channel
+pub struct CapturePin<'d, PWM: PwmPeripheral, const CAP: u8> {
+    edge: CaptureEdge,
+    frequency: HertzU32,
+    previous: Option<RawCapture>,
+    phantom: PhantomData<&'d PWM>,
+    _guard: PeripheralGuard,
+}
+
+impl<'d, PWM: PwmPeripheral, const CAP: u8> CapturePin<'d, PWM, CAP> {
+    fn new(
+        pin: impl Peripheral<P = impl PeripheralInput> + 'd,
+        config: CaptureConfig,
+        frequency: HertzU32,
+    ) -> Self {
+        crate::into_mapped_ref!(pin);
+        pin.enable_input(true);
+        PWM::capture_signal::<CAP>().connect_to(pin);
+
+        let block = unsafe { &*PWM::block() };
+        block.cap_ch_cfg(CAP as usize).write(|w| unsafe {
+            w.mode().bits(config.edge as u8);
+            w.prescale().bits(config.prescaler);
+            w.en().set_bit()
+        });
+        // Don't report a capture from a previous configuration.
+        clear_interrupt::<PWM>(CAP);
+
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        CapturePin {
+            edge: config.edge,
+            frequency,
+            previous: None,
+            phantom: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    /// Enable the capture interrupt of this channel.
+    ///
+    /// Use [`McPwm::set_interrupt_handler`](super::McPwm::set_interrupt_handler)
+    /// to handle it, and [`Self::capture`] in the handler to read the capture
+    /// and clear the interrupt.
+    pub fn listen(&mut self) {
+        listen::<PWM>(CAP, true);
+    }
+
+    /// Disable the capture interrupt of this channel.
+    pub fn unlisten(&mut self) {
+        listen::<PWM>(CAP, false);
+    }
+
+    /// Read the capture the channel took since the last call, if any.
+    ///
+    /// This also clears the capture interrupt of the channel.
+    pub fn capture(&mut self) -> Option<Capture> {
+        let block = unsafe { &*PWM::block() };
+        let raw = block.int_raw().read();
+        let pending = match CAP {
+            0 => raw.cap0().bit_is_set(),
+            1 => raw.cap1().bit_is_set(),
+            _ => raw.cap2().bit_is_set(),
+        };
+        if !pending {
+            return None;
+        }
+
+        let raw = read_capture::<PWM>(CAP);
+        Some(self.complete(raw))
+    }
+
+    /// Wait for the next capture of the channel.
+    ///
+    /// This keeps the capture interrupt of the channel enabled, and returns
+    /// the latest capture the interrupt handler read since the last call
+    /// right away. The first call binds the MCPWM interrupt handler, which
+    /// replaces any handler bound to the interrupt before.
+    pub async fn next_capture(&mut self) -> Capture {
+        super::bind_interrupt_handler::<PWM>();
+
+        let channel = &state::<PWM>().channels[CAP as usize];
+        let raw = poll_fn(|cx| {
+            channel.waker.register(cx.waker());
+            listen::<PWM>(CAP, true);
+            match channel.pending.with(|pending| pending.take()) {
+                Some(raw) => Poll::Ready(raw),
+                None => Poll::Pending,
+            }
+        })
+        .await;
+
+        self.complete(raw)
+    }
+
+    /// Turn a capture into one relative to the previous capture.
+    fn complete(&mut self, raw: RawCapture) -> Capture {
+        let mut missed = raw.missed;
+        let mut overflow = false;
+
+        if let Some(previous) = self.previous {
+            // Captures of both edges alternate, unless one got lost.
+            if self.edge == CaptureEdge::Both && previous.edge == raw.edge {
+                missed = true;
+            }
+
+            let elapsed_ticks = (raw.read_at - previous.read_at)
+                .to_micros()
+                .saturating_mul(self.frequency.raw() as u64)
+                / 1_000_000;
+            let timestamp_ticks = raw.timestamp.wrapping_sub(previous.timestamp) as u64;
+            // The reads happen some time after the captures, which doesn't
+            // matter as long as the delays differ by less than half a turn.
+            overflow = elapsed_ticks.saturating_sub(timestamp_ticks) > 1 << 31;
+        }
+        self.previous = Some(raw);
+
+        Capture {
+            timestamp: raw.timestamp,
+            edge: raw.edge,
+            missed,
+            overflow,
+        }
+    }
+}
+
+impl<PWM: PwmPeripheral, const CAP: u8> Drop for CapturePin<'_, PWM, CAP> {
+    fn drop(&mut self) {
+        listen::<PWM>(CAP, false);
+
+        let block = unsafe { &*PWM::block() };
+        block
+            .cap_ch_cfg(CAP as usize)
+            .modify(|_, w| w.en().clear_bit());
+        state::<PWM>().channels[CAP as usize]
+            .pending
+            .with(|pending| *pending = None);
+    }
+}
+
+/// Read the capture of a channel and clear its interrupt
+fn read_capture<PWM: PwmPeripheral>(cap: u8) -> RawCapture {
+    let block = unsafe { &*PWM::block() };
+    let read = || {
+        let timestamp = block.cap_ch(cap as usize).read().value().bits();
+        let status = block.cap_status().read();
+        let falling = match cap {
+            0 => status.cap0_edge().bit_is_set(),
+            1 => status.cap1_edge().bit_is_set(),
+            _ => status.cap2_edge().bit_is_set(),
+        };
+        let edge = if falling { Edge::Falling } else { Edge::Rising };
+        (timestamp, edge)
+    };
+
+    let (timestamp, edge) = read();
+    clear_interrupt::<PWM>(cap);
+
+    // An edge coming in before the interrupt is cleared would be lost
+    // silently, report the newer capture instead.
+    let (latest, latest_edge) = read();
+    RawCapture {
+        timestamp: latest,
+        edge: latest_edge,
+        read_at: time::now(),
+        missed: latest != timestamp || latest_edge != edge,
+    }
+}
+
+struct ChannelState {
+    waker: AtomicWaker,
+    pending: Locked<Option<RawCapture>>,
+}
+
+struct State {
+    channels: [ChannelState; 3],
+}
+
+impl State {
+    const fn new() -> Self {
+        State {
+            channels: [const {
+                ChannelState {
+                    waker: AtomicWaker::new(),
+                    pending: Locked::new(None),
+                }
+            }; 3],
+        }
+    }
+}
+
+static STATE: [State; NUM_INSTANCES] = [const { State::new() }; NUM_INSTANCES];
+
+fn state<PWM: PwmPeripheral>() -> &'static State {
+    &STATE[instance::<PWM>()]
+}
+
+fn listen<PWM: PwmPeripheral>(cap: u8, enable: bool) {
+    let block = unsafe { &*PWM::block() };
+    lock(&INT_ENA_LOCK, || {
+        block.int_ena().modify(|_, w| match cap {
+            0 => w.cap0().bit(enable),
+            1 => w.cap1().bit(enable),
+            _ => w.cap2().bit(enable),
+        })
+    });
+}
+
+fn clear_interrupt<PWM: PwmPeripheral>(cap: u8) {
+    let block = unsafe { &*PWM::block() };
+    block.int_clr().write(|w| match cap {
+        0 => w.cap0().clear_bit_by_one(),
+        1 => w.cap1().clear_bit_by_one(),
+        _ => w.cap2().clear_bit_by_one(),
+    });
+}
+
+/// Read the captures of the channels whose interrupt is pending, for
+/// [`CapturePin::next_capture`].
+pub(super) fn on_interrupt<PWM: PwmPeripheral>() {
+    let block = unsafe { &*PWM::block() };
+    let status = block.int_st().read();
+
+    let pending = [
+        status.cap0().bit_is_set(),
+        status.cap1().bit_is_set(),
+        status.cap2().bit_is_set(),
+    ];
+
+    for (cap, channel) in (0..).zip(state::<PWM>().channels.iter()) {
+        if !pending[cap as usize] {
+            continue;
+        }
+
+        let mut raw = read_capture::<PWM>(cap);
+        channel.pending.with(|pending| {
+            // The previous capture wasn't taken in time.
+            raw.missed |= pending.is_some();
+            *pending = Some(raw);
+        });
+        channel.waker.wake();
+    }
+}
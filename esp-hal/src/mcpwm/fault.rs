@@ -0,0 +1,260 @@
+//! # MCPWM Fault Detection Module
+//!
+//! ## Overview
+//! The `fault` module turns up to three GPIO inputs into fault events, for
+//! example the outputs of overcurrent comparators. An operator brakes on the
+//! fault events its [`BrakeConfig`] selects: the hardware forces its outputs
+//! to the configured levels without any help from the CPU, either until the
+//! fault ends (cycle-by-cycle) or until the application rearms the operator
+//! (one-shot).
+//!
+//! [`FaultPin::wait_for_fault`] notifies the application asynchronously, and
+//! [`FaultPin::listen`] enables the interrupt of the fault event for a handler
+//! set with [`McPwm::set_interrupt_handler`].
+//!
+//! [`BrakeConfig`]: crate::mcpwm::operator::BrakeConfig
+//! [`FaultPin::wait_for_fault`]: crate::mcpwm::fault::FaultPin::wait_for_fault
+//! [`FaultPin::listen`]: crate::mcpwm::fault::FaultPin::listen
+//! [`McPwm::set_interrupt_handler`]: crate::mcpwm::McPwm::set_interrupt_handler
+
+use core::{future::poll_fn, marker::PhantomData, task::Poll};
+
+use super::{instance, PeripheralGuard, INT_ENA_LOCK, NUM_INSTANCES};
+use crate::{
+    asynch::AtomicWaker,
+    gpio::{interconnect::PeripheralInput, Level},
+    mcpwm::PwmPeripheral,
+    peripheral::Peripheral,
+    sync::lock,
+};
+
+/// A fault event of a MCPWM peripheral
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultEvent {
+    /// The event of fault input 0
+    Fault0 = 0,
+    /// The event of fault input 1
+    Fault1 = 1,
+    /// The event of fault input 2
+    Fault2 = 2,
+}
+
+/// A fault input of a MCPWM peripheral
+///
+/// It raises its [`FaultEvent`] once it's connected to a pin with
+/// [`Self::with_pin`].
+pub struct FaultDetector<'d, const F: u8, PWM> {
+    phantom: PhantomData<&'d PWM>,
+    _guard: PeripheralGuard,
+}
+
+impl<'d, const F: u8, PWM: PwmPeripheral> FaultDetector<'d, F, PWM> {
+    pub(super) fn new() -> Self {
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        FaultDetector {
+            phantom: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    /// Raise the fault event while the given pin is at `active_level`
+    pub fn with_pin(
+        self,
+        pin: impl Peripheral<P = impl PeripheralInput> + 'd,
+        active_level: Level,
+    ) -> FaultPin<'d, PWM, F> {
+        FaultPin::new(pin, active_level)
+    }
+}
+
+/// A pin that raises a fault event
+pub struct FaultPin<'d, PWM: PwmPeripheral, const F: u8> {
+    phantom: PhantomData<&'d PWM>,
+    _guard: PeripheralGuard,
+}
+
+impl<'d, PWM: PwmPeripheral, const F: u8> FaultPin<'d, PWM, F> {
+    fn new(pin: impl Peripheral<P = impl PeripheralInput> + 'd, active_level: Level) -> Self {
+        crate::into_mapped_ref!(pin);
+        pin.enable_input(true);
+        PWM::fault_signal::<F>().connect_to(pin);
+
+        let active_high = active_level == Level::High;
+        let block = unsafe { &*PWM::block() };
+        block.fault_detect().modify(|_, w| match F {
+            0 => w.f0_pole().bit(active_high).f0_en().set_bit(),
+            1 => w.f1_pole().bit(active_high).f1_en().set_bit(),
+            _ => w.f2_pole().bit(active_high).f2_en().set_bit(),
+        });
+
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        FaultPin {
+            phantom: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    /// The fault event this pin raises, to select it in a
+    /// [`BrakeConfig`](super::operator::BrakeConfig)
+    pub fn event(&self) -> FaultEvent {
+        match F {
+            0 => FaultEvent::Fault0,
+            1 => FaultEvent::Fault1,
+            _ => FaultEvent::Fault2,
+        }
+    }
+
+    /// Whether the fault event is active, i.e. the pin is at its active level
+    pub fn is_active(&self) -> bool {
+        is_active::<PWM>(F)
+    }
+
+    /// Enable the interrupt that signals the start of the fault event.
+    pub fn listen(&mut self) {
+        listen::<PWM>(F, Transition::Start, true);
+    }
+
+    /// Disable the interrupt that signals the start of the fault event.
+    pub fn unlisten(&mut self) {
+        listen::<PWM>(F, Transition::Start, false);
+    }
+
+    /// Returns true if the fault event started since the interrupt was last
+    /// reset.
+    pub fn interrupt_is_set(&self) -> bool {
+        let block = unsafe { &*PWM::block() };
+        let raw = block.int_raw().read();
+        match F {
+            0 => raw.fault0().bit_is_set(),
+            1 => raw.fault1().bit_is_set(),
+            _ => raw.fault2().bit_is_set(),
+        }
+    }
+
+    /// Reset the interrupt that signals the start of the fault event.
+    pub fn reset_interrupt(&mut self) {
+        clear_interrupt::<PWM>(F, Transition::Start);
+    }
+
+    /// Wait for the fault event to be active.
+    ///
+    /// Returns right away if it's active already. The first call binds the
+    /// MCPWM interrupt handler, which replaces any handler bound to the
+    /// interrupt before.
+    pub async fn wait_for_fault(&mut self) {
+        wait_for::<PWM>(F, Transition::Start).await
+    }
+
+    /// Wait for the fault event to end, e.g. before rearming an operator with
+    /// [`PwmPin::clear_one_shot_brake`](super::operator::PwmPin::clear_one_shot_brake).
+    ///
+    /// Returns right away if it isn't active. The first call binds the MCPWM
+    /// interrupt handler, like [`Self::wait_for_fault`].
+    pub async fn wait_for_fault_end(&mut self) {
+        wait_for::<PWM>(F, Transition::End).await
+    }
+}
+
+impl<PWM: PwmPeripheral, const F: u8> Drop for FaultPin<'_, PWM, F> {
+    fn drop(&mut self) {
+        listen::<PWM>(F, Transition::Start, false);
+        listen::<PWM>(F, Transition::End, false);
+
+        let block = unsafe { &*PWM::block() };
+        block.fault_detect().modify(|_, w| match F {
+            0 => w.f0_en().clear_bit(),
+            1 => w.f1_en().clear_bit(),
+            _ => w.f2_en().clear_bit(),
+        });
+    }
+}
+
+/// The interrupts of a fault event
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Transition {
+    Start,
+    End,
+}
+
+fn is_active<PWM: PwmPeripheral>(fault: u8) -> bool {
+    let block = unsafe { &*PWM::block() };
+    let status = block.fault_detect().read();
+    match fault {
+        0 => status.event_f0().bit_is_set(),
+        1 => status.event_f1().bit_is_set(),
+        _ => status.event_f2().bit_is_set(),
+    }
+}
+
+fn listen<PWM: PwmPeripheral>(fault: u8, transition: Transition, enable: bool) {
+    let block = unsafe { &*PWM::block() };
+    lock(&INT_ENA_LOCK, || {
+        block.int_ena().modify(|_, w| match (fault, transition) {
+            (0, Transition::Start) => w.fault0().bit(enable),
+            (1, Transition::Start) => w.fault1().bit(enable),
+            (_, Transition::Start) => w.fault2().bit(enable),
+            (0, Transition::End) => w.fault0_clr().bit(enable),
+            (1, Transition::End) => w.fault1_clr().bit(enable),
+            (_, Transition::End) => w.fault2_clr().bit(enable),
+        })
+    });
+}
+
+fn clear_interrupt<PWM: PwmPeripheral>(fault: u8, transition: Transition) {
+    let block = unsafe { &*PWM::block() };
+    block.int_clr().write(|w| match (fault, transition) {
+        (0, Transition::Start) => w.fault0().clear_bit_by_one(),
+        (1, Transition::Start) => w.fault1().clear_bit_by_one(),
+        (_, Transition::Start) => w.fault2().clear_bit_by_one(),
+        (0, Transition::End) => w.fault0_clr().clear_bit_by_one(),
+        (1, Transition::End) => w.fault1_clr().clear_bit_by_one(),
+        (_, Transition::End) => w.fault2_clr().clear_bit_by_one(),
+    });
+}
+
+async fn wait_for<PWM: PwmPeripheral>(fault: u8, transition: Transition) {
+    super::bind_interrupt_handler::<PWM>();
+
+    let waker = &WAKERS[instance::<PWM>()][fault as usize];
+    poll_fn(|cx| {
+        waker.register(cx.waker());
+
+        // Listen before looking, so that the transition can't slip in between.
+        listen::<PWM>(fault, transition, true);
+        if is_active::<PWM>(fault) == (transition == Transition::Start) {
+            listen::<PWM>(fault, transition, false);
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+static WAKERS: [[AtomicWaker; 3]; NUM_INSTANCES] =
+    [const { [const { AtomicWaker::new() }; 3] }; NUM_INSTANCES];
+
+/// Wake the tasks waiting for the fault events whose interrupt is pending.
+pub(super) fn on_interrupt<PWM: PwmPeripheral>() {
+    let block = unsafe { &*PWM::block() };
+    let status = block.int_st().read();
+    let pending = [
+        status.fault0().bit_is_set() || status.fault0_clr().bit_is_set(),
+        status.fault1().bit_is_set() || status.fault1_clr().bit_is_set(),
+        status.fault2().bit_is_set() || status.fault2_clr().bit_is_set(),
+    ];
+
+    for (fault, waker) in (0..).zip(WAKERS[instance::<PWM>()].iter()) {
+        if !pending[fault as usize] {
+            continue;
+        }
+
+        // The waiting task looks at the fault status itself.
+        for transition in [Transition::Start, Transition::End] {
+            listen::<PWM>(fault, transition, false);
+            clear_interrupt::<PWM>(fault, transition);
+        }
+        waker.wake();
+    }
+}
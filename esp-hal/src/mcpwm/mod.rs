@@ -20,21 +20,30 @@
 //!     * The 16-bit counter in the PWM timer can work in count-up mode,
 //!       count-down mode or count-up-down mode.
 //!     * A hardware sync or software sync can trigger a reload on the PWM timer
-//!       with a phase register (Not yet implemented)
+//!       with a phase register, which keeps the timers phase-aligned.
 //! * PWM Operators 0, 1 and 2
 //!     * Every PWM operator has two PWM outputs: PWMxA and PWMxB. They can work
 //!       independently, in symmetric and asymmetric configuration.
 //!     * Software, asynchronously override control of PWM signals.
 //!     * Configurable dead-time on rising and falling edges; each set up
-//!       independently. (Not yet implemented)
+//!       independently.
 //!     * All events can trigger CPU interrupts. (Not yet implemented)
 //!     * Modulating of PWM output by high-frequency carrier signals, useful
 //!       when gate drivers are insulated with a transformer. (Not yet
 //!       implemented)
 //!     * Period, time stamps and important control registers have shadow
 //!       registers with flexible updating methods.
-//! * Fault Detection Module (Not yet implemented)
-//! * Capture Module (Not yet implemented)
+//! * Fault Detection Module
+//!     * Three fault inputs with a configurable polarity.
+//!     * Every PWM operator brakes on the fault events of its choice, cycle by
+//!       cycle or until it's rearmed, forcing its outputs to configured levels
+//!       in hardware.
+//!     * The fault events can trigger CPU interrupts.
+//! * Capture Module
+//!     * A 32-bit capture timer, shared by the three capture channels.
+//!     * Every capture channel timestamps the rising and/or falling edges of
+//!       an input signal, after an 8-bit prescaler.
+//!     * The captures can trigger CPU interrupts.
 #![doc = ""]
 #![cfg_attr(esp32, doc = "Clock source is PWM_CLOCK")]
 #![cfg_attr(esp32s3, doc = "Clock source is CRYPTO_PWM_CLOCK")]
@@ -84,18 +93,29 @@
 //! # }
 //! ```
 
+use capture::{CaptureChannel, CaptureTimer};
+use fault::FaultDetector;
 use fugit::HertzU32;
 use operator::Operator;
-use timer::Timer;
+use portable_atomic::{AtomicBool, Ordering};
+use timer::{SyncInput, Timer};
 
 use crate::{
     clock::Clocks,
-    gpio::OutputSignal,
+    gpio::{InputSignal, OutputSignal},
+    handler,
+    interrupt::InterruptHandler,
     pac,
     peripheral::{Peripheral, PeripheralRef},
+    peripherals::Interrupt,
+    sync::RawMutex,
     system::{self, PeripheralGuard},
 };
 
+/// MCPWM capture channels
+pub mod capture;
+/// MCPWM fault detection
+pub mod fault;
 /// MCPWM operators
 pub mod operator;
 /// MCPWM timers
@@ -119,6 +139,26 @@ pub struct McPwm<'d, PWM> {
     pub operator1: Operator<'d, 1, PWM>,
     /// Operator2
     pub operator2: Operator<'d, 2, PWM>,
+    /// Capture timer
+    pub capture_timer: CaptureTimer<PWM>,
+    /// Capture channel 0
+    pub capture0: CaptureChannel<'d, 0, PWM>,
+    /// Capture channel 1
+    pub capture1: CaptureChannel<'d, 1, PWM>,
+    /// Capture channel 2
+    pub capture2: CaptureChannel<'d, 2, PWM>,
+    /// Fault input 0
+    pub fault0: FaultDetector<'d, 0, PWM>,
+    /// Fault input 1
+    pub fault1: FaultDetector<'d, 1, PWM>,
+    /// Fault input 2
+    pub fault2: FaultDetector<'d, 2, PWM>,
+    /// Sync input 0
+    pub sync0: SyncInput<'d, 0, PWM>,
+    /// Sync input 1
+    pub sync1: SyncInput<'d, 1, PWM>,
+    /// Sync input 2
+    pub sync2: SyncInput<'d, 2, PWM>,
     _guard: PeripheralGuard,
 }
 
@@ -174,17 +214,94 @@ impl<'d, PWM: PwmPeripheral> McPwm<'d, PWM> {
                 });
         }
 
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32, esp32s3))] {
+                let capture_clock = Clocks::get().apb_clock;
+            } else {
+                let capture_clock = peripheral_clock.frequency();
+            }
+        }
+
         Self {
             _inner: peripheral,
-            timer0: Timer::new(),
-            timer1: Timer::new(),
-            timer2: Timer::new(),
-            operator0: Operator::new(),
-            operator1: Operator::new(),
-            operator2: Operator::new(),
+            timer0: Timer::new(peripheral_clock.frequency()),
+            timer1: Timer::new(peripheral_clock.frequency()),
+            timer2: Timer::new(peripheral_clock.frequency()),
+            operator0: Operator::new(peripheral_clock.frequency()),
+            operator1: Operator::new(peripheral_clock.frequency()),
+            operator2: Operator::new(peripheral_clock.frequency()),
+            capture_timer: CaptureTimer::new(capture_clock),
+            capture0: CaptureChannel::new(capture_clock),
+            capture1: CaptureChannel::new(capture_clock),
+            capture2: CaptureChannel::new(capture_clock),
+            fault0: FaultDetector::new(),
+            fault1: FaultDetector::new(),
+            fault2: FaultDetector::new(),
+            sync0: SyncInput::new(),
+            sync1: SyncInput::new(),
+            sync2: SyncInput::new(),
             _guard: guard,
         }
     }
+
+    /// Set the interrupt handler for the MCPWM peripheral.
+    ///
+    /// Note that this will replace any previously registered interrupt
+    /// handlers, including the one the async functions of the capture
+    /// channels and fault inputs bind.
+    pub fn set_interrupt_handler(&mut self, handler: InterruptHandler) {
+        for core in crate::Cpu::other() {
+            crate::interrupt::disable(core, PWM::interrupt());
+        }
+        unsafe { crate::interrupt::bind_interrupt(PWM::interrupt(), handler.handler()) };
+        unwrap!(crate::interrupt::enable(PWM::interrupt(), handler.priority()));
+    }
+}
+
+impl<PWM> crate::private::Sealed for McPwm<'_, PWM> {}
+
+impl<PWM: PwmPeripheral> crate::interrupt::InterruptConfigurable for McPwm<'_, PWM> {
+    fn set_interrupt_handler(&mut self, handler: InterruptHandler) {
+        self.set_interrupt_handler(handler);
+    }
+}
+
+#[cfg(mcpwm1)]
+const NUM_INSTANCES: usize = 2;
+#[cfg(not(mcpwm1))]
+const NUM_INSTANCES: usize = 1;
+
+static INTERRUPT_HANDLER_BOUND: [AtomicBool; NUM_INSTANCES] =
+    [const { AtomicBool::new(false) }; NUM_INSTANCES];
+
+/// Serializes the read-modify-writes of the interrupt enable register.
+static INT_ENA_LOCK: RawMutex = RawMutex::new();
+
+/// Serializes the read-modify-writes of the shadow register update config.
+static UPDATE_CFG_LOCK: RawMutex = RawMutex::new();
+
+/// The index of the peripheral instance, for the state of the async API.
+#[cfg_attr(not(mcpwm1), allow(clippy::extra_unused_type_parameters))]
+fn instance<PWM: PwmPeripheral>() -> usize {
+    #[cfg(mcpwm1)]
+    if PWM::peripheral() == system::Peripheral::Mcpwm1 {
+        return 1;
+    }
+    0
+}
+
+/// Bind the interrupt handler of the async API, unless it's bound already.
+fn bind_interrupt_handler<PWM: PwmPeripheral>() {
+    if INTERRUPT_HANDLER_BOUND[instance::<PWM>()].swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let handler = PWM::async_handler();
+    unsafe { crate::interrupt::bind_interrupt(PWM::interrupt(), handler.handler()) };
+    unwrap!(crate::interrupt::enable(
+        PWM::interrupt(),
+        handler.priority()
+    ));
 }
 
 /// Clock configuration of the MCPWM peripheral
@@ -319,8 +436,18 @@ pub trait PwmPeripheral: crate::private::Sealed {
     fn block() -> *const RegisterBlock;
     /// Get operator GPIO mux output signal
     fn output_signal<const OP: u8, const IS_A: bool>() -> OutputSignal;
+    /// Get capture channel GPIO mux input signal
+    fn capture_signal<const CAP: u8>() -> InputSignal;
+    /// Get fault input GPIO mux input signal
+    fn fault_signal<const F: u8>() -> InputSignal;
+    /// Get sync input GPIO mux input signal
+    fn sync_signal<const S: u8>() -> InputSignal;
     /// Peripheral
     fn peripheral() -> system::Peripheral;
+    /// Interrupt of the peripheral
+    fn interrupt() -> Interrupt;
+    /// Interrupt handler of the async API
+    fn async_handler() -> InterruptHandler;
 }
 
 #[cfg(mcpwm0)]
@@ -341,9 +468,51 @@ impl PwmPeripheral for crate::peripherals::MCPWM0 {
         }
     }
 
+    fn capture_signal<const CAP: u8>() -> InputSignal {
+        match CAP {
+            0 => InputSignal::PWM0_CAP0,
+            1 => InputSignal::PWM0_CAP1,
+            2 => InputSignal::PWM0_CAP2,
+            _ => unreachable!(),
+        }
+    }
+
+    fn fault_signal<const F: u8>() -> InputSignal {
+        match F {
+            0 => InputSignal::PWM0_F0,
+            1 => InputSignal::PWM0_F1,
+            2 => InputSignal::PWM0_F2,
+            _ => unreachable!(),
+        }
+    }
+
+    fn sync_signal<const S: u8>() -> InputSignal {
+        match S {
+            0 => InputSignal::PWM0_SYNC0,
+            1 => InputSignal::PWM0_SYNC1,
+            2 => InputSignal::PWM0_SYNC2,
+            _ => unreachable!(),
+        }
+    }
+
     fn peripheral() -> system::Peripheral {
         system::Peripheral::Mcpwm0
     }
+
+    fn interrupt() -> Interrupt {
+        Interrupt::MCPWM0
+    }
+
+    fn async_handler() -> InterruptHandler {
+        mcpwm0_interrupt_handler
+    }
+}
+
+#[cfg(mcpwm0)]
+#[handler]
+fn mcpwm0_interrupt_handler() {
+    capture::on_interrupt::<crate::peripherals::MCPWM0>();
+    fault::on_interrupt::<crate::peripherals::MCPWM0>();
 }
 
 #[cfg(mcpwm1)]
@@ -364,7 +533,49 @@ impl PwmPeripheral for crate::peripherals::MCPWM1 {
         }
     }
 
+    fn capture_signal<const CAP: u8>() -> InputSignal {
+        match CAP {
+            0 => InputSignal::PWM1_CAP0,
+            1 => InputSignal::PWM1_CAP1,
+            2 => InputSignal::PWM1_CAP2,
+            _ => unreachable!(),
+        }
+    }
+
+    fn fault_signal<const F: u8>() -> InputSignal {
+        match F {
+            0 => InputSignal::PWM1_F0,
+            1 => InputSignal::PWM1_F1,
+            2 => InputSignal::PWM1_F2,
+            _ => unreachable!(),
+        }
+    }
+
+    fn sync_signal<const S: u8>() -> InputSignal {
+        match S {
+            0 => InputSignal::PWM1_SYNC0,
+            1 => InputSignal::PWM1_SYNC1,
+            2 => InputSignal::PWM1_SYNC2,
+            _ => unreachable!(),
+        }
+    }
+
     fn peripheral() -> system::Peripheral {
         system::Peripheral::Mcpwm1
     }
+
+    fn interrupt() -> Interrupt {
+        Interrupt::MCPWM1
+    }
+
+    fn async_handler() -> InterruptHandler {
+        mcpwm1_interrupt_handler
+    }
+}
+
+#[cfg(mcpwm1)]
+#[handler]
+fn mcpwm1_interrupt_handler() {
+    capture::on_interrupt::<crate::peripherals::MCPWM1>();
+    fault::on_interrupt::<crate::peripherals::MCPWM1>();
 }
@@ -11,12 +11,15 @@
 
 use core::marker::PhantomData;
 
-use super::PeripheralGuard;
+use fugit::HertzU32;
+
+use super::{PeripheralGuard, UPDATE_CFG_LOCK};
 use crate::{
     gpio::interconnect::{OutputConnection, PeripheralOutput},
-    mcpwm::{timer::Timer, PwmPeripheral},
+    mcpwm::{fault::FaultEvent, timer::Timer, PwmPeripheral},
     pac,
     peripheral::{Peripheral, PeripheralRef},
+    sync::lock,
 };
 
 /// Input/Output Stream descriptor for each channel
@@ -86,7 +89,33 @@ impl DeadTimeCfg {
     pub const fn new_ahc() -> DeadTimeCfg {
         DeadTimeCfg { cfg_reg: Self::S3 }
     }
-    // TODO: Add some common configurations ~AHC~,ALC,AH,AC
+
+    /// Active Low Complementary (ALC) from Technical Reference manual
+    ///
+    /// Like [`Self::new_ahc`] for gate drivers with active low inputs: output
+    /// PWMA is the inverted, rising edge delayed input PWMA and output PWMB
+    /// its falling edge delayed copy. Both are high during the deadtime.
+    pub const fn new_alc() -> DeadTimeCfg {
+        DeadTimeCfg { cfg_reg: Self::S2 }
+    }
+
+    /// Active High (AH) from Technical Reference manual
+    ///
+    /// Output PWMA is input PWMA with its rising edge delayed, output PWMB is
+    /// input PWMA with its falling edge delayed. The outputs are not
+    /// complementary.
+    pub const fn new_ah() -> DeadTimeCfg {
+        DeadTimeCfg { cfg_reg: 0 }
+    }
+
+    /// Active Low (AL) from Technical Reference manual
+    ///
+    /// The inverted outputs of [`Self::new_ah`].
+    pub const fn new_al() -> DeadTimeCfg {
+        DeadTimeCfg {
+            cfg_reg: Self::S2 | Self::S3,
+        }
+    }
 
     #[must_use]
     const fn set_flag(mut self, flag: u32, val: bool) -> Self {
@@ -134,9 +163,12 @@ impl DeadTimeCfg {
     }
 
     /// Select Between PWMClk & PT_Clk
+    ///
+    /// PWM_clk, the peripheral clock, is used if `pwm_clock` is true, PT_clk,
+    /// the clock of the timer of the operator, otherwise.
     #[must_use]
     pub const fn select_clock(self, pwm_clock: bool) -> Self {
-        self.set_flag(Self::CLK_SEL, pwm_clock)
+        self.set_flag(Self::CLK_SEL, !pwm_clock)
     }
 
     /// Select which stream is used for the input of FED/RED
@@ -159,23 +191,127 @@ impl DeadTimeCfg {
     }
 }
 
+/// The dead time could not be set.
+///
+/// Each delay has to fit in the 16-bit counter of the dead time generator, in
+/// ticks of its clock.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeadTimeError;
+
+/// What a brake does to an output of the operator
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultAction {
+    /// Keep generating the PWM signal.
+    Nothing = 0,
+    /// Force the output low.
+    Low     = 1,
+    /// Force the output high.
+    High    = 2,
+    /// Toggle the output.
+    Toggle  = 3,
+}
+
+/// Configuration of the brakes of an operator
+///
+/// A brake forces the outputs of the operator on the fault events it is
+/// configured for:
+/// * A cycle-by-cycle brake holds the outputs while the fault event is active,
+///   and releases them when the timer of the operator next equals zero after
+///   it ended.
+/// * A one-shot brake holds the outputs until it's rearmed with
+///   [`PwmPin::clear_one_shot_brake`].
+///
+/// With [`DeadTimeCfg::new_ahc`] linked pins, braking both outputs
+/// [`FaultAction::Low`] turns the bridge off.
+#[derive(Copy, Clone)]
+pub struct BrakeConfig {
+    cycle_by_cycle: u8,
+    one_shot: u8,
+    cycle_by_cycle_actions: (FaultAction, FaultAction),
+    one_shot_actions: (FaultAction, FaultAction),
+}
+
+impl BrakeConfig {
+    /// A configuration without any brake, which forces both outputs low once
+    /// a brake is added.
+    pub const fn new() -> Self {
+        BrakeConfig {
+            cycle_by_cycle: 0,
+            one_shot: 0,
+            cycle_by_cycle_actions: (FaultAction::Low, FaultAction::Low),
+            one_shot_actions: (FaultAction::Low, FaultAction::Low),
+        }
+    }
+
+    /// Brake cycle by cycle on the given fault event, too
+    pub const fn with_cycle_by_cycle(self, event: FaultEvent) -> Self {
+        BrakeConfig {
+            cycle_by_cycle: self.cycle_by_cycle | 1 << event as u8,
+            ..self
+        }
+    }
+
+    /// Brake once on the given fault event, too
+    pub const fn with_one_shot(self, event: FaultEvent) -> Self {
+        BrakeConfig {
+            one_shot: self.one_shot | 1 << event as u8,
+            ..self
+        }
+    }
+
+    /// Set what the cycle-by-cycle brake does to the A and B outputs
+    pub const fn with_cycle_by_cycle_actions(self, a: FaultAction, b: FaultAction) -> Self {
+        BrakeConfig {
+            cycle_by_cycle_actions: (a, b),
+            ..self
+        }
+    }
+
+    /// Set what the one-shot brake does to the A and B outputs
+    pub const fn with_one_shot_actions(self, a: FaultAction, b: FaultAction) -> Self {
+        BrakeConfig {
+            one_shot_actions: (a, b),
+            ..self
+        }
+    }
+}
+
+impl Default for BrakeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The brakes that hold the outputs of an operator
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BrakeStatus {
+    /// The cycle-by-cycle brake is on.
+    pub cycle_by_cycle: bool,
+    /// The one-shot brake is on.
+    pub one_shot: bool,
+}
+
 /// A MCPWM operator
 ///
 /// The PWM Operator submodule has the following functions:
 /// * Generates a PWM signal pair, based on timing references obtained from the
 ///   corresponding PWM timer.
 /// * Each signal out of the PWM signal pair includes a specific pattern of dead
-///   time. (Not yet implemented)
+///   time, see [`Operator::with_linked_pins`] and [`Operator::set_deadtime`].
 /// * Superimposes a carrier on the PWM signal, if configured to do so. (Not yet
 ///   implemented)
-/// * Handles response under fault conditions. (Not yet implemented)
+/// * Handles response under fault conditions, see [`Operator::set_brake`].
 pub struct Operator<'d, const OP: u8, PWM> {
     phantom: PhantomData<&'d PWM>,
+    clock: HertzU32,
     _guard: PeripheralGuard,
 }
 
 impl<'d, const OP: u8, PWM: PwmPeripheral> Operator<'d, OP, PWM> {
-    pub(super) fn new() -> Self {
+    pub(super) fn new(clock: HertzU32) -> Self {
         let guard = PeripheralGuard::new(PWM::peripheral());
 
         // Side note:
@@ -186,10 +322,63 @@ impl<'d, const OP: u8, PWM: PwmPeripheral> Operator<'d, OP, PWM> {
         // written.
         Operator {
             phantom: PhantomData,
+            clock,
             _guard: guard,
         }
     }
 
+    /// Set the rising and falling edge delays of the dead time generator, in
+    /// nanoseconds
+    ///
+    /// The delays are rounded up to whole ticks of the dead time generator
+    /// clock, PWM_clk unless [`DeadTimeCfg::select_clock`] selects PT_clk.
+    /// They apply to pins linked with [`Self::with_linked_pins`], as
+    /// configured by its [`DeadTimeCfg`]. [`LinkedPins::set_deadtime`]
+    /// changes them once the pins are linked, which can be done while the
+    /// timer runs.
+    pub fn set_deadtime(&mut self, rising_edge_ns: u32, falling_edge_ns: u32) -> Result<(), DeadTimeError> {
+        set_deadtime::<PWM, OP>(self.clock, rising_edge_ns, falling_edge_ns)
+    }
+
+    /// Configure the brakes of the operator
+    ///
+    /// Fault events come from the [`FaultPin`](super::fault::FaultPin)s of
+    /// the same peripheral. Once the brakes are configured, the hardware
+    /// forces the outputs as soon as a fault event starts, even while the CPU
+    /// is busy.
+    pub fn set_brake(&mut self, config: BrakeConfig) {
+        // SAFETY:
+        // We only write to our fault handler registers
+        let ch = unsafe { &*PWM::block() }.ch(OP as usize);
+        #[cfg(esp32s3)]
+        let (cfg0, cfg1) = (ch.tz_cfg0(), ch.tz_cfg1());
+        #[cfg(not(esp32s3))]
+        let (cfg0, cfg1) = (ch.fh_cfg0(), ch.fh_cfg1());
+
+        let (cbc, ost) = (config.cycle_by_cycle, config.one_shot);
+        let (cbc_a, cbc_b) = config.cycle_by_cycle_actions;
+        let (ost_a, ost_b) = config.one_shot_actions;
+        cfg0.write(|w| unsafe {
+            w.f0_cbc().bit(cbc & 1 != 0);
+            w.f1_cbc().bit(cbc & 2 != 0);
+            w.f2_cbc().bit(cbc & 4 != 0);
+            w.f0_ost().bit(ost & 1 != 0);
+            w.f1_ost().bit(ost & 2 != 0);
+            w.f2_ost().bit(ost & 4 != 0);
+            // The same action whichever way the timer counts.
+            w.a_cbc_u().bits(cbc_a as u8);
+            w.a_cbc_d().bits(cbc_a as u8);
+            w.b_cbc_u().bits(cbc_b as u8);
+            w.b_cbc_d().bits(cbc_b as u8);
+            w.a_ost_u().bits(ost_a as u8);
+            w.a_ost_d().bits(ost_a as u8);
+            w.b_ost_u().bits(ost_b as u8);
+            w.b_ost_d().bits(ost_b as u8)
+        });
+        // Release cycle-by-cycle brakes when the timer equals zero.
+        cfg1.modify(|_, w| unsafe { w.cbcpulse().bits(0b01) });
+    }
+
     /// Select a [`Timer`] to be the timing reference for this operator
     ///
     /// ### Note:
@@ -250,7 +439,7 @@ impl<'d, const OP: u8, PWM: PwmPeripheral> Operator<'d, OP, PWM> {
         config_b: PwmPinConfig<false>,
         config_dt: DeadTimeCfg,
     ) -> LinkedPins<'d, PWM, OP> {
-        LinkedPins::new(pin_a, config_a, pin_b, config_b, config_dt)
+        LinkedPins::new(self.clock, pin_a, config_a, pin_b, config_b, config_dt)
     }
 }
 
@@ -394,6 +583,85 @@ impl<'d, PWM: PwmPeripheral, const OP: u8, const IS_A: bool> PwmPin<'d, PWM, OP,
         }
     }
 
+    /// Whether a timestamp written with [`Self::set_timestamp`] waits for its
+    /// [`PwmUpdateMethod`]
+    pub fn is_update_pending(&self) -> bool {
+        // SAFETY:
+        // We only read from our GENx_STMP_CFG register
+        let ch = unsafe { Self::ch() };
+
+        #[cfg(esp32s3)]
+        let cfg = ch.cmpr_cfg().read();
+        #[cfg(any(esp32, esp32c6, esp32h2))]
+        let cfg = ch.gen_stmp_cfg().read();
+
+        if IS_A {
+            cfg.a_shdw_full().bit_is_set()
+        } else {
+            cfg.b_shdw_full().bit_is_set()
+        }
+    }
+
+    /// Apply the pending timestamps of the operator right away
+    ///
+    /// This updates the timestamps of both pins of the operator, whatever
+    /// their [`PwmUpdateMethod`]. A timestamp below the current counter
+    /// value doesn't take its action until the next PWM period, which can
+    /// make the running one a glitch.
+    pub fn force_update(&mut self) {
+        // SAFETY:
+        // We only toggle our OPx_FORCE_UP bit
+        let block = unsafe { &*PWM::block() };
+        lock(&UPDATE_CFG_LOCK, || {
+            block.update_cfg().modify(|r, w| match OP {
+                0 => w.op0_force_up().bit(!r.op0_force_up().bit()),
+                1 => w.op1_force_up().bit(!r.op1_force_up().bit()),
+                2 => w.op2_force_up().bit(!r.op2_force_up().bit()),
+                _ => {
+                    unreachable!()
+                }
+            })
+        });
+    }
+
+    /// Get the brakes that hold the outputs of the operator.
+    ///
+    /// See [`Operator::set_brake`].
+    pub fn brake_status(&self) -> BrakeStatus {
+        // SAFETY:
+        // We only read from our fault handler status register
+        let ch = unsafe { Self::ch() };
+        #[cfg(esp32s3)]
+        let status = ch.tz_status().read();
+        #[cfg(not(esp32s3))]
+        let status = ch.fh_status().read();
+
+        BrakeStatus {
+            cycle_by_cycle: status.cbc_on().bit_is_set(),
+            one_shot: status.ost_on().bit_is_set(),
+        }
+    }
+
+    /// Rearm the one-shot brake of the operator, which releases the outputs.
+    ///
+    /// The outputs follow the PWM signal again from the next timing event
+    /// on. If a fault event that brakes once is still active, the brake
+    /// engages again right away, see
+    /// [`FaultPin::wait_for_fault_end`](super::fault::FaultPin::wait_for_fault_end).
+    pub fn clear_one_shot_brake(&mut self) {
+        // SAFETY:
+        // We only write to our fault handler registers
+        let ch = unsafe { Self::ch() };
+        #[cfg(esp32s3)]
+        let cfg1 = ch.tz_cfg1();
+        #[cfg(not(esp32s3))]
+        let cfg1 = ch.fh_cfg1();
+
+        // The brake is cleared on the rising edge of the bit.
+        cfg1.modify(|_, w| w.clr_ost().set_bit());
+        cfg1.modify(|_, w| w.clr_ost().clear_bit());
+    }
+
     /// Get the period of the timer.
     pub fn period(&self) -> u16 {
         // SAFETY:
@@ -493,10 +761,12 @@ impl<PWM: PwmPeripheral, const OP: u8, const IS_A: bool> embedded_hal::pwm::SetD
 pub struct LinkedPins<'d, PWM, const OP: u8> {
     pin_a: PwmPin<'d, PWM, OP, true>,
     pin_b: PwmPin<'d, PWM, OP, false>,
+    clock: HertzU32,
 }
 
 impl<'d, PWM: PwmPeripheral, const OP: u8> LinkedPins<'d, PWM, OP> {
     fn new(
+        clock: HertzU32,
         pin_a: impl Peripheral<P = impl PeripheralOutput> + 'd,
         config_a: PwmPinConfig<true>,
         pin_b: impl Peripheral<P = impl PeripheralOutput> + 'd,
@@ -513,7 +783,11 @@ impl<'d, PWM: PwmPeripheral, const OP: u8> LinkedPins<'d, PWM, OP> {
         let pin_a = PwmPin::new(pin_a, config_a);
         let pin_b = PwmPin::new(pin_b, config_b);
 
-        LinkedPins { pin_a, pin_b }
+        LinkedPins {
+            pin_a,
+            pin_b,
+            clock,
+        }
     }
 
     /// Configure what actions should be taken on timing events
@@ -544,7 +818,28 @@ impl<'d, PWM: PwmPeripheral, const OP: u8> LinkedPins<'d, PWM, OP> {
     /// The written value will take effect according to the set
     /// [`PwmUpdateMethod`].
     pub fn set_timestamp_b(&mut self, value: u16) {
-        self.pin_a.set_timestamp(value)
+        self.pin_b.set_timestamp(value)
+    }
+
+    /// Apply the pending timestamps of both pins right away
+    ///
+    /// See [`PwmPin::force_update`].
+    pub fn force_update(&mut self) {
+        self.pin_a.force_update()
+    }
+
+    /// Get the brakes that hold the outputs of the operator.
+    ///
+    /// See [`Operator::set_brake`].
+    pub fn brake_status(&self) -> BrakeStatus {
+        self.pin_a.brake_status()
+    }
+
+    /// Rearm the one-shot brake of the operator, which releases the outputs.
+    ///
+    /// See [`PwmPin::clear_one_shot_brake`].
+    pub fn clear_one_shot_brake(&mut self) {
+        self.pin_a.clear_one_shot_brake()
     }
 
     /// Configure the deadtime generator
@@ -556,6 +851,19 @@ impl<'d, PWM: PwmPeripheral, const OP: u8> LinkedPins<'d, PWM, OP> {
         dt_cfg.write(|w| unsafe { w.bits(config.cfg_reg) });
     }
 
+    /// Set the deadtime generator rising and falling edge delays, in
+    /// nanoseconds
+    ///
+    /// See [`Operator::set_deadtime`] for how the delays are converted. On a
+    /// running timer, both delays take effect together when the timer next
+    /// equals zero, so neither is changed while it is delaying an edge. With
+    /// [`DeadTimeCfg::new_ahc`] and [`DeadTimeCfg::new_alc`], the delays only
+    /// ever postpone the edge that turns an output on, so changing them can't
+    /// make the outputs overlap.
+    pub fn set_deadtime(&mut self, rising_edge_ns: u32, falling_edge_ns: u32) -> Result<(), DeadTimeError> {
+        set_deadtime::<PWM, OP>(self.clock, rising_edge_ns, falling_edge_ns)
+    }
+
     /// Set the deadtime generator rising edge delay
     pub fn set_rising_edge_deadtime(&mut self, dead_time: u16) {
         #[cfg(esp32s3)]
@@ -579,6 +887,103 @@ impl<'d, PWM: PwmPeripheral, const OP: u8> LinkedPins<'d, PWM, OP> {
     }
 }
 
+/// Writes the dead time generator delays of operator `OP`, in nanoseconds.
+fn set_deadtime<PWM: PwmPeripheral, const OP: u8>(
+    clock: HertzU32,
+    rising_edge_ns: u32,
+    falling_edge_ns: u32,
+) -> Result<(), DeadTimeError> {
+    // SAFETY:
+    // We only write to our dead time generator registers
+    let block = unsafe { &*PWM::block() };
+    let ch = block.ch(OP as usize);
+    #[cfg(esp32s3)]
+    let (dt_cfg, dt_red, dt_fed) = (ch.db_cfg(), ch.db_red_cfg(), ch.db_fed_cfg());
+    #[cfg(not(esp32s3))]
+    let (dt_cfg, dt_red, dt_fed) = (ch.dt_cfg(), ch.dt_red_cfg(), ch.dt_fed_cfg());
+
+    let timersel = block.operator_timersel().read();
+    let tim = match OP {
+        0 => timersel.operator0_timersel().bits(),
+        1 => timersel.operator1_timersel().bits(),
+        2 => timersel.operator2_timersel().bits(),
+        _ => unreachable!(),
+    };
+    let tmr = block.timer(tim as usize);
+
+    let mut clock = clock.raw();
+    if dt_cfg.read().clk_sel().bit_is_set() {
+        // PT_clk
+        clock /= tmr.cfg0().read().prescale().bits() as u32 + 1;
+    }
+    let red = deadtime_ticks(rising_edge_ns, clock)?;
+    let fed = deadtime_ticks(falling_edge_ns, clock)?;
+
+    // A stopped timer would only take the shadowed values after it starts
+    // and first reaches zero, too late for the first period.
+    let running = tmr.cfg1().read().mod_().bits() != 0;
+    let upmethod = if running { UPMETHOD_TEZ } else { 0 };
+    dt_cfg.modify(|_, w| unsafe {
+        w.red_upmethod().bits(upmethod);
+        w.fed_upmethod().bits(upmethod)
+    });
+    dt_red.write(|w| unsafe { w.red().bits(red) });
+    dt_fed.write(|w| unsafe { w.fed().bits(fed) });
+
+    Ok(())
+}
+
+/// Shadow register update when the timer equals zero.
+const UPMETHOD_TEZ: u8 = 0b0001;
+
+/// Scale the timestamps of the operators that use the given timer to a new
+/// PWM period, for [`Timer::set_frequency`](super::timer::Timer::set_frequency).
+pub(super) fn rescale_timestamps<PWM: PwmPeripheral>(timer: u8, old_cycle: u32, new_cycle: u32) {
+    if old_cycle == 0 {
+        return;
+    }
+
+    // SAFETY:
+    // The timestamps keep their duty cycles, which is all their pins rely on
+    let block = unsafe { &*PWM::block() };
+    let tim_select = block.operator_timersel().read();
+    let timers = [
+        tim_select.operator0_timersel().bits(),
+        tim_select.operator1_timersel().bits(),
+        tim_select.operator2_timersel().bits(),
+    ];
+
+    let rescale = |timestamp: u16| {
+        let scaled = (timestamp as u64 * new_cycle as u64 + old_cycle as u64 / 2) / old_cycle as u64;
+        scaled.min(u16::MAX as u64) as u16
+    };
+    for (op, _) in timers.iter().enumerate().filter(|(_, &tim)| tim == timer) {
+        let ch = block.ch(op);
+
+        #[cfg(esp32s3)]
+        {
+            let a = rescale(ch.cmpr_value0().read().a().bits());
+            let b = rescale(ch.cmpr_value1().read().b().bits());
+            ch.cmpr_value0().write(|w| unsafe { w.a().bits(a) });
+            ch.cmpr_value1().write(|w| unsafe { w.b().bits(b) });
+        }
+
+        #[cfg(any(esp32, esp32c6, esp32h2))]
+        {
+            let a = rescale(ch.gen_tstmp_a().read().a().bits());
+            let b = rescale(ch.gen_tstmp_b().read().b().bits());
+            ch.gen_tstmp_a().write(|w| unsafe { w.a().bits(a) });
+            ch.gen_tstmp_b().write(|w| unsafe { w.b().bits(b) });
+        }
+    }
+}
+
+/// Converts nanoseconds to dead time generator ticks, rounding up.
+fn deadtime_ticks(ns: u32, clock: u32) -> Result<u16, DeadTimeError> {
+    let ticks = (ns as u64 * clock as u64).div_ceil(1_000_000_000);
+    u16::try_from(ticks).map_err(|_| DeadTimeError)
+}
+
 /// An action the operator applies to an output
 #[non_exhaustive]
 #[repr(u32)]
@@ -691,7 +1096,10 @@ impl<const IS_A: bool> PwmActions<IS_A> {
 
 /// Settings for when [`PwmPin::set_timestamp`] takes effect
 ///
-/// Multiple syncing triggers can be set.
+/// Multiple syncing triggers can be set. A timestamp that is applied
+/// immediately and is below the current counter value misses its event in the
+/// running PWM period, so only [`PwmUpdateMethod::SYNC_IMMEDIATLY`] can make a
+/// PWM period a glitch.
 pub struct PwmUpdateMethod(u8);
 
 impl PwmUpdateMethod {
@@ -701,6 +1109,8 @@ impl PwmUpdateMethod {
     pub const SYNC_ON_ZERO: Self = Self::empty().sync_on_timer_equals_zero();
     /// New timestamp will be applied when timer is equal to period
     pub const SYNC_ON_PERIOD: Self = Self::empty().sync_on_timer_equals_period();
+    /// New timestamp will be applied on a sync event of the timer
+    pub const SYNC_ON_TIMER_SYNC: Self = Self::empty().sync_on_timer_sync();
 
     /// `PwmUpdateMethod` with no sync triggers.
     /// Corresponds to syncing immediately
@@ -719,4 +1129,11 @@ impl PwmUpdateMethod {
         self.0 |= 0b0010;
         self
     }
+
+    /// Enable syncing new timestamp values on a sync event of the timer, see
+    /// [`Timer::set_sync`](super::timer::Timer::set_sync)
+    pub const fn sync_on_timer_sync(mut self) -> Self {
+        self.0 |= 0b0100;
+        self
+    }
 }
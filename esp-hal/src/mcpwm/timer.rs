@@ -3,15 +3,61 @@
 //! ## Overview
 //! The `timer` module provides an interface to configure and use timers for
 //! generating `PWM` signals used in motor control and other applications.
+//!
+//! ## Synchronization
+//! On a sync event, a timer reloads its counter with the phase of its
+//! [`SyncConfig`]. Sync events come from [`Timer::sync_now`], from the sync
+//! output of a timer of the same peripheral or from a GPIO connected to a
+//! [`SyncInput`].
+//!
+//! Timers that take the sync output of the same timer stay phase-aligned with
+//! it. For example, the three phases of a motor, 120° apart:
+//!
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::mcpwm::{timer::{CounterDirection, PwmWorkingMode, SyncConfig, SyncOut, SyncSource}, McPwm, PeripheralClockConfig};
+//! let clock_cfg = PeripheralClockConfig::with_frequency(10.MHz())?;
+//! let mut mcpwm = McPwm::new(peripherals.MCPWM0, clock_cfg);
+//! let timer_clock_cfg =
+//!     clock_cfg.timer_clock_with_frequency(499, PwmWorkingMode::UpDown, 10.kHz())?;
+//!
+//! // Timer 0 only passes on its software sync events.
+//! mcpwm.timer0.set_sync_out(SyncOut::Software);
+//! mcpwm.timer1.set_sync(
+//!     SyncConfig::new()
+//!         .with_source(SyncSource::Timer0)
+//!         .with_phase(333, CounterDirection::Decreasing),
+//! );
+//! mcpwm.timer2.set_sync(
+//!     SyncConfig::new()
+//!         .with_source(SyncSource::Timer0)
+//!         .with_phase(333, CounterDirection::Increasing),
+//! );
+//!
+//! mcpwm.timer0.start(timer_clock_cfg);
+//! mcpwm.timer1.start(timer_clock_cfg);
+//! mcpwm.timer2.start(timer_clock_cfg);
+//! // Align all three timers at once.
+//! mcpwm.timer0.sync_now();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SyncConfig`]: crate::mcpwm::timer::SyncConfig
+//! [`Timer::sync_now`]: crate::mcpwm::timer::Timer::sync_now
+//! [`SyncInput`]: crate::mcpwm::timer::SyncInput
 
 use core::marker::PhantomData;
 
 use fugit::HertzU32;
 
-use super::PeripheralGuard;
+use super::{PeripheralGuard, UPDATE_CFG_LOCK};
 use crate::{
-    mcpwm::{FrequencyError, PeripheralClockConfig, PwmPeripheral},
+    gpio::interconnect::PeripheralInput,
+    mcpwm::{capture::Edge, FrequencyError, PeripheralClockConfig, PwmPeripheral},
     pac,
+    peripheral::Peripheral,
+    sync::lock,
 };
 
 /// A MCPWM timer
@@ -21,14 +67,18 @@ use crate::{
 /// [`Operator`](super::operator::Operator) of that peripheral
 pub struct Timer<const TIM: u8, PWM> {
     pub(super) phantom: PhantomData<PWM>,
+    clock: HertzU32,
+    mode: Option<PwmWorkingMode>,
     _guard: PeripheralGuard,
 }
 
 impl<const TIM: u8, PWM: PwmPeripheral> Timer<TIM, PWM> {
-    pub(super) fn new() -> Self {
+    pub(super) fn new(clock: HertzU32) -> Self {
         let guard = PeripheralGuard::new(PWM::peripheral());
         Timer {
             phantom: PhantomData,
+            clock,
+            mode: None,
             _guard: guard,
         }
     }
@@ -36,26 +86,24 @@ impl<const TIM: u8, PWM: PwmPeripheral> Timer<TIM, PWM> {
     /// Apply the given timer configuration.
     ///
     /// ### Note:
-    /// The prescaler and period configuration will be applied immediately by
-    /// default and before setting the [`PwmWorkingMode`].
-    /// If the timer is already running you might want to call [`Timer::stop`]
-    /// and/or [`Timer::set_counter`] first
-    /// (if the new period is larger than the current counter value this will
-    /// cause weird behavior).
-    ///
-    /// If configured via [`TimerClockConfig::with_period_updating_method`],
-    /// another behavior can be applied. Currently, only
-    /// [`PeriodUpdatingMethod::Immediately`]
-    /// and [`PeriodUpdatingMethod::TimerEqualsZero`] are useful as the sync
-    /// method is not yet implemented.
-    ///
-    /// The hardware supports writing these settings in sync with certain timer
-    /// events but this HAL does not expose these for now.
+    /// The prescaler and period configuration are applied before setting the
+    /// [`PwmWorkingMode`]. The period of a stopped timer is applied
+    /// immediately, the period of a running timer according to the
+    /// [`PeriodUpdatingMethod`] of the configuration, see
+    /// [`Timer::set_period`]. The prescaler is always applied immediately.
     pub fn start(&mut self, timer_config: TimerClockConfig) {
-        // write prescaler and period with immediate update method
+        let running = self.cfg1().read().mod_().bits() != 0;
+        let method = if running {
+            timer_config.period_updating_method
+        } else {
+            PeriodUpdatingMethod::Immediately
+        };
         self.cfg0().write(|w| unsafe {
             w.prescale().bits(timer_config.prescaler);
             w.period().bits(timer_config.period);
+            w.period_upmethod().bits(method as u8)
+        });
+        self.cfg0().modify(|_, w| unsafe {
             w.period_upmethod()
                 .bits(timer_config.period_updating_method as u8)
         });
@@ -65,6 +113,100 @@ impl<const TIM: u8, PWM: PwmPeripheral> Timer<TIM, PWM> {
             w.start().bits(2);
             w.mod_().bits(timer_config.mode as u8)
         });
+        self.mode = Some(timer_config.mode);
+    }
+
+    /// Write a new period
+    ///
+    /// The period takes effect according to the [`PeriodUpdatingMethod`] the
+    /// timer was started with. [`PeriodUpdatingMethod::TimerEqualsZero`], the
+    /// default, changes it between two PWM periods.
+    ///
+    /// With [`PeriodUpdatingMethod::Immediately`], a period shorter than the
+    /// current counter value can't end the running PWM period: an increasing
+    /// counter runs on to `u16::MAX` and wraps around to zero, which makes that
+    /// PWM period up to 65536 ticks long.
+    pub fn set_period(&mut self, period: u16) {
+        self.cfg0()
+            .modify(|_, w| unsafe { w.period().bits(period) });
+    }
+
+    /// Change the frequency of the PWM signal, keeping the duty cycles of the
+    /// operators that use this timer
+    ///
+    /// The timer keeps its prescaler if the period fits in 16 bits with it,
+    /// and otherwise takes the smallest prescaler that fits. A new prescaler
+    /// applies immediately, which stretches or shrinks the rest of the running
+    /// PWM period, but keeps its duty cycle.
+    ///
+    /// The new period and the rescaled timestamps take effect together, each
+    /// according to its update method. With [`PeriodUpdatingMethod::TimerEqualsZero`]
+    /// and [`PwmUpdateMethod::SYNC_ON_ZERO`], the defaults, no PWM period mixes
+    /// the old and new values.
+    ///
+    /// Returns [`FrequencyError`] if the timer wasn't started, or if the
+    /// frequency can't be reached with a 16-bit period and an 8-bit
+    /// prescaler.
+    ///
+    /// [`PwmUpdateMethod::SYNC_ON_ZERO`]: super::operator::PwmUpdateMethod::SYNC_ON_ZERO
+    pub fn set_frequency(&mut self, frequency: HertzU32) -> Result<(), FrequencyError> {
+        let mode = self.mode.ok_or(FrequencyError)?;
+        if frequency.raw() == 0 {
+            return Err(FrequencyError);
+        }
+        let clock_cycles = self.clock.raw() / frequency.raw();
+
+        let max_cycle_period = match mode {
+            PwmWorkingMode::Increase | PwmWorkingMode::Decrease => 1 << 16,
+            PwmWorkingMode::UpDown => 2 * u16::MAX as u32,
+        };
+        let cfg0 = self.cfg0().read();
+        let (old_prescaler, old_period) = (cfg0.prescale().bits(), cfg0.period().bits());
+        let min_prescaler = clock_cycles.div_ceil(max_cycle_period).saturating_sub(1);
+        let prescaler = if old_prescaler as u32 >= min_prescaler
+            && clock_cycles / (old_prescaler as u32 + 1) >= 2
+        {
+            old_prescaler as u32
+        } else {
+            min_prescaler
+        };
+        if prescaler > u8::MAX as u32 {
+            return Err(FrequencyError);
+        }
+
+        let cycle_period = clock_cycles / (prescaler + 1);
+        let period = match mode {
+            PwmWorkingMode::Increase | PwmWorkingMode::Decrease => cycle_period.checked_sub(1),
+            PwmWorkingMode::UpDown => Some(cycle_period / 2),
+        };
+        let period = match period {
+            Some(period) if period > 0 => period as u16,
+            _ => return Err(FrequencyError),
+        };
+        let old_cycle_period = cycle_period_of(mode, old_period);
+        let new_cycle_period = cycle_period_of(mode, period);
+
+        // SAFETY:
+        // We only write to our CFG0 register, and to the timestamps of the
+        // operators that use this timer, which we only rescale
+        let block = unsafe { &*PWM::block() };
+        lock(&UPDATE_CFG_LOCK, || {
+            // Hold back all updates until the period and the timestamps are
+            // written, so that they land together.
+            block
+                .update_cfg()
+                .modify(|_, w| w.global_up_en().clear_bit());
+            self.cfg0().modify(|_, w| unsafe {
+                w.prescale().bits(prescaler as u8);
+                w.period().bits(period)
+            });
+            super::operator::rescale_timestamps::<PWM>(TIM, old_cycle_period, new_cycle_period);
+            block
+                .update_cfg()
+                .modify(|_, w| w.global_up_en().set_bit());
+        });
+
+        Ok(())
     }
 
     /// Stop the timer in its current state
@@ -74,17 +216,73 @@ impl<const TIM: u8, PWM: PwmPeripheral> Timer<TIM, PWM> {
     }
 
     /// Set the timer counter to the provided value
+    ///
+    /// This is a software sync event with the given phase, which replaces the
+    /// phase set with [`Timer::set_sync`].
     pub fn set_counter(&mut self, phase: u16, direction: CounterDirection) {
         // SAFETY:
         // We only write to our TIMERx_SYNC register
         let tmr = unsafe { Self::tmr() };
-        let sw = tmr.sync().read().sw().bit_is_set();
-        tmr.sync().write(|w| {
+        tmr.sync().modify(|r, w| {
             w.phase_direction().bit(direction as u8 != 0);
             unsafe {
                 w.phase().bits(phase);
             }
-            w.sw().bit(!sw)
+            w.synci_en().set_bit();
+            w.sw().bit(!r.sw().bit())
+        });
+    }
+
+    /// Configure how the timer reloads its counter on sync events
+    ///
+    /// The timer always reloads on [`Timer::sync_now`], and on the sync events
+    /// of the source of the config, if any.
+    pub fn set_sync(&mut self, config: SyncConfig) {
+        // SAFETY:
+        // We only write to our TIMERx_SYNC register and our field of the
+        // TIMER_SYNCI_CFG register
+        let block = unsafe { &*PWM::block() };
+        let source = config.source.map_or(0, |source| source as u8);
+        block.timer_synci_cfg().modify(|_, w| match TIM {
+            0 => unsafe { w.timer0_syncisel().bits(source) },
+            1 => unsafe { w.timer1_syncisel().bits(source) },
+            2 => unsafe { w.timer2_syncisel().bits(source) },
+            _ => {
+                unreachable!()
+            }
+        });
+
+        unsafe { Self::tmr() }.sync().modify(|_, w| {
+            w.phase_direction().bit(config.direction as u8 != 0);
+            unsafe {
+                w.phase().bits(config.phase);
+            }
+            // Enables the software sync, too.
+            w.synci_en().set_bit()
+        });
+    }
+
+    /// Select the events the timer passes on to the timers that take its sync
+    /// output
+    pub fn set_sync_out(&mut self, sync_out: SyncOut) {
+        // SAFETY:
+        // We only write to our TIMERx_SYNC register
+        unsafe { Self::tmr() }
+            .sync()
+            .modify(|_, w| unsafe { w.synco_sel().bits(sync_out as u8) });
+    }
+
+    /// Trigger a software sync event
+    ///
+    /// The timer reloads its counter with the phase set with
+    /// [`Timer::set_sync`], and raises its sync output whatever
+    /// [`Timer::set_sync_out`] selects.
+    pub fn sync_now(&mut self) {
+        // SAFETY:
+        // We only write to our TIMERx_SYNC register
+        unsafe { Self::tmr() }.sync().modify(|r, w| {
+            w.synci_en().set_bit();
+            w.sw().bit(!r.sw().bit())
         });
     }
 
@@ -114,6 +312,162 @@ impl<const TIM: u8, PWM: PwmPeripheral> Timer<TIM, PWM> {
     }
 }
 
+/// The number of timer ticks of a PWM period
+fn cycle_period_of(mode: PwmWorkingMode, period: u16) -> u32 {
+    match mode {
+        PwmWorkingMode::Increase | PwmWorkingMode::Decrease => period as u32 + 1,
+        // The reference manual seems to provide an incorrect formula for UpDown
+        PwmWorkingMode::UpDown => period as u32 * 2,
+    }
+}
+
+/// Sync configuration of a MCPWM timer, see [`Timer::set_sync`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncConfig {
+    source: Option<SyncSource>,
+    phase: u16,
+    direction: CounterDirection,
+}
+
+impl SyncConfig {
+    /// A configuration that only reloads on software sync events, to zero
+    /// and increasing.
+    pub const fn new() -> Self {
+        SyncConfig {
+            source: None,
+            phase: 0,
+            direction: CounterDirection::Increasing,
+        }
+    }
+
+    /// Reload on the sync events of the given source, too
+    pub const fn with_source(self, source: SyncSource) -> Self {
+        SyncConfig {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    /// Set the value and direction the counter reloads with
+    ///
+    /// The direction only matters in [`PwmWorkingMode::UpDown`].
+    pub const fn with_phase(self, phase: u16, direction: CounterDirection) -> Self {
+        SyncConfig {
+            phase,
+            direction,
+            ..self
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A source of sync events for a MCPWM timer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncSource {
+    /// The sync output of timer 0, see [`Timer::set_sync_out`].
+    Timer0 = 1,
+    /// The sync output of timer 1.
+    Timer1 = 2,
+    /// The sync output of timer 2.
+    Timer2 = 3,
+    /// The GPIO of sync input 0, see [`SyncInput`].
+    Sync0  = 4,
+    /// The GPIO of sync input 1.
+    Sync1  = 5,
+    /// The GPIO of sync input 2.
+    Sync2  = 6,
+}
+
+/// The events a MCPWM timer raises its sync output on
+///
+/// Software sync events always raise it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncOut {
+    /// The sync events of the source of the timer, passed on.
+    SyncIn            = 0,
+    /// The timer equals zero.
+    TimerEqualsZero   = 1,
+    /// The timer equals the period.
+    TimerEqualsPeriod = 2,
+    /// Only software sync events.
+    Software          = 3,
+}
+
+/// A sync input of a MCPWM peripheral
+///
+/// Timers take its sync events with [`SyncSource::Sync0`] to
+/// [`SyncSource::Sync2`] once it's connected to a pin with
+/// [`Self::with_pin`].
+pub struct SyncInput<'d, const S: u8, PWM> {
+    phantom: PhantomData<&'d PWM>,
+    _guard: PeripheralGuard,
+}
+
+impl<'d, const S: u8, PWM: PwmPeripheral> SyncInput<'d, S, PWM> {
+    pub(super) fn new() -> Self {
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        SyncInput {
+            phantom: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    /// Raise a sync event on the given edge of the pin
+    pub fn with_pin(
+        self,
+        pin: impl Peripheral<P = impl PeripheralInput> + 'd,
+        edge: Edge,
+    ) -> SyncPin<'d, PWM, S> {
+        SyncPin::new(pin, edge)
+    }
+}
+
+/// A pin that raises sync events
+pub struct SyncPin<'d, PWM, const S: u8> {
+    phantom: PhantomData<&'d PWM>,
+    _guard: PeripheralGuard,
+}
+
+impl<'d, PWM: PwmPeripheral, const S: u8> SyncPin<'d, PWM, S> {
+    fn new(pin: impl Peripheral<P = impl PeripheralInput> + 'd, edge: Edge) -> Self {
+        crate::into_mapped_ref!(pin);
+        pin.enable_input(true);
+        PWM::sync_signal::<S>().connect_to(pin);
+
+        // The sync inputs raise their events on rising edges.
+        let invert = edge == Edge::Falling;
+        let block = unsafe { &*PWM::block() };
+        block.timer_synci_cfg().modify(|_, w| match S {
+            0 => w.external_synci0_invert().bit(invert),
+            1 => w.external_synci1_invert().bit(invert),
+            _ => w.external_synci2_invert().bit(invert),
+        });
+
+        let guard = PeripheralGuard::new(PWM::peripheral());
+        SyncPin {
+            phantom: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    /// The source to select in a [`SyncConfig`] for the events of this pin
+    pub fn source(&self) -> SyncSource {
+        match S {
+            0 => SyncSource::Sync0,
+            1 => SyncSource::Sync1,
+            _ => SyncSource::Sync2,
+        }
+    }
+}
+
 /// Clock configuration of a MCPWM timer
 ///
 /// Use [`PeripheralClockConfig::timer_clock_with_prescaler`](super::PeripheralClockConfig::timer_clock_with_prescaler) or
@@ -134,18 +488,14 @@ impl TimerClockConfig {
         mode: PwmWorkingMode,
         prescaler: u8,
     ) -> Self {
-        let cycle_period = match mode {
-            PwmWorkingMode::Increase | PwmWorkingMode::Decrease => period as u32 + 1,
-            // The reference manual seems to provide an incorrect formula for UpDown
-            PwmWorkingMode::UpDown => period as u32 * 2,
-        };
+        let cycle_period = cycle_period_of(mode, period);
         let frequency = clock.frequency / (prescaler as u32 + 1) / cycle_period;
 
         TimerClockConfig {
             frequency,
             prescaler,
             period,
-            period_updating_method: PeriodUpdatingMethod::Immediately,
+            period_updating_method: PeriodUpdatingMethod::TimerEqualsZero,
             mode,
         }
     }
@@ -156,11 +506,7 @@ impl TimerClockConfig {
         mode: PwmWorkingMode,
         target_freq: HertzU32,
     ) -> Result<Self, FrequencyError> {
-        let cycle_period = match mode {
-            PwmWorkingMode::Increase | PwmWorkingMode::Decrease => period as u32 + 1,
-            // The reference manual seems to provide an incorrect formula for UpDown
-            PwmWorkingMode::UpDown => period as u32 * 2,
-        };
+        let cycle_period = cycle_period_of(mode, period);
         let target_timer_frequency = target_freq
             .raw()
             .checked_mul(cycle_period)
@@ -178,12 +524,13 @@ impl TimerClockConfig {
             frequency,
             prescaler: prescaler as u8,
             period,
-            period_updating_method: PeriodUpdatingMethod::Immediately,
+            period_updating_method: PeriodUpdatingMethod::TimerEqualsZero,
             mode,
         })
     }
 
-    /// Set the method for updating the PWM period
+    /// Set the method for updating the PWM period,
+    /// [`PeriodUpdatingMethod::TimerEqualsZero`] by default
     pub fn with_period_updating_method(self, method: PeriodUpdatingMethod) -> Self {
         Self {
             period_updating_method: method,
@@ -208,7 +555,8 @@ pub enum PeriodUpdatingMethod {
     Immediately           = 0,
     /// The period is updated when the timer equals zero.
     TimerEqualsZero       = 1,
-    /// The period is updated on a synchronization event.
+    /// The period is updated on a synchronization event, see
+    /// [`Timer::set_sync`].
     Sync                  = 2,
     /// The period is updated either when the timer equals zero or on a
     /// synchronization event.
@@ -237,7 +585,8 @@ pub enum PwmWorkingMode {
 }
 
 /// The direction the timer counter is changing
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum CounterDirection {
     /// The timer counter is increasing
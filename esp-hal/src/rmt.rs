@@ -68,9 +68,9 @@
 //!             .with_idle_output_level(Level::Low)
 //!             .with_idle_output(false)
 //!             .with_carrier_modulation(false)
-//!             .with_carrier_high(1)
-//!             .with_carrier_low(1)
-//!             .with_carrier_level(Level::Low),
+//!             .with_carrier_frequency(38.kHz())
+//!             .with_carrier_duty_cycle(33)
+//!             .with_carrier_level(Level::High),
 //!     )?;
 //! # Ok(())
 //! # }
@@ -162,10 +162,10 @@
 //!     }
 //!
 //!     match transaction.wait() {
-//!         Ok(channel_res) => {
+//!         Ok((len, channel_res)) => {
 //!             channel = channel_res;
 //!             let mut total = 0usize;
-//!             for entry in &data[..data.len()] {
+//!             for entry in &data[..len] {
 //!                 if entry.length1() == 0 {
 //!                     break;
 //!                 }
@@ -177,7 +177,7 @@
 //!                 total += entry.length2() as usize;
 //!             }
 //!
-//!             for entry in &data[..data.len()] {
+//!             for entry in &data[..len] {
 //!                 if entry.length1() == 0 {
 //!                     break;
 //!                 }
@@ -228,6 +228,7 @@ use core::{
 
 use enumset::{EnumSet, EnumSetType};
 use fugit::HertzU32;
+use portable_atomic::{AtomicU8, Ordering};
 
 use crate::{
     asynch::AtomicWaker,
@@ -239,7 +240,9 @@ use crate::{
     peripheral::Peripheral,
     peripherals::{Interrupt, RMT},
     soc::constants,
+    sync::Locked,
     system::{self, GenericPeripheralGuard},
+    time::{Duration, Instant},
     Async,
     Blocking,
 };
@@ -259,6 +262,11 @@ pub enum Error {
     TransmissionError,
     /// No transmission end marker found
     EndMarkerMissing,
+    /// The chip doesn't support the requested operation
+    Unsupported,
+    /// The channel RAM wasn't refilled in time, and the transmission was
+    /// stopped
+    Underrun,
 }
 
 ///  Convenience trait to work with pulse codes.
@@ -329,11 +337,14 @@ pub struct TxChannelConfig {
     idle_output: bool,
     /// Enable carrier modulation
     carrier_modulation: bool,
-    /// Carrier high phase in ticks
-    carrier_high: u16,
-    /// Carrier low phase in ticks
-    carrier_low: u16,
-    /// Level of the carrier
+    /// Frequency of the carrier
+    ///
+    /// The carrier is generated from the frequency passed to [`Rmt::new`],
+    /// independently of the clock divider of the channel.
+    carrier_frequency: HertzU32,
+    /// Part of the carrier period during which the carrier is high, in percent
+    carrier_duty_cycle: u8,
+    /// Output level that the carrier is applied to
     carrier_level: Level,
 }
 
@@ -344,8 +355,8 @@ impl Default for TxChannelConfig {
             idle_output_level: Level::Low,
             idle_output: Default::default(),
             carrier_modulation: Default::default(),
-            carrier_high: Default::default(),
-            carrier_low: Default::default(),
+            carrier_frequency: HertzU32::kHz(38),
+            carrier_duty_cycle: 50,
             carrier_level: Level::Low,
         }
     }
@@ -369,6 +380,18 @@ pub struct RxChannelConfig {
     filter_threshold: u8,
     /// Idle threshold in ticks
     idle_threshold: u16,
+    /// Idle threshold as a duration, which takes precedence over
+    /// `idle_threshold`
+    ///
+    /// It is converted to ticks of the channel clock when the channel is
+    /// configured. A blocking reception also ends with no pulse codes when
+    /// nothing was received for this long after it started.
+    idle_timeout: Option<Duration>,
+    /// Number of RAM blocks the channel receives into
+    ///
+    /// Blocks beyond the first are taken from the channels that follow this
+    /// one, which can't be used while this channel is configured.
+    memsize: u8,
 }
 
 impl Default for RxChannelConfig {
@@ -381,6 +404,8 @@ impl Default for RxChannelConfig {
             carrier_level: Level::Low,
             filter_threshold: Default::default(),
             idle_threshold: Default::default(),
+            idle_timeout: None,
+            memsize: 1,
         }
     }
 }
@@ -485,7 +510,24 @@ fn configure_rx_channel<'d, P: PeripheralInput, T: RxChannelInternal>(
         }
     }
 
-    if config.idle_threshold > threshold {
+    let idle_threshold = match config.idle_timeout {
+        Some(timeout) => {
+            let tick_rate = chip_specific::clock_frequency().raw() as u64
+                / divider_value(config.clk_divider);
+            let ticks = timeout.to_micros() * tick_rate / 1_000_000;
+            if ticks == 0 || ticks > threshold as u64 {
+                return Err(Error::InvalidArgument);
+            }
+            ticks as u16
+        }
+        None => config.idle_threshold,
+    };
+
+    if idle_threshold > threshold {
+        return Err(Error::InvalidArgument);
+    }
+
+    if config.memsize == 0 || T::CHANNEL as usize + config.memsize as usize > NUM_CHANNELS {
         return Err(Error::InvalidArgument);
     }
 
@@ -501,15 +543,67 @@ fn configure_rx_channel<'d, P: PeripheralInput, T: RxChannelInternal>(
         config.carrier_level,
     );
     T::set_filter_threshold(config.filter_threshold);
-    T::set_idle_threshold(config.idle_threshold);
+    T::set_idle_threshold(idle_threshold);
+    T::set_memsize(config.memsize);
 
     Ok(T::new())
 }
 
+/// Counts the pulse codes of a received frame.
+///
+/// The receiver ends a frame with a code that has a zero length. That code is
+/// counted if its first half holds the last pulse of the frame.
+fn frame_length(codes: impl IntoIterator<Item = u32>) -> usize {
+    let mut count = 0;
+    for code in codes {
+        if code.length1() == 0 {
+            break;
+        }
+        count += 1;
+        if code.length2() == 0 {
+            break;
+        }
+    }
+    count
+}
+
+/// The clock divider of a channel, where 0 divides by 256.
+fn divider_value(divider: u8) -> u64 {
+    match divider {
+        0 => 256,
+        divider => divider as u64,
+    }
+}
+
+/// Splits a carrier period into its high and low phase, in cycles of the
+/// RMT clock.
+fn carrier_cycles(frequency: HertzU32, duty_cycle: u8) -> Result<(u16, u16), Error> {
+    if duty_cycle == 0 || duty_cycle >= 100 {
+        return Err(Error::InvalidArgument);
+    }
+
+    if frequency.raw() == 0 {
+        return Err(Error::UnreachableTargetFrequency);
+    }
+
+    let clock = chip_specific::clock_frequency().raw() as u64;
+    let period = (clock + frequency.raw() as u64 / 2) / frequency.raw() as u64;
+    let high = (period * duty_cycle as u64 + 50) / 100;
+    let low = period - high;
+
+    match (u16::try_from(high), u16::try_from(low)) {
+        (Ok(high), Ok(low)) if high > 0 && low > 0 => Ok((high, low)),
+        _ => Err(Error::UnreachableTargetFrequency),
+    }
+}
+
 fn configure_tx_channel<'d, P: PeripheralOutput, T: TxChannelInternal>(
     pin: impl Peripheral<P = P> + 'd,
     config: TxChannelConfig,
 ) -> Result<T, Error> {
+    let (carrier_high, carrier_low) =
+        carrier_cycles(config.carrier_frequency, config.carrier_duty_cycle)?;
+
     crate::into_mapped_ref!(pin);
     pin.set_to_push_pull_output();
     T::output_signal().connect_to(pin);
@@ -517,8 +611,8 @@ fn configure_tx_channel<'d, P: PeripheralOutput, T: TxChannelInternal>(
     T::set_divider(config.clk_divider);
     T::set_carrier(
         config.carrier_modulation,
-        config.carrier_high,
-        config.carrier_low,
+        carrier_high,
+        carrier_low,
         config.carrier_level,
     );
     T::set_idle_output(config.idle_output, config.idle_output_level);
@@ -631,26 +725,13 @@ where
             while !<C as TxChannelInternal>::is_threshold_set() {}
             <C as TxChannelInternal>::reset_threshold_set();
 
-            // re-fill TX RAM
-            let ram_index = (((self.index - constants::RMT_CHANNEL_RAM_SIZE)
-                / (constants::RMT_CHANNEL_RAM_SIZE / 2))
-                % 2)
-                * (constants::RMT_CHANNEL_RAM_SIZE / 2);
-
-            let ptr = (constants::RMT_RAM_START
-                + C::CHANNEL as usize * constants::RMT_CHANNEL_RAM_SIZE * 4
-                + ram_index * 4) as *mut u32;
-            for (idx, entry) in self.data[self.index..]
-                .iter()
-                .take(constants::RMT_CHANNEL_RAM_SIZE / 2)
-                .enumerate()
-            {
-                unsafe {
-                    ptr.add(idx).write_volatile(*entry);
+            match <C as TxChannelInternal>::refill(self.data, self.index) {
+                Ok(index) => self.index = index,
+                Err(error) => {
+                    <C as TxChannelInternal>::abort();
+                    return Err((error, self.channel));
                 }
             }
-
-            self.index += constants::RMT_CHANNEL_RAM_SIZE / 2;
         }
 
         loop {
@@ -673,12 +754,39 @@ where
     C: TxChannel,
 {
     channel: C,
+    /// The hardware stops on its own when the loop count is reached.
+    counted: bool,
 }
 
 impl<C> ContinuousTxTransaction<C>
 where
     C: TxChannel,
 {
+    /// Wait for the loop count passed to [`TxChannel::transmit_looped`] to be
+    /// reached.
+    ///
+    /// This returns [`Error::InvalidArgument`] right away if the transmission
+    /// loops forever.
+    pub fn wait(self) -> Result<C, (Error, C)> {
+        if !self.counted {
+            return Err((Error::InvalidArgument, self.channel));
+        }
+
+        loop {
+            if <C as TxChannelInternal>::is_error() {
+                return Err((Error::TransmissionError, self.channel));
+            }
+
+            if <C as TxChannelInternal>::is_loopcount_interrupt_set() {
+                break;
+            }
+        }
+
+        Self::reset_loop();
+
+        Ok(self.channel)
+    }
+
     /// Stop transaction when the current iteration ends.
     pub fn stop_next(self) -> Result<C, (Error, C)> {
         <C as TxChannelInternal>::set_continuous(false);
@@ -689,11 +797,13 @@ where
                 return Err((Error::TransmissionError, self.channel));
             }
 
-            if <C as TxChannelInternal>::is_done() {
+            if <C as TxChannelInternal>::is_done() || self.is_stopped_by_count() {
                 break;
             }
         }
 
+        Self::reset_loop();
+
         Ok(self.channel)
     }
 
@@ -716,11 +826,13 @@ where
                 return Err((Error::TransmissionError, self.channel));
             }
 
-            if <C as TxChannelInternal>::is_done() {
+            if <C as TxChannelInternal>::is_done() || self.is_stopped_by_count() {
                 break;
             }
         }
 
+        Self::reset_loop();
+
         Ok(self.channel)
     }
 
@@ -728,6 +840,17 @@ where
     pub fn is_loopcount_interrupt_set(&self) -> bool {
         <C as TxChannelInternal>::is_loopcount_interrupt_set()
     }
+
+    // A channel stopped by the loop count doesn't report the end of the
+    // transmission.
+    fn is_stopped_by_count(&self) -> bool {
+        self.counted && <C as TxChannelInternal>::is_loopcount_interrupt_set()
+    }
+
+    fn reset_loop() {
+        <C as TxChannelInternal>::set_loop_stop(false);
+        <C as TxChannelInternal>::update();
+    }
 }
 
 macro_rules! impl_tx_channel_creator {
@@ -1193,17 +1316,167 @@ where
     _guard: GenericPeripheralGuard<{ system::Peripheral::Rmt as u8 }>,
 }
 
+/// The TX channels of the enabled [`SyncGroup`].
+static SYNC_CHANNELS: AtomicU8 = AtomicU8::new(0);
+
+/// The channels of the enabled [`SyncGroup`] with a transmission waiting for
+/// the others, on chips that start them from software.
+#[cfg(esp32)]
+static ARMED_CHANNELS: AtomicU8 = AtomicU8::new(0);
+
+/// TX channels that start their transmissions at the same time.
+///
+/// While the group is enabled, a transmission on one of its channels only
+/// starts once every channel in the group has been given one, which includes
+/// looped transmissions that are restarted after stopping. Only a single group
+/// can be enabled at a time.
+///
+/// The channels start on the same clock edge, and their clock dividers are
+/// reset together. The ESP32 has no hardware for this, and starts the
+/// channels one after the other in a critical section instead, a few APB
+/// clock cycles apart.
+///
+/// The transmissions shouldn't need their RAM refilled by a blocking
+/// [`SingleShotTxTransaction::wait`], as the others aren't refilled meanwhile.
+///
+/// ```rust, no_run
+#[doc = crate::before_snippet!()]
+/// # use esp_hal::rmt::{PulseCode, Rmt, SyncGroup, TxChannel, TxChannelConfig, TxChannelCreator};
+/// # use esp_hal::gpio::Level;
+/// # use esp_hal::time::RateExtU32;
+#[cfg_attr(esp32h2, doc = "let freq = 32.MHz();")]
+#[cfg_attr(not(esp32h2), doc = "let freq = 80.MHz();")]
+/// let rmt = Rmt::new(peripherals.RMT, freq)?;
+/// let config = TxChannelConfig::default().with_clk_divider(1);
+///
+/// let first = rmt.channel0.configure(peripherals.GPIO4, config)?;
+/// let second = rmt.channel1.configure(peripherals.GPIO5, config)?;
+///
+/// let mut group = SyncGroup::new().with_channel(&first).with_channel(&second);
+/// group.enable()?;
+///
+/// let data = [
+///     PulseCode::new(Level::High, 200, Level::Low, 50),
+///     PulseCode::empty(),
+/// ];
+///
+/// // The first transmission waits for the second one.
+/// let first = first.transmit(&data)?;
+/// let second = second.transmit(&data)?;
+/// first.wait().unwrap();
+/// second.wait().unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncGroup {
+    channels: u8,
+    enabled: bool,
+}
+
+impl SyncGroup {
+    /// Creates an empty group.
+    pub const fn new() -> Self {
+        Self {
+            channels: 0,
+            enabled: false,
+        }
+    }
+
+    /// Adds a TX channel to the group.
+    #[must_use]
+    pub fn with_channel<C: TxChannelInternal>(mut self, _channel: &C) -> Self {
+        self.channels |= 1 << C::CHANNEL;
+        self
+    }
+
+    /// Starts holding back the transmissions of the channels until all of them
+    /// have one.
+    ///
+    /// Fails with [`Error::InvalidArgument`] if the group is empty, or if
+    /// another group is enabled.
+    pub fn enable(&mut self) -> Result<(), Error> {
+        if self.enabled {
+            return Ok(());
+        }
+
+        if self.channels == 0
+            || SYNC_CHANNELS
+                .compare_exchange(0, self.channels, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+        {
+            return Err(Error::InvalidArgument);
+        }
+
+        #[cfg(esp32)]
+        ARMED_CHANNELS.store(0, Ordering::Release);
+
+        chip_specific::enable_tx_sync(self.channels);
+        self.enabled = true;
+
+        Ok(())
+    }
+
+    /// Lets the channels start their transmissions on their own again.
+    pub fn disable(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        chip_specific::enable_tx_sync(0);
+        SYNC_CHANNELS.store(0, Ordering::Release);
+        self.enabled = false;
+    }
+
+    /// Resets the clock dividers of the channels together.
+    ///
+    /// Channels that keep transmitting in a loop can use this to line up their
+    /// ticks again. This has no effect on the ESP32.
+    pub fn resynchronize(&self) {
+        chip_specific::reset_dividers(self.channels);
+    }
+}
+
+impl Default for SyncGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SyncGroup {
+    fn drop(&mut self) {
+        self.disable();
+    }
+}
+
+/// The channels whose clock divider is reset when `channel` starts to
+/// transmit.
+fn divider_reset_mask(channel: u8) -> u8 {
+    let group = SYNC_CHANNELS.load(Ordering::Acquire);
+    if group & (1 << channel) != 0 {
+        group
+    } else {
+        1 << channel
+    }
+}
+
 /// Channel in TX mode
 pub trait TxChannel: TxChannelInternal {
     /// Start transmitting the given pulse code sequence.
     /// This returns a [`SingleShotTxTransaction`] which can be used to wait for
     /// the transaction to complete and get back the channel for further
     /// use.
+    ///
+    /// Sequences longer than the RMT RAM of the channel are written in halves
+    /// by [`SingleShotTxTransaction::wait`]. If it doesn't get to write a half
+    /// in time, e.g. because interrupts take too long, the transmission is
+    /// stopped and fails with [`Error::Underrun`].
     fn transmit(self, data: &[u32]) -> Result<SingleShotTxTransaction<'_, Self>, Error>
     where
         Self: Sized,
     {
-        let index = Self::send_raw(data, false, 0)?;
+        let index = Self::send_raw(data, false, 0, false)?;
         Ok(SingleShotTxTransaction {
             channel: self,
             index,
@@ -1222,6 +1495,15 @@ pub trait TxChannel: TxChannelInternal {
         self.transmit_continuously_with_loopcount(0, data)
     }
 
+    /// Enables or disables carrier modulation for the following
+    /// transmissions.
+    ///
+    /// The carrier keeps the frequency and duty cycle it was configured with.
+    fn set_carrier_modulation(&mut self, enable: bool) {
+        Self::enable_carrier(enable);
+        Self::update();
+    }
+
     /// Like [`Self::transmit_continuously`] but also sets a loop count.
     /// [`ContinuousTxTransaction`] can be used to check if the loop count is
     /// reached.
@@ -1237,8 +1519,60 @@ pub trait TxChannel: TxChannelInternal {
             return Err(Error::Overflow);
         }
 
-        let _index = Self::send_raw(data, true, loopcount)?;
-        Ok(ContinuousTxTransaction { channel: self })
+        let _index = Self::send_raw(data, true, loopcount, false)?;
+        Ok(ContinuousTxTransaction {
+            channel: self,
+            counted: false,
+        })
+    }
+
+    /// Transmit the given pulse code sequence `count` times, or until stopped
+    /// if `count` is `None`.
+    ///
+    /// Each iteration ends at the end marker of the sequence, and the hardware
+    /// starts the next one without involving the CPU. Use
+    /// [`ContinuousTxTransaction::wait`] to wait for `count` iterations, or
+    /// [`ContinuousTxTransaction::stop_next`] to stop at the end of the
+    /// current iteration.
+    ///
+    /// The length of sequence cannot exceed the size of the allocated RMT RAM,
+    /// and `count` must be between 1 and 1023.
+    #[cfg_attr(
+        any(esp32, esp32s2, esp32c3),
+        doc = "\n\nThis chip can't stop the transmission after a number of iterations, passing a `count` returns [`Error::Unsupported`]."
+    )]
+    fn transmit_looped(
+        self,
+        data: &[u32],
+        count: Option<u16>,
+    ) -> Result<ContinuousTxTransaction<Self>, Error>
+    where
+        Self: Sized,
+    {
+        if data.len() > constants::RMT_CHANNEL_RAM_SIZE {
+            return Err(Error::Overflow);
+        }
+
+        if !data
+            .iter()
+            .any(|code| code.length1() == 0 || code.length2() == 0)
+        {
+            return Err(Error::EndMarkerMissing);
+        }
+
+        let counted = count.is_some();
+        let count = match count {
+            None => 0,
+            Some(_) if cfg!(any(esp32, esp32s2, esp32c3)) => return Err(Error::Unsupported),
+            Some(count @ 1..=1023) => count,
+            Some(_) => return Err(Error::InvalidArgument),
+        };
+
+        let _index = Self::send_raw(data, true, count, counted)?;
+        Ok(ContinuousTxTransaction {
+            channel: self,
+            counted,
+        })
     }
 }
 
@@ -1249,37 +1583,50 @@ where
 {
     channel: C,
     data: &'a mut [u32],
+    start_address: usize,
+    started: Instant,
 }
 
 impl<C> RxTransaction<'_, C>
 where
     C: RxChannel,
 {
-    /// Wait for the transaction to complete
-    pub fn wait(self) -> Result<C, (Error, C)> {
+    /// Wait for the transaction to complete, and return the number of pulse
+    /// codes written to the buffer.
+    ///
+    /// If nothing is received within the idle threshold of the channel, the
+    /// reception ends with no pulse codes. This returns [`Error::Overflow`] if
+    /// the RAM of the channel filled up before the input went idle.
+    pub fn wait(self) -> Result<(usize, C), (Error, C)> {
+        let timeout = <C as RxChannelInternal>::idle_timeout();
+
         loop {
             if <C as RxChannelInternal>::is_error() {
-                return Err((Error::TransmissionError, self.channel));
+                return Err((<C as RxChannelInternal>::stop_on_error(), self.channel));
             }
 
             if <C as RxChannelInternal>::is_done() {
                 break;
             }
+
+            if <C as RxChannelInternal>::write_address() == self.start_address
+                && crate::time::now() - self.started >= timeout
+            {
+                <C as RxChannelInternal>::stop();
+                <C as RxChannelInternal>::clear_interrupts();
+                <C as RxChannelInternal>::update();
+
+                return Ok((0, self.channel));
+            }
         }
 
         <C as RxChannelInternal>::stop();
         <C as RxChannelInternal>::clear_interrupts();
         <C as RxChannelInternal>::update();
 
-        let ptr = (constants::RMT_RAM_START
-            + C::CHANNEL as usize * constants::RMT_CHANNEL_RAM_SIZE * 4)
-            as *mut u32;
-        let len = self.data.len();
-        for (idx, entry) in self.data.iter_mut().take(len).enumerate() {
-            *entry = unsafe { ptr.add(idx).read_volatile() };
-        }
+        let count = <C as RxChannelInternal>::read_received(self.data);
 
-        Ok(self.channel)
+        Ok((count, self.channel))
     }
 }
 
@@ -1288,12 +1635,13 @@ pub trait RxChannel: RxChannelInternal {
     /// Start receiving pulse codes into the given buffer.
     /// This returns a [RxTransaction] which can be used to wait for receive to
     /// complete and get back the channel for further use.
-    /// The length of the received data cannot exceed the allocated RMT RAM.
+    /// The length of the received data cannot exceed the RMT RAM allocated
+    /// with [`RxChannelConfig::with_memsize`].
     fn receive(self, data: &mut [u32]) -> Result<RxTransaction<'_, Self>, Error>
     where
         Self: Sized,
     {
-        if data.len() > constants::RMT_CHANNEL_RAM_SIZE {
+        if data.len() > Self::memsize() as usize * constants::RMT_CHANNEL_RAM_SIZE {
             return Err(Error::InvalidArgument);
         }
 
@@ -1302,6 +1650,8 @@ pub trait RxChannel: RxChannelInternal {
         Ok(RxTransaction {
             channel: self,
             data,
+            start_address: Self::write_address(),
+            started: crate::time::now(),
         })
     }
 }
@@ -1314,65 +1664,111 @@ const NUM_CHANNELS: usize = 4;
 static WAKER: [AtomicWaker; NUM_CHANNELS] = [const { AtomicWaker::new() }; NUM_CHANNELS];
 
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-pub(crate) struct RmtTxFuture<T>
+pub(crate) struct RmtTxFuture<'a, T>
 where
     T: TxChannelAsync,
 {
-    _phantom: PhantomData<T>,
+    data: &'a [u32],
+    index: usize,
+    finished: bool,
+    _phantom: PhantomData<fn() -> T>,
 }
 
-impl<T> RmtTxFuture<T>
+impl<'a, T> RmtTxFuture<'a, T>
 where
     T: TxChannelAsync,
 {
-    pub fn new(_instance: &T) -> Self {
+    pub fn new(_instance: &T, data: &'a [u32], index: usize) -> Self {
         Self {
+            data,
+            index,
+            finished: false,
             _phantom: PhantomData,
         }
     }
 }
 
-impl<T> core::future::Future for RmtTxFuture<T>
+impl<T> core::future::Future for RmtTxFuture<'_, T>
 where
     T: TxChannelAsync,
 {
-    type Output = ();
+    type Output = Result<(), Error>;
 
-    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
         WAKER[T::CHANNEL as usize].register(ctx.waker());
 
-        if T::is_error() || T::is_done() {
-            Poll::Ready(())
-        } else {
-            Poll::Pending
+        if T::is_error() {
+            self.finished = true;
+            return Poll::Ready(Err(Error::TransmissionError));
+        }
+
+        if T::is_done() {
+            self.finished = true;
+            return Poll::Ready(Ok(()));
+        }
+
+        let mut events = Event::End | Event::Error;
+        if self.index < self.data.len() {
+            if T::is_threshold_set() {
+                T::reset_threshold_set();
+                match T::refill(self.data, self.index) {
+                    Ok(index) => self.index = index,
+                    Err(error) => {
+                        T::abort();
+                        self.finished = true;
+                        return Poll::Ready(Err(error));
+                    }
+                }
+            }
+            events |= Event::Threshold;
+        }
+
+        // The interrupt handler disables the events, and fires right away for
+        // events that happened in the meantime.
+        T::listen_interrupt(events);
+
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for RmtTxFuture<'_, T>
+where
+    T: TxChannelAsync,
+{
+    fn drop(&mut self) {
+        if !self.finished {
+            T::abort();
         }
     }
 }
 
 /// TX channel in async mode
 pub trait TxChannelAsync: TxChannelInternal {
+    /// Enables or disables carrier modulation for the following
+    /// transmissions.
+    ///
+    /// The carrier keeps the frequency and duty cycle it was configured with.
+    fn set_carrier_modulation(&mut self, enable: bool) {
+        Self::enable_carrier(enable);
+        Self::update();
+    }
+
     /// Start transmitting the given pulse code sequence.
-    /// The length of sequence cannot exceed the size of the allocated RMT
-    /// RAM.
+    ///
+    /// Sequences longer than the RMT RAM of the channel are written in halves
+    /// when the future is polled after the hardware is done with one half. The
+    /// executor needs to poll the future before the other half is sent, or the
+    /// transmission is stopped and fails with [`Error::Underrun`].
+    ///
+    /// Dropping the future stops the transmission, and the channel can be used
+    /// again right away.
     async fn transmit(&mut self, data: &[u32]) -> Result<(), Error>
     where
         Self: Sized,
     {
-        if data.len() > constants::RMT_CHANNEL_RAM_SIZE {
-            return Err(Error::InvalidArgument);
-        }
+        let index = Self::send_raw(data, false, 0, false)?;
 
-        Self::clear_interrupts();
-        Self::listen_interrupt(Event::End | Event::Error);
-        Self::send_raw(data, false, 0)?;
-
-        RmtTxFuture::new(self).await;
-
-        if Self::is_error() {
-            Err(Error::TransmissionError)
-        } else {
-            Ok(())
-        }
+        RmtTxFuture::new(self, data, index).await
     }
 }
 
@@ -1381,7 +1777,8 @@ pub(crate) struct RmtRxFuture<T>
 where
     T: RxChannelAsync,
 {
-    _phantom: PhantomData<T>,
+    finished: bool,
+    _phantom: PhantomData<fn() -> T>,
 }
 
 impl<T> RmtRxFuture<T>
@@ -1390,6 +1787,7 @@ where
 {
     pub fn new(_instance: &T) -> Self {
         Self {
+            finished: false,
             _phantom: PhantomData,
         }
     }
@@ -1401,9 +1799,10 @@ where
 {
     type Output = ();
 
-    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
         WAKER[T::CHANNEL as usize].register(ctx.waker());
         if T::is_error() || T::is_done() {
+            self.finished = true;
             Poll::Ready(())
         } else {
             Poll::Pending
@@ -1411,16 +1810,35 @@ where
     }
 }
 
+impl<T> Drop for RmtRxFuture<T>
+where
+    T: RxChannelAsync,
+{
+    fn drop(&mut self) {
+        if !self.finished {
+            T::unlisten_interrupt(Event::End | Event::Error);
+            T::stop();
+            T::clear_interrupts();
+            T::update();
+        }
+    }
+}
+
 /// RX channel in async mode
 pub trait RxChannelAsync: RxChannelInternal {
-    /// Start receiving a pulse code sequence.
-    /// The length of sequence cannot exceed the size of the allocated RMT
-    /// RAM.
-    async fn receive<T: From<u32> + Copy>(&mut self, data: &mut [T]) -> Result<(), Error>
+    /// Start receiving a pulse code sequence, and return the number of pulse
+    /// codes written to the buffer.
+    /// The length of sequence cannot exceed the RMT RAM allocated with
+    /// [`RxChannelConfig::with_memsize`], and a sequence that fills up the RAM
+    /// before the input goes idle fails with [`Error::Overflow`].
+    ///
+    /// Unlike [`RxTransaction::wait`], this doesn't give up on an input that
+    /// stays silent. Race it against a timer to stop waiting.
+    async fn receive<T: From<u32> + Copy>(&mut self, data: &mut [T]) -> Result<usize, Error>
     where
         Self: Sized,
     {
-        if data.len() > constants::RMT_CHANNEL_RAM_SIZE {
+        if data.len() > Self::memsize() as usize * constants::RMT_CHANNEL_RAM_SIZE {
             return Err(Error::InvalidArgument);
         }
 
@@ -1431,100 +1849,291 @@ pub trait RxChannelAsync: RxChannelInternal {
         RmtRxFuture::new(self).await;
 
         if Self::is_error() {
-            Err(Error::TransmissionError)
+            Err(Self::stop_on_error())
         } else {
             Self::stop();
             Self::clear_interrupts();
             Self::update();
 
-            let ptr = (constants::RMT_RAM_START
-                + Self::CHANNEL as usize * constants::RMT_CHANNEL_RAM_SIZE * 4)
-                as *mut u32;
-            let len = data.len();
-            for (idx, entry) in data.iter_mut().take(len).enumerate() {
-                *entry = unsafe { ptr.add(idx).read_volatile().into() };
-            }
+            Ok(Self::read_received(data))
+        }
+    }
+
+    /// Keep receiving frames into the slots of `buffer`, so that frames that
+    /// arrive back to back aren't lost while the previous one is processed.
+    ///
+    /// `buffer` is split into slots of `frame_len` pulse codes, which cannot
+    /// exceed the RMT RAM allocated with [`RxChannelConfig::with_memsize`].
+    /// The interrupt handler copies each frame to the next free slot and
+    /// restarts the reception right away. Take the frames out in order with
+    /// [`ContinuousRxTransaction::next`].
+    fn receive_continuously(
+        &mut self,
+        buffer: &'static mut [u32],
+        frame_len: usize,
+    ) -> Result<ContinuousRxTransaction<'_, Self>, Error>
+    where
+        Self: Sized,
+    {
+        if frame_len == 0
+            || frame_len > Self::memsize() as usize * constants::RMT_CHANNEL_RAM_SIZE
+            || buffer.len() < frame_len
+        {
+            return Err(Error::InvalidArgument);
+        }
+
+        RX_RINGS[Self::CHANNEL as usize].with(|ring| {
+            *ring = Some(RxRing {
+                buffer,
+                frame_len,
+                received: 0,
+                taken: 0,
+                error: None,
+            })
+        });
+
+        Self::clear_interrupts();
+        Self::listen_interrupt(Event::End | Event::Error);
+        Self::start_receive_raw();
+
+        Ok(ContinuousRxTransaction {
+            _channel: PhantomData,
+        })
+    }
+}
+
+/// The frames of a continuous reception, shared with the interrupt handler.
+struct RxRing {
+    buffer: &'static mut [u32],
+    frame_len: usize,
+    received: usize,
+    taken: usize,
+    error: Option<Error>,
+}
+
+impl RxRing {
+    fn slots(&self) -> usize {
+        self.buffer.len() / self.frame_len
+    }
+
+    fn slot(&mut self, frame: usize) -> &mut [u32] {
+        let start = (frame % self.slots()) * self.frame_len;
+        &mut self.buffer[start..][..self.frame_len]
+    }
 
-            Ok(())
+    fn push<C: RxChannelInternal>(&mut self) {
+        if self.received - self.taken == self.slots() {
+            self.error = Some(Error::Overflow);
+            return;
         }
+
+        C::read_received(self.slot(self.received));
+        self.received += 1;
+    }
+
+    fn pop(&mut self, data: &mut [u32]) -> Option<usize> {
+        if self.taken == self.received {
+            return None;
+        }
+
+        let slot = self.slot(self.taken);
+        let len = data.len().min(slot.len());
+        data[..len].copy_from_slice(&slot[..len]);
+        self.taken += 1;
+
+        Some(frame_length(data[..len].iter().copied()))
     }
 }
 
-#[cfg(not(any(esp32, esp32s2)))]
+static RX_RINGS: [Locked<Option<RxRing>>; NUM_CHANNELS] =
+    [const { Locked::new(None) }; NUM_CHANNELS];
+
+/// An in-progress continuous RX transaction
+///
+/// Dropping it stops the reception.
+pub struct ContinuousRxTransaction<'a, C>
+where
+    C: RxChannelAsync,
+{
+    _channel: PhantomData<&'a mut C>,
+}
+
+impl<C> ContinuousRxTransaction<'_, C>
+where
+    C: RxChannelAsync,
+{
+    /// Wait for the next frame, copy it to `data` and return the number of
+    /// pulse codes it holds.
+    ///
+    /// This fails with [`Error::Overflow`] once for frames that were lost,
+    /// because they filled up the RAM of the channel or because all slots were
+    /// taken.
+    pub async fn next(&mut self, data: &mut [u32]) -> Result<usize, Error> {
+        core::future::poll_fn(|ctx| {
+            WAKER[C::CHANNEL as usize].register(ctx.waker());
+
+            RX_RINGS[C::CHANNEL as usize].with(|ring| {
+                let ring = unwrap!(ring.as_mut());
+                if let Some(error) = ring.error.take() {
+                    return Poll::Ready(Err(error));
+                }
+
+                match ring.pop(data) {
+                    Some(count) => Poll::Ready(Ok(count)),
+                    None => Poll::Pending,
+                }
+            })
+        })
+        .await
+    }
+
+    /// Stop receiving.
+    pub fn stop(self) {}
+}
+
+impl<C> Drop for ContinuousRxTransaction<'_, C>
+where
+    C: RxChannelAsync,
+{
+    fn drop(&mut self) {
+        C::unlisten_interrupt(Event::End | Event::Error);
+        C::stop();
+        C::clear_interrupts();
+        C::update();
+
+        RX_RINGS[C::CHANNEL as usize].with(|ring| *ring = None);
+    }
+}
+
+/// Moves the frame of a continuous reception to its ring, and restarts the
+/// reception. Returns `false` if the channel doesn't receive continuously.
+fn receive_next_frame<C: RxChannelInternal>() -> bool {
+    RX_RINGS[C::CHANNEL as usize].with(|ring| {
+        let Some(ring) = ring.as_mut() else {
+            return false;
+        };
+
+        if C::is_error() {
+            ring.error = Some(C::stop_on_error());
+        } else if C::is_done() {
+            C::stop();
+            C::update();
+            ring.push::<C>();
+        } else {
+            return true;
+        }
+
+        C::start_receive_raw();
+        true
+    })
+}
+
 #[handler]
 fn async_interrupt_handler() {
-    let Some(channel) = chip_specific::pending_interrupt_for_channel() else {
-        return;
-    };
+    // Several channels may have finished at the same time.
+    let pending = chip_specific::pending_interrupts();
+
+    for (channel, waker) in WAKER.iter().enumerate() {
+        if pending & (1 << channel) == 0 {
+            continue;
+        }
+
+        if !continue_reception(channel) {
+            unlisten_channel(channel);
+        }
+        waker.wake();
+    }
+}
+
+#[cfg(not(any(esp32, esp32s2)))]
+fn unlisten_channel(channel: usize) {
+    let events = Event::End | Event::Error | Event::Threshold;
     match channel {
-        0 => Channel::<Async, 0>::unlisten_interrupt(Event::End | Event::Error),
-        1 => Channel::<Async, 1>::unlisten_interrupt(Event::End | Event::Error),
-        2 => Channel::<Async, 2>::unlisten_interrupt(Event::End | Event::Error),
-        3 => Channel::<Async, 3>::unlisten_interrupt(Event::End | Event::Error),
-
-        #[cfg(any(esp32, esp32s3))]
-        4 => Channel::<Async, 4>::unlisten_interrupt(Event::End | Event::Error),
-        #[cfg(any(esp32, esp32s3))]
-        5 => Channel::<Async, 5>::unlisten_interrupt(Event::End | Event::Error),
-        #[cfg(any(esp32, esp32s3))]
-        6 => Channel::<Async, 6>::unlisten_interrupt(Event::End | Event::Error),
-        #[cfg(any(esp32, esp32s3))]
-        7 => Channel::<Async, 7>::unlisten_interrupt(Event::End | Event::Error),
+        0 => Channel::<Async, 0>::unlisten_interrupt(events),
+        1 => Channel::<Async, 1>::unlisten_interrupt(events),
+        2 => Channel::<Async, 2>::unlisten_interrupt(events),
+        3 => Channel::<Async, 3>::unlisten_interrupt(events),
+
+        #[cfg(esp32s3)]
+        4 => Channel::<Async, 4>::unlisten_interrupt(events),
+        #[cfg(esp32s3)]
+        5 => Channel::<Async, 5>::unlisten_interrupt(events),
+        #[cfg(esp32s3)]
+        6 => Channel::<Async, 6>::unlisten_interrupt(events),
+        #[cfg(esp32s3)]
+        7 => Channel::<Async, 7>::unlisten_interrupt(events),
 
         _ => unreachable!(),
     }
+}
 
-    WAKER[channel].wake();
+#[cfg(not(any(esp32, esp32s2)))]
+fn continue_reception(channel: usize) -> bool {
+    match channel {
+        #[cfg(not(esp32s3))]
+        2 => receive_next_frame::<Channel<Async, 2>>(),
+        #[cfg(not(esp32s3))]
+        3 => receive_next_frame::<Channel<Async, 3>>(),
+
+        #[cfg(esp32s3)]
+        4 => receive_next_frame::<Channel<Async, 4>>(),
+        #[cfg(esp32s3)]
+        5 => receive_next_frame::<Channel<Async, 5>>(),
+        #[cfg(esp32s3)]
+        6 => receive_next_frame::<Channel<Async, 6>>(),
+        #[cfg(esp32s3)]
+        7 => receive_next_frame::<Channel<Async, 7>>(),
+
+        _ => false,
+    }
 }
 
 #[cfg(any(esp32, esp32s2))]
-#[handler]
-fn async_interrupt_handler() {
-    let Some(channel) = chip_specific::pending_interrupt_for_channel() else {
-        return;
-    };
+fn continue_reception(channel: usize) -> bool {
     match channel {
-        0 => {
-            <Channel<Async, 0> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 0> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
-        1 => {
-            <Channel<Async, 1> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 1> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
-        2 => {
-            <Channel<Async, 2> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 2> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
-        3 => {
-            <Channel<Async, 3> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 3> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
+        0 => receive_next_frame::<Channel<Async, 0>>(),
+        1 => receive_next_frame::<Channel<Async, 1>>(),
+        2 => receive_next_frame::<Channel<Async, 2>>(),
+        3 => receive_next_frame::<Channel<Async, 3>>(),
+
         #[cfg(esp32)]
-        4 => {
-            <Channel<Async, 4> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 4> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
-        #[cfg(any(esp32, esp32s3))]
-        5 => {
-            <Channel<Async, 5> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 5> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
-        #[cfg(any(esp32, esp32s3))]
-        6 => {
-            <Channel<Async, 6> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 6> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
-        #[cfg(any(esp32, esp32s3))]
-        7 => {
-            <Channel<Async, 7> as TxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-            <Channel<Async, 7> as RxChannelInternal>::unlisten_interrupt(Event::End | Event::Error);
-        }
+        4 => receive_next_frame::<Channel<Async, 4>>(),
+        #[cfg(esp32)]
+        5 => receive_next_frame::<Channel<Async, 5>>(),
+        #[cfg(esp32)]
+        6 => receive_next_frame::<Channel<Async, 6>>(),
+        #[cfg(esp32)]
+        7 => receive_next_frame::<Channel<Async, 7>>(),
 
         _ => unreachable!(),
     }
+}
 
-    WAKER[channel].wake();
+#[cfg(any(esp32, esp32s2))]
+fn unlisten_channel(channel: usize) {
+    fn unlisten<C: TxChannelInternal + RxChannelInternal>() {
+        let events = Event::End | Event::Error | Event::Threshold;
+        <C as TxChannelInternal>::unlisten_interrupt(events);
+        <C as RxChannelInternal>::unlisten_interrupt(events);
+    }
+
+    match channel {
+        0 => unlisten::<Channel<Async, 0>>(),
+        1 => unlisten::<Channel<Async, 1>>(),
+        2 => unlisten::<Channel<Async, 2>>(),
+        3 => unlisten::<Channel<Async, 3>>(),
+
+        #[cfg(esp32)]
+        4 => unlisten::<Channel<Async, 4>>(),
+        #[cfg(esp32)]
+        5 => unlisten::<Channel<Async, 5>>(),
+        #[cfg(esp32)]
+        6 => unlisten::<Channel<Async, 6>>(),
+        #[cfg(esp32)]
+        7 => unlisten::<Channel<Async, 7>>(),
+
+        _ => unreachable!(),
+    }
 }
 
 #[derive(Debug, EnumSetType)]
@@ -1550,6 +2159,8 @@ pub trait TxChannelInternal {
 
     fn set_generate_repeat_interrupt(repeats: u16);
 
+    fn set_loop_stop(stop: bool);
+
     fn clear_interrupts();
 
     fn set_continuous(continuous: bool);
@@ -1558,6 +2169,8 @@ pub trait TxChannelInternal {
 
     fn set_carrier(carrier: bool, high: u16, low: u16, level: Level);
 
+    fn enable_carrier(enable: bool);
+
     fn set_idle_output(enable: bool, level: Level);
 
     fn set_memsize(memsize: u8);
@@ -1576,7 +2189,71 @@ pub trait TxChannelInternal {
 
     fn is_loopcount_interrupt_set() -> bool;
 
-    fn send_raw(data: &[u32], continuous: bool, repeat: u16) -> Result<usize, Error> {
+    /// The RAM address the transmitter reads the current code from.
+    fn read_address() -> usize;
+
+    /// Writes the half of the channel RAM that the hardware is done with, and
+    /// returns the index of the code that follows.
+    ///
+    /// Fails if the hardware already went on to read that half again, in which
+    /// case it is sending codes that were meant for an earlier half.
+    fn refill(data: &[u32], index: usize) -> Result<usize, Error> {
+        let ram_index = (((index - constants::RMT_CHANNEL_RAM_SIZE)
+            / (constants::RMT_CHANNEL_RAM_SIZE / 2))
+            % 2)
+            * (constants::RMT_CHANNEL_RAM_SIZE / 2);
+
+        // Right after the threshold event, the transmitter may still be on the
+        // last code of the half it just finished.
+        let position = Self::read_address() % constants::RMT_CHANNEL_RAM_SIZE;
+        if (ram_index..ram_index + constants::RMT_CHANNEL_RAM_SIZE / 2 - 1).contains(&position) {
+            return Err(Error::Underrun);
+        }
+
+        let ptr = (constants::RMT_RAM_START
+            + Self::CHANNEL as usize * constants::RMT_CHANNEL_RAM_SIZE * 4
+            + ram_index * 4) as *mut u32;
+        for (idx, entry) in data[index..]
+            .iter()
+            .take(constants::RMT_CHANNEL_RAM_SIZE / 2)
+            .enumerate()
+        {
+            unsafe {
+                ptr.add(idx).write_volatile(*entry);
+            }
+        }
+
+        Ok(index + constants::RMT_CHANNEL_RAM_SIZE / 2)
+    }
+
+    /// Stops the ongoing transmission and resets the channel, so that it can
+    /// start the next one.
+    fn abort() {
+        Self::unlisten_interrupt(Event::End | Event::Error | Event::Threshold);
+        Self::set_continuous(false);
+        Self::stop();
+
+        // Not all chips can stop a transmission right away, but all of them
+        // stop at an end marker.
+        let ptr = (constants::RMT_RAM_START
+            + Self::CHANNEL as usize * constants::RMT_CHANNEL_RAM_SIZE * 4)
+            as *mut u32;
+        for idx in 0..constants::RMT_CHANNEL_RAM_SIZE {
+            unsafe {
+                ptr.add(idx).write_volatile(0);
+            }
+        }
+
+        Self::clear_interrupts();
+        Self::update();
+    }
+
+    fn send_raw(
+        data: &[u32],
+        continuous: bool,
+        repeat: u16,
+        stop_after_repeat: bool,
+    ) -> Result<usize, Error> {
         Self::clear_interrupts();
 
         if let Some(last) = data.last() {
@@ -1603,6 +2280,7 @@ pub trait TxChannelInternal {
         Self::set_threshold((constants::RMT_CHANNEL_RAM_SIZE / 2) as u8);
         Self::set_continuous(continuous);
         Self::set_generate_repeat_interrupt(repeat);
+        Self::set_loop_stop(stop_after_repeat);
         Self::set_wrap_mode(true);
         Self::set_memsize(1);
         Self::update();
@@ -1649,22 +2327,68 @@ pub trait RxChannelInternal {
 
     fn set_memsize(memsize: u8);
 
+    fn memsize() -> u8;
+
     fn start_rx();
 
     fn is_done() -> bool;
 
     fn is_error() -> bool;
 
+    fn is_memory_full() -> bool;
+
     fn start_receive_raw() {
         Self::clear_interrupts();
         Self::set_wrap_mode(false);
-        Self::set_memsize(1);
         Self::start_rx();
         Self::update();
     }
 
     fn stop();
 
+    /// The RAM address the receiver writes the next code to.
+    fn write_address() -> usize;
+
+    fn divider() -> u8;
+
+    fn idle_threshold() -> u16;
+
+    /// How long the input needs to stay idle for a reception to end.
+    fn idle_timeout() -> Duration {
+        let ticks = Self::idle_threshold() as u64 * divider_value(Self::divider());
+        Duration::micros(ticks * 1_000_000 / chip_specific::clock_frequency().raw() as u64)
+    }
+
+    /// Copies the channel RAM to `data`, and returns how many of the codes
+    /// were received.
+    fn read_received<T: From<u32>>(data: &mut [T]) -> usize {
+        let ptr = (constants::RMT_RAM_START
+            + Self::CHANNEL as usize * constants::RMT_CHANNEL_RAM_SIZE * 4)
+            as *mut u32;
+
+        for (idx, entry) in data.iter_mut().enumerate() {
+            *entry = unsafe { ptr.add(idx).read_volatile().into() };
+        }
+
+        frame_length((0..data.len()).map(|idx| unsafe { ptr.add(idx).read_volatile() }))
+    }
+
+    /// Stops a failed reception and tells why it failed.
+    fn stop_on_error() -> Error {
+        // Resetting the channel clears the memory full flag.
+        let error = if Self::is_memory_full() {
+            Error::Overflow
+        } else {
+            Error::TransmissionError
+        };
+
+        Self::stop();
+        Self::clear_interrupts();
+        Self::update();
+
+        error
+    }
+
     fn set_filter_threshold(value: u8);
 
     fn set_idle_threshold(value: u16);
@@ -1682,8 +2406,68 @@ pub trait RxChannelInternal {
 
 #[cfg(not(any(esp32, esp32s2)))]
 mod chip_specific {
+    use fugit::HertzU32;
+
     use crate::peripherals::RMT;
 
+    pub fn clock_frequency() -> HertzU32 {
+        #[cfg(not(pcr))]
+        let div = RMT::regs().sys_conf().read().sclk_div_num().bits();
+
+        #[cfg(pcr)]
+        let div = crate::peripherals::PCR::regs()
+            .rmt_sclk_conf()
+            .read()
+            .sclk_div_num()
+            .bits();
+
+        crate::soc::constants::RMT_CLOCK_SRC_FREQ / (div as u32 + 1)
+    }
+
+    /// Makes the given TX channels start their transmissions together, or
+    /// none of them if the mask is empty.
+    pub fn enable_tx_sync(channels: u8) {
+        #[cfg(esp32s3)]
+        const ENABLE: u32 = 1 << 4;
+        #[cfg(not(esp32s3))]
+        const ENABLE: u32 = 1 << 2;
+
+        let bits = if channels == 0 {
+            0
+        } else {
+            channels as u32 | ENABLE
+        };
+        RMT::regs().tx_sim().write(|w| unsafe { w.bits(bits) });
+    }
+
+    pub fn reset_dividers(channels: u8) {
+        RMT::regs()
+            .ref_cnt_rst()
+            .write(|w| unsafe { w.bits(channels as u32) });
+    }
+
+    /// Returns a bit mask of the channels with pending interrupts.
+    pub fn pending_interrupts() -> u8 {
+        #[cfg(esp32s3)]
+        const TX_CHANNELS: u8 = 4;
+        #[cfg(not(esp32s3))]
+        const TX_CHANNELS: u8 = 2;
+
+        let st = RMT::regs().int_st().read();
+
+        let mut pending = 0;
+        for ch in 0..TX_CHANNELS {
+            if st.ch_tx_end(ch).bit() || st.ch_tx_err(ch).bit() || st.ch_tx_thr_event(ch).bit() {
+                pending |= 1 << ch;
+            }
+            if st.ch_rx_end(ch).bit() || st.ch_rx_err(ch).bit() {
+                pending |= 1 << (TX_CHANNELS + ch);
+            }
+        }
+
+        pending
+    }
+
     pub fn configure_clock(div: u32) {
         #[cfg(not(pcr))]
         {
@@ -1721,50 +2505,6 @@ mod chip_specific {
         }
     }
 
-    #[allow(unused)]
-    #[cfg(not(esp32s3))]
-    pub fn pending_interrupt_for_channel() -> Option<usize> {
-        let st = RMT::regs().int_st().read();
-
-        if st.ch0_tx_end().bit() || st.ch0_tx_err().bit() {
-            Some(0)
-        } else if st.ch1_tx_end().bit() || st.ch1_tx_err().bit() {
-            Some(1)
-        } else if st.ch2_rx_end().bit() || st.ch2_rx_err().bit() {
-            Some(2)
-        } else if st.ch3_rx_end().bit() || st.ch3_rx_err().bit() {
-            Some(3)
-        } else {
-            None
-        }
-    }
-
-    #[allow(unused)]
-    #[cfg(esp32s3)]
-    pub fn pending_interrupt_for_channel() -> Option<usize> {
-        let st = RMT::regs().int_st().read();
-
-        if st.ch0_tx_end().bit() || st.ch0_tx_err().bit() {
-            Some(0)
-        } else if st.ch1_tx_end().bit() || st.ch1_tx_err().bit() {
-            Some(1)
-        } else if st.ch2_tx_end().bit() || st.ch2_tx_err().bit() {
-            Some(2)
-        } else if st.ch3_tx_end().bit() || st.ch3_tx_err().bit() {
-            Some(3)
-        } else if st.ch4_rx_end().bit() || st.ch4_rx_err().bit() {
-            Some(4)
-        } else if st.ch5_rx_end().bit() || st.ch5_rx_err().bit() {
-            Some(5)
-        } else if st.ch6_rx_end().bit() || st.ch6_rx_err().bit() {
-            Some(6)
-        } else if st.ch7_rx_end().bit() || st.ch7_rx_err().bit() {
-            Some(7)
-        } else {
-            None
-        }
-    }
-
     macro_rules! impl_tx_channel {
         ($signal:ident, $ch_num:literal) => {
             impl<Dm> $crate::rmt::TxChannelInternal for $crate::rmt::Channel<Dm, $ch_num>
@@ -1799,7 +2539,7 @@ mod chip_specific {
 
                 fn set_generate_repeat_interrupt(repeats: u16) {
                     let rmt = crate::peripherals::RMT::regs();
-                    if repeats > 1 {
+                    if repeats > 0 {
                         rmt.ch_tx_lim($ch_num).modify(|_, w| unsafe {
                             w.loop_count_reset().set_bit();
                             w.tx_loop_cnt_en().set_bit();
@@ -1817,6 +2557,18 @@ mod chip_specific {
                         .modify(|_, w| w.loop_count_reset().clear_bit());
                 }
 
+                #[cfg(not(esp32c3))]
+                fn set_loop_stop(stop: bool) {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_tx_lim($ch_num)
+                        .modify(|_, w| w.loop_stop_en().bit(stop));
+                }
+
+                #[cfg(esp32c3)]
+                fn set_loop_stop(_stop: bool) {
+                    // unsupported
+                }
+
                 fn clear_interrupts() {
                     let rmt = crate::peripherals::RMT::regs();
 
@@ -1855,6 +2607,12 @@ mod chip_specific {
                     });
                 }
 
+                fn enable_carrier(enable: bool) {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_tx_conf0($ch_num)
+                        .modify(|_, w| w.carrier_en().bit(enable));
+                }
+
                 fn set_idle_output(enable: bool, level: $crate::gpio::Level) {
                     let rmt = crate::peripherals::RMT::regs();
                     rmt.ch_tx_conf0($ch_num)
@@ -1871,7 +2629,8 @@ mod chip_specific {
                 fn start_tx() {
                     let rmt = crate::peripherals::RMT::regs();
 
-                    rmt.ref_cnt_rst().write(|w| unsafe { w.bits(1 << $ch_num) });
+                    let channels = super::divider_reset_mask($ch_num);
+                    rmt.ref_cnt_rst().write(|w| unsafe { w.bits(channels as u32) });
                     Self::update();
 
                     rmt.ch_tx_conf0($ch_num).modify(|_, w| {
@@ -1914,6 +2673,11 @@ mod chip_specific {
                     rmt.int_raw().read().ch_tx_loop($ch_num).bit()
                 }
 
+                fn read_address() -> usize {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_tx_status($ch_num).read().mem_raddr_ex().bits() as usize
+                }
+
                 fn stop() {
                     let rmt = crate::peripherals::RMT::regs();
                     rmt.ch_tx_conf0($ch_num)
@@ -2013,6 +2777,11 @@ mod chip_specific {
                         .modify(|_, w| unsafe { w.mem_size().bits(memsize) });
                 }
 
+                fn memsize() -> u8 {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_rx_conf0($ch_index).read().mem_size().bits()
+                }
+
                 fn start_rx() {
                     let rmt = crate::peripherals::RMT::regs();
                     rmt.ch_rx_conf1($ch_index).modify(|_, w| {
@@ -2033,6 +2802,26 @@ mod chip_specific {
                     rmt.int_raw().read().ch_rx_err($ch_index).bit()
                 }
 
+                fn is_memory_full() -> bool {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_rx_status($ch_index).read().mem_full().bit()
+                }
+
+                fn write_address() -> usize {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_rx_status($ch_index).read().mem_waddr_ex().bits() as usize
+                }
+
+                fn divider() -> u8 {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_rx_conf0($ch_index).read().div_cnt().bits()
+                }
+
+                fn idle_threshold() -> u16 {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.ch_rx_conf0($ch_index).read().idle_thres().bits()
+                }
+
                 fn stop() {
                     let rmt = crate::peripherals::RMT::regs();
                     rmt.ch_rx_conf1($ch_index)
@@ -2083,8 +2872,85 @@ mod chip_specific {
 
 #[cfg(any(esp32, esp32s2))]
 mod chip_specific {
+    use fugit::HertzU32;
+    #[cfg(esp32)]
+    use portable_atomic::Ordering;
+
     use crate::peripherals::RMT;
 
+    pub fn clock_frequency() -> HertzU32 {
+        // The channels run from the APB clock.
+        HertzU32::MHz(80)
+    }
+
+    /// Makes the given TX channels start their transmissions together, or
+    /// none of them if the mask is empty.
+    pub fn enable_tx_sync(channels: u8) {
+        #[cfg(esp32s2)]
+        {
+            let bits = if channels == 0 {
+                0
+            } else {
+                channels as u32 | 1 << 4
+            };
+            RMT::regs().tx_sim().write(|w| unsafe { w.bits(bits) });
+        }
+
+        // Transmissions are started together by `start_synchronized`.
+        #[cfg(esp32)]
+        let _ = channels;
+    }
+
+    pub fn reset_dividers(channels: u8) {
+        #[cfg(esp32s2)]
+        RMT::regs()
+            .ref_cnt_rst()
+            .write(|w| unsafe { w.bits(channels as u32) });
+
+        #[cfg(esp32)]
+        let _ = channels;
+    }
+
+    /// Starts the transmissions of the synchronized channels once all of them
+    /// have one.
+    #[cfg(esp32)]
+    pub fn start_synchronized(channel: u8) {
+        let group = super::SYNC_CHANNELS.load(Ordering::Acquire);
+        let armed = super::ARMED_CHANNELS.fetch_or(1 << channel, Ordering::AcqRel) | 1 << channel;
+        if armed & group != group {
+            return;
+        }
+
+        super::ARMED_CHANNELS.store(0, Ordering::Release);
+
+        let rmt = RMT::regs();
+        critical_section::with(|_| {
+            for ch in 0..super::NUM_CHANNELS {
+                if group & (1 << ch) != 0 {
+                    rmt.chconf1(ch).modify(|_, w| w.tx_start().set_bit());
+                }
+            }
+        });
+    }
+
+    /// Returns a bit mask of the channels with pending interrupts.
+    pub fn pending_interrupts() -> u8 {
+        let st = RMT::regs().int_st().read();
+
+        let mut pending = 0;
+        for ch in 0..super::NUM_CHANNELS as u8 {
+            if st.ch_tx_end(ch).bit()
+                || st.ch_rx_end(ch).bit()
+                || st.ch_err(ch).bit()
+                || st.ch_tx_thr_event(ch).bit()
+            {
+                pending |= 1 << ch;
+            }
+        }
+
+        pending
+    }
+
     pub fn configure_clock() {
         let rmt = RMT::regs();
 
@@ -2106,52 +2972,6 @@ mod chip_specific {
         rmt.apb_conf().modify(|_, w| w.clk_en().set_bit());
     }
 
-    #[allow(unused)]
-    #[cfg(esp32)]
-    pub fn pending_interrupt_for_channel() -> Option<usize> {
-        let rmt = RMT::regs();
-        let st = rmt.int_st().read();
-
-        if st.ch0_rx_end().bit() || st.ch0_tx_end().bit() || st.ch0_err().bit() {
-            Some(0)
-        } else if st.ch1_rx_end().bit() || st.ch1_tx_end().bit() || st.ch1_err().bit() {
-            Some(1)
-        } else if st.ch2_rx_end().bit() || st.ch2_tx_end().bit() || st.ch2_err().bit() {
-            Some(2)
-        } else if st.ch3_rx_end().bit() || st.ch3_tx_end().bit() || st.ch3_err().bit() {
-            Some(3)
-        } else if st.ch4_rx_end().bit() || st.ch4_tx_end().bit() || st.ch4_err().bit() {
-            Some(4)
-        } else if st.ch5_rx_end().bit() || st.ch5_tx_end().bit() || st.ch5_err().bit() {
-            Some(5)
-        } else if st.ch6_rx_end().bit() || st.ch6_tx_end().bit() || st.ch6_err().bit() {
-            Some(6)
-        } else if st.ch7_rx_end().bit() || st.ch7_tx_end().bit() || st.ch7_err().bit() {
-            Some(7)
-        } else {
-            None
-        }
-    }
-
-    #[allow(unused)]
-    #[cfg(esp32s2)]
-    pub fn pending_interrupt_for_channel() -> Option<usize> {
-        let rmt = RMT::regs();
-        let st = rmt.int_st().read();
-
-        if st.ch0_rx_end().bit() || st.ch0_tx_end().bit() || st.ch0_err().bit() {
-            Some(0)
-        } else if st.ch1_rx_end().bit() || st.ch1_tx_end().bit() || st.ch1_err().bit() {
-            Some(1)
-        } else if st.ch2_rx_end().bit() || st.ch2_tx_end().bit() || st.ch2_err().bit() {
-            Some(2)
-        } else if st.ch3_rx_end().bit() || st.ch3_tx_end().bit() || st.ch3_err().bit() {
-            Some(3)
-        } else {
-            None
-        }
-    }
-
     macro_rules! impl_tx_channel {
         ($signal:ident, $ch_num:literal) => {
             impl<Dm> super::TxChannelInternal for $crate::rmt::Channel<Dm, $ch_num>
@@ -2185,7 +3005,7 @@ mod chip_specific {
                 #[cfg(not(esp32))]
                 fn set_generate_repeat_interrupt(repeats: u16) {
                     let rmt = crate::peripherals::RMT::regs();
-                    if repeats > 1 {
+                    if repeats > 0 {
                         rmt.ch_tx_lim($ch_num)
                             .modify(|_, w| unsafe { w.tx_loop_num().bits(repeats) });
                     } else {
@@ -2199,6 +3019,10 @@ mod chip_specific {
                     // unsupported
                 }
 
+                fn set_loop_stop(_stop: bool) {
+                    // unsupported
+                }
+
                 fn clear_interrupts() {
                     let rmt = crate::peripherals::RMT::regs();
 
@@ -2236,6 +3060,12 @@ mod chip_specific {
                     });
                 }
 
+                fn enable_carrier(enable: bool) {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chconf0($ch_num)
+                        .modify(|_, w| w.carrier_en().bit(enable));
+                }
+
                 fn set_idle_output(enable: bool, level: $crate::gpio::Level) {
                     let rmt = crate::peripherals::RMT::regs();
                     rmt.chconf1($ch_num)
@@ -2252,6 +3082,19 @@ mod chip_specific {
                 fn start_tx() {
                     let rmt = crate::peripherals::RMT::regs();
 
+                    #[cfg(esp32)]
+                    if super::SYNC_CHANNELS.load(portable_atomic::Ordering::Acquire) & (1 << $ch_num) != 0 {
+                        rmt.chconf1($ch_num).modify(|_, w| {
+                            w.mem_rd_rst().set_bit();
+                            w.apb_mem_rst().set_bit()
+                        });
+                        super::chip_specific::start_synchronized($ch_num);
+                        return;
+                    }
+
+                    #[cfg(esp32s2)]
+                    super::chip_specific::reset_dividers(super::divider_reset_mask($ch_num));
+
                     rmt.chconf1($ch_num).modify(|_, w| {
                         w.mem_rd_rst().set_bit();
                         w.apb_mem_rst().set_bit();
@@ -2291,6 +3134,11 @@ mod chip_specific {
                     false
                 }
 
+                fn read_address() -> usize {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chstatus($ch_num).read().mem_raddr_ex().bits() as usize
+                }
+
                 fn stop() {
                     #[cfg(esp32s2)]
                     {
@@ -2394,6 +3242,11 @@ mod chip_specific {
                         .modify(|_, w| unsafe { w.mem_size().bits(memsize) });
                 }
 
+                fn memsize() -> u8 {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chconf0($ch_num).read().mem_size().bits()
+                }
+
                 fn start_rx() {
                     let rmt = crate::peripherals::RMT::regs();
 
@@ -2415,6 +3268,26 @@ mod chip_specific {
                     rmt.int_raw().read().ch_err($ch_num).bit()
                 }
 
+                fn is_memory_full() -> bool {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chstatus($ch_num).read().mem_full().bit()
+                }
+
+                fn write_address() -> usize {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chstatus($ch_num).read().mem_waddr_ex().bits() as usize
+                }
+
+                fn divider() -> u8 {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chconf0($ch_num).read().div_cnt().bits()
+                }
+
+                fn idle_threshold() -> u16 {
+                    let rmt = crate::peripherals::RMT::regs();
+                    rmt.chconf0($ch_num).read().idle_thres().bits()
+                }
+
                 fn stop() {
                     let rmt = crate::peripherals::RMT::regs();
                     rmt.chconf1($ch_num).modify(|_, w| w.rx_en().clear_bit());
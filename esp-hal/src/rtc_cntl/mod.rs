@@ -113,6 +113,7 @@ use chrono::{DateTime, NaiveDateTime};
 #[cfg(not(any(esp32c6, esp32h2)))]
 use fugit::HertzU32;
 use fugit::MicrosDurationU64;
+use portable_atomic::{AtomicBool, Ordering};
 
 pub use self::rtc::SocResetReason;
 #[cfg(not(any(esp32c6, esp32h2)))]
@@ -131,6 +132,9 @@ use crate::{
     reset::{SleepSource, WakeupReason},
     Cpu,
 };
+/// Set once the chip has woken up from light sleep.
+static LIGHT_SLEEP_WAKEUP: AtomicBool = AtomicBool::new(false);
+
 // only include sleep where it's been implemented
 #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
 pub mod sleep;
@@ -437,6 +441,10 @@ impl<'d> Rtc<'d> {
 
         config.start_sleep(wakeup_triggers);
         config.finish_sleep();
+
+        // Waking up from light sleep doesn't reset the chip, so
+        // `wakeup_cause` can't rely on the reset reason alone.
+        LIGHT_SLEEP_WAKEUP.store(true, Ordering::Relaxed);
     }
 
     const RTC_DISABLE_ROM_LOG: u32 = 1;
@@ -1151,8 +1159,14 @@ pub fn reset_reason(cpu: Cpu) -> Option<SocResetReason> {
 }
 
 /// Return wakeup reason.
+///
+/// This reports the source that woke the chip up from the last deep sleep, or
+/// from the last light sleep if the chip has entered light sleep since it was
+/// reset.
 pub fn wakeup_cause() -> SleepSource {
-    if reset_reason(Cpu::ProCpu) != Some(SocResetReason::CoreDeepSleep) {
+    if reset_reason(Cpu::ProCpu) != Some(SocResetReason::CoreDeepSleep)
+        && !LIGHT_SLEEP_WAKEUP.load(Ordering::Relaxed)
+    {
         return SleepSource::Undefined;
     }
 
@@ -445,6 +445,55 @@ macro_rules! uart_wakeup_impl {
 uart_wakeup_impl!(0);
 uart_wakeup_impl!(1);
 
+/// UART wakeup source
+///
+/// Wakes the chip up from light sleep once a UART has seen enough rising
+/// edges on its RX line. Unlike [Uart0WakeupSource] and [Uart1WakeupSource],
+/// this wakeup source uses the thresholds set up by the UART drivers, and
+/// covers every UART that has been configured via
+/// [crate::uart::Uart::wakeup_enable].
+///
+/// This wakeup source can be used to wake up from light sleep only.
+#[instability::unstable]
+pub struct UartWakeupSource {}
+
+impl UartWakeupSource {
+    /// Create a new instance of [UartWakeupSource]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for UartWakeupSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WakeSource for UartWakeupSource {
+    fn apply(
+        &self,
+        _rtc: &Rtc<'_>,
+        triggers: &mut WakeTriggers,
+        _sleep_config: &mut RtcSleepConfig,
+    ) {
+        use core::sync::atomic::Ordering;
+
+        use crate::uart::Instance;
+
+        // SAFETY: only the driver state is read.
+        let uart0 = unsafe { crate::peripherals::UART0::steal() };
+        let uart1 = unsafe { crate::peripherals::UART1::steal() };
+
+        if uart0.state().wakeup_enabled.load(Ordering::Relaxed) {
+            triggers.set_uart0(true);
+        }
+        if uart1.state().wakeup_enabled.load(Ordering::Relaxed) {
+            triggers.set_uart1(true);
+        }
+    }
+}
+
 #[cfg(not(pmu))]
 bitfield::bitfield! {
     /// Represents the wakeup triggers.
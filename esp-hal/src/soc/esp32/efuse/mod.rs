@@ -50,7 +50,7 @@
 use fugit::{HertzU32, RateExtU32};
 
 pub use self::fields::*;
-use crate::peripherals::EFUSE;
+use crate::{peripherals::EFUSE, soc::efuse_field::EfuseField};
 
 mod fields;
 
@@ -135,6 +135,57 @@ impl Efuse {
     pub fn flash_encryption() -> bool {
         (Self::read_field_le::<u8>(FLASH_CRYPT_CNT).count_ones() % 2) != 0
     }
+
+    /// Get the ADC reference voltage in millivolts, if it was measured and
+    /// burned into eFuse
+    ///
+    /// see <https://github.com/espressif/esp-idf/blob/903af13e8/components/esp_adc/esp32/adc_cali_line_fitting.c>
+    pub fn adc_vref() -> Option<u16> {
+        let bits: u8 = Self::read_field_le(ADC_VREF);
+
+        if bits == 0 {
+            return None;
+        }
+
+        // The deviation from 1100 mV is stored in 7 mV steps, as a 5-bit
+        // sign-magnitude number
+        let deviation = (bits & 0xf) as u16 * 7;
+        let vref = if bits & 0x10 != 0 {
+            1100 - deviation
+        } else {
+            1100 + deviation
+        };
+
+        Some(vref)
+    }
+
+    /// Get the raw 12-bit readings of an ADC unit at 150 mV and 850 mV, if
+    /// two-point calibration data was burned into eFuse
+    ///
+    /// see <https://github.com/espressif/esp-idf/blob/903af13e8/components/esp_adc/esp32/adc_cali_line_fitting.c>
+    pub fn adc_two_point(unit: u8) -> Option<(u16, u16)> {
+        if !Self::read_bit(BLK3_PART_RESERVE) {
+            return None;
+        }
+
+        let (low, high, low_offset, high_offset) = match unit {
+            1 => (ADC1_TP_LOW, ADC1_TP_HIGH, 278, 3265),
+            2 => (ADC2_TP_LOW, ADC2_TP_HIGH, 421, 3406),
+            _ => return None,
+        };
+
+        // The deviations from the typical readings are stored in steps of 4, as
+        // two's complement numbers: 7 bits wide for the low point and 9 bits
+        // wide for the high point
+        let decode = |field: EfuseField, width: u32, offset: i32| {
+            let bits: u16 = Self::read_field_le(field);
+            let deviation = ((bits as i32) << (32 - width)) >> (32 - width);
+
+            (offset + deviation * 4) as u16
+        };
+
+        Some((decode(low, 7, low_offset), decode(high, 9, high_offset)))
+    }
 }
 
 #[allow(unused)]
@@ -18,6 +18,9 @@
 //!         nmi_enable to control the interrupt and NMI enable settings. The
 //!         function returns an u8 value representing the interrupt enable
 //!         settings.
+//!   - `set_pad_hold(gpio_num: u8, enable: bool)`:
+//!       * Enables or disables the hold of a pad, which keeps its configuration
+//!         and output level until it is released.
 //!   - `errata36(pin_num: u8, pull_up: bool, pull_down: bool)`:
 //!       * Handles the configuration of pull-up and pull-down resistors for
 //!         specific GPIO pins
@@ -119,6 +122,40 @@ pub(crate) fn gpio_intr_enable(int_enable: bool, nmi_enable: bool) -> u8 {
     }
 }
 
+/// Latches the configuration and level of a pad.
+///
+/// RTC pads are held by the RTC controller. The other pads are held by the
+/// digital pad hold register, whose bits aren't in GPIO order.
+pub(crate) fn set_pad_hold(gpio_num: u8, enable: bool) {
+    // see <https://github.com/espressif/esp-idf/blob/903af13e8/components/soc/esp32/gpio_periph.c>
+    let bit = match gpio_num {
+        1 => 1,
+        3 => 0,
+        5 => 8,
+        6..=11 => gpio_num - 4,
+        16..=23 => gpio_num - 7,
+        _ => {
+            let pin = unsafe { crate::gpio::AnyPin::steal(gpio_num) };
+            return crate::gpio::RtcPin::rtcio_pad_hold(&pin, enable);
+        }
+    };
+
+    // The hold of the digital pads is forced off after reset
+    crate::peripherals::LPWR::regs()
+        .dig_iso()
+        .modify(|_, w| w.dg_pad_force_unhold().clear_bit());
+
+    crate::peripherals::RTC_IO::regs()
+        .dig_pad_hold()
+        .modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | 1 << bit)
+            } else {
+                w.bits(r.bits() & !(1 << bit))
+            }
+        });
+}
+
 /// Peripheral input signals for the GPIO mux
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -643,6 +680,28 @@ macro_rules! rtcio_analog {
                 pin.rtcio_pulldown(pull_down);
             }
         }
+
+        /// The drive strength of RTC pads is set in the RTC IO registers, the value in the
+        /// IO MUX only applies to the other pins.
+        pub(crate) fn rtcio_drive_strength(pin: $crate::gpio::AnyPin, strength: $crate::gpio::DriveStrength) {
+            use $crate::gpio::Pin;
+
+            match pin.number() {
+                $(
+                    $(
+                        $pin_num => {
+                            // FIXME: replace with $(ignore($rue)) once stable
+                            $crate::ignore!($rue);
+                            paste::paste! {
+                                $crate::peripherals::RTC_IO::regs()
+                                    .$pin_reg.modify(|_, w| unsafe { w.[< $prefix drv >]().bits(strength as u8) });
+                            }
+                        }
+                    )?
+                )+
+                _ => {}
+            }
+        }
     };
 }
 
@@ -30,6 +30,7 @@ crate::peripherals! {
         ASSIST_DEBUG <= ASSIST_DEBUG,
         BB <= BB,
         BT <= virtual,
+        DEDICATED_GPIO <= virtual,
         DMA <= DMA,
         ECC <= ECC,
         EFUSE <= EFUSE,
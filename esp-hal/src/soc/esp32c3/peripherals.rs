@@ -32,6 +32,7 @@ crate::peripherals! {
         ASSIST_DEBUG <= ASSIST_DEBUG,
         BB <= BB,
         BT <= virtual,
+        DEDICATED_GPIO <= virtual,
         DMA <= DMA,
         DS <= DS,
         EFUSE <= EFUSE,
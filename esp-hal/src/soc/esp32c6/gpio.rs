@@ -17,6 +17,9 @@
 //!         nmi_enable to control the interrupt and NMI enable settings. The
 //!         function returns an u8 value representing the interrupt enable
 //!         settings.
+//!   - `set_pad_hold(gpio_num: u8, enable: bool)`:
+//!       * Enables or disables the hold of a pad, which keeps its configuration
+//!         and output level until it is released.
 //!   - `gpio` block:
 //!       * Defines the pin configurations for various GPIO pins. Each line
 //!         represents a pin and its associated options such as input/output
@@ -65,6 +68,19 @@ pub(crate) fn gpio_intr_enable(int_enable: bool, nmi_enable: bool) -> u8 {
     int_enable as u8 | ((nmi_enable as u8) << 1)
 }
 
+/// Latches the configuration and level of a pad.
+pub(crate) fn set_pad_hold(gpio_num: u8, enable: bool) {
+    crate::peripherals::LP_AON::regs()
+        .gpio_hold0()
+        .modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | 1 << gpio_num)
+            } else {
+                w.bits(r.bits() & !(1 << gpio_num))
+            }
+        });
+}
+
 /// Peripheral input signals for the GPIO mux
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -30,6 +30,7 @@ crate::peripherals! {
         ASSIST_DEBUG <= ASSIST_DEBUG,
         ATOMIC <= ATOMIC,
         BT <= virtual,
+        DEDICATED_GPIO <= virtual,
         DMA <= DMA,
         DS <= DS,
         ECC <= ECC,
@@ -30,6 +30,7 @@ crate::peripherals! {
         APB_SARADC <= APB_SARADC,
         ASSIST_DEBUG <= ASSIST_DEBUG,
         BT <= virtual,
+        DEDICATED_GPIO <= virtual,
         DMA <= DMA,
         DS <= DS,
         ECC <= ECC,
@@ -18,6 +18,9 @@
 //!         nmi_enable to control the interrupt and NMI enable settings. The
 //!         function returns an u8 value representing the interrupt enable
 //!         settings.
+//!   - `set_pad_hold(gpio_num: u8, enable: bool)`:
+//!       * Enables or disables the hold of a pad, which keeps its configuration
+//!         and output level until it is released.
 //!   - `impl_get_rtc_pad`:
 //!       * This macro_rule generates a function to get a specific RTC pad. It
 //!         takes a single argument `$pad_name`, which is an identifier
@@ -63,7 +66,7 @@ pub(crate) const FUNC_IN_SEL_OFFSET: usize = 0;
 pub(crate) type InputSignalType = u16;
 pub(crate) type OutputSignalType = u16;
 pub(crate) const OUTPUT_SIGNAL_MAX: u16 = 256;
-pub(crate) const INPUT_SIGNAL_MAX: u16 = 204;
+pub(crate) const INPUT_SIGNAL_MAX: u16 = 242;
 
 pub(crate) const ONE_INPUT: u8 = 0x38;
 pub(crate) const ZERO_INPUT: u8 = 0x3c;
@@ -125,6 +128,31 @@ pub(crate) fn gpio_intr_enable(int_enable: bool, nmi_enable: bool) -> u8 {
         | ((nmi_enable as u8) << 3)
 }
 
+/// Latches the configuration and level of a pad.
+///
+/// The RTC pads GPIO0-21 are held by the RTC controller, the digital pad hold
+/// register starts at GPIO21.
+pub(crate) fn set_pad_hold(gpio_num: u8, enable: bool) {
+    let rtc_cntl = crate::peripherals::LPWR::regs();
+    if gpio_num <= 21 {
+        rtc_cntl.pad_hold().modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | 1 << gpio_num)
+            } else {
+                w.bits(r.bits() & !(1 << gpio_num))
+            }
+        });
+    } else {
+        rtc_cntl.dig_pad_hold().modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | 1 << (gpio_num - 21))
+            } else {
+                w.bits(r.bits() & !(1 << (gpio_num - 21)))
+            }
+        });
+    }
+}
+
 /// Peripheral input signals for the GPIO mux
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -210,6 +238,14 @@ pub enum InputSignal {
     SUBSPIDQS         = 171,
     PCMFSYNC          = 203,
     PCMCLK            = 204,
+    PRO_ALONEGPIO_IN0 = 235,
+    PRO_ALONEGPIO_IN1 = 236,
+    PRO_ALONEGPIO_IN2 = 237,
+    PRO_ALONEGPIO_IN3 = 238,
+    PRO_ALONEGPIO_IN4 = 239,
+    PRO_ALONEGPIO_IN5 = 240,
+    PRO_ALONEGPIO_IN6 = 241,
+    PRO_ALONEGPIO_IN7 = 242,
 }
 
 /// Peripheral output signals for the GPIO mux
@@ -218,103 +254,111 @@ pub enum InputSignal {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[doc(hidden)]
 pub enum OutputSignal {
-    SPIQ             = 0,
-    SPID             = 1,
-    SPIHD            = 2,
-    SPIWP            = 3,
-    SPICLK           = 4,
-    SPICS0           = 5,
-    SPICS1           = 6,
-    SPID4            = 7,
-    SPID5            = 8,
-    SPID6            = 9,
-    SPID7            = 10,
-    SPIDQS           = 11,
-    U0TXD            = 14,
-    U0RTS            = 15,
-    U0DTR            = 16,
-    U1TXD            = 17,
-    U1RTS            = 18,
-    U1DTR            = 21,
-    I2S0O_BCK        = 23,
-    I2S0O_WS         = 25,
-    I2S0I_BCK        = 27,
-    I2S0I_WS         = 28,
-    I2CEXT0_SCL      = 29,
-    I2CEXT0_SDA      = 30,
-    SDIO_TOHOST_INT  = 31,
-    USB_EXTPHY_OEN   = 61,
-    USB_EXTPHY_VPO   = 63,
-    USB_EXTPHY_VMO   = 64,
-    SPI3_CLK         = 72,
-    SPI3_Q           = 73,
-    SPI3_D           = 74,
-    SPI3_HD          = 75,
-    SPI3_CS0         = 76,
-    SPI3_CS1         = 77,
-    SPI3_CS2         = 78,
-    LEDC_LS_SIG0     = 79,
-    LEDC_LS_SIG1     = 80,
-    LEDC_LS_SIG2     = 81,
-    LEDC_LS_SIG3     = 82,
-    LEDC_LS_SIG4     = 83,
-    LEDC_LS_SIG5     = 84,
-    LEDC_LS_SIG6     = 85,
-    LEDC_LS_SIG7     = 86,
-    RMT_SIG_0        = 87,
-    RMT_SIG_1        = 88,
-    RMT_SIG_2        = 89,
-    RMT_SIG_3        = 90,
-    I2CEXT1_SCL      = 95,
-    I2CEXT1_SDA      = 96,
-    GPIO_SD0         = 100,
-    GPIO_SD1         = 101,
-    GPIO_SD2         = 102,
-    GPIO_SD3         = 103,
-    GPIO_SD4         = 104,
-    GPIO_SD5         = 105,
-    GPIO_SD6         = 106,
-    GPIO_SD7         = 107,
-    FSPICLK          = 108,
-    FSPIQ            = 109,
-    FSPID            = 110,
-    FSPIHD           = 111,
-    FSPIWP           = 112,
-    FSPIIO4          = 113,
-    FSPIIO5          = 114,
-    FSPIIO6          = 115,
-    FSPIIO7          = 116,
-    FSPICS0          = 117,
-    FSPICS1          = 118,
-    FSPICS2          = 119,
-    FSPICS3          = 120,
-    FSPICS4          = 121,
-    FSPICS5          = 122,
-    TWAI_TX          = 123,
-    SUBSPICLK        = 126,
-    SUBSPIQ          = 127,
-    SUBSPID          = 128,
-    SUBSPIHD         = 129,
-    SUBSPIWP         = 130,
-    SUBSPICS0        = 131,
-    SUBSPICS1        = 132,
-    FSPIDQS          = 133,
-    FSPI_HSYNC       = 134,
-    FSPI_VSYNC       = 135,
-    FSPI_DE          = 136,
-    FSPICD           = 137,
-    SPI3_CD          = 139,
-    SPI3_DQS         = 140,
-    I2S0O_DATA_OUT23 = 166,
-    SUBSPID4         = 167,
-    SUBSPID5         = 168,
-    SUBSPID6         = 169,
-    SUBSPID7         = 170,
-    SUBSPIDQS        = 171,
-    PCMFSYNC         = 209,
-    PCMCLK           = 210,
-    CLK_I2S          = 251,
-    GPIO             = 256,
+    SPIQ               = 0,
+    SPID               = 1,
+    SPIHD              = 2,
+    SPIWP              = 3,
+    SPICLK             = 4,
+    SPICS0             = 5,
+    SPICS1             = 6,
+    SPID4              = 7,
+    SPID5              = 8,
+    SPID6              = 9,
+    SPID7              = 10,
+    SPIDQS             = 11,
+    U0TXD              = 14,
+    U0RTS              = 15,
+    U0DTR              = 16,
+    U1TXD              = 17,
+    U1RTS              = 18,
+    U1DTR              = 21,
+    I2S0O_BCK          = 23,
+    I2S0O_WS           = 25,
+    I2S0I_BCK          = 27,
+    I2S0I_WS           = 28,
+    I2CEXT0_SCL        = 29,
+    I2CEXT0_SDA        = 30,
+    SDIO_TOHOST_INT    = 31,
+    USB_EXTPHY_OEN     = 61,
+    USB_EXTPHY_VPO     = 63,
+    USB_EXTPHY_VMO     = 64,
+    SPI3_CLK           = 72,
+    SPI3_Q             = 73,
+    SPI3_D             = 74,
+    SPI3_HD            = 75,
+    SPI3_CS0           = 76,
+    SPI3_CS1           = 77,
+    SPI3_CS2           = 78,
+    LEDC_LS_SIG0       = 79,
+    LEDC_LS_SIG1       = 80,
+    LEDC_LS_SIG2       = 81,
+    LEDC_LS_SIG3       = 82,
+    LEDC_LS_SIG4       = 83,
+    LEDC_LS_SIG5       = 84,
+    LEDC_LS_SIG6       = 85,
+    LEDC_LS_SIG7       = 86,
+    RMT_SIG_0          = 87,
+    RMT_SIG_1          = 88,
+    RMT_SIG_2          = 89,
+    RMT_SIG_3          = 90,
+    I2CEXT1_SCL        = 95,
+    I2CEXT1_SDA        = 96,
+    GPIO_SD0           = 100,
+    GPIO_SD1           = 101,
+    GPIO_SD2           = 102,
+    GPIO_SD3           = 103,
+    GPIO_SD4           = 104,
+    GPIO_SD5           = 105,
+    GPIO_SD6           = 106,
+    GPIO_SD7           = 107,
+    FSPICLK            = 108,
+    FSPIQ              = 109,
+    FSPID              = 110,
+    FSPIHD             = 111,
+    FSPIWP             = 112,
+    FSPIIO4            = 113,
+    FSPIIO5            = 114,
+    FSPIIO6            = 115,
+    FSPIIO7            = 116,
+    FSPICS0            = 117,
+    FSPICS1            = 118,
+    FSPICS2            = 119,
+    FSPICS3            = 120,
+    FSPICS4            = 121,
+    FSPICS5            = 122,
+    TWAI_TX            = 123,
+    SUBSPICLK          = 126,
+    SUBSPIQ            = 127,
+    SUBSPID            = 128,
+    SUBSPIHD           = 129,
+    SUBSPIWP           = 130,
+    SUBSPICS0          = 131,
+    SUBSPICS1          = 132,
+    FSPIDQS            = 133,
+    FSPI_HSYNC         = 134,
+    FSPI_VSYNC         = 135,
+    FSPI_DE            = 136,
+    FSPICD             = 137,
+    SPI3_CD            = 139,
+    SPI3_DQS           = 140,
+    I2S0O_DATA_OUT23   = 166,
+    SUBSPID4           = 167,
+    SUBSPID5           = 168,
+    SUBSPID6           = 169,
+    SUBSPID7           = 170,
+    SUBSPIDQS          = 171,
+    PCMFSYNC           = 209,
+    PCMCLK             = 210,
+    PRO_ALONEGPIO_OUT0 = 235,
+    PRO_ALONEGPIO_OUT1 = 236,
+    PRO_ALONEGPIO_OUT2 = 237,
+    PRO_ALONEGPIO_OUT3 = 238,
+    PRO_ALONEGPIO_OUT4 = 239,
+    PRO_ALONEGPIO_OUT5 = 240,
+    PRO_ALONEGPIO_OUT6 = 241,
+    PRO_ALONEGPIO_OUT7 = 242,
+    CLK_I2S            = 251,
+    GPIO               = 256,
 }
 
 macro_rules! rtcio_analog {
@@ -17,6 +17,9 @@
 //!         nmi_enable to control the interrupt and NMI enable settings. The
 //!         function returns an u8 value representing the interrupt enable
 //!         settings.
+//!   - `set_pad_hold(gpio_num: u8, enable: bool)`:
+//!       * Enables or disables the hold of a pad, which keeps its configuration
+//!         and output level until it is released.
 //!   - `gpio` block:
 //!       * Defines the pin configurations for various GPIO pins. Each line
 //!         represents a pin and its associated options such as input/output
@@ -50,7 +53,7 @@ pub(crate) const FUNC_IN_SEL_OFFSET: usize = 0;
 pub(crate) type InputSignalType = u16;
 pub(crate) type OutputSignalType = u16;
 pub(crate) const OUTPUT_SIGNAL_MAX: u16 = 256;
-pub(crate) const INPUT_SIGNAL_MAX: u16 = 189;
+pub(crate) const INPUT_SIGNAL_MAX: u16 = 228;
 
 pub(crate) const ONE_INPUT: u8 = 0x38;
 pub(crate) const ZERO_INPUT: u8 = 0x3c;
@@ -65,6 +68,31 @@ pub(crate) fn gpio_intr_enable(int_enable: bool, nmi_enable: bool) -> u8 {
     int_enable as u8 | ((nmi_enable as u8) << 1)
 }
 
+/// Latches the configuration and level of a pad.
+///
+/// The RTC pads GPIO0-21 are held by the RTC controller, the digital pad hold
+/// register starts at GPIO21.
+pub(crate) fn set_pad_hold(gpio_num: u8, enable: bool) {
+    let rtc_cntl = crate::peripherals::LPWR::regs();
+    if gpio_num <= 21 {
+        rtc_cntl.pad_hold().modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | 1 << gpio_num)
+            } else {
+                w.bits(r.bits() & !(1 << gpio_num))
+            }
+        });
+    } else {
+        rtc_cntl.dig_pad_hold().modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | 1 << (gpio_num - 21))
+            } else {
+                w.bits(r.bits() & !(1 << (gpio_num - 21)))
+            }
+        });
+    }
+}
+
 /// Peripheral input signals for the GPIO mux
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -226,6 +254,14 @@ pub enum InputSignal {
     SDHOST_CDATA_IN_25      = 218,
     SDHOST_CDATA_IN_26      = 219,
     SDHOST_CDATA_IN_27      = 220,
+    PRO_ALONEGPIO_IN0       = 221,
+    PRO_ALONEGPIO_IN1       = 222,
+    PRO_ALONEGPIO_IN2       = 223,
+    PRO_ALONEGPIO_IN3       = 224,
+    PRO_ALONEGPIO_IN4       = 225,
+    PRO_ALONEGPIO_IN5       = 226,
+    PRO_ALONEGPIO_IN6       = 227,
+    PRO_ALONEGPIO_IN7       = 228,
 }
 
 /// Peripheral output signals for the GPIO mux
@@ -392,6 +428,14 @@ pub enum OutputSignal {
     SDHOST_CDATA_OUT_25        = 218,
     SDHOST_CDATA_OUT_26        = 219,
     SDHOST_CDATA_OUT_27        = 220,
+    PRO_ALONEGPIO_OUT0         = 221,
+    PRO_ALONEGPIO_OUT1         = 222,
+    PRO_ALONEGPIO_OUT2         = 223,
+    PRO_ALONEGPIO_OUT3         = 224,
+    PRO_ALONEGPIO_OUT4         = 225,
+    PRO_ALONEGPIO_OUT5         = 226,
+    PRO_ALONEGPIO_OUT6         = 227,
+    PRO_ALONEGPIO_OUT7         = 228,
     GPIO                       = 256,
 }
 
@@ -35,6 +35,7 @@ crate::peripherals! {
         ASSIST_DEBUG <= ASSIST_DEBUG,
         BT <= virtual,
         CPU_CTRL <= virtual,
+        DEDICATED_GPIO <= virtual,
         DMA <= DMA,
         DS <= DS,
         EFUSE <= EFUSE,
@@ -26,6 +26,70 @@
 //! implementations provided by [`embedded-hal-bus`] and
 //! [`embassy-embedded-hal`].
 //!
+//! Alternatively, the CS lines of up to three (ESP32) or six devices can be
+//! driven by the peripheral itself: connect them with [`Spi::with_cs`],
+//! [`Spi::with_cs1`], etc., and pick the device of the next transactions with
+//! [`Spi::select_cs`]. This keeps the [CS timing](#cs-timing) guarantees for
+//! every device. `Cs0` to `Cs2` are available on every instance; the lines
+//! some instances don't have need a driver created with [`Spi::new_typed`],
+//! so using a missing line fails to compile.
+//!
+//! The frequency and mode can be changed at any time with
+//! [`Spi::apply_config`], without reconstructing the driver. The
+//! `SpiDeviceWithConfig` types of [`embassy-embedded-hal`] use this to apply a
+//! per-device [`Config`] before each transaction.
+//!
+//! ### CS timing
+//!
+//! Some devices need CS to be asserted for a while before the first clock
+//! edge, or held after the last one. [`Config`] sets these delays, along with
+//! the time CS stays deasserted between transactions, as a whole number of
+//! SCK cycles. To meet a minimum delay given in nanoseconds, round up:
+//! `cycles = ceil(delay_ns * frequency_hz / 1_000_000_000)`.
+//!
+//! The SPI doesn't start clocking before the setup time has passed, and a
+//! transfer only completes after the hold time, so these two delays also
+//! apply when CS is a GPIO toggled around the transfer, e.g. by
+//! `ExclusiveDevice`. The idle time only applies to the CS signal driven by
+//! the SPI peripheral.
+//!
+//! | Chip       | Setup time | Hold time | Idle time   |
+//! |------------|------------|-----------|-------------|
+//! | ESP32      | 0..=16     | 0..=16    | 0..=15      |
+//! | ESP32-S2   | 0..=8192   | 0..=8192  | 0..=63      |
+//! | Others     | 0..=32     | 0..=32    | unsupported |
+//!
+//! Values outside these ranges are rejected with
+//! [`ConfigError::UnsupportedCsTiming`].
+//!
+//! ### Dual and quad data lines
+//!
+//! Half-duplex transfers can move the command, address and data phases over
+//! one, two or four data lines, selected per phase with [`DataMode`]. Quad
+//! transfers additionally need the SIO2 and SIO3 pins ([`Spi::with_sio2`],
+//! [`Spi::with_sio3`]). Devices like QSPI flash typically keep the command on
+//! a single line and only use more lines for the address and data.
+//!
+//! Connecting the SIO2 and SIO3 pins of an instance that doesn't have these
+//! signals fails to compile if the driver was created with
+//! [`Spi::new_typed`], and panics if it was created with [`Spi::new`].
+//!
+//! The following combinations are rejected with [`Error::Unsupported`] before
+//! the transfer starts:
+//!
+//! - [`DataMode::Single`] (three-wire mode) mixed with any other mode,
+//! - [`DataMode::Quad`] without the SIO2 and SIO3 signals,
+//! - octal transfers, which the driver doesn't support yet,
+//! - on the ESP32, a command that isn't single-line, or an address that uses
+//!   more than one line but a different number of lines than the data.
+//!
+//! ### Transaction sequences
+//!
+//! On chips with GDMA, `SpiDmaBus` can run a `TransactionSequence` of
+//! half-duplex transactions back-to-back, with the peripheral reloading its
+//! configuration from the DMA stream between them. The CPU only starts the
+//! sequence and collects the data of the read segments when it's done.
+//!
 //! ## Usage
 //!
 //! The module implements several third-party traits from embedded-hal@1.x.x
@@ -34,12 +98,13 @@
 //! [`embedded-hal-bus`]: https://docs.rs/embedded-hal-bus/latest/embedded_hal_bus/spi/index.html
 //! [`embassy-embedded-hal`]: embassy_embedded_hal::shared_bus
 
-use core::marker::PhantomData;
+use core::{marker::PhantomData, sync::atomic::Ordering};
 
 #[instability::unstable]
 pub use dma::*;
 use enumset::{EnumSet, EnumSetType};
 use fugit::{HertzU32, RateExtU32};
+use portable_atomic::AtomicUsize;
 #[cfg(place_spi_driver_in_ram)]
 use procmacros::ram;
 
@@ -414,6 +479,53 @@ impl Address {
     }
 }
 
+/// The number of hardware CS lines of the SPI2 peripheral.
+const MAX_CS_LINES: usize = if cfg!(esp32) { 3 } else { 6 };
+
+/// A hardware chip select line.
+///
+/// Each line is connected to a pin with the corresponding `with_cs*` method,
+/// and [`Spi::select_cs`] picks the line that subsequent transactions
+/// assert. Only the lines of the SPI instance, see [`HasCsLine`], can be used.
+#[instability::unstable]
+pub trait CsLine: Copy + Sealed {
+    #[doc(hidden)]
+    const INDEX: usize;
+}
+
+macro_rules! cs_lines {
+    ($($(#[$meta:meta])* $line:ident = $index:literal, $method:ident;)+) => {
+        $(
+            #[doc = concat!("The CS", $index, " line, connected with [`Spi::", stringify!($method), "`].")]
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+            #[instability::unstable]
+            pub struct $line;
+
+            $(#[$meta])*
+            impl Sealed for $line {}
+
+            $(#[$meta])*
+            impl CsLine for $line {
+                const INDEX: usize = $index;
+            }
+        )+
+    };
+}
+
+cs_lines! {
+    Cs0 = 0, with_cs;
+    Cs1 = 1, with_cs1;
+    Cs2 = 2, with_cs2;
+    #[cfg(not(esp32))]
+    Cs3 = 3, with_cs3;
+    #[cfg(not(esp32))]
+    Cs4 = 4, with_cs4;
+    #[cfg(not(esp32))]
+    Cs5 = 5, with_cs5;
+}
+
 /// SPI clock source.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -460,6 +572,36 @@ pub struct Config {
 
     /// Bit order of the written data.
     write_bit_order: BitOrder,
+
+    /// The number of SCK cycles CS is asserted before the first clock edge.
+    ///
+    /// See [CS timing](self#cs-timing) for the supported range.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    cs_setup_time: u16,
+
+    /// The number of SCK cycles CS is kept asserted after the last clock
+    /// edge.
+    ///
+    /// See [CS timing](self#cs-timing) for the supported range.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    cs_hold_time: u16,
+
+    /// The number of SCK cycles CS is kept deasserted between two
+    /// transactions.
+    ///
+    /// See [CS timing](self#cs-timing) for the supported range.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    cs_idle_time: u16,
+
+    /// The longest transfer, in bytes, that an async
+    /// [`SpiDmaBus`](dma::SpiDmaBus) moves through the FIFO instead of
+    /// setting up a DMA transfer.
+    ///
+    /// The DMA setup dominates the duration of short transfers, which complete
+    /// sooner when the CPU fills the FIFO and waits for the transfer done
+    /// interrupt. Set to 0 to use DMA for every transfer.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    fifo_threshold: usize,
 }
 
 impl Default for Config {
@@ -471,6 +613,10 @@ impl Default for Config {
             mode: Mode::_0,
             read_bit_order: BitOrder::MsbFirst,
             write_bit_order: BitOrder::MsbFirst,
+            cs_setup_time: 1,
+            cs_hold_time: 1,
+            cs_idle_time: 0,
+            fifo_threshold: 64,
         };
 
         this.reg = this.recalculate();
@@ -487,11 +633,20 @@ impl core::hash::Hash for Config {
         self.mode.hash(state);
         self.read_bit_order.hash(state);
         self.write_bit_order.hash(state);
+        self.cs_setup_time.hash(state);
+        self.cs_hold_time.hash(state);
+        self.cs_idle_time.hash(state);
+        self.fifo_threshold.hash(state);
     }
 }
 
 impl Config {
     /// Set the frequency of the SPI bus clock.
+    ///
+    /// The bus runs at the closest frequency the clock dividers can produce.
+    /// Applying the configuration fails with
+    /// [`ConfigError::UnsupportedFrequency`] if that frequency differs from
+    /// the requested one by more than 10%.
     pub fn with_frequency(mut self, frequency: HertzU32) -> Self {
         self.frequency = frequency;
         self.reg = self.recalculate();
@@ -521,15 +676,23 @@ impl Config {
             }
         }
 
+        // In HW, n, h and l fields range from 1 to 64, pre ranges from 1 to 8K
+        // (ESP32, ESP32-S2) or 1 to 16 (other chips).
+        // The value written to register is one lower than the used value.
+        const MAX_PRE: i32 = if cfg!(any(esp32, esp32s2)) { 8192 } else { 16 };
+
+        if self.frequency.raw() == 0 {
+            return Err(ConfigError::UnsupportedFrequency);
+        }
+
         let reg_val: u32;
+        let actual_frequency: u32;
         let duty_cycle = 128;
 
-        // In HW, n, h and l fields range from 1 to 64, pre ranges from 1 to 8K.
-        // The value written to register is one lower than the used value.
-
         if self.frequency > ((apb_clk_freq / 4) * 3) {
             // Using APB frequency directly will give us the best result here.
             reg_val = 1 << 31;
+            actual_frequency = apb_clk_freq.raw();
         } else {
             // For best duty cycle resolution, we want n to be as close to 32 as
             // possible, but we also need a pre/n combo that gets us as close as
@@ -560,8 +723,8 @@ impl Config {
                     pre = 1;
                 }
 
-                if pre > 16 {
-                    pre = 16;
+                if pre > MAX_PRE {
+                    pre = MAX_PRE;
                 }
 
                 errval = (raw_apb_freq / (pre * n) - raw_freq).abs();
@@ -588,6 +751,11 @@ impl Config {
                 | ((h as u32 - 1) << 6)
                 | ((n as u32 - 1) << 12)
                 | ((pre as u32 - 1) << 18);
+            actual_frequency = (raw_apb_freq / (pre * n)) as u32;
+        }
+
+        if actual_frequency.abs_diff(self.frequency.raw()) > self.frequency.raw() / 10 {
+            return Err(ConfigError::UnsupportedFrequency);
         }
 
         Ok(reg_val)
@@ -596,6 +764,33 @@ impl Config {
     fn raw_clock_reg_value(&self) -> Result<u32, ConfigError> {
         self.reg
     }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        cfg_if::cfg_if! {
+            if #[cfg(esp32)] {
+                const MAX_CS_SETUP_TIME: u16 = 16;
+                const MAX_CS_HOLD_TIME: u16 = 16;
+                const MAX_CS_IDLE_TIME: u16 = 15;
+            } else if #[cfg(esp32s2)] {
+                const MAX_CS_SETUP_TIME: u16 = 8192;
+                const MAX_CS_HOLD_TIME: u16 = 8192;
+                const MAX_CS_IDLE_TIME: u16 = 63;
+            } else {
+                const MAX_CS_SETUP_TIME: u16 = 32;
+                const MAX_CS_HOLD_TIME: u16 = 32;
+                const MAX_CS_IDLE_TIME: u16 = 0;
+            }
+        }
+
+        if self.cs_setup_time > MAX_CS_SETUP_TIME
+            || self.cs_hold_time > MAX_CS_HOLD_TIME
+            || self.cs_idle_time > MAX_CS_IDLE_TIME
+        {
+            return Err(ConfigError::UnsupportedCsTiming);
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -603,7 +798,7 @@ impl Config {
 struct SpiPinGuard {
     mosi_pin: PinGuard,
     sclk_pin: PinGuard,
-    cs_pin: PinGuard,
+    cs_pins: [Option<PinGuard>; MAX_CS_LINES],
     sio1_pin: PinGuard,
     sio2_pin: Option<PinGuard>,
     sio3_pin: Option<PinGuard>,
@@ -613,7 +808,31 @@ struct SpiPinGuard {
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub enum ConfigError {}
+pub enum ConfigError {
+    /// The requested CS setup, hold or idle time is not supported.
+    UnsupportedCsTiming,
+
+    /// The requested bus frequency can't be produced within 10%.
+    UnsupportedFrequency,
+}
+
+impl core::error::Error for ConfigError {}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::UnsupportedCsTiming => {
+                write!(
+                    f,
+                    "The requested CS setup, hold or idle time is not supported"
+                )
+            }
+            ConfigError::UnsupportedFrequency => {
+                write!(f, "The requested bus frequency is not supported")
+            }
+        }
+    }
+}
 
 /// SPI peripheral driver
 ///
@@ -632,18 +851,26 @@ pub enum ConfigError {}
 /// # Ok(())
 /// # }
 /// ```
+/// 
+/// The `T` parameter is the SPI instance if the driver was created with
+/// [`Spi::new_typed`], which unlocks the features that not every instance
+/// has, like the SIO2 and SIO3 signals of
+/// [quad transfers](self#dual-and-quad-data-lines) and the
+/// [extra CS lines](self#shared-spi-access). Drivers created with
+/// [`Spi::new`] can use the features common to all instances.
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct Spi<'d, Dm> {
+pub struct Spi<'d, Dm, T = AnySpi> {
     spi: PeripheralRef<'d, AnySpi>,
     _mode: PhantomData<Dm>,
+    _instance: PhantomData<T>,
     guard: PeripheralGuard,
     pins: SpiPinGuard,
 }
 
-impl<Dm: DriverMode> Sealed for Spi<'_, Dm> {}
+impl<Dm: DriverMode, T> Sealed for Spi<'_, Dm, T> {}
 
-impl<Dm> Spi<'_, Dm>
+impl<Dm, T> Spi<'_, Dm, T>
 where
     Dm: DriverMode,
 {
@@ -683,12 +910,42 @@ impl<'d> Spi<'d, Blocking> {
         config: Config,
     ) -> Result<Self, ConfigError> {
         crate::into_mapped_ref!(spi);
+        Self::new_inner(spi, config)
+    }
+}
+
+impl<'d, T> Spi<'d, Blocking, T>
+where
+    T: PeripheralInstance,
+{
+    /// Constructs an SPI instance in 8bit dataframe mode, keeping the type of
+    /// the instance.
+    ///
+    /// Unlike [`Spi::new`], this allows connecting the CS lines and the SIO2
+    /// and SIO3 signals of instances that have them, and rejects the others at
+    /// compile time.
+    #[instability::unstable]
+    pub fn new_typed(
+        spi: impl Peripheral<P = T> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        crate::into_mapped_ref!(spi);
+        Self::new_inner(spi, config)
+    }
+}
 
+impl<'d, T> Spi<'d, Blocking, T> {
+    fn new_inner(spi: PeripheralRef<'d, AnySpi>, config: Config) -> Result<Self, ConfigError> {
         let guard = PeripheralGuard::new(spi.info().peripheral);
 
         let mosi_pin = PinGuard::new_unconnected(spi.info().mosi);
         let sclk_pin = PinGuard::new_unconnected(spi.info().sclk);
-        let cs_pin = PinGuard::new_unconnected(spi.info().cs);
+        let cs_pins = core::array::from_fn(|line| {
+            spi.info()
+                .cs
+                .get(line)
+                .map(|signal| PinGuard::new_unconnected(*signal))
+        });
         let sio1_pin = PinGuard::new_unconnected(spi.info().sio1_output);
         let sio2_pin = spi.info().sio2_output.map(PinGuard::new_unconnected);
         let sio3_pin = spi.info().sio3_output.map(PinGuard::new_unconnected);
@@ -696,11 +953,12 @@ impl<'d> Spi<'d, Blocking> {
         let mut this = Spi {
             spi,
             _mode: PhantomData,
+            _instance: PhantomData,
             guard,
             pins: SpiPinGuard {
                 mosi_pin,
                 sclk_pin,
-                cs_pin,
+                cs_pins,
                 sio1_pin,
                 sio2_pin,
                 sio3_pin,
@@ -708,6 +966,7 @@ impl<'d> Spi<'d, Blocking> {
         };
 
         this.driver().init();
+        this.driver().select_cs(Cs0::INDEX);
         this.apply_config(&config)?;
 
         let this = this
@@ -728,11 +987,12 @@ impl<'d> Spi<'d, Blocking> {
     }
 
     /// Converts the SPI instance into async mode.
-    pub fn into_async(mut self) -> Spi<'d, Async> {
+    pub fn into_async(mut self) -> Spi<'d, Async, T> {
         self.set_interrupt_handler(self.spi.handler());
         Spi {
             spi: self.spi,
             _mode: PhantomData,
+            _instance: PhantomData,
             guard: self.guard,
             pins: self.pins,
         }
@@ -777,7 +1037,7 @@ impl<'d> Spi<'d, Blocking> {
     /// # }
     /// ```
     #[instability::unstable]
-    pub fn with_dma<CH>(self, channel: impl Peripheral<P = CH> + 'd) -> SpiDma<'d, Blocking>
+    pub fn with_dma<CH>(self, channel: impl Peripheral<P = CH> + 'd) -> SpiDma<'d, Blocking, T>
     where
         CH: DmaChannelFor<AnySpi>,
     {
@@ -819,7 +1079,7 @@ impl<'d> Spi<'d, Blocking> {
 }
 
 #[instability::unstable]
-impl crate::interrupt::InterruptConfigurable for Spi<'_, Blocking> {
+impl<T> crate::interrupt::InterruptConfigurable for Spi<'_, Blocking, T> {
     /// Sets the interrupt handler
     ///
     /// Interrupts are not enabled at the peripheral level here.
@@ -828,13 +1088,14 @@ impl crate::interrupt::InterruptConfigurable for Spi<'_, Blocking> {
     }
 }
 
-impl<'d> Spi<'d, Async> {
+impl<'d, T> Spi<'d, Async, T> {
     /// Converts the SPI instance into blocking mode.
-    pub fn into_blocking(self) -> Spi<'d, Blocking> {
+    pub fn into_blocking(self) -> Spi<'d, Blocking, T> {
         crate::interrupt::disable(Cpu::current(), self.driver().info.interrupt);
         Spi {
             spi: self.spi,
             _mode: PhantomData,
+            _instance: PhantomData,
             guard: self.guard,
             pins: self.pins,
         }
@@ -863,7 +1124,7 @@ impl<'d> Spi<'d, Async> {
     }
 }
 
-impl<'d, Dm> Spi<'d, Dm>
+impl<'d, Dm, T> Spi<'d, Dm, T>
 where
     Dm: DriverMode,
 {
@@ -976,19 +1237,88 @@ where
     /// Assign the CS (Chip Select) pin for the SPI instance.
     ///
     /// Configures the specified pin to push-pull output and connects it to the
-    /// SPI CS signal.
+    /// SPI CS0 signal, which is the line selected by default.
     ///
     /// Disconnects the previous pin that was assigned with `with_cs`.
+    #[instability::unstable]
+    pub fn with_cs<CS: PeripheralOutput>(self, cs: impl Peripheral<P = CS> + 'd) -> Self {
+        self.with_cs_line::<Cs0, _>(cs)
+    }
+
+    /// Assign the CS1 pin for the SPI instance.
     ///
-    /// # Current Stability Limitations
-    /// The hardware chip select functionality is limited; only one CS line can
-    /// be set, regardless of the total number available. There is no
-    /// mechanism to select which CS line to use.
+    /// Works like [`Self::with_cs`], for the line selected by [`Cs1`]. Only
+    /// available if the SPI instance has this line.
+    #[instability::unstable]
+    pub fn with_cs1<CS: PeripheralOutput>(self, cs: impl Peripheral<P = CS> + 'd) -> Self
+    where
+        T: HasCsLine<Cs1>,
+    {
+        self.with_cs_line::<Cs1, _>(cs)
+    }
+
+    /// Assign the CS2 pin for the SPI instance.
+    ///
+    /// Works like [`Self::with_cs`], for the line selected by [`Cs2`]. Only
+    /// available if the SPI instance has this line.
+    #[instability::unstable]
+    pub fn with_cs2<CS: PeripheralOutput>(self, cs: impl Peripheral<P = CS> + 'd) -> Self
+    where
+        T: HasCsLine<Cs2>,
+    {
+        self.with_cs_line::<Cs2, _>(cs)
+    }
+
+    /// Assign the CS3 pin for the SPI instance.
+    ///
+    /// Works like [`Self::with_cs`], for the line selected by [`Cs3`]. Only
+    /// available if the SPI instance has this line.
+    #[cfg(not(esp32))]
+    #[instability::unstable]
+    pub fn with_cs3<CS: PeripheralOutput>(self, cs: impl Peripheral<P = CS> + 'd) -> Self
+    where
+        T: HasCsLine<Cs3>,
+    {
+        self.with_cs_line::<Cs3, _>(cs)
+    }
+
+    /// Assign the CS4 pin for the SPI instance.
+    ///
+    /// Works like [`Self::with_cs`], for the line selected by [`Cs4`]. Only
+    /// available if the SPI instance has this line.
+    #[cfg(not(esp32))]
+    #[instability::unstable]
+    pub fn with_cs4<CS: PeripheralOutput>(self, cs: impl Peripheral<P = CS> + 'd) -> Self
+    where
+        T: HasCsLine<Cs4>,
+    {
+        self.with_cs_line::<Cs4, _>(cs)
+    }
+
+    /// Assign the CS5 pin for the SPI instance.
+    ///
+    /// Works like [`Self::with_cs`], for the line selected by [`Cs5`]. Only
+    /// available if the SPI instance has this line.
+    #[cfg(not(esp32))]
     #[instability::unstable]
-    pub fn with_cs<CS: PeripheralOutput>(mut self, cs: impl Peripheral<P = CS> + 'd) -> Self {
+    pub fn with_cs5<CS: PeripheralOutput>(self, cs: impl Peripheral<P = CS> + 'd) -> Self
+    where
+        T: HasCsLine<Cs5>,
+    {
+        self.with_cs_line::<Cs5, _>(cs)
+    }
+
+    fn with_cs_line<L: CsLine, CS: PeripheralOutput>(
+        mut self,
+        cs: impl Peripheral<P = CS> + 'd,
+    ) -> Self {
         crate::into_mapped_ref!(cs);
+        // All instances have the lines that `AnySpi` has, and typed instances
+        // only have the lines they implement `HasCsLine` for.
+        let signal = self.driver().info.cs[L::INDEX];
+
         cs.set_to_push_pull_output();
-        self.pins.cs_pin = OutputConnection::connect_with_guard(cs, self.driver().info.cs);
+        self.pins.cs_pins[L::INDEX] = Some(OutputConnection::connect_with_guard(cs, signal));
 
         self
     }
@@ -998,10 +1328,26 @@ where
     pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
         self.driver().apply_config(config)
     }
+
+    /// Selects the hardware CS line asserted by the following transactions.
+    ///
+    /// The other lines stay deasserted, so several devices can share the bus
+    /// with the peripheral handling the CS timing of each of them. Waits for
+    /// the current transaction to finish first.
+    #[instability::unstable]
+    pub fn select_cs<L: CsLine>(&mut self, _cs: L)
+    where
+        T: HasCsLine<L>,
+    {
+        // The blocking transfer functions may return while a transfer is still
+        // in progress.
+        unwrap!(self.driver().flush());
+        self.driver().select_cs(L::INDEX);
+    }
 }
 
 #[instability::unstable]
-impl<Dm> embassy_embedded_hal::SetConfig for Spi<'_, Dm>
+impl<Dm, T> embassy_embedded_hal::SetConfig for Spi<'_, Dm, T>
 where
     Dm: DriverMode,
 {
@@ -1013,7 +1359,7 @@ where
     }
 }
 
-impl<'d, Dm> Spi<'d, Dm>
+impl<'d, Dm, T> Spi<'d, Dm, T>
 where
     Dm: DriverMode,
 {
@@ -1022,20 +1368,30 @@ where
     /// Enables both input and output functionality for the pin, and connects it
     /// to the SIO2 output and input signals.
     ///
+    /// Only available if the SPI instance is QSPI-capable.
+    ///
     /// # Current Stability Limitations
     /// QSPI operations are unstable, associated pins configuration is
     /// inefficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the driver was created with [`Spi::new`] for an instance
+    /// that doesn't have the SIO2 signals.
     #[instability::unstable]
-    pub fn with_sio2<SIO2: PeripheralOutput>(
-        mut self,
-        sio2: impl Peripheral<P = SIO2> + 'd,
-    ) -> Self {
-        // TODO: panic if not QSPI?
+    pub fn with_sio2<SIO2: PeripheralOutput>(mut self, sio2: impl Peripheral<P = SIO2> + 'd) -> Self
+    where
+        T: QspiInstance,
+    {
         crate::into_mapped_ref!(sio2);
+        let input = unwrap!(
+            self.driver().info.sio2_input,
+            "The SPI instance doesn't have the SIO2 signals"
+        );
         sio2.enable_input(true);
         sio2.enable_output(true);
 
-        unwrap!(self.driver().info.sio2_input).connect_to(&mut sio2);
+        input.connect_to(&mut sio2);
         self.pins.sio2_pin = self
             .driver()
             .info
@@ -1050,20 +1406,30 @@ where
     /// Enables both input and output functionality for the pin, and connects it
     /// to the SIO3 output and input signals.
     ///
+    /// Only available if the SPI instance is QSPI-capable.
+    ///
     /// # Current Stability Limitations
     /// QSPI operations are unstable, associated pins configuration is
     /// inefficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the driver was created with [`Spi::new`] for an instance
+    /// that doesn't have the SIO3 signals.
     #[instability::unstable]
-    pub fn with_sio3<SIO3: PeripheralOutput>(
-        mut self,
-        sio3: impl Peripheral<P = SIO3> + 'd,
-    ) -> Self {
-        // TODO: panic if not QSPI?
+    pub fn with_sio3<SIO3: PeripheralOutput>(mut self, sio3: impl Peripheral<P = SIO3> + 'd) -> Self
+    where
+        T: QspiInstance,
+    {
         crate::into_mapped_ref!(sio3);
+        let input = unwrap!(
+            self.driver().info.sio3_input,
+            "The SPI instance doesn't have the SIO3 signals"
+        );
         sio3.enable_input(true);
         sio3.enable_output(true);
 
-        unwrap!(self.driver().info.sio3_input).connect_to(&mut sio3);
+        input.connect_to(&mut sio3);
         self.pins.sio3_pin = self
             .driver()
             .info
@@ -1074,17 +1440,20 @@ where
     }
 }
 
-impl<Dm> Spi<'_, Dm>
+impl<Dm, T> Spi<'_, Dm, T>
 where
     Dm: DriverMode,
 {
     /// Half-duplex read.
     ///
+    /// An empty `buffer` skips the data phase, so only the command, address
+    /// and dummy phases are performed.
+    ///
     /// # Errors
     ///
     /// The corresponding error variant from [`Error`] will be returned if
-    /// passed buffer is bigger than FIFO size or if buffer is empty (currently
-    /// unsupported).
+    /// passed buffer is bigger than FIFO size, or if the data modes can't
+    /// be combined (see the [module documentation](crate::spi::master)).
     #[instability::unstable]
     pub fn half_duplex_read(
         &mut self,
@@ -1099,7 +1468,9 @@ where
         }
 
         if buffer.is_empty() {
-            return Err(Error::Unsupported);
+            // Without a data phase, there is no difference between a read and a
+            // write on the bus.
+            return self.half_duplex_write(data_mode, cmd, address, dummy, &[]);
         }
 
         self.driver().setup_half_duplex(
@@ -1123,7 +1494,8 @@ where
     /// # Errors
     ///
     /// The corresponding error variant from [`Error`] will be returned if
-    /// passed buffer is bigger than FIFO size.
+    /// passed buffer is bigger than FIFO size, or if the data modes can't
+    /// be combined (see the [module documentation](crate::spi::master)).
     #[cfg_attr(
         esp32,
         doc = "Dummy phase configuration is currently not supported, only value `0` is valid (see issue [#2240](https://github.com/esp-rs/esp-hal/issues/2240))."
@@ -1185,11 +1557,40 @@ where
 
         self.driver().flush()
     }
+
+    /// 3-wire transfer: writes `write`, then reads into `read` over the SIO0
+    /// line, within a single CS assertion.
+    ///
+    /// The data line needs to be assigned with [`Self::with_sio0`]. The
+    /// peripheral only drives it while writing, and releases it before the
+    /// first clock cycle of the read, so the device can drive the line
+    /// without bus contention.
+    #[instability::unstable]
+    pub fn transfer_sio(&mut self, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        self.driver().set_cs_keep_active(true);
+        let result = self.transfer_sio_parts(write, read);
+        self.driver().set_cs_keep_active(false);
+
+        result
+    }
+
+    fn transfer_sio_parts(&mut self, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        for chunk in write.chunks(FIFO_SIZE) {
+            self.half_duplex_write(DataMode::Single, Command::None, Address::None, 0, chunk)?;
+        }
+        for chunk in read.chunks_mut(FIFO_SIZE) {
+            self.half_duplex_read(DataMode::Single, Command::None, Address::None, 0, chunk)?;
+        }
+
+        Ok(())
+    }
 }
 
 mod dma {
+    #[cfg(gdma)]
+    use core::ops::ControlFlow;
     use core::{
-        cmp::min,
+        cmp::{max, min},
         mem::ManuallyDrop,
         sync::atomic::{fence, Ordering},
     };
@@ -1203,6 +1604,19 @@ mod dma {
         EmptyBuf,
         PeripheralDmaChannel,
     };
+    #[cfg(pdma)]
+    use crate::dma::{
+        dma_private::{DmaSupport, DmaSupportTx},
+        ChannelTx,
+        DescriptorChain,
+        DmaDescriptor,
+        DmaTransferTxCircular,
+        DmaTxInterrupt,
+        PeripheralTxChannel,
+        ReadBuffer,
+    };
+    #[cfg(gdma)]
+    use crate::timer::PeriodicTimer;
 
     /// A DMA capable SPI instance.
     ///
@@ -1245,12 +1659,13 @@ mod dma {
     /// # }
     /// ```
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-    pub struct SpiDma<'d, Dm>
+    pub struct SpiDma<'d, Dm, T = AnySpi>
     where
         Dm: DriverMode,
     {
         pub(crate) spi: PeripheralRef<'d, AnySpi>,
         pub(crate) channel: Channel<'d, Dm, PeripheralDmaChannel<AnySpi>>,
+        _instance: PhantomData<T>,
         tx_transfer_in_progress: bool,
         rx_transfer_in_progress: bool,
         #[cfg(all(esp32, spi_address_workaround))]
@@ -1259,14 +1674,16 @@ mod dma {
         pins: SpiPinGuard,
     }
 
-    impl<Dm> crate::private::Sealed for SpiDma<'_, Dm> where Dm: DriverMode {}
+    impl<Dm, T> crate::private::Sealed for SpiDma<'_, Dm, T> where Dm: DriverMode {}
 
-    impl<'d> SpiDma<'d, Blocking> {
+    impl<'d, T> SpiDma<'d, Blocking, T> {
         /// Converts the SPI instance into async mode.
         #[instability::unstable]
-        pub fn into_async(self) -> SpiDma<'d, Async> {
+        pub fn into_async(mut self) -> SpiDma<'d, Async, T> {
+            self.set_interrupt_handler(self.spi.handler());
             SpiDma {
                 spi: self.spi,
+                _instance: PhantomData,
                 channel: self.channel.into_async(),
                 tx_transfer_in_progress: self.tx_transfer_in_progress,
                 rx_transfer_in_progress: self.rx_transfer_in_progress,
@@ -1278,12 +1695,14 @@ mod dma {
         }
     }
 
-    impl<'d> SpiDma<'d, Async> {
+    impl<'d, T> SpiDma<'d, Async, T> {
         /// Converts the SPI instance into async mode.
         #[instability::unstable]
-        pub fn into_blocking(self) -> SpiDma<'d, Blocking> {
+        pub fn into_blocking(self) -> SpiDma<'d, Blocking, T> {
+            crate::interrupt::disable(Cpu::current(), self.driver().info.interrupt);
             SpiDma {
                 spi: self.spi,
+                _instance: PhantomData,
                 channel: self.channel.into_blocking(),
                 tx_transfer_in_progress: self.tx_transfer_in_progress,
                 rx_transfer_in_progress: self.rx_transfer_in_progress,
@@ -1295,7 +1714,7 @@ mod dma {
         }
     }
 
-    impl<Dm> core::fmt::Debug for SpiDma<'_, Dm>
+    impl<Dm, T> core::fmt::Debug for SpiDma<'_, Dm, T>
     where
         Dm: DriverMode,
     {
@@ -1309,10 +1728,16 @@ mod dma {
     }
 
     #[instability::unstable]
-    impl crate::interrupt::InterruptConfigurable for SpiDma<'_, Blocking> {
+    impl<T> crate::interrupt::InterruptConfigurable for SpiDma<'_, Blocking, T> {
         /// Sets the interrupt handler
         ///
         /// Interrupts are not enabled at the peripheral level here.
+        fn set_interrupt_handler(&mut self, handler: InterruptHandler) {
+            SpiDma::set_interrupt_handler(self, handler);
+        }
+    }
+
+    impl<T> SpiDma<'_, Blocking, T> {
         fn set_interrupt_handler(&mut self, handler: InterruptHandler) {
             let interrupt = self.driver().info.interrupt;
             for core in crate::Cpu::other() {
@@ -1321,9 +1746,7 @@ mod dma {
             unsafe { crate::interrupt::bind_interrupt(interrupt, handler.handler()) };
             unwrap!(crate::interrupt::enable(interrupt, handler.priority()));
         }
-    }
 
-    impl SpiDma<'_, Blocking> {
         /// Listen for the given interrupts
         #[instability::unstable]
         pub fn listen(&mut self, interrupts: impl Into<EnumSet<SpiInterrupt>>) {
@@ -1349,7 +1772,7 @@ mod dma {
         }
     }
 
-    impl<'d> SpiDma<'d, Blocking> {
+    impl<'d, T> SpiDma<'d, Blocking, T> {
         pub(super) fn new(
             spi: PeripheralRef<'d, AnySpi>,
             pins: SpiPinGuard,
@@ -1382,6 +1805,7 @@ mod dma {
             Self {
                 spi,
                 channel,
+                _instance: PhantomData,
                 #[cfg(all(esp32, spi_address_workaround))]
                 address_buffer,
                 tx_transfer_in_progress: false,
@@ -1392,7 +1816,7 @@ mod dma {
         }
     }
 
-    impl<'d, Dm> SpiDma<'d, Dm>
+    impl<'d, Dm, T> SpiDma<'d, Dm, T>
     where
         Dm: DriverMode,
     {
@@ -1442,8 +1866,6 @@ mod dma {
         }
 
         async fn wait_for_idle_async(&mut self) {
-            // As a future enhancement, setup Spi Future in here as well.
-
             if self.rx_transfer_in_progress {
                 _ = DmaRxFuture::new(&mut self.channel.rx).await;
                 self.rx_transfer_in_progress = false;
@@ -1453,12 +1875,23 @@ mod dma {
                 self.tx_transfer_in_progress = false;
             }
 
+            // The SPI may still be shifting out the last bits after the DMA is done,
+            // so wait for the transfer done interrupt.
+            let driver = self.driver();
             core::future::poll_fn(|cx| {
                 use core::task::Poll;
+                driver.state.waker.register(cx.waker());
+                if self.is_done() {
+                    return Poll::Ready(());
+                }
+
+                driver.enable_listen(SpiInterrupt::TransferDone.into(), true);
+
+                // The transfer may have completed before the interrupt was enabled.
                 if self.is_done() {
+                    driver.enable_listen(SpiInterrupt::TransferDone.into(), false);
                     Poll::Ready(())
                 } else {
-                    cx.waker().wake_by_ref();
                     Poll::Pending
                 }
             })
@@ -1497,6 +1930,53 @@ mod dma {
             }
         }
 
+        /// Sets up the registers for `segment`, chaining the next segment if
+        /// there is one.
+        #[cfg(gdma)]
+        fn set_up_segment(&self, segment: &Segment<'_>, has_next: bool) -> Result<(), Error> {
+            let driver = self.driver();
+            let (bytes_to_read, bytes_to_write) = (segment.read_len(), segment.write_data().len());
+
+            driver.setup_half_duplex(
+                matches!(segment.data, SegmentData::Write(_)),
+                segment.cmd,
+                segment.address,
+                false,
+                segment.dummy,
+                bytes_to_read == 0 && bytes_to_write == 0,
+                segment.data_mode,
+            )?;
+            driver.configure_datalen(bytes_to_read, bytes_to_write);
+            driver
+                .regs()
+                .user()
+                .modify(|_, w| w.usr_conf_nxt().bit(has_next));
+
+            Ok(())
+        }
+
+        #[cfg(gdma)]
+        unsafe fn start_sequence_dma(
+            &mut self,
+            bytes_to_read: usize,
+            bytes_to_write: usize,
+            rx_buffer: &mut DmaRxBuf,
+            tx_buffer: &mut DmaTxBuf,
+        ) -> Result<(), Error> {
+            self.rx_transfer_in_progress = bytes_to_read > 0;
+            self.tx_transfer_in_progress = bytes_to_write > 0;
+            unsafe {
+                self.dma_driver().start_sequence_dma(
+                    bytes_to_read,
+                    bytes_to_write,
+                    rx_buffer,
+                    tx_buffer,
+                    &mut self.channel.rx,
+                    &mut self.channel.tx,
+                )
+            }
+        }
+
         #[cfg(all(esp32, spi_address_workaround))]
         fn set_up_address_workaround(
             &mut self,
@@ -1571,19 +2051,51 @@ mod dma {
             self.driver().apply_config(config)
         }
 
+        /// Selects the hardware CS line asserted by the following transfers.
+        ///
+        /// See [`Spi::select_cs`].
+        #[instability::unstable]
+        pub fn select_cs<L: CsLine>(&mut self, _cs: L)
+        where
+            T: HasCsLine<L>,
+        {
+            self.driver().select_cs(L::INDEX);
+        }
+
         /// Configures the DMA buffers for the SPI instance.
         ///
         /// This method sets up both RX and TX buffers for DMA transfers.
         /// It returns an instance of `SpiDmaBus` that can be used for SPI
         /// communication.
         #[instability::unstable]
-        pub fn with_buffers(self, dma_rx_buf: DmaRxBuf, dma_tx_buf: DmaTxBuf) -> SpiDmaBus<'d, Dm> {
+        pub fn with_buffers(
+            self,
+            dma_rx_buf: DmaRxBuf,
+            dma_tx_buf: DmaTxBuf,
+        ) -> SpiDmaBus<'d, Dm, T> {
             SpiDmaBus::new(self, dma_rx_buf, dma_tx_buf)
         }
+
+        /// Configures the SPI instance for continuous transmission of a
+        /// circular buffer.
+        ///
+        /// The descriptors are used to link the buffer passed to
+        /// [`SpiDmaCircular::write_circular`] into a ring.
+        #[cfg(pdma)]
+        #[instability::unstable]
+        pub fn with_circular_tx(
+            self,
+            tx_descriptors: &'static mut [DmaDescriptor],
+        ) -> SpiDmaCircular<'d, Dm, T> {
+            SpiDmaCircular {
+                spi_dma: self,
+                tx_chain: DescriptorChain::new(tx_descriptors),
+            }
+        }
     }
 
     #[instability::unstable]
-    impl<Dm> embassy_embedded_hal::SetConfig for SpiDma<'_, Dm>
+    impl<Dm, T> embassy_embedded_hal::SetConfig for SpiDma<'_, Dm, T>
     where
         Dm: DriverMode,
     {
@@ -1600,19 +2112,19 @@ mod dma {
     /// This structure holds references to the SPI instance, DMA buffers, and
     /// transfer status.
     #[instability::unstable]
-    pub struct SpiDmaTransfer<'d, Dm, Buf>
+    pub struct SpiDmaTransfer<'d, Dm, Buf, T = AnySpi>
     where
         Dm: DriverMode,
     {
-        spi_dma: ManuallyDrop<SpiDma<'d, Dm>>,
+        spi_dma: ManuallyDrop<SpiDma<'d, Dm, T>>,
         dma_buf: ManuallyDrop<Buf>,
     }
 
-    impl<'d, Dm, Buf> SpiDmaTransfer<'d, Dm, Buf>
+    impl<'d, Dm, Buf, T> SpiDmaTransfer<'d, Dm, Buf, T>
     where
         Dm: DriverMode,
     {
-        fn new(spi_dma: SpiDma<'d, Dm>, dma_buf: Buf) -> Self {
+        fn new(spi_dma: SpiDma<'d, Dm, T>, dma_buf: Buf) -> Self {
             Self {
                 spi_dma: ManuallyDrop::new(spi_dma),
                 dma_buf: ManuallyDrop::new(dma_buf),
@@ -1632,7 +2144,7 @@ mod dma {
         /// This method blocks until the transfer is finished and returns the
         /// `SpiDma` instance and the associated buffer.
         #[instability::unstable]
-        pub fn wait(mut self) -> (SpiDma<'d, Dm>, Buf) {
+        pub fn wait(mut self) -> (SpiDma<'d, Dm, T>, Buf) {
             self.spi_dma.wait_for_idle();
             let retval = unsafe {
                 (
@@ -1653,7 +2165,7 @@ mod dma {
         }
     }
 
-    impl<Dm, Buf> Drop for SpiDmaTransfer<'_, Dm, Buf>
+    impl<Dm, Buf, T> Drop for SpiDmaTransfer<'_, Dm, Buf, T>
     where
         Dm: DriverMode,
     {
@@ -1670,7 +2182,7 @@ mod dma {
         }
     }
 
-    impl<Buf> SpiDmaTransfer<'_, Async, Buf> {
+    impl<Buf, T> SpiDmaTransfer<'_, Async, Buf, T> {
         /// Waits for the DMA transfer to complete asynchronously.
         ///
         /// This method awaits the completion of both RX and TX operations.
@@ -1680,7 +2192,7 @@ mod dma {
         }
     }
 
-    impl<'d, Dm> SpiDma<'d, Dm>
+    impl<'d, Dm, T> SpiDma<'d, Dm, T>
     where
         Dm: DriverMode,
     {
@@ -1709,7 +2221,7 @@ mod dma {
             mut self,
             bytes_to_write: usize,
             mut buffer: TX,
-        ) -> Result<SpiDmaTransfer<'d, Dm, TX>, (Error, Self, TX)> {
+        ) -> Result<SpiDmaTransfer<'d, Dm, TX, T>, (Error, Self, TX)> {
             self.wait_for_idle();
 
             match unsafe { self.start_dma_write(bytes_to_write, &mut buffer) } {
@@ -1743,7 +2255,7 @@ mod dma {
             mut self,
             bytes_to_read: usize,
             mut buffer: RX,
-        ) -> Result<SpiDmaTransfer<'d, Dm, RX>, (Error, Self, RX)> {
+        ) -> Result<SpiDmaTransfer<'d, Dm, RX, T>, (Error, Self, RX)> {
             self.wait_for_idle();
             match unsafe { self.start_dma_read(bytes_to_read, &mut buffer) } {
                 Ok(_) => Ok(SpiDmaTransfer::new(self, buffer)),
@@ -1780,7 +2292,7 @@ mod dma {
             mut rx_buffer: RX,
             bytes_to_write: usize,
             mut tx_buffer: TX,
-        ) -> Result<SpiDmaTransfer<'d, Dm, (RX, TX)>, (Error, Self, RX, TX)> {
+        ) -> Result<SpiDmaTransfer<'d, Dm, (RX, TX), T>, (Error, Self, RX, TX)> {
             self.wait_for_idle();
             match unsafe {
                 self.start_dma_transfer(
@@ -1809,6 +2321,17 @@ mod dma {
             bytes_to_read: usize,
             buffer: &mut impl DmaRxBuffer,
         ) -> Result<(), Error> {
+            if bytes_to_read == 0 {
+                return self.start_half_duplex_write(
+                    data_mode,
+                    cmd,
+                    address,
+                    dummy,
+                    0,
+                    &mut EmptyBuf,
+                );
+            }
+
             self.driver().setup_half_duplex(
                 false,
                 cmd,
@@ -1823,6 +2346,9 @@ mod dma {
         }
 
         /// Perform a half-duplex read operation using DMA.
+        ///
+        /// If `bytes_to_read` is 0, the data phase is skipped, so only the
+        /// command, address and dummy phases are performed.
         #[allow(clippy::type_complexity)]
         #[cfg_attr(place_spi_driver_in_ram, ram)]
         #[instability::unstable]
@@ -1834,7 +2360,7 @@ mod dma {
             dummy: u8,
             bytes_to_read: usize,
             mut buffer: RX,
-        ) -> Result<SpiDmaTransfer<'d, Dm, RX>, (Error, Self, RX)> {
+        ) -> Result<SpiDmaTransfer<'d, Dm, RX, T>, (Error, Self, RX)> {
             self.wait_for_idle();
 
             match unsafe {
@@ -1900,7 +2426,7 @@ mod dma {
             dummy: u8,
             bytes_to_write: usize,
             mut buffer: TX,
-        ) -> Result<SpiDmaTransfer<'d, Dm, TX>, (Error, Self, TX)> {
+        ) -> Result<SpiDmaTransfer<'d, Dm, TX, T>, (Error, Self, TX)> {
             self.wait_for_idle();
 
             match unsafe {
@@ -1923,24 +2449,57 @@ mod dma {
     ///
     /// This structure is responsible for managing SPI transfers using DMA
     /// buffers.
+    ///
+    /// Transfers of any length are split into chunks that fit both the DMA
+    /// buffers and the maximum length of a single SPI transaction. CS stays
+    /// asserted across the chunks, so the device sees a single transaction.
+    /// The size of the DMA buffers passed to [`SpiDma::with_buffers`] trades
+    /// memory use for the number of chunks.
+    ///
+    /// In async mode, transfers of up to [`Config::fifo_threshold`] bytes
+    /// bypass the DMA and go through the FIFO, which only involves the SPI
+    /// interrupt and leaves the DMA channel untouched.
     #[derive(Debug)]
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[instability::unstable]
-    pub struct SpiDmaBus<'d, Dm>
+    pub struct SpiDmaBus<'d, Dm, T = AnySpi>
     where
         Dm: DriverMode,
     {
-        spi_dma: SpiDma<'d, Dm>,
+        spi_dma: SpiDma<'d, Dm, T>,
         rx_buf: DmaRxBuf,
         tx_buf: DmaTxBuf,
     }
 
-    impl<Dm> crate::private::Sealed for SpiDmaBus<'_, Dm> where Dm: DriverMode {}
+    impl<Dm, T> crate::private::Sealed for SpiDmaBus<'_, Dm, T> where Dm: DriverMode {}
+
+    /// Keeps CS asserted until dropped, if a transfer needs more than one
+    /// SPI transaction.
+    struct CsHold(Option<Driver>);
+
+    impl CsHold {
+        fn new(driver: Driver, hold: bool) -> Self {
+            if hold {
+                driver.set_cs_keep_active(true);
+                Self(Some(driver))
+            } else {
+                Self(None)
+            }
+        }
+    }
+
+    impl Drop for CsHold {
+        fn drop(&mut self) {
+            if let Some(driver) = self.0.take() {
+                driver.set_cs_keep_active(false);
+            }
+        }
+    }
 
-    impl<'d> SpiDmaBus<'d, Blocking> {
+    impl<'d, T> SpiDmaBus<'d, Blocking, T> {
         /// Converts the SPI instance into async mode.
         #[instability::unstable]
-        pub fn into_async(self) -> SpiDmaBus<'d, Async> {
+        pub fn into_async(self) -> SpiDmaBus<'d, Async, T> {
             SpiDmaBus {
                 spi_dma: self.spi_dma.into_async(),
                 rx_buf: self.rx_buf,
@@ -1949,10 +2508,10 @@ mod dma {
         }
     }
 
-    impl<'d> SpiDmaBus<'d, Async> {
+    impl<'d, T> SpiDmaBus<'d, Async, T> {
         /// Converts the SPI instance into async mode.
         #[instability::unstable]
-        pub fn into_blocking(self) -> SpiDmaBus<'d, Blocking> {
+        pub fn into_blocking(self) -> SpiDmaBus<'d, Blocking, T> {
             SpiDmaBus {
                 spi_dma: self.spi_dma.into_blocking(),
                 rx_buf: self.rx_buf,
@@ -1961,13 +2520,13 @@ mod dma {
         }
     }
 
-    impl<'d, Dm> SpiDmaBus<'d, Dm>
+    impl<'d, Dm, T> SpiDmaBus<'d, Dm, T>
     where
         Dm: DriverMode,
     {
         /// Creates a new `SpiDmaBus` with the specified SPI instance and DMA
         /// buffers.
-        pub fn new(spi_dma: SpiDma<'d, Dm>, rx_buf: DmaRxBuf, tx_buf: DmaTxBuf) -> Self {
+        pub fn new(spi_dma: SpiDma<'d, Dm, T>, rx_buf: DmaRxBuf, tx_buf: DmaTxBuf) -> Self {
             Self {
                 spi_dma,
                 rx_buf,
@@ -1977,14 +2536,14 @@ mod dma {
 
         /// Splits [SpiDmaBus] back into [SpiDma], [DmaRxBuf] and [DmaTxBuf].
         #[instability::unstable]
-        pub fn split(mut self) -> (SpiDma<'d, Dm>, DmaRxBuf, DmaTxBuf) {
+        pub fn split(mut self) -> (SpiDma<'d, Dm, T>, DmaRxBuf, DmaTxBuf) {
             self.wait_for_idle();
             (self.spi_dma, self.rx_buf, self.tx_buf)
         }
     }
 
     #[instability::unstable]
-    impl crate::interrupt::InterruptConfigurable for SpiDmaBus<'_, Blocking> {
+    impl<T> crate::interrupt::InterruptConfigurable for SpiDmaBus<'_, Blocking, T> {
         /// Sets the interrupt handler
         ///
         /// Interrupts are not enabled at the peripheral level here.
@@ -1993,7 +2552,7 @@ mod dma {
         }
     }
 
-    impl SpiDmaBus<'_, Blocking> {
+    impl<T> SpiDmaBus<'_, Blocking, T> {
         /// Listen for the given interrupts
         #[instability::unstable]
         pub fn listen(&mut self, interrupts: impl Into<EnumSet<SpiInterrupt>>) {
@@ -2019,7 +2578,7 @@ mod dma {
         }
     }
 
-    impl<Dm> SpiDmaBus<'_, Dm>
+    impl<Dm, T> SpiDmaBus<'_, Dm, T>
     where
         Dm: DriverMode,
     {
@@ -2027,6 +2586,28 @@ mod dma {
             self.spi_dma.wait_for_idle();
         }
 
+        fn rx_chunk_size(&self) -> usize {
+            min(self.rx_buf.capacity(), MAX_DMA_SIZE)
+        }
+
+        fn tx_chunk_size(&self) -> usize {
+            min(self.tx_buf.capacity(), MAX_DMA_SIZE)
+        }
+
+        fn hold_cs(&self, read_len: usize, write_len: usize) -> CsHold {
+            let chunk_size = match (read_len, write_len) {
+                (0, _) => self.tx_chunk_size(),
+                (_, 0) => self.rx_chunk_size(),
+                _ => min(self.rx_chunk_size(), self.tx_chunk_size()),
+            };
+            // Differing lengths are transferred as a common part and a
+            // remainder.
+            let hold = max(read_len, write_len) > chunk_size
+                || (read_len != 0 && write_len != 0 && read_len != write_len);
+
+            CsHold::new(self.spi_dma.driver(), hold)
+        }
+
         /// Change the bus configuration.
         // FIXME: when https://github.com/esp-rs/esp-hal/issues/2839 is resolved, add an appropriate `# Error` entry.
         #[instability::unstable]
@@ -2034,11 +2615,24 @@ mod dma {
             self.spi_dma.apply_config(config)
         }
 
+        /// Selects the hardware CS line asserted by the following transfers.
+        ///
+        /// See [`Spi::select_cs`].
+        #[instability::unstable]
+        pub fn select_cs<L: CsLine>(&mut self, cs: L)
+        where
+            T: HasCsLine<L>,
+        {
+            self.wait_for_idle();
+            self.spi_dma.select_cs(cs);
+        }
+
         /// Reads data from the SPI bus using DMA.
         #[instability::unstable]
         pub fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
             self.wait_for_idle();
-            for chunk in words.chunks_mut(self.rx_buf.capacity()) {
+            let _cs = self.hold_cs(words.len(), 0);
+            for chunk in words.chunks_mut(self.rx_chunk_size()) {
                 self.rx_buf.set_length(chunk.len());
 
                 unsafe {
@@ -2063,7 +2657,8 @@ mod dma {
         #[instability::unstable]
         pub fn write(&mut self, words: &[u8]) -> Result<(), Error> {
             self.wait_for_idle();
-            for chunk in words.chunks(self.tx_buf.capacity()) {
+            let _cs = self.hold_cs(0, words.len());
+            for chunk in words.chunks(self.tx_chunk_size()) {
                 self.tx_buf.fill(chunk);
 
                 unsafe {
@@ -2085,7 +2680,8 @@ mod dma {
         #[instability::unstable]
         pub fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
             self.wait_for_idle();
-            let chunk_size = min(self.tx_buf.capacity(), self.rx_buf.capacity());
+            let _cs = self.hold_cs(read.len(), write.len());
+            let chunk_size = min(self.tx_chunk_size(), self.rx_chunk_size());
 
             let common_length = min(read.len(), write.len());
             let (read_common, read_remainder) = read.split_at_mut(common_length);
@@ -2125,7 +2721,8 @@ mod dma {
         #[instability::unstable]
         pub fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
             self.wait_for_idle();
-            let chunk_size = min(self.tx_buf.capacity(), self.rx_buf.capacity());
+            let _cs = self.hold_cs(words.len(), words.len());
+            let chunk_size = min(self.tx_chunk_size(), self.rx_chunk_size());
 
             for chunk in words.chunks_mut(chunk_size) {
                 self.tx_buf.fill(chunk);
@@ -2149,6 +2746,9 @@ mod dma {
         }
 
         /// Half-duplex read.
+        ///
+        /// An empty `buffer` skips the data phase, so only the command,
+        /// address and dummy phases are performed.
         #[instability::unstable]
         pub fn half_duplex_read(
             &mut self,
@@ -2214,12 +2814,37 @@ mod dma {
 
             Ok(())
         }
-    }
 
-    #[instability::unstable]
-    impl<Dm> embassy_embedded_hal::SetConfig for SpiDmaBus<'_, Dm>
-    where
-        Dm: DriverMode,
+        /// 3-wire transfer: writes `write`, then reads into `read` over the
+        /// SIO0 line, within a single CS assertion.
+        ///
+        /// See [`Spi::transfer_sio`] for details. Transfers larger than the
+        /// DMA buffers are split into several DMA transfers.
+        #[instability::unstable]
+        pub fn transfer_sio(&mut self, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+            self.spi_dma.driver().set_cs_keep_active(true);
+            let result = self.transfer_sio_parts(write, read);
+            self.spi_dma.driver().set_cs_keep_active(false);
+
+            result
+        }
+
+        fn transfer_sio_parts(&mut self, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+            for chunk in write.chunks(self.tx_chunk_size()) {
+                self.half_duplex_write(DataMode::Single, Command::None, Address::None, 0, chunk)?;
+            }
+            for chunk in read.chunks_mut(self.rx_chunk_size()) {
+                self.half_duplex_read(DataMode::Single, Command::None, Address::None, 0, chunk)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[instability::unstable]
+    impl<Dm, T> embassy_embedded_hal::SetConfig for SpiDmaBus<'_, Dm, T>
+    where
+        Dm: DriverMode,
     {
         type Config = Config;
         type ConfigError = ConfigError;
@@ -2229,6 +2854,658 @@ mod dma {
         }
     }
 
+    /// A DMA capable SPI instance that continuously transmits a circular
+    /// buffer.
+    ///
+    /// The buffer is split into descriptor sized segments which are linked
+    /// into a ring. While the DMA transmits one segment, the ones already sent
+    /// can be refilled through the returned [`DmaTransferTxCircular`]. If the
+    /// DMA catches up with the data pushed, `available` and `push` return
+    /// [`DmaError::Late`] instead of the stale data being repeated unnoticed.
+    ///
+    /// Stopping the transfer (or dropping it) lets the DMA finish the segment
+    /// it is working on, so the data on the bus always ends at a segment
+    /// boundary.
+    ///
+    /// Only the ESP32 and ESP32-S2 support continuous SPI DMA transfers.
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::spi::master::{Config, Spi};
+    /// # use esp_hal::dma_circular_buffers;
+    /// let (_, _, tx_buffer, tx_descriptors) = dma_circular_buffers!(0, 3000);
+    ///
+    /// let mut spi = Spi::new(peripherals.SPI2, Config::default())?
+    ///     .with_sck(peripherals.GPIO0)
+    ///     .with_mosi(peripherals.GPIO1)
+    ///     .with_dma(peripherals.DMA_SPI2)
+    ///     .with_circular_tx(tx_descriptors);
+    ///
+    /// let mut transfer = spi.write_circular(tx_buffer)?;
+    /// let data = [0x55u8; 1000];
+    /// loop {
+    ///     let available = transfer.available()?;
+    ///     if available >= data.len() {
+    ///         transfer.push(&data)?;
+    ///     }
+    /// #   break;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(pdma)]
+    #[derive(Debug)]
+    #[instability::unstable]
+    pub struct SpiDmaCircular<'d, Dm, T = AnySpi>
+    where
+        Dm: DriverMode,
+    {
+        spi_dma: SpiDma<'d, Dm, T>,
+        tx_chain: DescriptorChain,
+    }
+
+    #[cfg(pdma)]
+    impl<'d, Dm, T> SpiDmaCircular<'d, Dm, T>
+    where
+        Dm: DriverMode,
+    {
+        /// Starts continuously transmitting `words`.
+        ///
+        /// The current contents of the buffer are sent first, after that the
+        /// buffer is refilled through the returned [`DmaTransferTxCircular`].
+        #[instability::unstable]
+        pub fn write_circular<'t>(
+            &'t mut self,
+            words: &'t impl ReadBuffer,
+        ) -> Result<DmaTransferTxCircular<'t, Self>, Error> {
+            let (ptr, len) = unsafe { words.read_buffer() };
+
+            unsafe {
+                self.tx_chain.fill_for_tx(true, ptr, len)?;
+                self.spi_dma.dma_driver().start_circular_tx_dma(
+                    len,
+                    &self.tx_chain,
+                    &mut self.spi_dma.channel.tx,
+                )?;
+            }
+            self.spi_dma.tx_transfer_in_progress = true;
+
+            // The SPI DMA of these chips has no descriptor write-back
+            Ok(DmaTransferTxCircular::new_without_write_back(self))
+        }
+
+        /// Change the bus configuration.
+        #[instability::unstable]
+        pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+            self.spi_dma.apply_config(config)
+        }
+
+        /// Splits [SpiDmaCircular] back into [SpiDma] and the TX descriptors.
+        #[instability::unstable]
+        pub fn split(self) -> (SpiDma<'d, Dm, T>, &'static mut [DmaDescriptor]) {
+            (self.spi_dma, self.tx_chain.descriptors)
+        }
+    }
+
+    #[cfg(pdma)]
+    impl<Dm, T> DmaSupport for SpiDmaCircular<'_, Dm, T>
+    where
+        Dm: DriverMode,
+    {
+        fn peripheral_wait_dma(&mut self, _is_rx: bool, _is_tx: bool) {
+            while !self.spi_dma.is_done() {}
+        }
+
+        fn peripheral_dma_stop(&mut self) {
+            if !self.spi_dma.tx_transfer_in_progress {
+                return;
+            }
+
+            // Unlinking the ring makes the DMA run out of data once it has
+            // finished the segment it is working on (or the one it has
+            // already fetched), which is reported as the end of the list.
+            self.spi_dma.channel.tx.clear_out(DmaTxInterrupt::TotalEof);
+            for descriptor in self.tx_chain.descriptors.iter_mut() {
+                unsafe { core::ptr::write_volatile(&mut descriptor.next, core::ptr::null_mut()) };
+            }
+            while !self.spi_dma.channel.tx.is_done() {}
+
+            self.spi_dma.dma_driver().stop_circular_tx_dma();
+            self.spi_dma.channel.tx.stop_transfer();
+            self.spi_dma.tx_transfer_in_progress = false;
+        }
+    }
+
+    #[cfg(pdma)]
+    impl<'d, Dm, T> DmaSupportTx for SpiDmaCircular<'d, Dm, T>
+    where
+        Dm: DriverMode,
+    {
+        type TX = ChannelTx<'d, Dm, PeripheralTxChannel<AnySpi>>;
+
+        fn tx(&mut self) -> &mut Self::TX {
+            &mut self.spi_dma.channel.tx
+        }
+
+        fn chain(&mut self) -> &mut DescriptorChain {
+            &mut self.tx_chain
+        }
+    }
+
+    /// The most segments a [`TransactionSequence`] can hold.
+    #[cfg(gdma)]
+    const MAX_SEQUENCE_SEGMENTS: usize = 16;
+
+    /// Marks the first word of a CONF buffer. Must match the value written to
+    /// the `dma_seg_magic_value` field.
+    #[cfg(gdma)]
+    const SEQUENCE_MAGIC: u8 = 0xA;
+
+    /// The registers a CONF buffer reloads before a segment, in the order of
+    /// their bits in the buffer's bitmap: ADDR (0), CTRL (1), USER (3), USER1
+    /// (4), USER2 (5) and MS_DLEN (6).
+    #[cfg(gdma)]
+    const SEQUENCE_CONF_BITMAP: u32 = 0b111_1011;
+
+    /// The bitmap word, followed by one word per reloaded register.
+    #[cfg(gdma)]
+    const SEQUENCE_CONF_LEN: usize = 7 * 4;
+
+    /// Errors that can occur while building a [`TransactionSequence`].
+    #[cfg(gdma)]
+    #[non_exhaustive]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[instability::unstable]
+    pub enum SequenceError {
+        /// The sequence already holds [`TransactionSequence::MAX_SEGMENTS`]
+        /// segments.
+        TooManySegments,
+
+        /// The data phase of a segment is longer than a single SPI transaction
+        /// can be.
+        SegmentTooLong,
+
+        /// The gap between segments is longer than the peripheral can wait.
+        GapTooLong,
+    }
+
+    #[cfg(gdma)]
+    impl core::error::Error for SequenceError {}
+
+    #[cfg(gdma)]
+    impl core::fmt::Display for SequenceError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                SequenceError::TooManySegments => {
+                    write!(f, "The sequence can't hold more segments")
+                }
+                SequenceError::SegmentTooLong => {
+                    write!(f, "The data phase of the segment is too long")
+                }
+                SequenceError::GapTooLong => {
+                    write!(f, "The gap between segments is too long")
+                }
+            }
+        }
+    }
+
+    #[cfg(gdma)]
+    #[derive(Debug, Clone, Copy)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    enum SegmentData<'a> {
+        Read(usize),
+        Write(&'a [u8]),
+    }
+
+    #[cfg(gdma)]
+    #[derive(Debug, Clone, Copy)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    struct Segment<'a> {
+        data_mode: DataMode,
+        cmd: Command,
+        address: Address,
+        dummy: u8,
+        data: SegmentData<'a>,
+    }
+
+    #[cfg(gdma)]
+    impl Segment<'_> {
+        fn read_len(&self) -> usize {
+            match self.data {
+                SegmentData::Read(len) => len,
+                SegmentData::Write(_) => 0,
+            }
+        }
+
+        fn write_data(&self) -> &[u8] {
+            match self.data {
+                SegmentData::Read(_) => &[],
+                SegmentData::Write(data) => data,
+            }
+        }
+    }
+
+    /// A list of half-duplex transactions that the SPI peripheral runs
+    /// back-to-back, without CPU intervention between them.
+    ///
+    /// Each segment is a complete transaction with its own command, address,
+    /// dummy and data phases. Between two segments, the peripheral reloads its
+    /// configuration from the DMA stream, which takes
+    /// [`with_gap`](Self::with_gap) APB clock cycles. This makes the timing of
+    /// the segments independent of interrupt latency, e.g. to send a command
+    /// to a sensor and read the result at a fixed spacing.
+    ///
+    /// The sequence is checked against the hardware limits while it is built,
+    /// and run with [`SpiDmaBus::run_sequence`] and its variants.
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::dma::{DmaRxBuf, DmaTxBuf};
+    /// # use esp_hal::dma_buffers;
+    /// # use esp_hal::spi::master::{
+    /// #     Address, Command, Config, Spi, TransactionSequence,
+    /// # };
+    /// # use esp_hal::spi::DataMode;
+    /// let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) =
+    ///     dma_buffers!(256);
+    /// let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer)?;
+    /// let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer)?;
+    ///
+    /// let mut spi = Spi::new(peripherals.SPI2, Config::default())?
+    ///     .with_sck(peripherals.GPIO0)
+    ///     .with_mosi(peripherals.GPIO1)
+    ///     .with_miso(peripherals.GPIO2)
+    ///     .with_cs(peripherals.GPIO3)
+    ///     .with_dma(peripherals.DMA_CH0)
+    ///     .with_buffers(dma_rx_buf, dma_tx_buf);
+    ///
+    /// let mode = DataMode::SingleTwoDataLines;
+    /// // Start a conversion, then read the result.
+    /// let start = Command::_8Bit(0x01, mode);
+    /// let read = Command::_8Bit(0x02, mode);
+    /// let sequence = TransactionSequence::new()
+    ///     .write(mode, start, Address::None, 0, &[])?
+    ///     .read(mode, read, Address::None, 0, 2)?
+    ///     .with_gap(8000)?;
+    ///
+    /// let mut result = [0; 2];
+    /// spi.run_sequence(&sequence, &mut result)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(gdma)]
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[instability::unstable]
+    pub struct TransactionSequence<'a> {
+        segments: [Option<Segment<'a>>; MAX_SEQUENCE_SEGMENTS],
+        gap: u32,
+    }
+
+    #[cfg(gdma)]
+    impl Default for TransactionSequence<'_> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[cfg(gdma)]
+    #[cfg_attr(not(feature = "unstable"), allow(dead_code))]
+    impl<'a> TransactionSequence<'a> {
+        /// The most segments a sequence can hold.
+        pub const MAX_SEGMENTS: usize = MAX_SEQUENCE_SEGMENTS;
+
+        /// The longest gap between two segments, in APB clock cycles.
+        pub const MAX_GAP: u32 = (1 << 18) - 1;
+
+        /// Creates an empty sequence.
+        pub const fn new() -> Self {
+            Self {
+                segments: [None; MAX_SEQUENCE_SEGMENTS],
+                gap: 0,
+            }
+        }
+
+        /// Appends a segment that reads `len` bytes.
+        ///
+        /// The data of all read segments is stored one after the other in the
+        /// buffer passed to [`SpiDmaBus::run_sequence`]. A `len` of 0 only
+        /// performs the command, address and dummy phases.
+        pub fn read(
+            self,
+            data_mode: DataMode,
+            cmd: Command,
+            address: Address,
+            dummy: u8,
+            len: usize,
+        ) -> Result<Self, SequenceError> {
+            self.push(Segment {
+                data_mode,
+                cmd,
+                address,
+                dummy,
+                data: SegmentData::Read(len),
+            })
+        }
+
+        /// Appends a segment that writes `data`.
+        ///
+        /// An empty `data` only performs the command, address and dummy
+        /// phases.
+        pub fn write(
+            self,
+            data_mode: DataMode,
+            cmd: Command,
+            address: Address,
+            dummy: u8,
+            data: &'a [u8],
+        ) -> Result<Self, SequenceError> {
+            self.push(Segment {
+                data_mode,
+                cmd,
+                address,
+                dummy,
+                data: SegmentData::Write(data),
+            })
+        }
+
+        /// Sets the number of APB clock cycles between two segments.
+        ///
+        /// The peripheral always needs a few cycles to reload its
+        /// configuration, so this is a lower bound. The default is 0.
+        pub fn with_gap(mut self, cycles: u32) -> Result<Self, SequenceError> {
+            if cycles > Self::MAX_GAP {
+                return Err(SequenceError::GapTooLong);
+            }
+
+            self.gap = cycles;
+            Ok(self)
+        }
+
+        /// Returns the number of segments in the sequence.
+        pub fn len(&self) -> usize {
+            self.segments().count()
+        }
+
+        /// Returns `true` if the sequence has no segments.
+        pub fn is_empty(&self) -> bool {
+            self.segments[0].is_none()
+        }
+
+        /// Returns the number of bytes the read segments receive in total.
+        pub fn read_len(&self) -> usize {
+            self.segments().map(Segment::read_len).sum()
+        }
+
+        /// The number of bytes the sequence needs in the DMA TX buffer.
+        fn write_len(&self) -> usize {
+            self.segments()
+                .enumerate()
+                .map(|(i, segment)| {
+                    let conf_len = if i > 0 { SEQUENCE_CONF_LEN } else { 0 };
+                    conf_len + segment.write_data().len()
+                })
+                .sum()
+        }
+
+        fn segments(&self) -> impl Iterator<Item = &Segment<'a>> {
+            self.segments.iter().map_while(Option::as_ref)
+        }
+
+        fn push(mut self, segment: Segment<'a>) -> Result<Self, SequenceError> {
+            if max(segment.read_len(), segment.write_data().len()) > MAX_DMA_SIZE {
+                return Err(SequenceError::SegmentTooLong);
+            }
+
+            let Some(slot) = self.segments.iter_mut().find(|slot| slot.is_none()) else {
+                return Err(SequenceError::TooManySegments);
+            };
+            *slot = Some(segment);
+
+            Ok(self)
+        }
+    }
+
+    #[cfg(gdma)]
+    impl<Dm, T> SpiDmaBus<'_, Dm, T>
+    where
+        Dm: DriverMode,
+    {
+        /// Runs `sequence` once, and stores the data of its read segments one
+        /// after the other in `read`.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`Error::DmaError`] with [`DmaError::BufferTooSmall`] if
+        /// `read` or the DMA buffers can't hold the data of the sequence, and
+        /// [`Error::Unsupported`] if a segment uses an unsupported combination
+        /// of [data modes](super#dual-and-quad-data-lines).
+        #[instability::unstable]
+        pub fn run_sequence(
+            &mut self,
+            sequence: &TransactionSequence<'_>,
+            read: &mut [u8],
+        ) -> Result<(), Error> {
+            self.wait_for_idle();
+            self.load_sequence(sequence, read)?;
+            if sequence.is_empty() {
+                return Ok(());
+            }
+
+            self.start_sequence(sequence)?;
+            self.wait_for_sequence();
+            self.finish_sequence(sequence, read);
+
+            Ok(())
+        }
+
+        /// Runs `sequence` every time `timer` expires, until `on_complete`
+        /// returns [`ControlFlow::Break`].
+        ///
+        /// `on_complete` is called with the data of the read segments after
+        /// every run. The timer must already have been started with the
+        /// desired period. The sequence is only compiled once, so only
+        /// setting up the first segment and starting the DMA is left to do
+        /// when the timer expires.
+        ///
+        /// The CPU busy-waits for the timer and for each run of the sequence,
+        /// so it can't do anything else until `on_complete` breaks the loop,
+        /// other than handling interrupts. To run other tasks in between, call
+        /// [`SpiDmaBus::run_sequence_async`] from an async task that waits for
+        /// a timer instead.
+        ///
+        /// ```rust, no_run
+        #[doc = crate::before_snippet!()]
+        /// # use core::ops::ControlFlow;
+        /// # use esp_hal::dma::{DmaRxBuf, DmaTxBuf};
+        /// # use esp_hal::dma_buffers;
+        /// # use esp_hal::spi::master::{
+        /// #     Address, Command, Config, Spi, TransactionSequence,
+        /// # };
+        /// # use esp_hal::spi::DataMode;
+        /// # use esp_hal::timer::{timg::TimerGroup, PeriodicTimer};
+        /// # let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) =
+        /// #     dma_buffers!(256);
+        /// # let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer)?;
+        /// # let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer)?;
+        /// # let mut spi = Spi::new(peripherals.SPI2, Config::default())?
+        /// #     .with_dma(peripherals.DMA_CH0)
+        /// #     .with_buffers(dma_rx_buf, dma_tx_buf);
+        /// let mode = DataMode::SingleTwoDataLines;
+        /// let read = Command::_8Bit(0x02, mode);
+        /// let sequence = TransactionSequence::new()
+        ///     .read(mode, read, Address::None, 0, 2)?;
+        ///
+        /// let timg0 = TimerGroup::new(peripherals.TIMG0);
+        /// let mut timer = PeriodicTimer::new(timg0.timer0);
+        /// timer.start(10.millis())?;
+        ///
+        /// // Take 100 samples, 10 ms apart.
+        /// let mut samples = 0;
+        /// let mut result = [0; 2];
+        /// spi.run_sequence_periodically(
+        ///     &sequence,
+        ///     &mut result,
+        ///     &mut timer,
+        ///     |_data| {
+        ///         samples += 1;
+        ///         if samples == 100 {
+        ///             ControlFlow::Break(())
+        ///         } else {
+        ///             ControlFlow::Continue(())
+        ///         }
+        ///     },
+        /// )?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        /// 
+        /// # Errors
+        ///
+        /// See [`Self::run_sequence`].
+        #[instability::unstable]
+        pub fn run_sequence_periodically<Tm>(
+            &mut self,
+            sequence: &TransactionSequence<'_>,
+            read: &mut [u8],
+            timer: &mut PeriodicTimer<'_, Tm>,
+            mut on_complete: impl FnMut(&[u8]) -> ControlFlow<()>,
+        ) -> Result<(), Error>
+        where
+            Tm: DriverMode,
+        {
+            self.wait_for_idle();
+            self.load_sequence(sequence, read)?;
+
+            let read = &mut read[..sequence.read_len()];
+            loop {
+                timer.wait();
+
+                if !sequence.is_empty() {
+                    self.start_sequence(sequence)?;
+                    self.wait_for_sequence();
+                    self.finish_sequence(sequence, read);
+                }
+
+                if on_complete(read).is_break() {
+                    return Ok(());
+                }
+            }
+        }
+
+        /// Checks the buffers, and compiles every segment but the first one
+        /// into the DMA TX buffer.
+        fn load_sequence(
+            &mut self,
+            sequence: &TransactionSequence<'_>,
+            read: &[u8],
+        ) -> Result<(), Error> {
+            let bytes_to_read = sequence.read_len();
+            let bytes_to_write = sequence.write_len();
+            if bytes_to_read > read.len()
+                || bytes_to_read > self.rx_buf.capacity()
+                || bytes_to_write > self.tx_buf.capacity()
+            {
+                return Err(DmaError::BufferTooSmall.into());
+            }
+
+            // The CONF buffers hold the register values the driver computes
+            // for each segment, read back after setting the segment up.
+            let driver = self.spi_dma.driver();
+            let regs = driver.regs();
+            let count = sequence.len();
+            let buffer = self.tx_buf.as_mut_slice();
+            let mut offset = 0;
+            for (i, segment) in sequence.segments().enumerate() {
+                if i > 0 {
+                    self.spi_dma.set_up_segment(segment, i + 1 < count)?;
+
+                    let conf = [
+                        ((SEQUENCE_MAGIC as u32) << 28) | SEQUENCE_CONF_BITMAP,
+                        regs.addr().read().bits(),
+                        regs.ctrl().read().bits(),
+                        regs.user().read().bits(),
+                        regs.user1().read().bits(),
+                        regs.user2().read().bits(),
+                        regs.ms_dlen().read().bits(),
+                    ];
+                    for word in conf {
+                        buffer[offset..][..4].copy_from_slice(&word.to_le_bytes());
+                        offset += 4;
+                    }
+                }
+
+                let data = segment.write_data();
+                buffer[offset..][..data.len()].copy_from_slice(data);
+                offset += data.len();
+            }
+
+            Ok(())
+        }
+
+        /// Sets up the first segment of a sequence loaded by
+        /// [`Self::load_sequence`], and starts the transfer.
+        fn start_sequence(&mut self, sequence: &TransactionSequence<'_>) -> Result<(), Error> {
+            let Some(first) = sequence.segments().next() else {
+                return Ok(());
+            };
+
+            self.spi_dma.set_up_segment(first, sequence.len() > 1)?;
+
+            let driver = self.spi_dma.driver();
+            let regs = driver.regs();
+            regs.slave().modify(|_, w| unsafe {
+                w.usr_conf().set_bit();
+                w.dma_seg_magic_value().bits(SEQUENCE_MAGIC)
+            });
+            regs.cmd()
+                .modify(|_, w| unsafe { w.conf_bitlen().bits(sequence.gap) });
+
+            let bytes_to_read = sequence.read_len();
+            let bytes_to_write = sequence.write_len();
+            if bytes_to_read > 0 {
+                self.rx_buf.set_length(bytes_to_read);
+            }
+            if bytes_to_write > 0 {
+                self.tx_buf.set_length(bytes_to_write);
+            }
+
+            unsafe {
+                self.spi_dma.start_sequence_dma(
+                    bytes_to_read,
+                    bytes_to_write,
+                    &mut self.rx_buf,
+                    &mut self.tx_buf,
+                )
+            }
+        }
+
+        fn wait_for_sequence(&mut self) {
+            let driver = self.spi_dma.driver();
+            while !driver
+                .interrupts()
+                .contains(SpiInterrupt::DmaSegmentedTransferDone)
+            {}
+            self.wait_for_idle();
+        }
+
+        fn finish_sequence(&mut self, sequence: &TransactionSequence<'_>, read: &mut [u8]) {
+            let driver = self.spi_dma.driver();
+            driver
+                .regs()
+                .slave()
+                .modify(|_, w| w.usr_conf().clear_bit());
+            driver.clear_interrupts(SpiInterrupt::DmaSegmentedTransferDone.into());
+
+            let bytes_to_read = sequence.read_len();
+            if bytes_to_read > 0 {
+                let bytes_read = self.rx_buf.read_received_data(&mut read[..bytes_to_read]);
+                debug_assert_eq!(bytes_read, bytes_to_read);
+            }
+        }
+    }
+
     /// Async functionality
     mod asynch {
         #[cfg(any(doc, feature = "unstable"))]
@@ -2277,12 +3554,29 @@ mod dma {
             }
         }
 
-        impl SpiDmaBus<'_, Async> {
+        impl<T> SpiDmaBus<'_, Async, T> {
+            /// Prepares the peripheral for a FIFO transfer if `len` bytes are
+            /// few enough to not be worth the DMA setup.
+            fn use_fifo(&self, len: usize) -> Option<CsHold> {
+                let driver = self.spi_dma.driver();
+                if len > driver.state.fifo_threshold.load(Ordering::Relaxed) {
+                    return None;
+                }
+
+                self.spi_dma.dma_driver().disable_dma();
+                Some(CsHold::new(driver, len > FIFO_SIZE))
+            }
+
             /// Fill the given buffer with data from the bus.
             #[instability::unstable]
             pub async fn read_async(&mut self, words: &mut [u8]) -> Result<(), Error> {
                 self.spi_dma.wait_for_idle_async().await;
-                let chunk_size = self.rx_buf.capacity();
+                if let Some(_cs) = self.use_fifo(words.len()) {
+                    return self.spi_dma.driver().read_bytes_async(words).await;
+                }
+
+                let _cs = self.hold_cs(words.len(), 0);
+                let chunk_size = self.rx_chunk_size();
 
                 for chunk in words.chunks_mut(chunk_size) {
                     self.rx_buf.set_length(chunk.len());
@@ -2308,9 +3602,13 @@ mod dma {
             #[instability::unstable]
             pub async fn write_async(&mut self, words: &[u8]) -> Result<(), Error> {
                 self.spi_dma.wait_for_idle_async().await;
+                if let Some(_cs) = self.use_fifo(words.len()) {
+                    return self.spi_dma.driver().write_bytes_async(words).await;
+                }
 
+                let _cs = self.hold_cs(0, words.len());
+                let chunk_size = self.tx_chunk_size();
                 let mut spi = DropGuard::new(&mut self.spi_dma, |spi| spi.cancel_transfer());
-                let chunk_size = self.tx_buf.capacity();
 
                 for chunk in words.chunks(chunk_size) {
                     self.tx_buf.fill(chunk);
@@ -2335,9 +3633,13 @@ mod dma {
                 write: &[u8],
             ) -> Result<(), Error> {
                 self.spi_dma.wait_for_idle_async().await;
+                if let Some(_cs) = self.use_fifo(max(read.len(), write.len())) {
+                    return self.spi_dma.driver().transfer_async(read, write).await;
+                }
 
+                let _cs = self.hold_cs(read.len(), write.len());
+                let chunk_size = min(self.tx_chunk_size(), self.rx_chunk_size());
                 let mut spi = DropGuard::new(&mut self.spi_dma, |spi| spi.cancel_transfer());
-                let chunk_size = min(self.tx_buf.capacity(), self.rx_buf.capacity());
 
                 let common_length = min(read.len(), write.len());
                 let (read_common, read_remainder) = read.split_at_mut(common_length);
@@ -2375,14 +3677,58 @@ mod dma {
                 }
             }
 
+            /// Runs `sequence` once, and stores the data of its read segments
+            /// one after the other in `read`.
+            ///
+            /// Completion is signalled by the segmented transfer done
+            /// interrupt, so the CPU is free while the sequence runs. To run
+            /// the sequence at a fixed rate, call this from a periodic task,
+            /// e.g. driven by an `embassy_time::Ticker`.
+            ///
+            /// # Errors
+            ///
+            /// See [`SpiDmaBus::run_sequence`].
+            #[cfg(gdma)]
+            #[instability::unstable]
+            pub async fn run_sequence_async(
+                &mut self,
+                sequence: &TransactionSequence<'_>,
+                read: &mut [u8],
+            ) -> Result<(), Error> {
+                self.spi_dma.wait_for_idle_async().await;
+                self.load_sequence(sequence, read)?;
+                if sequence.is_empty() {
+                    return Ok(());
+                }
+
+                let driver = self.spi_dma.driver();
+                let future =
+                    SpiFuture::setup_for(&driver, SpiInterrupt::DmaSegmentedTransferDone).await;
+                self.start_sequence(sequence)?;
+
+                let mut spi = DropGuard::new(&mut self.spi_dma, |spi| spi.cancel_transfer());
+                future.await;
+                spi.wait_for_idle_async().await;
+                spi.defuse();
+
+                self.finish_sequence(sequence, read);
+
+                Ok(())
+            }
+
             /// Transfer by writing out a buffer and reading the response from
             /// the bus into the same buffer.
             #[instability::unstable]
             pub async fn transfer_in_place_async(&mut self, words: &mut [u8]) -> Result<(), Error> {
                 self.spi_dma.wait_for_idle_async().await;
+                if let Some(_cs) = self.use_fifo(words.len()) {
+                    return self.spi_dma.driver().transfer_in_place_async(words).await;
+                }
 
+                let _cs = self.hold_cs(words.len(), words.len());
+                let chunk_size = min(self.tx_chunk_size(), self.rx_chunk_size());
                 let mut spi = DropGuard::new(&mut self.spi_dma, |spi| spi.cancel_transfer());
-                for chunk in words.chunks_mut(self.tx_buf.capacity()) {
+                for chunk in words.chunks_mut(chunk_size) {
                     self.tx_buf.fill(chunk);
                     self.rx_buf.set_length(chunk.len());
 
@@ -2407,7 +3753,7 @@ mod dma {
         }
 
         #[instability::unstable]
-        impl embedded_hal_async::spi::SpiBus for SpiDmaBus<'_, Async> {
+        impl<T> embedded_hal_async::spi::SpiBus for SpiDmaBus<'_, Async, T> {
             async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
                 self.read_async(words).await
             }
@@ -2439,7 +3785,7 @@ mod dma {
         use super::*;
 
         #[instability::unstable]
-        impl<Dm> ErrorType for SpiDmaBus<'_, Dm>
+        impl<Dm, T> ErrorType for SpiDmaBus<'_, Dm, T>
         where
             Dm: DriverMode,
         {
@@ -2447,7 +3793,7 @@ mod dma {
         }
 
         #[instability::unstable]
-        impl<Dm> SpiBus for SpiDmaBus<'_, Dm>
+        impl<Dm, T> SpiBus for SpiDmaBus<'_, Dm, T>
         where
             Dm: DriverMode,
         {
@@ -2481,11 +3827,11 @@ mod ehal1 {
 
     use super::*;
 
-    impl<Dm> embedded_hal::spi::ErrorType for Spi<'_, Dm> {
+    impl<Dm, T> embedded_hal::spi::ErrorType for Spi<'_, Dm, T> {
         type Error = Error;
     }
 
-    impl<Dm> SpiBus for Spi<'_, Dm>
+    impl<Dm, T> SpiBus for Spi<'_, Dm, T>
     where
         Dm: DriverMode,
     {
@@ -2552,7 +3898,7 @@ mod ehal1 {
         }
     }
 
-    impl SpiBusAsync for Spi<'_, Async> {
+    impl<T> SpiBusAsync for Spi<'_, Async, T> {
         async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
             // We need to flush because the blocking transfer functions may return while a
             // transfer is still in progress.
@@ -2568,48 +3914,10 @@ mod ehal1 {
         }
 
         async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
-            // Optimizations
-            if read.is_empty() {
-                return SpiBusAsync::write(self, write).await;
-            } else if write.is_empty() {
-                return SpiBusAsync::read(self, read).await;
-            }
-
-            let mut write_from = 0;
-            let mut read_from = 0;
-
-            loop {
-                // How many bytes we write in this chunk
-                let write_inc = core::cmp::min(FIFO_SIZE, write.len() - write_from);
-                let write_to = write_from + write_inc;
-                // How many bytes we read in this chunk
-                let read_inc = core::cmp::min(FIFO_SIZE, read.len() - read_from);
-                let read_to = read_from + read_inc;
-
-                if (write_inc == 0) && (read_inc == 0) {
-                    break;
-                }
-
-                // No need to flush here, `SpiBusAsync::write` will do it for us
-
-                if write_to < read_to {
-                    // Read more than we write, must pad writing part with zeros
-                    let mut empty = [EMPTY_WRITE_PAD; FIFO_SIZE];
-                    empty[0..write_inc].copy_from_slice(&write[write_from..write_to]);
-                    SpiBusAsync::write(self, &empty).await?;
-                } else {
-                    SpiBusAsync::write(self, &write[write_from..write_to]).await?;
-                }
-
-                if read_inc > 0 {
-                    self.driver()
-                        .read_bytes_from_fifo(&mut read[read_from..read_to])?;
-                }
-
-                write_from = write_to;
-                read_from = read_to;
-            }
-            Ok(())
+            // We need to flush because the blocking transfer functions may return while a
+            // transfer is still in progress.
+            self.flush_async().await?;
+            self.driver().transfer_async(read, write).await
         }
 
         async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
@@ -2633,6 +3941,12 @@ pub trait PeripheralInstance: private::Sealed + Into<AnySpi> + DmaEligible + 'st
 #[doc(hidden)]
 pub trait QspiInstance: PeripheralInstance {}
 
+/// Marker trait for the SPI peripherals that have the CS line `L`.
+///
+/// [`AnySpi`] has the lines that every instance has, `Cs0` to `Cs2`.
+#[instability::unstable]
+pub trait HasCsLine<L: CsLine>: PeripheralInstance {}
+
 /// Peripheral data describing a particular SPI instance.
 #[doc(hidden)]
 #[non_exhaustive]
@@ -2657,8 +3971,8 @@ pub struct Info {
     /// MISO signal.
     pub miso: InputSignal,
 
-    /// Chip select signal.
-    pub cs: OutputSignal,
+    /// Chip select signals, starting with CS0.
+    pub cs: &'static [OutputSignal],
 
     /// SIO0 (MOSI) input signal for half-duplex mode.
     pub sio0_input: InputSignal,
@@ -2687,6 +4001,16 @@ struct DmaDriver {
 impl DmaDriver {
     fn abort_transfer(&self) {
         self.driver.configure_datalen(1, 1);
+
+        // Don't let a segmented transfer continue with its next segment.
+        #[cfg(gdma)]
+        {
+            self.regs()
+                .user()
+                .modify(|_, w| w.usr_conf_nxt().clear_bit());
+            self.regs().slave().modify(|_, w| w.usr_conf().clear_bit());
+        }
+
         self.driver.update();
     }
 
@@ -2748,11 +4072,129 @@ impl DmaDriver {
         #[cfg(gdma)]
         self.reset_dma();
 
+        // Async waiters rely on the transfer done flag to signal the end of this
+        // transfer, not a previous one.
+        self.driver
+            .clear_interrupts(SpiInterrupt::TransferDone.into());
+        self.driver.start_operation();
+
+        Ok(())
+    }
+
+    /// Starts a segmented transfer, whose first segment has already been set
+    /// up.
+    ///
+    /// The TX stream holds the data of the first segment, followed by the
+    /// CONF buffer and data of every other segment. The RX stream receives the
+    /// data of all read segments.
+    #[cfg(gdma)]
+    unsafe fn start_sequence_dma<RX: Rx, TX: Tx>(
+        &self,
+        rx_len: usize,
+        tx_len: usize,
+        rx_buffer: &mut impl DmaRxBuffer,
+        tx_buffer: &mut impl DmaTxBuffer,
+        rx: &mut RX,
+        tx: &mut TX,
+    ) -> Result<(), Error> {
+        self.enable_dma();
+
+        if rx_len > 0 {
+            rx.prepare_transfer(self.dma_peripheral, rx_buffer)
+                .and_then(|_| rx.start_transfer())?;
+        }
+        if tx_len > 0 {
+            tx.prepare_transfer(self.dma_peripheral, tx_buffer)
+                .and_then(|_| tx.start_transfer())?;
+        }
+
+        self.reset_dma();
+
+        self.driver
+            .clear_interrupts(SpiInterrupt::TransferDone | SpiInterrupt::DmaSegmentedTransferDone);
+        self.driver.start_operation();
+
+        Ok(())
+    }
+
+    /// Starts transmitting the ring linked by `chain` in continuous mode, in
+    /// which the SPI keeps going for as long as the DMA provides data.
+    #[cfg(pdma)]
+    unsafe fn start_circular_tx_dma<TX: Tx>(
+        &self,
+        len: usize,
+        chain: &crate::dma::DescriptorChain,
+        tx: &mut TX,
+    ) -> Result<(), Error> {
+        #[cfg(esp32s2)]
+        {
+            // without this a transfer after a write will fail
+            self.regs().dma_out_link().write(|w| w.bits(0));
+            self.regs().dma_in_link().write(|w| w.bits(0));
+        }
+
+        self.driver.configure_datalen(0, len);
+
+        self.regs()
+            .user()
+            .modify(|_, w| w.usr_miso().clear_bit().usr_mosi().set_bit());
+
+        self.enable_dma();
+
+        #[cfg(esp32)]
+        {
+            // see https://github.com/espressif/esp-idf/commit/366e4397e9dae9d93fe69ea9d389b5743295886f
+            self.regs()
+                .dma_in_link()
+                .modify(|_, w| unsafe { w.inlink_addr().bits(0) });
+            self.regs()
+                .dma_in_link()
+                .modify(|_, w| w.inlink_start().set_bit());
+        }
+
+        self.regs().dma_conf().modify(|_, w| {
+            w.dma_tx_stop().clear_bit();
+            w.dma_continue().set_bit()
+        });
+
+        unsafe {
+            tx.prepare_transfer_without_start(self.dma_peripheral, chain)
+                .and_then(|_| tx.start_transfer())?;
+        }
+
+        self.driver
+            .clear_interrupts(SpiInterrupt::TransferDone.into());
         self.driver.start_operation();
 
         Ok(())
     }
 
+    /// Ends a continuous transmission once the DMA has run out of data.
+    #[cfg(pdma)]
+    fn stop_circular_tx_dma(&self) {
+        // The data fetched last may still be on its way to the SPI.
+        let out_fifo_empty = || {
+            cfg_if::cfg_if! {
+                if #[cfg(esp32)] {
+                    self.regs().dma_rstatus().read().dma_out_status().bits() & 0x8000_0000 != 0
+                } else {
+                    self.regs().dma_outstatus().read().dma_outfifo_empty().bit_is_set()
+                }
+            }
+        };
+        while !out_fifo_empty() {}
+
+        self.regs()
+            .dma_conf()
+            .modify(|_, w| w.dma_tx_stop().set_bit());
+        while self.driver.busy() {}
+
+        self.regs().dma_conf().modify(|_, w| {
+            w.dma_tx_stop().clear_bit();
+            w.dma_continue().clear_bit()
+        });
+    }
+
     fn enable_dma(&self) {
         #[cfg(gdma)]
         // for non GDMA this is done in `assign_tx_device` / `assign_rx_device`
@@ -2765,6 +4207,27 @@ impl DmaDriver {
         self.reset_dma();
     }
 
+    /// Hands the data phase back to the FIFO after DMA transfers.
+    fn disable_dma(&self) {
+        #[cfg(gdma)]
+        self.regs().dma_conf().modify(|_, w| {
+            w.dma_tx_ena().clear_bit();
+            w.dma_rx_ena().clear_bit()
+        });
+
+        #[cfg(pdma)]
+        {
+            self.regs().dma_out_link().reset();
+            self.regs().dma_in_link().reset();
+        }
+
+        // DMA transfers only enable the phases they use, the FIFO functions
+        // expect both.
+        self.regs()
+            .user()
+            .modify(|_, w| w.usr_miso().set_bit().usr_mosi().set_bit());
+    }
+
     fn reset_dma(&self) {
         fn set_reset_bit(reg_block: &RegisterBlock, bit: bool) {
             #[cfg(pdma)]
@@ -2835,7 +4298,6 @@ impl Driver {
             w.doutdin().set_bit();
             w.usr_miso().set_bit();
             w.usr_mosi().set_bit();
-            w.cs_hold().set_bit();
             w.usr_dummy_idle().set_bit();
             w.usr_addr().clear_bit();
             w.usr_command().clear_bit()
@@ -2881,12 +4343,7 @@ impl Driver {
     }
 
     #[cfg(not(esp32))]
-    fn init_spi_data_mode(
-        &self,
-        cmd_mode: DataMode,
-        address_mode: DataMode,
-        data_mode: DataMode,
-    ) -> Result<(), Error> {
+    fn init_spi_data_mode(&self, cmd_mode: DataMode, address_mode: DataMode, data_mode: DataMode) {
         self.regs().ctrl().modify(|_, w| {
             w.fcmd_dual().bit(cmd_mode == DataMode::Dual);
             w.fcmd_quad().bit(cmd_mode == DataMode::Quad);
@@ -2899,23 +4356,10 @@ impl Driver {
             w.fwrite_dual().bit(data_mode == DataMode::Dual);
             w.fwrite_quad().bit(data_mode == DataMode::Quad)
         });
-        Ok(())
     }
 
     #[cfg(esp32)]
-    fn init_spi_data_mode(
-        &self,
-        cmd_mode: DataMode,
-        address_mode: DataMode,
-        data_mode: DataMode,
-    ) -> Result<(), Error> {
-        match cmd_mode {
-            DataMode::Single => (),
-            DataMode::SingleTwoDataLines => (),
-            // FIXME: more detailed error - Only 1-bit commands are supported.
-            _ => return Err(Error::Unsupported),
-        }
-
+    fn init_spi_data_mode(&self, _cmd_mode: DataMode, address_mode: DataMode, data_mode: DataMode) {
         match address_mode {
             DataMode::Single | DataMode::SingleTwoDataLines => {
                 self.regs().ctrl().modify(|_, w| {
@@ -2932,7 +4376,9 @@ impl Driver {
                     w.fwrite_quad().bit(data_mode == DataMode::Quad)
                 });
             }
-            address_mode if address_mode == data_mode => {
+            // `validate_data_modes` only lets multi-line addresses through if
+            // they match the data mode.
+            _ => {
                 self.regs().ctrl().modify(|_, w| {
                     w.fastrd_mode().set_bit();
                     w.fread_dio().bit(address_mode == DataMode::Dual);
@@ -2948,8 +4394,59 @@ impl Driver {
                     w.fwrite_quad().clear_bit()
                 });
             }
-            // FIXME: more detailed error - Unsupported combination of data-modes,
-            _ => return Err(Error::Unsupported),
+        }
+    }
+
+    /// Checks that the hardware can perform a transfer whose phases use the
+    /// given data modes, before any register is touched.
+    ///
+    /// Missing SIO2 and SIO3 signals are rejected when the pins are connected,
+    /// so the quad check only catches drivers whose pins were never
+    /// connected. The other rules depend on the modes of each transfer.
+    fn validate_data_modes(
+        &self,
+        cmd: Command,
+        address: Address,
+        data_mode: DataMode,
+    ) -> Result<(), Error> {
+        let modes = [cmd.mode(), address.mode(), data_mode];
+
+        // Single-line phases turn the peripheral into three-wire mode, which
+        // can't be mixed with phases that use more data lines.
+        let three_wire = modes.contains(&DataMode::Single);
+        if three_wire
+            && ((cmd != Command::None && cmd.mode() != DataMode::Single)
+                || (address != Address::None && address.mode() != DataMode::Single)
+                || data_mode != DataMode::Single)
+        {
+            return Err(Error::Unsupported);
+        }
+
+        // Not every instance has the SIO2 and SIO3 signals routed to the GPIO
+        // matrix. `with_sio2` and `with_sio3` already reject these instances.
+        if modes.contains(&DataMode::Quad)
+            && (self.info.sio2_output.is_none() || self.info.sio3_output.is_none())
+        {
+            return Err(Error::Unsupported);
+        }
+
+        // The driver doesn't configure the SIO4..SIO7 lines.
+        #[cfg(spi_octal)]
+        if modes.contains(&DataMode::Octal) {
+            return Err(Error::Unsupported);
+        }
+
+        // The ESP32 can only send the command on a single line, and can only send
+        // the address on a single line or on as many lines as the data.
+        #[cfg(esp32)]
+        {
+            let single_line =
+                |mode| matches!(mode, DataMode::Single | DataMode::SingleTwoDataLines);
+            if !single_line(cmd.mode())
+                || (!single_line(address.mode()) && address.mode() != data_mode)
+            {
+                return Err(Error::Unsupported);
+            }
         }
 
         Ok(())
@@ -3075,12 +4572,93 @@ impl Driver {
     }
 
     fn apply_config(&self, config: &Config) -> Result<(), ConfigError> {
+        config.validate()?;
         self.ch_bus_freq(config)?;
         self.set_bit_order(config.read_bit_order, config.write_bit_order);
         self.set_data_mode(config.mode);
+        self.set_cs_timing(config);
+        self.state
+            .fifo_threshold
+            .store(config.fifo_threshold, Ordering::Relaxed);
         Ok(())
     }
 
+    fn set_cs_timing(&self, config: &Config) {
+        let setup = config.cs_setup_time;
+        let hold = config.cs_hold_time;
+
+        // The setup and hold time registers hold one cycle less than the
+        // configured time, which is why a time of 0 needs the phase disabled.
+        self.regs().user().modify(|_, w| {
+            w.cs_setup().bit(setup > 0);
+            w.cs_hold().bit(hold > 0)
+        });
+
+        cfg_if::cfg_if! {
+            if #[cfg(esp32)] {
+                self.regs().ctrl2().modify(|_, w| unsafe {
+                    w.setup_time().bits(setup.saturating_sub(1) as u8);
+                    w.hold_time().bits(hold.saturating_sub(1) as u8)
+                });
+                self.regs()
+                    .ctrl1()
+                    .modify(|_, w| unsafe { w.cs_hold_delay().bits(config.cs_idle_time as u8) });
+            } else if #[cfg(esp32s2)] {
+                self.regs().ctrl2().modify(|_, w| unsafe {
+                    w.cs_setup_time().bits(setup.saturating_sub(1));
+                    w.cs_hold_time().bits(hold.saturating_sub(1))
+                });
+                self.regs()
+                    .ctrl1()
+                    .modify(|_, w| unsafe { w.cs_hold_delay().bits(config.cs_idle_time as u8) });
+            } else {
+                self.regs().user1().modify(|_, w| unsafe {
+                    w.cs_setup_time().bits(setup.saturating_sub(1) as u8);
+                    w.cs_hold_time().bits(hold.saturating_sub(1) as u8)
+                });
+            }
+        }
+    }
+
+    /// Enables the given CS line and disables all the others.
+    fn select_cs(&self, line: usize) {
+        cfg_if::cfg_if! {
+            if #[cfg(esp32)] {
+                self.regs().pin().modify(|_, w| {
+                    w.cs0_dis().bit(line != 0);
+                    w.cs1_dis().bit(line != 1);
+                    w.cs2_dis().bit(line != 2)
+                });
+            } else {
+                self.regs().misc().modify(|_, w| {
+                    w.cs0_dis().bit(line != 0);
+                    w.cs1_dis().bit(line != 1);
+                    w.cs2_dis().bit(line != 2);
+                    w.cs3_dis().bit(line != 3);
+                    w.cs4_dis().bit(line != 4);
+                    w.cs5_dis().bit(line != 5)
+                });
+            }
+        }
+        self.update();
+    }
+
+    /// Keeps CS asserted after the current transaction, so that several
+    /// transactions appear as a single transfer on the bus.
+    #[cfg_attr(not(feature = "unstable"), allow(dead_code))]
+    fn set_cs_keep_active(&self, keep_active: bool) {
+        cfg_if::cfg_if! {
+            if #[cfg(esp32)] {
+                let pin_reg = self.regs().pin();
+            } else {
+                let pin_reg = self.regs().misc();
+            }
+        };
+
+        pin_reg.modify(|_, w| w.cs_keep_active().bit(keep_active));
+        self.update();
+    }
+
     fn set_data_mode(&self, data_mode: Mode) {
         cfg_if::cfg_if! {
             if #[cfg(esp32)] {
@@ -3318,6 +4896,49 @@ impl Driver {
         Ok(())
     }
 
+    #[cfg_attr(place_spi_driver_in_ram, ram)]
+    async fn transfer_async(&self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        // Optimizations
+        if read.is_empty() {
+            return self.write_bytes_async(write).await;
+        } else if write.is_empty() {
+            return self.read_bytes_async(read).await;
+        }
+
+        let mut write_from = 0;
+        let mut read_from = 0;
+
+        loop {
+            // How many bytes we write in this chunk
+            let write_inc = core::cmp::min(FIFO_SIZE, write.len() - write_from);
+            let write_to = write_from + write_inc;
+            // How many bytes we read in this chunk
+            let read_inc = core::cmp::min(FIFO_SIZE, read.len() - read_from);
+            let read_to = read_from + read_inc;
+
+            if (write_inc == 0) && (read_inc == 0) {
+                break;
+            }
+
+            if write_to < read_to {
+                // Read more than we write, must pad writing part with zeros
+                let mut empty = [EMPTY_WRITE_PAD; FIFO_SIZE];
+                empty[0..write_inc].copy_from_slice(&write[write_from..write_to]);
+                self.write_bytes_async(&empty[0..read_inc]).await?;
+            } else {
+                self.write_bytes_async(&write[write_from..write_to]).await?;
+            }
+
+            if read_inc > 0 {
+                self.read_bytes_from_fifo(&mut read[read_from..read_to])?;
+            }
+
+            write_from = write_to;
+            read_from = read_to;
+        }
+        Ok(())
+    }
+
     fn start_operation(&self) {
         self.update();
         self.regs().cmd().modify(|_, w| w.usr().set_bit());
@@ -3343,19 +4964,13 @@ impl Driver {
         no_mosi_miso: bool,
         data_mode: DataMode,
     ) -> Result<(), Error> {
+        self.validate_data_modes(cmd, address, data_mode)?;
+
         let three_wire = cmd.mode() == DataMode::Single
             || address.mode() == DataMode::Single
             || data_mode == DataMode::Single;
 
-        if three_wire
-            && ((cmd != Command::None && cmd.mode() != DataMode::Single)
-                || (address != Address::None && address.mode() != DataMode::Single)
-                || data_mode != DataMode::Single)
-        {
-            return Err(Error::Unsupported);
-        }
-
-        self.init_spi_data_mode(cmd.mode(), address.mode(), data_mode)?;
+        self.init_spi_data_mode(cmd.mode(), address.mode(), data_mode);
 
         let reg_block = self.regs();
         reg_block.user().modify(|_, w| {
@@ -3365,7 +4980,6 @@ impl Driver {
             w.doutdin().clear_bit();
             w.usr_miso().bit(!is_write && !no_mosi_miso);
             w.usr_mosi().bit(is_write && !no_mosi_miso);
-            w.cs_hold().set_bit();
             w.usr_dummy_idle().bit(dummy_idle);
             w.usr_dummy().bit(dummy != 0);
             w.usr_addr().bit(!address.is_none());
@@ -3385,9 +4999,6 @@ impl Driver {
             .spi2_clkm_conf()
             .modify(|_, w| unsafe { w.spi2_clkm_sel().bits(1) });
 
-        #[cfg(not(esp32))]
-        reg_block.misc().write(|w| unsafe { w.bits(0) });
-
         reg_block.slave().write(|w| unsafe { w.bits(0) });
 
         self.update();
@@ -3493,7 +5104,7 @@ unsafe impl Sync for Info {}
 // hardware fully. The master module should extend it with the master specific
 // details.
 macro_rules! spi_instance {
-    ($num:literal, $sclk:ident, $mosi:ident, $miso:ident, $cs:ident $(, $sio2:ident, $sio3:ident)?) => {
+    ($num:literal, $sclk:ident, $mosi:ident, $miso:ident, [$($line:ident: $cs:ident),+] $(, $sio2:ident, $sio3:ident)?) => {
         paste::paste! {
             impl PeripheralInstance for crate::peripherals::[<SPI $num>] {
                 #[inline(always)]
@@ -3505,7 +5116,7 @@ macro_rules! spi_instance {
                         sclk: OutputSignal::$sclk,
                         mosi: OutputSignal::$mosi,
                         miso: InputSignal::$miso,
-                        cs: OutputSignal::$cs,
+                        cs: &[$(OutputSignal::$cs),+],
                         sio0_input: InputSignal::$mosi,
                         sio1_output: OutputSignal::$miso,
                         sio2_output: $crate::if_set!($(Some(OutputSignal::$sio2))?, None),
@@ -3518,6 +5129,10 @@ macro_rules! spi_instance {
                 }
             }
 
+            $(
+                impl HasCsLine<$line> for crate::peripherals::[<SPI $num>] {}
+            )+
+
             $(
                 // If the extra pins are set, implement QspiInstance
                 $crate::ignore!($sio2);
@@ -3530,22 +5145,46 @@ macro_rules! spi_instance {
 #[cfg(spi2)]
 cfg_if::cfg_if! {
     if #[cfg(esp32)] {
-        spi_instance!(2, HSPICLK, HSPID, HSPIQ, HSPICS0, HSPIWP, HSPIHD);
+        spi_instance!(2, HSPICLK, HSPID, HSPIQ, [Cs0: HSPICS0, Cs1: HSPICS1, Cs2: HSPICS2], HSPIWP, HSPIHD);
     } else if #[cfg(any(esp32s2, esp32s3))] {
-        spi_instance!(2, FSPICLK, FSPID, FSPIQ, FSPICS0, FSPIWP, FSPIHD);
+        spi_instance!(
+            2,
+            FSPICLK,
+            FSPID,
+            FSPIQ,
+            [Cs0: FSPICS0, Cs1: FSPICS1, Cs2: FSPICS2, Cs3: FSPICS3, Cs4: FSPICS4, Cs5: FSPICS5],
+            FSPIWP,
+            FSPIHD
+        );
     } else {
-        spi_instance!(2, FSPICLK_MUX, FSPID, FSPIQ, FSPICS0, FSPIWP, FSPIHD);
+        spi_instance!(
+            2,
+            FSPICLK_MUX,
+            FSPID,
+            FSPIQ,
+            [Cs0: FSPICS0, Cs1: FSPICS1, Cs2: FSPICS2, Cs3: FSPICS3, Cs4: FSPICS4, Cs5: FSPICS5],
+            FSPIWP,
+            FSPIHD
+        );
     }
 }
 
 #[cfg(spi3)]
 cfg_if::cfg_if! {
     if #[cfg(esp32)] {
-        spi_instance!(3, VSPICLK, VSPID, VSPIQ, VSPICS0, HSPIWP, HSPIHD);
+        spi_instance!(3, VSPICLK, VSPID, VSPIQ, [Cs0: VSPICS0, Cs1: VSPICS1, Cs2: VSPICS2], HSPIWP, HSPIHD);
     } else if #[cfg(esp32s3)] {
-        spi_instance!(3, SPI3_CLK, SPI3_D, SPI3_Q, SPI3_CS0, SPI3_WP, SPI3_HD);
+        spi_instance!(
+            3,
+            SPI3_CLK,
+            SPI3_D,
+            SPI3_Q,
+            [Cs0: SPI3_CS0, Cs1: SPI3_CS1, Cs2: SPI3_CS2],
+            SPI3_WP,
+            SPI3_HD
+        );
     } else {
-        spi_instance!(3, SPI3_CLK, SPI3_D, SPI3_Q, SPI3_CS0);
+        spi_instance!(3, SPI3_CLK, SPI3_D, SPI3_Q, [Cs0: SPI3_CS0, Cs1: SPI3_CS1, Cs2: SPI3_CS2]);
     }
 }
 
@@ -3563,9 +5202,14 @@ impl PeripheralInstance for super::AnySpi {
 
 impl QspiInstance for super::AnySpi {}
 
+impl HasCsLine<Cs0> for super::AnySpi {}
+impl HasCsLine<Cs1> for super::AnySpi {}
+impl HasCsLine<Cs2> for super::AnySpi {}
+
 #[doc(hidden)]
 pub struct State {
     waker: AtomicWaker,
+    fifo_threshold: AtomicUsize,
 }
 
 #[cfg_attr(place_spi_driver_in_ram, ram)]
@@ -3574,10 +5218,16 @@ fn handle_async<I: Instance>(instance: I) {
     let info = instance.info();
 
     let driver = Driver { info, state };
-    if driver.interrupts().contains(SpiInterrupt::TransferDone) {
+    let interrupts = driver.interrupts();
+    if interrupts.contains(SpiInterrupt::TransferDone) {
         state.waker.wake();
         driver.enable_listen(SpiInterrupt::TransferDone.into(), false);
     }
+    #[cfg(gdma)]
+    if interrupts.contains(SpiInterrupt::DmaSegmentedTransferDone) {
+        state.waker.wake();
+        driver.enable_listen(SpiInterrupt::DmaSegmentedTransferDone.into(), false);
+    }
 }
 
 #[doc(hidden)]
@@ -3592,6 +5242,7 @@ macro_rules! master_instance {
             fn state(&self) -> &'static State {
                 static STATE: State = State {
                     waker: AtomicWaker::new(),
+                    fifo_threshold: AtomicUsize::new(0),
                 };
 
                 &STATE
@@ -3629,17 +5280,22 @@ impl Instance for super::AnySpi {
 
 struct SpiFuture<'a> {
     driver: &'a Driver,
+    event: SpiInterrupt,
 }
 
 impl<'a> SpiFuture<'a> {
     fn setup(driver: &'a Driver) -> impl Future<Output = Self> {
+        Self::setup_for(driver, SpiInterrupt::TransferDone)
+    }
+
+    fn setup_for(driver: &'a Driver, event: SpiInterrupt) -> impl Future<Output = Self> {
         // Make sure this is called before starting an async operation. On the ESP32,
         // calling after may cause the interrupt to not fire.
         core::future::poll_fn(move |cx| {
             driver.state.waker.register(cx.waker());
-            driver.clear_interrupts(SpiInterrupt::TransferDone.into());
-            driver.enable_listen(SpiInterrupt::TransferDone.into(), true);
-            Poll::Ready(Self { driver })
+            driver.clear_interrupts(event.into());
+            driver.enable_listen(event.into(), true);
+            Poll::Ready(Self { driver, event })
         })
     }
 }
@@ -3654,13 +5310,8 @@ impl Future for SpiFuture<'_> {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        if self
-            .driver
-            .interrupts()
-            .contains(SpiInterrupt::TransferDone)
-        {
-            self.driver
-                .clear_interrupts(SpiInterrupt::TransferDone.into());
+        if self.driver.interrupts().contains(self.event) {
+            self.driver.clear_interrupts(self.event.into());
             return Poll::Ready(());
         }
 
@@ -3670,7 +5321,6 @@ impl Future for SpiFuture<'_> {
 
 impl Drop for SpiFuture<'_> {
     fn drop(&mut self) {
-        self.driver
-            .enable_listen(SpiInterrupt::TransferDone.into(), false);
+        self.driver.enable_listen(self.event.into(), false);
     }
 }
@@ -51,7 +51,46 @@
 //! # Ok(())
 //! # }
 //! ```
-//! 
+//!
+//! ### Frames of variable length
+//!
+//! The transfers above complete once the buffers are exhausted. When the
+//! master decides the length of a frame, [`queue_receive`] and
+//! [`queue_transmit`] complete as soon as CS is deasserted instead, and
+//! report how many bytes they moved:
+//!
+//! ```rust, no_run
+#![doc = crate::before_snippet!()]
+//! # use esp_hal::dma_buffers;
+//! # use esp_hal::spi::Mode;
+//! # use esp_hal::spi::slave::Spi;
+#![cfg_attr(pdma, doc = "let dma_channel = peripherals.DMA_SPI2;")]
+#![cfg_attr(gdma, doc = "let dma_channel = peripherals.DMA_CH0;")]
+//! let (rx_buffer, rx_descriptors, _, tx_descriptors) = dma_buffers!(1024, 0);
+//! let mut spi = Spi::new(peripherals.SPI2, Mode::_1)
+//!     .with_sck(peripherals.GPIO0)
+//!     .with_mosi(peripherals.GPIO2)
+//!     .with_cs(peripherals.GPIO3)
+//!     .with_dma(dma_channel, rx_descriptors, tx_descriptors);
+//!
+//! let mut receive = rx_buffer;
+//!
+//! let transfer = spi.queue_receive(&mut receive)?;
+//! let len = transfer.wait();
+//! let frame = &receive[..len];
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! To be notified of the end of a frame without polling, listen for
+//! [`SpiInterrupt::TransferDone`] or convert the driver into async mode and
+//! await [`SpiDmaTransfer::wait_for_done`]. The start of a frame is reported
+//! by [`SpiInterrupt::CsAsserted`].
+//!
+//! [`queue_receive`]: dma::SpiDma::queue_receive
+//! [`queue_transmit`]: dma::SpiDma::queue_transmit
+//! [`SpiDmaTransfer::wait_for_done`]: dma::SpiDmaTransfer::wait_for_done
+//!
 //! ## Implementation State
 //!
 //! This driver is currently **unstable**.
@@ -61,7 +100,11 @@
 //! - Single transfers (not segmented transfers)
 //! - Full duplex, single bit (not dual or quad SPI)
 //! - DMA mode (not CPU mode).
-#![cfg_attr(esp32, doc = "- ESP32 only supports SPI mode 1 and 3.\n\n")]
+#![cfg_attr(esp32, doc = "- ESP32 only supports SPI mode 1 and 3.")]
+#![cfg_attr(
+    esp32,
+    doc = "- The ESP32 DMA moves received data in whole words. Receive buffers must be a multiple of 4 bytes long, and frames should be too, as trailing bytes past the last complete word are lost.\n\n"
+)]
 //! It also does not support blocking operations, as the actual
 //! transfer is controlled by the SPI master; if these are necessary,
 //! then the `DmaTransfer` object can be `wait()`ed on or polled for
@@ -71,15 +114,21 @@
 
 use core::marker::PhantomData;
 
+use enumset::{EnumSet, EnumSetType};
+
 use super::{Error, Mode};
 use crate::{
+    asynch::AtomicWaker,
     dma::DmaEligible,
     gpio::{
         interconnect::{PeripheralInput, PeripheralOutput},
+        AnyPin,
+        Event,
         InputSignal,
         NoPin,
         OutputSignal,
     },
+    interrupt::InterruptHandler,
     pac::spi2::RegisterBlock,
     peripheral::{Peripheral, PeripheralRef},
     spi::AnySpi,
@@ -89,6 +138,64 @@ use crate::{
 
 const MAX_DMA_SIZE: usize = 32768 - 32;
 
+/// Enumeration of possible SPI slave interrupt events.
+#[derive(Debug, Hash, EnumSetType)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+#[instability::unstable]
+pub enum SpiInterrupt {
+    /// Indicates that the master deasserted CS, ending the current frame.
+    TransferDone,
+
+    /// Indicates that the master asserted CS, starting a frame.
+    ///
+    /// The SPI peripheral has no event for the start of a frame, so this one
+    /// is raised by the GPIO that CS is connected to. Listening for it
+    /// enables the interrupt of that GPIO, which runs the GPIO interrupt
+    /// handler (see [`Io::set_interrupt_handler`]) instead of the handler of
+    /// the SPI driver. The GPIO interrupt handler clears the event once the
+    /// user handler returns.
+    ///
+    /// The event is never raised if CS isn't connected to a GPIO.
+    ///
+    /// [`Io::set_interrupt_handler`]: crate::gpio::Io::set_interrupt_handler
+    CsAsserted,
+}
+
+/// The GPIO that carries the CS signal, and the edge that asserts it.
+struct CsPin {
+    pin: AnyPin,
+    assert: Event,
+}
+
+impl CsPin {
+    fn new(pin: AnyPin, is_inverted: bool) -> Self {
+        // CS is active low, unless the signal is inverted on its way to the
+        // peripheral.
+        let assert = if is_inverted {
+            Event::RisingEdge
+        } else {
+            Event::FallingEdge
+        };
+
+        // Latch the event in the status bit, without interrupting the CPU.
+        pin.set_interrupt_event(Some(assert), false);
+        pin.clear_interrupt();
+
+        Self { pin, assert }
+    }
+
+    fn enable_listen(&self, enable: bool) {
+        self.pin.set_interrupt_event(Some(self.assert), enable);
+    }
+}
+
+impl Drop for CsPin {
+    fn drop(&mut self) {
+        self.pin.set_interrupt_event(None, false);
+    }
+}
+
 /// SPI peripheral driver.
 ///
 /// See the [module-level documentation][self] for more details.
@@ -97,6 +204,7 @@ pub struct Spi<'d, Dm> {
     spi: PeripheralRef<'d, AnySpi>,
     #[allow(dead_code)]
     data_mode: Mode,
+    cs: Option<CsPin>,
     _mode: PhantomData<Dm>,
     _guard: PeripheralGuard,
 }
@@ -111,6 +219,7 @@ impl<'d> Spi<'d, Blocking> {
         let this = Spi {
             spi,
             data_mode: mode,
+            cs: None,
             _mode: PhantomData,
             _guard: guard,
         };
@@ -153,9 +262,12 @@ impl<'d> Spi<'d, Blocking> {
 
     /// Assign the CS (Chip Select) pin for the SPI instance.
     #[instability::unstable]
-    pub fn with_cs(self, cs: impl Peripheral<P = impl PeripheralInput> + 'd) -> Self {
+    pub fn with_cs(mut self, cs: impl Peripheral<P = impl PeripheralInput> + 'd) -> Self {
         crate::into_mapped_ref!(cs);
         cs.enable_input(true);
+        self.cs = cs
+            .pin()
+            .map(|(pin, is_inverted)| CsPin::new(pin, is_inverted));
         self.spi.info().cs.connect_to(cs);
         self
     }
@@ -185,6 +297,8 @@ pub mod dma {
             Tx,
             WriteBuffer,
         },
+        interrupt::InterruptConfigurable,
+        Async,
         DriverMode,
     };
 
@@ -205,6 +319,7 @@ pub mod dma {
             self.spi.info().set_data_mode(self.data_mode, true);
             SpiDma::new(
                 self.spi,
+                self.cs,
                 channel.map(|ch| ch.degrade()).into_ref(),
                 rx_descriptors,
                 tx_descriptors,
@@ -220,6 +335,7 @@ pub mod dma {
     {
         pub(crate) spi: PeripheralRef<'d, AnySpi>,
         pub(crate) channel: Channel<'d, Dm, PeripheralDmaChannel<AnySpi>>,
+        cs: Option<CsPin>,
         rx_chain: DescriptorChain,
         tx_chain: DescriptorChain,
         _guard: PeripheralGuard,
@@ -285,6 +401,7 @@ pub mod dma {
     impl<'d> SpiDma<'d, Blocking> {
         fn new(
             spi: PeripheralRef<'d, AnySpi>,
+            cs: Option<CsPin>,
             channel: PeripheralRef<'d, PeripheralDmaChannel<AnySpi>>,
             rx_descriptors: &'static mut [DmaDescriptor],
             tx_descriptors: &'static mut [DmaDescriptor],
@@ -296,6 +413,7 @@ pub mod dma {
             Self {
                 spi,
                 channel,
+                cs,
                 rx_chain: DescriptorChain::new(rx_descriptors),
                 tx_chain: DescriptorChain::new(tx_descriptors),
                 _guard: guard,
@@ -303,7 +421,105 @@ pub mod dma {
         }
     }
 
-    impl<Dm> SpiDma<'_, Dm>
+    impl<'d> SpiDma<'d, Blocking> {
+        /// Converts the SPI instance into async mode.
+        #[instability::unstable]
+        pub fn into_async(mut self) -> SpiDma<'d, Async> {
+            self.set_interrupt_handler(self.spi.handler());
+            SpiDma {
+                spi: self.spi,
+                channel: self.channel.into_async(),
+                cs: self.cs,
+                rx_chain: self.rx_chain,
+                tx_chain: self.tx_chain,
+                _guard: self._guard,
+            }
+        }
+
+        /// Listen for the given interrupts
+        #[instability::unstable]
+        pub fn listen(&mut self, interrupts: impl Into<EnumSet<SpiInterrupt>>) {
+            self.enable_listen(interrupts.into(), true);
+        }
+
+        /// Unlisten the given interrupts
+        #[instability::unstable]
+        pub fn unlisten(&mut self, interrupts: impl Into<EnumSet<SpiInterrupt>>) {
+            self.enable_listen(interrupts.into(), false);
+        }
+
+        fn enable_listen(&mut self, interrupts: EnumSet<SpiInterrupt>, enable: bool) {
+            self.spi.info().enable_listen(interrupts, enable);
+            if let Some(cs) = self.cs.as_ref() {
+                if interrupts.contains(SpiInterrupt::CsAsserted) {
+                    cs.enable_listen(enable);
+                }
+            }
+        }
+
+        /// Gets asserted interrupts
+        #[instability::unstable]
+        pub fn interrupts(&mut self) -> EnumSet<SpiInterrupt> {
+            let mut res = self.spi.info().interrupts();
+            if self.cs.as_ref().is_some_and(|cs| cs.pin.is_interrupt_set()) {
+                res.insert(SpiInterrupt::CsAsserted);
+            }
+            res
+        }
+
+        /// Resets asserted interrupts
+        #[instability::unstable]
+        pub fn clear_interrupts(&mut self, interrupts: impl Into<EnumSet<SpiInterrupt>>) {
+            let interrupts = interrupts.into();
+            self.spi.info().clear_interrupts(interrupts);
+            if let Some(cs) = self.cs.as_ref() {
+                if interrupts.contains(SpiInterrupt::CsAsserted) {
+                    cs.pin.clear_interrupt();
+                }
+            }
+        }
+    }
+
+    impl<'d> SpiDma<'d, Async> {
+        /// Converts the SPI instance into blocking mode.
+        #[instability::unstable]
+        pub fn into_blocking(self) -> SpiDma<'d, Blocking> {
+            crate::interrupt::disable(crate::Cpu::current(), self.spi.info().interrupt);
+            SpiDma {
+                spi: self.spi,
+                channel: self.channel.into_blocking(),
+                cs: self.cs,
+                rx_chain: self.rx_chain,
+                tx_chain: self.tx_chain,
+                _guard: self._guard,
+            }
+        }
+    }
+
+    impl<Dm> crate::private::Sealed for SpiDma<'_, Dm> where Dm: DriverMode {}
+
+    #[instability::unstable]
+    impl InterruptConfigurable for SpiDma<'_, Blocking> {
+        /// Sets the interrupt handler
+        ///
+        /// Interrupts are not enabled at the peripheral level here.
+        fn set_interrupt_handler(&mut self, handler: InterruptHandler) {
+            SpiDma::set_interrupt_handler(self, handler);
+        }
+    }
+
+    impl SpiDma<'_, Blocking> {
+        fn set_interrupt_handler(&mut self, handler: InterruptHandler) {
+            let interrupt = self.spi.info().interrupt;
+            for core in crate::Cpu::other() {
+                crate::interrupt::disable(core, interrupt);
+            }
+            unsafe { crate::interrupt::bind_interrupt(interrupt, handler.handler()) };
+            unwrap!(crate::interrupt::enable(interrupt, handler.priority()));
+        }
+    }
+
+    impl<'d, Dm> SpiDma<'d, Dm>
     where
         Dm: DriverMode,
     {
@@ -314,6 +530,97 @@ pub mod dma {
             }
         }
 
+        /// Queues a buffer to be filled by the next frame of the master.
+        ///
+        /// Unlike [Self::read], the returned [SpiDmaTransfer] completes when
+        /// the master deasserts CS, and reports how many bytes were received.
+        /// The maximum amount of data to be received is 32736 bytes, bytes
+        /// the master sends beyond the end of the buffer are dropped.
+        ///
+        /// MISO is not driven by the slave during the frame.
+        #[cfg_attr(
+            esp32,
+            doc = "\n\n**Note**: The DMA of the ESP32 only moves whole words into the buffer, so its length must be a multiple of 4 bytes, and received bytes past the last complete word are lost."
+        )]
+        #[instability::unstable]
+        pub fn queue_receive<'t, RXBUF>(
+            &'t mut self,
+            words: &'t mut RXBUF,
+        ) -> Result<SpiDmaTransfer<'t, 'd, Dm>, Error>
+        where
+            RXBUF: WriteBuffer,
+        {
+            let (ptr, len) = unsafe { words.write_buffer() };
+
+            if len > MAX_DMA_SIZE {
+                return Err(Error::MaxDmaTransferSizeExceeded);
+            }
+
+            if cfg!(esp32) && len % 4 != 0 {
+                return Err(Error::Unsupported);
+            }
+
+            unsafe {
+                self.driver().start_transfer_dma(
+                    &mut self.rx_chain,
+                    &mut self.tx_chain,
+                    ptr,
+                    len,
+                    core::ptr::null(),
+                    0,
+                    &mut self.channel.rx,
+                    &mut self.channel.tx,
+                )?;
+            }
+
+            Ok(SpiDmaTransfer {
+                spi_dma: self,
+                len,
+                is_rx: true,
+            })
+        }
+
+        /// Queues a buffer to be sent during the next frame of the master.
+        ///
+        /// Unlike [Self::write], the returned [SpiDmaTransfer] completes when
+        /// the master deasserts CS, and reports how many bytes were clocked
+        /// out. The maximum amount of data to be sent is 32736 bytes.
+        ///
+        /// Data sent by the master on MOSI is ignored.
+        #[instability::unstable]
+        pub fn queue_transmit<'t, TXBUF>(
+            &'t mut self,
+            words: &'t TXBUF,
+        ) -> Result<SpiDmaTransfer<'t, 'd, Dm>, Error>
+        where
+            TXBUF: ReadBuffer,
+        {
+            let (ptr, len) = unsafe { words.read_buffer() };
+
+            if len > MAX_DMA_SIZE {
+                return Err(Error::MaxDmaTransferSizeExceeded);
+            }
+
+            unsafe {
+                self.driver().start_transfer_dma(
+                    &mut self.rx_chain,
+                    &mut self.tx_chain,
+                    core::ptr::null_mut(),
+                    0,
+                    ptr,
+                    len,
+                    &mut self.channel.rx,
+                    &mut self.channel.tx,
+                )?;
+            }
+
+            Ok(SpiDmaTransfer {
+                spi_dma: self,
+                len,
+                is_rx: false,
+            })
+        }
+
         /// Register a buffer for a DMA write.
         ///
         /// This will return a [DmaTransferTx]. The maximum amount of data to be
@@ -427,6 +734,116 @@ pub mod dma {
         }
     }
 
+    /// A frame queued with [SpiDma::queue_receive] or
+    /// [SpiDma::queue_transmit].
+    ///
+    /// Dropping the transfer before the frame ended stops the DMA transfer.
+    #[instability::unstable]
+    pub struct SpiDmaTransfer<'t, 'd, Dm>
+    where
+        Dm: DriverMode,
+    {
+        spi_dma: &'t mut SpiDma<'d, Dm>,
+        len: usize,
+        is_rx: bool,
+    }
+
+    impl<Dm> core::fmt::Debug for SpiDmaTransfer<'_, '_, Dm>
+    where
+        Dm: DriverMode,
+    {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("SpiDmaTransfer")
+                .field("len", &self.len)
+                .finish()
+        }
+    }
+
+    impl<Dm> SpiDmaTransfer<'_, '_, Dm>
+    where
+        Dm: DriverMode,
+    {
+        /// Checks if the master ended the frame.
+        #[instability::unstable]
+        pub fn is_done(&self) -> bool {
+            if self.spi_dma.spi.info().is_bus_busy() {
+                return false;
+            }
+
+            // The received data is flushed to memory shortly after the end of
+            // the frame. The DMA of the ESP32 and ESP32-S2 never signals the
+            // end of a frame that didn't fill the buffer, though.
+            !self.is_rx || cfg!(pdma) || self.spi_dma.channel.rx.is_done()
+        }
+
+        /// Waits for the master to end the frame.
+        ///
+        /// Returns the number of bytes that were transferred.
+        #[instability::unstable]
+        pub fn wait(mut self) -> usize {
+            while !self.is_done() {}
+
+            self.finish()
+        }
+
+        fn finish(&mut self) -> usize {
+            if self.is_rx {
+                self.spi_dma.channel.rx.stop_transfer();
+            } else {
+                self.spi_dma.channel.tx.stop_transfer();
+            }
+
+            let bits = self.spi_dma.spi.info().transferred_bits(self.len * 8);
+            let bytes = (bits / 8).min(self.len);
+
+            if cfg!(esp32) && self.is_rx {
+                // Trailing bytes that don't make up a full word never leave
+                // the DMA FIFO.
+                bytes & !3
+            } else {
+                bytes
+            }
+        }
+    }
+
+    impl SpiDmaTransfer<'_, '_, Async> {
+        /// Waits for the master to end the frame asynchronously.
+        ///
+        /// Returns the number of bytes that were transferred.
+        #[instability::unstable]
+        pub async fn wait_for_done(mut self) -> usize {
+            let info = self.spi_dma.spi.info();
+            let state = self.spi_dma.spi.state();
+
+            core::future::poll_fn(|cx| {
+                state.waker.register(cx.waker());
+                if info.is_bus_busy() {
+                    info.enable_listen(SpiInterrupt::TransferDone.into(), true);
+                    core::task::Poll::Pending
+                } else {
+                    core::task::Poll::Ready(())
+                }
+            })
+            .await;
+
+            while !self.is_done() {}
+
+            self.finish()
+        }
+    }
+
+    impl<Dm> Drop for SpiDmaTransfer<'_, '_, Dm>
+    where
+        Dm: DriverMode,
+    {
+        fn drop(&mut self) {
+            if !self.is_done() {
+                self.spi_dma.channel.rx.stop_transfer();
+                self.spi_dma.channel.tx.stop_transfer();
+            }
+        }
+    }
+
     struct DmaDriver {
         info: &'static Info,
         dma_peripheral: crate::dma::DmaPeripheral,
@@ -561,6 +978,12 @@ pub mod dma {
 pub trait Instance: Peripheral<P = Self> + Into<AnySpi> + 'static {
     /// Returns the peripheral data describing this SPI instance.
     fn info(&self) -> &'static Info;
+
+    /// Returns the state used by the async driver of this SPI instance.
+    fn state(&self) -> &'static State;
+
+    /// Returns the interrupt handler used by the async driver.
+    fn handler(&self) -> InterruptHandler;
 }
 
 /// A marker for DMA-capable SPI peripheral instances.
@@ -583,6 +1006,9 @@ pub struct Info {
     /// System peripheral marker.
     pub peripheral: crate::system::Peripheral,
 
+    /// Interrupt for this SPI instance.
+    pub interrupt: crate::peripherals::Interrupt,
+
     /// SCLK signal.
     pub sclk: InputSignal,
 
@@ -731,17 +1157,23 @@ impl Info {
         }
     }
 
-    fn is_bus_busy(&self) -> bool {
+    /// Returns the raw transfer done bit, which is set when the master
+    /// deasserts CS.
+    fn is_transfer_done(&self) -> bool {
         #[cfg(pdma)]
         {
-            self.regs().slave().read().trans_done().bit_is_clear()
+            self.regs().slave().read().trans_done().bit_is_set()
         }
         #[cfg(gdma)]
         {
-            self.regs().dma_int_raw().read().trans_done().bit_is_clear()
+            self.regs().dma_int_raw().read().trans_done().bit_is_set()
         }
     }
 
+    fn is_bus_busy(&self) -> bool {
+        !self.is_transfer_done()
+    }
+
     // Check if the bus is busy and if it is wait for it to be idle
     fn flush(&self) -> Result<(), Error> {
         while self.is_bus_busy() {
@@ -764,6 +1196,74 @@ impl Info {
     }
 }
 
+impl Info {
+    /// Enable or disable listening for the given interrupts.
+    fn enable_listen(&self, interrupts: EnumSet<SpiInterrupt>, enable: bool) {
+        for interrupt in interrupts {
+            match interrupt {
+                SpiInterrupt::TransferDone => {
+                    cfg_if::cfg_if! {
+                        if #[cfg(esp32)] {
+                            self.regs().slave().modify(|_, w| w.trans_inten().bit(enable));
+                        } else if #[cfg(esp32s2)] {
+                            self.regs().slave().modify(|_, w| w.int_trans_done_en().bit(enable));
+                        } else {
+                            self.regs().dma_int_ena().modify(|_, w| w.trans_done().bit(enable));
+                        }
+                    }
+                }
+                // Raised by the GPIO of the CS line, which the driver handles
+                SpiInterrupt::CsAsserted => {}
+            }
+        }
+    }
+
+    /// Gets asserted interrupts
+    fn interrupts(&self) -> EnumSet<SpiInterrupt> {
+        let mut res = EnumSet::new();
+
+        if self.is_transfer_done() {
+            res.insert(SpiInterrupt::TransferDone);
+        }
+
+        res
+    }
+
+    /// Resets asserted interrupts
+    fn clear_interrupts(&self, interrupts: EnumSet<SpiInterrupt>) {
+        for interrupt in interrupts {
+            match interrupt {
+                SpiInterrupt::TransferDone => self.setup_for_flush(),
+                SpiInterrupt::CsAsserted => {}
+            }
+        }
+    }
+
+    /// Returns the number of bits the master clocked in the last frame.
+    ///
+    /// `expected_bits` is the length of the frame the slave was set up for.
+    fn transferred_bits(&self, expected_bits: usize) -> usize {
+        cfg_if::cfg_if! {
+            if #[cfg(esp32)] {
+                // The counter holds the length minus one if the whole frame was
+                // transferred, but the length itself if the master stopped early.
+                let bits = self.regs().slv_rd_bit().read().slv_rdata_bit().bits() as usize;
+                if bits + 1 == expected_bits {
+                    expected_bits
+                } else {
+                    bits
+                }
+            } else if #[cfg(esp32s2)] {
+                _ = expected_bits;
+                self.regs().slv_rd_byte().read().slv_data_bytelen().bits() as usize * 8
+            } else {
+                _ = expected_bits;
+                self.regs().slave1().read().slv_data_bitlen().bits() as usize
+            }
+        }
+    }
+}
+
 impl PartialEq for Info {
     fn eq(&self, other: &Self) -> bool {
         self.register_block == other.register_block
@@ -781,6 +1281,7 @@ macro_rules! spi_instance {
                     static INFO: Info = Info {
                         register_block: crate::peripherals::[<SPI $num>]::regs(),
                         peripheral: crate::system::Peripheral::[<Spi $num>],
+                        interrupt: crate::peripherals::Interrupt::[<SPI $num>],
                         sclk: InputSignal::$sclk,
                         mosi: InputSignal::$mosi,
                         miso: OutputSignal::$miso,
@@ -789,6 +1290,23 @@ macro_rules! spi_instance {
 
                     &INFO
                 }
+
+                fn state(&self) -> &'static State {
+                    static STATE: State = State {
+                        waker: AtomicWaker::new(),
+                    };
+
+                    &STATE
+                }
+
+                fn handler(&self) -> InterruptHandler {
+                    #[$crate::handler]
+                    fn handle() {
+                        handle_async(unsafe { $crate::peripherals::[<SPI $num>]::steal() })
+                    }
+
+                    handle
+                }
             }
         }
     };
@@ -816,8 +1334,24 @@ impl Instance for super::AnySpi {
             super::AnySpiInner::Spi3(spi) => spi,
         } {
             fn info(&self) -> &'static Info;
+            fn state(&self) -> &'static State;
+            fn handler(&self) -> InterruptHandler;
         }
     }
 }
 
 impl InstanceDma for super::AnySpi {}
+
+#[doc(hidden)]
+pub struct State {
+    waker: AtomicWaker,
+}
+
+fn handle_async<I: Instance>(instance: I) {
+    let info = instance.info();
+
+    if info.interrupts().contains(SpiInterrupt::TransferDone) {
+        info.enable_listen(SpiInterrupt::TransferDone.into(), false);
+        instance.state().waker.wake();
+    }
+}
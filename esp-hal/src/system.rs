@@ -108,6 +108,9 @@ pub enum Peripheral {
     /// UART2 peripheral.
     #[cfg(uart2)]
     Uart2,
+    /// UHCI0 peripheral (UART DMA controller).
+    #[cfg(uhci0)]
+    Uhci0,
     /// RSA peripheral (Rivest-Shamir-Adleman encryption).
     #[cfg(rsa)]
     Rsa,
@@ -135,6 +138,9 @@ pub enum Peripheral {
     /// Temperature sensor peripheral.
     #[cfg(tsens)]
     Tsens,
+    /// Dedicated GPIO peripheral.
+    #[cfg(all(dedicated_gpio, not(any(esp32c6, esp32h2))))]
+    DedicatedGpio,
 }
 
 impl Peripheral {
@@ -374,6 +380,10 @@ impl PeripheralClockControl {
             Peripheral::Uart2 => {
                 perip_clk_en0.modify(|_, w| w.uart2_clk_en().bit(enable));
             }
+            #[cfg(uhci0)]
+            Peripheral::Uhci0 => {
+                perip_clk_en0.modify(|_, w| w.uhci0_clk_en().bit(enable));
+            }
             #[cfg(all(rsa, esp32))]
             Peripheral::Rsa => {
                 peri_clk_en.modify(|r, w| unsafe { w.bits(r.bits() | (enable as u32) << 2) });
@@ -405,6 +415,12 @@ impl PeripheralClockControl {
             Peripheral::Tsens => {
                 perip_clk_en1.modify(|_, w| w.tsens_clk_en().bit(enable));
             }
+            #[cfg(dedicated_gpio)]
+            Peripheral::DedicatedGpio => {
+                system
+                    .cpu_peri_clk_en()
+                    .modify(|_, w| w.clk_en_dedicated_gpio().bit(enable));
+            }
         }
     }
 
@@ -587,6 +603,11 @@ impl PeripheralClockControl {
                 perip_rst_en0.modify(|_, w| w.uart2_rst().set_bit());
                 perip_rst_en0.modify(|_, w| w.uart2_rst().clear_bit());
             }
+            #[cfg(uhci0)]
+            Peripheral::Uhci0 => {
+                perip_rst_en0.modify(|_, w| w.uhci0_rst().set_bit());
+                perip_rst_en0.modify(|_, w| w.uhci0_rst().clear_bit());
+            }
             #[cfg(all(rsa, esp32))]
             Peripheral::Rsa => {
                 peri_rst_en.modify(|r, w| unsafe { w.bits(r.bits() | 1 << 2) });
@@ -627,6 +648,15 @@ impl PeripheralClockControl {
                 perip_rst_en1.modify(|_, w| w.tsens_rst().set_bit());
                 perip_rst_en1.modify(|_, w| w.tsens_rst().clear_bit());
             }
+            #[cfg(dedicated_gpio)]
+            Peripheral::DedicatedGpio => {
+                system
+                    .cpu_peri_rst_en()
+                    .modify(|_, w| w.rst_en_dedicated_gpio().set_bit());
+                system
+                    .cpu_peri_rst_en()
+                    .modify(|_, w| w.rst_en_dedicated_gpio().clear_bit());
+            }
         });
     }
 }
@@ -756,6 +786,10 @@ impl PeripheralClockControl {
                     .uart1_conf()
                     .modify(|_, w| w.uart1_clk_en().bit(enable));
             }
+            #[cfg(uhci0)]
+            Peripheral::Uhci0 => {
+                system.uhci_conf().modify(|_, w| w.uhci_clk_en().bit(enable));
+            }
             #[cfg(rsa)]
             Peripheral::Rsa => {
                 system.rsa_conf().modify(|_, w| w.rsa_clk_en().bit(enable));
@@ -955,6 +989,11 @@ impl PeripheralClockControl {
                     .uart1_conf()
                     .modify(|_, w| w.uart1_rst_en().clear_bit());
             }
+            #[cfg(uhci0)]
+            Peripheral::Uhci0 => {
+                system.uhci_conf().modify(|_, w| w.uhci_rst_en().set_bit());
+                system.uhci_conf().modify(|_, w| w.uhci_rst_en().clear_bit());
+            }
             #[cfg(rsa)]
             Peripheral::Rsa => {
                 system.rsa_conf().modify(|_, w| w.rsa_rst_en().set_bit());
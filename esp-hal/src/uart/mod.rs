@@ -36,6 +36,33 @@
 //! available. See the examples below for more information on how to interact
 //! with this driver.
 //!
+//! ## DMA
+//!
+//! The UHCI peripheral connects a UART to a DMA channel. Received bytes are
+//! streamed into a [`DmaRxStreamBuf`](crate::dma::DmaRxStreamBuf) without any
+//! CPU involvement, and written bytes are sent from a
+//! [`DmaTxBuf`](crate::dma::DmaTxBuf). This takes the load of moving bytes
+//! through the FIFOs off the CPU, which matters at high baud rates.
+//!
+//! There is a single UHCI peripheral, which can be bound to one of these UART
+//! instances at a time:
+//!
+//! | Chip                         | UART instances            |
+//! |------------------------------|---------------------------|
+//! | ESP32-C3, ESP32-C6, ESP32-H2 | `UART0`, `UART1`          |
+//! | ESP32-S3                     | `UART0`, `UART1`, `UART2` |
+//!
+//! The ESP32, ESP32-C2 and ESP32-S2 can't move UART data with DMA, so
+//! `Uart::with_dma` doesn't exist on these chips.
+//!
+//! ### Frames
+//!
+//! The DMA hands received bytes over to the driver one descriptor at a time.
+//! A descriptor is handed over when it is full, or at the end of a frame. A
+//! frame ends when the RX line goes idle, or, if one is configured with
+//! `UartDma::with_separator`, at a separator character. Smaller descriptors
+//! make received bytes available sooner.
+//!
 //! ## Example
 //!
 //! ### Handling UART Interrupts
@@ -105,31 +132,43 @@
 //! [embedded-hal-async]: embedded_hal_async
 //! [embedded-io-async]: embedded_io_async
 
-use core::{marker::PhantomData, sync::atomic::Ordering, task::Poll};
+use core::{cell::Cell, future::poll_fn, marker::PhantomData, sync::atomic::Ordering, task::Poll};
 
+use critical_section::Mutex;
 use enumset::{EnumSet, EnumSetType};
-use portable_atomic::AtomicBool;
+use portable_atomic::{AtomicBool, AtomicPtr, AtomicUsize};
 
 use crate::{
     asynch::AtomicWaker,
     clock::Clocks,
     gpio::{
         interconnect::{OutputConnection, PeripheralInput, PeripheralOutput},
+        AnyPin,
         InputSignal,
         OutputSignal,
         PinGuard,
         Pull,
     },
     interrupt::InterruptHandler,
-    pac::uart0::RegisterBlock,
+    pac::uart0::{
+        int_ena::{R as IntEnaR, W as IntEnaW},
+        RegisterBlock,
+    },
     peripheral::{Peripheral, PeripheralRef},
     peripherals::Interrupt,
     system::{PeripheralClockControl, PeripheralGuard},
+    time::{Duration, Instant},
     Async,
     Blocking,
     DriverMode,
 };
 
+#[cfg(all(uhci0, gdma, feature = "unstable"))]
+mod uhci;
+#[cfg(all(uhci0, gdma, feature = "unstable"))]
+#[instability::unstable]
+pub use uhci::*;
+
 const UART_FIFO_SIZE: u16 = 128;
 const CMD_CHAR_DEFAULT: u8 = 0x2b;
 
@@ -161,6 +200,40 @@ pub enum Error {
     /// This error occurs when the parity bit in the received data does not
     /// match the expected parity configuration.
     ParityMismatch,
+
+    /// The software RX buffer was full, and received bytes were dropped.
+    ///
+    /// See [`UartRx::into_async_with_buffer`].
+    BufferOverflowed,
+
+    /// A break was detected on the RX line.
+    ///
+    /// The RX line was held low for longer than a frame. Breaks cause
+    /// framing errors too, but they're reported as this variant instead.
+    BreakDetected,
+
+    /// A collision was detected on the RS-485 bus.
+    ///
+    /// This error occurs when the level received during a transmission
+    /// differs from the transmitted one, i.e. when another device drove the
+    /// bus at the same time. See [`Rs485Config::collision_detection`].
+    CollisionDetected,
+
+    /// The DMA failed to move data through the UHCI peripheral.
+    ///
+    /// See [`Uart::with_dma`].
+    #[cfg(all(uhci0, gdma, any(doc, feature = "unstable")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+    #[allow(clippy::enum_variant_names, reason = "DMA is unstable")]
+    DmaError(crate::dma::DmaError),
+}
+
+#[doc(hidden)]
+#[cfg(all(uhci0, gdma, feature = "unstable"))]
+impl From<crate::dma::DmaError> for Error {
+    fn from(value: crate::dma::DmaError) -> Self {
+        Error::DmaError(value)
+    }
 }
 
 impl core::error::Error for Error {}
@@ -172,6 +245,11 @@ impl core::fmt::Display for Error {
             Error::GlitchOccurred => write!(f, "A glitch was detected on the RX line"),
             Error::FrameFormatViolated => write!(f, "A framing error was detected on the RX line"),
             Error::ParityMismatch => write!(f, "A parity error was detected on the RX line"),
+            Error::BufferOverflowed => write!(f, "The software RX buffer overflowed"),
+            Error::BreakDetected => write!(f, "A break was detected on the RX line"),
+            Error::CollisionDetected => write!(f, "A collision was detected on the RS-485 bus"),
+            #[cfg(all(uhci0, gdma, any(doc, feature = "unstable")))]
+            Error::DmaError(error) => write!(f, "A DMA error occurred: {error:?}"),
         }
     }
 }
@@ -208,6 +286,21 @@ pub enum ClockSource {
 const UART_FULL_THRESH_DEFAULT: u16 = 120;
 // see <https://github.com/espressif/esp-idf/blob/8760e6d2a/components/esp_driver_uart/src/uart.c#L63>
 const UART_TOUT_THRESH_DEFAULT: u8 = 10;
+// Leaves room for the bytes the sender transmits before it notices RTS.
+const UART_FLOW_THRESH_DEFAULT: u16 = 100;
+// see <https://github.com/espressif/esp-idf/blob/8760e6d2a/components/esp_driver_uart/include/driver/uart.h#L35>
+#[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+const UART_MIN_WAKEUP_EDGES: u8 = 3;
+
+// A `U` character, start and stop bits included, has 10 edges.
+const AUTOBAUD_EDGES: u16 = 10;
+const AUTOBAUD_MIN_BAUDRATE: u32 = 300;
+const AUTOBAUD_MAX_BAUDRATE: u32 = 5_000_000;
+// The value of the pulse counters until a pulse has been measured.
+#[cfg(any(esp32, esp32s2))]
+const AUTOBAUD_COUNTER_MAX: u32 = 0xF_FFFF;
+#[cfg(not(any(esp32, esp32s2)))]
+const AUTOBAUD_COUNTER_MAX: u32 = 0xFFF;
 
 /// Number of data bits
 ///
@@ -286,6 +379,15 @@ pub struct Config {
     rx: RxConfig,
     /// UART Transmit part configuration.
     tx: TxConfig,
+    /// RS-485 half-duplex configuration.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    rs485: Rs485Config,
+    /// Hardware flow control using the RTS and CTS pins.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    flow_control: FlowControl,
+    /// IrDA SIR encoder and decoder configuration.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    irda: IrdaConfig,
 }
 
 /// UART Receive part configuration.
@@ -297,6 +399,18 @@ pub struct RxConfig {
     fifo_full_threshold: u16,
     /// Optional timeout value for RX operations.
     timeout: Option<u8>,
+    /// Number of bytes in the RX FIFO above which RTS is deasserted, if
+    /// [`FlowControl::Rts`] or [`FlowControl::RtsCts`] is used.
+    ///
+    /// RTS is asserted again once the FIFO has been drained below this
+    /// level. The async read functions are woken up at the lower of this
+    /// and [`Self::fifo_full_threshold`], so that they drain the FIFO before
+    /// the sender is stopped. Must be less than the FIFO size of 128 bytes.
+    flow_threshold: u16,
+    /// What happens to bytes that are received with a parity or framing
+    /// error.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    corrupted_bytes: CorruptedBytes,
 }
 
 impl Default for Config {
@@ -304,6 +418,9 @@ impl Default for Config {
         Config {
             rx: RxConfig::default(),
             tx: TxConfig::default(),
+            rs485: Rs485Config::default(),
+            flow_control: Default::default(),
+            irda: IrdaConfig::default(),
             baudrate: 115_200,
             data_bits: Default::default(),
             parity: Default::default(),
@@ -317,18 +434,159 @@ impl Default for Config {
 #[derive(Debug, Clone, Copy, Default, procmacros::BuilderLite)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
-pub struct TxConfig {}
+pub struct TxConfig {
+    /// Whether dropping the TX half waits until the queued data has been
+    /// sent, see [`UartTx::flush_tx_done`].
+    ///
+    /// Without this, the TX pin is released right away, and the bytes still
+    /// in the FIFO are cut off. The wait gives up after twice the time the
+    /// queued data needs, e.g. if CTS flow control holds the data back.
+    #[cfg_attr(not(feature = "unstable"), builder_lite(skip))]
+    flush_on_drop: bool,
+}
 
 impl Default for RxConfig {
     fn default() -> RxConfig {
         RxConfig {
             fifo_full_threshold: UART_FULL_THRESH_DEFAULT,
             timeout: Some(UART_TOUT_THRESH_DEFAULT),
+            flow_threshold: UART_FLOW_THRESH_DEFAULT,
+            corrupted_bytes: Default::default(),
         }
     }
 }
 
+/// Handling of bytes that are received with a parity or framing error.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[instability::unstable]
+pub enum CorruptedBytes {
+    /// Reads fail with [`Error::ParityMismatch`] or
+    /// [`Error::FrameFormatViolated`], and the data in the RX FIFO is
+    /// discarded.
+    #[default]
+    Fail,
+    /// The receiver drops corrupted bytes, and reads carry on with the bytes
+    /// that follow. [`UartRx::errors`] tells whether bytes have been dropped.
+    Discard,
+}
+
+/// Hardware flow control
+///
+/// RTS and CTS are active low: a device asserts RTS to signal that it can
+/// receive data, and the transmitter only sends data while CTS is asserted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[instability::unstable]
+pub enum FlowControl {
+    /// No flow control.
+    #[default]
+    None,
+    /// The receiver deasserts RTS while the RX FIFO holds more than
+    /// [`RxConfig::flow_threshold`] bytes.
+    Rts,
+    /// The transmitter pauses while CTS is deasserted.
+    Cts,
+    /// Both [`FlowControl::Rts`] and [`FlowControl::Cts`].
+    RtsCts,
+}
+
+impl FlowControl {
+    fn rts(self) -> bool {
+        matches!(self, FlowControl::Rts | FlowControl::RtsCts)
+    }
+
+    fn cts(self) -> bool {
+        matches!(self, FlowControl::Cts | FlowControl::RtsCts)
+    }
+}
+
+/// Polarity of the RS-485 driver-enable signal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[instability::unstable]
+pub enum DePolarity {
+    /// The transceiver drives the bus while DE is high.
+    #[default]
+    ActiveHigh,
+    /// The transceiver drives the bus while DE is low.
+    ActiveLow,
+}
+
+/// RS-485 half-duplex configuration.
+///
+/// In RS-485 mode, the RTS pin (see [`Uart::with_rts`]) is used as the
+/// driver-enable (DE) signal of the transceiver: it is asserted before a
+/// write starts, and deasserted once the last stop bit has left the
+/// transmitter. Writes therefore only return after the data has been sent
+/// completely.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, procmacros::BuilderLite)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[instability::unstable]
+#[non_exhaustive]
+pub struct Rs485Config {
+    /// Enables RS-485 mode.
+    enable: bool,
+    /// The level of the DE signal that enables the transmitter.
+    de_polarity: DePolarity,
+    /// The number of bit periods DE stays asserted after the last stop bit.
+    ///
+    /// The driver busy-waits for this time, including in the async write
+    /// functions.
+    turnaround_delay: u8,
+    /// Enables collision detection.
+    ///
+    /// The receiver listens to the bus while transmitting, and a write
+    /// returns [`Error::CollisionDetected`] if the received levels differ
+    /// from the transmitted ones. This requires a transceiver whose receiver
+    /// stays enabled while the driver is enabled. Note that the transmitted
+    /// bytes are received, too.
+    collision_detection: bool,
+}
+
+/// IrDA SIR configuration.
+///
+/// In IrDA mode, the encoder sends every 0 bit as a pulse that lasts 3/16 of
+/// a bit period, and nothing for 1 bits. The TX pin, which drives the LED of
+/// the transceiver, therefore stays low while the line is idle. The decoder
+/// turns the pulses on the RX pin back into bits. The pulse width is fixed by
+/// the hardware.
+///
+/// IrDA SIR is specified up to 115.2 kbaud, and higher baud rates are
+/// rejected with [`ConfigError::UnsupportedIrdaBaudrate`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, procmacros::BuilderLite)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[instability::unstable]
+#[non_exhaustive]
+pub struct IrdaConfig {
+    /// Enables IrDA mode.
+    enable: bool,
+    /// Inverts the transmitted pulses, for transceivers with an active-low
+    /// LED input. The TX pin then idles high.
+    tx_invert: bool,
+    /// Inverts the signal on the RX pin before it is decoded.
+    rx_invert: bool,
+    /// Feeds the encoded TX signal back into the decoder, for self-tests.
+    ///
+    /// The TX pin keeps transmitting, and the RX pin is ignored.
+    loopback: bool,
+}
+
+/// The highest baud rate IrDA SIR supports.
+const IRDA_MAX_BAUDRATE: u32 = 115_200;
+
 impl Config {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.rs485.enable && self.flow_control.rts() {
+            return Err(ConfigError::FlowControlConflict);
+        }
+        if self.irda.enable && self.baudrate > IRDA_MAX_BAUDRATE {
+            return Err(ConfigError::UnsupportedIrdaBaudrate);
+        }
+
+        Ok(())
+    }
+
     /// Calculates the total symbol length in bits based on the configured
     /// data bits, parity, and stop bits.
     fn symbol_length(&self) -> u8 {
@@ -408,6 +666,11 @@ where
         let rts_pin = PinGuard::new_unconnected(self.uart.info().rts_signal);
         let tx_pin = PinGuard::new_unconnected(self.uart.info().tx_signal);
 
+        // An earlier driver may have left its software buffers behind.
+        let state = self.uart.parts().1;
+        state.rx_buffer.detach();
+        state.tx_buffer.detach();
+
         let mut serial = Uart {
             rx: UartRx {
                 uart: unsafe { self.uart.clone_unchecked() },
@@ -415,11 +678,18 @@ where
                 guard: rx_guard,
             },
             tx: UartTx {
+                flush_on_drop: FlushOnDrop {
+                    uart: self.uart.info(),
+                    state: self.uart.state(),
+                    byte_time: None,
+                },
                 uart: self.uart,
                 phantom: PhantomData,
                 guard: tx_guard,
                 rts_pin,
                 tx_pin,
+                rs485_turnaround: None,
+                baudrate: config.baudrate,
             },
         };
         serial.init(config)?;
@@ -436,11 +706,17 @@ pub struct Uart<'d, Dm> {
 
 /// UART (Transmit)
 pub struct UartTx<'d, Dm> {
+    // Must be dropped before the pins and the peripheral are released.
+    flush_on_drop: FlushOnDrop,
     uart: PeripheralRef<'d, AnyUart>,
     phantom: PhantomData<Dm>,
     guard: PeripheralGuard,
     rts_pin: PinGuard,
     tx_pin: PinGuard,
+    // How long DE is held after a transmission in microseconds, if RS-485
+    // mode is enabled.
+    rs485_turnaround: Option<u32>,
+    baudrate: u32,
 }
 
 /// UART (Receive)
@@ -459,6 +735,11 @@ pub enum ConfigError {
     UnsupportedTimeout,
     /// The requested FIFO threshold is not supported.
     UnsupportedFifoThreshold,
+    /// RTS flow control was requested in RS-485 mode, which uses RTS as the
+    /// driver-enable signal.
+    FlowControlConflict,
+    /// IrDA mode was requested with a baud rate above 115.2 kbaud.
+    UnsupportedIrdaBaudrate,
 }
 
 impl core::error::Error for ConfigError {}
@@ -470,6 +751,102 @@ impl core::fmt::Display for ConfigError {
             ConfigError::UnsupportedFifoThreshold => {
                 write!(f, "The requested FIFO threshold is not supported")
             }
+            ConfigError::FlowControlConflict => {
+                write!(f, "RTS flow control is not supported in RS-485 mode")
+            }
+            ConfigError::UnsupportedIrdaBaudrate => {
+                write!(f, "IrDA mode only supports baud rates up to 115.2 kbaud")
+            }
+        }
+    }
+}
+
+/// An error returned by [`UartRx::wakeup_enable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+#[instability::unstable]
+#[non_exhaustive]
+pub enum WakeupError {
+    /// The threshold is below the minimum of 3 edges.
+    UnsupportedThreshold,
+    /// Only UART0 and UART1 can wake the chip up.
+    #[cfg(uart2)]
+    UnsupportedInstance,
+}
+
+#[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+impl core::error::Error for WakeupError {}
+
+#[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+impl core::fmt::Display for WakeupError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WakeupError::UnsupportedThreshold => {
+                write!(f, "The wake-up threshold must be at least 3 edges")
+            }
+            #[cfg(uart2)]
+            WakeupError::UnsupportedInstance => {
+                write!(f, "Only UART0 and UART1 can wake the chip up")
+            }
+        }
+    }
+}
+
+/// The halves passed to [`UartTx::reunite`] belong to different UARTs.
+#[instability::unstable]
+pub struct ReuniteError<'d, Dm> {
+    /// The receiver that was passed in.
+    pub rx: UartRx<'d, Dm>,
+    /// The transmitter that was passed in.
+    pub tx: UartTx<'d, Dm>,
+}
+
+impl<Dm> core::fmt::Debug for ReuniteError<'_, Dm> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ReuniteError").finish_non_exhaustive()
+    }
+}
+
+impl<Dm> core::fmt::Display for ReuniteError<'_, Dm> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "The halves belong to different UARTs")
+    }
+}
+
+impl<Dm> core::error::Error for ReuniteError<'_, Dm> {}
+
+#[cfg(feature = "defmt")]
+impl<Dm> defmt::Format for ReuniteError<'_, Dm> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "ReuniteError")
+    }
+}
+
+/// An error returned by [`Uart::detect_baud`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[instability::unstable]
+#[non_exhaustive]
+pub enum AutobaudError {
+    /// Not enough edges have been received before the timeout.
+    Timeout,
+    /// The measured baud rate is outside of the supported range.
+    ImplausibleBaudrate,
+    /// The detected baud rate couldn't be applied.
+    Config(ConfigError),
+}
+
+impl core::error::Error for AutobaudError {}
+
+impl core::fmt::Display for AutobaudError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AutobaudError::Timeout => write!(f, "Timed out waiting for the baud rate training"),
+            AutobaudError::ImplausibleBaudrate => {
+                write!(f, "The measured baud rate is out of the supported range")
+            }
+            AutobaudError::Config(e) => write!(f, "The detected baud rate can't be applied: {e}"),
         }
     }
 }
@@ -517,9 +894,32 @@ impl<'d, Dm> UartTx<'d, Dm>
 where
     Dm: DriverMode,
 {
+    /// Puts a UART back together from the halves returned by
+    /// [`Uart::split`].
+    ///
+    /// Returns both halves in a [`ReuniteError`] if they belong to different
+    /// UARTs.
+    #[instability::unstable]
+    pub fn reunite(self, rx: UartRx<'d, Dm>) -> Result<Uart<'d, Dm>, ReuniteError<'d, Dm>> {
+        if self.uart.info() != rx.uart.info() {
+            return Err(ReuniteError { rx, tx: self });
+        }
+
+        Ok(Uart { rx, tx: self })
+    }
+
     /// Configure RTS pin
+    ///
+    /// In RS-485 mode, this pin is the driver-enable signal of the
+    /// transceiver. See [`Rs485Config`].
+    ///
+    /// With RTS flow control, the pin is driven by the receiver, but it
+    /// stays connected only as long as this half of the driver exists.
+    /// Dropping the driver disconnects the pin, which then keeps RTS (or DE
+    /// in RS-485 mode) deasserted.
     pub fn with_rts(mut self, rts: impl Peripheral<P = impl PeripheralOutput> + 'd) -> Self {
         crate::into_mapped_ref!(rts);
+        rts.set_output_high(self.uart.info().is_rts_deasserted_high());
         rts.set_to_push_pull_output();
         self.rts_pin = OutputConnection::connect_with_guard(rts, self.uart.info().rts_signal);
 
@@ -534,8 +934,8 @@ where
     /// Disconnects the previous pin that was assigned with `with_tx`.
     pub fn with_tx(mut self, tx: impl Peripheral<P = impl PeripheralOutput> + 'd) -> Self {
         crate::into_mapped_ref!(tx);
-        // Make sure we don't cause an unexpected low pulse on the pin.
-        tx.set_output_high(true);
+        // Make sure we don't cause an unexpected pulse on the pin.
+        tx.set_output_high(self.uart.info().is_tx_idle_high());
         tx.set_to_push_pull_output();
         self.tx_pin = OutputConnection::connect_with_guard(tx, self.uart.info().tx_signal);
 
@@ -544,56 +944,207 @@ where
 
     /// Change the configuration.
     ///
-    /// Note that this also changes the configuration of the RX half.
+    /// Note that this also changes the configuration of the RX half. The
+    /// baud rate and the frame format are shared by both halves, and
+    /// changing them resets both FIFOs, so the RX half should be idle.
     // FIXME: when https://github.com/esp-rs/esp-hal/issues/2839 is resolved, add an appropriate `# Error` entry.
     #[instability::unstable]
-    pub fn apply_config(&mut self, _config: &Config) -> Result<(), ConfigError> {
-        // Nothing to do so far.
-        self.uart.info().txfifo_reset();
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        config.validate()?;
+
+        self.uart.info().apply_config(config)?;
+        self.apply_tx_config(config);
+        self.uart.state().set_config(config);
         Ok(())
     }
 
+    fn apply_tx_config(&mut self, config: &Config) {
+        self.uart
+            .info()
+            .set_tx_flow_control(config.flow_control.cts());
+        self.uart.info().set_rs485(&config.rs485);
+        if let Some(pin) = self.rts_pin.pin_number() {
+            // The level the pin falls back to when it's disconnected.
+            let pin = unsafe { AnyPin::steal(pin) };
+            pin.set_output_high(self.uart.info().is_rts_deasserted_high());
+        }
+        self.uart.info().set_irda(&config.irda);
+        if let Some(pin) = self.tx_pin.pin_number() {
+            // Don't leave the LED of an IrDA transceiver on.
+            let pin = unsafe { AnyPin::steal(pin) };
+            pin.set_output_high(self.uart.info().is_tx_idle_high());
+        }
+        self.rs485_turnaround = config
+            .rs485
+            .enable
+            .then(|| (config.rs485.turnaround_delay as u32 * 1_000_000).div_ceil(config.baudrate));
+        self.baudrate = config.baudrate;
+        self.flush_on_drop.byte_time = config
+            .tx
+            .flush_on_drop
+            .then(|| (config.symbol_length() as u32 * 1_000_000).div_ceil(config.baudrate));
+
+        self.uart.info().txfifo_reset();
+    }
+
     /// Writes bytes
+    ///
+    /// In RS-485 mode, this function returns once the data has been sent
+    /// and the driver-enable signal is deasserted.
     pub fn write_bytes(&mut self, data: &[u8]) -> Result<usize, Error> {
+        self.flush_tx_buffer();
         let count = data.len();
 
+        let rs485_turnaround = self.rs485_turnaround.filter(|_| count > 0);
+        if rs485_turnaround.is_some() {
+            self.begin_rs485_transmission();
+        }
+
         for &byte in data {
             self.write_byte(byte);
         }
 
+        if let Some(turnaround) = rs485_turnaround {
+            self.clear_tx_done();
+            while !self.is_tx_done() {}
+            self.end_rs485_transmission(turnaround)?;
+        }
+
         Ok(count)
     }
 
+    /// Sends a break, i.e. holds the TX line low for `bits` bit periods.
+    ///
+    /// Data written before is sent first, and the function returns once the
+    /// break is over. The hardware generates breaks of up to 255 bits; for
+    /// longer ones, the TX line is inverted for the duration instead. In
+    /// RS-485 mode, the driver-enable signal is asserted during the break.
+    #[instability::unstable]
+    pub fn send_break(&mut self, bits: u16) {
+        if bits == 0 {
+            return;
+        }
+
+        self.flush_tx_buffer();
+        while self.tx_fifo_count() > 0 || !self.is_tx_idle() {}
+
+        if self.rs485_turnaround.is_some() {
+            self.uart.info().set_rs485_de(true);
+        }
+
+        if let Ok(bits) = u8::try_from(bits) {
+            cfg_if::cfg_if! {
+                if #[cfg(any(esp32, esp32s2))] {
+                    let reg_brk = self.regs().idle_conf();
+                } else {
+                    let reg_brk = self.regs().txbrk_conf();
+                }
+            }
+            reg_brk.modify(|_, w| unsafe { w.tx_brk_num().bits(bits) });
+            self.regs()
+                .int_clr()
+                .write(|w| w.tx_brk_done().clear_bit_by_one());
+            self.regs().conf0().modify(|_, w| w.txd_brk().set_bit());
+            self.uart.info().sync_regs();
+
+            while self.regs().int_raw().read().tx_brk_done().bit_is_clear() {}
+
+            self.regs().conf0().modify(|_, w| w.txd_brk().clear_bit());
+            self.uart.info().sync_regs();
+            self.regs()
+                .int_clr()
+                .write(|w| w.tx_brk_done().clear_bit_by_one());
+        } else {
+            self.regs().conf0().modify(|_, w| w.txd_inv().set_bit());
+            self.uart.info().sync_regs();
+            crate::rom::ets_delay_us((bits as u32 * 1_000_000).div_ceil(self.baudrate));
+            self.regs().conf0().modify(|_, w| w.txd_inv().clear_bit());
+            self.uart.info().sync_regs();
+        }
+
+        if let Some(turnaround) = self.rs485_turnaround {
+            crate::rom::ets_delay_us(turnaround);
+            self.uart.info().set_rs485_de(false);
+        }
+    }
+
+    // Asserts DE and forgets about earlier collisions.
+    fn begin_rs485_transmission(&self) {
+        self.regs()
+            .int_clr()
+            .write(|w| w.rs485_clash().clear_bit_by_one());
+        self.uart.info().set_rs485_de(true);
+    }
+
+    // Deasserts DE after the turnaround delay. Must only be called once the
+    // transmission is done.
+    fn end_rs485_transmission(&self, turnaround: u32) -> Result<(), Error> {
+        crate::rom::ets_delay_us(turnaround);
+        self.uart.info().set_rs485_de(false);
+
+        if self.regs().int_raw().read().rs485_clash().bit_is_set() {
+            self.regs()
+                .int_clr()
+                .write(|w| w.rs485_clash().clear_bit_by_one());
+            return Err(Error::CollisionDetected);
+        }
+
+        Ok(())
+    }
+
+    // The FIFO may run empty between chunks, so the flag must be cleared
+    // after the last byte has been written to the FIFO.
+    fn clear_tx_done(&self) {
+        self.uart.info().clear_tx_done();
+    }
+
+    // Returns whether the transmitter finished after the last call to
+    // `clear_tx_done`, or is idle.
+    fn is_tx_done(&self) -> bool {
+        self.uart.info().is_tx_done()
+    }
+
     fn write_byte(&mut self, word: u8) {
         while self.tx_fifo_count() >= UART_FIFO_SIZE {}
-        self.regs()
-            .fifo()
-            .write(|w| unsafe { w.rxfifo_rd_byte().bits(word) });
+        self.uart.info().write_tx_fifo_byte(word);
     }
 
-    #[allow(clippy::useless_conversion)]
     /// Returns the number of bytes currently in the TX FIFO for this UART
     /// instance.
     fn tx_fifo_count(&self) -> u16 {
-        self.regs().status().read().txfifo_cnt().bits().into()
+        self.uart.info().tx_fifo_count()
     }
 
     /// Flush the transmit buffer of the UART
     pub fn flush(&mut self) {
+        self.flush_tx_buffer();
         while !self.is_tx_idle() {}
     }
 
+    /// Waits until the last byte has been shifted out, stop bits included.
+    ///
+    /// Unlike [`Self::flush`], this waits for the transmitter to report that
+    /// it's done, so the TX line is idle when this function returns. Use it
+    /// before switching an external RS-485 transceiver to receive, or before
+    /// entering light sleep.
+    #[instability::unstable]
+    pub fn flush_tx_done(&mut self) {
+        self.flush_tx_buffer();
+        self.uart.info().wait_for_tx_done();
+    }
+
+    // Waits until the interrupt handler has moved the software TX buffer, if
+    // any, into the FIFO.
+    fn flush_tx_buffer(&self) {
+        while !self.uart.state().tx_buffer.is_empty() {}
+    }
+
     /// Checks if the TX line is idle for this UART instance.
     ///
     /// Returns `true` if the transmit line is idle, meaning no data is
     /// currently being transmitted.
     fn is_tx_idle(&self) -> bool {
-        #[cfg(esp32)]
-        let status = self.regs().status();
-        #[cfg(not(esp32))]
-        let status = self.regs().fsm_status();
-
-        status.read().st_utx_out().bits() == 0x0
+        self.uart.info().is_tx_idle()
     }
 
     /// Disables all TX-related interrupts for this UART instance.
@@ -609,7 +1160,7 @@ where
             w.tx_done().clear_bit_by_one()
         });
 
-        self.regs().int_ena().write(|w| {
+        self.uart.info().modify_int_ena(|_, w| {
             w.txfifo_empty().clear_bit();
             w.tx_brk_done().clear_bit();
             w.tx_brk_idle_done().clear_bit();
@@ -643,6 +1194,20 @@ impl<'d> UartTx<'d, Blocking> {
         Ok(uart_tx)
     }
 
+    /// Reconfigures the driver to operate in [`Async`] mode, with a software
+    /// TX buffer.
+    ///
+    /// [`UartTx::write_async`] copies the data into `buffer`, and only waits
+    /// while `buffer` is full. The interrupt handler moves the data into the
+    /// TX FIFO as the FIFO empties. [`UartTx::flush_async`] waits until
+    /// everything has been sent.
+    #[instability::unstable]
+    pub fn into_async_with_buffer(self, buffer: &'static mut [u8]) -> UartTx<'d, Async> {
+        let tx = self.into_async();
+        tx.uart.state().tx_buffer.attach(buffer);
+        tx
+    }
+
     /// Reconfigures the driver to operate in [`Async`] mode.
     pub fn into_async(self) -> UartTx<'d, Async> {
         if !self.uart.state().is_rx_async.load(Ordering::Acquire) {
@@ -653,11 +1218,14 @@ impl<'d> UartTx<'d, Blocking> {
         self.uart.state().is_tx_async.store(true, Ordering::Release);
 
         UartTx {
+            flush_on_drop: self.flush_on_drop,
             uart: self.uart,
             phantom: PhantomData,
             guard: self.guard,
             rts_pin: self.rts_pin,
             tx_pin: self.tx_pin,
+            rs485_turnaround: self.rs485_turnaround,
+            baudrate: self.baudrate,
         }
     }
 }
@@ -665,6 +1233,8 @@ impl<'d> UartTx<'d, Blocking> {
 impl<'d> UartTx<'d, Async> {
     /// Reconfigures the driver to operate in [`Blocking`] mode.
     pub fn into_blocking(self) -> UartTx<'d, Blocking> {
+        self.flush_tx_buffer();
+        self.uart.state().tx_buffer.detach();
         self.uart
             .state()
             .is_tx_async
@@ -674,11 +1244,14 @@ impl<'d> UartTx<'d, Async> {
         }
 
         UartTx {
+            flush_on_drop: self.flush_on_drop,
             uart: self.uart,
             phantom: PhantomData,
             guard: self.guard,
             rts_pin: self.rts_pin,
             tx_pin: self.tx_pin,
+            rs485_turnaround: self.rs485_turnaround,
+            baudrate: self.baudrate,
         }
     }
 }
@@ -712,6 +1285,9 @@ where
     }
 
     /// Configure CTS pin
+    ///
+    /// With CTS flow control, the transmitter only sends data while this
+    /// pin is low. See [`FlowControl`].
     pub fn with_cts(self, cts: impl Peripheral<P = impl PeripheralInput> + 'd) -> Self {
         crate::into_mapped_ref!(cts);
         cts.init_input(Pull::None);
@@ -738,29 +1314,54 @@ where
 
     /// Change the configuration.
     ///
-    /// Note that this also changes the configuration of the TX half.
+    /// Note that this also changes the configuration of the TX half. The
+    /// baud rate and the frame format are shared by both halves, and
+    /// changing them resets both FIFOs, so the TX half should be idle.
     // FIXME: when https://github.com/esp-rs/esp-hal/issues/2839 is resolved, add an appropriate `# Error` entry.
     #[instability::unstable]
     pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        config.validate()?;
+
+        self.uart.info().apply_config(config)?;
+        self.apply_rx_config(config)?;
+        self.uart.state().set_config(config);
+        Ok(())
+    }
+
+    fn apply_rx_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        let flow_threshold = config
+            .flow_control
+            .rts()
+            .then_some(config.rx.flow_threshold);
+        self.uart.info().set_rx_flow_control(flow_threshold)?;
+
+        let fifo_full_threshold = match flow_threshold {
+            Some(flow_threshold) => config.rx.fifo_full_threshold.min(flow_threshold),
+            None => config.rx.fifo_full_threshold,
+        };
         self.uart
             .info()
-            .set_rx_fifo_full_threshold(config.rx.fifo_full_threshold)?;
+            .set_rx_fifo_full_threshold(fifo_full_threshold)?;
         self.uart
             .info()
             .set_rx_timeout(config.rx.timeout, config.symbol_length())?;
+        self.uart
+            .info()
+            .set_discard_corrupted_bytes(config.rx.corrupted_bytes == CorruptedBytes::Discard);
 
         self.uart.info().rxfifo_reset();
+        // Errors flagged under the old configuration don't apply anymore.
+        self.uart.info().clear_rx_events(self.error_events());
         Ok(())
     }
 
     /// Reads and clears errors.
+    ///
+    /// With [`CorruptedBytes::Discard`], parity and framing errors are not
+    /// reported here, but by [`Self::errors`].
     #[instability::unstable]
     pub fn check_for_errors(&mut self) -> Result<(), Error> {
-        let errors = RxEvent::FifoOvf
-            | RxEvent::FifoTout
-            | RxEvent::GlitchDetected
-            | RxEvent::FrameError
-            | RxEvent::ParityError;
+        let errors = self.error_events();
         let events = self.uart.info().rx_events(errors);
         let result = rx_event_check_for_error(events);
         if result.is_err() {
@@ -769,40 +1370,64 @@ where
         result
     }
 
-    // Read a byte from the UART
-    fn read_byte(&mut self) -> Option<u8> {
-        cfg_if::cfg_if! {
-            if #[cfg(esp32s2)] {
-                // On the ESP32-S2 we need to use PeriBus2 to read the FIFO:
-                let fifo = unsafe {
-                    &*((self.regs().fifo().as_ptr() as *mut u8).add(0x20C00000)
-                        as *mut crate::pac::uart0::FIFO)
-                };
-            } else {
-                let fifo = self.regs().fifo();
-            }
+    /// Returns the errors the receiver has flagged since they were last
+    /// cleared, and clears them.
+    ///
+    /// Reads that fail clear the errors they report, so this returns the
+    /// errors that have not been reported by a read. With
+    /// [`CorruptedBytes::Discard`], this is how to tell that corrupted bytes
+    /// have been dropped.
+    #[instability::unstable]
+    pub fn errors(&mut self) -> EnumSet<RxError> {
+        let events = self.uart.info().rx_events(
+            RxEvent::FifoOvf
+                | RxEvent::GlitchDetected
+                | RxEvent::BreakDetected
+                | RxEvent::FrameError
+                | RxEvent::ParityError,
+        );
+        self.uart.info().clear_rx_events(events);
+
+        events
+            .iter()
+            .filter_map(|event| match event {
+                RxEvent::FifoOvf => Some(RxError::FifoOverflowed),
+                RxEvent::GlitchDetected => Some(RxError::GlitchOccurred),
+                RxEvent::BreakDetected => Some(RxError::BreakDetected),
+                RxEvent::FrameError => Some(RxError::FrameFormatViolated),
+                RxEvent::ParityError => Some(RxError::ParityMismatch),
+                RxEvent::FifoFull | RxEvent::CmdCharDetected | RxEvent::FifoTout => None,
+            })
+            .collect()
+    }
+
+    // The events that make reads fail.
+    fn error_events(&self) -> EnumSet<RxEvent> {
+        let mut events =
+            RxEvent::FifoOvf | RxEvent::FifoTout | RxEvent::GlitchDetected | RxEvent::BreakDetected;
+        if !self.uart.info().discards_corrupted_bytes() {
+            events |= RxEvent::FrameError | RxEvent::ParityError;
         }
+        events
+    }
 
-        if self.rx_fifo_count() > 0 {
-            // https://docs.espressif.com/projects/esp-chip-errata/en/latest/esp32/03-errata-description/esp32/cpu-subsequent-access-halted-when-get-interrupted.html
-            cfg_if::cfg_if! {
-                if #[cfg(esp32)] {
-                    let byte = crate::interrupt::free(|| fifo.read().rxfifo_rd_byte().bits());
-                } else {
-                    let byte = fifo.read().rxfifo_rd_byte().bits();
-                }
-            }
-
-            Some(byte)
-        } else {
-            None
-        }
+    // Read a byte from the UART
+    fn read_byte(&mut self) -> Option<u8> {
+        self.uart.info().read_rx_fifo_byte()
     }
 
     /// Reads bytes from the UART
     pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), Error> {
-        let buffered = self.read_buffered_bytes(buf)?;
-        let buf = &mut buf[buffered..];
+        if self.uart.state().rx_buffer.is_attached() {
+            let mut count = 0;
+            while count < buf.len() {
+                count += self.read_from_rx_buffer(&mut buf[count..])?;
+            }
+            return Ok(());
+        }
+
+        let buffered = self.read_buffered_bytes(buf)?;
+        let buf = &mut buf[buffered..];
 
         for byte in buf.iter_mut() {
             loop {
@@ -818,7 +1443,23 @@ where
 
     /// Read all available bytes from the RX FIFO into the provided buffer and
     /// returns the number of read bytes without blocking.
+    ///
+    /// With a software RX buffer, this reads from that buffer instead, and
+    /// can return more bytes than the FIFO holds.
+    ///
+    /// If a break has been received, this returns
+    /// [`Error::BreakDetected`] and discards the bytes received up to and
+    /// including the break. The bytes that follow the break are kept.
     pub fn read_buffered_bytes(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.uart.state().rx_buffer.is_attached() {
+            return self.read_from_rx_buffer(buf);
+        }
+
+        if self.regs().int_raw().read().brk_det().bit_is_set() {
+            self.discard_break(&[]);
+            return Err(Error::BreakDetected);
+        }
+
         let mut count = 0;
         while count < buf.len() {
             if let Some(byte) = self.read_byte() {
@@ -829,17 +1470,121 @@ where
             }
         }
         if let Err(err) = self.check_for_errors() {
+            if err == Error::BreakDetected {
+                self.discard_break(&buf[..count]);
+                return Err(err);
+            }
             // Drain the buffer. We don't know where the error occurred, so returning
             // these bytes would be incorrect. We also don't know if the number of buffered
             // bytes fit into the buffer.
-            // TODO: make this behaviour configurable using UART_ERR_WR_MASK. If the user
-            // wants to keep the bytes regardless of errors, they should be able to do so.
+            // With `CorruptedBytes::Discard`, the hardware drops the corrupted bytes
+            // instead, and parity and framing errors don't end up here.
             while self.read_byte().is_some() {}
             return Err(err);
         }
         Ok(count)
     }
 
+    // Reads from the software RX buffer, after topping it up from the FIFO.
+    fn read_from_rx_buffer(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let buffer = &self.uart.state().rx_buffer;
+        self.uart.info().fill_rx_buffer(buffer);
+        if buffer.take_overflow() {
+            return Err(Error::BufferOverflowed);
+        }
+        Ok(buffer.pop_into(buf))
+    }
+
+    /// Returns how many received bytes have been dropped because the
+    /// software RX buffer was full.
+    ///
+    /// See [`UartRx::into_async_with_buffer`].
+    #[instability::unstable]
+    pub fn dropped_bytes(&self) -> usize {
+        self.uart.state().rx_buffer.dropped()
+    }
+
+    /// Enables the UART as a wake-up source.
+    ///
+    /// During light sleep, the UART counts the rising edges on the RX line,
+    /// and wakes the chip up once it has seen `threshold_edges` of them. Pass
+    /// a [`UartWakeupSource`][crate::rtc_cntl::sleep::UartWakeupSource] to
+    /// `Rtc::sleep_light` to let the UART end the sleep. Once woken up,
+    /// [`wakeup_cause`][crate::rtc_cntl::wakeup_cause] returns
+    /// [`SleepSource::Uart`][crate::reset::SleepSource::Uart].
+    ///
+    /// The UART can't receive until the chip's clocks are running again, so
+    /// the bytes that wake the chip up are lost, and so are the bytes sent
+    /// right after them. The sender should start with a wake-up pattern,
+    /// and wait a few milliseconds before sending the actual data. Each
+    /// `0x55` byte has 5 rising edges. Prefixing the data with an AT-style
+    /// command sequence, which the receiver detects with [`AtCmdConfig`],
+    /// lets the receiver tell the data apart from what's left of the
+    /// pattern.
+    ///
+    /// Returns [`WakeupError::UnsupportedThreshold`] if `threshold_edges` is
+    /// less than 3.
+    #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+    #[instability::unstable]
+    pub fn wakeup_enable(&mut self, threshold_edges: u8) -> Result<(), WakeupError> {
+        #[cfg(uart2)]
+        if self.uart.info().peripheral == crate::system::Peripheral::Uart2 {
+            return Err(WakeupError::UnsupportedInstance);
+        }
+        if threshold_edges < UART_MIN_WAKEUP_EDGES {
+            return Err(WakeupError::UnsupportedThreshold);
+        }
+
+        // The hardware wakes up two edges later than its threshold.
+        let threshold = (threshold_edges - 2) as u16;
+        cfg_if::cfg_if! {
+            if #[cfg(esp32c6)] {
+                self.regs().sleep_conf2().modify(|_, w| unsafe {
+                    w.wk_mode_sel().bits(0);
+                    w.active_threshold().bits(threshold)
+                });
+            } else {
+                self.regs()
+                    .sleep_conf()
+                    .modify(|_, w| unsafe { w.active_threshold().bits(threshold) });
+            }
+        }
+        self.uart.info().sync_regs();
+        self.uart
+            .state()
+            .wakeup_enabled
+            .store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Stops the UART from waking the chip up.
+    #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+    #[instability::unstable]
+    pub fn wakeup_disable(&mut self) {
+        self.uart
+            .state()
+            .wakeup_enabled
+            .store(false, Ordering::Relaxed);
+    }
+
+    // A break leaves a NUL in the RX FIFO. Unless that has been dropped or read
+    // into `read` already, this discards the FIFO up to and including the NUL,
+    // so that the bytes after the break can still be read.
+    fn discard_break(&mut self, read: &[u8]) {
+        // The NUL is a corrupted byte, too.
+        if !read.contains(&0) && !self.uart.info().discards_corrupted_bytes() {
+            while let Some(byte) = self.read_byte() {
+                if byte == 0 {
+                    break;
+                }
+            }
+        }
+        self.uart
+            .info()
+            .clear_rx_events(RxEvent::BreakDetected | RxEvent::FrameError | RxEvent::FifoTout);
+    }
+
     /// Read bytes from the RX FIFO without checking for errors.
     fn flush_buffer(&mut self, buf: &mut [u8]) -> usize {
         let mut count = 0;
@@ -854,32 +1599,8 @@ where
         count
     }
 
-    #[allow(clippy::useless_conversion)]
     fn rx_fifo_count(&self) -> u16 {
-        let fifo_cnt: u16 = self.regs().status().read().rxfifo_cnt().bits().into();
-
-        // Calculate the real count based on the FIFO read and write offset address:
-        // https://www.espressif.com/sites/default/files/documentation/esp32_errata_en.pdf
-        // section 3.17
-        #[cfg(esp32)]
-        {
-            let status = self.regs().mem_rx_status().read();
-            let rd_addr = status.mem_rx_rd_addr().bits();
-            let wr_addr = status.mem_rx_wr_addr().bits();
-
-            if wr_addr > rd_addr {
-                wr_addr - rd_addr
-            } else if wr_addr < rd_addr {
-                (wr_addr + UART_FIFO_SIZE) - rd_addr
-            } else if fifo_cnt > 0 {
-                UART_FIFO_SIZE
-            } else {
-                0
-            }
-        }
-
-        #[cfg(not(esp32))]
-        fifo_cnt
+        self.uart.info().rx_fifo_count()
     }
 
     /// Disables all RX-related interrupts for this UART instance.
@@ -895,7 +1616,7 @@ where
             w.at_cmd_char_det().clear_bit_by_one()
         });
 
-        self.regs().int_ena().write(|w| {
+        self.uart.info().modify_int_ena(|_, w| {
             w.rxfifo_full().clear_bit();
             w.rxfifo_ovf().clear_bit();
             w.rxfifo_tout().clear_bit();
@@ -925,6 +1646,32 @@ impl<'d> UartRx<'d, Blocking> {
         Ok(uart_rx)
     }
 
+    /// Reconfigures the driver to operate in [`Async`] mode, with a software
+    /// RX buffer.
+    ///
+    /// The interrupt handler moves received data from the RX FIFO into
+    /// `buffer`, whenever the FIFO holds more than
+    /// [`RxConfig::fifo_full_threshold`] bytes, or the RX timeout expires.
+    /// The read functions then take the data from `buffer`, so that a single
+    /// read can return more than the FIFO holds. A lower FIFO threshold
+    /// leaves more time to react at high baud rates.
+    ///
+    /// If `buffer` is full, received bytes are dropped. The next read then
+    /// fails with [`Error::BufferOverflowed`], and
+    /// [`UartRx::dropped_bytes`] counts the bytes. RX errors flagged by the
+    /// hardware are only reported by [`UartRx::errors`], and breaks are not
+    /// treated specially.
+    #[instability::unstable]
+    pub fn into_async_with_buffer(self, buffer: &'static mut [u8]) -> UartRx<'d, Async> {
+        let rx = self.into_async();
+        rx.uart.state().rx_buffer.attach(buffer);
+        if rx.uart.state().rx_buffer.is_attached() {
+            rx.uart.state().rx_idle.store(false, Ordering::Relaxed);
+            rx.uart.info().enable_listen_rx(buffered_rx_events(), true);
+        }
+        rx
+    }
+
     /// Reconfigures the driver to operate in [`Async`] mode.
     pub fn into_async(self) -> UartRx<'d, Async> {
         if !self.uart.state().is_tx_async.load(Ordering::Acquire) {
@@ -945,6 +1692,10 @@ impl<'d> UartRx<'d, Blocking> {
 impl<'d> UartRx<'d, Async> {
     /// Reconfigures the driver to operate in [`Blocking`] mode.
     pub fn into_blocking(self) -> UartRx<'d, Blocking> {
+        self.uart
+            .info()
+            .enable_listen_rx(buffered_rx_events(), false);
+        self.uart.state().rx_buffer.detach();
         self.uart
             .state()
             .is_rx_async
@@ -982,6 +1733,24 @@ impl<'d> Uart<'d, Blocking> {
         UartBuilder::new(uart).init(config)
     }
 
+    /// Reconfigures the driver to operate in [`Async`] mode, with software RX
+    /// and TX buffers.
+    ///
+    /// Either buffer can be empty, to leave that direction unbuffered. See
+    /// [`UartRx::into_async_with_buffer`] and
+    /// [`UartTx::into_async_with_buffer`].
+    #[instability::unstable]
+    pub fn into_async_with_buffers(
+        self,
+        rx_buffer: &'static mut [u8],
+        tx_buffer: &'static mut [u8],
+    ) -> Uart<'d, Async> {
+        Uart {
+            rx: self.rx.into_async_with_buffer(rx_buffer),
+            tx: self.tx.into_async_with_buffer(tx_buffer),
+        }
+    }
+
     /// Reconfigures the driver to operate in [`Async`] mode.
     pub fn into_async(self) -> Uart<'d, Async> {
         Uart {
@@ -1039,6 +1808,31 @@ pub enum UartInterrupt {
     /// The receiver has received more data than what
     /// [`RxConfig::fifo_full_threshold`] specifies.
     RxFifoFull,
+
+    /// The receiver has detected a break.
+    RxBreakDetected,
+}
+
+/// Errors flagged by the receiver, see [`UartRx::errors`].
+#[derive(Debug, EnumSetType)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+#[instability::unstable]
+pub enum RxError {
+    /// The RX FIFO overflowed, and bytes were lost.
+    FifoOverflowed,
+
+    /// A glitch was detected on the RX line.
+    GlitchOccurred,
+
+    /// A break was detected on the RX line.
+    BreakDetected,
+
+    /// A byte was received without a valid stop bit.
+    FrameFormatViolated,
+
+    /// A byte was received with the wrong parity.
+    ParityMismatch,
 }
 
 impl<'d, Dm> Uart<'d, Dm>
@@ -1052,6 +1846,11 @@ where
     }
 
     /// Configure RTS pin
+    ///
+    /// In RS-485 mode, this pin is the driver-enable signal of the
+    /// transceiver. See [`Rs485Config`]. With RTS flow control, the
+    /// receiver deasserts RTS while its FIFO is filled above
+    /// [`RxConfig::flow_threshold`]. See [`FlowControl`].
     pub fn with_rts(mut self, rts: impl Peripheral<P = impl PeripheralOutput> + 'd) -> Self {
         self.tx = self.tx.with_rts(rts);
         self
@@ -1066,6 +1865,13 @@ where
     ///
     /// This is particularly useful when having two tasks correlating to
     /// transmitting and receiving.
+    ///
+    /// Each half only enables and waits for its own interrupts, so the
+    /// halves can be used from separate tasks without any locking.
+    /// Either half can change the configuration shared by both of them, see
+    /// [`UartRx::apply_config`] and [`UartTx::apply_config`].
+    ///
+    /// The halves can be put back together with [`UartTx::reunite`].
     /// ## Example
     /// ```rust, no_run
     #[doc = crate::before_snippet!()]
@@ -1111,6 +1917,100 @@ where
         self.rx.check_for_errors()
     }
 
+    /// Returns and clears the errors flagged by the receiver.
+    ///
+    /// See [`UartRx::errors`].
+    #[instability::unstable]
+    pub fn rx_errors(&mut self) -> EnumSet<RxError> {
+        self.rx.errors()
+    }
+
+    /// Returns how many received bytes have been dropped because the
+    /// software RX buffer was full.
+    ///
+    /// See [`UartRx::dropped_bytes`].
+    #[instability::unstable]
+    pub fn dropped_rx_bytes(&self) -> usize {
+        self.rx.dropped_bytes()
+    }
+
+    /// Enables the UART as a wake-up source.
+    ///
+    /// See [`UartRx::wakeup_enable`].
+    #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+    #[instability::unstable]
+    pub fn wakeup_enable(&mut self, threshold_edges: u8) -> Result<(), WakeupError> {
+        self.rx.wakeup_enable(threshold_edges)
+    }
+
+    /// Stops the UART from waking the chip up.
+    ///
+    /// See [`UartRx::wakeup_disable`].
+    #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+    #[instability::unstable]
+    pub fn wakeup_disable(&mut self) {
+        self.rx.wakeup_disable()
+    }
+
+    /// Detects the baud rate of the peer, and switches to it.
+    ///
+    /// The hardware measures the shortest low and high pulses on the RX
+    /// line. The peer should send a few `U` (`0x55`) characters, whose bits
+    /// alternate between low and high, so that the shortest pulses are one
+    /// bit long. Glitches shorter than the RX filter's threshold are
+    /// ignored.
+    ///
+    /// Once the measurement is done, the detected baud rate is applied with
+    /// [`Self::apply_config`], keeping the rest of the current
+    /// configuration, and returned. The characters received during the
+    /// detection are discarded, the UART can be used right away.
+    ///
+    /// Some chips can only measure a part of the 300 to 5 000 000 baud range
+    /// at a time. If the signal is too slow for the first measurement, the
+    /// detection is repeated with a slower clock, so the peer should
+    /// keep sending until this function returns.
+    ///
+    /// Nothing should be transmitted during the detection.
+    ///
+    /// ## Errors
+    ///
+    /// - [`AutobaudError::Timeout`] if not enough edges have been received
+    ///   within `timeout`,
+    /// - [`AutobaudError::ImplausibleBaudrate`] if the measured baud rate is
+    ///   outside of 300 to 5 000 000 baud,
+    /// - [`AutobaudError::Config`] if the detected baud rate can't be applied
+    ///   to the current configuration.
+    ///
+    /// In all of these cases, the previous configuration stays in effect.
+    ///
+    /// ## Example
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::uart::{Config, Uart};
+    /// use esp_hal::time::Duration;
+    ///
+    /// let mut uart1 = Uart::new(peripherals.UART1, Config::default())?
+    ///     .with_rx(peripherals.GPIO1)
+    ///     .with_tx(peripherals.GPIO2);
+    ///
+    /// let baudrate = uart1.detect_baud(Duration::secs(5))?;
+    /// uart1.write_bytes(b"OK\r\n")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instability::unstable]
+    pub fn detect_baud(&mut self, timeout: Duration) -> Result<u32, AutobaudError> {
+        let deadline = crate::time::now() + timeout;
+        let config = self.rx.uart.state().config();
+        let baudrate = self.rx.uart.info().detect_baudrate(&config, deadline)?;
+
+        self.apply_config(&config.with_baudrate(baudrate))
+            .map_err(AutobaudError::Config)?;
+
+        Ok(baudrate)
+    }
+
     /// Reads bytes from the UART
     pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), Error> {
         self.rx.read_bytes(buf)
@@ -1164,12 +2064,31 @@ where
         self.tx.flush()
     }
 
+    /// Waits until the last byte has been shifted out, stop bits included.
+    ///
+    /// See [`UartTx::flush_tx_done`].
+    #[instability::unstable]
+    pub fn flush_tx_done(&mut self) {
+        self.tx.flush_tx_done()
+    }
+
+    /// Sends a break, i.e. holds the TX line low for `bits` bit periods.
+    ///
+    /// See [`UartTx::send_break`].
+    #[instability::unstable]
+    pub fn send_break(&mut self, bits: u16) {
+        self.tx.send_break(bits)
+    }
+
     /// Change the configuration.
     // FIXME: when https://github.com/esp-rs/esp-hal/issues/2839 is resolved, add an appropriate `# Error` entry.
     pub fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
-        self.rx.apply_config(config)?;
-        self.tx.apply_config(config)?;
+        config.validate()?;
+
         self.rx.uart.info().apply_config(config)?;
+        self.rx.apply_rx_config(config)?;
+        self.tx.apply_tx_config(config);
+        self.rx.uart.state().set_config(config);
         Ok(())
     }
 
@@ -1463,6 +2382,8 @@ pub(crate) enum RxEvent {
     FifoOvf,
     FifoTout,
     GlitchDetected,
+    // Comes before `FrameError`, which a break causes, too.
+    BreakDetected,
     FrameError,
     ParityError,
 }
@@ -1472,6 +2393,7 @@ fn rx_event_check_for_error(events: EnumSet<RxEvent>) -> Result<(), Error> {
         match event {
             RxEvent::FifoOvf => return Err(Error::FifoOverflowed),
             RxEvent::GlitchDetected => return Err(Error::GlitchOccurred),
+            RxEvent::BreakDetected => return Err(Error::BreakDetected),
             RxEvent::FrameError => return Err(Error::FrameFormatViolated),
             RxEvent::ParityError => return Err(Error::ParityMismatch),
             RxEvent::FifoFull | RxEvent::CmdCharDetected | RxEvent::FifoTout => continue,
@@ -1536,6 +2458,44 @@ impl Drop for UartRxFuture {
     }
 }
 
+// Deasserts DE when an RS-485 transmission is abandoned.
+struct DeGuard(&'static Info);
+
+impl Drop for DeGuard {
+    fn drop(&mut self) {
+        self.0.set_rs485_de(false);
+    }
+}
+
+// Waits for the queued data to be sent when the TX half is dropped, see
+// `TxConfig::flush_on_drop`.
+struct FlushOnDrop {
+    uart: &'static Info,
+    state: &'static State,
+    // How long it takes to send a byte in microseconds, if enabled.
+    byte_time: Option<u32>,
+}
+
+impl Drop for FlushOnDrop {
+    fn drop(&mut self) {
+        let Some(byte_time) = self.byte_time else {
+            return;
+        };
+
+        let pending = self.state.tx_buffer.len() + self.uart.tx_fifo_count() as usize + 1;
+        let deadline = crate::time::now() + Duration::micros(2 * pending as u64 * byte_time as u64);
+        let timed_out = || crate::time::now() > deadline;
+
+        while !self.state.tx_buffer.is_empty() {
+            if timed_out() {
+                return;
+            }
+        }
+        self.uart.clear_tx_done();
+        while !self.uart.is_tx_done() && !timed_out() {}
+    }
+}
+
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 struct UartTxFuture {
     events: EnumSet<TxEvent>,
@@ -1568,7 +2528,7 @@ impl UartTxFuture {
     }
 
     fn enable_listen(&self, enable: bool) {
-        self.uart.regs().int_ena().modify(|_, w| {
+        self.uart.modify_int_ena(|_, w| {
             for event in self.events {
                 match event {
                     TxEvent::Done => w.tx_done().bit(enable),
@@ -1617,6 +2577,14 @@ impl Uart<'_, Async> {
         self.rx.read_async(buf).await
     }
 
+    /// Asynchronously reads data until the RX line goes idle or the provided
+    /// buffer is full.
+    ///
+    /// See [`UartRx::read_until_idle_async`].
+    pub async fn read_until_idle_async(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.rx.read_until_idle_async(buf).await
+    }
+
     /// Asynchronously writes data to the UART transmit buffer.
     pub async fn write_async(&mut self, words: &[u8]) -> Result<usize, Error> {
         self.tx.write_async(words).await
@@ -1626,6 +2594,22 @@ impl Uart<'_, Async> {
     pub async fn flush_async(&mut self) -> Result<(), Error> {
         self.tx.flush_async().await
     }
+
+    /// Waits until the last byte has been shifted out, stop bits included.
+    ///
+    /// See [`UartTx::flush_tx_done_async`].
+    #[instability::unstable]
+    pub async fn flush_tx_done_async(&mut self) {
+        self.tx.flush_tx_done_async().await
+    }
+
+    /// Waits for the transmitter to run out of data.
+    ///
+    /// See [`UartTx::wait_tx_done`].
+    #[instability::unstable]
+    pub async fn wait_tx_done(&mut self) {
+        self.tx.wait_tx_done().await
+    }
 }
 
 impl UartTx<'_, Async> {
@@ -1635,7 +2619,81 @@ impl UartTx<'_, Async> {
     /// the UART. Data is written in chunks to avoid overflowing the
     /// transmit FIFO, and the function waits asynchronously when
     /// necessary for space in the buffer to become available.
+    ///
+    /// With a software TX buffer, the data is copied into the buffer
+    /// instead, see [`UartTx::into_async_with_buffer`].
+    ///
+    /// In RS-485 mode, this function returns once the data has been sent
+    /// and the driver-enable signal is deasserted. If the future is dropped
+    /// before that, DE is deasserted immediately.
     pub async fn write_async(&mut self, words: &[u8]) -> Result<usize, Error> {
+        let rs485_turnaround = self.rs485_turnaround.filter(|_| !words.is_empty());
+        let _de_guard = rs485_turnaround.map(|_| {
+            self.begin_rs485_transmission();
+            DeGuard(self.uart.info())
+        });
+
+        let count = if self.uart.state().tx_buffer.is_attached() {
+            self.write_to_tx_buffer(words).await
+        } else {
+            self.write_to_fifo(words).await
+        };
+
+        if let Some(turnaround) = rs485_turnaround {
+            self.wait_for_tx_buffer().await;
+            self.clear_tx_done();
+            if !self.is_tx_done() {
+                UartTxFuture::new(self.uart.reborrow(), TxEvent::Done).await;
+            }
+            self.end_rs485_transmission(turnaround)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Asynchronously flushes the UART transmit buffer.
+    ///
+    /// This function ensures that all pending data in the transmit FIFO has
+    /// been sent over the UART. If the FIFO contains data, it waits
+    /// for the transmission to complete before returning.
+    pub async fn flush_async(&mut self) -> Result<(), Error> {
+        self.wait_for_tx_buffer().await;
+        let count = self.tx_fifo_count();
+        if count > 0 {
+            UartTxFuture::new(self.uart.reborrow(), TxEvent::Done).await;
+        }
+
+        Ok(())
+    }
+
+    /// Waits until the last byte has been shifted out, stop bits included.
+    ///
+    /// This is the async version of [`UartTx::flush_tx_done`]. The software
+    /// TX buffer, if any, is sent first.
+    #[instability::unstable]
+    pub async fn flush_tx_done_async(&mut self) {
+        self.wait_for_tx_buffer().await;
+        self.wait_tx_done().await;
+    }
+
+    /// Waits for the transmitter to run out of data.
+    ///
+    /// Returns once the TX FIFO is empty and its last byte has been shifted
+    /// out, or right away if the transmitter is idle. This doesn't wait for
+    /// the software TX buffer: the FIFO may run empty before the interrupt
+    /// handler refills it, so use [`Self::flush_tx_done_async`] to wait for
+    /// everything that has been written.
+    #[instability::unstable]
+    pub async fn wait_tx_done(&mut self) {
+        // A flag left over from an earlier transmission would end the wait
+        // right away.
+        self.clear_tx_done();
+        if !self.is_tx_done() {
+            UartTxFuture::new(self.uart.reborrow(), TxEvent::Done).await;
+        }
+    }
+
+    async fn write_to_fifo(&mut self, words: &[u8]) -> usize {
         let mut count = 0;
         let mut offset: usize = 0;
         loop {
@@ -1656,22 +2714,40 @@ impl UartTx<'_, Async> {
             offset = next_offset;
             UartTxFuture::new(self.uart.reborrow(), TxEvent::FiFoEmpty).await;
         }
-
-        Ok(count)
+        count
     }
 
-    /// Asynchronously flushes the UART transmit buffer.
-    ///
-    /// This function ensures that all pending data in the transmit FIFO has
-    /// been sent over the UART. If the FIFO contains data, it waits
-    /// for the transmission to complete before returning.
-    pub async fn flush_async(&mut self) -> Result<(), Error> {
-        let count = self.tx_fifo_count();
-        if count > 0 {
-            UartTxFuture::new(self.uart.reborrow(), TxEvent::Done).await;
-        }
+    async fn write_to_tx_buffer(&mut self, words: &[u8]) -> usize {
+        let (info, state) = (self.uart.info(), self.uart.state());
+        let mut count = 0;
+        poll_fn(|cx| {
+            state.tx_waker.register(cx.waker());
+            count += state.tx_buffer.push_slice(&words[count..]);
+            info.drain_tx_buffer(&state.tx_buffer);
 
-        Ok(())
+            if count == words.len() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        count
+    }
+
+    // Waits until the interrupt handler has moved the software TX buffer, if
+    // any, into the FIFO.
+    async fn wait_for_tx_buffer(&mut self) {
+        let state = self.uart.state();
+        poll_fn(|cx| {
+            state.tx_waker.register(cx.waker());
+            if state.tx_buffer.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
     }
 }
 
@@ -1689,6 +2765,9 @@ impl UartRx<'_, Async> {
     /// has already occurred before calling this method (e.g. status
     /// bit set, but interrupt not enabled)
     ///
+    /// With a software RX buffer, this waits until the buffer holds data
+    /// instead, see [`UartRx::into_async_with_buffer`].
+    ///
     /// # Params
     /// - `buf` buffer slice to write the bytes into
     ///
@@ -1696,34 +2775,34 @@ impl UartRx<'_, Async> {
     /// # Ok
     /// When successful, returns the number of bytes written to buf.
     /// If the passed in buffer is of length 0, Ok(0) is returned.
+    ///
+    /// # Errors
+    /// If a break is received, [`Error::BreakDetected`] is returned and the
+    /// bytes up to and including the break are discarded.
     pub async fn read_async(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
         if buf.is_empty() {
             return Ok(0);
         }
 
-        loop {
-            let mut events = RxEvent::FifoFull
-                | RxEvent::FifoOvf
-                | RxEvent::FrameError
-                | RxEvent::GlitchDetected
-                | RxEvent::ParityError;
+        if self.uart.state().rx_buffer.is_attached() {
+            let state = self.uart.state();
+            return poll_fn(|cx| {
+                state.rx_waker.register(cx.waker());
+                match self.read_from_rx_buffer(buf) {
+                    Ok(0) => Poll::Pending,
+                    result => Poll::Ready(result),
+                }
+            })
+            .await;
+        }
 
-            if self.regs().at_cmd_char().read().char_num().bits() > 0 {
-                events |= RxEvent::CmdCharDetected;
+        loop {
+            let events = self.read_events();
+            let events_happened = UartRxFuture::new(self.uart.reborrow(), events).await;
+            if events_happened.contains(RxEvent::BreakDetected) {
+                self.discard_break(&[]);
+                return Err(Error::BreakDetected);
             }
-
-            cfg_if::cfg_if! {
-                if #[cfg(any(esp32c6, esp32h2))] {
-                    let reg_en = self.regs().tout_conf();
-                } else {
-                    let reg_en = self.regs().conf1();
-                }
-            };
-            if reg_en.read().rx_tout_en().bit_is_set() {
-                events |= RxEvent::FifoTout;
-            }
-
-            let events_happened = UartRxFuture::new(self.uart.reborrow(), events).await;
             // always drain the fifo, if an error has occurred the data is lost
             let read_bytes = self.flush_buffer(buf);
             // check error events
@@ -1741,6 +2820,118 @@ impl UartRx<'_, Async> {
             }
         }
     }
+
+    /// Reads into `buf` until the RX line has been idle for
+    /// [`RxConfig::timeout`], or `buf` is full.
+    ///
+    /// This is meant for protocols that separate packets by pauses, like
+    /// Modbus RTU. Bytes that don't fit into `buf` stay in the RX FIFO for
+    /// the next read. If AT-CMD detection is set up, detecting the command
+    /// character also ends the read. Without an RX timeout, the read only
+    /// ends when `buf` is full.
+    ///
+    /// # Ok
+    /// When successful, returns the number of bytes written to buf.
+    /// If the passed in buffer is of length 0, Ok(0) is returned.
+    ///
+    /// # Errors
+    /// If an RX error occurs, the bytes read so far are lost. After a break,
+    /// the bytes that follow it remain in the RX FIFO.
+    pub async fn read_until_idle_async(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let state = self.uart.state();
+        if state.rx_buffer.is_attached() {
+            if self.rx_fifo_count() == 0 && state.rx_buffer.is_empty() {
+                state.rx_idle.store(false, Ordering::Relaxed);
+            }
+
+            let mut count = 0;
+            return poll_fn(|cx| {
+                state.rx_waker.register(cx.waker());
+                count += match self.read_from_rx_buffer(&mut buf[count..]) {
+                    Ok(read) => read,
+                    Err(error) => return Poll::Ready(Err(error)),
+                };
+
+                let idle = count > 0
+                    && state.rx_buffer.is_empty()
+                    && state.rx_idle.swap(false, Ordering::Acquire);
+                if count == buf.len() || idle {
+                    Poll::Ready(Ok(count))
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+        }
+
+        // Flags raised by earlier traffic are stale unless its data is still
+        // waiting in the FIFO.
+        if self.rx_fifo_count() == 0 {
+            self.uart
+                .info()
+                .clear_rx_events(RxEvent::FifoTout | RxEvent::CmdCharDetected);
+        }
+
+        let mut count = 0;
+        loop {
+            let events = self.read_events();
+            let events_happened = UartRxFuture::new(self.uart.reborrow(), events).await;
+            if events_happened.contains(RxEvent::BreakDetected) {
+                self.discard_break(&[]);
+                return Err(Error::BreakDetected);
+            }
+            let idle = !events_happened.is_disjoint(RxEvent::FifoTout | RxEvent::CmdCharDetected);
+
+            // The RX timeout only counts while the FIFO holds data, so one
+            // byte is left behind until the line is idle.
+            let available = self.rx_fifo_count() as usize;
+            let take = if idle {
+                available
+            } else {
+                available.saturating_sub(1)
+            };
+            let end = buf.len().min(count + take);
+            count += self.flush_buffer(&mut buf[count..end]);
+
+            rx_event_check_for_error(events_happened)?;
+            // Restart the timeout for the bytes still in the FIFO, see
+            // `read_async`.
+            self.regs()
+                .int_clr()
+                .write(|w| w.rxfifo_tout().clear_bit_by_one());
+
+            if count == buf.len() || (idle && count > 0) {
+                return Ok(count);
+            }
+        }
+    }
+
+    // The events that end a read: the FIFO filling up, RX errors, and the
+    // RX timeout and AT-CMD detection if they're enabled.
+    fn read_events(&self) -> EnumSet<RxEvent> {
+        let mut events = RxEvent::FifoFull | (self.error_events() - RxEvent::FifoTout);
+
+        if self.regs().at_cmd_char().read().char_num().bits() > 0 {
+            events |= RxEvent::CmdCharDetected;
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32c6, esp32h2))] {
+                let reg_en = self.regs().tout_conf();
+            } else {
+                let reg_en = self.regs().conf1();
+            }
+        };
+        if reg_en.read().rx_tout_en().bit_is_set() {
+            events |= RxEvent::FifoTout;
+        }
+
+        events
+    }
 }
 
 #[instability::unstable]
@@ -1798,14 +2989,31 @@ pub(super) fn intr_handler(uart: &Info, state: &State) {
         || interrupts.at_cmd_char_det().bit_is_set()
         || interrupts.glitch_det().bit_is_set()
         || interrupts.frm_err().bit_is_set()
-        || interrupts.parity_err().bit_is_set();
+        || interrupts.parity_err().bit_is_set()
+        || interrupts.brk_det().bit_is_set();
     let tx_wake = interrupts.tx_done().bit_is_set() || interrupts.txfifo_empty().bit_is_set();
+
+    // With software buffers, the handler moves the data itself and keeps
+    // listening.
+    let rx_buffered = state.rx_buffer.is_attached();
+    if rx_buffered {
+        if interrupts.rxfifo_tout().bit_is_set() || interrupts.at_cmd_char_det().bit_is_set() {
+            state.rx_idle.store(true, Ordering::Release);
+        }
+        uart.fill_rx_buffer(&state.rx_buffer);
+    }
+
     uart.regs()
         .int_clr()
         .write(|w| unsafe { w.bits(interrupt_bits) });
-    uart.regs()
-        .int_ena()
-        .modify(|r, w| unsafe { w.bits(r.bits() & !interrupt_bits) });
+    uart.modify_int_ena(|r, w| unsafe { w.bits(r.bits() & !interrupt_bits) });
+
+    if rx_buffered {
+        uart.enable_listen_rx(buffered_rx_events(), true);
+    }
+    if tx_wake {
+        uart.drain_tx_buffer(&state.tx_buffer);
+    }
 
     if tx_wake {
         state.tx_waker.wake();
@@ -2104,6 +3312,150 @@ pub struct State {
 
     /// Stores whether the RX half is configured for async operation.
     pub is_tx_async: AtomicBool,
+
+    // The software buffers, if the driver has been given any.
+    rx_buffer: RingBuffer,
+    tx_buffer: RingBuffer,
+
+    // Set by the interrupt handler when the RX line has gone idle while the
+    // RX buffer is in use.
+    rx_idle: AtomicBool,
+
+    // Whether `UartWakeupSource` lets this UART wake the chip up.
+    #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+    pub(crate) wakeup_enabled: AtomicBool,
+
+    // The last configuration applied through either half.
+    config: Mutex<Cell<Option<Config>>>,
+}
+
+impl State {
+    fn config(&self) -> Config {
+        unwrap!(critical_section::with(|cs| self.config.borrow(cs).get()))
+    }
+
+    fn set_config(&self, config: &Config) {
+        critical_section::with(|cs| self.config.borrow(cs).set(Some(*config)))
+    }
+}
+
+// A single-producer, single-consumer queue in a caller-provided buffer, shared
+// by the driver and the interrupt handler.
+struct RingBuffer {
+    buffer: AtomicPtr<u8>,
+    // Zero while no buffer is attached.
+    capacity: AtomicUsize,
+    len: AtomicUsize,
+    // Only changed by the consumer and the producer, respectively.
+    read: AtomicUsize,
+    write: AtomicUsize,
+    dropped: AtomicUsize,
+    overflowed: AtomicBool,
+}
+
+impl RingBuffer {
+    const fn new() -> Self {
+        Self {
+            buffer: AtomicPtr::new(core::ptr::null_mut()),
+            capacity: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+            overflowed: AtomicBool::new(false),
+        }
+    }
+
+    fn attach(&self, buffer: &'static mut [u8]) {
+        critical_section::with(|_| {
+            self.read.store(0, Ordering::Relaxed);
+            self.write.store(0, Ordering::Relaxed);
+            self.len.store(0, Ordering::Relaxed);
+            self.dropped.store(0, Ordering::Relaxed);
+            self.overflowed.store(false, Ordering::Relaxed);
+            self.buffer.store(buffer.as_mut_ptr(), Ordering::Relaxed);
+            self.capacity.store(buffer.len(), Ordering::Release);
+        })
+    }
+
+    fn detach(&self) {
+        critical_section::with(|_| self.capacity.store(0, Ordering::Release))
+    }
+
+    fn is_attached(&self) -> bool {
+        self.capacity.load(Ordering::Acquire) > 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len.load(Ordering::Acquire) == 0
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    fn push(&self, byte: u8) -> bool {
+        let capacity = self.capacity.load(Ordering::Acquire);
+        if self.len.load(Ordering::Acquire) >= capacity {
+            return false;
+        }
+
+        let write = self.write.load(Ordering::Relaxed);
+        unsafe { self.buffer.load(Ordering::Relaxed).add(write).write(byte) };
+        self.write.store((write + 1) % capacity, Ordering::Relaxed);
+        self.len.fetch_add(1, Ordering::Release);
+        true
+    }
+
+    // Returns how many bytes have been queued.
+    fn push_slice(&self, data: &[u8]) -> usize {
+        data.iter().take_while(|&&byte| self.push(byte)).count()
+    }
+
+    fn pop(&self) -> Option<u8> {
+        let capacity = self.capacity.load(Ordering::Acquire);
+        if capacity == 0 || self.is_empty() {
+            return None;
+        }
+
+        let read = self.read.load(Ordering::Relaxed);
+        let byte = unsafe { self.buffer.load(Ordering::Relaxed).add(read).read() };
+        self.read.store((read + 1) % capacity, Ordering::Relaxed);
+        self.len.fetch_sub(1, Ordering::Release);
+        Some(byte)
+    }
+
+    // Returns how many bytes have been written to `buf`.
+    fn pop_into(&self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        while count < buf.len() {
+            let Some(byte) = self.pop() else {
+                break;
+            };
+            buf[count] = byte;
+            count += 1;
+        }
+        count
+    }
+
+    fn count_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        self.overflowed.store(true, Ordering::Release);
+    }
+
+    fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // Returns whether bytes have been dropped since the last call.
+    fn take_overflow(&self) -> bool {
+        self.overflowed.swap(false, Ordering::Acquire)
+    }
+}
+
+// The interrupts that keep the RX buffer filled.
+fn buffered_rx_events() -> EnumSet<RxEvent> {
+    RxEvent::FifoFull | RxEvent::FifoTout | RxEvent::CmdCharDetected
 }
 
 impl Info {
@@ -2112,16 +3464,50 @@ impl Info {
         unsafe { &*self.register_block }
     }
 
+    // The halves and the interrupt handler all enable and disable their own
+    // interrupts, so the read-modify-write must not be interrupted.
+    fn modify_int_ena<F>(&self, f: F)
+    where
+        F: for<'w> FnOnce(&IntEnaR, &'w mut IntEnaW) -> &'w mut IntEnaW,
+    {
+        critical_section::with(|_| self.regs().int_ena().modify(|r, w| f(r, w)));
+    }
+
+    fn is_tx_idle(&self) -> bool {
+        #[cfg(esp32)]
+        let status = self.regs().status();
+        #[cfg(not(esp32))]
+        let status = self.regs().fsm_status();
+
+        status.read().st_utx_out().bits() == 0x0
+    }
+
+    fn clear_tx_done(&self) {
+        self.regs()
+            .int_clr()
+            .write(|w| w.tx_done().clear_bit_by_one());
+    }
+
+    fn is_tx_done(&self) -> bool {
+        self.regs().int_raw().read().tx_done().bit_is_set()
+            || (self.tx_fifo_count() == 0 && self.is_tx_idle())
+    }
+
+    // Waits for the data in the TX FIFO to be shifted out.
+    fn wait_for_tx_done(&self) {
+        self.clear_tx_done();
+        while !self.is_tx_done() {}
+    }
+
     /// Listen for the given interrupts
     fn enable_listen(&self, interrupts: EnumSet<UartInterrupt>, enable: bool) {
-        let reg_block = self.regs();
-
-        reg_block.int_ena().modify(|_, w| {
+        self.modify_int_ena(|_, w| {
             for interrupt in interrupts {
                 match interrupt {
                     UartInterrupt::AtCmd => w.at_cmd_char_det().bit(enable),
                     UartInterrupt::TxDone => w.tx_done().bit(enable),
                     UartInterrupt::RxFifoFull => w.rxfifo_full().bit(enable),
+                    UartInterrupt::RxBreakDetected => w.brk_det().bit(enable),
                 };
             }
             w
@@ -2143,6 +3529,9 @@ impl Info {
         if ints.rxfifo_full().bit_is_set() {
             res.insert(UartInterrupt::RxFifoFull);
         }
+        if ints.brk_det().bit_is_set() {
+            res.insert(UartInterrupt::RxBreakDetected);
+        }
 
         res
     }
@@ -2156,6 +3545,7 @@ impl Info {
                     UartInterrupt::AtCmd => w.at_cmd_char_det().clear_bit_by_one(),
                     UartInterrupt::TxDone => w.tx_done().clear_bit_by_one(),
                     UartInterrupt::RxFifoFull => w.rxfifo_full().clear_bit_by_one(),
+                    UartInterrupt::RxBreakDetected => w.brk_det().clear_bit_by_one(),
                 };
             }
             w
@@ -2189,8 +3579,72 @@ impl Info {
         Ok(())
     }
 
+    fn set_rs485(&self, config: &Rs485Config) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32c6, esp32h2))] {
+                let reg_inv = self.regs().conf1();
+            } else {
+                let reg_inv = self.regs().conf0();
+            }
+        }
+        // RTS is an active-low signal, so DE is high while RTS is deasserted.
+        reg_inv.modify(|_, w| {
+            w.rts_inv()
+                .bit(config.enable && config.de_polarity == DePolarity::ActiveLow)
+        });
+        // Start with the transmitter disabled.
+        self.regs()
+            .conf0()
+            .modify(|_, w| w.sw_rts().bit(config.enable));
+
+        self.regs().rs485_conf().modify(|_, w| {
+            w.rs485_en().bit(config.enable);
+            w.rs485tx_rx_en().bit(config.collision_detection);
+            w.rs485rxby_tx_en().bit(config.collision_detection)
+        });
+
+        self.sync_regs();
+    }
+
+    // RTS is active low, DE in RS-485 mode is its inverse.
+    fn is_rts_deasserted_high(&self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32c6, esp32h2))] {
+                let reg_inv = self.regs().conf1();
+            } else {
+                let reg_inv = self.regs().conf0();
+            }
+        }
+        let rs485 = self.regs().rs485_conf().read().rs485_en().bit_is_set();
+        let inverted = reg_inv.read().rts_inv().bit_is_set();
+
+        !rs485 ^ inverted
+    }
+
+    fn set_irda(&self, config: &IrdaConfig) {
+        self.regs().conf0().modify(|_, w| {
+            w.irda_en().bit(config.enable);
+            w.irda_tx_en().bit(config.enable);
+            w.irda_tx_inv().bit(config.tx_invert);
+            w.irda_rx_inv().bit(config.rx_invert);
+            w.irda_dplx().bit(config.enable && config.loopback)
+        });
+        self.sync_regs();
+    }
+
+    // A UART idles high, the IrDA encoder idles low unless it's inverted.
+    fn is_tx_idle_high(&self) -> bool {
+        let conf0 = self.regs().conf0().read();
+        !conf0.irda_en().bit_is_set() || conf0.irda_tx_inv().bit_is_set()
+    }
+
+    fn set_rs485_de(&self, asserted: bool) {
+        self.regs().conf0().modify(|_, w| w.sw_rts().bit(!asserted));
+        self.sync_regs();
+    }
+
     fn enable_listen_rx(&self, events: EnumSet<RxEvent>, enable: bool) {
-        self.regs().int_ena().modify(|_, w| {
+        self.modify_int_ena(|_, w| {
             for event in events {
                 match event {
                     RxEvent::FifoFull => w.rxfifo_full().bit(enable),
@@ -2199,6 +3653,7 @@ impl Info {
                     RxEvent::FifoOvf => w.rxfifo_ovf().bit(enable),
                     RxEvent::FifoTout => w.rxfifo_tout().bit(enable),
                     RxEvent::GlitchDetected => w.glitch_det().bit(enable),
+                    RxEvent::BreakDetected => w.brk_det().bit(enable),
                     RxEvent::FrameError => w.frm_err().bit(enable),
                     RxEvent::ParityError => w.parity_err().bit(enable),
                 };
@@ -2219,6 +3674,7 @@ impl Info {
                 RxEvent::FifoOvf => interrupts_enabled.rxfifo_ovf().bit_is_clear(),
                 RxEvent::FifoTout => interrupts_enabled.rxfifo_tout().bit_is_clear(),
                 RxEvent::GlitchDetected => interrupts_enabled.glitch_det().bit_is_clear(),
+                RxEvent::BreakDetected => interrupts_enabled.brk_det().bit_is_clear(),
                 RxEvent::FrameError => interrupts_enabled.frm_err().bit_is_clear(),
                 RxEvent::ParityError => interrupts_enabled.parity_err().bit_is_clear(),
             };
@@ -2231,7 +3687,7 @@ impl Info {
 
     fn rx_events(&self, events: impl Into<EnumSet<RxEvent>>) -> EnumSet<RxEvent> {
         let events = events.into();
-        let interrupts_enabled = self.regs().int_st().read();
+        let interrupts_enabled = self.regs().int_raw().read();
         let mut events_triggered = EnumSet::new();
         for event in events {
             let event_triggered = match event {
@@ -2241,6 +3697,7 @@ impl Info {
                 RxEvent::FifoOvf => interrupts_enabled.rxfifo_ovf().bit_is_set(),
                 RxEvent::FifoTout => interrupts_enabled.rxfifo_tout().bit_is_set(),
                 RxEvent::GlitchDetected => interrupts_enabled.glitch_det().bit_is_set(),
+                RxEvent::BreakDetected => interrupts_enabled.brk_det().bit_is_set(),
                 RxEvent::FrameError => interrupts_enabled.frm_err().bit_is_set(),
                 RxEvent::ParityError => interrupts_enabled.parity_err().bit_is_set(),
             };
@@ -2262,6 +3719,7 @@ impl Info {
                     RxEvent::FifoOvf => w.rxfifo_ovf().clear_bit_by_one(),
                     RxEvent::FifoTout => w.rxfifo_tout().clear_bit_by_one(),
                     RxEvent::GlitchDetected => w.glitch_det().clear_bit_by_one(),
+                    RxEvent::BreakDetected => w.brk_det().clear_bit_by_one(),
                     RxEvent::FrameError => w.frm_err().clear_bit_by_one(),
                     RxEvent::ParityError => w.parity_err().clear_bit_by_one(),
                 };
@@ -2303,6 +3761,162 @@ impl Info {
         Ok(())
     }
 
+    fn read_rx_fifo_byte(&self) -> Option<u8> {
+        cfg_if::cfg_if! {
+            if #[cfg(esp32s2)] {
+                // On the ESP32-S2 we need to use PeriBus2 to read the FIFO:
+                let fifo = unsafe {
+                    &*((self.regs().fifo().as_ptr() as *mut u8).add(0x20C00000)
+                        as *mut crate::pac::uart0::FIFO)
+                };
+            } else {
+                let fifo = self.regs().fifo();
+            }
+        }
+
+        if self.rx_fifo_count() > 0 {
+            // https://docs.espressif.com/projects/esp-chip-errata/en/latest/esp32/03-errata-description/esp32/cpu-subsequent-access-halted-when-get-interrupted.html
+            cfg_if::cfg_if! {
+                if #[cfg(esp32)] {
+                    let byte = crate::interrupt::free(|| fifo.read().rxfifo_rd_byte().bits());
+                } else {
+                    let byte = fifo.read().rxfifo_rd_byte().bits();
+                }
+            }
+
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    #[allow(clippy::useless_conversion)]
+    fn rx_fifo_count(&self) -> u16 {
+        let fifo_cnt: u16 = self.regs().status().read().rxfifo_cnt().bits().into();
+
+        // Calculate the real count based on the FIFO read and write offset address:
+        // https://www.espressif.com/sites/default/files/documentation/esp32_errata_en.pdf
+        // section 3.17
+        #[cfg(esp32)]
+        {
+            let status = self.regs().mem_rx_status().read();
+            let rd_addr = status.mem_rx_rd_addr().bits();
+            let wr_addr = status.mem_rx_wr_addr().bits();
+
+            if wr_addr > rd_addr {
+                wr_addr - rd_addr
+            } else if wr_addr < rd_addr {
+                (wr_addr + UART_FIFO_SIZE) - rd_addr
+            } else if fifo_cnt > 0 {
+                UART_FIFO_SIZE
+            } else {
+                0
+            }
+        }
+
+        #[cfg(not(esp32))]
+        fifo_cnt
+    }
+
+    #[allow(clippy::useless_conversion)]
+    fn tx_fifo_count(&self) -> u16 {
+        self.regs().status().read().txfifo_cnt().bits().into()
+    }
+
+    fn write_tx_fifo_byte(&self, word: u8) {
+        self.regs()
+            .fifo()
+            .write(|w| unsafe { w.rxfifo_rd_byte().bits(word) });
+    }
+
+    // Moves the received data from the RX FIFO into the software buffer.
+    // Bytes that don't fit are dropped.
+    fn fill_rx_buffer(&self, buffer: &RingBuffer) {
+        // Both the interrupt handler and the driver may get here.
+        critical_section::with(|_| {
+            if !buffer.is_attached() {
+                return;
+            }
+            while let Some(byte) = self.read_rx_fifo_byte() {
+                if !buffer.push(byte) {
+                    buffer.count_dropped();
+                }
+            }
+        })
+    }
+
+    // Moves data from the software buffer into the TX FIFO, and listens for
+    // the FIFO to empty while there's more.
+    fn drain_tx_buffer(&self, buffer: &RingBuffer) {
+        critical_section::with(|_| {
+            if !buffer.is_attached() {
+                return;
+            }
+            while self.tx_fifo_count() < UART_FIFO_SIZE {
+                let Some(byte) = buffer.pop() else {
+                    break;
+                };
+                self.write_tx_fifo_byte(byte);
+            }
+            self.modify_int_ena(|_, w| w.txfifo_empty().bit(!buffer.is_empty()));
+        })
+    }
+
+    fn set_discard_corrupted_bytes(&self, discard: bool) {
+        self.regs()
+            .conf0()
+            .modify(|_, w| w.err_wr_mask().bit(discard));
+        self.sync_regs();
+    }
+
+    fn discards_corrupted_bytes(&self) -> bool {
+        self.regs().conf0().read().err_wr_mask().bit_is_set()
+    }
+
+    /// Enables RTS flow control if `threshold` is set.
+    ///
+    /// # Errors
+    /// [`Err(ConfigError::UnsupportedFifoThreshold)`][ConfigError::UnsupportedFifoThreshold]
+    /// if the threshold isn't less than the FIFO size.
+    fn set_rx_flow_control(&self, threshold: Option<u16>) -> Result<(), ConfigError> {
+        if let Some(threshold) = threshold {
+            if threshold >= UART_FIFO_SIZE {
+                return Err(ConfigError::UnsupportedFifoThreshold);
+            }
+
+            cfg_if::cfg_if! {
+                if #[cfg(esp32)] {
+                    let reg_thrhd = self.regs().conf1();
+                } else if #[cfg(any(esp32c6, esp32h2))] {
+                    let reg_thrhd = self.regs().hwfc_conf();
+                } else {
+                    let reg_thrhd = self.regs().mem_conf();
+                }
+            }
+            reg_thrhd.modify(|_, w| unsafe { w.rx_flow_thrhd().bits(threshold as _) });
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32c6, esp32h2))] {
+                let reg_en = self.regs().hwfc_conf();
+            } else {
+                let reg_en = self.regs().conf1();
+            }
+        }
+        reg_en.modify(|_, w| w.rx_flow_en().bit(threshold.is_some()));
+
+        self.sync_regs();
+
+        Ok(())
+    }
+
+    fn set_tx_flow_control(&self, enable: bool) {
+        self.regs()
+            .conf0()
+            .modify(|_, w| w.tx_flow_en().bit(enable));
+        self.sync_regs();
+    }
+
     /// Configures the Receive Timeout detection setting
     ///
     /// # Arguments
@@ -2365,10 +3979,10 @@ impl Info {
         Ok(())
     }
 
-    #[cfg(any(esp32c2, esp32c3, esp32s3))]
-    fn change_baud(&self, config: &Config) {
-        use crate::peripherals::LPWR;
-
+    /// Returns the frequency of the selected clock source, and the divider
+    /// that turns it into the UART's core clock.
+    #[cfg(not(any(esp32, esp32s2)))]
+    fn sclk(config: &Config) -> (u32, u32) {
         let clocks = Clocks::get();
         let clk = match config.clock_source {
             ClockSource::Apb => clocks.apb_clock.to_Hz(),
@@ -2376,6 +3990,16 @@ impl Info {
             ClockSource::RcFast => crate::soc::constants::RC_FAST_CLK.to_Hz(),
         };
 
+        let max_div = 0b1111_1111_1111 - 1;
+        (clk, clk.div_ceil(max_div * config.baudrate))
+    }
+
+    #[cfg(any(esp32c2, esp32c3, esp32s3))]
+    fn change_baud(&self, config: &Config) {
+        use crate::peripherals::LPWR;
+
+        let (clk, clk_div) = Self::sclk(config);
+
         if config.clock_source == ClockSource::RcFast {
             LPWR::regs()
                 .clk_conf()
@@ -2384,8 +4008,6 @@ impl Info {
             crate::rom::ets_delay_us(5);
         }
 
-        let max_div = 0b1111_1111_1111 - 1;
-        let clk_div = clk.div_ceil(max_div * config.baudrate);
         self.regs().clk_conf().write(|w| unsafe {
             w.sclk_sel().bits(match config.clock_source {
                 ClockSource::Apb => 1,
@@ -2417,15 +4039,7 @@ impl Info {
 
     #[cfg(any(esp32c6, esp32h2))]
     fn change_baud(&self, config: &Config) {
-        let clocks = Clocks::get();
-        let clk = match config.clock_source {
-            ClockSource::Apb => clocks.apb_clock.to_Hz(),
-            ClockSource::Xtal => clocks.xtal_clock.to_Hz(),
-            ClockSource::RcFast => crate::soc::constants::RC_FAST_CLK.to_Hz(),
-        };
-
-        let max_div = 0b1111_1111_1111 - 1;
-        let clk_div = clk.div_ceil(max_div * config.baudrate);
+        let (clk, clk_div) = Self::sclk(config);
 
         // UART clocks are configured via PCR
         let pcr = crate::peripherals::PCR::regs();
@@ -2493,6 +4107,99 @@ impl Info {
             .write(|w| unsafe { w.clkdiv().bits(divider).frag().bits(0) });
     }
 
+    /// Measures the baud rate of the signal on the RX line, and restores the
+    /// baud rate of `config`.
+    fn detect_baudrate(&self, config: &Config, deadline: Instant) -> Result<u32, AutobaudError> {
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32, esp32s2))] {
+                // The pulse counters run on the APB clock, and they are wide
+                // enough for the whole range.
+                let probes = [Clocks::get().apb_clock.to_Hz()];
+            } else {
+                // The pulse counters run on the core clock, and they are too
+                // narrow to measure slow signals with a fast clock. The
+                // second probe selects a clock slow enough for the lowest
+                // baud rate.
+                let probes = [AUTOBAUD_MAX_BAUDRATE, AUTOBAUD_MIN_BAUDRATE];
+                let rx_filt = self.regs().rx_filt().read().bits();
+                self.regs()
+                    .rx_filt()
+                    .modify(|_, w| w.glitch_filt_en().set_bit());
+            }
+        }
+
+        let mut result = Err(AutobaudError::ImplausibleBaudrate);
+        for probe in probes {
+            cfg_if::cfg_if! {
+                if #[cfg(any(esp32, esp32s2))] {
+                    let counter_clock = probe;
+                } else {
+                    let probe_config = config.with_baudrate(probe);
+                    self.change_baud(&probe_config);
+                    let (clk, clk_div) = Self::sclk(&probe_config);
+                    let counter_clock = clk / clk_div;
+                }
+            }
+
+            result = self.measure_baudrate(counter_clock, deadline);
+            if result != Err(AutobaudError::ImplausibleBaudrate) {
+                break;
+            }
+        }
+
+        self.set_autobaud(false);
+        #[cfg(not(any(esp32, esp32s2)))]
+        {
+            self.regs().rx_filt().write(|w| unsafe { w.bits(rx_filt) });
+            self.change_baud(config);
+        }
+        self.sync_regs();
+
+        result
+    }
+
+    fn measure_baudrate(
+        &self,
+        counter_clock: u32,
+        deadline: Instant,
+    ) -> Result<u32, AutobaudError> {
+        // Restarting the measurement resets the counters.
+        self.set_autobaud(false);
+        self.set_autobaud(true);
+
+        while self.regs().rxd_cnt().read().rxd_edge_cnt().bits() < AUTOBAUD_EDGES {
+            if crate::time::now() > deadline {
+                return Err(AutobaudError::Timeout);
+            }
+        }
+
+        let low = self.regs().lowpulse().read().min_cnt().bits() as u32;
+        let high = self.regs().highpulse().read().min_cnt().bits() as u32;
+        if low >= AUTOBAUD_COUNTER_MAX || high >= AUTOBAUD_COUNTER_MAX {
+            // No pulse was short enough to be measured.
+            return Err(AutobaudError::ImplausibleBaudrate);
+        }
+
+        // The counters hold the pulse lengths minus one.
+        let baudrate = (counter_clock as u64 * 2 / (low + high + 2) as u64) as u32;
+        if !(AUTOBAUD_MIN_BAUDRATE..=AUTOBAUD_MAX_BAUDRATE).contains(&baudrate) {
+            return Err(AutobaudError::ImplausibleBaudrate);
+        }
+
+        Ok(baudrate)
+    }
+
+    fn set_autobaud(&self, enable: bool) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(esp32, esp32s2))] {
+                self.regs().autobaud().modify(|_, w| w.en().bit(enable));
+            } else {
+                self.regs().conf0().modify(|_, w| w.autobaud_en().bit(enable));
+            }
+        }
+        self.sync_regs();
+    }
+
     fn change_data_bits(&self, data_bits: DataBits) {
         self.regs()
             .conf0()
@@ -2511,15 +4218,18 @@ impl Info {
         #[cfg(esp32)]
         {
             // workaround for hardware issue, when UART stop bit set as 2-bit mode.
-            if stop_bits == StopBits::_2 {
-                self.regs()
-                    .rs485_conf()
-                    .modify(|_, w| w.dl1_en().bit(stop_bits == StopBits::_2));
+            self.regs()
+                .rs485_conf()
+                .modify(|_, w| w.dl1_en().bit(stop_bits == StopBits::_2));
 
-                self.regs()
-                    .conf0()
-                    .modify(|_, w| unsafe { w.stop_bit_num().bits(1) });
-            }
+            let stop_bit_num = if stop_bits == StopBits::_2 {
+                StopBits::_1
+            } else {
+                stop_bits
+            };
+            self.regs()
+                .conf0()
+                .modify(|_, w| unsafe { w.stop_bit_num().bits(stop_bit_num as u8) });
         }
 
         #[cfg(not(esp32))]
@@ -2571,6 +4281,12 @@ macro_rules! impl_instance {
                     rx_waker: AtomicWaker::new(),
                     is_rx_async: AtomicBool::new(false),
                     is_tx_async: AtomicBool::new(false),
+                    rx_buffer: RingBuffer::new(),
+                    tx_buffer: RingBuffer::new(),
+                    rx_idle: AtomicBool::new(false),
+                    #[cfg(any(esp32, esp32s3, esp32c3, esp32c6, esp32c2))]
+                    wakeup_enabled: AtomicBool::new(false),
+                    config: Mutex::new(Cell::new(None)),
                 };
 
                 static PERIPHERAL: Info = Info {
@@ -0,0 +1,422 @@
+//! UART DMA support through the UHCI peripheral.
+
+use core::{future::poll_fn, mem::ManuallyDrop, task::Poll};
+
+use super::{Error, Instance, Uart};
+use crate::{
+    dma::{
+        asynch::DmaTxFuture,
+        Channel,
+        DmaChannelFor,
+        DmaError,
+        DmaPeripheral,
+        DmaRxBuffer,
+        DmaRxInterrupt,
+        DmaRxStreamBuf,
+        DmaRxStreamBufView,
+        DmaTxBuf,
+        PeripheralDmaChannel,
+        Rx,
+        Tx,
+    },
+    pac::uhci0::RegisterBlock,
+    peripheral::{Peripheral, PeripheralRef},
+    peripherals::UHCI0,
+    system::{self, PeripheralGuard},
+    Async,
+    Blocking,
+    DriverMode,
+};
+
+impl<'d> Uart<'d, Blocking> {
+    /// Moves the UART's data with DMA, through the UHCI peripheral.
+    ///
+    /// Received bytes are streamed into `rx_buffer` for as long as the
+    /// driver exists. Writes are sent from `tx_buffer`, so a write can send
+    /// at most [`DmaTxBuf::capacity`] bytes at a time; longer writes are
+    /// split up.
+    ///
+    /// See the [module documentation](super#dma) for the UART instances the
+    /// UHCI can be bound to.
+    ///
+    /// ## Example
+    ///
+    /// ```rust, no_run
+    #[doc = crate::before_snippet!()]
+    /// # use esp_hal::uart::{Config, Uart};
+    /// # use esp_hal::dma_rx_stream_buffer;
+    /// # use esp_hal::dma_tx_buffer;
+    /// let rx_buffer = dma_rx_stream_buffer!(4096, 256);
+    /// let tx_buffer = dma_tx_buffer!(1024).unwrap();
+    ///
+    /// let mut uart = Uart::new(peripherals.UART1, Config::default())?
+    ///     .with_rx(peripherals.GPIO1)
+    ///     .with_tx(peripherals.GPIO2)
+    ///     .with_dma(
+    ///         peripherals.UHCI0,
+    ///         peripherals.DMA_CH0,
+    ///         rx_buffer,
+    ///         tx_buffer,
+    ///     );
+    ///
+    /// uart.write_bytes(b"Hello, world!")?;
+    /// let mut buf = [0; 64];
+    /// let received = uart.read_buffered_bytes(&mut buf)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instability::unstable]
+    pub fn with_dma<CH>(
+        self,
+        uhci: impl Peripheral<P = UHCI0> + 'd,
+        channel: impl Peripheral<P = CH> + 'd,
+        mut rx_buffer: DmaRxStreamBuf,
+        tx_buffer: DmaTxBuf,
+    ) -> UartDma<'d, Blocking>
+    where
+        CH: DmaChannelFor<UHCI0>,
+    {
+        crate::into_ref!(uhci);
+        let uhci = Uhci::new(uhci, self.tx.uart.info().peripheral);
+        let mut channel = Channel::new(channel.map(|ch| ch.degrade()));
+        unwrap!(start_rx(&mut channel.rx, &mut rx_buffer));
+        let rx_view = rx_buffer.into_view();
+
+        UartDma {
+            uhci,
+            uart: self,
+            channel,
+            rx_view: ManuallyDrop::new(rx_view),
+            tx_buffer,
+        }
+    }
+}
+
+/// The UHCI peripheral, bound to a UART.
+struct Uhci<'d> {
+    _uhci: PeripheralRef<'d, UHCI0>,
+    _guard: PeripheralGuard,
+}
+
+impl<'d> Uhci<'d> {
+    fn new(uhci: PeripheralRef<'d, UHCI0>, uart: system::Peripheral) -> Self {
+        let guard = PeripheralGuard::new(system::Peripheral::Uhci0);
+
+        let regs = Self::regs();
+        regs.conf0().write(|w| {
+            match uart {
+                system::Peripheral::Uart0 => w.uart0_ce().set_bit(),
+                system::Peripheral::Uart1 => w.uart1_ce().set_bit(),
+                #[cfg(uart2)]
+                system::Peripheral::Uart2 => w.uart2_ce().set_bit(),
+                _ => unreachable!(),
+            };
+            // Hand received bytes over when the line goes idle. Bytes are
+            // passed through unchanged: no headers, CRCs or separators.
+            w.uart_idle_eof_en().set_bit();
+            w.clk_en().set_bit()
+        });
+        regs.conf1().write(|w| unsafe { w.bits(0) });
+        regs.escape_conf().write(|w| unsafe { w.bits(0) });
+
+        Self {
+            _uhci: uhci,
+            _guard: guard,
+        }
+    }
+
+    fn set_separator(&self, separator: Option<u8>) {
+        let regs = Self::regs();
+        if let Some(separator) = separator {
+            regs.esc_conf(0)
+                .modify(|_, w| unsafe { w.seper_char().bits(separator) });
+        }
+        regs.escape_conf().write(|w| {
+            w.tx_c0_esc_en().bit(separator.is_some());
+            w.tx_db_esc_en().bit(separator.is_some());
+            w.rx_c0_esc_en().bit(separator.is_some());
+            w.rx_db_esc_en().bit(separator.is_some())
+        });
+        regs.conf0()
+            .modify(|_, w| w.seper_en().bit(separator.is_some()));
+    }
+
+    fn regs() -> &'static RegisterBlock {
+        UHCI0::regs()
+    }
+}
+
+impl Drop for Uhci<'_> {
+    fn drop(&mut self) {
+        // Unbind the UART, so that the DMA channel stops receiving.
+        Self::regs().conf0().write(|w| unsafe { w.bits(0) });
+    }
+}
+
+/// A UART driver that moves data with DMA.
+///
+/// Created by [`Uart::with_dma`].
+#[instability::unstable]
+pub struct UartDma<'d, Dm: DriverMode> {
+    // Dropped first, so that the DMA stops before the UART is released.
+    uhci: Uhci<'d>,
+    uart: Uart<'d, Dm>,
+    channel: Channel<'d, Dm, PeripheralDmaChannel<UHCI0>>,
+    rx_view: ManuallyDrop<DmaRxStreamBufView>,
+    tx_buffer: DmaTxBuf,
+}
+
+impl<'d> UartDma<'d, Blocking> {
+    /// Converts the driver to [`Async`] mode.
+    #[instability::unstable]
+    pub fn into_async(self) -> UartDma<'d, Async> {
+        UartDma {
+            uhci: self.uhci,
+            uart: self.uart.into_async(),
+            channel: self.channel.into_async(),
+            rx_view: self.rx_view,
+            tx_buffer: self.tx_buffer,
+        }
+    }
+}
+
+impl<'d> UartDma<'d, Async> {
+    /// Converts the driver to [`Blocking`] mode.
+    #[instability::unstable]
+    pub fn into_blocking(self) -> UartDma<'d, Blocking> {
+        UartDma {
+            uhci: self.uhci,
+            uart: self.uart.into_blocking(),
+            channel: self.channel.into_blocking(),
+            rx_view: self.rx_view,
+            tx_buffer: self.tx_buffer,
+        }
+    }
+
+    /// Writes bytes, and returns when the last of them has been moved into
+    /// the TX FIFO.
+    ///
+    /// Returns the number of bytes written, or [`Error::DmaError`] if the DMA
+    /// failed to send them.
+    #[instability::unstable]
+    pub async fn write_async(&mut self, data: &[u8]) -> Result<usize, Error> {
+        for chunk in data.chunks(self.tx_buffer.capacity()) {
+            self.start_tx(chunk)?;
+            DmaTxFuture::new(&mut self.channel.tx).await?;
+        }
+
+        Ok(data.len())
+    }
+
+    /// Waits until all written bytes have been transmitted.
+    #[instability::unstable]
+    pub async fn flush_async(&mut self) -> Result<(), Error> {
+        self.uart.flush_async().await
+    }
+
+    /// Reads bytes, waiting until at least one has been received.
+    ///
+    /// Returns the number of bytes read.
+    #[instability::unstable]
+    pub async fn read_async(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        poll_fn(|cx| {
+            self.channel.rx.waker().register(cx.waker());
+            self.channel
+                .rx
+                .clear_in(DmaRxInterrupt::Done | DmaRxInterrupt::SuccessfulEof);
+            if self.rx_view.available_bytes() > 0 || self.is_rx_stalled() {
+                return Poll::Ready(());
+            }
+            self.channel.rx.listen_in(
+                DmaRxInterrupt::Done
+                    | DmaRxInterrupt::SuccessfulEof
+                    | DmaRxInterrupt::DescriptorEmpty,
+            );
+            Poll::Pending
+        })
+        .await;
+
+        self.read_buffered_bytes(buf)
+    }
+
+    /// Reads a frame.
+    ///
+    /// Returns when the frame has ended, or when `buf` is full, in which
+    /// case the next read returns the rest of the frame. See the
+    /// [module documentation](super#dma) for what ends a frame. Empty frames
+    /// are skipped.
+    ///
+    /// Returns the number of bytes read.
+    #[instability::unstable]
+    pub async fn read_frame_async(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.uart.rx.check_for_errors()?;
+
+        let mut count = 0;
+        poll_fn(|cx| {
+            self.channel.rx.waker().register(cx.waker());
+            self.channel
+                .rx
+                .clear_in(DmaRxInterrupt::Done | DmaRxInterrupt::SuccessfulEof);
+
+            loop {
+                let (read, ended) = self.read_frame_bytes(&mut buf[count..]);
+                count += read;
+                if let Err(error) = self.restart_rx_if_stalled() {
+                    return Poll::Ready(Err(error));
+                }
+                if (ended && count > 0) || count == buf.len() {
+                    return Poll::Ready(Ok(()));
+                }
+                if !ended {
+                    break;
+                }
+            }
+
+            self.channel.rx.listen_in(
+                DmaRxInterrupt::Done
+                    | DmaRxInterrupt::SuccessfulEof
+                    | DmaRxInterrupt::DescriptorEmpty,
+            );
+            Poll::Pending
+        })
+        .await?;
+
+        Ok(count)
+    }
+}
+
+impl<Dm> UartDma<'_, Dm>
+where
+    Dm: DriverMode,
+{
+    /// Ends received frames at `separator`.
+    ///
+    /// The separator works like in SLIP: the UHCI sends each write as a frame
+    /// that starts and ends with the separator, and escapes the separator
+    /// and `0xDB` bytes in the data. Received frames are unescaped, and the
+    /// separators are removed.
+    #[instability::unstable]
+    pub fn with_separator(self, separator: u8) -> Self {
+        self.uhci.set_separator(Some(separator));
+        self
+    }
+
+    /// Writes bytes, and returns when the last of them has been moved into
+    /// the TX FIFO.
+    ///
+    /// Returns the number of bytes written, or [`Error::DmaError`] if the DMA
+    /// failed to send them.
+    #[instability::unstable]
+    pub fn write_bytes(&mut self, data: &[u8]) -> Result<usize, Error> {
+        for chunk in data.chunks(self.tx_buffer.capacity()) {
+            self.start_tx(chunk)?;
+            while !self.channel.tx.is_done() {
+                if self.channel.tx.has_error() {
+                    return Err(Error::DmaError(DmaError::DescriptorError));
+                }
+            }
+        }
+
+        Ok(data.len())
+    }
+
+    /// Waits until all written bytes have been transmitted.
+    #[instability::unstable]
+    pub fn flush(&mut self) {
+        self.uart.flush();
+    }
+
+    /// Reads bytes until `buf` is full.
+    #[instability::unstable]
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let mut count = 0;
+        while count < buf.len() {
+            count += self.read_buffered_bytes(&mut buf[count..])?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the bytes that have been received, without waiting.
+    ///
+    /// Returns the number of bytes read.
+    #[instability::unstable]
+    pub fn read_buffered_bytes(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.uart.rx.check_for_errors()?;
+
+        let count = self.rx_view.pop(buf);
+        self.restart_rx_if_stalled()?;
+
+        Ok(count)
+    }
+
+    // Reads up to the end of the current frame, and returns the number of
+    // bytes read and whether the frame has ended.
+    fn read_frame_bytes(&mut self, buf: &mut [u8]) -> (usize, bool) {
+        let mut count = 0;
+        loop {
+            let (data, eof) = self.rx_view.peek_until_eof();
+            let len = data.len().min(buf.len() - count);
+            let frame_ends = eof && len == data.len();
+            buf[count..][..len].copy_from_slice(&data[..len]);
+            self.rx_view.consume(len);
+            count += len;
+
+            if frame_ends {
+                return (count, true);
+            }
+            if len == 0 || count == buf.len() {
+                return (count, false);
+            }
+        }
+    }
+
+    // The DMA stops when it runs out of descriptors. Meanwhile, the RX FIFO
+    // keeps receiving, and reports an overflow once it's full.
+    fn is_rx_stalled(&self) -> bool {
+        self.channel
+            .rx
+            .pending_in_interrupts()
+            .contains(DmaRxInterrupt::DescriptorEmpty)
+    }
+
+    fn restart_rx_if_stalled(&mut self) -> Result<(), Error> {
+        // Restarting resets every descriptor, so it must wait until all the
+        // bytes have been read.
+        if self.is_rx_stalled() && self.rx_view.available_bytes() == 0 {
+            self.channel.rx.stop_transfer();
+            let view = unsafe { ManuallyDrop::take(&mut self.rx_view) };
+            let mut buffer = DmaRxStreamBuf::from_view(view);
+            let result = start_rx(&mut self.channel.rx, &mut buffer);
+            // Keep the buffer even if the DMA couldn't be restarted.
+            self.rx_view = ManuallyDrop::new(buffer.into_view());
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn start_tx(&mut self, data: &[u8]) -> Result<(), Error> {
+        // A previous write may have been cancelled, and the DMA may still be
+        // reading the buffer.
+        self.channel.tx.stop_transfer();
+        self.tx_buffer.fill(data);
+        unsafe {
+            self.channel
+                .tx
+                .prepare_transfer(DmaPeripheral::Uhci0, &mut self.tx_buffer)?;
+        }
+        self.channel.tx.start_transfer()?;
+
+        Ok(())
+    }
+}
+
+fn start_rx(rx: &mut impl Rx, buffer: &mut DmaRxStreamBuf) -> Result<(), DmaError> {
+    unsafe { rx.prepare_transfer(DmaPeripheral::Uhci0, buffer)? };
+    rx.start_transfer()
+}
@@ -3,6 +3,13 @@ fn main() -> Result<(), String> {
     esp_build::assert_unique_used_features!(
         "esp32", "esp32c2", "esp32c3", "esp32c6", "esp32h2", "esp32s2", "esp32s3"
     );
+    // Only a single default flash size can be selected
+    esp_build::assert_unique_features!(
+        "default-capacity-2mb",
+        "default-capacity-4mb",
+        "default-capacity-8mb",
+        "default-capacity-16mb"
+    );
 
     if cfg!(feature = "esp32") {
         match std::env::var("OPT_LEVEL") {
@@ -0,0 +1,173 @@
+use embassy_futures::yield_now;
+use embedded_storage::nor_flash::{
+    ErrorType,
+    NorFlash as BlockingNorFlash,
+    ReadNorFlash as BlockingReadNorFlash,
+};
+use embedded_storage_async::nor_flash::{MultiwriteNorFlash, NorFlash, ReadNorFlash};
+
+use crate::{
+    common::range_length,
+    nor_flash::check_not_encrypted,
+    FlashStorage,
+    FlashStorageError,
+};
+
+/// Async wrapper around [`FlashStorage`].
+///
+/// The underlying ROM functions are still blocking, but long writes and
+/// erases are split into sector sized chunks, yielding to the executor in
+/// between. This keeps other tasks running while e.g. a whole partition is
+/// erased.
+#[derive(Debug)]
+pub struct AsyncFlashStorage {
+    flash: FlashStorage,
+}
+
+impl AsyncFlashStorage {
+    /// Wraps the given [`FlashStorage`].
+    pub fn new(flash: FlashStorage) -> Self {
+        Self { flash }
+    }
+
+    /// Returns the wrapped [`FlashStorage`].
+    pub fn into_inner(self) -> FlashStorage {
+        self.flash
+    }
+}
+
+impl From<FlashStorage> for AsyncFlashStorage {
+    fn from(flash: FlashStorage) -> Self {
+        Self::new(flash)
+    }
+}
+
+impl ErrorType for AsyncFlashStorage {
+    type Error = FlashStorageError;
+}
+
+impl ReadNorFlash for AsyncFlashStorage {
+    const READ_SIZE: usize = <FlashStorage as BlockingReadNorFlash>::READ_SIZE;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        BlockingReadNorFlash::read(&mut self.flash, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.flash.capacity()
+    }
+}
+
+impl NorFlash for AsyncFlashStorage {
+    const WRITE_SIZE: usize = <FlashStorage as BlockingNorFlash>::WRITE_SIZE;
+    const ERASE_SIZE: usize = <FlashStorage as BlockingNorFlash>::ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        // Validate the whole range first so an invalid range doesn't end up
+        // partially erased
+        let len = range_length(from, to, self.flash.capacity())?;
+        self.flash
+            .check_alignment::<{ FlashStorage::SECTOR_SIZE }>(from, len)?;
+        self.flash.check_bounds(from, len)?;
+
+        for sector in from / FlashStorage::SECTOR_SIZE..to / FlashStorage::SECTOR_SIZE {
+            self.flash.erase_sector(sector)?;
+            yield_now().await;
+        }
+
+        Ok(())
+    }
+
+    /// Returns [`FlashStorageError::EncryptionEnabled`] if flash encryption
+    /// is enabled.
+    async fn write(&mut self, mut offset: u32, mut bytes: &[u8]) -> Result<(), Self::Error> {
+        check_not_encrypted(&self.flash)?;
+        self.flash
+            .check_alignment::<{ FlashStorage::WORD_SIZE }>(offset, bytes.len())?;
+        self.flash.check_bounds(offset, bytes.len())?;
+
+        while !bytes.is_empty() {
+            // Don't let a chunk cross a sector boundary
+            let len = bytes
+                .len()
+                .min((FlashStorage::SECTOR_SIZE - offset % FlashStorage::SECTOR_SIZE) as usize);
+
+            self.flash.write_aligned(offset, &bytes[..len])?;
+            yield_now().await;
+
+            offset += len as u32;
+            bytes = &bytes[len..];
+        }
+
+        Ok(())
+    }
+}
+
+impl MultiwriteNorFlash for AsyncFlashStorage {}
+
+#[cfg(test)]
+mod test {
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use super::*;
+
+    const SECTOR_SIZE: u32 = FlashStorage::SECTOR_SIZE;
+    // Use sectors which aren't touched by the other tests
+    const BASE: u32 = SECTOR_SIZE * 5;
+
+    /// Runs the future to completion, returning its output and the number of
+    /// times it yielded.
+    fn run<F: Future>(future: F) -> (F::Output, usize) {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut yields = 0;
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return (output, yields),
+                Poll::Pending => yields += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn erase_and_write_yield_per_sector() {
+        let mut flash = AsyncFlashStorage::new(FlashStorage::new());
+
+        let (result, yields) = run(flash.erase(BASE, BASE + 2 * SECTOR_SIZE));
+        result.unwrap();
+        assert!(yields >= 2);
+
+        // Straddles the sector boundary
+        let data = [0x5au8; 64];
+        let (result, yields) = run(flash.write(BASE + SECTOR_SIZE - 32, &data));
+        result.unwrap();
+        assert!(yields >= 2);
+
+        let mut read = [0u8; 128];
+        let (result, _) = run(flash.read(BASE + SECTOR_SIZE - 64, &mut read));
+        result.unwrap();
+        assert_eq!(read[..32], [0xff; 32]);
+        assert_eq!(read[32..96], data);
+        assert_eq!(read[96..], [0xff; 32]);
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        let mut flash = AsyncFlashStorage::new(FlashStorage::new());
+
+        let (result, yields) = run(flash.erase(BASE + 1, BASE + SECTOR_SIZE));
+        assert!(matches!(result, Err(FlashStorageError::NotAligned { .. })));
+        assert_eq!(yields, 0);
+
+        let (result, _) = run(flash.erase(BASE + SECTOR_SIZE, BASE));
+        assert!(matches!(result, Err(FlashStorageError::OutOfBounds { .. })));
+
+        let (result, _) = run(flash.write(BASE + 2, &[0u8; 4]));
+        assert!(matches!(result, Err(FlashStorageError::NotAligned { .. })));
+    }
+}
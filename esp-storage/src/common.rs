@@ -1,7 +1,15 @@
-use core::ops::{Deref, DerefMut};
+use core::{
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
 
 use crate::chip_specific;
 
+/// A word-aligned buffer holding a whole sector, used by
+/// [`FlashStorage::write_sector`].
+///
+/// At 4 KiB it is rather large for the stack, so it can also be placed in a
+/// `static`.
 #[repr(C, align(4))]
 pub struct FlashSectorBuffer {
     // NOTE: Ensure that no unaligned fields are added above `data` to maintain its required
@@ -9,6 +17,21 @@ pub struct FlashSectorBuffer {
     data: [u8; FlashStorage::SECTOR_SIZE as usize],
 }
 
+impl FlashSectorBuffer {
+    /// Creates a zeroed buffer.
+    pub const fn new() -> Self {
+        Self {
+            data: [0; FlashStorage::SECTOR_SIZE as usize],
+        }
+    }
+}
+
+impl Default for FlashSectorBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Deref for FlashSectorBuffer {
     type Target = [u8; FlashStorage::SECTOR_SIZE as usize];
 
@@ -23,31 +46,243 @@ impl DerefMut for FlashSectorBuffer {
     }
 }
 
+#[repr(C, align(4))]
+struct FlashVerifyBuffer {
+    // NOTE: Ensure that no unaligned fields are added above `data` to maintain its required
+    // alignment
+    data: [u8; FlashStorage::VERIFY_CHUNK_SIZE],
+}
+
+/// How the new contents of a sector differ from the current ones.
+enum SectorDiff {
+    Identical,
+    /// Only bits which are `1` now become `0`, which programming can do.
+    ClearsBits,
+    NeedsErase,
+}
+
+#[repr(C, align(4))]
+struct FlashEncryptedBuffer {
+    // NOTE: Ensure that no unaligned fields are added above `data` to maintain its required
+    // alignment
+    data: [u8; FlashStorage::ENCRYPTED_WRITE_SIZE as usize],
+}
+
+#[repr(C, align(4))]
+pub struct FlashWordBuffer {
+    // NOTE: Ensure that no unaligned fields are added above `data` to maintain its required
+    // alignment
+    data: [u8; FlashStorage::WORD_SIZE as usize],
+}
+
+impl Deref for FlashWordBuffer {
+    type Target = [u8; FlashStorage::WORD_SIZE as usize];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for FlashWordBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+/// The flash operation which failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum FlashOperation {
+    /// Reading data.
+    Read,
+    /// Writing data.
+    Write,
+    /// Erasing a sector or block.
+    Erase,
+    /// Reading the JEDEC ID.
+    ReadId,
+}
+
+impl core::fmt::Display for FlashOperation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FlashOperation::Read => write!(f, "read"),
+            FlashOperation::Write => write!(f, "write"),
+            FlashOperation::Erase => write!(f, "erase"),
+            FlashOperation::ReadId => write!(f, "read ID"),
+        }
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum FlashStorageError {
-    IoError,
-    IoTimeout,
+    /// The ROM function reported an error.
+    IoError(FlashOperation),
+    /// The ROM function timed out waiting for the flash.
+    IoTimeout(FlashOperation),
+    /// The write protection of the flash couldn't be disabled.
     CantUnlock,
-    NotAligned,
-    OutOfBounds,
-    Other(i32),
+    /// The write protection of the flash couldn't be enabled.
+    CantLock,
+    /// The `offset` or `length` of an access isn't a multiple of
+    /// `alignment`.
+    NotAligned {
+        offset: u32,
+        length: usize,
+        alignment: u32,
+    },
+    /// The `length` bytes starting at `offset` don't fit into the `capacity`
+    /// bytes of the flash or region.
+    OutOfBounds {
+        offset: u32,
+        length: usize,
+        capacity: usize,
+    },
+    /// Reading back the data after a write or erase returned something else.
+    VerifyFailed { offset: u32 },
+    /// The flash chip or the SoC doesn't support the operation.
+    Unsupported,
+    /// Flash encryption is enabled, which the operation doesn't support.
+    EncryptionEnabled,
+    /// The ROM function returned an unknown error code.
+    Other(FlashOperation, i32),
+}
+
+impl core::error::Error for FlashStorageError {}
+
+impl core::fmt::Display for FlashStorageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FlashStorageError::IoError(op) => write!(f, "Flash {op} failed"),
+            FlashStorageError::IoTimeout(op) => write!(f, "Flash {op} timed out"),
+            FlashStorageError::CantUnlock => write!(f, "The flash couldn't be unlocked"),
+            FlashStorageError::CantLock => write!(f, "The flash couldn't be locked"),
+            FlashStorageError::NotAligned {
+                offset,
+                length,
+                alignment,
+            } => write!(
+                f,
+                "The access of {length} bytes at offset {offset:#x} isn't aligned to {alignment} bytes"
+            ),
+            FlashStorageError::OutOfBounds {
+                offset,
+                length,
+                capacity,
+            } => write!(
+                f,
+                "The access of {length} bytes at offset {offset:#x} exceeds the capacity of {capacity} bytes"
+            ),
+            FlashStorageError::VerifyFailed { offset } => {
+                write!(f, "Verification failed at offset {offset:#x}")
+            }
+            FlashStorageError::Unsupported => {
+                write!(f, "The operation isn't supported")
+            }
+            FlashStorageError::EncryptionEnabled => {
+                write!(f, "The operation isn't supported with flash encryption")
+            }
+            FlashStorageError::Other(op, code) => {
+                write!(f, "Flash {op} failed with error code {code}")
+            }
+        }
+    }
 }
 
+const ERASED_WORD: [u8; FlashStorage::WORD_SIZE as usize] =
+    [0xff; FlashStorage::WORD_SIZE as usize];
+
 #[inline(always)]
-pub fn check_rc(rc: i32) -> Result<(), FlashStorageError> {
+pub fn check_rc(rc: i32, op: FlashOperation) -> Result<(), FlashStorageError> {
     match rc {
         0 => Ok(()),
-        1 => Err(FlashStorageError::IoError),
-        2 => Err(FlashStorageError::IoTimeout),
-        _ => Err(FlashStorageError::Other(rc)),
+        1 => Err(FlashStorageError::IoError(op)),
+        2 => Err(FlashStorageError::IoTimeout(op)),
+        _ => Err(FlashStorageError::Other(op, rc)),
+    }
+}
+
+/// Returns the length of the range `from..to`, which must not be reversed.
+#[inline(always)]
+pub(crate) fn range_length(
+    from: u32,
+    to: u32,
+    capacity: usize,
+) -> Result<usize, FlashStorageError> {
+    match to.checked_sub(from) {
+        Some(length) => Ok(length as usize),
+        // Report the length such that `offset + length` wraps around to `to`
+        None => Err(FlashStorageError::OutOfBounds {
+            offset: from,
+            length: to.wrapping_sub(from) as usize,
+            capacity,
+        }),
+    }
+}
+
+/// Parameters of the flash chip, derived from its JEDEC ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashChipInfo {
+    /// JEDEC manufacturer ID, e.g. `0xef` for Winbond or `0xc8` for
+    /// GigaDevice.
+    pub manufacturer: u8,
+    /// Memory type in the high byte and capacity code in the low byte.
+    pub device_id: u16,
+    /// Size in bytes, as encoded by the capacity code. `0` if the code is out
+    /// of range.
+    pub capacity_bytes: usize,
+}
+
+impl FlashChipInfo {
+    /// Decodes a JEDEC ID as returned by [`FlashStorage::read_jedec_id`].
+    pub fn from_jedec_id(id: u32) -> Self {
+        let capacity_code = id & 0xff;
+        Self {
+            manufacturer: (id >> 16) as u8,
+            device_id: id as u16,
+            capacity_bytes: if capacity_code < usize::BITS {
+                1 << capacity_code
+            } else {
+                0
+            },
+        }
     }
 }
 
+/// Where the size returned by [`FlashStorage::capacity`] was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum CapacitySource {
+    /// The capacity code of the JEDEC ID reported by the flash chip.
+    JedecId,
+    /// The flash size field of the ESP image header of the bootloader.
+    ImageHeader,
+    /// The end of the last partition in the partition table, rounded up to
+    /// the next power of two.
+    PartitionTable,
+    /// The default selected by the `default-capacity-*` features, 1 MB if
+    /// none is enabled.
+    Default,
+    /// Set by [`FlashStorage::new_with_capacity`] or
+    /// [`FlashStorage::set_capacity`].
+    Configured,
+}
+
 #[derive(Debug)]
 pub struct FlashStorage {
     pub(crate) capacity: usize,
+    capacity_source: CapacitySource,
     unlocked: bool,
+    verify: bool,
+    diff_write: bool,
+    yield_fn: Option<fn()>,
+    erase_count: u32,
+    program_count: u32,
+    skipped_count: u32,
 }
 
 impl Default for FlashStorage {
@@ -59,54 +294,792 @@ impl Default for FlashStorage {
 impl FlashStorage {
     pub const WORD_SIZE: u32 = 4;
     pub const SECTOR_SIZE: u32 = 4096;
+    pub const BLOCK_SIZE: u32 = 65536;
+    /// Alignment of the offset and length of encrypted writes.
+    pub const ENCRYPTED_WRITE_SIZE: u32 = 32;
 
+    /// Number of bytes read back at once when verifying.
+    const VERIFY_CHUNK_SIZE: usize = 32;
+
+    /// Range of capacities reported by the JEDEC ID which are trusted over
+    /// the image header.
+    const JEDEC_CAPACITY_RANGE: core::ops::RangeInclusive<usize> = (1 << 20)..=Self::MAX_CAPACITY;
+
+    /// Largest flash which can be accessed. The ESP32 only supports 24 bit
+    /// addresses, the other chips use 4 byte address commands above 16 MB.
+    #[cfg(feature = "esp32")]
+    const MAX_CAPACITY: usize = 16 << 20;
+    #[cfg(not(feature = "esp32"))]
+    const MAX_CAPACITY: usize = 128 << 20;
+
+    /// Accesses ending above this need 4 byte addresses, which the ROM
+    /// functions don't send.
+    #[cfg(not(feature = "esp32"))]
+    const ADDRESS_24BIT_LIMIT: u32 = 1 << 24;
+
+    /// Size assumed if none of the other sources are usable.
+    #[cfg(feature = "default-capacity-2mb")]
+    const DEFAULT_CAPACITY_MB: usize = 2;
+    #[cfg(feature = "default-capacity-4mb")]
+    const DEFAULT_CAPACITY_MB: usize = 4;
+    #[cfg(feature = "default-capacity-8mb")]
+    const DEFAULT_CAPACITY_MB: usize = 8;
+    #[cfg(feature = "default-capacity-16mb")]
+    const DEFAULT_CAPACITY_MB: usize = 16;
+    // Every chip has at least this much flash
+    #[cfg(not(any(
+        feature = "default-capacity-2mb",
+        feature = "default-capacity-4mb",
+        feature = "default-capacity-8mb",
+        feature = "default-capacity-16mb"
+    )))]
+    const DEFAULT_CAPACITY_MB: usize = 1;
+
+    /// Offset of the ESP image header of the bootloader.
+    #[cfg(not(any(feature = "esp32", feature = "esp32s2")))]
+    const IMAGE_HEADER_OFFSET: u32 = 0x0000;
+    #[cfg(any(feature = "esp32", feature = "esp32s2"))]
+    const IMAGE_HEADER_OFFSET: u32 = 0x1000;
+
+    const IMAGE_MAGIC: u8 = 0xe9;
+
+    /// Creates a new instance, detecting the size of the flash.
+    ///
+    /// See [`FlashStorage::capacity`] for how the size is detected.
     pub fn new() -> FlashStorage {
-        let mut storage = FlashStorage {
-            capacity: 0,
-            unlocked: false,
-        };
+        // Allow the probes to read anywhere
+        let mut storage = Self::new_with_capacity(Self::MAX_CAPACITY);
 
-        #[cfg(not(any(feature = "esp32", feature = "esp32s2")))]
-        const ADDR: u32 = 0x0000;
-        #[cfg(any(feature = "esp32", feature = "esp32s2"))]
-        const ADDR: u32 = 0x1000;
+        let (capacity, source) = storage
+            .probe_jedec_id()
+            .map(|capacity| (capacity, CapacitySource::JedecId))
+            .or_else(|| {
+                storage
+                    .probe_image_header()
+                    .map(|capacity| (capacity, CapacitySource::ImageHeader))
+            })
+            .or_else(|| {
+                storage
+                    .probe_partition_table()
+                    .map(|capacity| (capacity, CapacitySource::PartitionTable))
+            })
+            .unwrap_or((Self::DEFAULT_CAPACITY_MB << 20, CapacitySource::Default));
+
+        storage.capacity = capacity;
+        storage.capacity_source = source;
+        storage
+    }
 
-        let mut buffer = [0u8; 8];
-        storage.internal_read(ADDR, &mut buffer).ok();
-        let mb = match buffer[3] & 0xf0 {
+    fn probe_jedec_id(&mut self) -> Option<usize> {
+        let id = self.read_jedec_id().ok()?;
+        let capacity = FlashChipInfo::from_jedec_id(id).capacity_bytes;
+        Self::JEDEC_CAPACITY_RANGE
+            .contains(&capacity)
+            .then_some(capacity)
+    }
+
+    fn probe_image_header(&mut self) -> Option<usize> {
+        let mut header = [0u32; 2];
+        self.read_words(Self::IMAGE_HEADER_OFFSET, &mut header)
+            .ok()?;
+        let header = header[0].to_le_bytes();
+
+        // Direct boot images and other bootloaders don't start with the
+        // header, so don't interpret whatever is there instead
+        if header[0] != Self::IMAGE_MAGIC {
+            return None;
+        }
+        Self::header_capacity(header[3]).map(|capacity| capacity.min(Self::MAX_CAPACITY))
+    }
+
+    fn probe_partition_table(&mut self) -> Option<usize> {
+        let end = crate::partitions::highest_end(self, crate::partitions::PARTITION_TABLE_OFFSET)?
+            as usize;
+        let capacity = end.next_power_of_two().max(1 << 20);
+        (capacity <= Self::MAX_CAPACITY).then_some(capacity)
+    }
+
+    /// Decodes the flash size from the size nibble of the image header.
+    fn header_capacity(size_byte: u8) -> Option<usize> {
+        let mb = match size_byte & 0xf0 {
             0x00 => 1,
             0x10 => 2,
             0x20 => 4,
             0x30 => 8,
             0x40 => 16,
             0x50 => 32,
-            _ => 0,
+            0x60 => 64,
+            0x70 => 128,
+            _ => return None,
         };
-        storage.capacity = mb * 1024 * 1024;
+        Some(mb * 1024 * 1024)
+    }
 
-        storage
+    /// Creates a new instance for a flash of `capacity` bytes.
+    ///
+    /// Unlike [`FlashStorage::new`], this doesn't probe the flash chip or the
+    /// image header at all, which is useful for boot flows without a second
+    /// stage bootloader.
+    pub fn new_with_capacity(capacity: usize) -> FlashStorage {
+        FlashStorage {
+            capacity,
+            capacity_source: CapacitySource::Configured,
+            unlocked: false,
+            verify: false,
+            diff_write: false,
+            yield_fn: None,
+            erase_count: 0,
+            program_count: 0,
+            skipped_count: 0,
+        }
+    }
+
+    /// Overrides the size of the flash in bytes.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.capacity_source = CapacitySource::Configured;
+    }
+
+    /// Reads the JEDEC ID of the flash chip.
+    ///
+    /// The ID is returned as `manufacturer << 16 | memory_type << 8 |
+    /// capacity_code`. Fails with [`FlashStorageError::IoError`] if the chip
+    /// doesn't answer.
+    pub fn read_jedec_id(&mut self) -> Result<u32, FlashStorageError> {
+        match chip_specific::spiflash_read_id() {
+            0 | 0xff_ffff => Err(FlashStorageError::IoError(FlashOperation::ReadId)),
+            id => Ok(id),
+        }
+    }
+
+    /// Reads the factory-programmed 64 bit unique ID of the flash chip.
+    ///
+    /// This uses the Read Unique ID (`0x4b`) command, which is supported by
+    /// most, but not all, SPI NOR flash chips. Fails with
+    /// [`FlashStorageError::Unsupported`] if the chip doesn't answer the
+    /// command.
+    pub fn unique_id(&mut self) -> Result<[u8; 8], FlashStorageError> {
+        let id = chip_specific::spiflash_read_unique_id();
+
+        // Chips without the command leave the data lines idle
+        if id == [0x00; 8] || id == [0xff; 8] {
+            return Err(FlashStorageError::Unsupported);
+        }
+        Ok(id)
+    }
+
+    /// Reads the JEDEC ID of the flash chip and decodes it.
+    pub fn chip_info(&mut self) -> Result<FlashChipInfo, FlashStorageError> {
+        self.read_jedec_id().map(FlashChipInfo::from_jedec_id)
+    }
+
+    /// Enables or disables read-back verification.
+    ///
+    /// When enabled, every write is followed by reading back the written range
+    /// and comparing it to the data, and every erase is followed by checking
+    /// that the sector reads as `0xff`. A mismatch is reported as
+    /// [`FlashStorageError::VerifyFailed`] with the offset of the first
+    /// differing word.
+    ///
+    /// Note that writing can only flip bits from `1` to `0`, so writing over
+    /// data which wasn't erased first fails verification unless the result
+    /// matches the written data.
+    ///
+    /// Verification is disabled by default as it roughly doubles the time
+    /// needed for writing.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Enables or disables diff writes of whole sectors.
+    ///
+    /// When enabled, [`FlashStorage::write_sector`],
+    /// [`FlashStorage::write_sector_uninit`] and `Storage::write` compare the
+    /// new contents of a sector with the current ones first:
+    ///
+    /// - If they are identical, nothing is done.
+    /// - If the new contents only clear bits, the sector is programmed without
+    ///   erasing it.
+    /// - Otherwise the sector is erased and written as usual.
+    ///
+    /// This saves erase cycles and time for data which rarely changes, at the
+    /// cost of reading the sector once more. The result is the same either
+    /// way, so this can be disabled again whenever the sector has to be
+    /// erased, e.g. if the flash chip doesn't allow programming a page twice.
+    ///
+    /// Diff writes are disabled by default.
+    pub fn set_diff_write(&mut self, enabled: bool) {
+        self.diff_write = enabled;
+    }
+
+    /// Returns the number of sectors erased by this instance. A block erase
+    /// counts as all of its sectors.
+    pub fn erase_count(&self) -> u32 {
+        self.erase_count
+    }
+
+    /// Returns the number of program operations done by this instance.
+    ///
+    /// Every piece of a write, see [`FlashStorage::set_yield_fn`], is a
+    /// separate program operation.
+    pub fn program_count(&self) -> u32 {
+        self.program_count
+    }
+
+    /// Returns the number of sector writes skipped because the sector already
+    /// held the data, see [`FlashStorage::set_diff_write`].
+    pub fn skipped_count(&self) -> u32 {
+        self.skipped_count
+    }
+
+    /// Resets the erase, program and skipped counters to 0.
+    pub fn reset_counters(&mut self) {
+        self.erase_count = 0;
+        self.program_count = 0;
+        self.skipped_count = 0;
+    }
+
+    /// Sets a function which is called between the pieces of a long erase or
+    /// write.
+    ///
+    /// Erases and writes spanning more than a sector are split into pieces of
+    /// at most a sector, each done by a separate ROM call. The cache is
+    /// enabled again and interrupts are serviced after every piece, and then
+    /// `yield_fn` is called. This is the place to feed watchdogs or to poll
+    /// peripherals which can't wait for the whole operation.
+    ///
+    /// With a yield function set, [`FlashStorage::erase`] no longer erases
+    /// whole blocks, so no single step blocks for longer than a sector erase.
+    ///
+    /// `yield_fn` must not access the flash, neither directly nor through
+    /// another [`FlashStorage`], as it runs in the middle of an operation
+    /// which left the flash partially erased or written.
+    pub fn set_yield_fn(&mut self, yield_fn: fn()) {
+        self.yield_fn = Some(yield_fn);
+    }
+
+    /// Enables the auto-suspend feature of the flash.
+    ///
+    /// Once enabled, a pending cache access suspends an ongoing erase or
+    /// write, which is resumed after the access. Interrupts are then no
+    /// longer disabled during flash operations, and the other core isn't
+    /// stalled either, so code running from flash keeps executing. The worst
+    /// case interrupt latency drops from the duration of an erase to the time
+    /// it takes to suspend it.
+    ///
+    /// This is supported on the ESP32-C3, ESP32-C6, ESP32-H2 and ESP32-S3,
+    /// [`FlashStorageError::Unsupported`] is returned on other chips. The flash
+    /// chip has to support the common suspend (`0x75`) and resume (`0x7a`)
+    /// commands, and report the suspended state in bit 7 of its second
+    /// status register, like most current chips do.
+    ///
+    /// The setting applies to the SPI1 peripheral, so it affects all
+    /// instances and can't be undone. Interrupt handlers must still not
+    /// access the flash themselves.
+    pub fn enable_auto_suspend(&mut self) -> Result<(), FlashStorageError> {
+        if !chip_specific::spiflash_enable_auto_suspend() {
+            return Err(FlashStorageError::Unsupported);
+        }
+        crate::AUTO_SUSPEND.store(true, core::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the size of the flash in bytes.
+    ///
+    /// [`FlashStorage::new`] tries these sources in order and uses the first
+    /// one which gives a plausible size:
+    ///
+    /// 1. The JEDEC ID reported by the flash chip.
+    /// 2. The flash size in the image header of the software bootloader, which
+    ///    is written during flashing in espflash / esptool. Images without the
+    ///    header, e.g. direct boot images or other bootloaders, are skipped.
+    /// 3. The end of the last partition in the partition table, rounded up to
+    ///    the next power of two.
+    /// 4. The default selected by the `default-capacity-2mb`,
+    ///    `default-capacity-4mb`, `default-capacity-8mb` or
+    ///    `default-capacity-16mb` feature, or 1 MB.
+    ///
+    /// [`FlashStorage::capacity_source`] tells which of them was used. Use
+    /// [`FlashStorage::new_with_capacity`] or [`FlashStorage::set_capacity`]
+    /// to override the detected size.
+    ///
+    /// Above 16 MB, the flash is accessed with the dedicated 4 byte address
+    /// commands, so the chip has to support those (`0x0c`, `0x12`, `0x21`
+    /// and `0xdc`). Octal flash isn't supported. The ESP32 can't address
+    /// more than 16 MB, so the size is capped there.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns where the size returned by [`FlashStorage::capacity`] was
+    /// taken from.
+    pub fn capacity_source(&self) -> CapacitySource {
+        self.capacity_source
+    }
+
+    /// Re-enables the write protection of the flash.
+    ///
+    /// The write protection is disabled automatically by the first write or
+    /// erase, and stays disabled until this is called. The next write or
+    /// erase disables it again.
+    pub fn lock(&mut self) -> Result<(), FlashStorageError> {
+        if chip_specific::spiflash_lock() != 0 {
+            return Err(FlashStorageError::CantLock);
+        }
+        self.unlocked = false;
+        Ok(())
+    }
+
+    /// Disables the write protection of the flash, runs `f` and re-enables
+    /// the write protection afterwards, even if `f` failed.
+    ///
+    /// This keeps the window in which the flash is writable as small as
+    /// possible.
+    pub fn writable<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, FlashStorageError>,
+    ) -> Result<R, FlashStorageError> {
+        self.unlock_once()?;
+        let result = f(self);
+        let locked = self.lock();
+
+        let value = result?;
+        locked?;
+        Ok(value)
     }
 
-    #[cfg(feature = "nor-flash")]
     #[inline(always)]
     pub(crate) fn check_alignment<const ALIGN: u32>(
         &self,
         offset: u32,
         length: usize,
     ) -> Result<(), FlashStorageError> {
-        let offset = offset as usize;
-        if offset % ALIGN as usize != 0 || length % ALIGN as usize != 0 {
-            return Err(FlashStorageError::NotAligned);
+        if offset % ALIGN != 0 || length % ALIGN as usize != 0 {
+            return Err(FlashStorageError::NotAligned {
+                offset,
+                length,
+                alignment: ALIGN,
+            });
         }
         Ok(())
     }
 
     #[inline(always)]
     pub(crate) fn check_bounds(&self, offset: u32, length: usize) -> Result<(), FlashStorageError> {
-        let offset = offset as usize;
-        if length > self.capacity || offset > self.capacity - length {
-            return Err(FlashStorageError::OutOfBounds);
+        if length > self.capacity || offset as usize > self.capacity - length {
+            return Err(FlashStorageError::OutOfBounds {
+                offset,
+                length,
+                capacity: self.capacity,
+            });
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn is_word_aligned(bytes: &[u8]) -> bool {
+        // TODO: Use is_aligned_to when stabilized (see `pointer_is_aligned`)
+        (unsafe { bytes.as_ptr().offset_from(core::ptr::null()) }) % Self::WORD_SIZE as isize == 0
+    }
+
+    /// Reads `bytes.len()` bytes starting at `offset`.
+    ///
+    /// Neither the offset nor the length need to be word-aligned, the
+    /// unaligned head and tail are read through a word-sized buffer.
+    pub fn read(&mut self, mut offset: u32, mut bytes: &mut [u8]) -> Result<(), FlashStorageError> {
+        self.check_bounds(offset, bytes.len())?;
+
+        let mut word_buffer = MaybeUninit::<FlashWordBuffer>::uninit();
+        let word_buffer = unsafe { word_buffer.assume_init_mut() };
+
+        // Read the unaligned head
+        let byte_offset = (offset % Self::WORD_SIZE) as usize;
+        if byte_offset > 0 {
+            let aligned_offset = offset - byte_offset as u32;
+            let length = bytes.len().min(word_buffer.len() - byte_offset);
+
+            self.internal_read(aligned_offset, &mut word_buffer[..])?;
+            bytes[..length].copy_from_slice(&word_buffer[byte_offset..][..length]);
+
+            offset = aligned_offset + Self::WORD_SIZE;
+            bytes = &mut bytes[length..];
+        }
+
+        // Read the aligned body
+        let length = bytes.len() - bytes.len() % Self::WORD_SIZE as usize;
+        let (body, tail) = bytes.split_at_mut(length);
+        self.read_aligned_unchecked(offset, body)?;
+
+        // Read the unaligned tail
+        if !tail.is_empty() {
+            self.internal_read(offset + length as u32, &mut word_buffer[..])?;
+            tail.copy_from_slice(&word_buffer[..tail.len()]);
+        }
+
+        Ok(())
+    }
+
+    /// Reads `bytes.len()` bytes starting at `offset`.
+    ///
+    /// Both `offset` and `bytes.len()` must be a multiple of
+    /// [`Self::WORD_SIZE`], otherwise [`FlashStorageError::NotAligned`] is
+    /// returned.
+    pub fn read_aligned(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), FlashStorageError> {
+        self.check_alignment::<{ Self::WORD_SIZE }>(offset, bytes.len())?;
+        self.check_bounds(offset, bytes.len())?;
+
+        self.read_aligned_unchecked(offset, bytes)
+    }
+
+    /// Reads `words.len()` words starting at `offset`.
+    ///
+    /// `offset` must be a multiple of [`Self::WORD_SIZE`], otherwise
+    /// [`FlashStorageError::NotAligned`] is returned. The words are read
+    /// directly into `words`, each of them holding the little endian value of
+    /// 4 bytes of flash.
+    pub fn read_words(&mut self, offset: u32, words: &mut [u32]) -> Result<(), FlashStorageError> {
+        // A `u32` slice is always word-aligned, so the bytes are read in-place
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(words.as_mut_ptr().cast::<u8>(), size_of_val(words))
+        };
+        self.read_aligned(offset, bytes)
+    }
+
+    fn read_aligned_unchecked(
+        &mut self,
+        offset: u32,
+        bytes: &mut [u8],
+    ) -> Result<(), FlashStorageError> {
+        if Self::is_word_aligned(bytes) {
+            // Bytes buffer is word-aligned so we can read directly to it
+            for (offset, chunk) in (offset..)
+                .step_by(Self::SECTOR_SIZE as _)
+                .zip(bytes.chunks_mut(Self::SECTOR_SIZE as _))
+            {
+                self.internal_read(offset, chunk)?;
+            }
+        } else {
+            // Bytes buffer isn't word-aligned so we might read only via aligned buffer
+            let mut buffer = MaybeUninit::<FlashSectorBuffer>::uninit();
+            let buffer = unsafe { buffer.assume_init_mut() };
+
+            for (offset, chunk) in (offset..)
+                .step_by(Self::SECTOR_SIZE as _)
+                .zip(bytes.chunks_mut(Self::SECTOR_SIZE as _))
+            {
+                // Read to temporary buffer first
+                self.internal_read(offset, &mut buffer[..chunk.len()])?;
+                // Copy to bytes buffer
+                chunk.copy_from_slice(&buffer[..chunk.len()]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes `bytes` starting at `offset` without erasing.
+    ///
+    /// Neither the offset nor the length need to be word-aligned. The words
+    /// surrounding an unaligned head or tail are read back first, so the
+    /// neighbouring bytes are written with their current contents and are
+    /// left untouched.
+    ///
+    /// As with any NOR flash, writing can only flip bits from `1` to `0`.
+    ///
+    /// The data is always stored unencrypted. If flash encryption is enabled,
+    /// data written by this reads back as garbage through the cache, use
+    /// [`FlashStorage::write_encrypted`] for data which is read that way.
+    pub fn write(&mut self, mut offset: u32, mut bytes: &[u8]) -> Result<(), FlashStorageError> {
+        self.check_bounds(offset, bytes.len())?;
+
+        let mut word_buffer = MaybeUninit::<FlashWordBuffer>::uninit();
+        let word_buffer = unsafe { word_buffer.assume_init_mut() };
+
+        // Read-modify-write the unaligned head
+        let byte_offset = (offset % Self::WORD_SIZE) as usize;
+        if byte_offset > 0 {
+            let aligned_offset = offset - byte_offset as u32;
+            let length = bytes.len().min(word_buffer.len() - byte_offset);
+
+            self.internal_read(aligned_offset, &mut word_buffer[..])?;
+            word_buffer[byte_offset..][..length].copy_from_slice(&bytes[..length]);
+            self.internal_write(aligned_offset, &word_buffer[..])?;
+
+            offset = aligned_offset + Self::WORD_SIZE;
+            bytes = &bytes[length..];
+        }
+
+        // Write the aligned body
+        let length = bytes.len() - bytes.len() % Self::WORD_SIZE as usize;
+        let (body, tail) = bytes.split_at(length);
+        self.write_aligned_unchecked(offset, body)?;
+
+        // Read-modify-write the unaligned tail
+        if !tail.is_empty() {
+            let offset = offset + length as u32;
+
+            self.internal_read(offset, &mut word_buffer[..])?;
+            word_buffer[..tail.len()].copy_from_slice(tail);
+            self.internal_write(offset, &word_buffer[..])?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `bytes` starting at `offset` without erasing.
+    ///
+    /// Both `offset` and `bytes.len()` must be a multiple of
+    /// [`Self::WORD_SIZE`], otherwise [`FlashStorageError::NotAligned`] is
+    /// returned. Like [`FlashStorage::write`], this doesn't encrypt the data.
+    pub fn write_aligned(&mut self, offset: u32, bytes: &[u8]) -> Result<(), FlashStorageError> {
+        self.check_alignment::<{ Self::WORD_SIZE }>(offset, bytes.len())?;
+        self.check_bounds(offset, bytes.len())?;
+
+        self.write_aligned_unchecked(offset, bytes)
+    }
+
+    /// Writes `words` starting at `offset` without erasing.
+    ///
+    /// `offset` must be a multiple of [`Self::WORD_SIZE`], otherwise
+    /// [`FlashStorageError::NotAligned`] is returned. This is the counterpart
+    /// of [`FlashStorage::read_words`] and writes straight from `words`.
+    pub fn write_words(&mut self, offset: u32, words: &[u32]) -> Result<(), FlashStorageError> {
+        let bytes =
+            unsafe { core::slice::from_raw_parts(words.as_ptr().cast::<u8>(), size_of_val(words)) };
+        self.write_aligned(offset, bytes)
+    }
+
+    /// Returns whether flash encryption is enabled.
+    ///
+    /// Flash encryption is enabled if an odd number of bits is set in the
+    /// `SPI_BOOT_CRYPT_CNT` eFuse (`FLASH_CRYPT_CNT` on the ESP32).
+    pub fn encryption_enabled(&self) -> bool {
+        chip_specific::flash_crypt_cnt().count_ones() % 2 == 1
+    }
+
+    /// Encrypts `bytes` with the flash encryption key and writes them starting
+    /// at `offset` without erasing.
+    ///
+    /// Both `offset` and `bytes.len()` must be a multiple of
+    /// [`Self::ENCRYPTED_WRITE_SIZE`], otherwise
+    /// [`FlashStorageError::NotAligned`] is returned. The range should be
+    /// erased before, as the encrypted data can't be written over existing
+    /// data.
+    ///
+    /// The data reads back decrypted only through the cache, i.e. from
+    /// memory mapped flash. [`FlashStorage::read`] returns the encrypted data
+    /// as stored, which is also why read-back verification is not done for
+    /// encrypted writes.
+    ///
+    /// Encrypted writes above 16 MB aren't supported and fail with
+    /// [`FlashStorageError::Unsupported`].
+    pub fn write_encrypted(&mut self, offset: u32, bytes: &[u8]) -> Result<(), FlashStorageError> {
+        self.check_alignment::<{ Self::ENCRYPTED_WRITE_SIZE }>(offset, bytes.len())?;
+        self.check_bounds(offset, bytes.len())?;
+        // The ROM function only sends 24 bit addresses
+        #[cfg(not(feature = "esp32"))]
+        if Self::needs_4b_address(offset, bytes.len() as u32) {
+            return Err(FlashStorageError::Unsupported);
+        }
+
+        let mut buffer = MaybeUninit::<FlashEncryptedBuffer>::uninit();
+        let buffer = unsafe { buffer.assume_init_mut() };
+
+        for (offset, chunk) in (offset..)
+            .step_by(Self::ENCRYPTED_WRITE_SIZE as _)
+            .zip(bytes.chunks(Self::ENCRYPTED_WRITE_SIZE as _))
+        {
+            buffer.data.copy_from_slice(chunk);
+            self.internal_write_encrypted(offset, &buffer.data)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_aligned_unchecked(
+        &mut self,
+        offset: u32,
+        bytes: &[u8],
+    ) -> Result<(), FlashStorageError> {
+        if Self::is_word_aligned(bytes) {
+            // Bytes buffer is word-aligned so we can write directly from it
+            for (offset, chunk) in (offset..)
+                .step_by(Self::SECTOR_SIZE as _)
+                .zip(bytes.chunks(Self::SECTOR_SIZE as _))
+            {
+                self.internal_write(offset, chunk)?;
+                self.yield_now();
+            }
+        } else {
+            // Bytes buffer isn't word-aligned so we might write only via aligned buffer
+            let mut buffer = MaybeUninit::<FlashSectorBuffer>::uninit();
+            let buffer = unsafe { buffer.assume_init_mut() };
+
+            for (offset, chunk) in (offset..)
+                .step_by(Self::SECTOR_SIZE as _)
+                .zip(bytes.chunks(Self::SECTOR_SIZE as _))
+            {
+                // Copy to temporary buffer first
+                buffer[..chunk.len()].copy_from_slice(chunk);
+                // Write from temporary buffer
+                self.internal_write(offset, &buffer[..chunk.len()])?;
+                self.yield_now();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Erases the sector with the given number, setting all of its bytes to
+    /// `0xff`.
+    ///
+    /// Erasing blocks for the whole duration of the operation, which
+    /// typically takes around 50 ms per sector and may take a few hundred
+    /// milliseconds on some flash parts. Interrupts are not serviced in the
+    /// meantime if the `critical-section` feature is enabled.
+    pub fn erase_sector(&mut self, sector: u32) -> Result<(), FlashStorageError> {
+        self.sector_offset(sector)?;
+
+        self.internal_erase(sector)
+    }
+
+    /// Replaces the contents of the sector with the given number.
+    ///
+    /// The sector is read into `buffer`, `f` modifies it and the sector is
+    /// then erased and written with the contents of the buffer. Bytes which
+    /// `f` doesn't touch keep their current value.
+    ///
+    /// The sector is erased even if its contents didn't change, unless diff
+    /// writes are enabled with [`FlashStorage::set_diff_write`]. If power is
+    /// lost after erasing, the data is gone, so this shouldn't be used for
+    /// data which needs to survive that.
+    pub fn write_sector(
+        &mut self,
+        sector: u32,
+        buffer: &mut FlashSectorBuffer,
+        f: impl FnOnce(&mut [u8; Self::SECTOR_SIZE as usize]),
+    ) -> Result<(), FlashStorageError> {
+        let offset = self.sector_offset(sector)?;
+
+        self.internal_read(offset, &mut buffer[..])?;
+        f(buffer);
+        self.rewrite_sector(sector, buffer)
+    }
+
+    /// Like [`FlashStorage::write_sector`], but doesn't read the sector into
+    /// `buffer` first.
+    ///
+    /// `f` gets the buffer with whatever it contained before, so it has to
+    /// fill all of it.
+    pub fn write_sector_uninit(
+        &mut self,
+        sector: u32,
+        buffer: &mut FlashSectorBuffer,
+        f: impl FnOnce(&mut [u8; Self::SECTOR_SIZE as usize]),
+    ) -> Result<(), FlashStorageError> {
+        self.sector_offset(sector)?;
+
+        f(buffer);
+        self.rewrite_sector(sector, buffer)
+    }
+
+    fn sector_offset(&self, sector: u32) -> Result<u32, FlashStorageError> {
+        // An overflowing offset saturates and is then rejected as out of
+        // bounds
+        let offset = sector.saturating_mul(Self::SECTOR_SIZE);
+        self.check_bounds(offset, Self::SECTOR_SIZE as usize)?;
+        Ok(offset)
+    }
+
+    fn rewrite_sector(
+        &mut self,
+        sector: u32,
+        buffer: &FlashSectorBuffer,
+    ) -> Result<(), FlashStorageError> {
+        let offset = sector * Self::SECTOR_SIZE;
+
+        if self.diff_write {
+            match self.compare_sector(offset, buffer)? {
+                SectorDiff::Identical => {
+                    self.skipped_count = self.skipped_count.wrapping_add(1);
+                    return Ok(());
+                }
+                SectorDiff::ClearsBits => return self.internal_write(offset, &buffer[..]),
+                SectorDiff::NeedsErase => {}
+            }
+        }
+
+        self.internal_erase(sector)?;
+        self.internal_write(offset, &buffer[..])
+    }
+
+    /// Compares the sector at `offset` with `buffer`, reading it in small
+    /// chunks so no second sector buffer is needed.
+    fn compare_sector(
+        &mut self,
+        offset: u32,
+        buffer: &FlashSectorBuffer,
+    ) -> Result<SectorDiff, FlashStorageError> {
+        let mut chunk = MaybeUninit::<FlashVerifyBuffer>::uninit();
+        let chunk = unsafe { chunk.assume_init_mut() };
+
+        let mut diff = SectorDiff::Identical;
+        for (start, new) in (0..)
+            .step_by(Self::VERIFY_CHUNK_SIZE)
+            .zip(buffer.chunks(Self::VERIFY_CHUNK_SIZE))
+        {
+            self.internal_read(offset + start, &mut chunk.data)?;
+
+            for (old, new) in chunk.data.iter().zip(new) {
+                if new & !old != 0 {
+                    return Ok(SectorDiff::NeedsErase);
+                } else if old != new {
+                    diff = SectorDiff::ClearsBits;
+                }
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Erases the range `from..to`, setting all of its bytes to `0xff`.
+    ///
+    /// Both `from` and `to` must be a multiple of [`Self::SECTOR_SIZE`],
+    /// otherwise [`FlashStorageError::NotAligned`] is returned. The range is
+    /// erased one piece after another, stopping at the first error.
+    ///
+    /// Wherever the remaining range is aligned to and at least as long as
+    /// [`Self::BLOCK_SIZE`], a whole block is erased at once, which is
+    /// considerably faster than erasing its sectors one by one. The edges of
+    /// the range are erased sector by sector. Erasing a block typically takes
+    /// a few hundred milliseconds and may take up to about two seconds, see
+    /// [`Self::erase_sector`] for the time it takes to erase a sector. Each
+    /// block or sector is erased by a separate call, so interrupts are
+    /// serviced in between.
+    ///
+    /// If a yield function is set with [`FlashStorage::set_yield_fn`], the
+    /// range is erased sector by sector and the function is called after each
+    /// of them.
+    pub fn erase(&mut self, from: u32, to: u32) -> Result<(), FlashStorageError> {
+        let len = range_length(from, to, self.capacity)?;
+        self.check_alignment::<{ Self::SECTOR_SIZE }>(from, len)?;
+        self.check_bounds(from, len)?;
+
+        let mut offset = from;
+        while offset < to {
+            if self.yield_fn.is_none()
+                && offset % Self::BLOCK_SIZE == 0
+                && to - offset >= Self::BLOCK_SIZE
+            {
+                self.internal_erase_block(offset / Self::BLOCK_SIZE)?;
+                offset += Self::BLOCK_SIZE;
+            } else {
+                self.internal_erase(offset / Self::SECTOR_SIZE)?;
+                offset += Self::SECTOR_SIZE;
+            }
+            self.yield_now();
         }
+
         Ok(())
     }
 
@@ -118,11 +1091,36 @@ impl FlashStorage {
         offset: u32,
         bytes: &mut [u8],
     ) -> Result<(), FlashStorageError> {
-        check_rc(chip_specific::spiflash_read(
-            offset,
-            bytes.as_ptr() as *mut u32,
-            bytes.len() as u32,
-        ))
+        #[cfg(not(feature = "esp32"))]
+        if Self::needs_4b_address(offset, bytes.len() as u32) {
+            return check_rc(
+                chip_specific::spiflash_read_4b(
+                    offset,
+                    bytes.as_ptr() as *mut u32,
+                    bytes.len() as u32,
+                ),
+                FlashOperation::Read,
+            );
+        }
+
+        check_rc(
+            chip_specific::spiflash_read(offset, bytes.as_ptr() as *mut u32, bytes.len() as u32),
+            FlashOperation::Read,
+        )
+    }
+
+    #[cfg(not(feature = "esp32"))]
+    #[inline(always)]
+    fn needs_4b_address(offset: u32, length: u32) -> bool {
+        offset.saturating_add(length) > Self::ADDRESS_24BIT_LIMIT
+    }
+
+    /// Calls the yield function, if any. This must only be called between ROM
+    /// calls, as the function is likely to be placed in flash.
+    pub(crate) fn yield_now(&self) {
+        if let Some(yield_fn) = self.yield_fn {
+            yield_fn();
+        }
     }
 
     #[inline(always)]
@@ -141,7 +1139,53 @@ impl FlashStorage {
     pub(crate) fn internal_erase(&mut self, sector: u32) -> Result<(), FlashStorageError> {
         self.unlock_once()?;
 
-        check_rc(chip_specific::spiflash_erase_sector(sector))
+        #[cfg(not(feature = "esp32"))]
+        let rc = if Self::needs_4b_address(sector * Self::SECTOR_SIZE, Self::SECTOR_SIZE) {
+            chip_specific::spiflash_erase_sector_4b(sector)
+        } else {
+            chip_specific::spiflash_erase_sector(sector)
+        };
+        #[cfg(feature = "esp32")]
+        let rc = chip_specific::spiflash_erase_sector(sector);
+        check_rc(rc, FlashOperation::Erase)?;
+        self.erase_count = self.erase_count.wrapping_add(1);
+
+        if self.verify {
+            self.verify_range(
+                sector * Self::SECTOR_SIZE,
+                Self::SECTOR_SIZE as usize,
+                |_| ERASED_WORD,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[inline(never)]
+    #[link_section = ".rwtext"]
+    pub(crate) fn internal_erase_block(&mut self, block: u32) -> Result<(), FlashStorageError> {
+        self.unlock_once()?;
+
+        #[cfg(not(feature = "esp32"))]
+        let rc = if Self::needs_4b_address(block * Self::BLOCK_SIZE, Self::BLOCK_SIZE) {
+            chip_specific::spiflash_erase_block_4b(block)
+        } else {
+            chip_specific::spiflash_erase_block(block)
+        };
+        #[cfg(feature = "esp32")]
+        let rc = chip_specific::spiflash_erase_block(block);
+        check_rc(rc, FlashOperation::Erase)?;
+        self.erase_count = self
+            .erase_count
+            .wrapping_add(Self::BLOCK_SIZE / Self::SECTOR_SIZE);
+
+        if self.verify {
+            self.verify_range(block * Self::BLOCK_SIZE, Self::BLOCK_SIZE as usize, |_| {
+                ERASED_WORD
+            })?;
+        }
+
+        Ok(())
     }
 
     #[inline(never)]
@@ -153,10 +1197,448 @@ impl FlashStorage {
     ) -> Result<(), FlashStorageError> {
         self.unlock_once()?;
 
-        check_rc(chip_specific::spiflash_write(
-            offset,
-            bytes.as_ptr() as *const u32,
-            bytes.len() as u32,
-        ))
+        #[cfg(not(feature = "esp32"))]
+        let rc = if Self::needs_4b_address(offset, bytes.len() as u32) {
+            chip_specific::spiflash_write_4b(
+                offset,
+                bytes.as_ptr() as *const u32,
+                bytes.len() as u32,
+            )
+        } else {
+            chip_specific::spiflash_write(offset, bytes.as_ptr() as *const u32, bytes.len() as u32)
+        };
+        #[cfg(feature = "esp32")]
+        let rc =
+            chip_specific::spiflash_write(offset, bytes.as_ptr() as *const u32, bytes.len() as u32);
+        check_rc(rc, FlashOperation::Write)?;
+        self.program_count = self.program_count.wrapping_add(1);
+
+        if self.verify {
+            self.verify_range(offset, bytes.len(), |index| {
+                let mut word = [0u8; Self::WORD_SIZE as usize];
+                word.copy_from_slice(&bytes[index..][..Self::WORD_SIZE as usize]);
+                word
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[inline(never)]
+    #[link_section = ".rwtext"]
+    fn internal_write_encrypted(
+        &mut self,
+        offset: u32,
+        bytes: &[u8],
+    ) -> Result<(), FlashStorageError> {
+        self.unlock_once()?;
+
+        check_rc(
+            chip_specific::spiflash_write_encrypted(
+                offset,
+                bytes.as_ptr() as *const u32,
+                bytes.len() as u32,
+            ),
+            FlashOperation::Write,
+        )?;
+        self.program_count = self.program_count.wrapping_add(1);
+
+        Ok(())
+    }
+
+    /// Reads back `length` bytes starting at the word-aligned `offset`,
+    /// comparing every word to the one returned by `expected` for its index.
+    fn verify_range(
+        &mut self,
+        offset: u32,
+        length: usize,
+        expected: impl Fn(usize) -> [u8; Self::WORD_SIZE as usize],
+    ) -> Result<(), FlashStorageError> {
+        let mut buffer = MaybeUninit::<FlashVerifyBuffer>::uninit();
+        let buffer = unsafe { buffer.assume_init_mut() };
+
+        for start in (0..length).step_by(Self::VERIFY_CHUNK_SIZE) {
+            let chunk = &mut buffer.data[..(length - start).min(Self::VERIFY_CHUNK_SIZE)];
+            self.internal_read(offset + start as u32, chunk)?;
+
+            for (index, word) in chunk.chunks(Self::WORD_SIZE as usize).enumerate() {
+                let index = start + index * Self::WORD_SIZE as usize;
+                if word != expected(index) {
+                    return Err(FlashStorageError::VerifyFailed {
+                        offset: offset + index as u32,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    // Use a sector which isn't touched by the other tests
+    const BASE: u32 = FlashStorage::SECTOR_SIZE * 10;
+
+    #[test]
+    fn chip_info_from_jedec_id() {
+        // Winbond W25Q128
+        assert_eq!(
+            FlashChipInfo::from_jedec_id(0xef4018),
+            FlashChipInfo {
+                manufacturer: 0xef,
+                device_id: 0x4018,
+                capacity_bytes: 16 * 1024 * 1024,
+            }
+        );
+        assert_eq!(FlashChipInfo::from_jedec_id(0xc840ff).capacity_bytes, 0);
+    }
+
+    #[test]
+    fn emulated_flash_has_no_jedec_id() {
+        let mut flash = FlashStorage::new();
+        assert!(matches!(
+            flash.read_jedec_id(),
+            Err(FlashStorageError::IoError(FlashOperation::ReadId))
+        ));
+    }
+
+    #[test]
+    fn error_display_names_operation() {
+        assert_eq!(
+            FlashStorageError::IoTimeout(FlashOperation::Erase).to_string(),
+            "Flash erase timed out"
+        );
+        assert_eq!(
+            FlashStorageError::Other(FlashOperation::Write, 5).to_string(),
+            "Flash write failed with error code 5"
+        );
+    }
+
+    #[test]
+    fn errors_describe_the_access() {
+        let mut flash = FlashStorage::new_with_capacity(2 * FlashStorage::SECTOR_SIZE as usize);
+
+        let error = flash.read_aligned(0x1002, &mut [0u8; 8]).unwrap_err();
+        assert!(matches!(
+            error,
+            FlashStorageError::NotAligned {
+                offset: 0x1002,
+                length: 8,
+                alignment: 4,
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "The access of 8 bytes at offset 0x1002 isn't aligned to 4 bytes"
+        );
+
+        let error = flash.read(0x1ffc, &mut [0u8; 8]).unwrap_err();
+        assert!(matches!(
+            error,
+            FlashStorageError::OutOfBounds {
+                offset: 0x1ffc,
+                length: 8,
+                capacity: 0x2000,
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "The access of 8 bytes at offset 0x1ffc exceeds the capacity of 8192 bytes"
+        );
+    }
+
+    #[test]
+    fn writable_relocks() {
+        let mut flash = FlashStorage::new();
+
+        flash
+            .writable(|flash| {
+                assert!(flash.unlocked);
+                flash.erase_sector(11)
+            })
+            .unwrap();
+        assert!(!flash.unlocked);
+
+        // The error of the closure is returned, and the flash is locked anyway
+        assert!(matches!(
+            flash.writable(|flash| flash.erase_sector(u32::MAX)),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+        assert!(!flash.unlocked);
+    }
+
+    #[test]
+    fn write_encrypted_alignment() {
+        const BASE: u32 = FlashStorage::SECTOR_SIZE * 12;
+
+        let mut flash = FlashStorage::new();
+        assert!(!flash.encryption_enabled());
+
+        flash
+            .erase_sector(BASE / FlashStorage::SECTOR_SIZE)
+            .unwrap();
+        assert!(matches!(
+            flash.write_encrypted(BASE + 16, &[0; 32]),
+            Err(FlashStorageError::NotAligned { .. })
+        ));
+        assert!(matches!(
+            flash.write_encrypted(BASE, &[0; 48]),
+            Err(FlashStorageError::NotAligned { .. })
+        ));
+
+        // The emulated flash doesn't encrypt
+        let data: [u8; 64] = core::array::from_fn(|i| i as u8);
+        flash.write_encrypted(BASE, &data).unwrap();
+        let mut read = [0u8; 64];
+        flash.read(BASE, &mut read).unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn header_capacity_decodes_all_sizes() {
+        for (nibble, mb) in [1, 2, 4, 8, 16, 32, 64, 128].into_iter().enumerate() {
+            assert_eq!(
+                FlashStorage::header_capacity((nibble as u8) << 4 | 0x0f),
+                Some(mb * 1024 * 1024)
+            );
+        }
+        assert_eq!(FlashStorage::header_capacity(0x80), None);
+    }
+
+    #[test]
+    fn capacity_falls_back_to_default() {
+        // The emulated flash has neither a JEDEC ID nor an image header, and
+        // the partition table at 0x8000 is missing or overwritten by the NVS
+        // test data
+        let mut flash = FlashStorage::new();
+        assert_eq!(flash.capacity(), 1024 * 1024);
+        assert_eq!(flash.capacity_source(), CapacitySource::Default);
+
+        flash.set_capacity(2 * 1024 * 1024);
+        assert_eq!(flash.capacity_source(), CapacitySource::Configured);
+    }
+
+    #[test]
+    fn capacity_override() {
+        let mut flash = FlashStorage::new_with_capacity(2 * FlashStorage::SECTOR_SIZE as usize);
+        assert_eq!(flash.capacity(), 2 * FlashStorage::SECTOR_SIZE as usize);
+        assert!(matches!(
+            flash.erase_sector(2),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+
+        flash.set_capacity(0);
+        assert!(matches!(
+            flash.read(0, &mut [0u8; 4]),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn emulated_flash_has_no_auto_suspend() {
+        let mut flash = FlashStorage::new();
+        assert!(matches!(
+            flash.enable_auto_suspend(),
+            Err(FlashStorageError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn emulated_flash_has_no_unique_id() {
+        let mut flash = FlashStorage::new();
+        assert!(matches!(
+            flash.unique_id(),
+            Err(FlashStorageError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn verify_detects_mismatch() {
+        let mut flash = FlashStorage::new();
+        flash.set_verify(true);
+
+        flash.erase(BASE, BASE + FlashStorage::SECTOR_SIZE).unwrap();
+        flash.write(BASE, &[0x0f; 64]).unwrap();
+        flash.write(BASE + 64, &[0x5a; 3]).unwrap();
+
+        // Bits which are already `0` can't be set by writing
+        assert!(matches!(
+            flash.write(BASE + 40, &[0xf0; 8]),
+            Err(FlashStorageError::VerifyFailed { offset }) if offset == BASE + 40
+        ));
+
+        // Without verification, the same write goes through
+        flash.set_verify(false);
+        flash.write(BASE + 40, &[0xf0; 8]).unwrap();
+    }
+
+    #[test]
+    fn words_round_trip() {
+        // Use a sector which isn't touched by the other tests
+        const BASE: u32 = FlashStorage::SECTOR_SIZE * 15;
+
+        let mut flash = FlashStorage::new();
+        flash.erase(BASE, BASE + FlashStorage::SECTOR_SIZE).unwrap();
+
+        let records = [0x1122_3344, 0x5566_7788, 0x99aa_bbcc, 0xddee_ff00];
+        flash.write_words(BASE + 16, &records).unwrap();
+
+        let mut words = [0u32; 4];
+        flash.read_words(BASE + 16, &mut words).unwrap();
+        assert_eq!(words, records);
+
+        // Words are stored little endian
+        let mut bytes = [0u8; 4];
+        flash.read(BASE + 16, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x44, 0x33, 0x22, 0x11]);
+
+        assert!(matches!(
+            flash.write_words(BASE + 2, &records),
+            Err(FlashStorageError::NotAligned { .. })
+        ));
+        let capacity = flash.capacity() as u32;
+        assert!(matches!(
+            flash.read_words(capacity - 8, &mut words),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn write_sector_keeps_untouched_bytes() {
+        // Use a sector which isn't touched by the other tests
+        const SECTOR: u32 = 48;
+        const BASE: u32 = FlashStorage::SECTOR_SIZE * SECTOR;
+
+        let mut flash = FlashStorage::new();
+        let mut buffer = FlashSectorBuffer::new();
+
+        flash
+            .write_sector_uninit(SECTOR, &mut buffer, |data| data[..16].fill(0x5a))
+            .unwrap();
+        flash
+            .write_sector(SECTOR, &mut buffer, |data| data[4..8].fill(0x00))
+            .unwrap();
+
+        let mut bytes = [0u8; 12];
+        flash.read(BASE, &mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            [0x5a, 0x5a, 0x5a, 0x5a, 0, 0, 0, 0, 0x5a, 0x5a, 0x5a, 0x5a]
+        );
+
+        let sectors = (flash.capacity() / FlashStorage::SECTOR_SIZE as usize) as u32;
+        assert!(matches!(
+            flash.write_sector(sectors, &mut buffer, |_| {}),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn diff_write_skips_erases() {
+        // Use a sector which isn't touched by the other tests
+        const SECTOR: u32 = 49;
+        const BASE: u32 = FlashStorage::SECTOR_SIZE * SECTOR;
+
+        let mut flash = FlashStorage::new();
+        let mut buffer = FlashSectorBuffer::new();
+        flash.set_diff_write(true);
+        flash.erase_sector(SECTOR).unwrap();
+        flash.reset_counters();
+
+        // Unchanged data isn't written at all
+        flash
+            .write_sector(SECTOR, &mut buffer, |data| data[..16].fill(0xff))
+            .unwrap();
+        assert_eq!(
+            (
+                flash.erase_count(),
+                flash.program_count(),
+                flash.skipped_count()
+            ),
+            (0, 0, 1)
+        );
+
+        // Clearing bits only programs
+        flash
+            .write_sector(SECTOR, &mut buffer, |data| data[..16].fill(0xf0))
+            .unwrap();
+        assert_eq!(
+            (
+                flash.erase_count(),
+                flash.program_count(),
+                flash.skipped_count()
+            ),
+            (0, 1, 1)
+        );
+
+        // Setting bits needs an erase
+        flash
+            .write_sector(SECTOR, &mut buffer, |data| {
+                data[4..6].copy_from_slice(&[0xff, 0x0f])
+            })
+            .unwrap();
+        assert_eq!(
+            (
+                flash.erase_count(),
+                flash.program_count(),
+                flash.skipped_count()
+            ),
+            (1, 2, 1)
+        );
+
+        let mut bytes = [0u8; 8];
+        flash.read(BASE, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xf0, 0xf0, 0xf0, 0xf0, 0xff, 0x0f, 0xf0, 0xf0]);
+
+        // Without diff writes, the sector is always erased
+        flash.set_diff_write(false);
+        flash.write_sector(SECTOR, &mut buffer, |_| {}).unwrap();
+        assert_eq!(flash.erase_count(), 2);
+    }
+
+    #[test]
+    fn erase_uses_blocks_and_sectors() {
+        // Sectors 16 to 47 aren't touched by the other tests, block 2 starts at
+        // sector 32
+        const FROM: u32 = FlashStorage::SECTOR_SIZE * 17;
+        const TO: u32 = FlashStorage::SECTOR_SIZE * 48;
+
+        let mut flash = FlashStorage::new();
+        flash.set_verify(true);
+
+        flash.erase(FROM - FlashStorage::SECTOR_SIZE, TO).unwrap();
+        flash
+            .write(FROM - FlashStorage::SECTOR_SIZE, &[0u8; 16])
+            .unwrap();
+        flash.write(FROM, &[0u8; 16]).unwrap();
+        flash.write(TO - 16, &[0u8; 16]).unwrap();
+
+        flash.erase(FROM, TO).unwrap();
+
+        let mut bytes = [0u8; 16];
+        flash
+            .read(FROM - FlashStorage::SECTOR_SIZE, &mut bytes)
+            .unwrap();
+        assert_eq!(bytes, [0u8; 16]);
+        flash.read(FROM, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff; 16]);
+        flash.read(TO - 16, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff; 16]);
+
+        // With a yield function, the block is erased sector by sector as well
+        static YIELDS: AtomicUsize = AtomicUsize::new(0);
+        flash.set_yield_fn(|| {
+            YIELDS.fetch_add(1, Ordering::Relaxed);
+        });
+        flash.erase(FROM, TO).unwrap();
+        assert_eq!(
+            YIELDS.load(Ordering::Relaxed),
+            ((TO - FROM) / FlashStorage::SECTOR_SIZE) as usize
+        );
     }
 }
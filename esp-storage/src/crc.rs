@@ -0,0 +1,24 @@
+//! CRC32 as used by the ESP-IDF on-flash formats.
+
+/// Computes a CRC32 the same way as the ROM function `esp_rom_crc32_le`.
+pub(crate) fn crc32_le(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_rom() {
+        // `esp_rom_crc32_le(0, ..)` computes the standard CRC32
+        assert_eq!(crc32_le(0, b"123456789"), 0xcbf4_3926);
+    }
+}
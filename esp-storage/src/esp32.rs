@@ -1,4 +1,7 @@
-use crate::maybe_with_critical_section;
+use crate::{
+    maybe_with_critical_section,
+    spi_command::{self, SpiRegisters},
+};
 
 const SPI_BASE_REG: u32 = 0x3ff42000; // SPI peripheral 1, used for SPI flash
 const SPI0_BASE_REG: u32 = 0x3ff43000; // SPI peripheral 0, inner state machine
@@ -20,16 +23,35 @@ const SPI_USR_ADDR_BITLEN_S: u32 = 26;
 const SPI_FLASH_WREN: u32 = 1 << 30;
 const STATUS_WIP_BIT: u32 = 1 << 0;
 const STATUS_QIE_BIT: u32 = 1 << 9; // Quad Enable
+const STATUS_WR_PROTECT: u32 = 0x3c; // BP0 to BP3
 const SPI_WRSR_2B: u32 = 1 << 22;
 
+const EFUSE_BLK0_RDATA0_REG: u32 = 0x3ff5a000;
+const EFUSE_FLASH_CRYPT_CNT_S: u32 = 20;
+const EFUSE_FLASH_CRYPT_CNT_M: u32 = 0x7f;
+
 const FLASH_CHIP_ADDR: u32 = 0x3ffae270;
 const FLASH_DUMMY_LEN_PLUS_ADDR: u32 = 0x3ffae290;
 
+const SPI1_REGISTERS: SpiRegisters = SpiRegisters {
+    cmd: SPI_CMD_REG,
+    addr: SPI_ADDR_REG,
+    ctrl: SPI_CTRL_REG,
+    user: SPI_USER_REG,
+    user1: SPI_USER1_REG,
+    user2: SPI_BASE_REG + 0x24,
+    mosi_dlen: SPI_BASE_REG + 0x28,
+    miso_dlen: SPI_BASE_REG + 0x2c,
+    w0: SPI_W0_REG,
+};
+
 crate::rom_fn! {
     fn esp_rom_cache_flush(cpu_num: u32) = 0x40009a14;
     fn esp_rom_cache_read_enable(cpu_num: u32) = 0x40009a84;
     fn esp_rom_spiflash_read(src_addr: u32, data: *const u32, len: u32) -> i32 = 0x40062ed8;
     fn esp_rom_spiflash_erase_sector(sector_number: u32) -> i32 = 0x40062ccc;
+    fn esp_rom_spiflash_erase_block(block_number: u32) -> i32 = 0x40062c4c;
+    fn esp_rom_spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 = 0x40062e78;
     fn esp_rom_spi_read_status_high(
         flash_chip: *const EspRomSpiflashChipT,
         status: *mut u32
@@ -105,6 +127,16 @@ pub(crate) fn spiflash_erase_sector(sector_number: u32) -> i32 {
     })
 }
 
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_erase_block(block_number: u32) -> i32 {
+    maybe_with_critical_section(|| {
+        let res = esp_rom_spiflash_erase_block(block_number);
+        spiflash_wait_for_ready();
+        res
+    })
+}
+
 #[inline(always)]
 #[link_section = ".rwtext"]
 fn spi_write_enable() {
@@ -174,6 +206,23 @@ pub(crate) fn spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32
     })
 }
 
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| {
+        begin();
+        spiflash_wait_for_ready();
+        let res = esp_rom_spiflash_write_encrypted(dest_addr, data, len);
+        spiflash_wait_for_ready();
+        end();
+        res
+    })
+}
+
+pub(crate) fn flash_crypt_cnt() -> u32 {
+    (read_register(EFUSE_BLK0_RDATA0_REG) >> EFUSE_FLASH_CRYPT_CNT_S) & EFUSE_FLASH_CRYPT_CNT_M
+}
+
 #[inline(always)]
 #[link_section = ".rwtext"]
 pub fn read_register(address: u32) -> u32 {
@@ -248,3 +297,64 @@ pub(crate) fn spiflash_unlock() -> i32 {
         0
     })
 }
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_id() -> u32 {
+    maybe_with_critical_section(|| {
+        spiflash_wait_for_ready();
+        spi_command::read_id(&SPI1_REGISTERS, read_dummy_cycles())
+    })
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_unique_id() -> [u8; 8] {
+    maybe_with_critical_section(|| {
+        spiflash_wait_for_ready();
+        spi_command::read_unique_id(&SPI1_REGISTERS, read_dummy_cycles())
+    })
+}
+
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn read_dummy_cycles() -> u32 {
+    // When the flash pins are routed through the GPIO matrix an extra dummy
+    // cycle is needed before reading
+    let g_rom_spiflash_dummy_len_plus = FLASH_DUMMY_LEN_PLUS_ADDR as *const u8;
+    unsafe { g_rom_spiflash_dummy_len_plus.add(1).read_volatile() as u32 }
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_lock() -> i32 {
+    let flashchip = FLASH_CHIP_ADDR as *const EspRomSpiflashChipT;
+
+    maybe_with_critical_section(|| {
+        begin();
+        spiflash_wait_for_ready();
+
+        let mut status: u32 = 0;
+        if spi_read_status_high(flashchip, &mut status) != 0 {
+            return -1;
+        }
+
+        // Keep QE, if it is set, and protect all blocks
+        status = (status & STATUS_QIE_BIT) | STATUS_WR_PROTECT;
+
+        write_register(SPI_CTRL_REG, read_register(SPI_CTRL_REG) | SPI_WRSR_2B);
+
+        spiflash_wait_for_ready();
+        if spi_write_status(flashchip, status) != 0 {
+            end();
+            return -1;
+        }
+        spiflash_wait_for_ready();
+        end();
+        0
+    })
+}
+
+pub(crate) fn spiflash_enable_auto_suspend() -> bool {
+    false
+}
@@ -1,10 +1,21 @@
-use crate::maybe_with_critical_section;
+use crate::{
+    maybe_with_critical_section,
+    spi_command::{self, SpiRegisters},
+    FlashStorage,
+};
+
+const SPI1_REGISTERS: SpiRegisters = SpiRegisters::spi_mem(0x3f40_2000);
+const EFUSE_SPI_BOOT_CRYPT_CNT_REG: u32 = 0x3f41_a034;
+const EFUSE_SPI_BOOT_CRYPT_CNT_S: u32 = 18;
+const EFUSE_SPI_BOOT_CRYPT_CNT_M: u32 = 0x7;
 
 crate::rom_fn! {
     fn esp_rom_spiflash_read(src_addr: u32, data: *const u32, len: u32) -> i32 = 0x4001728c;
     fn esp_rom_spiflash_unlock() -> i32 = 0x40016e88;
     fn esp_rom_spiflash_erase_sector(sector_number: u32) -> i32 = 0x4001716c;
+    fn esp_rom_spiflash_erase_block(block_number: u32) -> i32 = 0x4001710c;
     fn esp_rom_spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32 = 0x400171cc;
+    fn esp_rom_spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 = 0x400177e0;
 }
 
 pub(crate) fn spiflash_read(src_addr: u32, data: *const u32, len: u32) -> i32 {
@@ -19,6 +30,69 @@ pub(crate) fn spiflash_erase_sector(sector_number: u32) -> i32 {
     maybe_with_critical_section(|| esp_rom_spiflash_erase_sector(sector_number))
 }
 
+pub(crate) fn spiflash_erase_block(block_number: u32) -> i32 {
+    maybe_with_critical_section(|| esp_rom_spiflash_erase_block(block_number))
+}
+
 pub(crate) fn spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32 {
     maybe_with_critical_section(|| esp_rom_spiflash_write(dest_addr, data, len))
 }
+
+pub(crate) fn spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| esp_rom_spiflash_write_encrypted(dest_addr, data, len))
+}
+
+pub(crate) fn flash_crypt_cnt() -> u32 {
+    let value = unsafe { (EFUSE_SPI_BOOT_CRYPT_CNT_REG as *const u32).read_volatile() };
+    (value >> EFUSE_SPI_BOOT_CRYPT_CNT_S) & EFUSE_SPI_BOOT_CRYPT_CNT_M
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_id() -> u32 {
+    maybe_with_critical_section(|| spi_command::read_id(&SPI1_REGISTERS, 0))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_unique_id() -> [u8; 8] {
+    maybe_with_critical_section(|| spi_command::read_unique_id(&SPI1_REGISTERS, 0))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_lock() -> i32 {
+    maybe_with_critical_section(|| spi_command::lock(&SPI1_REGISTERS, 0))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_4b(src_addr: u32, data: *mut u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| spi_command::read_4b(&SPI1_REGISTERS, src_addr, data, len))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_write_4b(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| spi_command::write_4b(&SPI1_REGISTERS, dest_addr, data, len))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_erase_sector_4b(sector_number: u32) -> i32 {
+    maybe_with_critical_section(|| {
+        spi_command::erase_sector_4b(&SPI1_REGISTERS, sector_number * FlashStorage::SECTOR_SIZE)
+    })
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_erase_block_4b(block_number: u32) -> i32 {
+    maybe_with_critical_section(|| {
+        spi_command::erase_block_4b(&SPI1_REGISTERS, block_number * FlashStorage::BLOCK_SIZE)
+    })
+}
+
+pub(crate) fn spiflash_enable_auto_suspend() -> bool {
+    false
+}
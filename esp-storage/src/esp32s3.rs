@@ -1,10 +1,22 @@
-use crate::maybe_with_critical_section;
+use crate::{
+    maybe_with_critical_section,
+    spi_command::{self, SpiRegisters},
+    FlashStorage,
+};
+
+const SPI1_BASE: u32 = 0x6000_2000;
+const SPI1_REGISTERS: SpiRegisters = SpiRegisters::spi_mem(SPI1_BASE);
+const EFUSE_SPI_BOOT_CRYPT_CNT_REG: u32 = 0x6000_7034;
+const EFUSE_SPI_BOOT_CRYPT_CNT_S: u32 = 18;
+const EFUSE_SPI_BOOT_CRYPT_CNT_M: u32 = 0x7;
 
 crate::rom_fn! {
     fn esp_rom_spiflash_read(src_addr: u32, data: *const u32, len: u32) -> i32 = 0x40000a20;
     fn esp_rom_spiflash_unlock() -> i32 = 0x40000a2c;
     fn esp_rom_spiflash_erase_sector(sector_number: u32) -> i32 = 0x400009fc;
+    fn esp_rom_spiflash_erase_block(block_number: u32) -> i32 = 0x40000a08;
     fn esp_rom_spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32 = 0x40000a14;
+    fn esp_rom_spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 = 0x4000096c;
 }
 
 #[inline(always)]
@@ -25,8 +37,95 @@ pub(crate) fn spiflash_erase_sector(sector_number: u32) -> i32 {
     maybe_with_critical_section(|| esp_rom_spiflash_erase_sector(sector_number))
 }
 
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_erase_block(block_number: u32) -> i32 {
+    maybe_with_critical_section(|| esp_rom_spiflash_erase_block(block_number))
+}
+
 #[inline(always)]
 #[link_section = ".rwtext"]
 pub(crate) fn spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32 {
     maybe_with_critical_section(|| esp_rom_spiflash_write(dest_addr, data, len))
 }
+
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| esp_rom_spiflash_write_encrypted(dest_addr, data, len))
+}
+
+pub(crate) fn flash_crypt_cnt() -> u32 {
+    let value = unsafe { (EFUSE_SPI_BOOT_CRYPT_CNT_REG as *const u32).read_volatile() };
+    (value >> EFUSE_SPI_BOOT_CRYPT_CNT_S) & EFUSE_SPI_BOOT_CRYPT_CNT_M
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_id() -> u32 {
+    maybe_with_critical_section(|| spi_command::read_id(&SPI1_REGISTERS, 0))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_unique_id() -> [u8; 8] {
+    maybe_with_critical_section(|| spi_command::read_unique_id(&SPI1_REGISTERS, 0))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_lock() -> i32 {
+    maybe_with_critical_section(|| spi_command::lock(&SPI1_REGISTERS, 0))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_read_4b(src_addr: u32, data: *mut u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| spi_command::read_4b(&SPI1_REGISTERS, src_addr, data, len))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_write_4b(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    maybe_with_critical_section(|| spi_command::write_4b(&SPI1_REGISTERS, dest_addr, data, len))
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_erase_sector_4b(sector_number: u32) -> i32 {
+    maybe_with_critical_section(|| {
+        spi_command::erase_sector_4b(&SPI1_REGISTERS, sector_number * FlashStorage::SECTOR_SIZE)
+    })
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+pub(crate) fn spiflash_erase_block_4b(block_number: u32) -> i32 {
+    maybe_with_critical_section(|| {
+        spi_command::erase_block_4b(&SPI1_REGISTERS, block_number * FlashStorage::BLOCK_SIZE)
+    })
+}
+
+const SPI1_FLASH_WAITI_CTRL_REG: u32 = SPI1_BASE + 0x98;
+const SPI1_FLASH_SUS_CMD_REG: u32 = SPI1_BASE + 0x9c;
+const SPI1_FLASH_SUS_CTRL_REG: u32 = SPI1_BASE + 0xa0;
+
+pub(crate) fn spiflash_enable_auto_suspend() -> bool {
+    use spi_command::auto_suspend::{set_field, CMD_RDSR, CMD_RESUME, CMD_SUSPEND};
+
+    maybe_with_critical_section(|| {
+        // Let the hardware wait for the flash to become idle after erasing
+        // or programming, so it can suspend and resume in the meantime
+        set_field(SPI1_FLASH_WAITI_CTRL_REG, 2, 8, CMD_RDSR);
+        set_field(SPI1_FLASH_WAITI_CTRL_REG, 0, 1, 1);
+
+        // Wait after sending the commands and resume automatically
+        set_field(SPI1_FLASH_SUS_CMD_REG, 2, 3, 0b111);
+
+        set_field(SPI1_FLASH_SUS_CTRL_REG, 1, 8, CMD_RESUME);
+        set_field(SPI1_FLASH_SUS_CTRL_REG, 9, 8, CMD_SUSPEND);
+        set_field(SPI1_FLASH_SUS_CTRL_REG, 0, 1, 1);
+    });
+
+    true
+}
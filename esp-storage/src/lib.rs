@@ -26,13 +26,48 @@ mod chip_specific;
 #[path = "stub.rs"]
 mod chip_specific;
 
+#[cfg(not(feature = "emulation"))]
+mod spi_command;
+
+#[cfg(all(
+    not(feature = "emulation"),
+    feature = "park-other-core",
+    any(feature = "esp32", feature = "esp32s3")
+))]
+mod multicore;
+
 #[cfg(any(feature = "storage", feature = "nor-flash"))]
 mod common;
 
 #[cfg(any(feature = "storage", feature = "nor-flash"))]
-use common::FlashSectorBuffer;
+pub use common::{
+    CapacitySource,
+    FlashChipInfo,
+    FlashOperation,
+    FlashSectorBuffer,
+    FlashStorage,
+    FlashStorageError,
+};
+
+#[cfg(any(feature = "storage", feature = "nor-flash"))]
+mod region;
+#[cfg(any(feature = "storage", feature = "nor-flash"))]
+pub use region::FlashRegion;
+
+#[cfg(any(feature = "storage", feature = "nor-flash"))]
+mod crc;
+
+#[cfg(any(feature = "storage", feature = "nor-flash"))]
+mod md5;
+
+#[cfg(any(feature = "storage", feature = "nor-flash"))]
+pub mod partitions;
+
+#[cfg(any(feature = "storage", feature = "nor-flash"))]
+pub mod nvs;
+
 #[cfg(any(feature = "storage", feature = "nor-flash"))]
-pub use common::{FlashStorage, FlashStorageError};
+pub mod ota;
 
 #[cfg(feature = "storage")]
 mod storage;
@@ -40,17 +75,46 @@ mod storage;
 #[cfg(feature = "nor-flash")]
 mod nor_flash;
 
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+pub use asynch::AsyncFlashStorage;
+
 #[cfg(feature = "low-level")]
 pub mod ll;
 
+/// Set once auto-suspend is enabled. The flash can then serve the cache while
+/// it is erased or programmed, so neither interrupts nor the other core need
+/// to be held off.
+static AUTO_SUSPEND: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
 #[cfg(not(feature = "emulation"))]
 #[inline(always)]
 #[link_section = ".rwtext"]
 fn maybe_with_critical_section<R>(f: impl FnOnce() -> R) -> R {
+    if AUTO_SUSPEND.load(core::sync::atomic::Ordering::Relaxed) {
+        return f();
+    }
+
     #[cfg(feature = "critical-section")]
-    return critical_section::with(|_| f());
+    return critical_section::with(|_| maybe_with_other_core_stalled(f));
 
     #[cfg(not(feature = "critical-section"))]
+    maybe_with_other_core_stalled(f)
+}
+
+// Stalling happens inside the critical section so the other core can't be
+// stopped while it holds the lock
+#[cfg(not(feature = "emulation"))]
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn maybe_with_other_core_stalled<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(all(
+        feature = "park-other-core",
+        any(feature = "esp32", feature = "esp32s3")
+    ))]
+    let _stall = multicore::OtherCoreStall::new();
+
     f()
 }
 
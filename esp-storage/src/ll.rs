@@ -29,6 +29,16 @@ pub unsafe fn spiflash_unlock() -> Result<(), i32> {
     }
 }
 
+/// Low-level SPI NOR Flash lock
+///
+/// # Safety
+pub unsafe fn spiflash_lock() -> Result<(), i32> {
+    match chip_specific::spiflash_lock() {
+        0 => Ok(()),
+        value => Err(value),
+    }
+}
+
 /// Low-level SPI NOR Flash erase
 ///
 /// # Safety
@@ -41,6 +51,18 @@ pub unsafe fn spiflash_erase_sector(sector_number: u32) -> Result<(), i32> {
     }
 }
 
+/// Low-level SPI NOR Flash block erase
+///
+/// # Safety
+///
+/// The `block_number` * block_size should not exceeds the size of flash.
+pub unsafe fn spiflash_erase_block(block_number: u32) -> Result<(), i32> {
+    match chip_specific::spiflash_erase_block(block_number) {
+        0 => Ok(()),
+        value => Err(value),
+    }
+}
+
 /// Low-level SPI NOR Flash write
 ///
 /// # Safety
@@ -54,3 +76,22 @@ pub unsafe fn spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> Resu
         value => Err(value),
     }
 }
+
+/// Low-level SPI NOR Flash encrypted write
+///
+/// # Safety
+///
+/// The `dest_addr` + `len` should not exceeds the size of flash.
+/// The `dest_addr` and `len` must be multiples of 32.
+/// The `data` expected to points to word-aligned buffer with size greater or
+/// equals to `len`.
+pub unsafe fn spiflash_write_encrypted(
+    dest_addr: u32,
+    data: *const u32,
+    len: u32,
+) -> Result<(), i32> {
+    match chip_specific::spiflash_write_encrypted(dest_addr, data, len) {
+        0 => Ok(()),
+        value => Err(value),
+    }
+}
@@ -0,0 +1,87 @@
+//! Stalling the other core while the flash is accessed.
+//!
+//! The cache can't serve the other core while a ROM function talks to the
+//! flash, so it would crash as soon as it fetches code or data from flash. It
+//! is stalled through the RTC controller instead, like ESP-IDF does when no OS
+//! is running, and continues where it left off afterwards.
+
+#[cfg(feature = "esp32")]
+const RTC_CNTL_BASE: u32 = 0x3ff4_8000;
+#[cfg(feature = "esp32s3")]
+const RTC_CNTL_BASE: u32 = 0x6000_8000;
+
+const RTC_CNTL_OPTIONS0_REG: u32 = RTC_CNTL_BASE;
+#[cfg(feature = "esp32")]
+const RTC_CNTL_SW_CPU_STALL_REG: u32 = RTC_CNTL_BASE + 0xac;
+#[cfg(feature = "esp32s3")]
+const RTC_CNTL_SW_CPU_STALL_REG: u32 = RTC_CNTL_BASE + 0xbc;
+
+const SW_STALL_APPCPU_C0_S: u32 = 0;
+const SW_STALL_PROCPU_C0_S: u32 = 2;
+const SW_STALL_C0_M: u32 = 0x3;
+const SW_STALL_APPCPU_C1_S: u32 = 20;
+const SW_STALL_PROCPU_C1_S: u32 = 26;
+const SW_STALL_C1_M: u32 = 0x3f;
+
+/// The core is stalled while `c1 << 2 | c0` is 0x86.
+const SW_STALL_C0: u32 = 0x2;
+const SW_STALL_C1: u32 = 0x21;
+
+#[inline(always)]
+fn modify_register(address: u32, shift: u32, mask: u32, value: u32) -> u32 {
+    let reg = address as *mut u32;
+    unsafe {
+        let old = reg.read_volatile();
+        reg.write_volatile((old & !(mask << shift)) | (value << shift));
+        (old >> shift) & mask
+    }
+}
+
+/// Keeps the other core stalled until dropped.
+pub(crate) struct OtherCoreStall {
+    c0_shift: u32,
+    c1_shift: u32,
+    c0: u32,
+    c1: u32,
+}
+
+impl OtherCoreStall {
+    #[inline(always)]
+    pub(crate) fn new() -> Self {
+        let (c0_shift, c1_shift) = if xtensa_lx::get_processor_id() & 0x2000 != 0 {
+            (SW_STALL_PROCPU_C0_S, SW_STALL_PROCPU_C1_S)
+        } else {
+            (SW_STALL_APPCPU_C0_S, SW_STALL_APPCPU_C1_S)
+        };
+
+        let c1 = modify_register(
+            RTC_CNTL_SW_CPU_STALL_REG,
+            c1_shift,
+            SW_STALL_C1_M,
+            SW_STALL_C1,
+        );
+        let c0 = modify_register(RTC_CNTL_OPTIONS0_REG, c0_shift, SW_STALL_C0_M, SW_STALL_C0);
+
+        Self {
+            c0_shift,
+            c1_shift,
+            c0,
+            c1,
+        }
+    }
+}
+
+impl Drop for OtherCoreStall {
+    #[inline(always)]
+    fn drop(&mut self) {
+        // Restore the previous values instead of clearing them, so a core
+        // which was parked or never started stays that way
+        modify_register(
+            RTC_CNTL_SW_CPU_STALL_REG,
+            self.c1_shift,
+            SW_STALL_C1_M,
+            self.c1,
+        );
+        modify_register(RTC_CNTL_OPTIONS0_REG, self.c0_shift, SW_STALL_C0_M, self.c0);
+    }
+}
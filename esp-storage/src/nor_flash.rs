@@ -1,5 +1,3 @@
-use core::mem::MaybeUninit;
-
 use embedded_storage::nor_flash::{
     ErrorType,
     MultiwriteNorFlash,
@@ -9,50 +7,28 @@ use embedded_storage::nor_flash::{
     ReadNorFlash,
 };
 
-use crate::{FlashSectorBuffer, FlashStorage, FlashStorageError};
-
-#[cfg(feature = "bytewise-read")]
-#[repr(C, align(4))]
-struct FlashWordBuffer {
-    // NOTE: Ensure that no unaligned fields are added above `data` to maintain its required
-    // alignment
-    data: [u8; FlashStorage::WORD_SIZE as usize],
-}
-
-#[cfg(feature = "bytewise-read")]
-impl core::ops::Deref for FlashWordBuffer {
-    type Target = [u8; FlashStorage::WORD_SIZE as usize];
-
-    fn deref(&self) -> &Self::Target {
-        &self.data
-    }
-}
-
-#[cfg(feature = "bytewise-read")]
-impl core::ops::DerefMut for FlashWordBuffer {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.data
-    }
-}
-
-impl FlashStorage {
-    #[inline(always)]
-    fn is_word_aligned(bytes: &[u8]) -> bool {
-        // TODO: Use is_aligned_to when stabilized (see `pointer_is_aligned`)
-        (unsafe { bytes.as_ptr().offset_from(core::ptr::null()) }) % Self::WORD_SIZE as isize == 0
-    }
-}
+use crate::{FlashRegion, FlashStorage, FlashStorageError};
 
 impl NorFlashError for FlashStorageError {
     fn kind(&self) -> NorFlashErrorKind {
         match self {
-            Self::NotAligned => NorFlashErrorKind::NotAligned,
-            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Self::NotAligned { .. } => NorFlashErrorKind::NotAligned,
+            Self::OutOfBounds { .. } => NorFlashErrorKind::OutOfBounds,
             _ => NorFlashErrorKind::Other,
         }
     }
 }
 
+/// Writes to an encrypted flash go through 32 byte blocks, so writing the same
+/// word again re-encrypts its neighbours and corrupts them. The trait methods
+/// refuse to write instead of breaking the [`MultiwriteNorFlash`] guarantee.
+pub(crate) fn check_not_encrypted(flash: &FlashStorage) -> Result<(), FlashStorageError> {
+    if flash.encryption_enabled() {
+        return Err(FlashStorageError::EncryptionEnabled);
+    }
+    Ok(())
+}
+
 impl ErrorType for FlashStorage {
     type Error = FlashStorageError;
 }
@@ -65,89 +41,11 @@ impl ReadNorFlash for FlashStorage {
     const READ_SIZE: usize = 1;
 
     fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
-        self.check_alignment::<{ Self::READ_SIZE as _ }>(offset, bytes.len())?;
-        self.check_bounds(offset, bytes.len())?;
+        #[cfg(not(feature = "bytewise-read"))]
+        return self.read_aligned(offset, bytes);
 
         #[cfg(feature = "bytewise-read")]
-        let (offset, bytes) = {
-            let byte_offset = (offset % Self::WORD_SIZE) as usize;
-            if byte_offset > 0 {
-                let mut word_buffer = MaybeUninit::<FlashWordBuffer>::uninit();
-                let word_buffer = unsafe { word_buffer.assume_init_mut() };
-
-                let offset = offset - byte_offset as u32;
-                let length = bytes.len().min(word_buffer.len() - byte_offset);
-
-                self.internal_read(offset, &mut word_buffer[..])?;
-                bytes[..length].copy_from_slice(&word_buffer[byte_offset..][..length]);
-
-                (offset + Self::WORD_SIZE, &mut bytes[length..])
-            } else {
-                (offset, bytes)
-            }
-        };
-
-        if Self::is_word_aligned(bytes) {
-            // Bytes buffer is word-aligned so we can read directly to it
-            for (offset, chunk) in (offset..)
-                .step_by(Self::SECTOR_SIZE as _)
-                .zip(bytes.chunks_mut(Self::SECTOR_SIZE as _))
-            {
-                // Chunk already is word aligned so we can read directly to it
-                #[cfg(not(feature = "bytewise-read"))]
-                self.internal_read(offset, chunk)?;
-
-                #[cfg(feature = "bytewise-read")]
-                {
-                    let length = chunk.len();
-                    let byte_length = length % Self::WORD_SIZE as usize;
-                    let length = length - byte_length;
-
-                    self.internal_read(offset, &mut chunk[..length])?;
-
-                    // Read not aligned rest of data
-                    if byte_length > 0 {
-                        let mut word_buffer = MaybeUninit::<FlashWordBuffer>::uninit();
-                        let word_buffer = unsafe { word_buffer.assume_init_mut() };
-
-                        self.internal_read(offset + length as u32, &mut word_buffer[..])?;
-                        chunk[length..].copy_from_slice(&word_buffer[..byte_length]);
-                    }
-                }
-            }
-        } else {
-            // Bytes buffer isn't word-aligned so we might read only via aligned buffer
-            let mut buffer = MaybeUninit::<FlashSectorBuffer>::uninit();
-            let buffer = unsafe { buffer.assume_init_mut() };
-
-            for (offset, chunk) in (offset..)
-                .step_by(Self::SECTOR_SIZE as _)
-                .zip(bytes.chunks_mut(Self::SECTOR_SIZE as _))
-            {
-                // Read to temporary buffer first (chunk length is aligned)
-                #[cfg(not(feature = "bytewise-read"))]
-                self.internal_read(offset, &mut buffer[..chunk.len()])?;
-
-                // Read to temporary buffer first (chunk length is not aligned)
-                #[cfg(feature = "bytewise-read")]
-                {
-                    let length = chunk.len();
-                    let byte_length = length % Self::WORD_SIZE as usize;
-                    let length = if byte_length > 0 {
-                        length - byte_length + Self::WORD_SIZE as usize
-                    } else {
-                        length
-                    };
-
-                    self.internal_read(offset, &mut buffer[..length])?;
-                }
-
-                // Copy to bytes buffer
-                chunk.copy_from_slice(&buffer[..chunk.len()]);
-            }
-        }
-
-        Ok(())
+        return FlashStorage::read(self, offset, bytes);
     }
 
     fn capacity(&self) -> usize {
@@ -159,56 +57,64 @@ impl NorFlash for FlashStorage {
     const WRITE_SIZE: usize = Self::WORD_SIZE as _;
     const ERASE_SIZE: usize = Self::SECTOR_SIZE as _;
 
+    /// Returns [`FlashStorageError::EncryptionEnabled`] if flash encryption
+    /// is enabled.
     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        self.check_alignment::<{ Self::WORD_SIZE }>(offset, bytes.len())?;
-        self.check_bounds(offset, bytes.len())?;
-
-        if Self::is_word_aligned(bytes) {
-            // Bytes buffer is word-aligned so we can write directly from it
-            for (offset, chunk) in (offset..)
-                .step_by(Self::SECTOR_SIZE as _)
-                .zip(bytes.chunks(Self::SECTOR_SIZE as _))
-            {
-                // Chunk already is word aligned so we can write directly from it
-                self.internal_write(offset, chunk)?;
-            }
-        } else {
-            // Bytes buffer isn't word-aligned so we might write only via aligned buffer
-            let mut buffer = MaybeUninit::<FlashSectorBuffer>::uninit();
-            let buffer = unsafe { buffer.assume_init_mut() };
-
-            for (offset, chunk) in (offset..)
-                .step_by(Self::SECTOR_SIZE as _)
-                .zip(bytes.chunks(Self::SECTOR_SIZE as _))
-            {
-                // Copy to temporary buffer first
-                buffer[..chunk.len()].copy_from_slice(chunk);
-                // Write from temporary buffer
-                self.internal_write(offset, &buffer[..chunk.len()])?;
-            }
-        }
-
-        Ok(())
+        check_not_encrypted(self)?;
+        self.write_aligned(offset, bytes)
     }
 
     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
-        let len = (to - from) as _;
-        self.check_alignment::<{ Self::SECTOR_SIZE }>(from, len)?;
-        self.check_bounds(from, len)?;
+        FlashStorage::erase(self, from, to)
+    }
+}
 
-        for sector in from / Self::SECTOR_SIZE..to / Self::SECTOR_SIZE {
-            self.internal_erase(sector)?;
-        }
+/// A word can be written any number of times between erases, each write
+/// clearing the bits which are `0` in the data and leaving the others as they
+/// are. Writing `0xf0` over `0x0f` thus results in `0x00`.
+impl MultiwriteNorFlash for FlashStorage {}
 
-        Ok(())
+impl ErrorType for FlashRegion<'_> {
+    type Error = FlashStorageError;
+}
+
+impl ReadNorFlash for FlashRegion<'_> {
+    const READ_SIZE: usize = FlashStorage::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = self.translate(offset, bytes.len())?;
+        ReadNorFlash::read(self.flash(), offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        FlashRegion::capacity(self)
     }
 }
 
-impl MultiwriteNorFlash for FlashStorage {}
+impl NorFlash for FlashRegion<'_> {
+    const WRITE_SIZE: usize = FlashStorage::WRITE_SIZE;
+    const ERASE_SIZE: usize = FlashStorage::ERASE_SIZE;
+
+    /// Returns [`FlashStorageError::EncryptionEnabled`] if flash encryption
+    /// is enabled.
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_not_encrypted(self.flash())?;
+        FlashRegion::write_aligned(self, offset, bytes)
+    }
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        FlashRegion::erase(self, from, to)
+    }
+}
+
+impl MultiwriteNorFlash for FlashRegion<'_> {}
 
 #[cfg(test)]
 mod test {
-    use core::mem::MaybeUninit;
+    use core::{
+        mem::MaybeUninit,
+        ops::{Deref, DerefMut},
+    };
 
     use super::*;
 
@@ -216,7 +122,7 @@ mod test {
     const SECTOR_SIZE: u32 = 4 << 10;
     const NUM_SECTORS: u32 = 3;
     const FLASH_SIZE: u32 = SECTOR_SIZE * NUM_SECTORS;
-    const MAX_OFFSET: u32 = SECTOR_SIZE * 1;
+    const MAX_OFFSET: u32 = SECTOR_SIZE;
     const MAX_LENGTH: u32 = SECTOR_SIZE * 2;
 
     #[repr(C, align(4))]
@@ -284,10 +190,10 @@ mod test {
         let mut data = TestBuffer::default();
 
         flash.erase(0, FLASH_SIZE).unwrap();
-        flash.write(0, &*src).unwrap();
+        NorFlash::write(&mut flash, 0, &*src).unwrap();
 
         for (off, len) in range_gen::<WORD_SIZE, MAX_OFFSET, MAX_LENGTH>(Some(true)) {
-            flash.read(off, &mut data[..len as usize]).unwrap();
+            ReadNorFlash::read(&mut flash, off, &mut data[..len as usize]).unwrap();
             assert_eq!(data[..len as usize], src[off as usize..][..len as usize]);
         }
     }
@@ -299,7 +205,7 @@ mod test {
         let mut data = TestBuffer::default();
 
         for (off, len) in range_gen::<WORD_SIZE, MAX_OFFSET, MAX_LENGTH>(Some(false)) {
-            flash.read(off, &mut data[..len as usize]).unwrap_err();
+            ReadNorFlash::read(&mut flash, off, &mut data[..len as usize]).unwrap_err();
         }
     }
 
@@ -311,10 +217,10 @@ mod test {
         let mut data = TestBuffer::default();
 
         flash.erase(0, FLASH_SIZE).unwrap();
-        flash.write(0, &*src).unwrap();
+        NorFlash::write(&mut flash, 0, &*src).unwrap();
 
         for (off, len) in range_gen::<WORD_SIZE, MAX_OFFSET, MAX_LENGTH>(Some(true)) {
-            flash.read(off, &mut data[1..][..len as usize]).unwrap();
+            ReadNorFlash::read(&mut flash, off, &mut data[1..][..len as usize]).unwrap();
             assert_eq!(
                 data[1..][..len as usize],
                 src[off as usize..][..len as usize]
@@ -330,10 +236,10 @@ mod test {
         let mut data = TestBuffer::default();
 
         flash.erase(0, FLASH_SIZE).unwrap();
-        flash.write(0, &*src).unwrap();
+        NorFlash::write(&mut flash, 0, &*src).unwrap();
 
         for (off, len) in range_gen::<WORD_SIZE, MAX_OFFSET, MAX_LENGTH>(None) {
-            flash.read(off, &mut data[..len as usize]).unwrap();
+            ReadNorFlash::read(&mut flash, off, &mut data[..len as usize]).unwrap();
             assert_eq!(data[..len as usize], src[off as usize..][..len as usize]);
         }
     }
@@ -346,14 +252,64 @@ mod test {
         let mut data = TestBuffer::default();
 
         flash.erase(0, FLASH_SIZE).unwrap();
-        flash.write(0, &*src).unwrap();
+        NorFlash::write(&mut flash, 0, &*src).unwrap();
 
         for (off, len) in range_gen::<WORD_SIZE, MAX_OFFSET, MAX_LENGTH>(None) {
-            flash.read(off, &mut data[1..][..len as usize]).unwrap();
+            ReadNorFlash::read(&mut flash, off, &mut data[1..][..len as usize]).unwrap();
             assert_eq!(
                 data[1..][..len as usize],
                 src[off as usize..][..len as usize]
             );
         }
     }
+
+    #[test]
+    fn not_aligned_write_and_read() {
+        // Use the sector after the ones touched by the other tests
+        const BASE: u32 = FLASH_SIZE;
+        const WINDOW: usize = 32;
+
+        let mut flash = FlashStorage::new();
+        let src = TestBuffer::seq();
+        let mut data = TestBuffer::default();
+
+        for (off, len) in range_gen::<WORD_SIZE, 8, 16>(Some(false)) {
+            let (off, len) = (off as usize, len as usize);
+
+            flash.erase(BASE, BASE + SECTOR_SIZE).unwrap();
+
+            // Write two adjacent records, both from a not aligned buffer
+            flash.write(BASE + off as u32, &src[1..][..len]).unwrap();
+            flash
+                .write(BASE + (off + len) as u32, &src[2..][..len])
+                .unwrap();
+
+            flash.read(BASE, &mut data[1..][..WINDOW]).unwrap();
+
+            for (index, byte) in data[1..][..WINDOW].iter().enumerate() {
+                let expected = if (off..off + len).contains(&index) {
+                    src[1 + index - off]
+                } else if (off + len..off + 2 * len).contains(&index) {
+                    src[2 + index - off - len]
+                } else {
+                    0xff
+                };
+                assert_eq!(*byte, expected, "offset {off}, length {len}, index {index}");
+            }
+
+            // Read back every sub-range through the unaligned read path
+            for (roff, rlen) in range_gen::<WORD_SIZE, 8, 16>(None) {
+                let (roff, rlen) = (roff as usize, rlen as usize);
+                let mut window = [0u8; WINDOW];
+                window.copy_from_slice(&data[1..][..WINDOW]);
+
+                flash
+                    .read(BASE + roff as u32, &mut data[1..][..rlen])
+                    .unwrap();
+                assert_eq!(data[1..][..rlen], window[roff..][..rlen]);
+
+                data[1..][..WINDOW].copy_from_slice(&window);
+            }
+        }
+    }
 }
@@ -0,0 +1,664 @@
+//! Read-only access to ESP-IDF NVS partitions
+//!
+//! NVS (non-volatile storage) is the key-value store ESP-IDF uses for e.g.
+//! WiFi credentials and calibration data. This module can look up values
+//! written by ESP-IDF or its tooling (`nvs_partition_gen.py`), but it can't
+//! modify them.
+//!
+//! ```rust, ignore
+//! let mut flash = FlashStorage::new();
+//! let table = PartitionTable::read(&mut flash)?;
+//! let partition = table.find_by_type(DataType::Nvs).unwrap();
+//!
+//! let mut nvs = Nvs::new(partition.as_region(&mut flash)?);
+//! let mut buffer = [0u8; 64];
+//! let ssid = nvs.get_str("nvs.net80211", "sta.ssid", &mut buffer)?;
+//! ```
+//!
+//! See <https://docs.espressif.com/projects/esp-idf/en/latest/esp32/api-reference/storage/nvs_flash.html>
+
+use crate::{crc::crc32_le, FlashRegion, FlashStorage, FlashStorageError};
+
+const PAGE_SIZE: u32 = FlashStorage::SECTOR_SIZE;
+const ENTRY_SIZE: u32 = 32;
+const ENTRY_COUNT: u32 = 126;
+const BITMAP_OFFSET: u32 = 32;
+const ENTRIES_OFFSET: u32 = 64;
+
+const PAGE_STATE_ACTIVE: u32 = 0xffff_fffe;
+const PAGE_STATE_FULL: u32 = 0xffff_fffc;
+const PAGE_STATE_FREEING: u32 = 0xffff_fff8;
+
+const PAGE_VERSION_1: u8 = 0xff;
+const PAGE_VERSION_2: u8 = 0xfe;
+
+const ENTRY_STATE_EMPTY: u8 = 0b11;
+const ENTRY_STATE_WRITTEN: u8 = 0b10;
+
+const TYPE_U8: u8 = 0x01;
+const TYPE_I8: u8 = 0x11;
+const TYPE_U16: u8 = 0x02;
+const TYPE_I16: u8 = 0x12;
+const TYPE_U32: u8 = 0x04;
+const TYPE_I32: u8 = 0x14;
+const TYPE_U64: u8 = 0x08;
+const TYPE_I64: u8 = 0x18;
+const TYPE_STR: u8 = 0x21;
+const TYPE_BLOB: u8 = 0x41;
+const TYPE_BLOB_DATA: u8 = 0x42;
+const TYPE_BLOB_IDX: u8 = 0x48;
+
+/// Namespace index used by the entries defining the namespaces themselves.
+const NAMESPACE_INDEX: u8 = 0;
+
+/// Maximum length of namespaces and keys.
+pub const MAX_KEY_LENGTH: usize = 15;
+
+/// Errors which can occur when reading an NVS partition.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NvsError {
+    /// Reading the flash failed.
+    Flash(FlashStorageError),
+    /// The namespace doesn't exist.
+    NamespaceNotFound,
+    /// The key doesn't exist in the namespace.
+    KeyNotFound,
+    /// The key exists but holds a value of a different type.
+    TypeMismatch,
+    /// The namespace or key is longer than [`MAX_KEY_LENGTH`].
+    InvalidKey,
+    /// The provided buffer is too small, the value needs the given number of
+    /// bytes.
+    BufferTooSmall(usize),
+    /// The header of the page with the given index is malformed.
+    CorruptedPage(u32),
+    /// The data of a string or blob is malformed or doesn't match its
+    /// checksum.
+    CorruptedValue,
+}
+
+impl From<FlashStorageError> for NvsError {
+    fn from(error: FlashStorageError) -> Self {
+        Self::Flash(error)
+    }
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// A raw 32 byte NVS entry.
+#[derive(Clone, Copy)]
+struct Entry([u8; ENTRY_SIZE as usize]);
+
+impl Entry {
+    fn namespace(&self) -> u8 {
+        self.0[0]
+    }
+
+    fn ty(&self) -> u8 {
+        self.0[1]
+    }
+
+    fn span(&self) -> u32 {
+        self.0[2] as u32
+    }
+
+    fn chunk_index(&self) -> u8 {
+        self.0[3]
+    }
+
+    fn key(&self) -> &[u8] {
+        let key = &self.0[8..24];
+        let len = key.iter().position(|byte| *byte == 0).unwrap_or(key.len());
+        &key[..len]
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.0[24..]
+    }
+
+    /// Size of a string, blob or blob chunk, stored in the data field.
+    fn data_size(&self) -> usize {
+        u16::from_le_bytes([self.0[24], self.0[25]]) as usize
+    }
+
+    /// Checksum of a string, blob or blob chunk, stored in the data field.
+    fn data_crc(&self) -> u32 {
+        u32_at(&self.0, 28)
+    }
+
+    fn is_valid(&self) -> bool {
+        let crc = crc32_le(0xffff_ffff, &self.0[..4]);
+        let crc = crc32_le(crc, &self.0[8..]);
+        crc == u32_at(&self.0, 4)
+    }
+}
+
+/// Location of an entry in the partition.
+#[derive(Clone, Copy)]
+struct Location {
+    page: u32,
+    index: u32,
+    seq: u32,
+    entry: Entry,
+}
+
+impl Location {
+    fn offset(&self) -> u32 {
+        self.page * PAGE_SIZE + ENTRIES_OFFSET + self.index * ENTRY_SIZE
+    }
+}
+
+/// Read-only view of an NVS partition.
+#[derive(Debug)]
+pub struct Nvs<'a> {
+    region: FlashRegion<'a>,
+}
+
+impl<'a> Nvs<'a> {
+    /// Creates a view of the NVS partition covered by `region`.
+    pub fn new(region: FlashRegion<'a>) -> Self {
+        Self { region }
+    }
+
+    /// Returns the underlying region.
+    pub fn into_inner(self) -> FlashRegion<'a> {
+        self.region
+    }
+
+    /// Reads an `u8` value.
+    pub fn get_u8(&mut self, namespace: &str, key: &str) -> Result<u8, NvsError> {
+        self.get_primitive::<1>(namespace, key, TYPE_U8)
+            .map(u8::from_le_bytes)
+    }
+
+    /// Reads an `i8` value.
+    pub fn get_i8(&mut self, namespace: &str, key: &str) -> Result<i8, NvsError> {
+        self.get_primitive::<1>(namespace, key, TYPE_I8)
+            .map(i8::from_le_bytes)
+    }
+
+    /// Reads an `u16` value.
+    pub fn get_u16(&mut self, namespace: &str, key: &str) -> Result<u16, NvsError> {
+        self.get_primitive::<2>(namespace, key, TYPE_U16)
+            .map(u16::from_le_bytes)
+    }
+
+    /// Reads an `i16` value.
+    pub fn get_i16(&mut self, namespace: &str, key: &str) -> Result<i16, NvsError> {
+        self.get_primitive::<2>(namespace, key, TYPE_I16)
+            .map(i16::from_le_bytes)
+    }
+
+    /// Reads an `u32` value.
+    pub fn get_u32(&mut self, namespace: &str, key: &str) -> Result<u32, NvsError> {
+        self.get_primitive::<4>(namespace, key, TYPE_U32)
+            .map(u32::from_le_bytes)
+    }
+
+    /// Reads an `i32` value.
+    pub fn get_i32(&mut self, namespace: &str, key: &str) -> Result<i32, NvsError> {
+        self.get_primitive::<4>(namespace, key, TYPE_I32)
+            .map(i32::from_le_bytes)
+    }
+
+    /// Reads an `u64` value.
+    pub fn get_u64(&mut self, namespace: &str, key: &str) -> Result<u64, NvsError> {
+        self.get_primitive::<8>(namespace, key, TYPE_U64)
+            .map(u64::from_le_bytes)
+    }
+
+    /// Reads an `i64` value.
+    pub fn get_i64(&mut self, namespace: &str, key: &str) -> Result<i64, NvsError> {
+        self.get_primitive::<8>(namespace, key, TYPE_I64)
+            .map(i64::from_le_bytes)
+    }
+
+    /// Reads a string into `buffer`, returning the part of the buffer holding
+    /// it.
+    ///
+    /// The NUL terminator stored by ESP-IDF is not included in the returned
+    /// string, but needs to fit into `buffer`.
+    pub fn get_str<'b>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b str, NvsError> {
+        let namespace = self.namespace_index(namespace)?;
+        let location = self
+            .find(namespace, key, |entry| entry.ty() == TYPE_STR)?
+            .ok_or(NvsError::KeyNotFound)?;
+
+        let len = self.read_data(&location, buffer)?;
+        let string = match buffer[..len].split_last() {
+            Some((0, string)) => string,
+            _ => return Err(NvsError::CorruptedValue),
+        };
+
+        core::str::from_utf8(string).map_err(|_| NvsError::CorruptedValue)
+    }
+
+    /// Reads a blob into `buffer`, returning the part of the buffer holding
+    /// it.
+    ///
+    /// Both blobs split into chunks across several pages and blobs stored
+    /// in the legacy single chunk format are supported.
+    pub fn get_blob<'b>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], NvsError> {
+        let namespace = self.namespace_index(namespace)?;
+        let location = self
+            .find(namespace, key, |entry| {
+                matches!(entry.ty(), TYPE_BLOB_IDX | TYPE_BLOB)
+            })?
+            .ok_or(NvsError::KeyNotFound)?;
+
+        if location.entry.ty() == TYPE_BLOB {
+            let len = self.read_data(&location, buffer)?;
+            return Ok(&buffer[..len]);
+        }
+
+        let data = location.entry.data();
+        let size = u32_at(data, 0) as usize;
+        let chunk_count = data[4];
+        let chunk_start = data[5];
+
+        if buffer.len() < size {
+            return Err(NvsError::BufferTooSmall(size));
+        }
+
+        let mut len = 0;
+        for chunk in 0..chunk_count {
+            let chunk_index = chunk_start.wrapping_add(chunk);
+            let location = self
+                .find(namespace, key, |entry| {
+                    entry.ty() == TYPE_BLOB_DATA && entry.chunk_index() == chunk_index
+                })?
+                .ok_or(NvsError::CorruptedValue)?;
+
+            if len + location.entry.data_size() > size {
+                return Err(NvsError::CorruptedValue);
+            }
+            len += self.read_data(&location, &mut buffer[len..size])?;
+        }
+
+        if len != size {
+            return Err(NvsError::CorruptedValue);
+        }
+
+        Ok(&buffer[..len])
+    }
+
+    fn get_primitive<const N: usize>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        ty: u8,
+    ) -> Result<[u8; N], NvsError> {
+        let namespace = self.namespace_index(namespace)?;
+        let location = self
+            .find(namespace, key, |_| true)?
+            .ok_or(NvsError::KeyNotFound)?;
+
+        if location.entry.ty() != ty {
+            return Err(NvsError::TypeMismatch);
+        }
+
+        let mut value = [0u8; N];
+        value.copy_from_slice(&location.entry.data()[..N]);
+        Ok(value)
+    }
+
+    fn namespace_index(&mut self, namespace: &str) -> Result<u8, NvsError> {
+        let location = self
+            .find(NAMESPACE_INDEX, namespace, |entry| entry.ty() == TYPE_U8)?
+            .ok_or(NvsError::NamespaceNotFound)?;
+
+        Ok(location.entry.data()[0])
+    }
+
+    /// Reads the data of a variable length entry into `buffer`, returning its
+    /// length.
+    fn read_data(&mut self, location: &Location, buffer: &mut [u8]) -> Result<usize, NvsError> {
+        let size = location.entry.data_size();
+        let span = location.entry.span();
+
+        // The data is stored in the entries following the header entry
+        if span == 0 || size > ((span - 1) * ENTRY_SIZE) as usize {
+            return Err(NvsError::CorruptedValue);
+        }
+        if buffer.len() < size {
+            return Err(NvsError::BufferTooSmall(size));
+        }
+
+        let buffer = &mut buffer[..size];
+        self.region.read(location.offset() + ENTRY_SIZE, buffer)?;
+
+        if crc32_le(0xffff_ffff, buffer) != location.entry.data_crc() {
+            return Err(NvsError::CorruptedValue);
+        }
+
+        Ok(size)
+    }
+
+    /// Finds the most recent valid entry with the given namespace and key for
+    /// which `filter` returns `true`.
+    fn find(
+        &mut self,
+        namespace: u8,
+        key: &str,
+        filter: impl Fn(&Entry) -> bool,
+    ) -> Result<Option<Location>, NvsError> {
+        if key.len() > MAX_KEY_LENGTH {
+            return Err(NvsError::InvalidKey);
+        }
+
+        let mut found: Option<Location> = None;
+
+        for page in 0..self.region.capacity() as u32 / PAGE_SIZE {
+            let Some(seq) = self.read_page_header(page)? else {
+                continue;
+            };
+
+            let mut bitmap = [0u8; 32];
+            self.region
+                .read(page * PAGE_SIZE + BITMAP_OFFSET, &mut bitmap)?;
+            let state = |index: u32| (bitmap[index as usize / 4] >> ((index % 4) * 2)) & 0b11;
+
+            let mut index = 0;
+            while index < ENTRY_COUNT {
+                match state(index) {
+                    ENTRY_STATE_EMPTY => break,
+                    ENTRY_STATE_WRITTEN => {}
+                    _ => {
+                        index += 1;
+                        continue;
+                    }
+                }
+
+                let mut entry = Entry([0u8; ENTRY_SIZE as usize]);
+                self.region.read(
+                    page * PAGE_SIZE + ENTRIES_OFFSET + index * ENTRY_SIZE,
+                    &mut entry.0,
+                )?;
+
+                // Skip entries which were only partially written
+                let span = entry.span();
+                if !entry.is_valid() || span == 0 || index + span > ENTRY_COUNT {
+                    index += 1;
+                    continue;
+                }
+
+                let location = Location {
+                    page,
+                    index,
+                    seq,
+                    entry,
+                };
+                let newer = found.is_none_or(|found| found.seq <= seq);
+
+                if entry.namespace() == namespace
+                    && entry.key() == key.as_bytes()
+                    && filter(&entry)
+                    && newer
+                {
+                    found = Some(location);
+                }
+
+                index += span;
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Validates the header of the page, returning its sequence number if it
+    /// holds entries.
+    fn read_page_header(&mut self, page: u32) -> Result<Option<u32>, NvsError> {
+        let mut header = [0u8; 32];
+        self.region.read(page * PAGE_SIZE, &mut header)?;
+
+        match u32_at(&header, 0) {
+            PAGE_STATE_ACTIVE | PAGE_STATE_FULL | PAGE_STATE_FREEING => {}
+            // Erased pages, and pages marked as corrupted by ESP-IDF itself
+            _ => return Ok(None),
+        }
+
+        if crc32_le(0xffff_ffff, &header[4..28]) != u32_at(&header, 28)
+            || !matches!(header[8], PAGE_VERSION_1 | PAGE_VERSION_2)
+        {
+            return Err(NvsError::CorruptedPage(page));
+        }
+
+        Ok(Some(u32_at(&header, 4)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Use sectors which aren't touched by the other tests
+    const BASE: u32 = FlashStorage::SECTOR_SIZE * 8;
+    const PAGES: u32 = 2;
+
+    /// Builds NVS pages in memory the way ESP-IDF lays them out.
+    struct PageBuilder {
+        pages: [[u8; PAGE_SIZE as usize]; PAGES as usize],
+        next: [u32; PAGES as usize],
+    }
+
+    impl PageBuilder {
+        fn new() -> Self {
+            let mut builder = Self {
+                pages: [[0xff; PAGE_SIZE as usize]; PAGES as usize],
+                next: [0; PAGES as usize],
+            };
+            for (seq, page) in builder.pages.iter_mut().enumerate() {
+                page[..4].copy_from_slice(&PAGE_STATE_FULL.to_le_bytes());
+                page[4..8].copy_from_slice(&(seq as u32).to_le_bytes());
+                page[8] = PAGE_VERSION_2;
+                let crc = crc32_le(0xffff_ffff, &page[4..28]);
+                page[28..32].copy_from_slice(&crc.to_le_bytes());
+            }
+            builder
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn entry(
+            &mut self,
+            page: usize,
+            namespace: u8,
+            ty: u8,
+            chunk_index: u8,
+            key: &str,
+            data: [u8; 8],
+            payload: &[u8],
+        ) {
+            let span = 1 + payload.len().div_ceil(ENTRY_SIZE as usize);
+
+            let mut entry = [0xffu8; 32];
+            entry[0] = namespace;
+            entry[1] = ty;
+            entry[2] = span as u8;
+            entry[3] = chunk_index;
+            entry[8..24].fill(0);
+            entry[8..][..key.len()].copy_from_slice(key.as_bytes());
+            entry[24..].copy_from_slice(&data);
+            let crc = crc32_le(crc32_le(0xffff_ffff, &entry[..4]), &entry[8..]);
+            entry[4..8].copy_from_slice(&crc.to_le_bytes());
+
+            let index = self.next[page] as usize;
+            let offset = ENTRIES_OFFSET as usize + index * ENTRY_SIZE as usize;
+            let bytes = &mut self.pages[page];
+            bytes[offset..][..32].copy_from_slice(&entry);
+            bytes[offset + 32..][..payload.len()].copy_from_slice(payload);
+
+            for index in index..index + span {
+                bytes[BITMAP_OFFSET as usize + index / 4] &= !(1 << ((index % 4) * 2));
+            }
+            self.next[page] += span as u32;
+        }
+
+        fn namespace(&mut self, page: usize, name: &str, index: u8) {
+            self.entry(
+                page,
+                0,
+                TYPE_U8,
+                0xff,
+                name,
+                [index, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                &[],
+            );
+        }
+
+        fn variable(
+            &mut self,
+            page: usize,
+            namespace: u8,
+            ty: u8,
+            chunk_index: u8,
+            key: &str,
+            payload: &[u8],
+        ) {
+            let mut data = [0xffu8; 8];
+            data[..2].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+            data[4..].copy_from_slice(&crc32_le(0xffff_ffff, payload).to_le_bytes());
+            self.entry(page, namespace, ty, chunk_index, key, data, payload);
+        }
+
+        fn write(&self, flash: &mut FlashStorage) {
+            flash.erase(BASE, BASE + PAGES * PAGE_SIZE).unwrap();
+            for (index, page) in self.pages.iter().enumerate() {
+                flash.write(BASE + index as u32 * PAGE_SIZE, page).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn read_values() {
+        let mut builder = PageBuilder::new();
+        builder.namespace(0, "storage", 1);
+        builder.namespace(0, "other", 2);
+        builder.entry(
+            0,
+            1,
+            TYPE_U8,
+            0xff,
+            "u8",
+            [0x12, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            &[],
+        );
+        builder.entry(0, 1, TYPE_I32, 0xff, "i32", (-5i64).to_le_bytes(), &[]);
+        builder.entry(
+            0,
+            2,
+            TYPE_U64,
+            0xff,
+            "u64",
+            0x0123_4567_89ab_cdefu64.to_le_bytes(),
+            &[],
+        );
+        builder.variable(
+            0,
+            1,
+            TYPE_STR,
+            0xff,
+            "ssid",
+            b"my network name which is long\0",
+        );
+
+        // A blob split into two chunks on different pages
+        let blob: [u8; 100] = core::array::from_fn(|index| index as u8);
+        builder.variable(0, 1, TYPE_BLOB_DATA, 0, "blob", &blob[..60]);
+        builder.variable(1, 1, TYPE_BLOB_DATA, 1, "blob", &blob[60..]);
+        let mut index = [0xffu8; 8];
+        index[..4].copy_from_slice(&100u32.to_le_bytes());
+        index[4] = 2;
+        index[5] = 0;
+        builder.entry(1, 1, TYPE_BLOB_IDX, 0xff, "blob", index, &[]);
+
+        // A newer value on a later page shadows the older one
+        builder.entry(
+            1,
+            1,
+            TYPE_U8,
+            0xff,
+            "u8",
+            [0x34, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            &[],
+        );
+
+        let mut flash = FlashStorage::new();
+        builder.write(&mut flash);
+
+        let mut nvs = Nvs::new(flash.region(BASE, PAGES * PAGE_SIZE).unwrap());
+        assert_eq!(nvs.get_u8("storage", "u8").unwrap(), 0x34);
+        assert_eq!(nvs.get_i32("storage", "i32").unwrap(), -5);
+        assert_eq!(nvs.get_u64("other", "u64").unwrap(), 0x0123_4567_89ab_cdef);
+
+        let mut buffer = [0u8; 128];
+        assert_eq!(
+            nvs.get_str("storage", "ssid", &mut buffer).unwrap(),
+            "my network name which is long"
+        );
+        assert_eq!(nvs.get_blob("storage", "blob", &mut buffer).unwrap(), &blob);
+
+        assert!(matches!(
+            nvs.get_blob("storage", "blob", &mut buffer[..99]),
+            Err(NvsError::BufferTooSmall(100))
+        ));
+        assert!(matches!(
+            nvs.get_u16("storage", "u8"),
+            Err(NvsError::TypeMismatch)
+        ));
+        assert!(matches!(
+            nvs.get_u8("storage", "missing"),
+            Err(NvsError::KeyNotFound)
+        ));
+        assert!(matches!(
+            nvs.get_u8("missing", "u8"),
+            Err(NvsError::NamespaceNotFound)
+        ));
+        assert!(matches!(
+            nvs.get_u8("storage", "a key which is too long"),
+            Err(NvsError::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn corrupted_data_is_reported() {
+        let mut builder = PageBuilder::new();
+        builder.namespace(0, "storage", 1);
+        builder.variable(0, 1, TYPE_STR, 0xff, "ssid", b"network\0");
+        // Flip a bit in the string payload
+        builder.pages[0][ENTRIES_OFFSET as usize + 2 * ENTRY_SIZE as usize] ^= 1;
+        // Corrupt the header of the second page
+        builder.pages[1][4] ^= 1;
+
+        let mut flash = FlashStorage::new();
+        builder.write(&mut flash);
+
+        let mut nvs = Nvs::new(flash.region(BASE, PAGES * PAGE_SIZE).unwrap());
+        let mut buffer = [0u8; 16];
+        assert!(matches!(
+            nvs.get_str("storage", "ssid", &mut buffer),
+            Err(NvsError::CorruptedPage(1))
+        ));
+
+        // Without the corrupted page, the bad payload checksum is reported
+        let mut region = nvs.into_inner();
+        region.erase(PAGE_SIZE, 2 * PAGE_SIZE).unwrap();
+        let mut nvs = Nvs::new(region);
+        assert!(matches!(
+            nvs.get_str("storage", "ssid", &mut buffer),
+            Err(NvsError::CorruptedValue)
+        ));
+    }
+}
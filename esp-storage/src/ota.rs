@@ -0,0 +1,425 @@
+//! OTA boot selection
+//!
+//! The second stage bootloader of ESP-IDF picks the OTA slot to boot from the
+//! `otadata` partition. It holds two copies of a selection entry, one per
+//! sector, and the valid copy with the higher sequence number wins. The slot
+//! is derived from the sequence number as `(seq - 1) % slot_count`.
+//!
+//! Updates are always written to the copy which isn't active, so a power cut
+//! in the middle of an update leaves the previous selection intact.
+//!
+//! ```rust, ignore
+//! let mut flash = FlashStorage::new();
+//! let table = PartitionTable::read(&mut flash)?;
+//!
+//! let mut ota = Ota::from_partition_table(&mut flash, &table)?;
+//! // After writing the new image into the OTA 1 partition
+//! ota.set_next_boot(Slot::Ota1)?;
+//! ```
+//!
+//! See <https://docs.espressif.com/projects/esp-idf/en/latest/esp32/api-reference/system/ota.html>
+
+use crate::{
+    crc::crc32_le,
+    partitions::{AppType, DataType, PartitionTable},
+    FlashRegion,
+    FlashStorage,
+    FlashStorageError,
+};
+
+const ENTRY_SIZE: usize = 32;
+const COPY_SIZE: u32 = FlashStorage::SECTOR_SIZE;
+const SEQ_ERASED: u32 = 0xffff_ffff;
+
+/// Errors which can occur when accessing the OTA data.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OtaError {
+    /// Accessing the flash failed.
+    Flash(FlashStorageError),
+    /// The partition table doesn't contain an `otadata` partition.
+    NoOtaData,
+    /// The `otadata` partition is smaller than two sectors.
+    InvalidPartition,
+    /// The slot doesn't exist in the partition table.
+    InvalidSlot,
+}
+
+impl From<FlashStorageError> for OtaError {
+    fn from(error: FlashStorageError) -> Self {
+        Self::Flash(error)
+    }
+}
+
+/// An OTA application slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Slot {
+    /// OTA slot 0
+    Ota0 = 0,
+    /// OTA slot 1
+    Ota1,
+    /// OTA slot 2
+    Ota2,
+    /// OTA slot 3
+    Ota3,
+    /// OTA slot 4
+    Ota4,
+    /// OTA slot 5
+    Ota5,
+    /// OTA slot 6
+    Ota6,
+    /// OTA slot 7
+    Ota7,
+    /// OTA slot 8
+    Ota8,
+    /// OTA slot 9
+    Ota9,
+    /// OTA slot 10
+    Ota10,
+    /// OTA slot 11
+    Ota11,
+    /// OTA slot 12
+    Ota12,
+    /// OTA slot 13
+    Ota13,
+    /// OTA slot 14
+    Ota14,
+    /// OTA slot 15
+    Ota15,
+}
+
+impl Slot {
+    const ALL: [Self; 16] = [
+        Self::Ota0,
+        Self::Ota1,
+        Self::Ota2,
+        Self::Ota3,
+        Self::Ota4,
+        Self::Ota5,
+        Self::Ota6,
+        Self::Ota7,
+        Self::Ota8,
+        Self::Ota9,
+        Self::Ota10,
+        Self::Ota11,
+        Self::Ota12,
+        Self::Ota13,
+        Self::Ota14,
+        Self::Ota15,
+    ];
+
+    /// Returns the slot with the given index, if it is in range.
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Returns the index of the slot.
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+impl From<Slot> for AppType {
+    fn from(slot: Slot) -> Self {
+        // Every slot has a corresponding subtype
+        AppType::ota(slot.index()).unwrap()
+    }
+}
+
+/// State of the image selected by an entry, used by the rollback logic of
+/// the bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtaImageState {
+    /// The image was just selected and hasn't been booted yet.
+    New,
+    /// The image was booted once and has to be marked valid by the
+    /// application, otherwise the bootloader rolls back on the next boot.
+    PendingVerify,
+    /// The image was marked valid.
+    Valid,
+    /// The image was marked invalid.
+    Invalid,
+    /// The image wasn't marked valid and was rolled back.
+    Aborted,
+    /// No state was set, which is what bootloaders without rollback support
+    /// expect.
+    Undefined,
+}
+
+impl OtaImageState {
+    fn from_raw(raw: u32) -> Self {
+        match raw {
+            0 => Self::New,
+            1 => Self::PendingVerify,
+            2 => Self::Valid,
+            3 => Self::Invalid,
+            4 => Self::Aborted,
+            _ => Self::Undefined,
+        }
+    }
+
+    fn into_raw(self) -> u32 {
+        match self {
+            Self::New => 0,
+            Self::PendingVerify => 1,
+            Self::Valid => 2,
+            Self::Invalid => 3,
+            Self::Aborted => 4,
+            Self::Undefined => 0xffff_ffff,
+        }
+    }
+}
+
+/// A raw 32 byte selection entry.
+#[derive(Clone, Copy)]
+struct Entry([u8; ENTRY_SIZE]);
+
+impl Entry {
+    fn new(seq: u32, state: OtaImageState) -> Self {
+        let mut entry = [0xff; ENTRY_SIZE];
+        entry[..4].copy_from_slice(&seq.to_le_bytes());
+        entry[24..28].copy_from_slice(&state.into_raw().to_le_bytes());
+        let crc = crc32_le(0xffff_ffff, &entry[..4]);
+        entry[28..].copy_from_slice(&crc.to_le_bytes());
+        Self(entry)
+    }
+
+    fn seq(&self) -> u32 {
+        u32::from_le_bytes([self.0[0], self.0[1], self.0[2], self.0[3]])
+    }
+
+    fn state(&self) -> OtaImageState {
+        OtaImageState::from_raw(u32::from_le_bytes([
+            self.0[24], self.0[25], self.0[26], self.0[27],
+        ]))
+    }
+
+    fn crc(&self) -> u32 {
+        u32::from_le_bytes([self.0[28], self.0[29], self.0[30], self.0[31]])
+    }
+
+    fn is_valid(&self) -> bool {
+        // Only the sequence number is covered by the CRC
+        self.seq() != SEQ_ERASED && self.crc() == crc32_le(0xffff_ffff, &self.0[..4])
+    }
+}
+
+/// Access to the `otadata` partition.
+pub struct Ota<'a> {
+    region: FlashRegion<'a>,
+    slot_count: u8,
+}
+
+impl<'a> Ota<'a> {
+    /// Uses `region` as the `otadata` partition, for a partition table with
+    /// `slot_count` OTA application partitions.
+    pub fn new(region: FlashRegion<'a>, slot_count: u8) -> Result<Self, OtaError> {
+        if region.capacity() < 2 * COPY_SIZE as usize {
+            return Err(OtaError::InvalidPartition);
+        }
+
+        Ok(Self { region, slot_count })
+    }
+
+    /// Uses the `otadata` partition of `table`, counting its OTA application
+    /// partitions.
+    pub fn from_partition_table(
+        flash: &'a mut FlashStorage,
+        table: &PartitionTable,
+    ) -> Result<Self, OtaError> {
+        let partition = table
+            .find_by_type(DataType::Ota)
+            .ok_or(OtaError::NoOtaData)?;
+        let slot_count = Slot::ALL
+            .into_iter()
+            .filter(|slot| table.find_by_type(AppType::from(*slot)).is_some())
+            .count() as u8;
+
+        Self::new(partition.as_region(flash)?, slot_count)
+    }
+
+    /// Returns the underlying region.
+    pub fn into_inner(self) -> FlashRegion<'a> {
+        self.region
+    }
+
+    /// Returns the slot the bootloader selects, or `None` if there is no
+    /// valid selection and the factory application is booted.
+    pub fn current_slot(&mut self) -> Result<Option<Slot>, OtaError> {
+        let entries = self.read_entries()?;
+        Ok(Self::active(&entries).and_then(|index| self.slot(entries[index].seq())))
+    }
+
+    /// Returns the state of the selected image, or `None` if there is no
+    /// valid selection.
+    pub fn current_state(&mut self) -> Result<Option<OtaImageState>, OtaError> {
+        let entries = self.read_entries()?;
+        Ok(Self::active(&entries).map(|index| entries[index].state()))
+    }
+
+    /// Selects `slot` for the next boot.
+    ///
+    /// The image state is set to [`OtaImageState::New`], so a bootloader with
+    /// rollback support expects the application to call
+    /// [`Ota::mark_app_valid`] after booting it.
+    pub fn set_next_boot(&mut self, slot: Slot) -> Result<(), OtaError> {
+        if slot.index() >= self.slot_count {
+            return Err(OtaError::InvalidSlot);
+        }
+
+        let entries = self.read_entries()?;
+        let mut seq = slot.index() as u32 + 1;
+
+        let copy = match Self::active(&entries) {
+            Some(index) => {
+                // The smallest sequence number above the current one which
+                // selects the slot, so the new copy wins
+                while seq <= entries[index].seq() {
+                    seq += self.slot_count as u32;
+                }
+                1 - index
+            }
+            None => 0,
+        };
+
+        self.write_entry(copy, Entry::new(seq, OtaImageState::New))
+    }
+
+    /// Marks the currently selected image as valid, which cancels a pending
+    /// rollback.
+    ///
+    /// Does nothing if the image is already valid, or if there is no valid
+    /// selection.
+    pub fn mark_app_valid(&mut self) -> Result<(), OtaError> {
+        let entries = self.read_entries()?;
+        let Some(index) = Self::active(&entries) else {
+            return Ok(());
+        };
+        if entries[index].state() == OtaImageState::Valid {
+            return Ok(());
+        }
+
+        // Stepping by the slot count keeps the slot, and the higher sequence
+        // number makes the new copy win over the current one
+        let seq = entries[index].seq() + self.slot_count as u32;
+        self.write_entry(1 - index, Entry::new(seq, OtaImageState::Valid))
+    }
+
+    fn slot(&self, seq: u32) -> Option<Slot> {
+        if self.slot_count == 0 {
+            return None;
+        }
+        Slot::from_index((seq.checked_sub(1)? % self.slot_count as u32) as u8)
+    }
+
+    /// Returns the index of the active copy, if any.
+    fn active(entries: &[Entry; 2]) -> Option<usize> {
+        match (entries[0].is_valid(), entries[1].is_valid()) {
+            (true, true) if entries[1].seq() > entries[0].seq() => Some(1),
+            (true, _) => Some(0),
+            (false, true) => Some(1),
+            (false, false) => None,
+        }
+    }
+
+    fn read_entries(&mut self) -> Result<[Entry; 2], OtaError> {
+        let mut entries = [Entry([0; ENTRY_SIZE]); 2];
+        for (copy, entry) in entries.iter_mut().enumerate() {
+            self.region.read(copy as u32 * COPY_SIZE, &mut entry.0)?;
+        }
+        Ok(entries)
+    }
+
+    fn write_entry(&mut self, copy: usize, entry: Entry) -> Result<(), OtaError> {
+        let offset = copy as u32 * COPY_SIZE;
+        self.region.erase(offset, offset + COPY_SIZE)?;
+        self.region.write(offset, &entry.0)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Use sectors which aren't touched by the other tests
+    const BASE: u32 = FlashStorage::SECTOR_SIZE * 13;
+
+    fn erase(flash: &mut FlashStorage) {
+        flash.erase(BASE, BASE + 2 * COPY_SIZE).unwrap();
+    }
+
+    #[test]
+    fn fresh_otadata() {
+        let mut flash = FlashStorage::new();
+        erase(&mut flash);
+
+        let mut ota = Ota::new(flash.region(BASE, 2 * COPY_SIZE).unwrap(), 2).unwrap();
+        assert_eq!(ota.current_slot().unwrap(), None);
+        assert_eq!(ota.current_state().unwrap(), None);
+        // Nothing is booted from otadata, so there is nothing to mark
+        ota.mark_app_valid().unwrap();
+        assert_eq!(ota.current_slot().unwrap(), None);
+
+        ota.set_next_boot(Slot::Ota1).unwrap();
+        assert_eq!(ota.current_slot().unwrap(), Some(Slot::Ota1));
+        assert_eq!(ota.current_state().unwrap(), Some(OtaImageState::New));
+
+        ota.mark_app_valid().unwrap();
+        assert_eq!(ota.current_slot().unwrap(), Some(Slot::Ota1));
+        assert_eq!(ota.current_state().unwrap(), Some(OtaImageState::Valid));
+
+        ota.set_next_boot(Slot::Ota0).unwrap();
+        assert_eq!(ota.current_slot().unwrap(), Some(Slot::Ota0));
+
+        assert!(matches!(
+            ota.set_next_boot(Slot::Ota2),
+            Err(OtaError::InvalidSlot)
+        ));
+
+        // Both copies are in use after several updates
+        let mut region = ota.into_inner();
+        let mut entries = [Entry([0; ENTRY_SIZE]); 2];
+        for (copy, entry) in entries.iter_mut().enumerate() {
+            region.read(copy as u32 * COPY_SIZE, &mut entry.0).unwrap();
+        }
+        assert!(entries.iter().all(Entry::is_valid));
+    }
+
+    #[test]
+    fn corrupted_copy() {
+        let mut flash = FlashStorage::new();
+        erase(&mut flash);
+
+        // The copy with the higher sequence number has a broken CRC
+        let mut corrupted = Entry::new(2, OtaImageState::Valid);
+        corrupted.0[28] ^= 1;
+        flash
+            .write(BASE, &Entry::new(1, OtaImageState::Valid).0)
+            .unwrap();
+        flash.write(BASE + COPY_SIZE, &corrupted.0).unwrap();
+
+        let mut ota = Ota::new(flash.region(BASE, 2 * COPY_SIZE).unwrap(), 2).unwrap();
+        assert_eq!(ota.current_slot().unwrap(), Some(Slot::Ota0));
+
+        // The corrupted copy is replaced, the valid one is kept
+        ota.set_next_boot(Slot::Ota1).unwrap();
+        assert_eq!(ota.current_slot().unwrap(), Some(Slot::Ota1));
+
+        let mut region = ota.into_inner();
+        let mut entry = Entry([0; ENTRY_SIZE]);
+        region.read(0, &mut entry.0).unwrap();
+        assert!(entry.is_valid());
+        assert_eq!(entry.seq(), 1);
+    }
+
+    #[test]
+    fn partition_too_small() {
+        let mut flash = FlashStorage::new();
+        assert!(matches!(
+            Ota::new(flash.region(BASE, COPY_SIZE).unwrap(), 2),
+            Err(OtaError::InvalidPartition)
+        ));
+    }
+}
@@ -0,0 +1,546 @@
+//! ESP-IDF partition table
+//!
+//! The partition table describes how the flash is split into partitions. In
+//! the standard layout it lives at [`PARTITION_TABLE_OFFSET`] and consists of
+//! up to [`MAX_PARTITIONS`] entries of 32 bytes, optionally followed by an
+//! entry holding the MD5 checksum of the preceding entries.
+//!
+//! See <https://docs.espressif.com/projects/esp-idf/en/latest/esp32/api-guides/partition-tables.html>
+
+use crate::{md5, FlashRegion, FlashStorage, FlashStorageError};
+
+/// Offset of the partition table in the standard flash layout.
+pub const PARTITION_TABLE_OFFSET: u32 = 0x8000;
+
+/// Maximum size of the partition table in bytes.
+pub const PARTITION_TABLE_SIZE: usize = 0xc00;
+
+/// Maximum number of partitions, leaving room for the MD5 checksum entry.
+pub const MAX_PARTITIONS: usize = PARTITION_TABLE_SIZE / ENTRY_SIZE - 1;
+
+const ENTRY_SIZE: usize = 32;
+const ENTRY_MAGIC: [u8; 2] = [0xaa, 0x50];
+const MD5_MAGIC: [u8; 2] = [0xeb, 0xeb];
+const END_MAGIC: [u8; 2] = [0xff, 0xff];
+
+const FLAG_ENCRYPTED: u32 = 1 << 0;
+const FLAG_READONLY: u32 = 1 << 1;
+
+/// Errors which can occur when reading the partition table.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PartitionTableError {
+    /// Reading the flash failed.
+    Flash(FlashStorageError),
+    /// An entry with an unknown magic was found at the given index.
+    InvalidMagic(usize),
+    /// The MD5 checksum doesn't match the entries.
+    InvalidChecksum,
+    /// The table doesn't fit into [`PARTITION_TABLE_SIZE`].
+    TooManyEntries,
+}
+
+impl From<FlashStorageError> for PartitionTableError {
+    fn from(error: FlashStorageError) -> Self {
+        Self::Flash(error)
+    }
+}
+
+/// Subtypes of application partitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AppType {
+    /// Factory application
+    Factory = 0x00,
+    /// OTA slot 0
+    Ota0    = 0x10,
+    /// OTA slot 1
+    Ota1    = 0x11,
+    /// OTA slot 2
+    Ota2    = 0x12,
+    /// OTA slot 3
+    Ota3    = 0x13,
+    /// OTA slot 4
+    Ota4    = 0x14,
+    /// OTA slot 5
+    Ota5    = 0x15,
+    /// OTA slot 6
+    Ota6    = 0x16,
+    /// OTA slot 7
+    Ota7    = 0x17,
+    /// OTA slot 8
+    Ota8    = 0x18,
+    /// OTA slot 9
+    Ota9    = 0x19,
+    /// OTA slot 10
+    Ota10   = 0x1a,
+    /// OTA slot 11
+    Ota11   = 0x1b,
+    /// OTA slot 12
+    Ota12   = 0x1c,
+    /// OTA slot 13
+    Ota13   = 0x1d,
+    /// OTA slot 14
+    Ota14   = 0x1e,
+    /// OTA slot 15
+    Ota15   = 0x1f,
+    /// Test application
+    Test    = 0x20,
+}
+
+impl AppType {
+    const ALL: [Self; 18] = [
+        Self::Factory,
+        Self::Ota0,
+        Self::Ota1,
+        Self::Ota2,
+        Self::Ota3,
+        Self::Ota4,
+        Self::Ota5,
+        Self::Ota6,
+        Self::Ota7,
+        Self::Ota8,
+        Self::Ota9,
+        Self::Ota10,
+        Self::Ota11,
+        Self::Ota12,
+        Self::Ota13,
+        Self::Ota14,
+        Self::Ota15,
+        Self::Test,
+    ];
+
+    fn from_raw(raw: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| *ty as u8 == raw)
+    }
+
+    /// Returns the OTA slot for the given index, if it is in range.
+    pub fn ota(index: u8) -> Option<Self> {
+        if index < 16 {
+            Self::from_raw(Self::Ota0 as u8 + index)
+        } else {
+            None
+        }
+    }
+}
+
+/// Subtypes of data partitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DataType {
+    /// OTA selection data
+    Ota       = 0x00,
+    /// PHY initialisation data
+    Phy       = 0x01,
+    /// Non-volatile storage
+    Nvs       = 0x02,
+    /// Core dump
+    Coredump  = 0x03,
+    /// NVS encryption keys
+    NvsKeys   = 0x04,
+    /// Emulated eFuse values
+    EfuseEm   = 0x05,
+    /// Undefined data
+    Undefined = 0x06,
+    /// ESP HTTPD file system
+    Esphttpd  = 0x80,
+    /// FAT file system
+    Fat       = 0x81,
+    /// SPIFFS file system
+    Spiffs    = 0x82,
+    /// LittleFS file system
+    LittleFs  = 0x83,
+}
+
+impl DataType {
+    const ALL: [Self; 11] = [
+        Self::Ota,
+        Self::Phy,
+        Self::Nvs,
+        Self::Coredump,
+        Self::NvsKeys,
+        Self::EfuseEm,
+        Self::Undefined,
+        Self::Esphttpd,
+        Self::Fat,
+        Self::Spiffs,
+        Self::LittleFs,
+    ];
+
+    fn from_raw(raw: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| *ty as u8 == raw)
+    }
+}
+
+/// Type and subtype of a partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    /// Application partition
+    App(AppType),
+    /// Data partition
+    Data(DataType),
+    /// Any other combination of type and subtype
+    Custom {
+        /// Raw type
+        ty: u8,
+        /// Raw subtype
+        subtype: u8,
+    },
+}
+
+impl PartitionType {
+    fn from_raw(ty: u8, subtype: u8) -> Self {
+        let known = match ty {
+            0x00 => AppType::from_raw(subtype).map(Self::App),
+            0x01 => DataType::from_raw(subtype).map(Self::Data),
+            _ => None,
+        };
+        known.unwrap_or(Self::Custom { ty, subtype })
+    }
+}
+
+impl From<AppType> for PartitionType {
+    fn from(ty: AppType) -> Self {
+        Self::App(ty)
+    }
+}
+
+impl From<DataType> for PartitionType {
+    fn from(ty: DataType) -> Self {
+        Self::Data(ty)
+    }
+}
+
+/// An entry of the partition table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionEntry {
+    ty: u8,
+    subtype: u8,
+    offset: u32,
+    size: u32,
+    label: [u8; 16],
+    flags: u32,
+}
+
+impl PartitionEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+
+        let mut label = [0u8; 16];
+        label.copy_from_slice(&bytes[12..28]);
+
+        Self {
+            ty: bytes[2],
+            subtype: bytes[3],
+            offset: word(4),
+            size: word(8),
+            label,
+            flags: word(28),
+        }
+    }
+
+    /// Returns the type and subtype of the partition.
+    pub fn partition_type(&self) -> PartitionType {
+        PartitionType::from_raw(self.ty, self.subtype)
+    }
+
+    /// Returns the raw type of the partition.
+    pub fn raw_type(&self) -> u8 {
+        self.ty
+    }
+
+    /// Returns the raw subtype of the partition.
+    pub fn raw_subtype(&self) -> u8 {
+        self.subtype
+    }
+
+    /// Returns the offset of the partition in the flash.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the size of the partition in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the label of the partition without the trailing NUL bytes.
+    pub fn label(&self) -> &[u8] {
+        let len = self
+            .label
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(self.label.len());
+        &self.label[..len]
+    }
+
+    /// Returns the label of the partition as a string, if it is valid UTF-8.
+    pub fn label_as_str(&self) -> Option<&str> {
+        core::str::from_utf8(self.label()).ok()
+    }
+
+    /// Returns whether the partition is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Returns whether the partition is marked as read-only.
+    pub fn is_readonly(&self) -> bool {
+        self.flags & FLAG_READONLY != 0
+    }
+
+    /// Creates a [`FlashRegion`] covering the partition.
+    pub fn as_region<'a>(
+        &self,
+        flash: &'a mut FlashStorage,
+    ) -> Result<FlashRegion<'a>, FlashStorageError> {
+        flash.region(self.offset, self.size)
+    }
+}
+
+/// A copy of the partition table read from flash.
+///
+/// The table is kept in its raw form, so it takes [`PARTITION_TABLE_SIZE`]
+/// bytes of memory. Entries are decoded when accessed.
+pub struct PartitionTable {
+    data: [u8; PARTITION_TABLE_SIZE],
+    len: usize,
+}
+
+impl core::fmt::Debug for PartitionTable {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartitionTable {
+    /// Reads the partition table from [`PARTITION_TABLE_OFFSET`].
+    pub fn read(flash: &mut FlashStorage) -> Result<Self, PartitionTableError> {
+        Self::read_from(flash, PARTITION_TABLE_OFFSET)
+    }
+
+    /// Reads the partition table from the given offset.
+    ///
+    /// The MD5 checksum entry is validated if present. Tables generated with
+    /// the checksum disabled are accepted as well.
+    pub fn read_from(flash: &mut FlashStorage, offset: u32) -> Result<Self, PartitionTableError> {
+        let mut table = Self {
+            data: [0xff; PARTITION_TABLE_SIZE],
+            len: 0,
+        };
+        flash.read(offset, &mut table.data)?;
+
+        for (index, entry) in table.data.chunks_exact(ENTRY_SIZE).enumerate() {
+            match [entry[0], entry[1]] {
+                ENTRY_MAGIC if index < MAX_PARTITIONS => table.len += 1,
+                ENTRY_MAGIC => return Err(PartitionTableError::TooManyEntries),
+                MD5_MAGIC => {
+                    let digest = md5::digest(&table.data[..index * ENTRY_SIZE]);
+                    if entry[16..] != digest {
+                        return Err(PartitionTableError::InvalidChecksum);
+                    }
+                    break;
+                }
+                END_MAGIC => break,
+                _ => return Err(PartitionTableError::InvalidMagic(index)),
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Returns the number of partitions.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the table doesn't contain any partitions.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the partition at the given index.
+    pub fn get(&self, index: usize) -> Option<PartitionEntry> {
+        if index < self.len {
+            Some(PartitionEntry::from_bytes(
+                &self.data[index * ENTRY_SIZE..][..ENTRY_SIZE],
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the partitions.
+    pub fn iter(&self) -> impl Iterator<Item = PartitionEntry> + '_ {
+        (0..self.len).filter_map(|index| self.get(index))
+    }
+
+    /// Returns the first partition with the given label.
+    pub fn find(&self, label: &str) -> Option<PartitionEntry> {
+        self.iter().find(|entry| entry.label() == label.as_bytes())
+    }
+
+    /// Returns the first partition with the given type.
+    pub fn find_by_type(&self, ty: impl Into<PartitionType>) -> Option<PartitionEntry> {
+        let ty = ty.into();
+        self.iter().find(|entry| entry.partition_type() == ty)
+    }
+}
+
+/// Returns the end of the partition which reaches furthest into the flash.
+///
+/// The table at `offset` is read entry by entry, which needs far less stack
+/// than [`PartitionTable`]. The MD5 checksum isn't validated,
+/// but any unknown magic or a missing end marker makes this return `None`.
+pub(crate) fn highest_end(flash: &mut FlashStorage, offset: u32) -> Option<u32> {
+    let mut entry = [0u8; ENTRY_SIZE];
+    let mut end = None;
+
+    for index in 0..=MAX_PARTITIONS {
+        flash
+            .read(offset + (index * ENTRY_SIZE) as u32, &mut entry)
+            .ok()?;
+
+        match [entry[0], entry[1]] {
+            ENTRY_MAGIC if index < MAX_PARTITIONS => {
+                let entry = PartitionEntry::from_bytes(&entry);
+                end = end.max(Some(entry.offset.checked_add(entry.size)?));
+            }
+            MD5_MAGIC | END_MAGIC => return end,
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Use a sector which isn't touched by the other tests
+    const BASE: u32 = FlashStorage::SECTOR_SIZE * 4;
+
+    fn entry(ty: u8, subtype: u8, offset: u32, size: u32, label: &str, flags: u32) -> [u8; 32] {
+        let mut entry = [0u8; 32];
+        entry[..2].copy_from_slice(&ENTRY_MAGIC);
+        entry[2] = ty;
+        entry[3] = subtype;
+        entry[4..8].copy_from_slice(&offset.to_le_bytes());
+        entry[8..12].copy_from_slice(&size.to_le_bytes());
+        entry[12..][..label.len()].copy_from_slice(label.as_bytes());
+        entry[28..].copy_from_slice(&flags.to_le_bytes());
+        entry
+    }
+
+    fn write_table(flash: &mut FlashStorage, entries: &[[u8; 32]], corrupt: bool) {
+        let mut table = [0xffu8; 4 * 32];
+        for (chunk, entry) in table.chunks_exact_mut(32).zip(entries) {
+            chunk.copy_from_slice(entry);
+        }
+
+        let len = entries.len() * 32;
+        let md5 = &mut table[len..][..32];
+        md5[..2].copy_from_slice(&MD5_MAGIC);
+        let digest = md5::digest(&table[..len]);
+        table[len + 16..][..16].copy_from_slice(&digest);
+        if corrupt {
+            table[len + 16] ^= 1;
+        }
+
+        flash.erase(BASE, BASE + FlashStorage::SECTOR_SIZE).unwrap();
+        flash.write(BASE, &table).unwrap();
+    }
+
+    #[test]
+    fn highest_end_of_table() {
+        let mut flash = FlashStorage::new();
+        write_table(
+            &mut flash,
+            &[
+                entry(0x01, 0x02, 0x9000, 0x6000, "nvs", 0),
+                entry(0x00, 0x00, 0x10000, 0x1f0000, "factory", 0),
+                entry(0x01, 0x01, 0xf000, 0x1000, "phy_init", 0),
+            ],
+            false,
+        );
+        assert_eq!(highest_end(&mut flash, BASE), Some(0x200000));
+
+        // Garbage instead of a table
+        flash.erase(BASE, BASE + FlashStorage::SECTOR_SIZE).unwrap();
+        flash.write(BASE, &[0x12; 32]).unwrap();
+        assert_eq!(highest_end(&mut flash, BASE), None);
+
+        // An entry ending above 4 GiB
+        write_table(
+            &mut flash,
+            &[entry(0x00, 0x00, 0x10000, u32::MAX, "factory", 0)],
+            false,
+        );
+        assert_eq!(highest_end(&mut flash, BASE), None);
+    }
+
+    #[test]
+    fn parse_partition_table() {
+        let mut flash = FlashStorage::new();
+        write_table(
+            &mut flash,
+            &[
+                entry(0x01, 0x02, 0x9000, 0x6000, "nvs", 0),
+                entry(0x00, 0x10, 0x10000, 0x100000, "ota_0", FLAG_ENCRYPTED),
+                entry(0x40, 0x01, 0x110000, 0x1000, "custom", FLAG_READONLY),
+            ],
+            false,
+        );
+
+        let table = PartitionTable::read_from(&mut flash, BASE).unwrap();
+        assert_eq!(table.len(), 3);
+
+        let nvs = table.find("nvs").unwrap();
+        assert_eq!(nvs.partition_type(), PartitionType::Data(DataType::Nvs));
+        assert_eq!((nvs.offset(), nvs.size()), (0x9000, 0x6000));
+        assert!(!nvs.is_encrypted());
+
+        let ota = table.find_by_type(AppType::Ota0).unwrap();
+        assert_eq!(ota.label_as_str(), Some("ota_0"));
+        assert!(ota.is_encrypted());
+
+        let custom = table.get(2).unwrap();
+        assert_eq!(
+            custom.partition_type(),
+            PartitionType::Custom {
+                ty: 0x40,
+                subtype: 0x01
+            }
+        );
+        assert!(custom.is_readonly());
+
+        assert!(table.get(3).is_none());
+        assert!(table.find("factory").is_none());
+        assert!(table.find_by_type(DataType::Phy).is_none());
+    }
+
+    #[test]
+    fn reject_invalid_partition_table() {
+        let mut flash = FlashStorage::new();
+        write_table(
+            &mut flash,
+            &[entry(0x01, 0x02, 0x9000, 0x6000, "nvs", 0)],
+            true,
+        );
+        assert!(matches!(
+            PartitionTable::read_from(&mut flash, BASE),
+            Err(PartitionTableError::InvalidChecksum)
+        ));
+
+        let mut garbage = entry(0x01, 0x02, 0x9000, 0x6000, "nvs", 0);
+        garbage[0] = 0x12;
+        write_table(&mut flash, &[garbage], false);
+        assert!(matches!(
+            PartitionTable::read_from(&mut flash, BASE),
+            Err(PartitionTableError::InvalidMagic(0))
+        ));
+    }
+}
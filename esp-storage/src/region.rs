@@ -0,0 +1,170 @@
+use crate::{common::range_length, FlashStorage, FlashStorageError};
+
+/// A window into the flash which can only access `len` bytes starting at
+/// `offset`.
+///
+/// All offsets passed to a region are relative to the start of the region,
+/// and accesses which don't fit into the window are rejected with
+/// [`FlashStorageError::OutOfBounds`]. This makes it possible to hand a single
+/// partition to e.g. a file system without risking that it overwrites the
+/// rest of the flash.
+///
+/// A region mutably borrows its [`FlashStorage`], so only one region of a
+/// given storage can be alive at any time.
+#[derive(Debug)]
+pub struct FlashRegion<'a> {
+    flash: &'a mut FlashStorage,
+    offset: u32,
+    len: u32,
+}
+
+impl FlashStorage {
+    /// Creates a [`FlashRegion`] covering `len` bytes starting at `offset`.
+    ///
+    /// Both `offset` and `len` must be a multiple of [`Self::SECTOR_SIZE`],
+    /// otherwise [`FlashStorageError::NotAligned`] is returned. The window
+    /// must fit into the flash, otherwise [`FlashStorageError::OutOfBounds`] is
+    /// returned.
+    pub fn region(&mut self, offset: u32, len: u32) -> Result<FlashRegion<'_>, FlashStorageError> {
+        self.check_alignment::<{ Self::SECTOR_SIZE }>(offset, len as usize)?;
+        self.check_bounds(offset, len as usize)?;
+
+        Ok(FlashRegion {
+            flash: self,
+            offset,
+            len,
+        })
+    }
+}
+
+impl FlashRegion<'_> {
+    /// Returns the offset of the region in the flash.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn capacity(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Translates an access relative to the region into an absolute flash
+    /// offset.
+    #[inline(always)]
+    pub(crate) fn translate(&self, offset: u32, length: usize) -> Result<u32, FlashStorageError> {
+        if length > self.len as usize || offset > self.len - length as u32 {
+            return Err(FlashStorageError::OutOfBounds {
+                offset,
+                length,
+                capacity: self.len as usize,
+            });
+        }
+        Ok(self.offset + offset)
+    }
+
+    /// Reads `bytes.len()` bytes starting at `offset`.
+    ///
+    /// See [`FlashStorage::read`].
+    pub fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), FlashStorageError> {
+        let offset = self.translate(offset, bytes.len())?;
+        self.flash.read(offset, bytes)
+    }
+
+    /// Reads `bytes.len()` bytes starting at `offset`.
+    ///
+    /// See [`FlashStorage::read_aligned`].
+    pub fn read_aligned(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), FlashStorageError> {
+        let offset = self.translate(offset, bytes.len())?;
+        self.flash.read_aligned(offset, bytes)
+    }
+
+    /// Writes `bytes` starting at `offset` without erasing.
+    ///
+    /// See [`FlashStorage::write`].
+    pub fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), FlashStorageError> {
+        let offset = self.translate(offset, bytes.len())?;
+        self.flash.write(offset, bytes)
+    }
+
+    /// Writes `bytes` starting at `offset` without erasing.
+    ///
+    /// See [`FlashStorage::write_aligned`].
+    pub fn write_aligned(&mut self, offset: u32, bytes: &[u8]) -> Result<(), FlashStorageError> {
+        let offset = self.translate(offset, bytes.len())?;
+        self.flash.write_aligned(offset, bytes)
+    }
+
+    /// Erases the range `from..to`.
+    ///
+    /// See [`FlashStorage::erase`].
+    pub fn erase(&mut self, from: u32, to: u32) -> Result<(), FlashStorageError> {
+        let len = range_length(from, to, self.len as usize)?;
+        let from = self.translate(from, len)?;
+        self.flash.erase(from, from + len as u32)
+    }
+
+    pub(crate) fn flash(&mut self) -> &mut FlashStorage {
+        self.flash
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECTOR_SIZE: u32 = FlashStorage::SECTOR_SIZE;
+    // Use a sector which isn't touched by the other tests
+    const BASE: u32 = SECTOR_SIZE * 7;
+
+    #[test]
+    fn region_creation_is_validated() {
+        let mut flash = FlashStorage::new();
+        let capacity = flash.capacity() as u32;
+
+        assert!(matches!(
+            flash.region(BASE + 4, SECTOR_SIZE),
+            Err(FlashStorageError::NotAligned { .. })
+        ));
+        assert!(matches!(
+            flash.region(BASE, 4),
+            Err(FlashStorageError::NotAligned { .. })
+        ));
+        assert!(matches!(
+            flash.region(capacity, SECTOR_SIZE),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+        assert_eq!(
+            flash.region(BASE, SECTOR_SIZE).unwrap().capacity(),
+            SECTOR_SIZE as usize
+        );
+    }
+
+    #[test]
+    fn region_accesses_are_translated_and_bounded() {
+        let mut flash = FlashStorage::new();
+        let mut region = flash.region(BASE, SECTOR_SIZE).unwrap();
+
+        region.erase(0, SECTOR_SIZE).unwrap();
+        region.write(SECTOR_SIZE - 3, &[1, 2, 3]).unwrap();
+
+        assert!(matches!(
+            region.write(SECTOR_SIZE - 2, &[1, 2, 3]),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            region.read(SECTOR_SIZE, &mut [0u8; 1]),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            region.erase(SECTOR_SIZE, 2 * SECTOR_SIZE),
+            Err(FlashStorageError::OutOfBounds { .. })
+        ));
+
+        let mut bytes = [0u8; 4];
+        region.read(SECTOR_SIZE - 4, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff, 1, 2, 3]);
+
+        flash.read(BASE + SECTOR_SIZE - 4, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff, 1, 2, 3]);
+    }
+}
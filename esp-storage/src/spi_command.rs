@@ -0,0 +1,332 @@
+//! Raw commands sent to the SPI flash through the user command interface of
+//! the SPI1 peripheral.
+//!
+//! The register layout is the same on all chips except for the offsets, which
+//! differ between the ESP32 and the chips using the SPI_MEM peripheral.
+//!
+//! The cache can't fetch code from flash while a command is executed, so the
+//! callers must be placed in RAM.
+
+const SPI_USR: u32 = 1 << 18;
+const SPI_WP_REG: u32 = 1 << 21;
+const SPI_USR_MOSI: u32 = 1 << 27;
+const SPI_USR_MISO: u32 = 1 << 28;
+const SPI_USR_DUMMY: u32 = 1 << 29;
+const SPI_USR_ADDR: u32 = 1 << 30;
+const SPI_USR_COMMAND: u32 = 1 << 31;
+const SPI_USR_COMMAND_BITLEN_S: u32 = 28;
+const SPI_USR_DUMMY_CYCLELEN_M: u32 = 0x3f;
+const SPI_USR_ADDR_BITLEN_S: u32 = 26;
+const SPI_USR_ADDR_BITLEN_M: u32 = 0x3f << SPI_USR_ADDR_BITLEN_S;
+
+/// Number of words in the data buffer, W0 to W15.
+const BUFFER_WORDS: usize = 16;
+const PAGE_SIZE: u32 = 256;
+
+const CMD_WRSR: u8 = 0x01;
+const CMD_RDSR: u8 = 0x05;
+const CMD_WREN: u8 = 0x06;
+const CMD_RDSR2: u8 = 0x35;
+const CMD_RDID: u8 = 0x9f;
+const CMD_RUID: u8 = 0x4b;
+const CMD_FASTRD_4B: u8 = 0x0c;
+const CMD_PP_4B: u8 = 0x12;
+const CMD_SE_4B: u8 = 0x21;
+const CMD_BE_4B: u8 = 0xdc;
+
+const STATUS_WIP_BIT: u32 = 1 << 0;
+/// Block protect bits BP0 to BP3, protecting the whole flash when all set.
+const STATUS_WR_PROTECT: u32 = 0x3c;
+
+/// Addresses of the SPI1 registers used to send a user command.
+pub(crate) struct SpiRegisters {
+    pub cmd: u32,
+    pub addr: u32,
+    pub ctrl: u32,
+    pub user: u32,
+    pub user1: u32,
+    pub user2: u32,
+    pub mosi_dlen: u32,
+    pub miso_dlen: u32,
+    pub w0: u32,
+}
+
+impl SpiRegisters {
+    /// Register layout of the SPI_MEM peripheral found on all chips except
+    /// the ESP32.
+    #[cfg(not(feature = "esp32"))]
+    pub const fn spi_mem(base: u32) -> Self {
+        Self {
+            cmd: base,
+            addr: base + 0x04,
+            ctrl: base + 0x08,
+            user: base + 0x18,
+            user1: base + 0x1c,
+            user2: base + 0x20,
+            mosi_dlen: base + 0x24,
+            miso_dlen: base + 0x28,
+            w0: base + 0x58,
+        }
+    }
+}
+
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn read_register(address: u32) -> u32 {
+    unsafe { (address as *const u32).read_volatile() }
+}
+
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn write_register(address: u32, value: u32) {
+    unsafe { (address as *mut u32).write_volatile(value) }
+}
+
+/// Sends `command`, followed by `mosi_bits` bits of `mosi_data`, waits for
+/// `dummy_cycles` cycles and then reads `miso_bits` (at most 64) bits.
+///
+/// The SPI1 configuration is restored afterwards, so this can be freely mixed
+/// with the ROM functions.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn execute(
+    regs: &SpiRegisters,
+    command: u8,
+    mosi_data: u32,
+    mosi_bits: u32,
+    dummy_cycles: u32,
+    miso_bits: u32,
+) -> [u32; 2] {
+    let mut data = [0; 2];
+    transfer(
+        regs,
+        command,
+        None,
+        &[mosi_data],
+        mosi_bits,
+        dummy_cycles,
+        &mut data,
+        miso_bits,
+    );
+    data
+}
+
+/// Like [`execute`], but optionally sends a 32 bit `address` after the
+/// command, and takes the data from and reads it into word buffers of up to
+/// [`BUFFER_WORDS`] words.
+#[inline(always)]
+#[link_section = ".rwtext"]
+#[allow(clippy::too_many_arguments)]
+fn transfer(
+    regs: &SpiRegisters,
+    command: u8,
+    address: Option<u32>,
+    mosi: &[u32],
+    mosi_bits: u32,
+    dummy_cycles: u32,
+    miso: &mut [u32],
+    miso_bits: u32,
+) {
+    while read_register(regs.cmd) & SPI_USR != 0 {}
+
+    let old_ctrl = read_register(regs.ctrl);
+    let old_user = read_register(regs.user);
+    let old_user1 = read_register(regs.user1);
+    let old_user2 = read_register(regs.user2);
+
+    // Keep WP high while idle, otherwise the flash leaves DIO mode
+    write_register(regs.ctrl, SPI_WP_REG);
+
+    let mut user = old_user & !(SPI_USR_ADDR | SPI_USR_DUMMY | SPI_USR_MISO | SPI_USR_MOSI);
+    let mut user1 = old_user1;
+    user |= SPI_USR_COMMAND;
+    if let Some(address) = address {
+        user |= SPI_USR_ADDR;
+        user1 = (user1 & !SPI_USR_ADDR_BITLEN_M) | (31 << SPI_USR_ADDR_BITLEN_S);
+        write_register(regs.addr, address);
+    }
+    if mosi_bits > 0 {
+        user |= SPI_USR_MOSI;
+        write_register(regs.mosi_dlen, mosi_bits - 1);
+    }
+    if dummy_cycles > 0 {
+        user |= SPI_USR_DUMMY;
+        user1 = (user1 & !SPI_USR_DUMMY_CYCLELEN_M) | (dummy_cycles - 1);
+    }
+    if miso_bits > 0 {
+        user |= SPI_USR_MISO;
+        write_register(regs.miso_dlen, miso_bits - 1);
+    }
+    write_register(regs.user, user);
+    write_register(regs.user1, user1);
+    write_register(regs.user2, (7 << SPI_USR_COMMAND_BITLEN_S) | command as u32);
+
+    for (index, word) in mosi.iter().enumerate() {
+        write_register(regs.w0 + 4 * index as u32, *word);
+    }
+
+    write_register(regs.cmd, SPI_USR);
+    while read_register(regs.cmd) & SPI_USR != 0 {}
+
+    for (index, word) in miso.iter_mut().enumerate() {
+        *word = read_register(regs.w0 + 4 * index as u32);
+    }
+
+    write_register(regs.ctrl, old_ctrl);
+    write_register(regs.user, old_user);
+    write_register(regs.user1, old_user1);
+    write_register(regs.user2, old_user2);
+}
+
+/// Reads the JEDEC ID of the flash, returned as `manufacturer << 16 |
+/// memory_type << 8 | capacity`.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn read_id(regs: &SpiRegisters, dummy_cycles: u32) -> u32 {
+    let [id, _] = execute(regs, CMD_RDID, 0, 0, dummy_cycles, 24);
+    // The first byte received ends up in the least significant byte
+    ((id & 0xff) << 16) | (id & 0xff00) | ((id >> 16) & 0xff)
+}
+
+/// Reads the 64 bit unique ID of the flash.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn read_unique_id(regs: &SpiRegisters, dummy_cycles: u32) -> [u8; 8] {
+    // The ID follows four dummy bytes, which are sent as data
+    let [low, high] = execute(regs, CMD_RUID, 0, 32, dummy_cycles, 64);
+
+    let mut id = [0u8; 8];
+    id[..4].copy_from_slice(&low.to_le_bytes());
+    id[4..].copy_from_slice(&high.to_le_bytes());
+    id
+}
+
+/// Reads both status registers, the second one in the high byte.
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn read_status(regs: &SpiRegisters, dummy_cycles: u32) -> u32 {
+    let [low, _] = execute(regs, CMD_RDSR, 0, 0, dummy_cycles, 8);
+    let [high, _] = execute(regs, CMD_RDSR2, 0, 0, dummy_cycles, 8);
+    (high & 0xff) << 8 | (low & 0xff)
+}
+
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn wait_idle(regs: &SpiRegisters, dummy_cycles: u32) {
+    while read_status(regs, dummy_cycles) & STATUS_WIP_BIT != 0 {}
+}
+
+/// Reads `len` bytes starting at `address` into the word-aligned `data`,
+/// using 4 byte addresses.
+///
+/// The ROM functions only send 3 byte addresses, so this is needed above 16
+/// MB. The dedicated commands work regardless of the address mode of the flash.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn read_4b(regs: &SpiRegisters, address: u32, data: *mut u32, len: u32) -> i32 {
+    let data = unsafe { core::slice::from_raw_parts_mut(data, len as usize / 4) };
+
+    for (index, chunk) in data.chunks_mut(BUFFER_WORDS).enumerate() {
+        let address = address + (index * BUFFER_WORDS * 4) as u32;
+        let bits = chunk.len() as u32 * 32;
+        // Fast read needs 8 dummy cycles, but works at any clock
+        transfer(regs, CMD_FASTRD_4B, Some(address), &[], 0, 8, chunk, bits);
+    }
+    0
+}
+
+/// Writes `len` bytes from the word-aligned `data` starting at `address`,
+/// using 4 byte addresses.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn write_4b(regs: &SpiRegisters, mut address: u32, data: *const u32, len: u32) -> i32 {
+    let mut data = unsafe { core::slice::from_raw_parts(data, len as usize / 4) };
+
+    while !data.is_empty() {
+        // Stay within the buffer and within a page
+        let room = (BUFFER_WORDS as u32 * 4).min(PAGE_SIZE - address % PAGE_SIZE);
+        let (chunk, rest) = data.split_at(data.len().min(room as usize / 4));
+
+        execute(regs, CMD_WREN, 0, 0, 0, 0);
+        let bits = chunk.len() as u32 * 32;
+        transfer(regs, CMD_PP_4B, Some(address), chunk, bits, 0, &mut [], 0);
+        wait_idle(regs, 0);
+
+        address += chunk.len() as u32 * 4;
+        data = rest;
+    }
+    0
+}
+
+/// Erases the sector at `address`, using a 4 byte address.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn erase_sector_4b(regs: &SpiRegisters, address: u32) -> i32 {
+    erase_4b(regs, CMD_SE_4B, address)
+}
+
+/// Erases the 64 KiB block at `address`, using a 4 byte address.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn erase_block_4b(regs: &SpiRegisters, address: u32) -> i32 {
+    erase_4b(regs, CMD_BE_4B, address)
+}
+
+#[inline(always)]
+#[link_section = ".rwtext"]
+fn erase_4b(regs: &SpiRegisters, command: u8, address: u32) -> i32 {
+    execute(regs, CMD_WREN, 0, 0, 0, 0);
+    transfer(regs, command, Some(address), &[], 0, 0, &mut [], 0);
+    wait_idle(regs, 0);
+    0
+}
+
+/// Sets the block protect bits so the whole flash is write protected.
+#[inline(always)]
+#[link_section = ".rwtext"]
+pub(crate) fn lock(regs: &SpiRegisters, dummy_cycles: u32) -> i32 {
+    let status = read_status(regs, dummy_cycles) | STATUS_WR_PROTECT;
+
+    execute(regs, CMD_WREN, 0, 0, 0, 0);
+    execute(regs, CMD_WRSR, status, 16, 0, 0);
+    wait_idle(regs, dummy_cycles);
+
+    if read_status(regs, dummy_cycles) & STATUS_WR_PROTECT != STATUS_WR_PROTECT {
+        return -1;
+    }
+    0
+}
+
+/// Building blocks for configuring auto-suspend, which the SPI_MEM peripheral
+/// of some chips supports.
+#[cfg(any(
+    feature = "esp32c3",
+    feature = "esp32c6",
+    feature = "esp32h2",
+    feature = "esp32s3"
+))]
+pub(crate) mod auto_suspend {
+    use super::{read_register, write_register};
+
+    pub(crate) const CMD_RDSR: u32 = super::CMD_RDSR as u32;
+    #[cfg(not(feature = "esp32s3"))]
+    pub(crate) const CMD_RDSR2: u32 = super::CMD_RDSR2 as u32;
+    pub(crate) const CMD_SUSPEND: u32 = 0x75;
+    pub(crate) const CMD_RESUME: u32 = 0x7a;
+
+    /// The SUS bit in the second status register, set while an erase or
+    /// program is suspended.
+    #[cfg(not(feature = "esp32s3"))]
+    pub(crate) const STATUS2_SUS_BIT: u32 = 1 << 7;
+
+    /// Replaces the field of `width` bits starting at bit `shift` of the
+    /// register at `address` with `value`.
+    #[inline(always)]
+    pub(crate) fn set_field(address: u32, shift: u32, width: u32, value: u32) {
+        let mask = ((1 << width) - 1) << shift;
+        write_register(
+            address,
+            (read_register(address) & !mask) | ((value << shift) & mask),
+        );
+    }
+}
@@ -2,43 +2,23 @@ use core::mem::MaybeUninit;
 
 use embedded_storage::{ReadStorage, Storage};
 
-use crate::{FlashSectorBuffer, FlashStorage, FlashStorageError};
+use crate::{FlashRegion, FlashSectorBuffer, FlashStorage, FlashStorageError};
 
 impl ReadStorage for FlashStorage {
     type Error = FlashStorageError;
 
-    fn read(&mut self, offset: u32, mut bytes: &mut [u8]) -> Result<(), Self::Error> {
-        self.check_bounds(offset, bytes.len())?;
-
-        let mut data_offset = offset % Self::WORD_SIZE;
-        let mut aligned_offset = offset - data_offset;
-
-        // Bypass clearing sector buffer for performance reasons
-        let mut sector_data = MaybeUninit::<FlashSectorBuffer>::uninit();
-        let sector_data = unsafe { sector_data.assume_init_mut() };
-
-        while !bytes.is_empty() {
-            let len = bytes.len().min((Self::SECTOR_SIZE - data_offset) as _);
-
-            let aligned_end = (data_offset as usize + len + (Self::WORD_SIZE - 1) as usize)
-                & !(Self::WORD_SIZE - 1) as usize;
-
-            // Read only needed data words
-            self.internal_read(aligned_offset, &mut sector_data[..aligned_end])?;
-
-            bytes[..len].copy_from_slice(&sector_data[data_offset as usize..][..len]);
-
-            aligned_offset += Self::SECTOR_SIZE;
-            data_offset = 0;
-            bytes = &mut bytes[len..];
-        }
-
-        Ok(())
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        FlashStorage::read(self, offset, bytes)
     }
 
-    /// The SPI flash size is configured by writing a field in the software
-    /// bootloader image header. This is done during flashing in espflash /
-    /// esptool.
+    /// The size of the flash is taken from the JEDEC ID of the chip, the
+    /// bootloader image header or the partition table, in that order, and
+    /// falls back to a default if none of them gives one, unless it was
+    /// configured explicitly.
+    ///
+    /// See [`FlashStorage::capacity`] and [`CapacitySource`].
+    ///
+    /// [`CapacitySource`]: crate::CapacitySource
     fn capacity(&self) -> usize {
         self.capacity
     }
@@ -56,13 +36,12 @@ impl Storage for FlashStorage {
         let sector_data = unsafe { sector_data.assume_init_mut() };
 
         while !bytes.is_empty() {
-            self.internal_read(aligned_offset, &mut sector_data[..])?;
-
             let len = bytes.len().min((Self::SECTOR_SIZE - data_offset) as _);
 
-            sector_data[data_offset as usize..][..len].copy_from_slice(&bytes[..len]);
-            self.internal_erase(aligned_offset / Self::SECTOR_SIZE)?;
-            self.internal_write(aligned_offset, &sector_data[..])?;
+            self.write_sector(aligned_offset / Self::SECTOR_SIZE, sector_data, |data| {
+                data[data_offset as usize..][..len].copy_from_slice(&bytes[..len]);
+            })?;
+            self.yield_now();
 
             aligned_offset += Self::SECTOR_SIZE;
             data_offset = 0;
@@ -72,3 +51,22 @@ impl Storage for FlashStorage {
         Ok(())
     }
 }
+
+impl ReadStorage for FlashRegion<'_> {
+    type Error = FlashStorageError;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        FlashRegion::read(self, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        FlashRegion::capacity(self)
+    }
+}
+
+impl Storage for FlashRegion<'_> {
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = self.translate(offset, bytes.len())?;
+        Storage::write(self.flash(), offset, bytes)
+    }
+}
@@ -7,7 +7,9 @@ const ERROR_CODE: i32 = 1;
 const ERASE_BYTE: u8 = 0xff;
 const WORD_SIZE: u32 = 4;
 const SECTOR_SIZE: u32 = 4 << 10;
-const NUM_SECTORS: u32 = 4;
+const BLOCK_SIZE: u32 = 64 << 10;
+const NUM_SECTORS: u32 = 64;
+const NUM_BLOCKS: u32 = NUM_SECTORS * SECTOR_SIZE / BLOCK_SIZE;
 const FLASH_SIZE: u32 = SECTOR_SIZE * NUM_SECTORS;
 
 static mut FLASH_LOCK: bool = true;
@@ -76,6 +78,12 @@ pub(crate) fn spiflash_unlock() -> i32 {
     SUCCESS_CODE
 }
 
+pub(crate) fn spiflash_lock() -> i32 {
+    // The emulated flash is shared by all tests running in parallel, so it is
+    // left writable
+    SUCCESS_CODE
+}
+
 pub(crate) fn spiflash_erase_sector(sector_number: u32) -> i32 {
     if check::<1, NUM_SECTORS, 1>(sector_number, 1, ptr::null()) {
         maybe_with_critical_section(|| {
@@ -89,6 +97,37 @@ pub(crate) fn spiflash_erase_sector(sector_number: u32) -> i32 {
     }
 }
 
+pub(crate) fn spiflash_erase_block(block_number: u32) -> i32 {
+    if check::<1, NUM_BLOCKS, 1>(block_number, 1, ptr::null()) {
+        maybe_with_critical_section(|| {
+            let dst_addr = block_number * BLOCK_SIZE;
+            let len = BLOCK_SIZE;
+            unsafe { FLASH_DATA[dst_addr as usize..][..len as usize].fill(ERASE_BYTE) };
+        });
+        SUCCESS_CODE
+    } else {
+        ERROR_CODE
+    }
+}
+
+pub(crate) fn spiflash_write_encrypted(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    // There is no encryption, the data is stored as is
+    spiflash_write(dest_addr, data, len)
+}
+
+pub(crate) fn flash_crypt_cnt() -> u32 {
+    0
+}
+
+pub(crate) fn spiflash_read_id() -> u32 {
+    // The emulated flash doesn't answer, just like a missing chip
+    0xff_ffff
+}
+
+pub(crate) fn spiflash_read_unique_id() -> [u8; 8] {
+    [0xff; 8]
+}
+
 pub(crate) fn spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32 {
     if check::<WORD_SIZE, FLASH_SIZE, SECTOR_SIZE>(dest_addr, len, data) {
         maybe_with_critical_section(|| {
@@ -105,3 +144,25 @@ pub(crate) fn spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32
         ERROR_CODE
     }
 }
+
+pub(crate) fn spiflash_enable_auto_suspend() -> bool {
+    false
+}
+
+// The emulated flash is far smaller than 16 MB, so these are never used with
+// addresses needing 4 bytes
+pub(crate) fn spiflash_read_4b(src_addr: u32, data: *mut u32, len: u32) -> i32 {
+    spiflash_read(src_addr, data, len)
+}
+
+pub(crate) fn spiflash_write_4b(dest_addr: u32, data: *const u32, len: u32) -> i32 {
+    spiflash_write(dest_addr, data, len)
+}
+
+pub(crate) fn spiflash_erase_sector_4b(sector_number: u32) -> i32 {
+    spiflash_erase_sector(sector_number)
+}
+
+pub(crate) fn spiflash_erase_block_4b(block_number: u32) -> i32 {
+    spiflash_erase_block(block_number)
+}
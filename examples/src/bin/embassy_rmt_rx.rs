@@ -79,9 +79,9 @@ async fn main(spawner: Spawner) {
 
     loop {
         println!("receive");
-        channel.receive(&mut data).await.unwrap();
+        let len = channel.receive(&mut data).await.unwrap();
         let mut total = 0usize;
-        for entry in &data[..data.len()] {
+        for entry in &data[..len] {
             if entry.length1() == 0 {
                 break;
             }
@@ -93,7 +93,7 @@ async fn main(spawner: Spawner) {
             total += entry.length2() as usize;
         }
 
-        for entry in &data[..data.len()] {
+        for entry in &data[..len] {
             if entry.length1() == 0 {
                 break;
             }
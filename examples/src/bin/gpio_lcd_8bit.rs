@@ -0,0 +1,126 @@
+//! Drives a HD44780 character LCD over its 8-bit parallel interface.
+//!
+//! The data lines and the register select line are grouped into a `PinGroup`,
+//! so a byte and its register select level are set with one write to the set
+//! and one to the clear register. The display only samples them on the falling
+//! edge of E, so it never sees the value between the two writes. The display
+//! shows a greeting and counts the seconds since start.
+//!
+//! The following wiring is assumed:
+//! - D0..D7 => GPIO0..GPIO7
+//! - RS => GPIO8
+//! - E => GPIO10
+//! - RW => GND
+
+//% CHIPS: esp32c2 esp32c3 esp32c6 esp32s3
+//% FEATURES: esp-hal/unstable
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use esp_backtrace as _;
+use esp_hal::{
+    delay::Delay,
+    gpio::{Level, Output, OutputConfig, PinGroup},
+    main,
+};
+
+/// Group bit of the register select line, above the eight data lines.
+const RS: u32 = 1 << 8;
+
+const CLEAR_DISPLAY: u8 = 0x01;
+const ENTRY_MODE_INCREMENT: u8 = 0x06;
+const DISPLAY_ON: u8 = 0x0C;
+const FUNCTION_SET_8BIT: u8 = 0x30;
+const FUNCTION_SET_8BIT_2_LINES: u8 = 0x38;
+const SET_DDRAM_ADDRESS: u8 = 0x80;
+
+struct Lcd<'d> {
+    bus: PinGroup<'d, 9>,
+    enable: Output<'d>,
+    delay: Delay,
+}
+
+impl Lcd<'_> {
+    fn init(&mut self) {
+        // Wait for the controller to power up, then force it into 8-bit mode
+        // regardless of the state it is in.
+        self.delay.delay_millis(50);
+        self.command(FUNCTION_SET_8BIT);
+        self.delay.delay_millis(5);
+        self.command(FUNCTION_SET_8BIT);
+        self.command(FUNCTION_SET_8BIT);
+
+        self.command(FUNCTION_SET_8BIT_2_LINES);
+        self.command(DISPLAY_ON);
+        self.command(CLEAR_DISPLAY);
+        self.delay.delay_millis(2);
+        self.command(ENTRY_MODE_INCREMENT);
+    }
+
+    fn set_cursor(&mut self, line: u8, column: u8) {
+        self.command(SET_DDRAM_ADDRESS | (line * 0x40 + column));
+    }
+
+    fn command(&mut self, command: u8) {
+        self.send(0, command);
+    }
+
+    fn send(&mut self, rs: u32, byte: u8) {
+        // Set up the data and register select lines at once, then latch them
+        // with a pulse on the enable line.
+        self.bus.write(RS | 0xFF, rs | byte as u32);
+        self.enable.set_high();
+        self.delay.delay_micros(1);
+        self.enable.set_low();
+
+        // Most instructions take 37 us to execute.
+        self.delay.delay_micros(50);
+    }
+}
+
+impl Write for Lcd<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            self.send(RS, byte);
+        }
+        Ok(())
+    }
+}
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let config = OutputConfig::default();
+    let bus = PinGroup::new([
+        Output::new(peripherals.GPIO0, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO1, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO2, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO3, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO4, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO5, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO6, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO7, Level::Low, config).into_flex(),
+        Output::new(peripherals.GPIO8, Level::Low, config).into_flex(),
+    ]);
+    let enable = Output::new(peripherals.GPIO10, Level::Low, config);
+
+    let delay = Delay::new();
+    let mut lcd = Lcd { bus, enable, delay };
+    lcd.init();
+
+    lcd.set_cursor(0, 0);
+    write!(lcd, "Hello, world!").unwrap();
+
+    let mut seconds = 0u32;
+    loop {
+        lcd.set_cursor(1, 0);
+        write!(lcd, "{seconds} s").unwrap();
+
+        delay.delay_millis(1000);
+        seconds += 1;
+    }
+}
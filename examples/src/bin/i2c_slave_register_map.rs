@@ -0,0 +1,83 @@
+//! Makes the chip appear as an I2C device with a small register map.
+//!
+//! The device responds to address 0x42. A write selects a register with its
+//! first byte and stores any following bytes, starting at that register. A
+//! read returns the registers starting at the selected one, so a master can
+//! read register 0x01 with a write-read of `[0x01]`.
+//!
+//! Register 0x00 holds a fixed ID, 0x01 counts the requests served, and the
+//! remaining registers are free for the master to use.
+//!
+//! The following wiring is assumed:
+//! - SDA => GPIO1
+//! - SCL => GPIO2
+//!
+//! Both lines need pull-up resistors, unless the master provides them.
+
+//% CHIPS: esp32c3 esp32c6 esp32h2 esp32s3
+//% FEATURES: esp-hal/unstable
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{
+    i2c::slave::{Config, I2c, Request},
+    main,
+};
+use esp_println::println;
+
+const ADDRESS: u8 = 0x42;
+const ID: u8 = 0xE5;
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let mut i2c = I2c::new(peripherals.I2C0, Config::default().with_address(ADDRESS))
+        .unwrap()
+        .with_sda(peripherals.GPIO1)
+        .with_scl(peripherals.GPIO2);
+
+    let mut registers = [0u8; 16];
+    registers[0] = ID;
+    let mut selected = 0;
+
+    loop {
+        let mut buffer = [0u8; 17];
+        let request = match i2c.wait_for_request(&mut buffer) {
+            Ok(request) => request,
+            Err(error) => {
+                println!("Error: {:?}", error);
+                continue;
+            }
+        };
+
+        let (len, read) = match request {
+            Request::Write(len) => (len, false),
+            Request::Read(len) => (len, true),
+            _ => continue,
+        };
+
+        if let Some((&register, data)) = buffer[..len].split_first() {
+            selected = register as usize % registers.len();
+
+            // The ID and the counter are read-only.
+            for (offset, byte) in data.iter().enumerate() {
+                let register = selected + offset;
+                if (2..registers.len()).contains(&register) {
+                    registers[register] = *byte;
+                }
+            }
+        }
+
+        if read {
+            // SCL is held low until the response is queued.
+            registers[1] = registers[1].wrapping_add(1);
+            i2c.respond(&registers[selected..]);
+            println!("Read from register {:#04x}", selected);
+        } else if len > 1 {
+            println!("Wrote {} bytes at register {:#04x}", len - 1, selected);
+        }
+    }
+}
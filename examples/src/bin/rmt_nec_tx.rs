@@ -0,0 +1,93 @@
+//! Sends NEC infrared remote control frames with RMT
+//!
+//! Each frame is sent on a 38 kHz carrier, which an IR LED turns into
+//! something a TV or a standard IR receiver module understands. Every other
+//! frame is sent without the carrier, so the envelope of the frame can be
+//! looked at with a logic analyzer.
+//!
+//! The following wiring is assumed:
+//! - IR LED (through a transistor) => GPIO4
+
+//% CHIPS: esp32 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: esp-hal/unstable
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{
+    delay::Delay,
+    gpio::Level,
+    main,
+    rmt::{PulseCode, Rmt, TxChannel, TxChannelConfig, TxChannelCreator},
+    time::RateExtU32,
+};
+use esp_println::println;
+
+/// Encodes an NEC frame, in pulse codes of 1 µs ticks.
+///
+/// A frame starts with a 9 ms mark and a 4.5 ms space, followed by the address,
+/// the command and their inverses, least significant bit first. All marks
+/// last 562.5 µs, a 0 bit is followed by a space of the same length and a 1 bit
+/// by a space of three times that. A final mark ends the last bit, and also
+/// serves as the end marker of the sequence.
+fn nec_frame(address: u8, command: u8) -> [u32; 34] {
+    let mut frame = [PulseCode::empty(); 34];
+
+    frame[0] = PulseCode::new(Level::High, 9000, Level::Low, 4500);
+
+    let bits = u32::from_le_bytes([address, !address, command, !command]);
+    for (bit, code) in frame[1..33].iter_mut().enumerate() {
+        let space = if bits & (1 << bit) != 0 { 1687 } else { 562 };
+        *code = PulseCode::new(Level::High, 562, Level::Low, space);
+    }
+
+    frame[33] = PulseCode::new(Level::High, 562, Level::Low, 0);
+
+    frame
+}
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "esp32h2")] {
+            let freq = 32.MHz();
+        } else {
+            let freq = 80.MHz();
+        }
+    };
+
+    let rmt = Rmt::new(peripherals.RMT, freq).unwrap();
+
+    // The carrier is applied to the marks, the LED stays off in between.
+    let tx_config = TxChannelConfig::default()
+        .with_clk_divider(freq.to_MHz() as u8)
+        .with_idle_output(true)
+        .with_idle_output_level(Level::Low)
+        .with_carrier_modulation(true)
+        .with_carrier_frequency(38.kHz())
+        .with_carrier_duty_cycle(33)
+        .with_carrier_level(Level::High);
+
+    let mut channel = rmt
+        .channel0
+        .configure(peripherals.GPIO4, tx_config)
+        .unwrap();
+
+    let delay = Delay::new();
+
+    let mut command = 0u8;
+    loop {
+        let carrier = command % 2 == 0;
+        channel.set_carrier_modulation(carrier);
+
+        println!("Sending command {:#04x}, carrier: {}", command, carrier);
+        let frame = nec_frame(0x00, command);
+        channel = channel.transmit(&frame).unwrap().wait().unwrap();
+
+        command = command.wrapping_add(1);
+        delay.delay_millis(500);
+    }
+}
@@ -0,0 +1,97 @@
+//! ADC calibration test
+//!
+//! The ADC reads a pin that's connected to an output.
+
+//% CHIPS: esp32c3 esp32c6 esp32h2
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    analog::adc::{Adc, AdcCalLine, AdcConfig, AdcPin, Attenuation},
+    delay::Delay,
+    gpio::{GpioPin, Level, Output, OutputConfig},
+    peripherals::ADC1,
+    Blocking,
+};
+use hil_test as _;
+
+struct Context {
+    adc: Adc<'static, ADC1, Blocking>,
+    pin: AdcPin<GpioPin<2>, ADC1, AdcCalLine<ADC1>>,
+    driver: Output<'static>,
+}
+
+fn read_mv(ctx: &mut Context) -> u16 {
+    Delay::new().delay_millis(1);
+    nb::block!(ctx.adc.read_mv(&mut ctx.pin)).unwrap()
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (analog, driver) = hil_test::common_test_pins!(peripherals);
+        let driver = Output::new(driver, Level::Low, OutputConfig::default());
+
+        let mut config = AdcConfig::new();
+        let pin = config.enable_pin_with_cal(analog, Attenuation::_11dB);
+        let adc = Adc::new(peripherals.ADC1, config);
+
+        Context { adc, pin, driver }
+    }
+
+    #[test]
+    fn readings_are_converted_to_millivolts(mut ctx: Context) {
+        ctx.driver.set_high();
+        let high = read_mv(&mut ctx);
+        assert!(high > 2000, "high: {} mV", high);
+
+        // The raw reading of a high input is close to full scale, which is
+        // well above the input voltage in millivolts.
+        let raw = nb::block!(ctx.adc.read_raw(&mut ctx.pin)).unwrap();
+        assert!(raw > 3500, "raw: {}", raw);
+        assert!(raw > high, "raw: {}, high: {} mV", raw, high);
+
+        // Oneshot reads are converted by the calibration scheme too.
+        let oneshot = nb::block!(ctx.adc.read_oneshot(&mut ctx.pin)).unwrap();
+        assert!(
+            oneshot.abs_diff(high) < 100,
+            "oneshot: {}, high: {} mV",
+            oneshot,
+            high
+        );
+
+        ctx.driver.set_low();
+        let low = read_mv(&mut ctx);
+        assert!(low < 100, "low: {} mV", low);
+    }
+
+    #[test]
+    fn attenuation_changes_recompute_the_calibration(mut ctx: Context) {
+        ctx.driver.set_high();
+        let high_11db = read_mv(&mut ctx);
+
+        // Without attenuation, the ADC saturates below 1 V.
+        ctx.adc.set_attenuation(&mut ctx.pin, Attenuation::_0dB);
+        let high_0db = read_mv(&mut ctx);
+        assert!(high_0db < 1200, "high: {} mV", high_0db);
+        assert!(high_0db < high_11db);
+
+        ctx.driver.set_low();
+        let low = read_mv(&mut ctx);
+        assert!(low < 100, "low: {} mV", low);
+    }
+
+    #[test]
+    fn efuse_calibration_is_reported(ctx: Context) {
+        // The ESP32-H2's calibration data isn't read from eFuse.
+        assert_eq!(ctx.pin.has_efuse_calibration(), cfg!(not(esp32h2)));
+    }
+}
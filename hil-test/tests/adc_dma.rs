@@ -0,0 +1,189 @@
+//! ADC continuous mode test
+//!
+//! The ADC converts a pin that's connected to an output.
+
+//% CHIPS: esp32c3 esp32c6 esp32h2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    analog::adc::{
+        Adc,
+        AdcChannel,
+        AdcConfig,
+        AdcDma,
+        AdcSample,
+        Attenuation,
+        ContinuousConfig,
+        Error,
+        PatternEntry,
+    },
+    delay::Delay,
+    dma_rx_stream_buffer,
+    gpio::{Level, Output, OutputConfig},
+    Blocking,
+};
+use fugit::HertzU32;
+use hil_test as _;
+
+const SAMPLE_FREQUENCY: u32 = 20_000;
+
+struct Context {
+    adc: AdcDma<'static, Blocking>,
+    driver: Output<'static>,
+    channel: u8,
+}
+
+fn channel_of<P: AdcChannel>(_pin: &P) -> u8 {
+    P::CHANNEL
+}
+
+// Discards the samples taken before the input settled at its new level.
+fn settle(adc: &mut AdcDma<'static, Blocking>) {
+    Delay::new().delay_millis(5);
+    let mut samples = [AdcSample {
+        channel: 0,
+        value: 0,
+    }; 64];
+    while adc.read_available_samples(&mut samples) != Ok(0) {}
+}
+
+fn mean(adc: &mut AdcDma<'static, Blocking>, channel: u8) -> u32 {
+    let mut samples = [AdcSample {
+        channel: 0,
+        value: 0,
+    }; 500];
+    let mut count = 0;
+    while count < samples.len() {
+        count += adc.read_samples(&mut samples[count..]).unwrap();
+    }
+
+    assert!(samples.iter().all(|sample| sample.channel == channel));
+    samples
+        .iter()
+        .map(|sample| sample.value as u32)
+        .sum::<u32>()
+        / samples.len() as u32
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (analog, driver) = hil_test::common_test_pins!(peripherals);
+        let driver = Output::new(driver, Level::Low, OutputConfig::default());
+
+        let mut config = AdcConfig::new();
+        let pin = config.enable_pin(analog, Attenuation::_11dB);
+        let channel = channel_of(&pin.pin);
+
+        // 100 samples per descriptor, i.e. 5 ms at 20 kHz.
+        let adc = Adc::new(peripherals.ADC1, config)
+            .into_continuous(
+                peripherals.DMA_CH0,
+                &[PatternEntry::new(&pin, Attenuation::_11dB)],
+                ContinuousConfig::default().with_sample_frequency(HertzU32::Hz(SAMPLE_FREQUENCY)),
+                dma_rx_stream_buffer!(4000, 400),
+            )
+            .unwrap();
+
+        Context {
+            adc,
+            driver,
+            channel,
+        }
+    }
+
+    #[test]
+    fn samples_follow_the_input_level(mut ctx: Context) {
+        ctx.driver.set_high();
+        settle(&mut ctx.adc);
+        let high = mean(&mut ctx.adc, ctx.channel);
+        assert!(high > 3500, "high: {}", high);
+
+        ctx.driver.set_low();
+        settle(&mut ctx.adc);
+        let low = mean(&mut ctx.adc, ctx.channel);
+        assert!(low < 300, "low: {}", low);
+    }
+
+    #[test]
+    fn values_can_be_read_without_channels(mut ctx: Context) {
+        ctx.driver.set_high();
+        settle(&mut ctx.adc);
+
+        let mut values = [0u16; 200];
+        let mut count = 0;
+        while count < values.len() {
+            count += ctx.adc.read(&mut values[count..]).unwrap();
+        }
+        assert!(values.iter().all(|&value| value > 3500));
+    }
+
+    #[test]
+    fn samples_are_taken_at_the_sample_frequency(mut ctx: Context) {
+        settle(&mut ctx.adc);
+
+        let mut samples = [AdcSample {
+            channel: 0,
+            value: 0,
+        }; 64];
+        let mut count = 0;
+        let start = esp_hal::time::now();
+        while count < 2000 {
+            count += ctx.adc.read_samples(&mut samples).unwrap();
+        }
+        let elapsed = (esp_hal::time::now() - start).to_micros();
+
+        // 100 ms, give or take a descriptor.
+        assert!((95_000..=106_000).contains(&elapsed), "{} µs", elapsed);
+    }
+
+    #[test]
+    fn overruns_are_reported_once(mut ctx: Context) {
+        // The buffer holds 50 ms of samples.
+        Delay::new().delay_millis(100);
+
+        let mut samples = [AdcSample {
+            channel: 0,
+            value: 0,
+        }; 64];
+        let mut count = 0;
+        let result = loop {
+            match ctx.adc.read_available_samples(&mut samples) {
+                Ok(read) if read > 0 => count += read,
+                result => break result,
+            }
+        };
+        assert_eq!(result, Err(Error::Overrun));
+        assert_eq!(count, 1000);
+
+        // The conversions started over.
+        assert!(ctx.adc.read_samples(&mut samples).unwrap() > 0);
+    }
+
+    #[test]
+    async fn async_reads_wait_for_samples(ctx: Context) {
+        let mut adc = ctx.adc.into_async();
+
+        let mut samples = [AdcSample {
+            channel: 0,
+            value: 0,
+        }; 200];
+        let mut count = 0;
+        while count < samples.len() {
+            count += adc.read_samples_async(&mut samples[count..]).await.unwrap();
+        }
+        assert!(samples.iter().all(|sample| sample.channel == ctx.channel));
+
+        let mut values = [0u16; 64];
+        assert!(adc.read_async(&mut values).await.unwrap() > 0);
+    }
+}
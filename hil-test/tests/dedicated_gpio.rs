@@ -0,0 +1,108 @@
+//! Dedicated GPIO Test
+
+//% CHIPS: esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    delay::Delay,
+    gpio::{
+        dedicated::{DedicatedGpio, Error},
+        AnyPin,
+        Input,
+        InputConfig,
+        Level,
+        Output,
+        OutputConfig,
+        Pin,
+    },
+    peripheral::Peripheral,
+    peripherals::DEDICATED_GPIO,
+};
+use hil_test as _;
+
+struct Context {
+    test_gpio1: AnyPin,
+    test_gpio2: AnyPin,
+    dedicated_gpio: DEDICATED_GPIO,
+    delay: Delay,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (gpio1, gpio2) = hil_test::common_test_pins!(peripherals);
+
+        Context {
+            test_gpio1: gpio1.degrade(),
+            test_gpio2: gpio2.degrade(),
+            dedicated_gpio: peripherals.DEDICATED_GPIO,
+            delay: Delay::new(),
+        }
+    }
+
+    #[test]
+    fn output_drives_input(mut ctx: Context) {
+        let mut gpio = DedicatedGpio::new(ctx.dedicated_gpio);
+        let output = gpio.add_output(&mut ctx.test_gpio2).unwrap();
+        let input = gpio.add_input(&mut ctx.test_gpio1).unwrap();
+        assert_eq!(output, 0);
+        assert_eq!(input, 0);
+
+        gpio.write_bits(1 << output);
+        ctx.delay.delay_micros(1);
+        assert_eq!(gpio.output_bits() & 1, 1);
+        assert_eq!(gpio.read_bits() & (1 << input), 1 << input);
+
+        gpio.write_masked(1 << output, 0);
+        ctx.delay.delay_micros(1);
+        assert_eq!(gpio.read_bits() & (1 << input), 0);
+
+        // Bits outside of the mask are left alone
+        gpio.write_bits(1 << output);
+        gpio.write_masked(!(1 << output), 0);
+        ctx.delay.delay_micros(1);
+        assert_eq!(gpio.read_bits() & (1 << input), 1 << input);
+    }
+
+    #[test]
+    fn channels_run_out(mut ctx: Context) {
+        let mut gpio = DedicatedGpio::new(ctx.dedicated_gpio);
+
+        for channel in 0..8 {
+            let pin = unsafe { ctx.test_gpio1.clone_unchecked() };
+            assert_eq!(gpio.add_input(pin), Ok(channel));
+        }
+        assert_eq!(
+            gpio.add_input(&mut ctx.test_gpio1),
+            Err(Error::NoChannelAvailable)
+        );
+    }
+
+    #[test]
+    fn drop_releases_pins(ctx: Context) {
+        let input = Input::new(ctx.test_gpio1, InputConfig::default());
+        let output = Output::new(ctx.test_gpio2, Level::Low, OutputConfig::default());
+
+        let mut gpio = DedicatedGpio::new(ctx.dedicated_gpio);
+        gpio.add_output(unsafe { output.clone_unchecked() })
+            .unwrap();
+        gpio.write_bits(0xFF);
+        ctx.delay.delay_micros(1);
+        assert_eq!(input.is_high(), true);
+
+        // The pin is driven by its GPIO output register again
+        core::mem::drop(gpio);
+        ctx.delay.delay_micros(1);
+        assert_eq!(input.is_low(), true);
+        assert_eq!(output.is_set_low(), true);
+    }
+}
@@ -15,12 +15,15 @@ use critical_section::Mutex;
 #[cfg(feature = "unstable")]
 use embassy_time::{Duration, Timer};
 use esp_hal::gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig, Pin, Pull};
+#[cfg(all(feature = "unstable", any(esp32c6, esp32h2)))]
+use esp_hal::gpio::{GlitchFilter, GlitchFilterError};
 #[cfg(feature = "unstable")]
 use esp_hal::{
     // OutputOpenDrain is here because will be unused otherwise
     delay::Delay,
-    gpio::{DriveMode, Event, Flex, Io},
+    gpio::{DriveMode, DriveStrength, Event, Flex, Io, PinGroup},
     handler,
+    peripheral::Peripheral,
     timer::timg::TimerGroup,
 };
 use hil_test as _;
@@ -128,6 +131,17 @@ mod tests {
         .await;
     }
 
+    #[test]
+    #[cfg(feature = "unstable")] // `is_listening` is unstable
+    async fn dropping_the_wait_stops_listening(ctx: Context) {
+        let mut input = Input::new(ctx.test_gpio1, InputConfig::default().with_pull(Pull::Down));
+
+        embassy_futures::select::select(input.wait_for_rising_edge(), embassy_futures::yield_now())
+            .await;
+
+        assert!(!input.is_listening());
+    }
+
     #[test]
     fn gpio_input(ctx: Context) {
         let test_gpio1 = Input::new(ctx.test_gpio1, InputConfig::default().with_pull(Pull::Down));
@@ -251,6 +265,72 @@ mod tests {
         test_gpio1.unlisten();
     }
 
+    #[test]
+    #[cfg(feature = "unstable")] // Interrupts are unstable
+    fn gpio_pin_interrupt_handler(ctx: Context) {
+        static PIN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        fn count_edges() {
+            PIN_COUNTER.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut test_gpio1 =
+            Input::new(ctx.test_gpio1, InputConfig::default().with_pull(Pull::Down));
+        let mut test_gpio2 = Output::new(ctx.test_gpio2, Level::Low, OutputConfig::default());
+
+        test_gpio1.set_interrupt_handler(count_edges);
+        test_gpio1.listen(Event::RisingEdge);
+        for _ in 0..5 {
+            test_gpio2.set_high();
+            ctx.delay.delay_millis(1);
+            test_gpio2.set_low();
+            ctx.delay.delay_millis(1);
+        }
+        test_gpio1.unlisten();
+        test_gpio1.remove_interrupt_handler();
+
+        // The status bit was cleared by the driver, otherwise the handler
+        // would have been called over and over again
+        assert_eq!(PIN_COUNTER.load(Ordering::Relaxed), 5);
+        assert!(!test_gpio1.is_interrupt_set());
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")] // Interrupts are unstable
+    async fn dropping_the_driver_removes_the_interrupt_handler(ctx: Context) {
+        static STALE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        fn count_edges() {
+            STALE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let Context {
+            mut test_gpio1,
+            test_gpio2,
+            ..
+        } = ctx;
+
+        let mut input = Input::new(
+            &mut test_gpio1,
+            InputConfig::default().with_pull(Pull::Down),
+        );
+        input.set_interrupt_handler(count_edges);
+        input.listen(Event::RisingEdge);
+        core::mem::drop(input);
+
+        // A new driver of the pin can wait for edges, which don't reach the
+        // handler of the old one.
+        let mut input = Input::new(test_gpio1, InputConfig::default().with_pull(Pull::Down));
+        let mut test_gpio2 = Output::new(test_gpio2, Level::Low, OutputConfig::default());
+        embassy_futures::join::join(input.wait_for_rising_edge(), async {
+            Timer::after(Duration::from_millis(10)).await;
+            test_gpio2.set_high();
+        })
+        .await;
+
+        assert_eq!(STALE_COUNTER.load(Ordering::Relaxed), 0);
+    }
+
     #[test]
     #[cfg(feature = "unstable")] // delay is unstable
     fn gpio_od(ctx: Context) {
@@ -304,6 +384,41 @@ mod tests {
         assert_eq!(input.level(), Level::High);
     }
 
+    #[test]
+    #[cfg(feature = "unstable")] // delay is unstable
+    fn gpio_od_reads_back_the_line(ctx: Context) {
+        let config = OutputConfig::default()
+            .with_drive_mode(DriveMode::OpenDrain)
+            .with_pull(Pull::Up);
+        let released = Output::new(ctx.test_gpio1, Level::High, config);
+        let mut holder = Output::new(ctx.test_gpio2, Level::Low, config);
+
+        ctx.delay.delay_millis(1);
+        assert!(released.is_set_high());
+        assert!(released.is_low());
+
+        holder.set_high();
+        ctx.delay.delay_millis(1);
+        assert!(released.is_high());
+        assert_eq!(
+            embedded_hal::digital::InputPin::is_high(&mut holder),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")] // delay is unstable
+    fn gpio_push_pull_reads_back_the_line(ctx: Context) {
+        let mut output = Output::new(ctx.test_gpio1, Level::High, OutputConfig::default());
+
+        ctx.delay.delay_millis(1);
+        assert!(output.is_high());
+
+        output.set_low();
+        ctx.delay.delay_millis(1);
+        assert!(output.is_low());
+    }
+
     #[test]
     #[cfg(feature = "unstable")]
     fn gpio_flex(ctx: Context) {
@@ -345,6 +460,180 @@ mod tests {
         assert_eq!(test_gpio2.is_set_low(), true);
     }
 
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn gpio_matrix_inverts_output(ctx: Context) {
+        let mut test_gpio1 = Flex::new(ctx.test_gpio1);
+        let test_gpio2 = Input::new(ctx.test_gpio2, InputConfig::default());
+
+        test_gpio1.set_high();
+        test_gpio1.set_as_output();
+        let mut signal = unsafe { test_gpio1.clone_unchecked() }.into_peripheral_output();
+        signal.set_inverted(true);
+        esp_hal::gpio::OutputSignal::GPIO.connect_to(&mut signal);
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_low(), true);
+
+        test_gpio1.set_low();
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_high(), true);
+
+        signal.set_inverted(false);
+        esp_hal::gpio::OutputSignal::GPIO.connect_to(&mut signal);
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_low(), true);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn gpio_flex_switches_direction(ctx: Context) {
+        let mut test_gpio1 = Flex::new(ctx.test_gpio1);
+        let mut test_gpio2 = Flex::new(ctx.test_gpio2);
+
+        test_gpio2.set_as_input(Pull::Down);
+        test_gpio1.set_high();
+        test_gpio1.set_as_output();
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_high(), true);
+
+        // The output latch survives switching to input and back
+        test_gpio1.set_as_input(Pull::None);
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio1.is_set_high(), true);
+        assert_eq!(test_gpio2.is_low(), true);
+
+        test_gpio2.set_as_input(Pull::None);
+        test_gpio1.set_as_open_drain(Pull::Up);
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_high(), true);
+
+        test_gpio1.set_low();
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_low(), true);
+
+        test_gpio1.set_as_input(Pull::Up);
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_high(), true);
+
+        test_gpio1.set_as_output();
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio2.is_low(), true);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn gpio_pin_group_writes_and_reads(ctx: Context) {
+        let test_gpio1 = Input::new(ctx.test_gpio1, InputConfig::default().with_pull(Pull::Down));
+        let mut test_gpio2 = Flex::new(ctx.test_gpio2);
+        test_gpio2.set_as_output();
+        test_gpio2.enable_input(true);
+
+        let mut group = PinGroup::new([test_gpio1.into_flex(), test_gpio2]);
+
+        group.write(0b10, 0b10);
+        ctx.delay.delay_millis(1);
+        assert_eq!(group.read(), 0b11);
+
+        group.toggle(0b10);
+        ctx.delay.delay_millis(1);
+        assert_eq!(group.read(), 0b00);
+
+        // Bits outside of the mask are left alone
+        group.write(0b01, 0b11);
+        ctx.delay.delay_millis(1);
+        assert_eq!(group.read(), 0b00);
+
+        group.toggle(0b10);
+        ctx.delay.delay_millis(1);
+        assert_eq!(group.read(), 0b11);
+
+        let [_, test_gpio2] = group.into_inner();
+        assert_eq!(test_gpio2.is_set_high(), true);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn gpio_hold_keeps_level(ctx: Context) {
+        let test_gpio1 = Input::new(ctx.test_gpio1, InputConfig::default());
+        let mut test_gpio2 = Output::new(ctx.test_gpio2, Level::High, OutputConfig::default());
+
+        test_gpio2.enable_hold();
+        test_gpio2.set_low();
+        ctx.delay.delay_millis(1);
+        assert!(test_gpio1.is_high());
+
+        test_gpio2.disable_hold();
+        ctx.delay.delay_millis(1);
+        assert!(test_gpio1.is_low());
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn gpio_pull_and_drive_strength(ctx: Context) {
+        let mut test_gpio1 = Input::new(ctx.test_gpio1, InputConfig::default());
+        let mut test_gpio2 = Output::new(
+            ctx.test_gpio2,
+            Level::Low,
+            OutputConfig::default().with_drive_mode(DriveMode::OpenDrain),
+        );
+        assert_eq!(test_gpio1.pull(), Pull::None);
+        assert_eq!(test_gpio2.drive_strength(), DriveStrength::_20mA);
+
+        test_gpio2.set_high();
+        test_gpio2.set_pull(Pull::Up);
+        test_gpio2.set_drive_strength(DriveStrength::_5mA);
+        ctx.delay.delay_millis(1);
+
+        assert_eq!(test_gpio2.pull(), Pull::Up);
+        assert_eq!(test_gpio2.drive_strength(), DriveStrength::_5mA);
+        assert_eq!(test_gpio1.is_high(), true);
+
+        test_gpio2.set_pull(Pull::None);
+        test_gpio1.set_pull(Pull::Down);
+        ctx.delay.delay_millis(1);
+
+        assert_eq!(test_gpio1.pull(), Pull::Down);
+        assert_eq!(test_gpio1.is_low(), true);
+    }
+
+    #[test]
+    #[cfg(all(feature = "unstable", any(esp32c6, esp32h2)))]
+    fn gpio_glitch_filter(ctx: Context) {
+        let mut test_gpio1 =
+            Input::new(ctx.test_gpio1, InputConfig::default().with_pull(Pull::Down));
+        let mut test_gpio2 = Output::new(ctx.test_gpio2, Level::Low, OutputConfig::default());
+
+        assert_eq!(
+            test_gpio1.set_glitch_filter(GlitchFilter::Flex {
+                window_ns: 500,
+                threshold_ns: 1000,
+            }),
+            Err(GlitchFilterError::InvalidTiming)
+        );
+        test_gpio1
+            .set_glitch_filter(GlitchFilter::Flex {
+                window_ns: 1000,
+                threshold_ns: 500,
+            })
+            .unwrap();
+
+        // Levels which are held for longer than the window pass the filter
+        test_gpio2.set_high();
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio1.is_high(), true);
+
+        test_gpio1
+            .set_glitch_filter(GlitchFilter::FixedTwoClocks)
+            .unwrap();
+        test_gpio2.set_low();
+        ctx.delay.delay_millis(1);
+        assert_eq!(test_gpio1.is_low(), true);
+
+        test_gpio1
+            .set_glitch_filter(GlitchFilter::Disabled)
+            .unwrap();
+    }
+
     // Tests touch pin (GPIO2) as AnyPin and Output
     // https://github.com/esp-rs/esp-hal/issues/1943
     #[test]
@@ -0,0 +1,102 @@
+//! GPIO ETM Test
+
+//% CHIPS: esp32c6 esp32h2
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    delay::Delay,
+    etm::Etm,
+    gpio::{
+        etm::{Channels, InputConfig, OutputConfig},
+        AnyPin,
+        Flex,
+        Level,
+        Pin,
+        Pull,
+    },
+    peripheral::Peripheral,
+    peripherals::{GPIO_SD, SOC_ETM},
+};
+use hil_test as _;
+
+struct Context {
+    trigger: AnyPin,
+    target: AnyPin,
+    gpio_sd: GPIO_SD,
+    soc_etm: SOC_ETM,
+    delay: Delay,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (trigger, _) = hil_test::common_test_pins!(peripherals);
+        let target = hil_test::unconnected_pin!(peripherals);
+
+        Context {
+            trigger: trigger.degrade(),
+            target: target.degrade(),
+            gpio_sd: peripherals.GPIO_SD,
+            soc_etm: peripherals.SOC_ETM,
+            delay: Delay::new(),
+        }
+    }
+
+    #[test]
+    fn edge_event_toggles_pin(ctx: Context) {
+        let mut trigger = Flex::new(ctx.trigger);
+        trigger.set_low();
+        trigger.set_as_output();
+
+        let mut observer = Flex::new(unsafe { ctx.target.clone_unchecked() });
+
+        let channels = Channels::new(ctx.gpio_sd);
+        let event = channels
+            .channel0_event
+            .rising_edge(trigger.peripheral_input(), InputConfig { pull: Pull::None });
+        let task = channels.channel0_task.toggle(
+            ctx.target,
+            OutputConfig {
+                open_drain: false,
+                pull: Pull::None,
+                initial_state: Level::Low,
+            },
+        );
+        observer.enable_input(true);
+
+        let etm = Etm::new(ctx.soc_etm);
+        let channel = etm.channel0.setup(&event, &task);
+
+        for expected in [true, false, true] {
+            trigger.set_high();
+            ctx.delay.delay_micros(10);
+            assert_eq!(observer.is_high(), expected);
+
+            trigger.set_low();
+            ctx.delay.delay_micros(10);
+            assert_eq!(observer.is_high(), expected);
+        }
+
+        core::mem::drop(channel);
+        core::mem::drop(task);
+        core::mem::drop(event);
+
+        // The pin is no longer controlled by the ETM
+        trigger.set_high();
+        ctx.delay.delay_micros(10);
+        assert_eq!(observer.is_high(), true);
+
+        observer.set_low();
+        ctx.delay.delay_micros(10);
+        assert_eq!(observer.is_low(), true);
+    }
+}
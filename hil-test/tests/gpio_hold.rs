@@ -0,0 +1,83 @@
+//! GPIO hold test
+//!
+//! The held pin drives a second pin, which reads its level.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use core::time::Duration;
+
+use esp_hal::{
+    delay::Delay,
+    gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig, Pin},
+    rtc_cntl::{sleep::TimerWakeupSource, Rtc},
+};
+use hil_test as _;
+
+struct Context {
+    rtc: Rtc<'static>,
+    input: Input<'static>,
+    output: AnyPin,
+    delay: Delay,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (input, output) = hil_test::common_test_pins!(peripherals);
+        let input = Input::new(input, InputConfig::default());
+
+        Context {
+            rtc: Rtc::new(peripherals.LPWR),
+            input,
+            output: output.degrade(),
+            delay: Delay::new(),
+        }
+    }
+
+    #[test]
+    fn hold_keeps_level_through_light_sleep(mut ctx: Context) {
+        let mut output = Output::new(ctx.output, Level::High, OutputConfig::default());
+
+        output.enable_hold();
+        output.set_low();
+
+        let timer = TimerWakeupSource::new(Duration::from_millis(10));
+        ctx.rtc.sleep_light(&[&timer]);
+
+        ctx.delay.delay_millis(1);
+        assert!(ctx.input.is_high());
+
+        output.disable_hold();
+        ctx.delay.delay_millis(1);
+        assert!(ctx.input.is_low());
+    }
+
+    #[test]
+    fn hold_survives_dropping_the_driver(mut ctx: Context) {
+        let mut output = Output::new(&mut ctx.output, Level::High, OutputConfig::default());
+        output.enable_hold();
+        core::mem::drop(output);
+
+        ctx.delay.delay_millis(1);
+        assert!(ctx.input.is_high());
+
+        // A new driver can't change the level until it releases the hold.
+        let mut output = Output::new(&mut ctx.output, Level::Low, OutputConfig::default());
+        ctx.delay.delay_millis(1);
+        assert!(ctx.input.is_high());
+
+        output.disable_hold();
+        ctx.delay.delay_millis(1);
+        assert!(ctx.input.is_low());
+    }
+}
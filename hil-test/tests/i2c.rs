@@ -7,7 +7,21 @@
 #![no_main]
 
 use esp_hal::{
-    i2c::master::{AcknowledgeCheckFailedReason, Config, Error, I2c, Operation},
+    gpio::{AnyPin, Level, Output, OutputConfig, Pin},
+    i2c::master::{
+        smbus::{self, Smbus},
+        AcknowledgeCheckFailedReason,
+        BusTimeout,
+        Config,
+        ConfigError,
+        Error,
+        I2c,
+        I2cAddress,
+        Operation,
+    },
+    peripheral::Peripheral,
+    rom::crc::crc8_be,
+    time::{ExtU64, RateExtU32},
     Async,
     Blocking,
 };
@@ -15,6 +29,7 @@ use hil_test as _;
 
 struct Context {
     i2c: I2c<'static, Blocking>,
+    sda: AnyPin,
 }
 
 fn _async_driver_is_compatible_with_blocking_ehal() {
@@ -29,7 +44,7 @@ const DUT_ADDRESS: u8 = 0x77;
 const NON_EXISTENT_ADDRESS: u8 = 0x6b;
 
 #[cfg(test)]
-#[embedded_test::tests(default_timeout = 3)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
 mod tests {
     use super::*;
 
@@ -43,10 +58,13 @@ mod tests {
         // I2C clock speed:
         let i2c = I2c::new(peripherals.I2C0, Config::default())
             .unwrap()
-            .with_sda(sda)
+            .with_sda(unsafe { sda.clone_unchecked() })
             .with_scl(scl);
 
-        Context { i2c }
+        Context {
+            i2c,
+            sda: sda.degrade(),
+        }
     }
 
     #[test]
@@ -74,6 +92,55 @@ mod tests {
         assert_eq!(ctx.i2c.write(DUT_ADDRESS, &[]), Ok(()));
     }
 
+    #[test]
+    fn invalid_addresses_are_rejected(mut ctx: Context) {
+        for address in [
+            I2cAddress::SevenBit(0x01),
+            I2cAddress::SevenBit(0x78),
+            I2cAddress::SevenBit(0x80),
+            I2cAddress::TenBit(0x400),
+        ] {
+            assert_eq!(
+                ctx.i2c.write(address, &[0xaa]),
+                Err(Error::AddressInvalid(address))
+            );
+            assert_eq!(
+                ctx.i2c.read(address, &mut [0u8; 1]),
+                Err(Error::AddressInvalid(address))
+            );
+        }
+
+        assert_eq!(ctx.i2c.write(DUT_ADDRESS, &[]), Ok(()));
+    }
+
+    #[test]
+    fn ten_bit_addresses_reach_the_bus(mut ctx: Context) {
+        // The device doesn't have a 10-bit address, so it doesn't acknowledge.
+        let address = I2cAddress::TenBit(0x277);
+        assert!(matches!(
+            ctx.i2c.write(address, &[0xaa]),
+            Err(Error::AcknowledgeCheckFailed(_))
+        ));
+        let mut read_data = [0u8; 22];
+        assert!(matches!(
+            ctx.i2c.write_read(address, &[0xaa], &mut read_data),
+            Err(Error::AcknowledgeCheckFailed(_))
+        ));
+        assert!(matches!(
+            embedded_hal::i2c::I2c::<embedded_hal::i2c::TenBitAddress>::read(
+                &mut ctx.i2c,
+                0x277,
+                &mut read_data
+            ),
+            Err(Error::AcknowledgeCheckFailed(_))
+        ));
+
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .unwrap();
+        assert_ne!(read_data, [0u8; 22]);
+    }
+
     #[test]
     fn test_read_cali(mut ctx: Context) {
         let mut read_data = [0u8; 22];
@@ -106,4 +173,476 @@ mod tests {
 
         assert_ne!(read_data, [0u8; 22])
     }
+
+    #[test]
+    async fn read_longer_than_a_command(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        // Needs several command lists, but is a single transaction.
+        let mut read_data = [0u8; 600];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .unwrap();
+        assert_eq!(read_data[..expected.len()], expected);
+
+        let mut i2c = ctx.i2c.into_async();
+        let mut read_data = [0u8; 600];
+        i2c.write_read_async(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .await
+            .unwrap();
+        assert_eq!(read_data[..expected.len()], expected);
+    }
+
+    #[test]
+    async fn write_and_read_longer_than_fifo_in_one_transaction(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        // The sensor takes the bytes as register/value pairs, and ignores
+        // writes to the read-only calibration registers. The odd byte at the
+        // end selects the register that the read starts at. Both operations
+        // need several FIFO refills and command lists.
+        let write_data = [0xaa; 301];
+        let mut read_data = [0u8; 300];
+        ctx.i2c
+            .transaction(
+                DUT_ADDRESS,
+                &mut [
+                    Operation::Write(&write_data),
+                    Operation::Read(&mut read_data),
+                ],
+            )
+            .unwrap();
+        assert_eq!(read_data[..expected.len()], expected);
+
+        let mut i2c = ctx.i2c.into_async();
+        let mut read_data = [0u8; 300];
+        i2c.transaction_async(
+            DUT_ADDRESS,
+            &mut [
+                Operation::Write(&write_data),
+                Operation::Read(&mut read_data),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(read_data[..expected.len()], expected);
+    }
+
+    #[test]
+    fn transaction_write_then_two_reads(mut ctx: Context) {
+        let mut expected = [0u8; 64];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        // Together, the reads are longer than the FIFO.
+        let mut first = [0u8; 40];
+        let mut second = [0u8; 24];
+        ctx.i2c
+            .transaction(
+                DUT_ADDRESS,
+                &mut [
+                    Operation::Write(&[0xaa]),
+                    Operation::Read(&mut first),
+                    Operation::Read(&mut second),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(first, expected[..40]);
+        assert_eq!(second, expected[40..]);
+    }
+
+    #[test]
+    fn transaction_merges_adjacent_writes(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        let mut read_data = [0u8; 22];
+        ctx.i2c
+            .transaction(
+                DUT_ADDRESS,
+                &mut [
+                    Operation::Write(&[]),
+                    Operation::Write(&[0xaa]),
+                    Operation::Read(&mut read_data),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(read_data, expected);
+    }
+
+    #[test]
+    async fn async_transaction_write_then_two_reads(mut ctx: Context) {
+        let mut expected = [0u8; 64];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        let mut i2c = ctx.i2c.into_async();
+
+        let mut first = [0u8; 40];
+        let mut second = [0u8; 24];
+        i2c.transaction_async(
+            DUT_ADDRESS,
+            &mut [
+                Operation::Write(&[0xaa]),
+                Operation::Read(&mut first),
+                Operation::Read(&mut second),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first, expected[..40]);
+        assert_eq!(second, expected[40..]);
+    }
+
+    #[test]
+    async fn async_errors_match_blocking(mut ctx: Context) {
+        let blocking_error = ctx.i2c.write(NON_EXISTENT_ADDRESS, &[0xaa]).unwrap_err();
+
+        let mut i2c = ctx.i2c.into_async();
+        assert_eq!(
+            i2c.write_async(NON_EXISTENT_ADDRESS, &[0xaa]).await,
+            Err(blocking_error)
+        );
+
+        let mut read_data = [0u8; 22];
+        assert!(i2c
+            .write_read_async(NON_EXISTENT_ADDRESS, &[0xaa], &mut read_data)
+            .await
+            .is_err());
+
+        assert_eq!(i2c.write_async(DUT_ADDRESS, &[]).await, Ok(()));
+    }
+
+    #[test]
+    async fn async_read_longer_than_fifo(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        let mut i2c = ctx.i2c.into_async();
+
+        // ESP32 and ESP32-S2 split transfers at the FIFO size.
+        let mut read_data = [0u8; 64];
+        i2c.write_read_async(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .await
+            .unwrap();
+
+        assert_eq!(read_data[..expected.len()], expected);
+    }
+
+    #[test]
+    async fn dropped_async_transfer_releases_bus(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        let mut i2c = ctx.i2c.into_async();
+
+        let mut read_data = [0u8; 22];
+        let result = embassy_futures::select::select(
+            i2c.write_read_async(DUT_ADDRESS, &[0xaa], &mut read_data),
+            embassy_futures::yield_now(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            embassy_futures::select::Either::Second(())
+        ));
+
+        let mut read_data = [0u8; 22];
+        i2c.write_read_async(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .await
+            .unwrap();
+        assert_eq!(read_data, expected);
+    }
+
+    #[test]
+    fn clear_bus_restores_the_pins(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        assert_eq!(ctx.i2c.clear_bus(), Ok(()));
+
+        let mut read_data = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .unwrap();
+        assert_eq!(read_data, expected);
+    }
+
+    #[test]
+    fn timeout_can_be_configured_as_duration(mut ctx: Context) {
+        ctx.i2c
+            .apply_config(&Config::default().with_timeout(BusTimeout::Duration(1.millis())))
+            .unwrap();
+
+        let mut read_data = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .unwrap();
+        assert_ne!(read_data, [0u8; 22]);
+
+        // Longer than any chip can represent.
+        assert_eq!(
+            ctx.i2c
+                .apply_config(&Config::default().with_timeout(BusTimeout::Duration(1000.secs()))),
+            Err(ConfigError::TimeoutInvalid)
+        );
+    }
+
+    #[test]
+    fn frequency_can_be_switched_between_transactions(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        assert_eq!(
+            ctx.i2c
+                .apply_config(&Config::default().with_frequency(0.Hz())),
+            Err(ConfigError::FrequencyInvalid)
+        );
+
+        // Faster than the dividers allow on any chip.
+        let closest = match ctx
+            .i2c
+            .apply_config(&Config::default().with_frequency(20.MHz()))
+        {
+            Err(ConfigError::FrequencyUnreachable(closest)) => closest,
+            other => panic!("{:?}", other),
+        };
+        assert!(closest.to_Hz() < 20_000_000);
+        ctx.i2c
+            .apply_config(&Config::default().with_frequency(closest))
+            .unwrap();
+
+        // The rejected configurations didn't affect the bus.
+        for frequency in [400.kHz(), 100.kHz()] {
+            ctx.i2c
+                .apply_config(&Config::default().with_frequency(frequency))
+                .unwrap();
+
+            let mut read_data = [0u8; 22];
+            ctx.i2c
+                .write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+                .unwrap();
+            assert_eq!(read_data, expected);
+        }
+    }
+
+    #[test]
+    fn stuck_sda_returns_within_timeout(mut ctx: Context) {
+        ctx.i2c
+            .apply_config(&Config::default().with_timeout(BusTimeout::Duration(1.millis())))
+            .unwrap();
+
+        let sda = Output::new(
+            unsafe { ctx.sda.clone_unchecked() },
+            Level::Low,
+            OutputConfig::default(),
+        );
+
+        let start = esp_hal::time::now();
+        let mut read_data = [0u8; 22];
+        let result = ctx.i2c.write_read(DUT_ADDRESS, &[0xaa], &mut read_data);
+        let elapsed = esp_hal::time::now() - start;
+
+        // Depending on the chip, the controller notices that it can't release
+        // SDA before the bus timeout triggers.
+        assert!(
+            matches!(result, Err(Error::Timeout | Error::ArbitrationLost)),
+            "{:?}",
+            result
+        );
+        assert!(elapsed.to_millis() < 20, "{}", elapsed);
+
+        // The bus works again once SDA is released.
+        drop(sda);
+        let mut i2c = ctx.i2c.with_sda(ctx.sda);
+        i2c.write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .unwrap();
+    }
+
+    #[test]
+    async fn stuck_sda_returns_within_timeout_async(mut ctx: Context) {
+        ctx.i2c
+            .apply_config(&Config::default().with_timeout(BusTimeout::Duration(1.millis())))
+            .unwrap();
+
+        let _sda = Output::new(ctx.sda, Level::Low, OutputConfig::default());
+
+        let mut i2c = ctx.i2c.into_async();
+
+        let start = esp_hal::time::now();
+        let mut read_data = [0u8; 22];
+        let result = i2c
+            .write_read_async(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .await;
+        let elapsed = esp_hal::time::now() - start;
+
+        assert!(
+            matches!(result, Err(Error::Timeout | Error::ArbitrationLost)),
+            "{:?}",
+            result
+        );
+        assert!(elapsed.to_millis() < 20, "{}", elapsed);
+    }
+
+    #[test]
+    fn smbus_read_word_matches_plain_read(mut ctx: Context) {
+        let mut expected = [0u8; 2];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        let mut smbus = Smbus::new(ctx.i2c);
+        assert_eq!(
+            smbus.read_word(DUT_ADDRESS, 0xaa),
+            Ok(u16::from_le_bytes(expected))
+        );
+    }
+
+    #[test]
+    fn smbus_pec_is_checked(mut ctx: Context) {
+        let mut data = [0u8; 3];
+        ctx.i2c.write_read(DUT_ADDRESS, &[0xaa], &mut data).unwrap();
+
+        // The sensor doesn't know about PEC, the third byte is just the next
+        // register.
+        let header = DUT_ADDRESS << 1;
+        let pec = !crc8_be(!0, &[header, 0xaa, header | 1, data[0], data[1]]);
+
+        let mut smbus = Smbus::new(ctx.i2c).with_pec(true);
+        let result = smbus.read_word(DUT_ADDRESS, 0xaa);
+        if pec == data[2] {
+            assert_eq!(result, Ok(u16::from_le_bytes([data[0], data[1]])));
+        } else {
+            assert_eq!(result, Err(smbus::Error::PecMismatch));
+        }
+    }
+
+    #[test]
+    fn smbus_block_read_handles_any_length(mut ctx: Context) {
+        let mut expected = [0u8; smbus::MAX_BLOCK_LEN + 1];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+        let len = expected[0];
+
+        let mut smbus = Smbus::new(ctx.i2c);
+
+        // The first calibration byte is taken as the length of the block.
+        let mut buffer = [0u8; smbus::MAX_BLOCK_LEN];
+        match smbus.block_read(DUT_ADDRESS, 0xaa, &mut buffer) {
+            Ok(read) => {
+                assert_eq!(read, len as usize);
+                assert_eq!(buffer[..read], expected[1..][..read]);
+            }
+            Err(error) => assert_eq!(error, smbus::Error::BlockLengthInvalid(len)),
+        }
+
+        let result = smbus.block_read(DUT_ADDRESS, 0xaa, &mut []);
+        if len == 0 {
+            assert_eq!(result, Ok(0));
+        } else {
+            assert_eq!(result, Err(smbus::Error::BlockLengthInvalid(len)));
+        }
+
+        // The transaction was terminated, the bus is usable.
+        assert_eq!(
+            smbus.read_word(DUT_ADDRESS, 0xaa),
+            Ok(u16::from_le_bytes([expected[0], expected[1]]))
+        );
+    }
+
+    #[test]
+    fn smbus_write_errors(ctx: Context) {
+        let mut smbus = Smbus::new(ctx.i2c);
+        assert_eq!(
+            smbus.block_write(DUT_ADDRESS, 0xaa, &[0; smbus::MAX_BLOCK_LEN + 1]),
+            Err(smbus::Error::BlockTooLong)
+        );
+        assert!(matches!(
+            smbus.write_word(NON_EXISTENT_ADDRESS, 0xaa, 0),
+            Err(smbus::Error::I2c(Error::AcknowledgeCheckFailed(_)))
+        ));
+    }
+
+    #[test]
+    fn polled_transfers_match_blocking(mut ctx: Context) {
+        let mut expected = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut expected)
+            .unwrap();
+
+        ctx.i2c
+            .start_write(DUT_ADDRESS, &[0xaa])
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        // Longer than a command list can read.
+        let mut read_data = [0u8; 300];
+        let mut transfer = ctx.i2c.start_read(DUT_ADDRESS, &mut read_data).unwrap();
+        let mut pending = 0;
+        loop {
+            match transfer.poll() {
+                Ok(()) => break,
+                Err(nb::Error::WouldBlock) => pending += 1,
+                Err(nb::Error::Other(error)) => panic!("{:?}", error),
+            }
+        }
+        assert!(transfer.is_done());
+        assert_eq!(transfer.poll(), Ok(()));
+        core::mem::drop(transfer);
+
+        assert!(pending > 0);
+        assert_eq!(read_data[..expected.len()], expected);
+    }
+
+    #[test]
+    fn polled_transfer_reports_errors(mut ctx: Context) {
+        let mut transfer = ctx.i2c.start_write(NON_EXISTENT_ADDRESS, &[0xaa]).unwrap();
+        let result = nb::block!(transfer.poll());
+        assert!(matches!(result, Err(Error::AcknowledgeCheckFailed(_))));
+        assert_eq!(transfer.poll(), Err(nb::Error::Other(result.unwrap_err())));
+        core::mem::drop(transfer);
+
+        assert!(matches!(
+            ctx.i2c.start_write(0x78, &[0xaa]),
+            Err(Error::AddressInvalid(_))
+        ));
+        assert_eq!(ctx.i2c.write(DUT_ADDRESS, &[]), Ok(()));
+    }
+
+    #[test]
+    fn dropped_polled_transfer_releases_bus(mut ctx: Context) {
+        let mut read_data = [0u8; 300];
+        let mut transfer = ctx.i2c.start_read(DUT_ADDRESS, &mut read_data).unwrap();
+        assert_eq!(transfer.poll(), Err(nb::Error::WouldBlock));
+        core::mem::drop(transfer);
+
+        let mut read_data = [0u8; 22];
+        ctx.i2c
+            .write_read(DUT_ADDRESS, &[0xaa], &mut read_data)
+            .unwrap();
+        assert_ne!(read_data, [0u8; 22]);
+    }
 }
@@ -0,0 +1,211 @@
+//! I2C slave test
+//!
+//! The master and the slave share SDA through the two connected test pins.
+//! Both read SCL from the same pin, which only the master drives, so the
+//! slave can't stretch the clock and serves reads from its TX FIFO.
+
+//% CHIPS: esp32h2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    i2c::{
+        master::{self, AcknowledgeCheckFailedReason},
+        slave::{self, Request},
+    },
+    peripheral::Peripheral,
+    Blocking,
+};
+use hil_test as _;
+
+const SLAVE_ADDRESS: u8 = 0x42;
+
+struct Context {
+    master: master::I2c<'static, Blocking>,
+    slave: slave::I2c<'static, Blocking>,
+    slave_config: slave::Config,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (master_sda, slave_sda) = hil_test::common_test_pins!(peripherals);
+        let (_, scl) = hil_test::i2c_pins!(peripherals);
+
+        let slave_config = slave::Config::default()
+            .with_address(SLAVE_ADDRESS)
+            .with_clock_stretch(false);
+
+        // The master is connected last, so that it drives SCL.
+        let slave = slave::I2c::new(peripherals.I2C1, slave_config)
+            .unwrap()
+            .with_sda(slave_sda)
+            .with_scl(unsafe { scl.clone_unchecked() });
+        let master = master::I2c::new(peripherals.I2C0, master::Config::default())
+            .unwrap()
+            .with_sda(master_sda)
+            .with_scl(scl);
+
+        Context {
+            master,
+            slave,
+            slave_config,
+        }
+    }
+
+    #[test]
+    fn master_write_is_received(mut ctx: Context) {
+        ctx.master.write(SLAVE_ADDRESS, &[1, 2, 3, 4]).unwrap();
+
+        let mut buffer = [0u8; 8];
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::Write(4))
+        );
+        assert_eq!(buffer[..4], [1, 2, 3, 4]);
+
+        // An address probe is a write without data.
+        ctx.master.write(SLAVE_ADDRESS, &[]).unwrap();
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::Write(0))
+        );
+    }
+
+    #[test]
+    fn data_beyond_the_buffer_is_discarded(mut ctx: Context) {
+        ctx.master.write(SLAVE_ADDRESS, &[1, 2, 3, 4]).unwrap();
+
+        let mut buffer = [0u8; 2];
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::Write(2))
+        );
+        assert_eq!(buffer, [1, 2]);
+    }
+
+    #[test]
+    fn master_reads_preloaded_response(mut ctx: Context) {
+        assert_eq!(ctx.slave.preload_response(&[0xA5, 0x5A, 0x01, 0x02]), 4);
+
+        let mut read = [0u8; 4];
+        ctx.master.read(SLAVE_ADDRESS, &mut read).unwrap();
+        assert_eq!(read, [0xA5, 0x5A, 0x01, 0x02]);
+
+        // Only 32 bytes fit into the TX FIFO.
+        assert_eq!(ctx.slave.preload_response(&[0x55; 40]), 32);
+    }
+
+    #[test]
+    fn write_read_reports_written_data(mut ctx: Context) {
+        ctx.slave.preload_response(&[0x12, 0x34]);
+
+        let mut read = [0u8; 2];
+        ctx.master
+            .write_read(SLAVE_ADDRESS, &[0x10], &mut read)
+            .unwrap();
+        assert_eq!(read, [0x12, 0x34]);
+
+        // Without clock stretching, the read isn't reported.
+        let mut buffer = [0u8; 4];
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::Write(1))
+        );
+        assert_eq!(buffer[0], 0x10);
+    }
+
+    #[test]
+    fn other_addresses_are_not_acknowledged(mut ctx: Context) {
+        assert_eq!(
+            ctx.master.write(SLAVE_ADDRESS + 1, &[0x01]),
+            Err(master::Error::AcknowledgeCheckFailed(
+                AcknowledgeCheckFailedReason::Address
+            ))
+        );
+    }
+
+    #[test]
+    fn general_call_is_reported_when_enabled(mut ctx: Context) {
+        // General calls are ignored unless enabled.
+        assert!(ctx.master.write(0x00, &[0x06]).is_err());
+
+        ctx.slave
+            .apply_config(&ctx.slave_config.with_general_call(true))
+            .unwrap();
+        ctx.master.write(0x00, &[0x06]).unwrap();
+
+        let mut buffer = [0u8; 4];
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::GeneralCall(1))
+        );
+        assert_eq!(buffer[0], 0x06);
+
+        // The device still responds to its own address.
+        ctx.master.write(SLAVE_ADDRESS, &[0x07]).unwrap();
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::Write(1))
+        );
+        assert_eq!(buffer[0], 0x07);
+    }
+
+    #[test]
+    fn reserved_addresses_are_rejected(mut ctx: Context) {
+        for address in [0x00, 0x07, 0x78, 0x80] {
+            assert_eq!(
+                ctx.slave
+                    .apply_config(&ctx.slave_config.with_address(address)),
+                Err(slave::ConfigError::AddressInvalid)
+            );
+        }
+    }
+
+    #[test]
+    fn full_rx_fifo_is_not_acknowledged(mut ctx: Context) {
+        // Without clock stretching, the slave can't hold off the master while
+        // the RX FIFO is full.
+        assert!(ctx.master.write(SLAVE_ADDRESS, &[0xAA; 40]).is_err());
+
+        // Discard the partial write and check that the slave still works.
+        ctx.slave.apply_config(&ctx.slave_config).unwrap();
+        ctx.master.write(SLAVE_ADDRESS, &[0x07]).unwrap();
+
+        let mut buffer = [0u8; 4];
+        assert_eq!(
+            ctx.slave.wait_for_request(&mut buffer),
+            Ok(Request::Write(1))
+        );
+        assert_eq!(buffer[0], 0x07);
+    }
+
+    #[test]
+    async fn async_requests(ctx: Context) {
+        let mut master = ctx.master.into_async();
+        let mut slave = ctx.slave.into_async();
+
+        let mut buffer = [0u8; 8];
+        let (written, request) = embassy_futures::join::join(
+            master.write_async(SLAVE_ADDRESS, &[5, 6, 7]),
+            slave.wait_for_request_async(&mut buffer),
+        )
+        .await;
+        written.unwrap();
+        assert_eq!(request, Ok(Request::Write(3)));
+        assert_eq!(buffer[..3], [5, 6, 7]);
+
+        slave.preload_response(&[0x0F; 3]);
+        let mut read = [0u8; 3];
+        master.read_async(SLAVE_ADDRESS, &mut read).await.unwrap();
+        assert_eq!(read, [0x0F; 3]);
+    }
+}
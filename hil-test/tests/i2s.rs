@@ -1,7 +1,7 @@
 //! I2S Loopback Test
 //!
-//! This test uses I2S TX to transmit known data to I2S RX (forced to slave mode
-//! with loopback mode enabled).
+//! This test uses I2S TX to transmit known data to I2S RX, which shares the
+//! clocks of the TX half.
 
 //% CHIPS: esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
 //% FEATURES: unstable
@@ -10,14 +10,19 @@
 #![no_std]
 #![no_main]
 
+#[cfg(not(esp32s2))]
+use esp_hal::i2s::master::TdmConfig;
 use esp_hal::{
     delay::Delay,
+    dma::DmaError,
     dma_buffers,
-    gpio::{AnyPin, NoPin, Pin},
-    i2s::master::{DataFormat, I2s, I2sTx, Standard},
+    dma_descriptors,
+    gpio::{AnyPin, Level, NoPin, Pin},
+    i2s::master::{ConfigError, DataFormat, Error, I2s, I2sTx, MclkMultiple, Standard},
     peripherals::I2S0,
     time::RateExtU32,
     Async,
+    Blocking,
 };
 use hil_test as _;
 
@@ -56,6 +61,89 @@ impl Iterator for SampleSource {
     }
 }
 
+/// A full-scale 24-bit sine, 16 samples per period, with the right channel in
+/// antiphase.
+#[cfg(not(esp32s2))]
+fn sine_24_bit(frames: usize) -> impl Iterator<Item = i32> {
+    const COS_STEP: f32 = 0.923_879_5;
+    const FULL_SCALE: f32 = 8_388_607.0;
+
+    let (mut previous, mut current) = (-0.382_683_43f32, 0.0f32);
+    (0..frames).flat_map(move |_| {
+        let sample = ((current * FULL_SCALE) as i32).clamp(-8_388_607, 8_388_607);
+        (previous, current) = (current, 2.0 * COS_STEP * current - previous);
+
+        [sample, -sample - 1]
+    })
+}
+
+/// Sends a full-scale 24-bit sine through a pin split into DOUT and DIN, and
+/// checks that every sample comes back exactly.
+#[cfg(not(esp32s2))]
+fn assert_24_bit_round_trip(
+    i2s: I2S0,
+    dma_channel: DmaChannel0,
+    pin: AnyPin,
+    data_format: DataFormat,
+) {
+    const FRAMES: usize = 128;
+    const SAMPLES: usize = 2 * FRAMES;
+    const BYTES: usize = 3 * SAMPLES;
+
+    assert_eq!(data_format.bytes_per_sample(), 3);
+
+    let mut sine = [0i32; SAMPLES];
+    for (sample, value) in sine.iter_mut().zip(sine_24_bit(FRAMES)) {
+        *sample = value;
+    }
+    assert!(sine.contains(&8_388_607) && sine.contains(&-8_388_608));
+
+    let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(BYTES, 2 * BYTES);
+
+    let i2s = I2s::new(
+        i2s,
+        Standard::Philips,
+        data_format,
+        16000.Hz(),
+        dma_channel,
+        rx_descriptors,
+        tx_descriptors,
+    )
+    .with_shared_clock();
+
+    let (din, dout) = pin.split();
+
+    let mut i2s_tx = i2s
+        .i2s_tx
+        .with_bclk(NoPin)
+        .with_ws(NoPin)
+        .with_dout(dout)
+        .build();
+
+    let mut i2s_rx = i2s
+        .i2s_rx
+        .with_bclk(NoPin)
+        .with_ws(NoPin)
+        .with_din(din)
+        .build();
+
+    // The trailing silence keeps the clocks running until RX is done.
+    tx_buffer.fill(0);
+    assert_eq!(data_format.pack_samples(&sine, tx_buffer), BYTES);
+
+    let rx_transfer = i2s_rx.read_dma(rx_buffer).unwrap();
+    let tx_transfer = i2s_tx.write_dma(tx_buffer).unwrap();
+    rx_transfer.wait().unwrap();
+    tx_transfer.wait().unwrap();
+
+    let mut received = [0i32; SAMPLES];
+    assert_eq!(
+        data_format.unpack_samples(rx_buffer, &mut received),
+        SAMPLES
+    );
+    assert_eq!(received, sine);
+}
+
 #[embassy_executor::task]
 async fn writer(tx_buffer: &'static mut [u8], i2s_tx: I2sTx<'static, Async>) {
     let mut samples = SampleSource::new();
@@ -78,19 +166,22 @@ async fn writer(tx_buffer: &'static mut [u8], i2s_tx: I2sTx<'static, Async>) {
     }
 }
 
-fn enable_loopback() {
-    let i2s = esp_hal::peripherals::I2S0::regs();
-    cfg_if::cfg_if! {
-        if #[cfg(any(esp32, esp32s2))] {
-            i2s.conf().modify(|_, w| w.sig_loopback().set_bit());
-            i2s.conf().modify(|_, w| w.rx_slave_mod().set_bit());
-        } else {
-            i2s.tx_conf().modify(|_, w| w.sig_loopback().set_bit());
-            i2s.rx_conf().modify(|_, w| w.rx_slave_mod().set_bit());
-
-            i2s.tx_conf().modify(|_, w| w.tx_update().set_bit());
-            i2s.rx_conf().modify(|_, w| w.rx_update().set_bit());
+#[embassy_executor::task]
+async fn chunked_writer(tx_buffer: &'static mut [u8], i2s_tx: I2sTx<'static, Async>) {
+    let mut samples = SampleSource::new();
+    for b in tx_buffer.iter_mut() {
+        *b = samples.next().unwrap();
+    }
+
+    let mut tx_transfer = i2s_tx.write_dma_circular_async(tx_buffer).unwrap();
+
+    // Deliberately not a multiple of the descriptor size.
+    let mut chunk = [0u8; 300];
+    loop {
+        for b in chunk.iter_mut() {
+            *b = samples.next().unwrap();
         }
+        tx_transfer.push_all(&chunk).await.unwrap();
     }
 }
 
@@ -100,6 +191,7 @@ mod tests {
     use super::*;
 
     struct Context {
+        din: AnyPin,
         dout: AnyPin,
         dma_channel: DmaChannel0,
         i2s: I2S0,
@@ -119,9 +211,10 @@ mod tests {
             }
         }
 
-        let (_, dout) = hil_test::common_test_pins!(peripherals);
+        let (din, dout) = hil_test::common_test_pins!(peripherals);
 
         Context {
+            din: din.degrade(),
             dout: dout.degrade(),
             dma_channel,
             i2s: peripherals.I2S0,
@@ -144,6 +237,7 @@ mod tests {
             rx_descriptors,
             tx_descriptors,
         )
+        .with_shared_clock()
         .into_async();
 
         let (din, dout) = ctx.dout.split();
@@ -162,8 +256,6 @@ mod tests {
             .with_din(din)
             .build();
 
-        enable_loopback();
-
         let mut rx_transfer = i2s_rx.read_dma_circular_async(rx_buffer).unwrap();
         spawner.must_spawn(writer(tx_buffer, i2s_tx));
 
@@ -184,6 +276,104 @@ mod tests {
         }
     }
 
+    #[test]
+    async fn test_i2s_async_streams_survive_cancellation(ctx: Context) {
+        let spawner = embassy_executor::Spawner::for_current_executor().await;
+
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) =
+            esp_hal::dma_circular_buffers!(BUFFER_SIZE, BUFFER_SIZE);
+
+        let i2s = I2s::new(
+            ctx.i2s,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            16000.Hz(),
+            ctx.dma_channel,
+            rx_descriptors,
+            tx_descriptors,
+        )
+        .with_shared_clock()
+        .into_async();
+
+        let (din, dout) = ctx.dout.split();
+
+        let i2s_tx = i2s
+            .i2s_tx
+            .with_bclk(NoPin)
+            .with_ws(NoPin)
+            .with_dout(dout)
+            .build();
+
+        let i2s_rx = i2s
+            .i2s_rx
+            .with_bclk(NoPin)
+            .with_ws(NoPin)
+            .with_din(din)
+            .build();
+
+        let mut rx_transfer = i2s_rx.read_dma_circular_async(rx_buffer).unwrap();
+        spawner.must_spawn(chunked_writer(tx_buffer, i2s_tx));
+
+        // Smaller than the whole buffer, but large enough for a descriptor.
+        let mut rcv = [0u8; BUFFER_SIZE / 2];
+        let mut sample_idx = 0;
+        let mut samples = SampleSource::new();
+        for _ in 0..60 {
+            // Start waiting, then give up. The transfer has to keep going.
+            let _ = embassy_futures::poll_once(rx_transfer.available());
+
+            let len = rx_transfer.pop(&mut rcv).await.unwrap();
+            assert!(len > 0);
+            for &b in &rcv[..len] {
+                let expected = samples.next().unwrap();
+                assert_eq!(
+                    b, expected,
+                    "Sample #{} does not match ({} != {})",
+                    sample_idx, b, expected
+                );
+                sample_idx += 1;
+            }
+        }
+
+        let mut tiny = [0u8; 4];
+        assert_eq!(
+            rx_transfer.pop(&mut tiny).await,
+            Err(Error::DmaError(DmaError::BufferTooSmall))
+        );
+    }
+
+    #[test]
+    async fn test_i2s_async_underrun_is_reported(ctx: Context) {
+        let (_, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(0, 16000);
+
+        let i2s = I2s::new(
+            ctx.i2s,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            16000.Hz(),
+            ctx.dma_channel,
+            rx_descriptors,
+            tx_descriptors,
+        )
+        .into_async();
+
+        let i2s_tx = i2s
+            .i2s_tx
+            .with_bclk(NoPin)
+            .with_ws(NoPin)
+            .with_dout(ctx.dout)
+            .build();
+
+        let mut tx_transfer = i2s_tx.write_dma_circular_async(tx_buffer).unwrap();
+
+        Delay::new().delay_millis(300);
+
+        assert_eq!(
+            tx_transfer.push(&[0; 128]).await,
+            Err(Error::DmaError(DmaError::Late))
+        );
+    }
+
     #[test]
     fn test_i2s_loopback(ctx: Context) {
         let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(16000, 16000);
@@ -196,7 +386,8 @@ mod tests {
             ctx.dma_channel,
             rx_descriptors,
             tx_descriptors,
-        );
+        )
+        .with_shared_clock();
 
         let (din, dout) = ctx.dout.split();
 
@@ -214,8 +405,6 @@ mod tests {
             .with_din(din)
             .build();
 
-        enable_loopback();
-
         let mut samples = SampleSource::new();
         for b in tx_buffer.iter_mut() {
             *b = samples.next().unwrap();
@@ -293,6 +482,385 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(not(esp32s2))]
+    fn test_i2s_24_bit_samples_in_32_bit_channels(ctx: Context) {
+        assert_24_bit_round_trip(
+            ctx.i2s,
+            ctx.dma_channel,
+            ctx.dout,
+            DataFormat::Data24Channel32,
+        );
+    }
+
+    #[test]
+    #[cfg(not(esp32s2))]
+    fn test_i2s_24_bit_samples_in_24_bit_channels(ctx: Context) {
+        assert_24_bit_round_trip(
+            ctx.i2s,
+            ctx.dma_channel,
+            ctx.dout,
+            DataFormat::Data24Channel24,
+        );
+    }
+
+    #[test]
+    #[cfg(not(esp32s2))]
+    fn test_i2s_sample_api_sign_extends(ctx: Context) {
+        let (rx_descriptors, tx_descriptors) = dma_descriptors!(4092);
+
+        let i2s = I2s::new(
+            ctx.i2s,
+            Standard::Philips,
+            DataFormat::Data24Channel32,
+            16000.Hz(),
+            ctx.dma_channel,
+            rx_descriptors,
+            tx_descriptors,
+        );
+
+        let mut i2s_tx = i2s.i2s_tx.with_dout(NoPin).build();
+        let mut i2s_rx = i2s.i2s_rx.with_din(Level::High).build();
+
+        // Packed for the transfer, but unchanged afterwards.
+        let mut samples = [0i32; 8];
+        for (sample, value) in samples.iter_mut().zip(sine_24_bit(4)) {
+            *sample = value;
+        }
+        let original = samples;
+        i2s_tx.write_samples(&mut samples).unwrap();
+        assert_eq!(samples, original);
+
+        // A line that stays high is all ones, which is -1 in any width.
+        let mut received = [0i32; 8];
+        i2s_rx.read_samples(&mut received).unwrap();
+        assert_eq!(received, [-1; 8]);
+
+        // 5 samples of 3 bytes aren't a whole number of words.
+        assert_eq!(
+            i2s_rx.read_samples(&mut received[..5]),
+            Err(Error::IllegalArgument)
+        );
+    }
+
+    #[test]
+    fn test_i2s_full_duplex(ctx: Context) {
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(4000, 4000);
+
+        let i2s = I2s::new(
+            ctx.i2s,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            16000.Hz(),
+            ctx.dma_channel,
+            rx_descriptors,
+            tx_descriptors,
+        )
+        .with_shared_clock();
+
+        // DOUT is wired to DIN outside of the chip.
+        let mut i2s_tx = i2s
+            .i2s_tx
+            .with_bclk(NoPin)
+            .with_ws(NoPin)
+            .with_dout(ctx.dout)
+            .build();
+
+        let mut i2s_rx = i2s.i2s_rx.with_din(ctx.din).build();
+
+        let mut samples = SampleSource::new();
+        for b in tx_buffer.iter_mut() {
+            *b = samples.next().unwrap();
+        }
+
+        let mut filler = [0u8; 4000];
+        let mut rcv = [0u8; 4000];
+
+        let mut tx_transfer = i2s_tx.write_dma_circular(tx_buffer).unwrap();
+
+        // Restarting RX must not disturb the running TX transfer.
+        for _ in 0..3 {
+            let mut rx_transfer = i2s_rx.read_dma_circular(rx_buffer).unwrap();
+
+            let mut previous = None;
+            let mut received = 0;
+            while received < 8000 {
+                let tx_avail = tx_transfer.available().unwrap();
+                if tx_avail > 0 {
+                    for b in &mut filler[..tx_avail] {
+                        *b = samples.next().unwrap();
+                    }
+                    tx_transfer.push(&filler[..tx_avail]).unwrap();
+                }
+
+                if rx_transfer.available().unwrap() == 0 {
+                    continue;
+                }
+                let len = rx_transfer.pop(&mut rcv).unwrap();
+
+                // RX joins in the middle of the stream, so only check that
+                // every sample follows the one before it.
+                for &b in &rcv[..len] {
+                    if let Some(previous) = previous {
+                        assert_eq!(
+                            b,
+                            (previous + SampleSource::ADD) % SampleSource::CUT_OFF,
+                            "Sample #{} does not match",
+                            received
+                        );
+                    }
+                    previous = Some(b);
+                    received += 1;
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(esp32s2))]
+    fn test_i2s_tdm_loopback(ctx: Context) {
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(3000, 3000);
+
+        // Three of the four slots carry data.
+        let config = TdmConfig::default()
+            .with_slots(4)
+            .with_active_slot_mask(0b1011);
+
+        let i2s = I2s::new_tdm(
+            ctx.i2s,
+            config,
+            16000.Hz(),
+            ctx.dma_channel,
+            rx_descriptors,
+            tx_descriptors,
+        )
+        .unwrap()
+        .with_shared_clock();
+
+        let (din, dout) = ctx.dout.split();
+
+        let mut i2s_tx = i2s
+            .i2s_tx
+            .with_bclk(NoPin)
+            .with_ws(NoPin)
+            .with_dout(dout)
+            .build();
+
+        let mut i2s_rx = i2s.i2s_rx.with_din(din).build();
+
+        let mut samples = SampleSource::new();
+        for b in tx_buffer.iter_mut() {
+            *b = samples.next().unwrap();
+        }
+
+        let mut filler = [0u8; 3000];
+        let mut rcv = [0u8; 3000];
+
+        let mut rx_transfer = i2s_rx.read_dma_circular(rx_buffer).unwrap();
+        let mut tx_transfer = i2s_tx.write_dma_circular(tx_buffer).unwrap();
+
+        let mut check_samples = SampleSource::new();
+        let mut sample_idx = 0;
+        while sample_idx < 12000 {
+            let tx_avail = tx_transfer.available().unwrap();
+            if tx_avail > 0 {
+                for b in &mut filler[..tx_avail] {
+                    *b = samples.next().unwrap();
+                }
+                tx_transfer.push(&filler[..tx_avail]).unwrap();
+            }
+
+            if rx_transfer.available().unwrap() == 0 {
+                continue;
+            }
+            let len = rx_transfer.pop(&mut rcv).unwrap();
+            for &b in &rcv[..len] {
+                let expected = check_samples.next().unwrap();
+                assert_eq!(
+                    b, expected,
+                    "Sample #{} does not match ({} != {})",
+                    sample_idx, b, expected
+                );
+                sample_idx += 1;
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(esp32s2))]
+    fn test_i2s_tdm_config_is_validated(mut ctx: Context) {
+        let mut check = |config: TdmConfig| {
+            I2s::new_tdm(
+                &mut ctx.i2s,
+                config,
+                16000.Hz(),
+                &mut ctx.dma_channel,
+                &mut [],
+                &mut [],
+            )
+            .err()
+        };
+
+        assert_eq!(
+            check(TdmConfig::default().with_slots(17)),
+            Some(ConfigError::UnsupportedSlotCount)
+        );
+        assert_eq!(
+            check(TdmConfig::default().with_slot_bits(12)),
+            Some(ConfigError::UnsupportedSlotBits)
+        );
+        assert_eq!(
+            check(TdmConfig::default().with_sample_bits(32)),
+            Some(ConfigError::UnsupportedSampleBits)
+        );
+        #[cfg(not(esp32h2))]
+        assert_eq!(
+            check(
+                TdmConfig::default()
+                    .with_slots(8)
+                    .with_slot_bits(32)
+                    .with_active_slot_mask(0xFF)
+            ),
+            Some(ConfigError::FrameTooLong)
+        );
+        assert_eq!(
+            check(TdmConfig::default().with_active_slot_mask(0b100)),
+            Some(ConfigError::InvalidSlotMask)
+        );
+        assert_eq!(
+            check(TdmConfig::default().with_ws_width(33)),
+            Some(ConfigError::UnsupportedWsWidth)
+        );
+
+        // The longest frame of 8-bit slots fits everywhere.
+        assert_eq!(
+            check(
+                TdmConfig::default()
+                    .with_slots(16)
+                    .with_slot_bits(8)
+                    .with_sample_bits(8)
+                    .with_active_slot_mask(0xFFFF)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_i2s_mclk_follows_the_sample_rate(mut ctx: Context) {
+        fn assert_close(actual: u32, expected: u32) {
+            // The ESP32 and ESP32-S2 only have 6-bit fractional dividers.
+            assert!(
+                actual.abs_diff(expected) <= expected / 20_000,
+                "MCLK is {}, expected {}",
+                actual,
+                expected
+            );
+        }
+
+        let mut i2s = I2s::new(
+            &mut ctx.i2s,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            48000.Hz(),
+            &mut ctx.dma_channel,
+            &mut [],
+            &mut [],
+        );
+        assert_close(i2s.mclk_frequency().raw(), 48000 * 256);
+
+        i2s.set_mclk_multiple(MclkMultiple::_384).unwrap();
+        assert_close(i2s.mclk_frequency().raw(), 48000 * 384);
+
+        i2s.set_sample_rate(44100.Hz()).unwrap();
+        assert_close(i2s.mclk_frequency().raw(), 44100 * 384);
+
+        assert_eq!(
+            i2s.set_sample_rate(100.Hz()),
+            Err(ConfigError::UnsupportedSampleRate)
+        );
+        assert_close(i2s.mclk_frequency().raw(), 44100 * 384);
+
+        #[cfg(not(esp32s2))]
+        {
+            let mut i2s = I2s::new(
+                &mut ctx.i2s,
+                Standard::Philips,
+                DataFormat::Data32Channel24,
+                48000.Hz(),
+                &mut ctx.dma_channel,
+                &mut [],
+                &mut [],
+            );
+            assert_close(i2s.mclk_frequency().raw(), 48000 * 192);
+
+            // 2 * 24 bits don't divide 256.
+            assert_eq!(
+                i2s.set_mclk_multiple(MclkMultiple::_256),
+                Err(ConfigError::UnsupportedMclkMultiple)
+            );
+            i2s.set_mclk_multiple(MclkMultiple::_384).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_i2s_tx_sample_rate_changes_between_transfers(ctx: Context) {
+        // 1000 frames of 16-bit stereo.
+        const BYTES: usize = 4000;
+
+        fn write_duration_us(i2s_tx: &mut I2sTx<'_, Blocking>, data: &[u8]) -> u64 {
+            let start = esp_hal::time::now();
+            i2s_tx.write(data).unwrap();
+            (esp_hal::time::now() - start).to_micros()
+        }
+
+        fn assert_close(actual: u64, expected: u64) {
+            assert!(
+                actual.abs_diff(expected) <= expected / 20,
+                "The write took {} us, expected {} us",
+                actual,
+                expected
+            );
+        }
+
+        let (_, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(0, BYTES);
+
+        let i2s = I2s::new(
+            ctx.i2s,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            16000.Hz(),
+            ctx.dma_channel,
+            rx_descriptors,
+            tx_descriptors,
+        );
+
+        let mut i2s_tx = i2s
+            .i2s_tx
+            .with_bclk(NoPin)
+            .with_ws(NoPin)
+            .with_dout(ctx.dout)
+            .build();
+
+        assert_close(write_duration_us(&mut i2s_tx, tx_buffer), 62_500);
+
+        i2s_tx.set_sample_rate(32000.Hz()).unwrap();
+        assert_close(write_duration_us(&mut i2s_tx, tx_buffer), 31_250);
+
+        assert_eq!(
+            i2s_tx.set_sample_rate(100.Hz()),
+            Err(ConfigError::UnsupportedSampleRate)
+        );
+        assert_close(write_duration_us(&mut i2s_tx, tx_buffer), 31_250);
+
+        // A stopped circular transfer leaves nothing behind for the next one.
+        let transfer = i2s_tx.write_dma_circular(tx_buffer).unwrap();
+        Delay::new().delay_millis(10);
+        core::mem::drop(transfer);
+
+        i2s_tx.set_sample_rate(16000.Hz()).unwrap();
+        assert_close(write_duration_us(&mut i2s_tx, tx_buffer), 62_500);
+    }
+
     #[test]
     fn test_i2s_push_too_late(ctx: Context) {
         let (_, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(0, 16000);
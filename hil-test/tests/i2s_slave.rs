@@ -0,0 +1,214 @@
+//! I2S Slave Mode Test
+//!
+//! I2S1 is the master that clocks I2S0 in slave mode. Each signal stays on a
+//! single pin, which is the output of the master and the input of the slave.
+
+//% CHIPS: esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use embedded_hal_async::delay::DelayNs;
+use esp_hal::{
+    dma::{DmaChannel0, DmaChannel1},
+    dma_buffers,
+    gpio::{
+        interconnect::{InputSignal, OutputSignal},
+        AnyPin,
+        Pin,
+    },
+    i2s::master::{ConfigError, DataFormat, Error, I2s, Standard},
+    peripheral::Peripheral,
+    peripherals::{I2S0, I2S1, TIMG0},
+    time::{self, Duration, RateExtU32},
+    timer::{timg::TimerGroup, OneShotTimer},
+};
+use hil_test as _;
+
+struct Context {
+    master: I2S1,
+    master_dma: DmaChannel1,
+    slave: I2S0,
+    slave_dma: DmaChannel0,
+    bclk: AnyPin,
+    ws: AnyPin,
+    data: AnyPin,
+    timg0: TIMG0,
+}
+
+/// Splits the pins of the link into `(bclk, ws, data_in, data_out)`.
+///
+/// The slave takes BCLK and WS from the same pins the master drives.
+fn signals(ctx: &mut Context) -> (OutputSignal, OutputSignal, InputSignal, OutputSignal) {
+    let (_, bclk) = unsafe { ctx.bclk.clone_unchecked() }.split();
+    let (_, ws) = unsafe { ctx.ws.clone_unchecked() }.split();
+    let (data_in, data_out) = unsafe { ctx.data.clone_unchecked() }.split();
+
+    (bclk, ws, data_in, data_out)
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        Context {
+            master: peripherals.I2S1,
+            master_dma: peripherals.DMA_CH1,
+            slave: peripherals.I2S0,
+            slave_dma: peripherals.DMA_CH0,
+            bclk: peripherals.GPIO6.degrade(),
+            ws: peripherals.GPIO7.degrade(),
+            data: peripherals.GPIO14.degrade(),
+            timg0: peripherals.TIMG0,
+        }
+    }
+
+    #[test]
+    fn slave_receives_from_the_master(mut ctx: Context) {
+        let (bclk, ws, data_in, data_out) = signals(&mut ctx);
+        let (rx_buffer, rx_descriptors, _, _) = dma_buffers!(4000, 0);
+        let (_, _, tx_buffer, tx_descriptors) = dma_buffers!(0, 8000);
+
+        let master = I2s::new(
+            ctx.master,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            16000.Hz(),
+            ctx.master_dma,
+            &mut [],
+            tx_descriptors,
+        );
+        let mut slave = I2s::new_slave(
+            ctx.slave,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            ctx.slave_dma,
+            rx_descriptors,
+            &mut [],
+        );
+        assert_eq!(
+            slave.set_sample_rate(48000.Hz()),
+            Err(ConfigError::ExternalClock)
+        );
+
+        let mut slave_rx = slave
+            .i2s_rx
+            .with_bclk(unsafe { bclk.clone_unchecked() })
+            .with_ws(unsafe { ws.clone_unchecked() })
+            .with_din(data_in)
+            .build();
+        let mut master_tx = master
+            .i2s_tx
+            .with_bclk(bclk)
+            .with_ws(ws)
+            .with_dout(data_out)
+            .build();
+
+        for (i, b) in tx_buffer.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        // The slave only receives once the master starts clocking.
+        let rx_transfer = slave_rx.read_dma(rx_buffer).unwrap();
+        let tx_transfer = master_tx.write_dma(tx_buffer).unwrap();
+        rx_transfer.wait().unwrap();
+        tx_transfer.wait().unwrap();
+
+        // Skip the first frame, which may be cut short as the clocks start.
+        for pair in rx_buffer[4..].windows(2) {
+            assert_eq!(pair[1], pair[0].wrapping_add(1));
+        }
+    }
+
+    #[test]
+    fn slave_read_times_out_when_the_master_stops(mut ctx: Context) {
+        let (bclk, ws, data_in, data_out) = signals(&mut ctx);
+        let (_, rx_descriptors, _, _) = dma_buffers!(4000, 0);
+        let (_, _, tx_buffer, tx_descriptors) = dma_buffers!(0, 400);
+
+        let master = I2s::new(
+            ctx.master,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            16000.Hz(),
+            ctx.master_dma,
+            &mut [],
+            tx_descriptors,
+        );
+        let slave = I2s::new_slave(
+            ctx.slave,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            ctx.slave_dma,
+            rx_descriptors,
+            &mut [],
+        );
+
+        let mut slave_rx = slave
+            .i2s_rx
+            .with_bclk(unsafe { bclk.clone_unchecked() })
+            .with_ws(unsafe { ws.clone_unchecked() })
+            .with_din(data_in)
+            .build();
+        let mut master_tx = master
+            .i2s_tx
+            .with_bclk(bclk)
+            .with_ws(ws)
+            .with_dout(data_out)
+            .build();
+
+        slave_rx.set_timeout(Some(Duration::millis(50)));
+
+        // The master sends a tenth of what the slave waits for, then stops.
+        let tx_transfer = master_tx.write_dma(tx_buffer).unwrap();
+
+        let start = time::now();
+        let mut rx_buffer = [0u8; 4000];
+        assert_eq!(slave_rx.read(&mut rx_buffer), Err(Error::Timeout));
+        let elapsed = time::now() - start;
+        assert!(elapsed >= Duration::millis(50) && elapsed < Duration::millis(100));
+
+        tx_transfer.wait().unwrap();
+    }
+
+    #[test]
+    async fn slave_async_read_gives_up_at_the_deadline(mut ctx: Context) {
+        let (bclk, ws, data_in, _) = signals(&mut ctx);
+        let (_, rx_descriptors, _, _) = dma_buffers!(4000, 0);
+
+        let slave = I2s::new_slave(
+            ctx.slave,
+            Standard::Philips,
+            DataFormat::Data16Channel16,
+            ctx.slave_dma,
+            rx_descriptors,
+            &mut [],
+        )
+        .into_async();
+
+        let mut slave_rx = slave
+            .i2s_rx
+            .with_bclk(bclk)
+            .with_ws(ws)
+            .with_din(data_in)
+            .build();
+
+        let timg0 = TimerGroup::new(ctx.timg0);
+        let mut timer = OneShotTimer::new(timg0.timer0).into_async();
+
+        // Nothing clocks the slave.
+        let mut rx_buffer = [0u8; 4000];
+        assert_eq!(
+            slave_rx
+                .read_dma_async_until(&mut rx_buffer, timer.delay_ms(50))
+                .await,
+            Err(Error::Timeout)
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! LEDC tests
+//!
+//! Two channels share a timer. The first one drives the common test pins, the
+//! second one the unconnected pin. PCNT counts the rising edges of the second
+//! channel while the first one is high, which tells where their duty windows
+//! are relative to each other.
+
+//% CHIPS: esp32 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use embedded_hal::pwm::SetDutyCycle;
+use esp_hal::{
+    delay::Delay,
+    gpio::{interconnect::InputSignal, AnyPin, Input, InputConfig, Pin},
+    ledc::{
+        channel::{self, ChannelIFace},
+        timer::{self, Timer, TimerIFace},
+        LSGlobalClkSource,
+        Ledc,
+        LowSpeed,
+    },
+    pcnt::{
+        channel::{CtrlMode, EdgeMode},
+        unit::Unit,
+        Pcnt,
+    },
+    peripherals::LEDC,
+    time::{self, Duration, RateExtU32},
+};
+use hil_test as _;
+
+struct Context {
+    ledc: LEDC,
+    pcnt: Pcnt<'static>,
+    reference: (AnyPin, AnyPin),
+    shifted: AnyPin,
+}
+
+fn ledc(ledc: LEDC) -> Ledc<'static> {
+    let mut ledc = Ledc::new(ledc);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    ledc
+}
+
+/// A 1 kHz timer with a 10-bit duty resolution.
+fn timer0(ledc: &Ledc<'static>) -> Timer<'static, LowSpeed> {
+    let mut timer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    timer0
+        .configure(timer::config::Config {
+            duty: timer::config::Duty::Duty10Bit,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency: 1.kHz(),
+        })
+        .unwrap();
+    timer0
+}
+
+/// Counts the rising edges of `shifted` while `reference` is high, over 20
+/// periods.
+fn edge_counter<'a>(
+    unit: &'a Unit<'static, 0>,
+    reference: AnyPin,
+    shifted: InputSignal,
+) -> impl Fn() -> i16 + 'a {
+    unit.channel0.set_edge_signal(shifted);
+    unit.channel0.set_ctrl_signal(reference);
+    unit.channel0
+        .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+    unit.channel0
+        .set_ctrl_mode(CtrlMode::Disable, CtrlMode::Keep);
+
+    let delay = Delay::new();
+    move || {
+        unit.pause();
+        unit.clear();
+        unit.resume();
+        delay.delay_millis(20);
+        unit.value()
+    }
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (din, dout) = hil_test::common_test_pins!(peripherals);
+        let shifted = hil_test::unconnected_pin!(peripherals);
+
+        Context {
+            ledc: peripherals.LEDC,
+            pcnt: Pcnt::new(peripherals.PCNT),
+            reference: (din.degrade(), dout.degrade()),
+            shifted: shifted.degrade(),
+        }
+    }
+
+    #[test]
+    fn phase_shifts_the_duty_window(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+
+        let (din, dout) = ctx.reference;
+        let (shifted_in, shifted_out) = ctx.shifted.split();
+
+        // High for the first half of the period.
+        let mut reference = ledc.channel(channel::Number::Channel0, dout);
+        reference
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 50,
+                hpoint: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        // Rises a quarter into the period, while the reference is high.
+        let mut shifted = ledc.channel(channel::Number::Channel1, shifted_out);
+        shifted
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 20,
+                hpoint: 256,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        let unit = ctx.pcnt.unit0;
+        let rising_edges_in_reference_window = edge_counter(&unit, din, shifted_in);
+
+        assert!(rising_edges_in_reference_window() >= 19);
+
+        // Three quarters into the period, the reference is low.
+        shifted.set_phase(270).unwrap();
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        // Changing the duty keeps the phase.
+        shifted.set_duty(10).unwrap();
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        // The reference window now ends before the shifted one starts.
+        shifted.set_phase(90).unwrap();
+        reference.set_duty(10).unwrap();
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        // Back to a window that covers it.
+        reference.set_duty(50).unwrap();
+        assert!(rising_edges_in_reference_window() >= 19);
+
+        // Only the last of quick successive updates is latched.
+        reference.set_duty(10).unwrap();
+        reference.set_duty(90).unwrap();
+        reference.set_duty(10).unwrap();
+        while reference.update_pending() {}
+        assert_eq!(rising_edges_in_reference_window(), 0);
+    }
+
+    #[test]
+    async fn duty_update_completes_after_a_period(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+
+        let mut channel0 = ledc.channel(channel::Number::Channel0, ctx.shifted);
+        channel0
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 50,
+                hpoint: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+        channel0.wait_for_update().await;
+        assert!(!channel0.update_pending());
+
+        let start = time::now();
+        channel0.set_duty(20).unwrap();
+        assert!(channel0.update_pending());
+        channel0.wait_for_update().await;
+        assert!(!channel0.update_pending());
+
+        // The rest of the current period, then a full one with the new duty.
+        let elapsed = time::now() - start;
+        assert!(elapsed >= Duration::micros(1000) && elapsed <= Duration::micros(2100));
+    }
+
+    #[test]
+    fn set_duty_cycle_rounds_to_the_timer_resolution(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+
+        let (din, dout) = ctx.reference;
+        let (shifted_in, shifted_out) = ctx.shifted.split();
+
+        let mut reference = ledc.channel(channel::Number::Channel0, dout);
+        reference
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 0,
+                hpoint: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+        assert_eq!(reference.max_duty_cycle(), 1024);
+
+        // Rises at tick 257 of 1024.
+        let mut shifted = ledc.channel(channel::Number::Channel1, shifted_out);
+        shifted
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 10,
+                hpoint: 257,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        let unit = ctx.pcnt.unit0;
+        let rising_edges_in_reference_window = edge_counter(&unit, din, shifted_in);
+
+        // 256.4 ticks round down, the window closes a tick before the edge.
+        reference.set_duty_cycle_fraction(2564, 10240).unwrap();
+        while reference.update_pending() {}
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        // 257.6 ticks round up, the window closes a tick after the edge.
+        reference.set_duty_cycle_fraction(2576, 10240).unwrap();
+        while reference.update_pending() {}
+        assert!(rising_edges_in_reference_window() >= 19);
+
+        // Fully on covers an edge at the very end of the period too.
+        shifted.set_hpoint(1023).unwrap();
+        reference.set_duty_cycle_fully_on().unwrap();
+        while reference.update_pending() {}
+        assert!(rising_edges_in_reference_window() >= 19);
+
+        reference.set_duty_cycle_fully_off().unwrap();
+        while reference.update_pending() {}
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        assert_eq!(
+            reference.set_duty_cycle_fraction(2, 1),
+            Err(channel::Error::Duty)
+        );
+    }
+
+    #[test]
+    fn hpoint_is_validated_against_the_timer_resolution(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+
+        let mut channel0 = ledc.channel(channel::Number::Channel0, ctx.shifted);
+        let config = |hpoint| channel::config::Config {
+            timer: &timer0,
+            duty_pct: 50,
+            hpoint,
+            pin_config: channel::config::PinConfig::PushPull,
+        };
+        assert_eq!(
+            channel0.configure(config(1024)),
+            Err(channel::Error::Hpoint)
+        );
+
+        channel0.configure(config(1023)).unwrap();
+        assert_eq!(channel0.set_hpoint(1024), Err(channel::Error::Hpoint));
+        assert_eq!(channel0.set_phase(360), Err(channel::Error::Hpoint));
+        channel0.set_phase(359).unwrap();
+    }
+
+    #[test]
+    fn staged_duties_switch_together_on_commit(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+
+        let (din, dout) = ctx.reference;
+        let (shifted_in, shifted_out) = ctx.shifted.split();
+
+        let mut reference = ledc.channel(channel::Number::Channel0, dout);
+        reference
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 50,
+                hpoint: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        // Rises at 60% of the period, after the reference window closes.
+        let mut shifted = ledc.channel(channel::Number::Channel1, shifted_out);
+        shifted
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 10,
+                hpoint: 614,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        let unit = ctx.pcnt.unit0;
+        let rising_edges_in_reference_window = edge_counter(&unit, din, shifted_in);
+        while reference.update_pending() || shifted.update_pending() {}
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        // Staged duties don't change the output.
+        reference.stage_duty(70).unwrap();
+        shifted.stage_duty(20).unwrap();
+        assert_eq!(rising_edges_in_reference_window(), 0);
+
+        ledc.commit(&[&reference, &shifted]).unwrap();
+        while reference.update_pending() || shifted.update_pending() {}
+        assert!(rising_edges_in_reference_window() >= 19);
+    }
+
+    #[test]
+    fn commit_rejects_channels_on_different_timers(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+        let mut timer1 = ledc.timer::<LowSpeed>(timer::Number::Timer1);
+        timer1
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty10Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: 1.kHz(),
+            })
+            .unwrap();
+
+        let (_, dout) = ctx.reference;
+        let mut channel0 = ledc.channel(channel::Number::Channel0, dout);
+        let mut channel1 = ledc.channel(channel::Number::Channel1, ctx.shifted);
+        for (channel, timer) in [(&mut channel0, &timer0), (&mut channel1, &timer1)] {
+            channel
+                .configure(channel::config::Config {
+                    timer,
+                    duty_pct: 50,
+                    hpoint: 0,
+                    pin_config: channel::config::PinConfig::PushPull,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(
+            ledc.commit(&[&channel0, &channel1]),
+            Err(channel::Error::TimerMismatch)
+        );
+    }
+
+    #[test]
+    fn timer_reports_the_achieved_frequency(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let timer0 = timer0(&ledc);
+        assert_eq!(timer0.achieved_frequency(), Some(1.kHz()));
+
+        // The closest the fractional divisor gets, to well within 0.1%.
+        let mut timer1 = ledc.timer::<LowSpeed>(timer::Number::Timer1);
+        timer1
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty10Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: 3.kHz(),
+            })
+            .unwrap();
+        let achieved = timer1.achieved_frequency().unwrap();
+        assert!(achieved.raw().abs_diff(300_000) < 300);
+
+        // Half a hertz at the highest resolution of the chip.
+        timer1
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty14Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: timer::config::Frequency::from_raw(50),
+            })
+            .map(|_| assert!(timer1.achieved_frequency().unwrap().raw().abs_diff(50) <= 1))
+            .or_else(|error| match error {
+                // Chips without REF_TICK can't go below ~5 Hz at 14 bits.
+                timer::Error::Frequency { min, .. } if min.raw() > 50 => Ok(()),
+                error => Err(error),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn timer_rejects_frequencies_out_of_range(ctx: Context) {
+        let ledc = ledc(ctx.ledc);
+        let mut timer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+        let config = |frequency| timer::config::Config {
+            duty: timer::config::Duty::Duty14Bit,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency,
+        };
+
+        let Err(timer::Error::Frequency { min, max }) = timer0.configure(config(24.kHz())) else {
+            panic!("24 kHz at a 14-bit resolution is out of range");
+        };
+        assert!(max < timer::config::Frequency::kHz(24) && min < max);
+        assert!(!timer0.is_configured());
+        assert_eq!(timer0.achieved_frequency(), None);
+
+        // Both ends of the range can be configured.
+        timer0.configure(config(max)).unwrap();
+        timer0.configure(config(min)).unwrap();
+    }
+
+    #[test]
+    #[cfg(any(feature = "esp32c6", feature = "esp32h2"))]
+    fn timer_runs_at_20_bits_from_the_crystal(ctx: Context) {
+        let mut ledc = Ledc::new(ctx.ledc);
+        ledc.set_global_slow_clock(LSGlobalClkSource::XTALClk);
+
+        let mut timer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+        timer0
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty20Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: 10.Hz(),
+            })
+            .unwrap();
+
+        let (din, dout) = ctx.reference;
+        let mut channel0 = ledc.channel(channel::Number::Channel0, dout);
+        channel0
+            .configure(channel::config::Config {
+                timer: &timer0,
+                duty_pct: 50,
+                hpoint: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        let input = Input::new(din, InputConfig::default());
+        let mut level = input.level();
+        let mut edges = 0;
+        let deadline = time::now() + Duration::millis(500);
+        while time::now() < deadline {
+            if input.level() != level {
+                level = input.level();
+                edges += 1;
+            }
+        }
+
+        // Five periods.
+        assert!((9..=11).contains(&edges));
+    }
+}
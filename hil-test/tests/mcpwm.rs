@@ -0,0 +1,698 @@
+//! MCPWM tests
+//!
+//! Operator 0 drives a complementary pair through its dead time generator: A
+//! on the common test pins, B on the unconnected pin. Polling both outputs
+//! tells how long they are both off around each edge.
+//!
+//! The capture tests timestamp the edges of a PWM output on the common test
+//! pins with capture channel 0.
+//!
+//! The fault tests brake a PWM output that is always high, on the common test
+//! pins, with fault input 0, which reads back the unconnected pin the test
+//! drives.
+//!
+//! The sync tests capture the rising edges of two PWM outputs, on the common
+//! test pins and on the unconnected pin, to tell the phase of their timers.
+
+//% CHIPS: esp32 esp32c6 esp32h2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use embassy_futures::join::join;
+use esp_hal::{
+    delay::Delay,
+    gpio::{interconnect::InputSignal, AnyPin, Flex, Input, InputConfig, Level, Pin},
+    mcpwm::{
+        capture::{Capture, CaptureConfig, CaptureEdge, CapturePin, Edge},
+        fault::{FaultEvent, FaultPin},
+        operator::{
+            BrakeConfig,
+            BrakeStatus,
+            DeadTimeCfg,
+            DeadTimeError,
+            FaultAction,
+            LinkedPins,
+            PwmPin,
+            PwmPinConfig,
+        },
+        timer::{
+            CounterDirection,
+            PwmWorkingMode,
+            SyncConfig,
+            SyncOut,
+            SyncSource,
+            Timer,
+            TimerClockConfig,
+        },
+        McPwm,
+        PeripheralClockConfig,
+    },
+    peripherals::MCPWM0,
+    time::{self, Duration, Instant, RateExtU32},
+};
+use hil_test as _;
+
+struct Context {
+    mcpwm: MCPWM0,
+    a: (AnyPin, AnyPin),
+    b: AnyPin,
+}
+
+/// Polls the outputs for 10 periods, and returns the longest time from A
+/// falling to B rising, and from B falling to A rising.
+///
+/// Panics if both outputs are high at the same time.
+fn dead_times(a: &Input<'_>, b: &InputSignal) -> (Duration, Duration) {
+    let mut last = (a.is_high(), b.is_input_high());
+    // When both outputs went off, and whether A was the one that fell.
+    let mut off_since = None;
+    let (mut a_to_b, mut b_to_a) = (Duration::micros(0), Duration::micros(0));
+
+    let deadline = time::now() + Duration::millis(10);
+    while time::now() < deadline {
+        let now = time::now();
+        let levels = (a.is_high(), b.is_input_high());
+        assert!(!(levels.0 && levels.1), "both outputs are on");
+
+        match (last, levels) {
+            ((true, false), (false, false)) => off_since = Some((now, true)),
+            ((false, true), (false, false)) => off_since = Some((now, false)),
+            ((false, false), (false, true)) => {
+                if let Some((since, true)) = off_since.take() {
+                    a_to_b = a_to_b.max(now - since);
+                }
+            }
+            ((false, false), (true, false)) => {
+                if let Some((since, false)) = off_since.take() {
+                    b_to_a = b_to_a.max(now - since);
+                }
+            }
+            _ => {}
+        }
+        last = levels;
+    }
+
+    (a_to_b, b_to_a)
+}
+
+fn assert_close(measured: Duration, expected_micros: u64) {
+    let measured = measured.to_micros();
+    assert!(
+        measured.abs_diff(expected_micros) <= 5,
+        "measured {} µs, expected {} µs",
+        measured,
+        expected_micros
+    );
+}
+
+/// Starts a 1 kHz, 50% duty complementary pair with 1 µs dead time ticks.
+fn complementary_pair(
+    ctx: Context,
+    rising_edge_ns: u32,
+    falling_edge_ns: u32,
+) -> (LinkedPins<'static, MCPWM0, 0>, Input<'static>, InputSignal) {
+    let (a_in, a_out) = ctx.a;
+    let a = Input::new(a_in, InputConfig::default());
+
+    let mut b = Flex::new(ctx.b);
+    b.set_as_output();
+    b.enable_input(true);
+    let (b_in, b_out) = b.split();
+
+    let clock_cfg = PeripheralClockConfig::with_frequency(1.MHz()).unwrap();
+    let mut mcpwm = McPwm::new(ctx.mcpwm, clock_cfg);
+    mcpwm.operator0.set_timer(&mcpwm.timer0);
+    mcpwm
+        .operator0
+        .set_deadtime(rising_edge_ns, falling_edge_ns)
+        .unwrap();
+
+    let mut pins = mcpwm.operator0.with_linked_pins(
+        a_out,
+        PwmPinConfig::UP_ACTIVE_HIGH,
+        b_out,
+        PwmPinConfig::EMPTY,
+        DeadTimeCfg::new_ahc(),
+    );
+    pins.set_timestamp_a(500);
+
+    let timer_clock_cfg = clock_cfg
+        .timer_clock_with_frequency(999, PwmWorkingMode::Increase, 1.kHz())
+        .unwrap();
+    mcpwm.timer0.start(timer_clock_cfg);
+    Delay::new().delay_millis(2);
+
+    (pins, a, b_in)
+}
+
+/// Starts a 1 kHz PWM signal that is high for 250 µs, and captures the edges
+/// `config` selects.
+///
+/// Returns the timer and the frequency of the capture timer in Hz, too.
+fn captured_pwm(
+    ctx: Context,
+    config: CaptureConfig,
+) -> (
+    Timer<0, MCPWM0>,
+    PwmPin<'static, MCPWM0, 0, true>,
+    CapturePin<'static, MCPWM0, 0>,
+    u32,
+) {
+    let (pin_in, pin_out) = ctx.a;
+
+    let clock_cfg = PeripheralClockConfig::with_frequency(8.MHz()).unwrap();
+    let mut mcpwm = McPwm::new(ctx.mcpwm, clock_cfg);
+
+    let capture = mcpwm.capture0.with_pin(pin_in, config);
+    mcpwm.capture_timer.start();
+    let frequency = mcpwm.capture_timer.frequency().raw();
+
+    mcpwm.operator0.set_timer(&mcpwm.timer0);
+    let mut pwm = mcpwm
+        .operator0
+        .with_pin_a(pin_out, PwmPinConfig::UP_ACTIVE_HIGH);
+    pwm.set_timestamp(2000);
+
+    let timer_clock_cfg = clock_cfg
+        .timer_clock_with_frequency(7999, PwmWorkingMode::Increase, 1.kHz())
+        .unwrap();
+    mcpwm.timer0.start(timer_clock_cfg);
+
+    (mcpwm.timer0, pwm, capture, frequency)
+}
+
+/// The time between two captures, for a capture timer running at `frequency`
+/// Hz.
+fn between(earlier: &Capture, later: &Capture, frequency: u32) -> Duration {
+    let ticks = later.timestamp.wrapping_sub(earlier.timestamp);
+    Duration::micros(ticks as u64 * 1_000_000 / frequency as u64)
+}
+
+/// Starts a 100 Hz PWM signal that is always high, which operator 0 brakes as
+/// `config` selects.
+///
+/// Returns the PWM output, the pin that drives fault input 0 and the fault
+/// input, which is active high.
+fn braked_pwm(
+    ctx: Context,
+    config: BrakeConfig,
+) -> (
+    PwmPin<'static, MCPWM0, 0, true>,
+    Input<'static>,
+    Flex<'static>,
+    FaultPin<'static, MCPWM0, 0>,
+) {
+    let (a_in, a_out) = ctx.a;
+    let a = Input::new(a_in, InputConfig::default());
+
+    let mut driver = Flex::new(ctx.b);
+    driver.set_low();
+    driver.set_as_output();
+    driver.enable_input(true);
+
+    let clock_cfg = PeripheralClockConfig::with_frequency(1.MHz()).unwrap();
+    let mut mcpwm = McPwm::new(ctx.mcpwm, clock_cfg);
+    let fault = mcpwm
+        .fault0
+        .with_pin(driver.peripheral_input(), Level::High);
+
+    mcpwm.operator0.set_timer(&mcpwm.timer0);
+    mcpwm.operator0.set_brake(config);
+    let mut pwm = mcpwm
+        .operator0
+        .with_pin_a(a_out, PwmPinConfig::UP_ACTIVE_HIGH);
+    // Beyond the period, so the output never falls.
+    pwm.set_timestamp(10_000);
+
+    let timer_clock_cfg = clock_cfg
+        .timer_clock_with_frequency(9999, PwmWorkingMode::Increase, 100.Hz())
+        .unwrap();
+    mcpwm.timer0.start(timer_clock_cfg);
+    Delay::new().delay_millis(1);
+
+    (pwm, a, driver, fault)
+}
+
+/// Starts the fault, and returns how long the output took to go low.
+fn brake(a: &Input<'_>, driver: &mut Flex<'_>) -> Duration {
+    let start = time::now();
+    driver.set_high();
+    while a.is_high() {
+        assert!(time::now() - start < Duration::millis(1), "no brake");
+    }
+    time::now() - start
+}
+
+/// Ends the fault, and returns when the output went high again.
+fn release(a: &Input<'_>, driver: &mut Flex<'_>) -> Instant {
+    let start = time::now();
+    driver.set_low();
+    while a.is_low() {
+        assert!(time::now() - start < Duration::millis(20), "no release");
+    }
+    time::now()
+}
+
+/// Polls for the next capture.
+fn next<const CAP: u8>(capture: &mut CapturePin<'_, MCPWM0, CAP>) -> Capture {
+    loop {
+        if let Some(c) = capture.capture() {
+            break c;
+        }
+    }
+}
+
+/// Polls for the next capture that came in after `earlier`.
+fn next_after<const CAP: u8>(
+    capture: &mut CapturePin<'_, MCPWM0, CAP>,
+    earlier: &Capture,
+) -> Capture {
+    loop {
+        let c = next(capture);
+        if c.timestamp.wrapping_sub(earlier.timestamp) as i32 > 0 {
+            break c;
+        }
+    }
+}
+
+/// Two 1 kHz PWM signals that are high for 250 µs, whose rising edges are
+/// captured: timer 0 on the common test pins, timer 1 on the unconnected pin.
+struct SyncedPwms {
+    timer0: Timer<0, MCPWM0>,
+    timer1: Timer<1, MCPWM0>,
+    timer_clock_cfg: TimerClockConfig,
+    _pwm0: PwmPin<'static, MCPWM0, 0, true>,
+    _pwm1: PwmPin<'static, MCPWM0, 1, true>,
+    capture0: CapturePin<'static, MCPWM0, 0>,
+    capture1: CapturePin<'static, MCPWM0, 1>,
+    frequency: u32,
+}
+
+impl SyncedPwms {
+    /// Starts both timers, timer 1 a third of a period after timer 0.
+    fn new(ctx: Context) -> Self {
+        let (a_in, a_out) = ctx.a;
+        let mut b = Flex::new(ctx.b);
+        b.set_as_output();
+        b.enable_input(true);
+        let (b_in, b_out) = b.split();
+
+        let clock_cfg = PeripheralClockConfig::with_frequency(8.MHz()).unwrap();
+        let mut mcpwm = McPwm::new(ctx.mcpwm, clock_cfg);
+
+        let rising = CaptureConfig::new(CaptureEdge::Rising);
+        let capture0 = mcpwm.capture0.with_pin(a_in, rising);
+        let capture1 = mcpwm.capture1.with_pin(b_in, rising);
+        mcpwm.capture_timer.start();
+        let frequency = mcpwm.capture_timer.frequency().raw();
+
+        mcpwm.operator0.set_timer(&mcpwm.timer0);
+        mcpwm.operator1.set_timer(&mcpwm.timer1);
+        let mut pwm0 = mcpwm
+            .operator0
+            .with_pin_a(a_out, PwmPinConfig::UP_ACTIVE_HIGH);
+        let mut pwm1 = mcpwm
+            .operator1
+            .with_pin_a(b_out, PwmPinConfig::UP_ACTIVE_HIGH);
+        pwm0.set_timestamp(2000);
+        pwm1.set_timestamp(2000);
+
+        let timer_clock_cfg = clock_cfg
+            .timer_clock_with_frequency(7999, PwmWorkingMode::Increase, 1.kHz())
+            .unwrap();
+        mcpwm.timer0.start(timer_clock_cfg);
+        Delay::new().delay_micros(333);
+        mcpwm.timer1.start(timer_clock_cfg);
+
+        SyncedPwms {
+            timer0: mcpwm.timer0,
+            timer1: mcpwm.timer1,
+            timer_clock_cfg,
+            _pwm0: pwm0,
+            _pwm1: pwm1,
+            capture0,
+            capture1,
+            frequency,
+        }
+    }
+
+    /// The time from a rising edge of timer 0's output to the next rising
+    /// edge of timer 1's output, a few periods from now.
+    fn lag(&mut self) -> Duration {
+        Delay::new().delay_millis(3);
+        let rise0 = next(&mut self.capture0);
+        let rise1 = next_after(&mut self.capture1, &rise0);
+        between(&rise0, &rise1, self.frequency)
+    }
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (din, dout) = hil_test::common_test_pins!(peripherals);
+        let b = hil_test::unconnected_pin!(peripherals);
+
+        Context {
+            mcpwm: peripherals.MCPWM0,
+            a: (din.degrade(), dout.degrade()),
+            b: b.degrade(),
+        }
+    }
+
+    #[test]
+    fn dead_time_separates_the_complementary_outputs(ctx: Context) {
+        let (_pins, a, b) = complementary_pair(ctx, 30_000, 60_000);
+
+        // The falling edge delay holds B off after A falls, the rising edge
+        // delay holds A off after B falls.
+        let (a_to_b, b_to_a) = dead_times(&a, &b);
+        assert_close(a_to_b, 60);
+        assert_close(b_to_a, 30);
+    }
+
+    #[test]
+    fn dead_time_changes_while_running_without_overlap(ctx: Context) {
+        let (mut pins, a, b) = complementary_pair(ctx, 30_000, 60_000);
+
+        for (rising_edge_ns, falling_edge_ns) in [(0, 20_000), (45_000, 1_000), (30_000, 60_000)] {
+            pins.set_deadtime(rising_edge_ns, falling_edge_ns).unwrap();
+            // Each poll fails on overlap, including the first period, where
+            // the new delays take over.
+            dead_times(&a, &b);
+            let (a_to_b, b_to_a) = dead_times(&a, &b);
+            assert_close(a_to_b, falling_edge_ns as u64 / 1000);
+            assert_close(b_to_a, rising_edge_ns as u64 / 1000);
+        }
+    }
+
+    #[test]
+    fn dead_time_has_to_fit_the_counter(ctx: Context) {
+        let (mut pins, _, _) = complementary_pair(ctx, 0, 0);
+
+        assert_eq!(pins.set_deadtime(65_536_000, 0), Err(DeadTimeError));
+        assert_eq!(pins.set_deadtime(0, 65_536_000), Err(DeadTimeError));
+        // Rounded up to the next tick.
+        assert_eq!(pins.set_deadtime(65_534_001, 0), Ok(()));
+        assert_eq!(pins.set_deadtime(65_535_001, 0), Err(DeadTimeError));
+    }
+
+    #[test]
+    fn capture_measures_pulse_width_and_period(ctx: Context) {
+        let (_timer, _pwm, mut capture, frequency) =
+            captured_pwm(ctx, CaptureConfig::new(CaptureEdge::Both));
+
+        // Skip to the first rising edge, then take the next two edges.
+        let mut captures = [None; 3];
+        let mut taken = 0;
+        while taken < 3 {
+            if let Some(c) = capture.capture() {
+                if taken > 0 || c.edge == Edge::Rising {
+                    captures[taken] = Some(c);
+                    taken += 1;
+                }
+            }
+        }
+        let [rise, fall, next_rise] = captures.map(Option::unwrap);
+
+        assert_eq!(
+            (rise.edge, fall.edge, next_rise.edge),
+            (Edge::Rising, Edge::Falling, Edge::Rising)
+        );
+        assert!(!fall.missed && !next_rise.missed);
+        assert!(!fall.overflow && !next_rise.overflow);
+        assert_close(between(&rise, &fall, frequency), 250);
+        assert_close(between(&rise, &next_rise, frequency), 1000);
+    }
+
+    #[test]
+    fn capture_prescaler_skips_periods(ctx: Context) {
+        let config = CaptureConfig::new(CaptureEdge::Rising).with_prescaler(3);
+        let (_timer, _pwm, mut capture, frequency) = captured_pwm(ctx, config);
+
+        let mut next = || loop {
+            if let Some(c) = capture.capture() {
+                break c;
+            }
+        };
+        let first = next();
+        let second = next();
+
+        assert_close(between(&first, &second, frequency), 4000);
+    }
+
+    #[test]
+    async fn next_capture_waits_for_the_edge(ctx: Context) {
+        let (_timer, _pwm, mut capture, frequency) =
+            captured_pwm(ctx, CaptureConfig::new(CaptureEdge::Falling));
+
+        let first = capture.next_capture().await;
+        let second = capture.next_capture().await;
+
+        assert_eq!((first.edge, second.edge), (Edge::Falling, Edge::Falling));
+        assert!(!second.missed);
+        assert_close(between(&first, &second, frequency), 1000);
+    }
+
+    #[test]
+    async fn next_capture_reports_captures_that_were_not_taken(ctx: Context) {
+        let (_timer, _pwm, mut capture, frequency) =
+            captured_pwm(ctx, CaptureConfig::new(CaptureEdge::Rising));
+
+        capture.next_capture().await;
+        // The interrupt keeps capturing while nobody waits.
+        Delay::new().delay_millis(3);
+        let late = capture.next_capture().await;
+        assert!(late.missed);
+
+        let next = capture.next_capture().await;
+        assert!(!next.missed);
+        assert_close(between(&late, &next, frequency), 1000);
+    }
+
+    #[test]
+    fn one_shot_brake_holds_until_cleared(ctx: Context) {
+        let config = BrakeConfig::new().with_one_shot(FaultEvent::Fault0);
+        let (mut pwm, a, mut driver, fault) = braked_pwm(ctx, config);
+        assert!(a.is_high());
+        assert_eq!(pwm.brake_status(), BrakeStatus::default());
+
+        assert_close(brake(&a, &mut driver), 0);
+        assert!(fault.is_active());
+        assert!(pwm.brake_status().one_shot);
+
+        // Several periods after the fault ended.
+        driver.set_low();
+        Delay::new().delay_millis(25);
+        assert!(!fault.is_active());
+        assert!(a.is_low());
+        assert!(pwm.brake_status().one_shot);
+
+        pwm.clear_one_shot_brake();
+        Delay::new().delay_millis(11);
+        assert!(a.is_high());
+        assert_eq!(pwm.brake_status(), BrakeStatus::default());
+    }
+
+    #[test]
+    fn cycle_by_cycle_brake_releases_when_the_timer_equals_zero(ctx: Context) {
+        let config = BrakeConfig::new()
+            .with_cycle_by_cycle(FaultEvent::Fault0)
+            .with_cycle_by_cycle_actions(FaultAction::Low, FaultAction::Nothing);
+        let (pwm, a, mut driver, _fault) = braked_pwm(ctx, config);
+
+        assert_close(brake(&a, &mut driver), 0);
+        // Held through the start of a period while the fault is active.
+        Delay::new().delay_millis(15);
+        assert!(a.is_low());
+        assert!(pwm.brake_status().cycle_by_cycle);
+        let first = release(&a, &mut driver);
+
+        Delay::new().delay_millis(3);
+        brake(&a, &mut driver);
+        let second = release(&a, &mut driver);
+        assert!(!pwm.brake_status().cycle_by_cycle);
+
+        // Both releases happen at the start of a period.
+        let offset = (second - first).to_micros() % 10_000;
+        assert!(
+            offset <= 5 || offset >= 9_995,
+            "released {} µs into a period",
+            offset
+        );
+    }
+
+    #[test]
+    async fn wait_for_fault_wakes_on_both_transitions(ctx: Context) {
+        let config = BrakeConfig::new().with_one_shot(FaultEvent::Fault0);
+        let (mut pwm, a, mut driver, mut fault) = braked_pwm(ctx, config);
+
+        join(fault.wait_for_fault(), async {
+            Delay::new().delay_millis(1);
+            driver.set_high();
+        })
+        .await;
+        assert!(a.is_low());
+        assert!(pwm.brake_status().one_shot);
+
+        join(fault.wait_for_fault_end(), async {
+            Delay::new().delay_millis(1);
+            driver.set_low();
+        })
+        .await;
+        assert!(!fault.is_active());
+
+        pwm.clear_one_shot_brake();
+        Delay::new().delay_millis(11);
+        assert!(a.is_high());
+    }
+
+    #[test]
+    fn software_sync_aligns_timers_with_a_phase(ctx: Context) {
+        let mut pwms = SyncedPwms::new(ctx);
+
+        pwms.timer0.set_sync_out(SyncOut::Software);
+        pwms.timer1.set_sync(
+            SyncConfig::new()
+                .with_source(SyncSource::Timer0)
+                .with_phase(2000, CounterDirection::Increasing),
+        );
+        // Timer 0 reloads to zero, timer 1 a quarter period ahead of it.
+        pwms.timer0.sync_now();
+
+        assert_close(pwms.lag(), 750);
+    }
+
+    #[test]
+    fn sync_output_keeps_timers_aligned(ctx: Context) {
+        let mut pwms = SyncedPwms::new(ctx);
+
+        pwms.timer0.set_sync_out(SyncOut::TimerEqualsZero);
+        pwms.timer1.set_sync(
+            SyncConfig::new()
+                .with_source(SyncSource::Timer0)
+                .with_phase(4000, CounterDirection::Increasing),
+        );
+        assert_close(pwms.lag(), 500);
+
+        // Timer 1 falls behind, and catches up at the next period of timer 0.
+        pwms.timer1.stop();
+        Delay::new().delay_micros(300);
+        pwms.timer1.start(pwms.timer_clock_cfg);
+        assert_close(pwms.lag(), 500);
+    }
+
+    #[test]
+    fn sync_input_reloads_the_timer(ctx: Context) {
+        let (a_in, a_out) = ctx.a;
+        let mut driver = Flex::new(ctx.b);
+        driver.set_low();
+        driver.set_as_output();
+        driver.enable_input(true);
+
+        let clock_cfg = PeripheralClockConfig::with_frequency(8.MHz()).unwrap();
+        let mut mcpwm = McPwm::new(ctx.mcpwm, clock_cfg);
+
+        let rising = CaptureConfig::new(CaptureEdge::Rising);
+        let mut pwm_capture = mcpwm.capture0.with_pin(a_in, rising);
+        let mut sync_capture = mcpwm.capture1.with_pin(driver.peripheral_input(), rising);
+        mcpwm.capture_timer.start();
+        let frequency = mcpwm.capture_timer.frequency().raw();
+
+        let sync = mcpwm
+            .sync0
+            .with_pin(driver.peripheral_input(), Edge::Rising);
+        mcpwm.timer0.set_sync(
+            SyncConfig::new()
+                .with_source(sync.source())
+                .with_phase(6000, CounterDirection::Increasing),
+        );
+
+        mcpwm.operator0.set_timer(&mcpwm.timer0);
+        let mut pwm = mcpwm
+            .operator0
+            .with_pin_a(a_out, PwmPinConfig::UP_ACTIVE_HIGH);
+        pwm.set_timestamp(2000);
+        let timer_clock_cfg = clock_cfg
+            .timer_clock_with_frequency(7999, PwmWorkingMode::Increase, 1.kHz())
+            .unwrap();
+        mcpwm.timer0.start(timer_clock_cfg);
+        Delay::new().delay_millis(2);
+
+        driver.set_high();
+        let sync_edge = next(&mut sync_capture);
+        let rise = next_after(&mut pwm_capture, &sync_edge);
+
+        // The timer restarts a quarter period before its end.
+        assert_close(between(&sync_edge, &rise, frequency), 250);
+    }
+
+    #[test]
+    fn pending_timestamps_wait_for_their_update_method(ctx: Context) {
+        let (_, a_out) = ctx.a;
+        let clock_cfg = PeripheralClockConfig::with_frequency(1.MHz()).unwrap();
+        let mut mcpwm = McPwm::new(ctx.mcpwm, clock_cfg);
+        mcpwm.operator0.set_timer(&mcpwm.timer0);
+        let mut pwm = mcpwm
+            .operator0
+            .with_pin_a(a_out, PwmPinConfig::UP_ACTIVE_HIGH);
+
+        // The timer doesn't run, so it never equals zero.
+        pwm.set_timestamp(500);
+        assert!(pwm.is_update_pending());
+        Delay::new().delay_millis(1);
+        assert!(pwm.is_update_pending());
+
+        pwm.force_update();
+        assert!(!pwm.is_update_pending());
+        assert_eq!(pwm.timestamp(), 500);
+    }
+
+    #[test]
+    fn set_frequency_keeps_the_duty_cycle_without_glitches(ctx: Context) {
+        let (mut timer, _pwm, mut capture, frequency) =
+            captured_pwm(ctx, CaptureConfig::new(CaptureEdge::Both));
+
+        let mut rise = next(&mut capture);
+        while rise.edge != Edge::Rising {
+            rise = next(&mut capture);
+        }
+        // Every PWM period is either the old or the new signal. The period
+        // that runs when the frequency changes still is an old one.
+        for cycle in 0..10 {
+            if cycle == 4 {
+                timer.set_frequency(2.kHz()).unwrap();
+            }
+            let fall = next(&mut capture);
+            let next_rise = next(&mut capture);
+            assert_eq!((fall.edge, next_rise.edge), (Edge::Falling, Edge::Rising));
+            assert!(!fall.missed && !next_rise.missed);
+
+            let (high, period) = if cycle <= 4 { (250, 1000) } else { (125, 500) };
+            assert_close(between(&rise, &fall, frequency), high);
+            assert_close(between(&rise, &next_rise, frequency), period);
+            rise = next_rise;
+        }
+    }
+
+    #[test]
+    fn set_frequency_has_to_fit_the_timer(ctx: Context) {
+        let (mut timer, pwm, _capture, _) =
+            captured_pwm(ctx, CaptureConfig::new(CaptureEdge::Rising));
+
+        // A period of a single tick.
+        assert!(timer.set_frequency(5.MHz()).is_err());
+        // The prescaler grows for long periods.
+        assert!(timer.set_frequency(50.Hz()).is_ok());
+        Delay::new().delay_millis(25);
+        assert_eq!(pwm.period(), 53332);
+        assert_eq!(pwm.timestamp(), 13333);
+    }
+}
@@ -6,6 +6,8 @@
 #![no_std]
 #![no_main]
 
+#[cfg(esp32s3)]
+use esp_hal::gpio::interconnect::InputSignal;
 #[cfg(pcnt)]
 use esp_hal::pcnt::{channel::EdgeMode, unit::Unit, Pcnt};
 use esp_hal::{
@@ -15,6 +17,7 @@ use esp_hal::{
     spi::{
         master::{Address, Command, Config, Spi, SpiDma},
         DataMode,
+        Error,
         Mode,
     },
     time::RateExtU32,
@@ -46,6 +49,8 @@ struct Context {
     pcnt: esp_hal::peripherals::PCNT,
     dma_channel: DmaChannel0,
     gpios: [AnyPin; 3],
+    #[cfg(esp32s3)]
+    quad_pins: [AnyPin; 4],
 }
 
 fn transfer_read(
@@ -184,6 +189,13 @@ fn execute_write(
     }
 }
 
+#[cfg(esp32s3)]
+fn count_rising_edges<const NUM: usize>(unit: &Unit<'static, NUM>, signal: InputSignal) {
+    unit.channel0.set_edge_signal(signal);
+    unit.channel0
+        .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+}
+
 #[cfg(test)]
 #[embedded_test::tests(default_timeout = 3)]
 mod tests {
@@ -224,6 +236,13 @@ mod tests {
             pcnt: peripherals.PCNT,
             dma_channel,
             gpios: [pin.into(), pin_mirror.into(), unconnected_pin.into()],
+            #[cfg(esp32s3)]
+            quad_pins: [
+                peripherals.GPIO11.into(),
+                peripherals.GPIO12.into(),
+                peripherals.GPIO13.into(),
+                peripherals.GPIO14.into(),
+            ],
         }
     }
 
@@ -428,4 +447,114 @@ mod tests {
 
         super::execute_write(unit0, unit1, spi, 0b0000_1000, true);
     }
+
+    #[test]
+    fn test_spi_reads_quad_data_from_all_lines(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 4;
+
+        let (_, _, buffer, descriptors) = dma_buffers!(0, DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(descriptors, buffer).unwrap();
+
+        // Every line contributes its own bit to each nibble, so a swapped or
+        // missing line shows up in the result.
+        let spi = ctx
+            .spi
+            .with_sio0(Level::High)
+            .with_sio1(Level::High)
+            .with_sio2(Level::Low)
+            .with_sio3(Level::High)
+            .with_dma(ctx.dma_channel);
+
+        let (_, dma_rx_buf) = transfer_read(spi, dma_rx_buf, Command::None);
+        assert_eq!(dma_rx_buf.as_slice(), &[0b1011_1011; DMA_BUFFER_SIZE]);
+    }
+
+    #[test]
+    fn test_spi_rejects_unsupported_data_modes(ctx: Context) {
+        let mut spi = ctx.spi;
+
+        // A single-line command puts the peripheral into three-wire mode.
+        let result = spi.half_duplex_write(
+            DataMode::Quad,
+            Command::_8Bit(0x06, DataMode::Single),
+            Address::None,
+            0,
+            &[0; 4],
+        );
+        assert_eq!(result, Err(Error::Unsupported));
+
+        #[cfg(esp32)]
+        {
+            let result = spi.half_duplex_write(
+                DataMode::Quad,
+                Command::_8Bit(0x06, DataMode::Quad),
+                Address::None,
+                0,
+                &[0; 4],
+            );
+            assert_eq!(result, Err(Error::Unsupported));
+        }
+
+        // The peripheral is still usable after rejecting a transfer.
+        let result = spi.half_duplex_write(
+            DataMode::Quad,
+            Command::_8Bit(0x06, DataMode::SingleTwoDataLines),
+            Address::None,
+            0,
+            &[0; 4],
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    #[cfg(esp32s3)]
+    fn test_spi_writes_quad_data_on_all_lines(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 4;
+
+        let pcnt = Pcnt::new(ctx.pcnt);
+        let [sio0, sio1, sio2, sio3] = ctx.quad_pins;
+
+        let (sio0_loopback, sio0) = sio0.split();
+        let (sio1_loopback, sio1) = sio1.split();
+        let (sio2_loopback, sio2) = sio2.split();
+        let (sio3_loopback, sio3) = sio3.split();
+
+        super::count_rising_edges(&pcnt.unit0, sio0_loopback);
+        super::count_rising_edges(&pcnt.unit1, sio1_loopback);
+        super::count_rising_edges(&pcnt.unit2, sio2_loopback);
+        super::count_rising_edges(&pcnt.unit3, sio3_loopback);
+
+        let spi = ctx
+            .spi
+            .with_sio0(sio0)
+            .with_sio1(sio1)
+            .with_sio2(sio2)
+            .with_sio3(sio3)
+            .with_dma(ctx.dma_channel);
+
+        let (_, _, buffer, descriptors) = dma_buffers!(0, DMA_BUFFER_SIZE);
+        let mut dma_tx_buf = DmaTxBuf::new(descriptors, buffer).unwrap();
+
+        // SIOn goes high in every second nibble, for the first n + 1 of them,
+        // so it sees n + 1 rising edges.
+        dma_tx_buf.fill(&[0xF0, 0xE0, 0xC0, 0x80]);
+
+        let transfer = spi
+            .half_duplex_write(
+                DataMode::Quad,
+                Command::None,
+                Address::None,
+                0,
+                dma_tx_buf.len(),
+                dma_tx_buf,
+            )
+            .map_err(|e| e.0)
+            .unwrap();
+        _ = transfer.wait();
+
+        assert_eq!(pcnt.unit0.value(), 1);
+        assert_eq!(pcnt.unit1.value(), 2);
+        assert_eq!(pcnt.unit2.value(), 3);
+        assert_eq!(pcnt.unit3.value(), 4);
+    }
 }
@@ -6,15 +6,150 @@
 #![no_std]
 #![no_main]
 
+use core::fmt::Debug;
+
 use esp_hal::{
+    delay::Delay,
     gpio::Level,
-    rmt::{PulseCode, Rmt, RxChannel, RxChannelConfig, TxChannel, TxChannelConfig},
-    time::RateExtU32,
+    peripheral::Peripheral,
+    rmt::{
+        PulseCode,
+        Rmt,
+        RxChannel,
+        RxChannelAsync,
+        RxChannelConfig,
+        SyncGroup,
+        TxChannel,
+        TxChannelAsync,
+        TxChannelConfig,
+    },
+    time::{self, Duration, RateExtU32},
 };
 use hil_test as _;
 
+/// Configures channel 0 to transmit, and an RX channel that can take several
+/// RAM blocks to receive through the jumper.
+fn loopback_channels(
+    tx_config: TxChannelConfig,
+    rx_config: RxChannelConfig,
+) -> (impl TxChannel + Debug, impl RxChannel + Debug) {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "esp32h2")] {
+            let freq = 32.MHz();
+        } else {
+            let freq = 80.MHz();
+        }
+    };
+
+    let rmt = Rmt::new(peripherals.RMT, freq).unwrap();
+
+    let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+    let tx_channel = {
+        use esp_hal::rmt::TxChannelCreator;
+        rmt.channel0.configure(tx, tx_config).unwrap()
+    };
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(feature = "esp32", feature = "esp32s2"))] {
+            let rx_channel = {
+                use esp_hal::rmt::RxChannelCreator;
+                rmt.channel1.configure(rx, rx_config).unwrap()
+            };
+        } else if #[cfg(feature = "esp32s3")] {
+            let rx_channel = {
+                use esp_hal::rmt::RxChannelCreator;
+                rmt.channel4.configure(rx, rx_config).unwrap()
+            };
+        } else {
+            let rx_channel = {
+                use esp_hal::rmt::RxChannelCreator;
+                rmt.channel2.configure(rx, rx_config).unwrap()
+            };
+        }
+    }
+
+    (tx_channel, rx_channel)
+}
+
+/// Like [`loopback_channels`], in async mode, with a second TX channel that
+/// drives the same pin as the first one.
+fn async_loopback_channels(
+    tx_config: TxChannelConfig,
+    rx_config: RxChannelConfig,
+) -> (
+    impl TxChannelAsync + Debug,
+    impl TxChannelAsync + Debug,
+    impl RxChannelAsync + Debug,
+) {
+    use esp_hal::rmt::{RxChannelCreatorAsync, TxChannelCreatorAsync};
+
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "esp32h2")] {
+            let freq = 32.MHz();
+        } else {
+            let freq = 80.MHz();
+        }
+    };
+
+    let rmt = Rmt::new(peripherals.RMT, freq).unwrap().into_async();
+
+    let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+    let tx_channel = rmt
+        .channel0
+        .configure(unsafe { tx.clone_unchecked() }, tx_config)
+        .unwrap();
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(feature = "esp32", feature = "esp32s2"))] {
+            let other_tx_channel = rmt.channel2.configure(tx, tx_config).unwrap();
+            let rx_channel = rmt.channel1.configure(rx, rx_config).unwrap();
+        } else if #[cfg(feature = "esp32s3")] {
+            let other_tx_channel = rmt.channel1.configure(tx, tx_config).unwrap();
+            let rx_channel = rmt.channel4.configure(rx, rx_config).unwrap();
+        } else {
+            let other_tx_channel = rmt.channel1.configure(tx, tx_config).unwrap();
+            let rx_channel = rmt.channel2.configure(rx, rx_config).unwrap();
+        }
+    }
+
+    (tx_channel, other_tx_channel, rx_channel)
+}
+
+fn tx_config() -> TxChannelConfig {
+    TxChannelConfig::default().with_clk_divider(255)
+}
+
+/// A frame of `N` pulse codes that ends with a pulse longer than the idle
+/// threshold used by the tests, followed by the end marker.
+fn frame<const N: usize>() -> [u32; N] {
+    let mut data = [PulseCode::new(Level::High, 200, Level::Low, 50); N];
+    data[N - 2] = PulseCode::new(Level::High, 3000, Level::Low, 500);
+    data[N - 1] = PulseCode::empty();
+    data
+}
+
+/// Counts the high pulses of a received sequence.
+fn high_pulses(data: &[u32]) -> usize {
+    data.iter()
+        .flat_map(|code| {
+            [
+                (code.level1(), code.length1()),
+                (code.level2(), code.length2()),
+            ]
+        })
+        .take_while(|(_, length)| *length != 0)
+        .filter(|(level, _)| *level == Level::High)
+        .count()
+}
+
 #[cfg(test)]
-#[embedded_test::tests(default_timeout = 1)]
+#[embedded_test::tests(default_timeout = 1, executor = hil_test::Executor::new())]
 mod tests {
     use esp_hal::rmt::Error;
 
@@ -122,4 +257,449 @@ mod tests {
         assert!(tx_transaction.is_err());
         assert!(matches!(tx_transaction, Err(Error::EndMarkerMissing)));
     }
+
+    #[test]
+    fn rmt_receives_frames_longer_than_one_block() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000)
+            .with_memsize(2);
+        let (tx_channel, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        // More than a single block holds on any chip.
+        let tx_data = frame::<80>();
+        let mut rcv_data: [u32; 96] = [PulseCode::empty(); 96];
+
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        // The TX channel needs to be refilled while it transmits.
+        tx_channel.transmit(&tx_data).unwrap().wait().unwrap();
+        rx_transaction.wait().unwrap();
+
+        assert_eq!(&tx_data[..78], &rcv_data[..78]);
+    }
+
+    #[test]
+    fn rmt_late_refill_is_reported_as_underrun() {
+        // 1 µs ticks
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "esp32h2")] {
+                let divider = 32;
+            } else {
+                let divider = 80;
+            }
+        };
+        let tx_config = TxChannelConfig::default().with_clk_divider(divider);
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000);
+        let (tx_channel, rx_channel) = loopback_channels(tx_config, rx_config);
+
+        let mut tx_data = [PulseCode::new(Level::High, 100, Level::Low, 100); 200];
+        tx_data[199] = PulseCode::empty();
+
+        // Wait until the transmitter is back in the first half of the RAM,
+        // which hasn't been refilled.
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "esp32", feature = "esp32s2"))] {
+                let delay_ms = 16;
+            } else {
+                let delay_ms = 12;
+            }
+        };
+        let transaction = tx_channel.transmit(&tx_data).unwrap();
+        Delay::new().delay_millis(delay_ms);
+        let tx_channel = match transaction.wait() {
+            Err((Error::Underrun, channel)) => channel,
+            Err((error, _)) => panic!("unexpected error: {:?}", error),
+            Ok(_) => panic!("the transmission should have been stopped"),
+        };
+
+        // The channel can be used again after the underrun.
+        let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+        let tx_data = frame::<20>();
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        tx_channel.transmit(&tx_data).unwrap().wait().unwrap();
+        rx_transaction.wait().unwrap();
+
+        assert_eq!(&tx_data[..18], &rcv_data[..18]);
+    }
+
+    #[test]
+    fn rmt_receive_returns_the_frame_length() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_timeout(Duration::millis(5));
+        let (tx_channel, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        let tx_data = frame::<20>();
+        let mut rcv_data: [u32; 48] = [PulseCode::empty(); 48];
+
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        tx_channel.transmit(&tx_data).unwrap().wait().unwrap();
+        let (count, _) = rx_transaction.wait().unwrap();
+
+        // The receiver ends the frame during the long pulse.
+        assert_eq!(count, 19);
+        assert_eq!(&tx_data[..18], &rcv_data[..18]);
+    }
+
+    #[test]
+    fn rmt_silent_receive_ends_after_the_idle_timeout() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_timeout(Duration::millis(5));
+        let (_, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+
+        let start = time::now();
+        let (count, _) = rx_channel.receive(&mut rcv_data).unwrap().wait().unwrap();
+
+        assert_eq!(count, 0);
+        assert!(time::now() - start >= Duration::millis(5));
+    }
+
+    #[test]
+    fn rmt_sync_group_holds_transmissions_back_until_all_channels_have_one() {
+        use esp_hal::rmt::{RxChannelCreator, TxChannelCreator};
+
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "esp32h2")] {
+                let freq = 32.MHz();
+            } else {
+                let freq = 80.MHz();
+            }
+        };
+
+        let rmt = Rmt::new(peripherals.RMT, freq).unwrap();
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000);
+
+        // Both channels drive the same pin, and the one configured last is
+        // received.
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "esp32", feature = "esp32s2"))] {
+                let other_channel = rmt
+                    .channel2
+                    .configure(unsafe { tx.clone_unchecked() }, tx_config())
+                    .unwrap();
+                let rx_channel = rmt.channel1.configure(rx, rx_config).unwrap();
+            } else if #[cfg(feature = "esp32s3")] {
+                let other_channel = rmt
+                    .channel1
+                    .configure(unsafe { tx.clone_unchecked() }, tx_config())
+                    .unwrap();
+                let rx_channel = rmt.channel4.configure(rx, rx_config).unwrap();
+            } else {
+                let other_channel = rmt
+                    .channel1
+                    .configure(unsafe { tx.clone_unchecked() }, tx_config())
+                    .unwrap();
+                let rx_channel = rmt.channel2.configure(rx, rx_config).unwrap();
+            }
+        }
+        let tx_channel = rmt.channel0.configure(tx, tx_config()).unwrap();
+
+        let mut group = SyncGroup::new()
+            .with_channel(&tx_channel)
+            .with_channel(&other_channel);
+        group.enable().unwrap();
+        assert!(matches!(
+            SyncGroup::new().with_channel(&tx_channel).enable(),
+            Err(Error::InvalidArgument)
+        ));
+
+        let tx_data = frame::<20>();
+        let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+
+        // Had the transmission started right away, the start of the frame
+        // would be missing.
+        let tx_transaction = tx_channel.transmit(&tx_data).unwrap();
+        Delay::new().delay_millis(10);
+
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        let other_transaction = other_channel.transmit(&tx_data).unwrap();
+        tx_transaction.wait().unwrap();
+        other_transaction.wait().unwrap();
+        rx_transaction.wait().unwrap();
+
+        assert_eq!(&tx_data[..18], &rcv_data[..18]);
+    }
+
+    #[test]
+    fn rmt_receive_overflow_is_reported() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000);
+        let (tx_channel, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        let tx_channel = tx_channel.transmit(&frame::<80>()).unwrap().wait().unwrap();
+        let rx_channel = match rx_transaction.wait() {
+            Err((Error::Overflow, channel)) => channel,
+            Err((error, _)) => panic!("unexpected error: {:?}", error),
+            Ok(_) => panic!("the frame doesn't fit the channel RAM"),
+        };
+
+        // The channel can be used again after the overflow.
+        let tx_data = frame::<20>();
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        tx_channel.transmit(&tx_data).unwrap().wait().unwrap();
+        rx_transaction.wait().unwrap();
+
+        assert_eq!(&tx_data[..18], &rcv_data[..18]);
+    }
+
+    #[test]
+    fn rmt_rx_buffer_must_fit_the_blocks() {
+        let rx_config = RxChannelConfig::default().with_idle_threshold(1000);
+        let (_, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        let mut rcv_data: [u32; 96] = [PulseCode::empty(); 96];
+        assert!(matches!(
+            rx_channel.receive(&mut rcv_data),
+            Err(Error::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn rmt_carrier_modulates_the_marks() {
+        // A mark lasts 320 us, a bit more than three periods of the carrier.
+        let tx_config = tx_config()
+            .with_carrier_modulation(true)
+            .with_carrier_frequency(10.kHz())
+            .with_carrier_duty_cycle(50)
+            .with_carrier_level(Level::High);
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(500);
+        let (mut tx_channel, mut rx_channel) = loopback_channels(tx_config, rx_config);
+
+        let tx_data = [
+            PulseCode::new(Level::High, 100, Level::Low, 600),
+            PulseCode::empty(),
+        ];
+
+        for carrier in [true, false, true] {
+            tx_channel.set_carrier_modulation(carrier);
+
+            let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+            let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+            tx_channel = tx_channel.transmit(&tx_data).unwrap().wait().unwrap();
+            (_, rx_channel) = rx_transaction.wait().unwrap();
+
+            let received = high_pulses(&rcv_data);
+
+            // Depending on the phase of the carrier, the first or last
+            // period may be cut short.
+            if carrier {
+                assert!(received >= 3, "{} marks", received);
+            } else {
+                assert_eq!(received, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn rmt_unreachable_carriers_are_rejected() {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "esp32h2")] {
+                let freq = 32.MHz();
+            } else {
+                let freq = 80.MHz();
+            }
+        };
+
+        let rmt = Rmt::new(peripherals.RMT, freq).unwrap();
+        let (_, tx) = hil_test::common_test_pins!(peripherals);
+
+        use esp_hal::rmt::TxChannelCreator;
+
+        // A single clock cycle can't be split into a high and a low phase.
+        let config = tx_config().with_carrier_frequency(freq);
+        assert!(matches!(
+            rmt.channel0
+                .configure(unsafe { tx.clone_unchecked() }, config),
+            Err(Error::UnreachableTargetFrequency)
+        ));
+
+        // A period of millions of cycles doesn't fit the registers.
+        let config = tx_config().with_carrier_frequency(10.Hz());
+        assert!(matches!(
+            rmt.channel1.configure(tx, config),
+            Err(Error::UnreachableTargetFrequency)
+        ));
+    }
+
+    #[test]
+    fn rmt_looped_transmission_stops_after_the_count() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000);
+        let (tx_channel, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        let tx_data = [
+            PulseCode::new(Level::High, 50, Level::Low, 50),
+            PulseCode::empty(),
+        ];
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "esp32", feature = "esp32s2", feature = "esp32c3"))] {
+                assert!(matches!(
+                    tx_channel.transmit_looped(&tx_data, Some(5)),
+                    Err(Error::Unsupported)
+                ));
+                _ = rx_channel;
+            } else {
+                let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+
+                let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+                let transaction = tx_channel.transmit_looped(&tx_data, Some(5)).unwrap();
+                let tx_channel = transaction.wait().unwrap();
+                rx_transaction.wait().unwrap();
+
+                assert_eq!(high_pulses(&rcv_data), 5);
+
+                assert!(matches!(
+                    tx_channel.transmit_looped(&tx_data, Some(1024)),
+                    Err(Error::InvalidArgument)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn rmt_infinite_loop_stops_at_the_end_of_an_iteration() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000);
+        let (tx_channel, rx_channel) = loopback_channels(tx_config(), rx_config);
+
+        let tx_data = [
+            PulseCode::new(Level::High, 50, Level::Low, 50),
+            PulseCode::new(Level::High, 100, Level::Low, 100),
+            PulseCode::empty(),
+        ];
+
+        let mut rcv_data: [u32; 48] = [PulseCode::empty(); 48];
+
+        let rx_transaction = rx_channel.receive(&mut rcv_data).unwrap();
+        let transaction = tx_channel.transmit_looped(&tx_data, None).unwrap();
+        Delay::new().delay_millis(5);
+        transaction.stop_next().unwrap();
+        rx_transaction.wait().unwrap();
+
+        // Each iteration is two pulses, and the last one is complete.
+        let pulses = high_pulses(&rcv_data);
+        assert!(pulses >= 4 && pulses % 2 == 0, "{} pulses", pulses);
+    }
+
+    #[test]
+    async fn rmt_async_transmits_sequences_longer_than_the_ram() {
+        // 125 ns ticks
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "esp32h2")] {
+                let divider = 4;
+            } else {
+                let divider = 10;
+            }
+        };
+        let tx_config = TxChannelConfig::default().with_clk_divider(divider);
+        let (mut tx_channel, _, _) = async_loopback_channels(tx_config, RxChannelConfig::default());
+
+        // A strip of 300 WS2812 LEDs, all turned off.
+        let mut tx_data = [PulseCode::new(Level::High, 3, Level::Low, 7); 300 * 24 + 1];
+        tx_data[300 * 24] = PulseCode::empty();
+
+        let start = time::now();
+        tx_channel.transmit(&tx_data).await.unwrap();
+
+        // Each bit takes 1.25 µs.
+        assert!(time::now() - start >= Duration::micros(300 * 24 * 5 / 4));
+    }
+
+    #[test]
+    async fn rmt_dropped_async_transmit_releases_the_channel() {
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_threshold(1000);
+        let (mut tx_channel, _, mut rx_channel) = async_loopback_channels(tx_config(), rx_config);
+
+        // Takes far longer than the test timeout, unless it is stopped.
+        let mut long_data = [PulseCode::new(Level::High, 30000, Level::Low, 30000); 100];
+        long_data[99] = PulseCode::empty();
+        assert!(embassy_futures::poll_once(tx_channel.transmit(&long_data)).is_pending());
+
+        let tx_data = frame::<20>();
+        let mut rcv_data: [u32; 20] = [PulseCode::empty(); 20];
+
+        let (received, transmitted) = embassy_futures::join::join(
+            rx_channel.receive(&mut rcv_data),
+            tx_channel.transmit(&tx_data),
+        )
+        .await;
+        transmitted.unwrap();
+        received.unwrap();
+
+        assert_eq!(&tx_data[..18], &rcv_data[..18]);
+    }
+
+    #[test]
+    async fn rmt_async_transmits_complete_on_several_channels() {
+        let (mut tx_channel, mut other_tx_channel, _) =
+            async_loopback_channels(tx_config(), RxChannelConfig::default());
+
+        // Both channels finish at about the same time, and the interrupt
+        // handler needs to wake both of them.
+        let tx_data = frame::<20>();
+        for _ in 0..3 {
+            let (first, second) = embassy_futures::join::join(
+                tx_channel.transmit(&tx_data),
+                other_tx_channel.transmit(&tx_data),
+            )
+            .await;
+            first.unwrap();
+            second.unwrap();
+        }
+    }
+
+    #[test]
+    async fn rmt_continuous_receive_keeps_back_to_back_frames() {
+        static RING: static_cell::StaticCell<[u32; 36]> = static_cell::StaticCell::new();
+
+        let rx_config = RxChannelConfig::default()
+            .with_clk_divider(255)
+            .with_idle_timeout(Duration::millis(5));
+        let (mut tx_channel, _, mut rx_channel) = async_loopback_channels(tx_config(), rx_config);
+
+        let mut tx_data = [PulseCode::new(Level::High, 200, Level::Low, 50); 11];
+        tx_data[10] = PulseCode::empty();
+
+        let mut transaction = rx_channel
+            .receive_continuously(RING.init([PulseCode::empty(); 36]), 12)
+            .unwrap();
+
+        // Nothing takes the frames out of the ring while they are sent.
+        for _ in 0..3 {
+            tx_channel.transmit(&tx_data).await.unwrap();
+            Delay::new().delay_millis(10);
+        }
+
+        for _ in 0..3 {
+            let mut rcv_data: [u32; 12] = [PulseCode::empty(); 12];
+            assert_eq!(transaction.next(&mut rcv_data).await, Ok(10));
+            assert_eq!(&tx_data[..9], &rcv_data[..9]);
+        }
+
+        transaction.stop();
+    }
 }
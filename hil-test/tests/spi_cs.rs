@@ -0,0 +1,168 @@
+//! SPI hardware CS Test
+
+//% CHIPS: esp32 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    dma::{DmaRxBuf, DmaTxBuf},
+    dma_buffers,
+    gpio::interconnect::InputSignal,
+    pcnt::{channel::EdgeMode, unit::Unit, Pcnt},
+    spi::master::{Config, Cs0, Cs1, Spi},
+    time::RateExtU32,
+    Blocking,
+};
+use hil_test as _;
+
+cfg_if::cfg_if! {
+    if #[cfg(pdma)] {
+        use esp_hal::dma::Spi2DmaChannel as DmaChannel0;
+    } else {
+        use esp_hal::dma::DmaChannel0;
+    }
+}
+
+struct Context {
+    spi: Spi<'static, Blocking>,
+    dma_channel: DmaChannel0,
+    // Count the deassertions of CS0 and CS1.
+    cs0_unit: Unit<'static, 0>,
+    cs1_unit: Unit<'static, 1>,
+}
+
+fn count_rising_edges<const NUM: usize>(unit: &Unit<'static, NUM>, signal: InputSignal) {
+    unit.channel0.set_edge_signal(signal);
+    unit.channel0
+        .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (cs1, _) = hil_test::common_test_pins!(peripherals);
+        let cs0 = hil_test::unconnected_pin!(peripherals);
+
+        let pcnt = Pcnt::new(peripherals.PCNT);
+
+        cfg_if::cfg_if! {
+            if #[cfg(pdma)] {
+                let dma_channel = peripherals.DMA_SPI2;
+            } else {
+                let dma_channel = peripherals.DMA_CH0;
+            }
+        }
+
+        let (cs0_loopback, cs0) = cs0.split();
+        let (cs1_loopback, cs1) = cs1.split();
+
+        count_rising_edges(&pcnt.unit0, cs0_loopback);
+        count_rising_edges(&pcnt.unit1, cs1_loopback);
+
+        let spi = Spi::new(
+            peripherals.SPI2,
+            Config::default().with_frequency(100.kHz()),
+        )
+        .unwrap()
+        .with_cs(cs0)
+        .with_cs1(cs1);
+
+        Context {
+            spi,
+            dma_channel,
+            cs0_unit: pcnt.unit0,
+            cs1_unit: pcnt.unit1,
+        }
+    }
+
+    #[test]
+    fn test_cs0_is_selected_by_default(ctx: Context) {
+        let mut spi = ctx.spi;
+
+        spi.write_bytes(&[0xA5; 4]).unwrap();
+
+        assert_eq!(ctx.cs0_unit.value(), 1);
+        assert_eq!(ctx.cs1_unit.value(), 0);
+    }
+
+    #[test]
+    fn test_select_cs_switches_lines(ctx: Context) {
+        let mut spi = ctx.spi;
+
+        spi.select_cs(Cs1);
+        spi.write_bytes(&[0xA5; 4]).unwrap();
+        spi.write_bytes(&[0xA5; 4]).unwrap();
+
+        assert_eq!(ctx.cs0_unit.value(), 0);
+        assert_eq!(ctx.cs1_unit.value(), 2);
+
+        spi.select_cs(Cs0);
+        spi.write_bytes(&[0xA5; 4]).unwrap();
+
+        assert_eq!(ctx.cs0_unit.value(), 1);
+        assert_eq!(ctx.cs1_unit.value(), 2);
+    }
+
+    #[test]
+    fn test_spidmabus_select_cs(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 4;
+
+        let (rx, rxd, tx, txd) = dma_buffers!(DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rxd, rx).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(txd, tx).unwrap();
+
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf);
+
+        spi.select_cs(Cs1);
+        spi.write(&[0xA5; DMA_BUFFER_SIZE]).unwrap();
+
+        assert_eq!(ctx.cs0_unit.value(), 0);
+        assert_eq!(ctx.cs1_unit.value(), 1);
+    }
+
+    #[test]
+    fn test_spidmabus_keeps_cs_asserted_across_chunks(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 4;
+
+        let (rx, rxd, tx, txd) = dma_buffers!(DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rxd, rx).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(txd, tx).unwrap();
+
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf);
+
+        // Fits the buffers exactly.
+        spi.write(&[0xA5; DMA_BUFFER_SIZE]).unwrap();
+        assert_eq!(ctx.cs0_unit.value(), 1);
+
+        // Each of these takes several chunks, but is a single transaction on
+        // the bus.
+        spi.write(&[0xA5; 2 * DMA_BUFFER_SIZE + 1]).unwrap();
+        assert_eq!(ctx.cs0_unit.value(), 2);
+
+        let mut read = [0; 3 * DMA_BUFFER_SIZE];
+        spi.read(&mut read).unwrap();
+        assert_eq!(ctx.cs0_unit.value(), 3);
+
+        spi.transfer(&mut read[..DMA_BUFFER_SIZE], &[0xA5; DMA_BUFFER_SIZE + 2])
+            .unwrap();
+        assert_eq!(ctx.cs0_unit.value(), 4);
+
+        spi.transfer_in_place(&mut read).unwrap();
+        assert_eq!(ctx.cs0_unit.value(), 5);
+        assert_eq!(ctx.cs1_unit.value(), 0);
+    }
+}
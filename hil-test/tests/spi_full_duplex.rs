@@ -12,7 +12,7 @@
 use embedded_hal::spi::SpiBus;
 use embedded_hal_async::spi::SpiBus as SpiBusAsync;
 use esp_hal::{
-    spi::master::{Config, Spi},
+    spi::master::{Config, ConfigError, Spi},
     Blocking,
 };
 use fugit::RateExtU32;
@@ -25,6 +25,8 @@ cfg_if::cfg_if! {
             dma_buffers,
             gpio::{Level, NoPin},
         };
+        #[cfg(pdma)]
+        use esp_hal::{dma::DmaError, dma_circular_buffers};
         #[cfg(pcnt)]
         use esp_hal::{
             gpio::interconnect::InputSignal,
@@ -281,6 +283,101 @@ mod tests {
         assert_eq!(unit.value(), 9);
     }
 
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn test_cs_timing_is_validated(mut ctx: Context) {
+        let config = Config::default().with_frequency(10.MHz());
+
+        assert_eq!(
+            ctx.spi.apply_config(&config.with_cs_setup_time(8193)),
+            Err(ConfigError::UnsupportedCsTiming)
+        );
+        assert_eq!(
+            ctx.spi.apply_config(&config.with_cs_hold_time(8193)),
+            Err(ConfigError::UnsupportedCsTiming)
+        );
+        #[cfg(not(pdma))]
+        assert_eq!(
+            ctx.spi.apply_config(&config.with_cs_idle_time(1)),
+            Err(ConfigError::UnsupportedCsTiming)
+        );
+
+        // The longest delays all chips support.
+        ctx.spi
+            .apply_config(&config.with_cs_setup_time(16).with_cs_hold_time(16))
+            .unwrap();
+
+        let write = [0xde, 0xad, 0xbe, 0xef];
+        let mut read = [0; 4];
+        SpiBus::transfer(&mut ctx.spi, &mut read, &write).unwrap();
+        assert_eq!(write, read);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn test_cs_hold_time_extends_the_transfer(mut ctx: Context) {
+        // Each SCK cycle takes 10 us, which is long enough to be measured.
+        let config = Config::default().with_frequency(100.kHz());
+
+        let mut transfer_time = |hold| {
+            ctx.spi
+                .apply_config(&config.with_cs_hold_time(hold))
+                .unwrap();
+
+            let write = [0xde, 0xad, 0xbe, 0xef];
+            let mut read = [0; 4];
+            let start = esp_hal::time::now();
+            SpiBus::transfer(&mut ctx.spi, &mut read, &write).unwrap();
+            let elapsed = (esp_hal::time::now() - start).to_micros();
+
+            assert_eq!(write, read);
+            elapsed
+        };
+
+        let without_hold = transfer_time(0);
+        let one_cycle = transfer_time(1);
+        let sixteen_cycles = transfer_time(16);
+
+        // A hold time of 0 disables the hold phase, and every cycle of it adds
+        // 10 us. The transfers themselves take 320 us.
+        assert!(
+            (5..=15).contains(&one_cycle.saturating_sub(without_hold)),
+            "{} us, {} us",
+            without_hold,
+            one_cycle
+        );
+        assert!(
+            (150..=170).contains(&sixteen_cycles.saturating_sub(without_hold)),
+            "{} us, {} us",
+            without_hold,
+            sixteen_cycles
+        );
+    }
+
+    #[test]
+    fn test_frequency_can_be_changed_at_runtime(mut ctx: Context) {
+        let write = [0xde, 0xad, 0xbe, 0xef];
+
+        for frequency in [400.kHz(), 8.MHz(), 1.MHz()] {
+            ctx.spi
+                .apply_config(&Config::default().with_frequency(frequency))
+                .unwrap();
+
+            let mut read = [0; 4];
+            SpiBus::transfer(&mut ctx.spi, &mut read, &write).unwrap();
+            assert_eq!(write, read);
+        }
+
+        // No divider setting gets close to these.
+        for frequency in [0.Hz(), 100.Hz()] {
+            assert_eq!(
+                ctx.spi
+                    .apply_config(&Config::default().with_frequency(frequency)),
+                Err(ConfigError::UnsupportedFrequency)
+            );
+        }
+    }
+
     #[test]
     fn test_symmetric_transfer_huge_buffer(mut ctx: Context) {
         let write = &mut ctx.tx_buffer[0..4096];
@@ -584,6 +681,223 @@ mod tests {
         assert_eq!(tx_buf, rx_buf);
     }
 
+    #[test]
+    #[cfg(feature = "unstable")]
+    fn test_dma_bus_transfer_at_chunk_boundaries(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 4;
+
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf);
+
+        let tx_buf: [u8; 3 * DMA_BUFFER_SIZE] = core::array::from_fn(|i| i as u8 + 1);
+
+        // Exactly one and two buffers, and one byte over each.
+        for len in [
+            DMA_BUFFER_SIZE,
+            DMA_BUFFER_SIZE + 1,
+            2 * DMA_BUFFER_SIZE,
+            2 * DMA_BUFFER_SIZE + 1,
+        ] {
+            let mut rx_buf = [0; 3 * DMA_BUFFER_SIZE];
+            spi.transfer(&mut rx_buf[..len], &tx_buf[..len]).unwrap();
+            assert_eq!(rx_buf[..len], tx_buf[..len]);
+
+            let mut in_place = tx_buf;
+            spi.transfer_in_place(&mut in_place[..len]).unwrap();
+            assert_eq!(in_place, tx_buf);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    async fn test_async_dma_bus_zero_length_transfers(ctx: Context) {
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(4);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf)
+            .into_async();
+
+        SpiBusAsync::read(&mut spi, &mut []).await.unwrap();
+        SpiBusAsync::write(&mut spi, &[]).await.unwrap();
+        SpiBusAsync::transfer(&mut spi, &mut [], &[]).await.unwrap();
+        SpiBusAsync::transfer_in_place(&mut spi, &mut [])
+            .await
+            .unwrap();
+
+        let mut rx_buf = [0; 4];
+        SpiBusAsync::transfer(&mut spi, &mut rx_buf, &[1, 2, 3, 4])
+            .await
+            .unwrap();
+        assert_eq!(rx_buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    async fn test_async_dma_bus_fifo_and_dma_transfers_agree(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 128;
+
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf)
+            .into_async();
+
+        let tx_buf: [u8; 65] = core::array::from_fn(|i| i as u8 + 1);
+
+        // The default threshold moves all but the longest transfer through the
+        // FIFO, 0 moves all of them through the DMA. Alternating between the
+        // two also checks that each path leaves the peripheral usable for the
+        // other.
+        for len in [0, 1, 63, 64, 65] {
+            for fifo_threshold in [64, 0] {
+                let config = Config::default()
+                    .with_frequency(10.MHz())
+                    .with_fifo_threshold(fifo_threshold);
+                spi.apply_config(&config).unwrap();
+
+                let mut rx_buf = [0; 65];
+                SpiBusAsync::transfer(&mut spi, &mut rx_buf[..len], &tx_buf[..len])
+                    .await
+                    .unwrap();
+                assert_eq!(rx_buf[..len], tx_buf[..len]);
+
+                let mut in_place = tx_buf;
+                SpiBusAsync::transfer_in_place(&mut spi, &mut in_place[..len])
+                    .await
+                    .unwrap();
+                assert_eq!(in_place, tx_buf);
+
+                // A shorter read buffer only keeps the first bytes.
+                if len > 0 {
+                    let mut rx_buf = [0; 1];
+                    SpiBusAsync::transfer(&mut spi, &mut rx_buf, &tx_buf[..len])
+                        .await
+                        .unwrap();
+                    assert_eq!(rx_buf[0], tx_buf[0]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    async fn test_async_dma_bus_cancelled_transfer_leaves_bus_usable(ctx: Context) {
+        const TRANSFER_SIZE: usize = 4096;
+
+        // The transfer is split into many chunks, so it can't complete in the
+        // time it takes to poll it twice.
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(40);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf)
+            .into_async();
+
+        let tx_buf = [0xA5; TRANSFER_SIZE];
+        let mut rx_buf = [0; TRANSFER_SIZE];
+
+        let result = embassy_futures::select::select(
+            SpiBusAsync::transfer(&mut spi, &mut rx_buf, &tx_buf),
+            embassy_futures::yield_now(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            embassy_futures::select::Either::Second(())
+        ));
+
+        let tx_buf = core::array::from_fn::<u8, 64, _>(|i| i as _);
+        let mut rx_buf = [0; 64];
+        SpiBusAsync::transfer(&mut spi, &mut rx_buf, &tx_buf)
+            .await
+            .unwrap();
+        assert_eq!(rx_buf, tx_buf);
+    }
+
+    #[test]
+    #[cfg(all(pdma, pcnt, feature = "unstable"))]
+    fn test_dma_circular_write_stops_at_segment_boundary(ctx: Context) {
+        // The buffer is split into 3 segments of 32 bytes.
+        const SEGMENT_SIZE: usize = 32;
+        const PUSHED: usize = 30 * SEGMENT_SIZE;
+        let (_, _, tx_buffer, tx_descriptors) = dma_circular_buffers!(0, 3 * SEGMENT_SIZE);
+
+        let unit = ctx.pcnt_unit;
+        unit.channel0.set_edge_signal(ctx.pcnt_source);
+        unit.channel0
+            .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+
+        // Each byte has 3 pos edges.
+        tx_buffer.fill(0b0110_1010);
+        let data = [0b0110_1010; SEGMENT_SIZE];
+
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_circular_tx(tx_descriptors);
+        let mut transfer = spi.write_circular(tx_buffer).unwrap();
+
+        let mut pushed = 0;
+        while pushed < PUSHED {
+            if transfer.available().unwrap() >= data.len() {
+                pushed += transfer.push(&data).unwrap();
+            }
+        }
+        transfer.stop().unwrap();
+
+        let value = unit.value() as usize;
+        assert!(value >= 3 * PUSHED);
+        assert_eq!(value % (3 * SEGMENT_SIZE), 0);
+    }
+
+    #[test]
+    #[cfg(all(pdma, feature = "unstable"))]
+    fn test_dma_circular_write_reports_underrun(ctx: Context) {
+        let (_, _, tx_buffer, tx_descriptors) = dma_circular_buffers!(0, 96);
+
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_circular_tx(tx_descriptors);
+        let mut transfer = spi.write_circular(tx_buffer).unwrap();
+
+        // Nothing is pushed, so the DMA runs out of fresh data after one lap.
+        loop {
+            match transfer.available() {
+                Ok(_) => {}
+                Err(DmaError::Late) => break,
+                Err(e) => panic!("unexpected error: {:?}", e),
+            }
+        }
+        assert!(matches!(transfer.push(&[0; 4]), Err(DmaError::Late)));
+        transfer.stop().unwrap();
+
+        // The driver can be taken back for regular transfers.
+        let (spi, _) = spi.split();
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(4);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+        let mut spi = spi.with_buffers(dma_rx_buf, dma_tx_buf);
+
+        let mut rx_buf = [0; 4];
+        spi.transfer(&mut rx_buf, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(rx_buf, [1, 2, 3, 4]);
+    }
+
     #[test]
     #[cfg(all(pcnt, feature = "unstable"))]
     async fn test_async_dma_read_dma_write_pcnt(ctx: Context) {
@@ -0,0 +1,303 @@
+//! SPI Half Duplex Transaction Sequence Test
+
+//% CHIPS: esp32c2 esp32c3 esp32c6 esp32h2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use core::ops::ControlFlow;
+
+use esp_hal::{
+    dma::{DmaError, DmaRxBuf, DmaTxBuf},
+    dma_buffers,
+    gpio::{Level, Output, OutputConfig},
+    spi::{
+        master::{Address, Command, Config, SequenceError, Spi, SpiDmaBus, TransactionSequence},
+        DataMode,
+        Error,
+        Mode,
+    },
+    time::{ExtU64, RateExtU32},
+    timer::{
+        timg::{Timer, TimerGroup},
+        PeriodicTimer,
+    },
+    Blocking,
+};
+#[cfg(pcnt)]
+use esp_hal::{
+    gpio::interconnect::InputSignal,
+    pcnt::{channel::EdgeMode, unit::Unit, Pcnt},
+};
+use hil_test as _;
+
+const DMA_BUFFER_SIZE: usize = 256;
+
+struct Context {
+    spi: SpiDmaBus<'static, Blocking>,
+    miso_mirror: Output<'static>,
+    timer: Timer,
+    #[cfg(pcnt)]
+    pcnt_unit: Unit<'static, 0>,
+    #[cfg(pcnt)]
+    pcnt_source: InputSignal,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let sclk = peripherals.GPIO0;
+        let (miso, miso_mirror) = hil_test::common_test_pins!(peripherals);
+        cfg_if::cfg_if! {
+            if #[cfg(esp32s3)] {
+                // The unconnected pin is the clock pin on this chip.
+                let mosi = peripherals.GPIO1;
+            } else {
+                let mosi = hil_test::unconnected_pin!(peripherals);
+            }
+        }
+
+        let miso_mirror = Output::new(miso_mirror, Level::High, OutputConfig::default());
+        let (mosi_loopback, mosi) = mosi.split();
+
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+
+        let spi = Spi::new(
+            peripherals.SPI2,
+            Config::default()
+                .with_frequency(100.kHz())
+                .with_mode(Mode::_0),
+        )
+        .unwrap()
+        .with_sck(sclk)
+        .with_miso(miso)
+        .with_mosi(mosi)
+        .with_dma(peripherals.DMA_CH0)
+        .with_buffers(dma_rx_buf, dma_tx_buf);
+
+        let timg0 = TimerGroup::new(peripherals.TIMG0);
+
+        #[cfg(pcnt)]
+        let pcnt = Pcnt::new(peripherals.PCNT);
+        #[cfg(not(pcnt))]
+        let _ = mosi_loopback;
+
+        Context {
+            spi,
+            miso_mirror,
+            timer: timg0.timer0,
+            #[cfg(pcnt)]
+            pcnt_unit: pcnt.unit0,
+            #[cfg(pcnt)]
+            pcnt_source: mosi_loopback,
+        }
+    }
+
+    #[test]
+    fn test_sequence_reads_all_segments(mut ctx: Context) {
+        let mode = DataMode::SingleTwoDataLines;
+        let sequence = TransactionSequence::new()
+            .read(mode, Command::_8Bit(0x03, mode), Address::None, 0, 2)
+            .unwrap()
+            .read(mode, Command::None, Address::_24Bit(0x12_3456, mode), 0, 3)
+            .unwrap()
+            .read(mode, Command::_8Bit(0x05, mode), Address::None, 0, 0)
+            .unwrap();
+        assert_eq!(sequence.len(), 3);
+        assert_eq!(sequence.read_len(), 5);
+
+        // The bytes past the read segments are left alone.
+        let mut read = [0x55; 6];
+        ctx.spi.run_sequence(&sequence, &mut read).unwrap();
+        assert_eq!(read, [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x55]);
+
+        ctx.miso_mirror.set_low();
+
+        ctx.spi.run_sequence(&sequence, &mut read).unwrap();
+        assert_eq!(read, [0x00, 0x00, 0x00, 0x00, 0x00, 0x55]);
+    }
+
+    #[test]
+    fn test_sequence_mixes_reads_and_writes(mut ctx: Context) {
+        let mode = DataMode::SingleTwoDataLines;
+        let sequence = TransactionSequence::new()
+            .write(mode, Command::_8Bit(0x06, mode), Address::None, 0, &[])
+            .unwrap()
+            .write(mode, Command::None, Address::None, 0, &[0xA5; 3])
+            .unwrap()
+            .read(mode, Command::None, Address::None, 0, 4)
+            .unwrap()
+            .with_gap(1000)
+            .unwrap();
+
+        let mut read = [0x00; 4];
+        ctx.spi.run_sequence(&sequence, &mut read).unwrap();
+        assert_eq!(read, [0xFF; 4]);
+
+        // The bus is usable for plain transfers afterwards.
+        ctx.miso_mirror.set_low();
+        let mut read = [0xFF; 4];
+        ctx.spi
+            .half_duplex_read(mode, Command::None, Address::None, 0, &mut read)
+            .unwrap();
+        assert_eq!(read, [0x00; 4]);
+    }
+
+    #[test]
+    #[cfg(pcnt)]
+    fn test_sequence_writes_all_segments(mut ctx: Context) {
+        let unit = ctx.pcnt_unit;
+        unit.channel0.set_edge_signal(ctx.pcnt_source);
+        unit.channel0
+            .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+
+        // Each byte has 3 positive edges.
+        let data = [0b0110_1010; 4];
+        let mode = DataMode::SingleTwoDataLines;
+        let mut sequence = TransactionSequence::new();
+        for _ in 0..3 {
+            sequence = sequence
+                .write(mode, Command::None, Address::None, 0, &data)
+                .unwrap();
+        }
+
+        ctx.spi.run_sequence(&sequence, &mut []).unwrap();
+        assert_eq!(unit.value(), 3 * 3 * data.len() as i16);
+    }
+
+    #[test]
+    fn test_sequence_runs_periodically(mut ctx: Context) {
+        let mode = DataMode::SingleTwoDataLines;
+        let sequence = TransactionSequence::new()
+            .write(mode, Command::_8Bit(0x01, mode), Address::None, 0, &[])
+            .unwrap()
+            .read(mode, Command::None, Address::None, 0, 2)
+            .unwrap();
+
+        let mut timer = PeriodicTimer::new(ctx.timer);
+        timer.start(10.millis()).unwrap();
+
+        let start = esp_hal::time::now();
+        let mut runs = 0;
+        let mut read = [0x00; 2];
+        ctx.spi
+            .run_sequence_periodically(&sequence, &mut read, &mut timer, |data| {
+                assert_eq!(data, [0xFF; 2]);
+                runs += 1;
+                if runs < 5 {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            })
+            .unwrap();
+
+        assert_eq!(runs, 5);
+        assert!((esp_hal::time::now() - start).to_millis() >= 50);
+    }
+
+    #[test]
+    async fn test_async_sequence(mut ctx: Context) {
+        let mode = DataMode::SingleTwoDataLines;
+        let sequence = TransactionSequence::new()
+            .write(
+                mode,
+                Command::_8Bit(0x01, mode),
+                Address::None,
+                0,
+                &[0x00; 8],
+            )
+            .unwrap()
+            .read(mode, Command::_8Bit(0x02, mode), Address::None, 0, 8)
+            .unwrap();
+
+        let mut spi = ctx.spi.into_async();
+
+        let mut read = [0x00; 8];
+        spi.run_sequence_async(&sequence, &mut read).await.unwrap();
+        assert_eq!(read, [0xFF; 8]);
+
+        ctx.miso_mirror.set_low();
+
+        spi.run_sequence_async(&sequence, &mut read).await.unwrap();
+        assert_eq!(read, [0x00; 8]);
+    }
+
+    #[test]
+    fn test_sequence_limits_are_validated(mut ctx: Context) {
+        let mode = DataMode::SingleTwoDataLines;
+
+        let mut sequence = TransactionSequence::new();
+        for _ in 0..TransactionSequence::MAX_SEGMENTS {
+            sequence = sequence
+                .read(mode, Command::None, Address::None, 0, 1)
+                .unwrap();
+        }
+        assert_eq!(
+            sequence
+                .clone()
+                .read(mode, Command::None, Address::None, 0, 1)
+                .unwrap_err(),
+            SequenceError::TooManySegments
+        );
+
+        assert_eq!(
+            TransactionSequence::new()
+                .read(mode, Command::None, Address::None, 0, 40_000)
+                .unwrap_err(),
+            SequenceError::SegmentTooLong
+        );
+        assert_eq!(
+            TransactionSequence::new()
+                .with_gap(TransactionSequence::MAX_GAP + 1)
+                .unwrap_err(),
+            SequenceError::GapTooLong
+        );
+
+        // The read buffer and the DMA buffers must fit the data.
+        let mut read = [0; TransactionSequence::MAX_SEGMENTS - 1];
+        assert_eq!(
+            ctx.spi.run_sequence(&sequence, &mut read),
+            Err(Error::DmaError(DmaError::BufferTooSmall))
+        );
+        let large = TransactionSequence::new()
+            .read(mode, Command::None, Address::None, 0, DMA_BUFFER_SIZE + 1)
+            .unwrap();
+        let mut read = [0; DMA_BUFFER_SIZE + 1];
+        assert_eq!(
+            ctx.spi.run_sequence(&large, &mut read),
+            Err(Error::DmaError(DmaError::BufferTooSmall))
+        );
+
+        // Three-wire segments can't be mixed with other data modes.
+        let mixed = TransactionSequence::new()
+            .read(mode, Command::None, Address::None, 0, 1)
+            .unwrap()
+            .read(
+                DataMode::Single,
+                Command::_8Bit(0x01, DataMode::Dual),
+                Address::None,
+                0,
+                1,
+            )
+            .unwrap();
+        assert_eq!(
+            ctx.spi.run_sequence(&mixed, &mut read),
+            Err(Error::Unsupported)
+        );
+
+        // The bus still works after the rejected sequences.
+        let mut read = [0; TransactionSequence::MAX_SEGMENTS];
+        ctx.spi.run_sequence(&sequence, &mut read).unwrap();
+        assert_eq!(read, [0xFF; TransactionSequence::MAX_SEGMENTS]);
+    }
+}
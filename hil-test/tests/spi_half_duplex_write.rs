@@ -102,6 +102,49 @@ fn perform_spidmabus_writes_are_correctly_by_pcnt(ctx: Context, mode: DataMode)
     assert_eq!(unit.value(), (6 * DMA_BUFFER_SIZE) as _);
 }
 
+fn perform_command_only_reads_are_correctly_by_pcnt(ctx: Context) {
+    // The command has 3 pos edges, and there's no data phase after it.
+    const COMMAND: Command = Command::_8Bit(0b0110_1010, DataMode::SingleTwoDataLines);
+
+    let (rx, rxd, tx, txd) = dma_buffers!(1, 1);
+    let dma_rx_buf = DmaRxBuf::new(rxd, rx).unwrap();
+    let dma_tx_buf = DmaTxBuf::new(txd, tx).unwrap();
+
+    let unit = ctx.pcnt_unit;
+    let spi = ctx.spi;
+
+    unit.channel0.set_edge_signal(ctx.pcnt_source);
+    unit.channel0
+        .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+
+    let transfer = spi
+        .half_duplex_read(
+            DataMode::SingleTwoDataLines,
+            COMMAND,
+            Address::None,
+            0,
+            0,
+            dma_rx_buf,
+        )
+        .map_err(|e| e.0)
+        .unwrap();
+    let (spi, dma_rx_buf) = transfer.wait();
+
+    assert_eq!(unit.value(), 3);
+
+    let mut spi = spi.with_buffers(dma_rx_buf, dma_tx_buf);
+    spi.half_duplex_read(
+        DataMode::SingleTwoDataLines,
+        COMMAND,
+        Address::None,
+        0,
+        &mut [],
+    )
+    .unwrap();
+
+    assert_eq!(unit.value(), 6);
+}
+
 #[cfg(test)]
 #[embedded_test::tests(default_timeout = 3)]
 mod tests {
@@ -173,4 +216,9 @@ mod tests {
     fn test_spidmabus_writes_are_correctly_by_pcnt_four_wire(ctx: Context) {
         super::perform_spidmabus_writes_are_correctly_by_pcnt(ctx, DataMode::Single);
     }
+
+    #[test]
+    fn test_command_only_reads_are_correctly_by_pcnt(ctx: Context) {
+        super::perform_command_only_reads_are_correctly_by_pcnt(ctx);
+    }
 }
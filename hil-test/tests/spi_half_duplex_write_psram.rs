@@ -26,6 +26,8 @@ use esp_hal::{
 use hil_test as _;
 extern crate alloc;
 
+use alloc::vec;
+
 macro_rules! dma_alloc_buffer {
     ($size:expr, $align:expr) => {{
         let layout = core::alloc::Layout::from_size_align($size, $align).unwrap();
@@ -179,4 +181,38 @@ mod tests {
 
         assert_eq!(unit.value(), (6 * DMA_BUFFER_SIZE) as _);
     }
+
+    #[test]
+    fn test_spidmabus_writes_large_psram_buffers_in_chunks(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 64 * 1024;
+        const DMA_ALIGNMENT: ExternalBurstConfig = ExternalBurstConfig::Size32;
+        const DMA_CHUNK_SIZE: usize = 4096 - DMA_ALIGNMENT as usize;
+        const TRANSFER_SIZE: usize = 100 * 1024;
+
+        let (_, descriptors) = dma_descriptors_chunk_size!(0, DMA_BUFFER_SIZE, DMA_CHUNK_SIZE);
+        let buffer = dma_alloc_buffer!(DMA_BUFFER_SIZE, DMA_ALIGNMENT as usize);
+        let dma_tx_buf = DmaTxBuf::new_with_config(descriptors, buffer, DMA_ALIGNMENT).unwrap();
+
+        let (rx, rxd, _, _) = dma_buffers!(1, 0);
+        let dma_rx_buf = DmaRxBuf::new(rxd, rx).unwrap();
+
+        let unit = ctx.pcnt_unit;
+        let mut spi = ctx.spi.with_buffers(dma_rx_buf, dma_tx_buf);
+        spi.apply_config(&Config::default().with_frequency(20.MHz()))
+            .unwrap();
+
+        unit.channel0.set_edge_signal(ctx.pcnt_source);
+        unit.channel0
+            .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+
+        // One positive edge per KiB, placed away from the chunk boundaries.
+        let mut data = vec![0u8; TRANSFER_SIZE];
+        for kib in data.chunks_mut(1024) {
+            kib[512] = 0x01;
+        }
+
+        spi.write(&data).unwrap();
+
+        assert_eq!(unit.value(), (TRANSFER_SIZE / 1024) as _);
+    }
 }
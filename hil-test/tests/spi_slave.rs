@@ -13,7 +13,10 @@ use esp_hal::{
     dma_buffers,
     gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull},
     peripheral::Peripheral,
-    spi::{slave::Spi, Mode},
+    spi::{
+        slave::{Spi, SpiInterrupt},
+        Mode,
+    },
     Blocking,
 };
 use hil_test as _;
@@ -168,4 +171,128 @@ mod tests {
         assert_eq!(slave_receive, master_send);
         assert_eq!(master_receive, slave_send);
     }
+
+    #[test]
+    fn queue_receive_reports_frame_length(mut ctx: Context) {
+        // Larger than the FIFO, and shorter than the buffer.
+        const DMA_SIZE: usize = 128;
+        const FRAME_SIZE: usize = 100;
+        let (rx_buffer, rx_descriptors, _, tx_descriptors) = dma_buffers!(DMA_SIZE, 0);
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel, rx_descriptors, tx_descriptors);
+        let slave_receive = rx_buffer;
+        slave_receive.fill(0xFF);
+
+        let master_send = &mut [0u8; FRAME_SIZE];
+        for (i, v) in master_send.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+
+        let transfer = spi.queue_receive(slave_receive).unwrap();
+        ctx.bitbang_spi
+            .transfer_buf(&mut [0u8; FRAME_SIZE], master_send);
+
+        assert_eq!(transfer.wait(), FRAME_SIZE);
+        assert_eq!(&slave_receive[..FRAME_SIZE], master_send);
+    }
+
+    #[test]
+    fn queue_transmit_reports_frame_length(mut ctx: Context) {
+        const DMA_SIZE: usize = 128;
+        const FRAME_SIZE: usize = 96;
+        let (_, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(0, DMA_SIZE);
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel, rx_descriptors, tx_descriptors);
+        let slave_send = tx_buffer;
+        for (i, v) in slave_send.iter_mut().enumerate() {
+            *v = (255 - i) as u8;
+        }
+
+        let master_receive = &mut [0u8; FRAME_SIZE];
+
+        let transfer = spi.queue_transmit(slave_send).unwrap();
+        ctx.bitbang_spi
+            .transfer_buf(master_receive, &[0u8; FRAME_SIZE]);
+
+        assert_eq!(transfer.wait(), FRAME_SIZE);
+        assert_eq!(master_receive, &slave_send[..FRAME_SIZE]);
+    }
+
+    #[test]
+    fn short_frame_length_granularity(mut ctx: Context) {
+        const DMA_SIZE: usize = 32;
+        let (rx_buffer, rx_descriptors, _, tx_descriptors) = dma_buffers!(DMA_SIZE, 0);
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel, rx_descriptors, tx_descriptors);
+        let slave_receive = rx_buffer;
+
+        let master_send = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+
+        let transfer = spi.queue_receive(slave_receive).unwrap();
+        ctx.bitbang_spi.transfer_buf(&mut [0u8; 6], &master_send);
+        let len = transfer.wait();
+
+        // The ESP32 only moves whole words into memory.
+        let expected = if cfg!(esp32) { 4 } else { 6 };
+        assert_eq!(len, expected);
+        assert_eq!(&slave_receive[..len], &master_send[..len]);
+    }
+
+    #[test]
+    fn frame_ends_on_cs_deassertion(mut ctx: Context) {
+        const DMA_SIZE: usize = 32;
+        let (rx_buffer, rx_descriptors, _, tx_descriptors) = dma_buffers!(DMA_SIZE, 0);
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel, rx_descriptors, tx_descriptors);
+        let slave_receive = rx_buffer;
+
+        assert!(!spi.interrupts().contains(SpiInterrupt::CsAsserted));
+        let transfer = spi.queue_receive(slave_receive).unwrap();
+
+        ctx.bitbang_spi.assert_cs();
+        for byte in 0..8 {
+            ctx.bitbang_spi.shift_byte(byte);
+        }
+        assert!(!transfer.is_done());
+
+        ctx.bitbang_spi.deassert_cs();
+        assert_eq!(transfer.wait(), 8);
+        assert_eq!(&slave_receive[..8], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        assert!(spi.interrupts().contains(SpiInterrupt::TransferDone));
+        spi.clear_interrupts(SpiInterrupt::TransferDone);
+        assert!(!spi.interrupts().contains(SpiInterrupt::TransferDone));
+
+        assert!(spi.interrupts().contains(SpiInterrupt::CsAsserted));
+        spi.clear_interrupts(SpiInterrupt::CsAsserted);
+        assert!(!spi.interrupts().contains(SpiInterrupt::CsAsserted));
+    }
+
+    #[test]
+    async fn queue_receive_async(mut ctx: Context) {
+        const DMA_SIZE: usize = 128;
+        const FRAME_SIZE: usize = 72;
+        let (rx_buffer, rx_descriptors, _, tx_descriptors) = dma_buffers!(DMA_SIZE, 0);
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel, rx_descriptors, tx_descriptors)
+            .into_async();
+        let slave_receive = rx_buffer;
+
+        let master_send = &mut [0u8; FRAME_SIZE];
+        for (i, v) in master_send.iter_mut().enumerate() {
+            *v = (i * 3) as u8;
+        }
+
+        let transfer = spi.queue_receive(slave_receive).unwrap();
+        ctx.bitbang_spi
+            .transfer_buf(&mut [0u8; FRAME_SIZE], master_send);
+
+        assert_eq!(transfer.wait_for_done().await, FRAME_SIZE);
+        assert_eq!(&slave_receive[..FRAME_SIZE], master_send);
+    }
 }
@@ -0,0 +1,135 @@
+//! SPI slave test, with a second SPI controller as the master.
+//!
+//! Both controllers share their pins through the GPIO matrix, so the test
+//! needs no external connections.
+
+//% CHIPS: esp32 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    dma::{DmaRxBuf, DmaTxBuf},
+    dma_buffers,
+    spi::{
+        master::{self, SpiDmaBus},
+        slave::{self, dma::SpiDma},
+        Mode,
+    },
+    time::RateExtU32,
+    Async,
+};
+use hil_test as _;
+
+struct Context {
+    master: SpiDmaBus<'static, Async>,
+    slave: SpiDma<'static, Async>,
+    slave_buffer: &'static mut [u8],
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (sclk, _) = hil_test::common_test_pins!(peripherals);
+        let (mosi, miso) = hil_test::i2c_pins!(peripherals);
+        let cs = hil_test::unconnected_pin!(peripherals);
+
+        let (sclk_in, sclk_out) = sclk.split();
+        let (mosi_in, mosi_out) = mosi.split();
+        let (miso_in, miso_out) = miso.split();
+        let (cs_in, cs_out) = cs.split();
+
+        cfg_if::cfg_if! {
+            if #[cfg(pdma)] {
+                let slave_channel = peripherals.DMA_SPI2;
+                let master_channel = peripherals.DMA_SPI3;
+            } else {
+                let slave_channel = peripherals.DMA_CH0;
+                let master_channel = peripherals.DMA_CH1;
+            }
+        }
+
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(128);
+        let master = master::Spi::new(
+            peripherals.SPI3,
+            master::Config::default()
+                .with_frequency(1.MHz())
+                .with_mode(Mode::_1),
+        )
+        .unwrap()
+        .with_sck(sclk_out)
+        .with_mosi(mosi_out)
+        .with_miso(miso_in)
+        .with_cs(cs_out)
+        .with_dma(master_channel)
+        .with_buffers(
+            DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap(),
+            DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap(),
+        )
+        .into_async();
+
+        let (slave_buffer, rx_descriptors, _, tx_descriptors) = dma_buffers!(128, 0);
+        let slave = slave::Spi::new(peripherals.SPI2, Mode::_1)
+            .with_sck(sclk_in)
+            .with_mosi(mosi_in)
+            .with_miso(miso_out)
+            .with_cs(cs_in)
+            .with_dma(slave_channel, rx_descriptors, tx_descriptors)
+            .into_async();
+
+        Context {
+            master,
+            slave,
+            slave_buffer,
+        }
+    }
+
+    #[test]
+    async fn transfer_done_wakes_the_slave(mut ctx: Context) {
+        const FRAME_SIZE: usize = 72;
+
+        let mut master_send = [0u8; FRAME_SIZE];
+        for (i, v) in master_send.iter_mut().enumerate() {
+            *v = (i * 5) as u8;
+        }
+
+        // The slave starts waiting before the master asserts CS, so it can
+        // only complete after the transfer done interrupt woke it up.
+        let transfer = ctx.slave.queue_receive(&mut ctx.slave_buffer).unwrap();
+        let (len, written) = embassy_futures::join::join(
+            transfer.wait_for_done(),
+            ctx.master.write_async(&master_send),
+        )
+        .await;
+
+        assert_eq!(written, Ok(()));
+        assert_eq!(len, FRAME_SIZE);
+        assert_eq!(&ctx.slave_buffer[..FRAME_SIZE], &master_send);
+    }
+
+    #[test]
+    async fn consecutive_frames_wake_the_slave(mut ctx: Context) {
+        for frame in 1..4usize {
+            let len = frame * 16;
+            let master_send = [frame as u8; 64];
+
+            let transfer = ctx.slave.queue_receive(&mut ctx.slave_buffer).unwrap();
+            let (received, written) = embassy_futures::join::join(
+                transfer.wait_for_done(),
+                ctx.master.write_async(&master_send[..len]),
+            )
+            .await;
+
+            assert_eq!(written, Ok(()));
+            assert_eq!(received, len);
+            assert_eq!(&ctx.slave_buffer[..len], &master_send[..len]);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! SPI 3-wire Test
+
+//% CHIPS: esp32 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    dma::{DmaRxBuf, DmaTxBuf},
+    dma_buffers,
+    gpio::{interconnect::InputSignal, Input, InputConfig, Pull},
+    pcnt::{channel::EdgeMode, unit::Unit, Pcnt},
+    spi::{
+        master::{Config, Spi},
+        Mode,
+    },
+    time::RateExtU32,
+    Blocking,
+};
+use hil_test as _;
+
+cfg_if::cfg_if! {
+    if #[cfg(pdma)] {
+        use esp_hal::dma::Spi2DmaChannel as DmaChannel0;
+    } else {
+        use esp_hal::dma::DmaChannel0;
+    }
+}
+
+struct Context {
+    spi: Spi<'static, Blocking>,
+    dma_channel: DmaChannel0,
+    // Counts the rising edges on the data line.
+    sio_unit: Unit<'static, 0>,
+    // Counts the deassertions of CS.
+    cs_unit: Unit<'static, 1>,
+    // Pulls the data line low whenever no one drives it.
+    _pull_down: Input<'static>,
+}
+
+fn count_rising_edges<const NUM: usize>(unit: &Unit<'static, NUM>, signal: InputSignal) {
+    unit.channel0.set_edge_signal(signal);
+    unit.channel0
+        .set_input_mode(EdgeMode::Hold, EdgeMode::Increment);
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let sclk = peripherals.GPIO0;
+        let (sio, pull_down) = hil_test::common_test_pins!(peripherals);
+        let cs = hil_test::unconnected_pin!(peripherals);
+
+        let pcnt = Pcnt::new(peripherals.PCNT);
+
+        cfg_if::cfg_if! {
+            if #[cfg(pdma)] {
+                let dma_channel = peripherals.DMA_SPI2;
+            } else {
+                let dma_channel = peripherals.DMA_CH0;
+            }
+        }
+
+        let (sio_loopback, sio) = sio.split();
+        let (cs_loopback, cs) = cs.split();
+
+        count_rising_edges(&pcnt.unit0, sio_loopback);
+        count_rising_edges(&pcnt.unit1, cs_loopback);
+
+        let _pull_down = Input::new(pull_down, InputConfig::default().with_pull(Pull::Down));
+
+        let spi = Spi::new(
+            peripherals.SPI2,
+            Config::default()
+                .with_frequency(100.kHz())
+                .with_mode(Mode::_0),
+        )
+        .unwrap()
+        .with_sck(sclk)
+        .with_sio0(sio)
+        .with_cs(cs);
+
+        Context {
+            spi,
+            dma_channel,
+            sio_unit: pcnt.unit0,
+            cs_unit: pcnt.unit1,
+            _pull_down,
+        }
+    }
+
+    #[test]
+    fn test_transfer_sio_releases_line_for_read(ctx: Context) {
+        let mut spi = ctx.spi;
+
+        // Each byte has 4 rising edges and ends with a high bit. If the
+        // peripheral kept driving the line after the write, the read would
+        // return 1s instead of the pulled-down level.
+        let write = [0b0101_0101; 4];
+        let mut read = [0xFF; 4];
+        spi.transfer_sio(&write, &mut read).unwrap();
+
+        assert_eq!(ctx.sio_unit.value(), 16);
+        assert_eq!(read, [0x00; 4]);
+        assert_eq!(ctx.cs_unit.value(), 1);
+    }
+
+    #[test]
+    fn test_transfer_sio_keeps_cs_asserted_across_fifo_chunks(ctx: Context) {
+        let mut spi = ctx.spi;
+
+        // Both halves need several transactions through the 64-byte FIFO.
+        let write = [0b0101_0101; 130];
+        let mut read = [0xFF; 100];
+        spi.transfer_sio(&write, &mut read).unwrap();
+
+        assert_eq!(ctx.sio_unit.value(), 4 * 130);
+        assert_eq!(read, [0x00; 100]);
+        assert_eq!(ctx.cs_unit.value(), 1);
+    }
+
+    #[test]
+    fn test_spidmabus_transfer_sio(ctx: Context) {
+        const DMA_BUFFER_SIZE: usize = 4;
+
+        let (rx, rxd, tx, txd) = dma_buffers!(DMA_BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rxd, rx).unwrap();
+        let dma_tx_buf = DmaTxBuf::new(txd, tx).unwrap();
+
+        let mut spi = ctx
+            .spi
+            .with_dma(ctx.dma_channel)
+            .with_buffers(dma_rx_buf, dma_tx_buf);
+
+        // Twice the size of the DMA buffers in both directions.
+        let write = [0b0101_0101; 2 * DMA_BUFFER_SIZE];
+        let mut read = [0xFF; 2 * DMA_BUFFER_SIZE];
+        spi.transfer_sio(&write, &mut read).unwrap();
+
+        assert_eq!(ctx.sio_unit.value(), (4 * 2 * DMA_BUFFER_SIZE) as _);
+        assert_eq!(read, [0x00; 2 * DMA_BUFFER_SIZE]);
+        assert_eq!(ctx.cs_unit.value(), 1);
+    }
+}
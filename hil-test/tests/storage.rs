@@ -0,0 +1,230 @@
+//! esp-storage Test
+//!
+//! Uses flash address 0x9000 (default NVS)
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable esp-storage
+
+#![no_std]
+#![no_main]
+
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use esp_storage::{
+    partitions::{AppType, DataType, PartitionTable},
+    CapacitySource,
+    FlashStorage,
+    FlashStorageError,
+};
+use hil_test as _;
+
+const FLASH_ADDR: u32 = 0x9000;
+
+struct Context {
+    flash: FlashStorage,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let _peripherals = esp_hal::init(esp_hal::Config::default());
+
+        Context {
+            flash: FlashStorage::new(),
+        }
+    }
+
+    #[test]
+    fn test_not_aligned_write_keeps_neighbours(mut ctx: Context) {
+        ctx.flash
+            .erase(FLASH_ADDR, FLASH_ADDR + FlashStorage::SECTOR_SIZE)
+            .unwrap();
+
+        // Straddles a word boundary at both ends
+        let record = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        ctx.flash.write(FLASH_ADDR + 3, &record).unwrap();
+
+        let mut bytes = [0u8; 12];
+        ctx.flash.read(FLASH_ADDR, &mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            [0xff, 0xff, 0xff, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0xff, 0xff, 0xff]
+        );
+
+        // A second record sharing a word with the first must not clobber it
+        ctx.flash.write(FLASH_ADDR + 9, &[0x77, 0x88]).unwrap();
+
+        ctx.flash.read(FLASH_ADDR, &mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            [0xff, 0xff, 0xff, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_not_aligned_read(mut ctx: Context) {
+        ctx.flash
+            .erase(FLASH_ADDR, FLASH_ADDR + FlashStorage::SECTOR_SIZE)
+            .unwrap();
+
+        let mut pattern = [0u8; 64];
+        for (index, byte) in pattern.iter_mut().enumerate() {
+            *byte = index as u8;
+        }
+        ctx.flash.write_aligned(FLASH_ADDR, &pattern).unwrap();
+
+        let mut bytes = [0u8; 64];
+        for offset in 0..8 {
+            for len in 0..(64 - offset) {
+                // Use a not aligned destination buffer as well
+                let buffer = &mut bytes[1..][..len];
+                ctx.flash.read(FLASH_ADDR + offset as u32, buffer).unwrap();
+                assert_eq!(buffer, &pattern[offset..][..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aligned_api_rejects_not_aligned(mut ctx: Context) {
+        let mut bytes = [0u8; 8];
+
+        assert!(ctx.flash.read_aligned(FLASH_ADDR + 1, &mut bytes).is_err());
+        assert!(ctx.flash.read_aligned(FLASH_ADDR, &mut bytes[..3]).is_err());
+        assert!(ctx.flash.write_aligned(FLASH_ADDR + 2, &bytes).is_err());
+    }
+
+    #[test]
+    fn test_erase_sector(mut ctx: Context) {
+        ctx.flash.write(FLASH_ADDR, &[0u8; 16]).unwrap();
+        ctx.flash
+            .erase_sector(FLASH_ADDR / FlashStorage::SECTOR_SIZE)
+            .unwrap();
+
+        let mut bytes = [0u8; 16];
+        ctx.flash.read(FLASH_ADDR, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff; 16]);
+    }
+
+    #[test]
+    fn test_erase_rejects_invalid_ranges(mut ctx: Context) {
+        let capacity = ctx.flash.capacity() as u32;
+
+        assert!(ctx.flash.erase(FLASH_ADDR + 1, FLASH_ADDR + 4096).is_err());
+        assert!(ctx.flash.erase(FLASH_ADDR, FLASH_ADDR + 1).is_err());
+        assert!(ctx.flash.erase(FLASH_ADDR + 4096, FLASH_ADDR).is_err());
+        assert!(ctx.flash.erase(capacity, capacity + 4096).is_err());
+        assert!(ctx
+            .flash
+            .erase_sector(capacity / FlashStorage::SECTOR_SIZE)
+            .is_err());
+    }
+
+    #[test]
+    fn test_writable(mut ctx: Context) {
+        ctx.flash
+            .writable(|flash| {
+                flash.erase_sector(FLASH_ADDR / FlashStorage::SECTOR_SIZE)?;
+                flash.write(FLASH_ADDR, &[0x12, 0x34])
+            })
+            .unwrap();
+
+        let mut bytes = [0u8; 2];
+        ctx.flash.read(FLASH_ADDR, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x12, 0x34]);
+
+        // Writing after locking unlocks the flash again
+        ctx.flash.write(FLASH_ADDR + 2, &[0x56]).unwrap();
+        ctx.flash.lock().unwrap();
+    }
+
+    #[test]
+    fn test_multiwrite_same_word(mut ctx: Context) {
+        ctx.flash
+            .erase(FLASH_ADDR, FLASH_ADDR + FlashStorage::SECTOR_SIZE)
+            .unwrap();
+
+        if ctx.flash.encryption_enabled() {
+            assert!(matches!(
+                NorFlash::write(&mut ctx.flash, FLASH_ADDR, &[0xf0; 4]),
+                Err(FlashStorageError::EncryptionEnabled)
+            ));
+            return;
+        }
+
+        let mut bytes = [0u8; 8];
+        for (value, expected) in [(0xf0, 0xf0), (0x0f, 0x00), (0xff, 0x00)] {
+            NorFlash::write(&mut ctx.flash, FLASH_ADDR, &[value; 4]).unwrap();
+
+            ReadNorFlash::read(&mut ctx.flash, FLASH_ADDR, &mut bytes).unwrap();
+            assert_eq!(bytes[..4], [expected; 4]);
+            // The next word is left erased
+            assert_eq!(bytes[4..], [0xff; 4]);
+        }
+    }
+
+    #[test]
+    fn test_jedec_id(mut ctx: Context) {
+        let info = ctx.flash.chip_info().unwrap();
+
+        assert_ne!(info.manufacturer, 0x00);
+        assert_ne!(info.manufacturer, 0xff);
+        assert_eq!(ctx.flash.capacity(), info.capacity_bytes);
+        assert_eq!(ctx.flash.capacity_source(), CapacitySource::JedecId);
+    }
+
+    #[test]
+    fn test_access_above_16_mb(mut ctx: Context) {
+        const BOUNDARY: u32 = 16 * 1024 * 1024;
+
+        // Only boards with a larger flash, e.g. the N32R8 ESP32-S3 modules,
+        // can run this
+        if ctx.flash.capacity() <= BOUNDARY as usize {
+            return;
+        }
+
+        ctx.flash
+            .erase(
+                BOUNDARY - FlashStorage::SECTOR_SIZE,
+                BOUNDARY + FlashStorage::SECTOR_SIZE,
+            )
+            .unwrap();
+
+        let data: [u8; 64] = core::array::from_fn(|i| i as u8);
+        ctx.flash.write(BOUNDARY - 32, &data).unwrap();
+
+        let mut bytes = [0u8; 64];
+        ctx.flash.read(BOUNDARY - 32, &mut bytes).unwrap();
+        assert_eq!(bytes, data);
+
+        // Nothing wrapped around to the start of the flash
+        let mut bytes = [0u8; 32];
+        ctx.flash.read(BOUNDARY + 32, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff; 32]);
+    }
+
+    #[test]
+    fn test_unique_id(mut ctx: Context) {
+        // Not all flash chips support reading the unique ID, but if they do it
+        // must be stable
+        if let Ok(id) = ctx.flash.unique_id() {
+            assert_eq!(ctx.flash.unique_id().unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn test_partition_table(mut ctx: Context) {
+        let table = PartitionTable::read(&mut ctx.flash).unwrap();
+
+        // The default partition table places the NVS partition at 0x9000
+        let nvs = table.find_by_type(DataType::Nvs).unwrap();
+        assert_eq!(nvs.offset(), FLASH_ADDR);
+        assert_eq!(table.find("nvs"), Some(nvs));
+
+        let factory = table.find_by_type(AppType::Factory).unwrap();
+        let region = factory.as_region(&mut ctx.flash).unwrap();
+        assert_eq!(region.capacity(), factory.size() as usize);
+    }
+}
@@ -0,0 +1,92 @@
+//! esp-storage auto-suspend Test
+//!
+//! Erases flash address 0x9000 (default NVS) while a timer interrupt placed in
+//! flash keeps firing.
+
+//% CHIPS: esp32c3 esp32c6 esp32h2 esp32s3
+//% FEATURES: unstable esp-storage
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use esp_hal::{
+    handler,
+    time::ExtU64,
+    timer::{
+        timg::{Timer, TimerGroup},
+        PeriodicTimer,
+    },
+    Blocking,
+};
+use esp_storage::FlashStorage;
+use hil_test as _;
+use portable_atomic::{AtomicUsize, Ordering};
+
+const FLASH_ADDR: u32 = 0x9000;
+const FLASH_LEN: u32 = 6 * FlashStorage::SECTOR_SIZE;
+const PERIOD_US: u64 = 500;
+
+static TIMER: Mutex<RefCell<Option<PeriodicTimer<'static, Blocking>>>> =
+    Mutex::new(RefCell::new(None));
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+
+#[handler(priority = esp_hal::interrupt::Priority::min())]
+fn count_tick() {
+    critical_section::with(|cs| TIMER.borrow_ref_mut(cs).as_mut().unwrap().clear_interrupt());
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+struct Context {
+    flash: FlashStorage,
+    timer: Timer,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 10)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+        let timg0 = TimerGroup::new(peripherals.TIMG0);
+
+        Context {
+            flash: FlashStorage::new(),
+            timer: timg0.timer0,
+        }
+    }
+
+    #[test]
+    fn test_no_missed_ticks_while_erasing(mut ctx: Context) {
+        ctx.flash.enable_auto_suspend().unwrap();
+
+        let mut timer = PeriodicTimer::new(ctx.timer);
+        critical_section::with(|cs| {
+            timer.set_interrupt_handler(count_tick);
+            timer.enable_interrupt(true);
+            timer.start(PERIOD_US.micros()).unwrap();
+
+            TIMER.borrow_ref_mut(cs).replace(timer);
+        });
+
+        let start = esp_hal::time::now();
+        let start_ticks = TICKS.load(Ordering::Relaxed);
+        for _ in 0..4 {
+            ctx.flash.erase(FLASH_ADDR, FLASH_ADDR + FLASH_LEN).unwrap();
+        }
+        let elapsed = (esp_hal::time::now() - start).to_micros();
+        let ticks = TICKS.load(Ordering::Relaxed) - start_ticks;
+
+        // A tick blocked for longer than a period would be lost. Allow for the
+        // ticks in flight at either end of the measurement.
+        assert!(ticks + 2 >= (elapsed / PERIOD_US) as usize);
+
+        let mut bytes = [0u8; 16];
+        ctx.flash.read(FLASH_ADDR, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff; 16]);
+    }
+}
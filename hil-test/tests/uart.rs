@@ -91,4 +91,39 @@ mod tests {
             byte_to_write = !byte_to_write;
         }
     }
+
+    #[test]
+    #[cfg(not(any(esp32h2, esp32s2)))]
+    fn wakeup_threshold_is_validated(mut ctx: Context) {
+        assert_eq!(
+            ctx.uart.wakeup_enable(2),
+            Err(uart::WakeupError::UnsupportedThreshold)
+        );
+        ctx.uart.wakeup_enable(3).unwrap();
+        ctx.uart.wakeup_disable();
+
+        // The UART still works normally.
+        ctx.uart.write_bytes(&[0x55]).unwrap();
+        let mut byte = [0u8; 1];
+        ctx.uart.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte[0], 0x55);
+    }
+
+    #[test]
+    fn split_halves_can_be_reunited(ctx: Context) {
+        let (mut rx, mut tx) = ctx.uart.split();
+
+        // Either half can change the configuration shared by both.
+        tx.apply_config(&uart::Config::default().with_baudrate(19_200))
+            .unwrap();
+        tx.write_bytes(&[0x42]).unwrap();
+        let mut byte = [0u8; 1];
+        rx.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte[0], 0x42);
+
+        let mut uart = tx.reunite(rx).unwrap();
+        uart.write_bytes(&[0x43]).unwrap();
+        uart.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte[0], 0x43);
+    }
 }
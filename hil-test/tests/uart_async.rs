@@ -48,4 +48,30 @@ mod tests {
         ctx.uart.read_async(&mut buf[..]).await.unwrap();
         assert_eq!(&buf[..], SEND);
     }
+
+    #[test]
+    async fn split_halves_can_wait_at_the_same_time(ctx: Context) {
+        const LEN: usize = 200;
+
+        let (mut rx, mut tx) = ctx.uart.split();
+
+        let reader = async {
+            let mut buffer = [0; LEN];
+            let mut received = 0;
+            while received < LEN {
+                received += rx.read_async(&mut buffer[received..]).await.unwrap();
+            }
+            buffer
+        };
+        // Longer than the FIFO, so both halves keep enabling their interrupts
+        // while the other one is waiting.
+        let data: [u8; LEN] = core::array::from_fn(|i| i as u8);
+        let writer = async {
+            tx.write_async(&data).await.unwrap();
+            tx.flush_async().await.unwrap();
+        };
+
+        let (received, _) = embassy_futures::join::join(reader, writer).await;
+        assert_eq!(received, data);
+    }
 }
@@ -0,0 +1,100 @@
+//! UART auto-baud Test
+//!
+//! UART0 sends training characters at a baud rate that UART1 doesn't know,
+//! and UART1 detects it.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    time::Duration,
+    uart::{self, AutobaudError, Uart, UartTx},
+    Blocking,
+};
+use hil_test as _;
+
+struct Context {
+    tx: UartTx<'static, Blocking>,
+    uart: Uart<'static, Blocking>,
+}
+
+fn assert_detects(ctx: &mut Context, baudrate: u32) {
+    ctx.tx
+        .apply_config(&uart::Config::default().with_baudrate(baudrate))
+        .unwrap();
+    // Fills the TX FIFO, which keeps sending during the detection.
+    ctx.tx.write_bytes(&[b'U'; 128]).unwrap();
+
+    let detected = ctx.uart.detect_baud(Duration::secs(1)).unwrap();
+    let error = detected.abs_diff(baudrate);
+    assert!(error <= baudrate / 50, "detected {} baud", detected);
+
+    // Drop the rest of the training, its first character may have been
+    // cut in half by the switch.
+    ctx.tx.flush();
+    let mut buffer = [0; 128];
+    while ctx
+        .uart
+        .read_buffered_bytes(&mut buffer)
+        .map_or(true, |len| len > 0)
+    {}
+
+    ctx.tx.write_bytes(&[0x42, 0x43]).unwrap();
+    let mut buffer = [0; 2];
+    ctx.uart.read_bytes(&mut buffer).unwrap();
+    assert_eq!(buffer, [0x42, 0x43]);
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 5)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let tx = UartTx::new(peripherals.UART0, uart::Config::default())
+            .unwrap()
+            .with_tx(tx);
+        let uart = Uart::new(peripherals.UART1, uart::Config::default())
+            .unwrap()
+            .with_rx(rx);
+
+        Context { tx, uart }
+    }
+
+    #[test]
+    fn detects_slow_baud_rates(mut ctx: Context) {
+        assert_detects(&mut ctx, 9600);
+    }
+
+    #[test]
+    fn detects_the_default_baud_rate(mut ctx: Context) {
+        assert_detects(&mut ctx, 115_200);
+    }
+
+    #[test]
+    fn detects_fast_baud_rates(mut ctx: Context) {
+        assert_detects(&mut ctx, 1_000_000);
+    }
+
+    #[test]
+    fn times_out_without_training(mut ctx: Context) {
+        assert_eq!(
+            ctx.uart.detect_baud(Duration::millis(10)),
+            Err(AutobaudError::Timeout)
+        );
+
+        // The previous configuration still works.
+        ctx.tx.write_bytes(&[0x42]).unwrap();
+        let mut buffer = [0; 1];
+        ctx.uart.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, [0x42]);
+    }
+}
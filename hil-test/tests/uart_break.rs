@@ -0,0 +1,111 @@
+//! UART break Test
+//!
+//! The UART sends breaks to itself at DMX512 settings: 250 kbaud, 8N2, with a
+//! break followed by the start code.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    uart::{self, Error, StopBits, Uart},
+    Blocking,
+};
+use hil_test as _;
+
+// A DMX512 break lasts at least 88 µs, i.e. 22 bit periods.
+const DMX_BREAK_BITS: u16 = 22;
+const START_CODE: u8 = 0x55;
+
+struct Context {
+    uart: Uart<'static, Blocking>,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let config = uart::Config::default()
+            .with_baudrate(250_000)
+            .with_stop_bits(StopBits::_2);
+        let uart = Uart::new(peripherals.UART1, config)
+            .unwrap()
+            .with_tx(tx)
+            .with_rx(rx);
+
+        Context { uart }
+    }
+
+    #[test]
+    fn break_is_reported_before_the_start_code(mut ctx: Context) {
+        ctx.uart.write_bytes(&[0x42]).unwrap();
+        ctx.uart.send_break(DMX_BREAK_BITS);
+        ctx.uart.write_bytes(&[START_CODE]).unwrap();
+        ctx.uart.flush();
+
+        // The byte before the break is discarded, the one after it is kept.
+        let mut buffer = [0; 4];
+        assert_eq!(
+            ctx.uart.read_buffered_bytes(&mut buffer),
+            Err(Error::BreakDetected)
+        );
+        assert_eq!(ctx.uart.read_buffered_bytes(&mut buffer), Ok(1));
+        assert_eq!(buffer[0], START_CODE);
+    }
+
+    #[test]
+    fn breaks_longer_than_the_hardware_supports(mut ctx: Context) {
+        ctx.uart.send_break(1000);
+        ctx.uart.write_bytes(&[START_CODE]).unwrap();
+        ctx.uart.flush();
+
+        let mut buffer = [0; 4];
+        assert_eq!(
+            ctx.uart.read_buffered_bytes(&mut buffer),
+            Err(Error::BreakDetected)
+        );
+        assert_eq!(ctx.uart.read_buffered_bytes(&mut buffer), Ok(1));
+        assert_eq!(buffer[0], START_CODE);
+    }
+
+    #[test]
+    fn data_without_breaks_is_read_normally(mut ctx: Context) {
+        ctx.uart.write_bytes(&[0x00, START_CODE, 0x00]).unwrap();
+        ctx.uart.flush();
+
+        let mut buffer = [0xFF; 3];
+        ctx.uart.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, [0x00, START_CODE, 0x00]);
+    }
+
+    #[test]
+    async fn async_reads_report_back_to_back_frames(ctx: Context) {
+        let mut uart = ctx.uart.into_async();
+        let mut buffer = [0; 4];
+
+        for frame in 0..3 {
+            uart.send_break(DMX_BREAK_BITS);
+            uart.write_async(&[START_CODE, frame]).await.unwrap();
+            uart.flush_async().await.unwrap();
+
+            assert_eq!(
+                uart.read_async(&mut buffer).await,
+                Err(Error::BreakDetected)
+            );
+            let mut received = 0;
+            while received < 2 {
+                received += uart.read_async(&mut buffer[received..]).await.unwrap();
+            }
+            assert_eq!(&buffer[..2], &[START_CODE, frame]);
+        }
+    }
+}
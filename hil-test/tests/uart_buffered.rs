@@ -0,0 +1,123 @@
+//! UART software buffer Test
+//!
+//! UART0 sends to UART1, both using software buffers.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    delay::Delay,
+    uart::{self, Error, UartRx, UartTx},
+    Async,
+};
+use hil_test as _;
+
+macro_rules! mk_static {
+    ($t:ty,$val:expr) => {{
+        static STATIC_CELL: static_cell::StaticCell<$t> = static_cell::StaticCell::new();
+        #[deny(unused_attributes)]
+        let x = STATIC_CELL.uninit().write(($val));
+        x
+    }};
+}
+
+struct Context {
+    rx: UartRx<'static, Async>,
+    tx: UartTx<'static, Async>,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    async fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let tx = UartTx::new(peripherals.UART0, uart::Config::default())
+            .unwrap()
+            .with_tx(tx)
+            .into_async_with_buffer(mk_static!([u8; 512], [0; 512]));
+        let rx = UartRx::new(peripherals.UART1, uart::Config::default())
+            .unwrap()
+            .with_rx(rx)
+            .into_async_with_buffer(mk_static!([u8; 400], [0; 400]));
+
+        Context { rx, tx }
+    }
+
+    #[test]
+    async fn reads_return_more_than_a_fifo(mut ctx: Context) {
+        let data: [u8; 400] = core::array::from_fn(|i| i as u8);
+
+        // The whole write fits into the TX buffer.
+        assert_eq!(ctx.tx.write_async(&data).await, Ok(data.len()));
+        ctx.tx.flush_async().await.unwrap();
+
+        let mut buffer = [0; 400];
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(data.len()));
+        assert_eq!(buffer, data);
+    }
+
+    #[test]
+    async fn async_reads_take_from_the_buffer(mut ctx: Context) {
+        let data: [u8; 300] = core::array::from_fn(|i| (i * 3) as u8);
+        let mut buffer = [0; 300];
+
+        let (written, read) = embassy_futures::join::join(ctx.tx.write_async(&data), async {
+            let mut received = 0;
+            while received < buffer.len() {
+                received += ctx.rx.read_async(&mut buffer[received..]).await?;
+            }
+            Ok::<_, Error>(received)
+        })
+        .await;
+
+        assert_eq!(written, Ok(data.len()));
+        assert_eq!(read, Ok(data.len()));
+        assert_eq!(buffer, data);
+    }
+
+    #[test]
+    async fn overflows_are_reported(mut ctx: Context) {
+        let data: [u8; 500] = core::array::from_fn(|i| i as u8);
+
+        ctx.tx.write_async(&data).await.unwrap();
+        ctx.tx.flush_async().await.unwrap();
+        // Wait for the RX timeout to move the rest of the FIFO.
+        Delay::new().delay_millis(1);
+
+        let mut buffer = [0; 500];
+        assert_eq!(
+            ctx.rx.read_buffered_bytes(&mut buffer),
+            Err(Error::BufferOverflowed)
+        );
+        assert_eq!(ctx.rx.dropped_bytes(), 100);
+
+        // The bytes that fit are still there.
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(400));
+        assert_eq!(&buffer[..400], &data[..400]);
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(0));
+    }
+
+    #[test]
+    async fn read_until_idle_returns_a_whole_packet(mut ctx: Context) {
+        let packet: [u8; 300] = core::array::from_fn(|i| i as u8);
+        let mut buffer = [0; 400];
+
+        let (_, read) = embassy_futures::join::join(
+            ctx.tx.write_async(&packet),
+            ctx.rx.read_until_idle_async(&mut buffer),
+        )
+        .await;
+
+        assert_eq!(read, Ok(packet.len()));
+        assert_eq!(&buffer[..packet.len()], &packet);
+    }
+}
@@ -0,0 +1,136 @@
+//! UART DMA Test
+//!
+//! UART1 is connected to the UHCI, and its TX and RX are connected to each
+//! other.
+
+//% CHIPS: esp32c3 esp32c6 esp32h2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    delay::Delay,
+    dma_rx_stream_buffer,
+    dma_tx_buffer,
+    uart::{self, Uart, UartDma},
+    Blocking,
+};
+use hil_test as _;
+
+struct Context {
+    uart: UartDma<'static, Blocking>,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let uart = Uart::new(peripherals.UART1, uart::Config::default())
+            .unwrap()
+            .with_rx(rx)
+            .with_tx(tx)
+            .with_dma(
+                peripherals.UHCI0,
+                peripherals.DMA_CH0,
+                dma_rx_stream_buffer!(1024, 64),
+                dma_tx_buffer!(256).unwrap(),
+            );
+
+        Context { uart }
+    }
+
+    #[test]
+    fn writes_longer_than_the_tx_buffer_are_received(mut ctx: Context) {
+        let data: [u8; 600] = core::array::from_fn(|i| i as u8);
+
+        assert_eq!(ctx.uart.write_bytes(&data), Ok(data.len()));
+        ctx.uart.flush();
+
+        let mut buffer = [0; 600];
+        ctx.uart.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, data);
+
+        // Nothing else was received.
+        Delay::new().delay_millis(10);
+        assert_eq!(ctx.uart.read_buffered_bytes(&mut buffer), Ok(0));
+    }
+
+    #[test]
+    fn reception_resumes_after_the_buffer_ran_full(mut ctx: Context) {
+        // The DMA stops once the 1024 bytes are full, but the RX FIFO takes
+        // the rest.
+        let data: [u8; 1100] = core::array::from_fn(|i| (i * 7) as u8);
+
+        ctx.uart.write_bytes(&data).unwrap();
+        ctx.uart.flush();
+
+        let mut buffer = [0; 1100];
+        ctx.uart.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, data);
+
+        // The restarted stream keeps working.
+        ctx.uart.write_bytes(&[1, 2, 3]).unwrap();
+        ctx.uart.flush();
+        let mut buffer = [0; 3];
+        ctx.uart.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3]);
+    }
+
+    #[test]
+    async fn frames_end_when_the_line_goes_idle(ctx: Context) {
+        let mut uart = ctx.uart.into_async();
+
+        uart.write_async(&[0x55; 10]).await.unwrap();
+        uart.flush_async().await.unwrap();
+        // Longer than the default RX idle threshold of 256 bits.
+        Delay::new().delay_millis(5);
+        uart.write_async(&[0xAA; 20]).await.unwrap();
+        uart.flush_async().await.unwrap();
+
+        let mut buffer = [0; 64];
+        assert_eq!(uart.read_frame_async(&mut buffer).await, Ok(10));
+        assert_eq!(&buffer[..10], &[0x55; 10]);
+        assert_eq!(uart.read_frame_async(&mut buffer).await, Ok(20));
+        assert_eq!(&buffer[..20], &[0xAA; 20]);
+    }
+
+    #[test]
+    async fn separators_frame_every_write(ctx: Context) {
+        let mut uart = ctx.uart.with_separator(0xC0).into_async();
+
+        // The separator and escape characters in the data survive the trip.
+        let first = [0x01, 0xC0, 0x02, 0xDB, 0x03];
+        let second = [0x04, 0x05];
+        uart.write_async(&first).await.unwrap();
+        uart.write_async(&second).await.unwrap();
+
+        let mut buffer = [0; 16];
+        let len = uart.read_frame_async(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..len], &first);
+        let len = uart.read_frame_async(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..len], &second);
+    }
+
+    #[test]
+    async fn async_reads_wait_for_data(ctx: Context) {
+        let mut uart = ctx.uart.into_async();
+
+        let data = [0x42; 32];
+        uart.write_async(&data).await.unwrap();
+
+        let mut buffer = [0; 32];
+        let mut received = 0;
+        while received < data.len() {
+            received += uart.read_async(&mut buffer[received..]).await.unwrap();
+        }
+        assert_eq!(buffer, data);
+    }
+}
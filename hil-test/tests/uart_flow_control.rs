@@ -0,0 +1,105 @@
+//! UART hardware flow control Test
+//!
+//! UART0 sends to UART1, and UART1's RTS signal is UART0's CTS signal.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    delay::Delay,
+    gpio::{interconnect::InputSignal, Level},
+    uart::{self, ConfigError, FlowControl, Rs485Config, RxConfig, Uart},
+    Blocking,
+};
+use hil_test as _;
+
+const FLOW_THRESHOLD: u16 = 16;
+
+struct Context {
+    uart0: Uart<'static, Blocking>,
+    uart1: Uart<'static, Blocking>,
+    rts: InputSignal,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+        let (rts, rts_output) = hil_test::unconnected_pin!(peripherals).split();
+
+        let uart0 = Uart::new(
+            peripherals.UART0,
+            uart::Config::default().with_flow_control(FlowControl::Cts),
+        )
+        .unwrap()
+        .with_tx(tx)
+        .with_cts(rts.clone());
+        let uart1 = Uart::new(
+            peripherals.UART1,
+            uart::Config::default()
+                .with_flow_control(FlowControl::Rts)
+                .with_rx(RxConfig::default().with_flow_threshold(FLOW_THRESHOLD)),
+        )
+        .unwrap()
+        .with_rx(rx)
+        .with_rts(rts_output);
+
+        Context { uart0, uart1, rts }
+    }
+
+    #[test]
+    fn rts_pauses_the_sender(mut ctx: Context) {
+        let data: [u8; 64] = core::array::from_fn(|i| i as u8);
+
+        assert_eq!(ctx.rts.level(), Level::Low);
+        ctx.uart0.write_bytes(&data).unwrap();
+
+        // Enough time to send everything without flow control.
+        Delay::new().delay_millis(10);
+        assert_eq!(ctx.rts.level(), Level::High);
+
+        let mut buffer = [0; 64];
+        let len = ctx.uart1.read_buffered_bytes(&mut buffer).unwrap();
+        assert!(len > FLOW_THRESHOLD as usize && len < data.len());
+
+        // Draining the FIFO lets the sender continue.
+        ctx.uart1.read_bytes(&mut buffer[len..]).unwrap();
+        assert_eq!(buffer, data);
+        assert_eq!(ctx.rts.level(), Level::Low);
+    }
+
+    #[test]
+    fn dropping_the_receiver_deasserts_rts(ctx: Context) {
+        assert_eq!(ctx.rts.level(), Level::Low);
+        core::mem::drop(ctx.uart1);
+        assert_eq!(ctx.rts.level(), Level::High);
+    }
+
+    #[test]
+    fn invalid_flow_control_configs_are_rejected(mut ctx: Context) {
+        let config = uart::Config::default()
+            .with_flow_control(FlowControl::RtsCts)
+            .with_rs485(Rs485Config::default().with_enable(true));
+        assert_eq!(
+            ctx.uart1.apply_config(&config),
+            Err(ConfigError::FlowControlConflict)
+        );
+
+        let config = uart::Config::default()
+            .with_flow_control(FlowControl::Rts)
+            .with_rx(RxConfig::default().with_flow_threshold(128));
+        assert_eq!(
+            ctx.uart1.apply_config(&config),
+            Err(ConfigError::UnsupportedFifoThreshold)
+        );
+    }
+}
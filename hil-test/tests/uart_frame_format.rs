@@ -0,0 +1,154 @@
+//! UART frame format Test
+//!
+//! UART0 sends to UART1, with data bits, parity and stop bits configured
+//! independently on both sides.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    uart::{
+        self,
+        CorruptedBytes,
+        DataBits,
+        Error,
+        Parity,
+        RxConfig,
+        RxError,
+        StopBits,
+        UartRx,
+        UartTx,
+    },
+    Blocking,
+};
+use hil_test as _;
+
+struct Context {
+    rx: UartRx<'static, Blocking>,
+    tx: UartTx<'static, Blocking>,
+}
+
+impl Context {
+    fn configure(&mut self, tx: &uart::Config, rx: &uart::Config) {
+        self.tx.apply_config(tx).unwrap();
+        self.rx.apply_config(rx).unwrap();
+    }
+
+    fn send(&mut self, data: &[u8]) {
+        self.tx.write_bytes(data).unwrap();
+        self.tx.flush();
+    }
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let tx = UartTx::new(peripherals.UART0, uart::Config::default())
+            .unwrap()
+            .with_tx(tx);
+        let rx = UartRx::new(peripherals.UART1, uart::Config::default())
+            .unwrap()
+            .with_rx(rx);
+
+        Context { rx, tx }
+    }
+
+    #[test]
+    fn all_frame_formats_round_trip(mut ctx: Context) {
+        let data_bits = [DataBits::_5, DataBits::_6, DataBits::_7, DataBits::_8];
+        let parities = [Parity::None, Parity::Even, Parity::Odd];
+        let stop_bits = [StopBits::_1, StopBits::_1p5, StopBits::_2];
+
+        for (i, data_bits) in data_bits.into_iter().enumerate() {
+            let mask = (1u16 << (5 + i)) as u8 - 1;
+            let data = [0xA5 & mask, 0x5A & mask, mask];
+
+            for parity in parities {
+                for stop_bits in stop_bits {
+                    let config = uart::Config::default()
+                        .with_data_bits(data_bits)
+                        .with_parity(parity)
+                        .with_stop_bits(stop_bits);
+                    ctx.configure(&config, &config);
+
+                    ctx.send(&data);
+                    let mut buffer = [0; 3];
+                    ctx.rx.read_bytes(&mut buffer).unwrap();
+                    assert_eq!(buffer, data);
+                    assert!(ctx.rx.errors().is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parity_mismatch_fails_reads(mut ctx: Context) {
+        let config = uart::Config::default()
+            .with_data_bits(DataBits::_7)
+            .with_parity(Parity::Even);
+        ctx.configure(&config.with_parity(Parity::Odd), &config);
+
+        ctx.send(&[0x41]);
+        let mut buffer = [0; 4];
+        assert_eq!(
+            ctx.rx.read_buffered_bytes(&mut buffer),
+            Err(Error::ParityMismatch)
+        );
+
+        // The failed read has reported the error already.
+        assert!(ctx.rx.errors().is_empty());
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(0));
+    }
+
+    #[test]
+    fn corrupted_bytes_can_be_discarded(mut ctx: Context) {
+        let config = uart::Config::default()
+            .with_data_bits(DataBits::_7)
+            .with_parity(Parity::Even);
+        let rx_config =
+            config.with_rx(RxConfig::default().with_corrupted_bytes(CorruptedBytes::Discard));
+        ctx.configure(&config, &rx_config);
+
+        ctx.send(&[0x41]);
+        ctx.tx
+            .apply_config(&config.with_parity(Parity::Odd))
+            .unwrap();
+        ctx.send(&[0x42]);
+        ctx.tx.apply_config(&config).unwrap();
+        ctx.send(&[0x43]);
+
+        let mut buffer = [0; 2];
+        ctx.rx.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, [0x41, 0x43]);
+        assert_eq!(ctx.rx.errors(), RxError::ParityMismatch);
+        assert!(ctx.rx.errors().is_empty());
+    }
+
+    #[test]
+    async fn async_reads_skip_discarded_bytes(mut ctx: Context) {
+        let config = uart::Config::default().with_parity(Parity::Odd);
+        let rx_config =
+            config.with_rx(RxConfig::default().with_corrupted_bytes(CorruptedBytes::Discard));
+        ctx.configure(&config.with_parity(Parity::Even), &rx_config);
+        ctx.send(&[0x11, 0x22]);
+        ctx.tx.apply_config(&config).unwrap();
+        ctx.send(&[0x33]);
+
+        let mut rx = ctx.rx.into_async();
+        let mut buffer = [0; 4];
+        let len = rx.read_async(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..len], &[0x33]);
+        assert_eq!(rx.errors(), RxError::ParityMismatch);
+    }
+}
@@ -0,0 +1,96 @@
+//! UART IrDA Test
+//!
+//! UART1 transmits IrDA pulses on the TX pin, and the test watches the wire
+//! through the connected RX pin.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    gpio::{Input, InputConfig, Level},
+    uart::{self, ConfigError, IrdaConfig, Uart},
+    Blocking,
+};
+use hil_test as _;
+
+struct Context {
+    uart: Uart<'static, Blocking>,
+    wire: Input<'static>,
+}
+
+fn irda_config(irda: IrdaConfig) -> uart::Config {
+    uart::Config::default().with_irda(irda.with_enable(true))
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3)]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        let uart = Uart::new(
+            peripherals.UART1,
+            irda_config(IrdaConfig::default().with_loopback(true)),
+        )
+        .unwrap()
+        .with_tx(tx);
+        let wire = Input::new(rx, InputConfig::default());
+
+        Context { uart, wire }
+    }
+
+    #[test]
+    fn loopback_round_trips(mut ctx: Context) {
+        let data = [0x00, 0x55, 0xAA, 0xFF, 0x42];
+
+        ctx.uart.write_bytes(&data).unwrap();
+        ctx.uart.flush();
+
+        let mut buffer = [0; 5];
+        ctx.uart.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, data);
+    }
+
+    #[test]
+    fn tx_idles_with_the_led_off(mut ctx: Context) {
+        assert_eq!(ctx.wire.level(), Level::Low);
+
+        ctx.uart.write_bytes(&[0x00; 4]).unwrap();
+        ctx.uart.flush();
+        assert_eq!(ctx.wire.level(), Level::Low);
+
+        ctx.uart
+            .apply_config(&irda_config(IrdaConfig::default().with_tx_invert(true)))
+            .unwrap();
+        assert_eq!(ctx.wire.level(), Level::High);
+
+        ctx.uart.apply_config(&uart::Config::default()).unwrap();
+        assert_eq!(ctx.wire.level(), Level::High);
+    }
+
+    #[test]
+    fn dropping_the_driver_keeps_the_led_off(ctx: Context) {
+        core::mem::drop(ctx.uart);
+        assert_eq!(ctx.wire.level(), Level::Low);
+    }
+
+    #[test]
+    fn high_baud_rates_are_rejected(mut ctx: Context) {
+        let config = irda_config(IrdaConfig::default()).with_baudrate(230_400);
+        assert_eq!(
+            ctx.uart.apply_config(&config),
+            Err(ConfigError::UnsupportedIrdaBaudrate)
+        );
+
+        let config = irda_config(IrdaConfig::default()).with_baudrate(9600);
+        ctx.uart.apply_config(&config).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+//! UART RS-485 Test
+//!
+//! UART0 and UART1 share a single wire: UART0 transmits on it and UART1
+//! receives. UART0 can listen to the wire, too, like a transceiver whose
+//! receiver stays enabled. UART0's driver-enable signal goes to an
+//! unconnected pin, where the test observes it.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use esp_hal::{
+    gpio::{interconnect::InputSignal, AnyPin, Level, Pin},
+    peripheral::Peripheral,
+    uart::{self, DePolarity, Error, Rs485Config, RxConfig, Uart, UartRx},
+    Blocking,
+};
+use hil_test as _;
+
+struct Context {
+    uart0: Uart<'static, Blocking>,
+    uart1: UartRx<'static, Blocking>,
+    wire: AnyPin,
+    de: InputSignal,
+}
+
+fn rs485_config() -> uart::Config {
+    uart::Config::default().with_rs485(Rs485Config::default().with_enable(true))
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 3, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+        let wire = rx.degrade();
+        let (de, de_output) = hil_test::unconnected_pin!(peripherals).split();
+
+        let uart0 = Uart::new(peripherals.UART0, rs485_config())
+            .unwrap()
+            .with_rx(unsafe { wire.clone_unchecked() })
+            .with_tx(tx)
+            .with_rts(de_output);
+        let uart1 = UartRx::new(peripherals.UART1, uart::Config::default())
+            .unwrap()
+            .with_rx(unsafe { wire.clone_unchecked() });
+
+        Context {
+            uart0,
+            uart1,
+            wire,
+            de,
+        }
+    }
+
+    #[test]
+    fn write_returns_after_transmission(mut ctx: Context) {
+        let data = [0x55; 16];
+
+        assert_eq!(ctx.de.level(), Level::Low);
+        ctx.uart0.write_bytes(&data).unwrap();
+        assert_eq!(ctx.de.level(), Level::Low);
+
+        // Without waiting, everything has been received already.
+        let mut buffer = [0; 32];
+        let len = ctx.uart1.read_buffered_bytes(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], &data);
+
+        // Without collision detection, UART0 doesn't hear itself.
+        assert_eq!(ctx.uart0.read_buffered_bytes(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    async fn de_is_asserted_during_async_write(ctx: Context) {
+        let mut uart0 = ctx.uart0.into_async();
+        let mut uart1 = ctx.uart1.into_async();
+        uart1
+            .apply_config(
+                &uart::Config::default().with_rx(RxConfig::default().with_fifo_full_threshold(1)),
+            )
+            .unwrap();
+
+        let data = [0xA5; 16];
+        let mut buffer = [0; 16];
+
+        let (written, de_level) = embassy_futures::join::join(uart0.write_async(&data), async {
+            let len = uart1.read_async(&mut buffer).await.unwrap();
+            (len, ctx.de.level())
+        })
+        .await;
+
+        assert_eq!(written.unwrap(), data.len());
+        let (len, de_level) = de_level;
+        assert!(len < data.len());
+        assert_eq!(de_level, Level::High);
+        assert_eq!(ctx.de.level(), Level::Low);
+
+        let mut received = len;
+        while received < data.len() {
+            received += uart1.read_async(&mut buffer[received..]).await.unwrap();
+        }
+        assert_eq!(buffer, data);
+    }
+
+    #[test]
+    fn de_polarity_can_be_inverted(mut ctx: Context) {
+        let config = uart::Config::default().with_rs485(
+            Rs485Config::default()
+                .with_enable(true)
+                .with_de_polarity(DePolarity::ActiveLow),
+        );
+        ctx.uart0.apply_config(&config).unwrap();
+
+        assert_eq!(ctx.de.level(), Level::High);
+        ctx.uart0.write_bytes(&[0x42]).unwrap();
+        assert_eq!(ctx.de.level(), Level::High);
+
+        let mut byte = [0];
+        ctx.uart1.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte, [0x42]);
+    }
+
+    #[test]
+    fn collisions_are_detected(ctx: Context) {
+        let config = uart::Config::default().with_rs485(
+            Rs485Config::default()
+                .with_enable(true)
+                .with_collision_detection(true),
+        );
+        let mut uart0 = ctx.uart0;
+        uart0.apply_config(&config).unwrap();
+
+        // UART0 hears what it sends...
+        uart0.write_bytes(&[0x42]).unwrap();
+        let mut byte = [0];
+        uart0.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte, [0x42]);
+
+        // ... but not if somebody else holds the wire high. The DE signal is
+        // high while UART0 sends zeroes.
+        let mut uart0 = uart0.with_rx(ctx.de);
+        assert_eq!(uart0.write_bytes(&[0x00; 4]), Err(Error::CollisionDetected));
+
+        // The error is only reported by the write that collided.
+        let mut uart0 = uart0.with_rx(ctx.wire);
+        uart0.write_bytes(&[0x42]).unwrap();
+    }
+}
@@ -7,7 +7,8 @@
 #![no_main]
 
 use esp_hal::{
-    uart::{self, UartRx, UartTx},
+    time::{self, Duration},
+    uart::{self, TxConfig, UartRx, UartTx},
     Blocking,
 };
 use hil_test as _;
@@ -62,4 +63,57 @@ mod tests {
 
         assert_eq!(buf, bytes);
     }
+
+    #[test]
+    fn halves_of_different_uarts_cant_be_reunited(ctx: Context) {
+        let Err(error) = ctx.tx.reunite(ctx.rx) else {
+            panic!("UART0 and UART1 have been reunited");
+        };
+
+        // The halves are handed back, and still work.
+        let (mut rx, mut tx) = (error.rx, error.tx);
+        tx.write_bytes(&[0x42]).unwrap();
+        let mut byte = [0u8; 1];
+        rx.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte[0], 0x42);
+    }
+
+    #[test]
+    fn flush_tx_done_waits_for_the_last_bit(mut ctx: Context) {
+        let config = uart::Config::default().with_baudrate(9600);
+        ctx.tx.apply_config(&config).unwrap();
+        ctx.rx.apply_config(&config).unwrap();
+
+        let start = time::now();
+        ctx.tx.write_bytes(&[0x55; 4]).unwrap();
+        ctx.tx.flush_tx_done();
+        let elapsed = time::now() - start;
+
+        // 4 bytes of 10 bits take 4.17 ms at 9600 baud.
+        assert!(elapsed >= Duration::micros(4_100), "{}", elapsed);
+        assert!(elapsed < Duration::micros(5_000), "{}", elapsed);
+
+        // The receiver has seen the stop bit of the last byte.
+        let mut buffer = [0; 8];
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(4));
+    }
+
+    #[test]
+    fn dropping_the_transmitter_can_wait_for_the_data(mut ctx: Context) {
+        let config = uart::Config::default()
+            .with_baudrate(9600)
+            .with_tx(TxConfig::default().with_flush_on_drop(true));
+        ctx.tx.apply_config(&config).unwrap();
+        ctx.rx.apply_config(&config).unwrap();
+
+        let data: [u8; 8] = core::array::from_fn(|i| i as u8);
+        ctx.tx.write_bytes(&data).unwrap();
+        let start = time::now();
+        core::mem::drop(ctx.tx);
+        assert!(time::now() - start >= Duration::micros(7_000));
+
+        let mut buffer = [0; 8];
+        ctx.rx.read_bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, data);
+    }
 }
@@ -7,6 +7,7 @@
 #![no_main]
 
 use esp_hal::{
+    time::{self, Duration},
     uart::{self, UartRx, UartTx},
     Async,
 };
@@ -51,4 +52,68 @@ mod tests {
 
         assert_eq!(read, byte);
     }
+
+    #[test]
+    async fn read_until_idle_returns_a_whole_packet(mut ctx: Context) {
+        // Longer than the FIFO, so the read is woken up before the line is idle.
+        let packet: [u8; 200] = core::array::from_fn(|i| i as u8);
+        let mut buffer = [0u8; 256];
+
+        let (written, read) = embassy_futures::join::join(
+            ctx.tx.write_async(&packet),
+            ctx.rx.read_until_idle_async(&mut buffer),
+        )
+        .await;
+
+        assert_eq!(written.unwrap(), packet.len());
+        assert_eq!(read.unwrap(), packet.len());
+        assert_eq!(&buffer[..packet.len()], &packet[..]);
+    }
+
+    #[test]
+    async fn read_until_idle_stops_when_the_buffer_is_full(mut ctx: Context) {
+        let packet: [u8; 24] = core::array::from_fn(|i| i as u8);
+        let mut buffer = [0u8; 16];
+
+        ctx.tx.write_async(&packet).await.unwrap();
+
+        let read = ctx.rx.read_until_idle_async(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..read], &packet[..16]);
+
+        let read = ctx.rx.read_until_idle_async(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..read], &packet[16..]);
+    }
+
+    #[test]
+    async fn flush_tx_done_waits_for_the_last_bit(mut ctx: Context) {
+        let config = uart::Config::default().with_baudrate(9600);
+        ctx.tx.apply_config(&config).unwrap();
+        ctx.rx.apply_config(&config).unwrap();
+
+        let start = time::now();
+        ctx.tx.write_async(&[0x55; 4]).await.unwrap();
+        ctx.tx.flush_tx_done_async().await;
+        let elapsed = time::now() - start;
+
+        // 4 bytes of 10 bits take 4.17 ms at 9600 baud.
+        assert!(elapsed >= Duration::micros(4_100), "{}", elapsed);
+
+        let mut buffer = [0; 8];
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(4));
+    }
+
+    #[test]
+    async fn wait_tx_done_returns_when_idle(mut ctx: Context) {
+        // Nothing has been sent, so there is nothing to wait for.
+        ctx.tx.wait_tx_done().await;
+
+        let config = uart::Config::default().with_baudrate(9600);
+        ctx.tx.apply_config(&config).unwrap();
+        ctx.rx.apply_config(&config).unwrap();
+
+        ctx.tx.write_async(&[0x55; 2]).await.unwrap();
+        ctx.tx.wait_tx_done().await;
+        let mut buffer = [0; 8];
+        assert_eq!(ctx.rx.read_buffered_bytes(&mut buffer), Ok(2));
+    }
 }
@@ -0,0 +1,96 @@
+//! UART wakeup test
+//!
+//! A second UART sends the wake-up pattern to the receiving UART, which wakes
+//! the chip up from light sleep and then receives the data that follows.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32s3
+//% FEATURES: unstable
+
+#![no_std]
+#![no_main]
+
+use core::time::Duration;
+
+use esp_hal::{
+    delay::Delay,
+    reset::SleepSource,
+    rtc_cntl::{
+        sleep::{TimerWakeupSource, UartWakeupSource},
+        wakeup_cause,
+        Rtc,
+    },
+    uart::{self, Uart, UartRx, UartTx},
+    Blocking,
+};
+use hil_test as _;
+
+// A `0x55` byte has 5 rising edges, counting the one into the stop bit.
+const WAKEUP_PATTERN: [u8; 32] = [0x55; 32];
+const WAKEUP_EDGES: u8 = 10;
+
+struct Context {
+    rtc: Rtc<'static>,
+    rx: UartRx<'static, Blocking>,
+    tx: UartTx<'static, Blocking>,
+    delay: Delay,
+}
+
+#[cfg(test)]
+#[embedded_test::tests(default_timeout = 5, executor = hil_test::Executor::new())]
+mod tests {
+    use super::*;
+
+    #[init]
+    fn init() -> Context {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let (rx, tx) = hil_test::common_test_pins!(peripherals);
+
+        // Slow enough for the pattern to last through the start of the sleep.
+        let config = uart::Config::default().with_baudrate(9600);
+        let mut rx = Uart::new(peripherals.UART1, config)
+            .unwrap()
+            .with_rx(rx)
+            .split()
+            .0;
+        let tx = Uart::new(peripherals.UART0, config)
+            .unwrap()
+            .with_tx(tx)
+            .split()
+            .1;
+
+        rx.wakeup_enable(WAKEUP_EDGES).unwrap();
+
+        Context {
+            rtc: Rtc::new(peripherals.LPWR),
+            rx,
+            tx,
+            delay: Delay::new(),
+        }
+    }
+
+    #[test]
+    fn second_uart_wakes_the_chip_up(mut ctx: Context) {
+        ctx.tx.write_bytes(&WAKEUP_PATTERN).unwrap();
+
+        let uart = UartWakeupSource::new();
+        // Ends the test if the UART doesn't wake the chip up.
+        let timer = TimerWakeupSource::new(Duration::from_secs(1));
+        ctx.rtc.sleep_light(&[&uart, &timer]);
+
+        assert!(matches!(wakeup_cause(), SleepSource::Uart));
+
+        // Discard what's left of the pattern. The bytes received while the
+        // clocks were starting up may be broken, so errors are ignored too.
+        ctx.tx.flush();
+        ctx.delay.delay_millis(5);
+        let mut discarded = [0u8; WAKEUP_PATTERN.len()];
+        while !matches!(ctx.rx.read_buffered_bytes(&mut discarded), Ok(0)) {}
+
+        let data = b"AT+DATA\n";
+        ctx.tx.write_bytes(data).unwrap();
+        let mut received = [0u8; 8];
+        ctx.rx.read_bytes(&mut received).unwrap();
+        assert_eq!(&received, data);
+    }
+}
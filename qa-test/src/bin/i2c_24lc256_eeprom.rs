@@ -0,0 +1,75 @@
+//! Writes 4 KiB to a 24LC256 EEPROM and reads it back
+//!
+//! The EEPROM accepts writes of up to one 64 byte page at a time, which,
+//! together with the two address bytes, is still more than the I2C FIFO holds.
+//! The whole 4 KiB is then read back in a single transfer. Both directions rely
+//! on the driver refilling and draining the FIFO while the transfer runs.
+//!
+//! The following wiring is assumed:
+//! - SDA => GPIO4
+//! - SCL => GPIO5
+//!
+//! The address pins of the EEPROM are expected to be tied low.
+
+//% CHIPS: esp32 esp32c2 esp32c3 esp32c6 esp32h2 esp32s2 esp32s3
+//% TAG: 24lc256
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{
+    i2c::master::{Config, Error, I2c},
+    main,
+    time::RateExtU32,
+};
+use esp_println::println;
+
+const EEPROM_ADDRESS: u8 = 0x50;
+const PAGE_SIZE: usize = 64;
+const LEN: usize = 4096;
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let mut i2c = I2c::new(
+        peripherals.I2C0,
+        Config::default().with_frequency(400.kHz()),
+    )
+    .unwrap()
+    .with_sda(peripherals.GPIO4)
+    .with_scl(peripherals.GPIO5);
+
+    let mut pattern = [0u8; LEN];
+    for (i, byte) in pattern.iter_mut().enumerate() {
+        *byte = (i ^ (i >> 8)) as u8;
+    }
+
+    for (page, data) in pattern.chunks(PAGE_SIZE).enumerate() {
+        let mut buffer = [0u8; 2 + PAGE_SIZE];
+        buffer[..2].copy_from_slice(&((page * PAGE_SIZE) as u16).to_be_bytes());
+        buffer[2..].copy_from_slice(data);
+
+        i2c.write(EEPROM_ADDRESS, &buffer).unwrap();
+
+        // The EEPROM doesn't acknowledge its address until the page is written.
+        while let Err(Error::AcknowledgeCheckFailed(_)) = i2c.write(EEPROM_ADDRESS, &[]) {}
+    }
+
+    let mut read_back = [0u8; LEN];
+    i2c.write_read(EEPROM_ADDRESS, &[0, 0], &mut read_back)
+        .unwrap();
+
+    if read_back == pattern {
+        println!("Read back {} bytes", LEN);
+    } else {
+        let first = read_back
+            .iter()
+            .zip(pattern.iter())
+            .position(|(a, b)| a != b);
+        println!("Mismatch at offset {:?}", first);
+    }
+
+    loop {}
+}
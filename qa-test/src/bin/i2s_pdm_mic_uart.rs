@@ -0,0 +1,72 @@
+//! Streams the audio of a PDM microphone over UART.
+//!
+//! The I2S peripheral decimates the PDM stream into 16 kHz, 16-bit mono PCM,
+//! which is written to UART1 as raw little-endian samples. On the host, the
+//! stream can be played back with e.g.
+//! `sox -t raw -r 16000 -e signed -b 16 -c 1 /dev/ttyUSB1 -d`.
+//!
+//! The following wiring is assumed:
+//! - PDM CLK => GPIO4
+//! - PDM DAT => GPIO5
+//! - The microphone set up to drive the left channel, on the rising clock edge
+//! - UART1 TX => GPIO6, 1 Mbaud 8N1
+
+//% CHIPS: esp32 esp32s3
+
+#![no_std]
+#![no_main]
+
+use esp_backtrace as _;
+use esp_hal::{
+    dma_buffers,
+    i2s::master::{I2s, PdmChannels, PdmRxConfig},
+    main,
+    time::RateExtU32,
+    uart::{self, Uart},
+};
+use esp_println::println;
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "esp32")] {
+            let dma_channel = peripherals.DMA_I2S0;
+        } else {
+            let dma_channel = peripherals.DMA_CH0;
+        }
+    }
+
+    let (rx_buffer, rx_descriptors, _, _) = dma_buffers!(4 * 4092, 0);
+
+    let i2s = I2s::new_pdm_rx(
+        peripherals.I2S0,
+        PdmRxConfig::default().with_channels(PdmChannels::Left),
+        16000.Hz(),
+        dma_channel,
+        rx_descriptors,
+    );
+
+    let mut i2s_rx = i2s
+        .i2s_rx
+        .with_ws(peripherals.GPIO4)
+        .with_din(peripherals.GPIO5)
+        .build();
+
+    let mut uart = Uart::new(
+        peripherals.UART1,
+        uart::Config::default().with_baudrate(1_000_000),
+    )
+    .unwrap()
+    .with_tx(peripherals.GPIO6);
+
+    println!("Streaming");
+
+    let mut samples = [0u8; 4 * 4092];
+    let mut transfer = i2s_rx.read_dma_circular(rx_buffer).unwrap();
+    loop {
+        let count = transfer.pop(&mut samples).unwrap();
+        uart.write_bytes(&samples[..count]).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+//! Demonstrates light sleep with GPIO wakeup
+//!
+//! The following wiring is assumed:
+//! - Wakeup button => GPIO0 for ESP32 and ESP32-S3, GPIO9 otherwise (low level)
+//!
+//! The pin doesn't need to be RTC capable, any GPIO can wake the chip from
+//! light sleep.
+
+//% CHIPS: esp32 esp32c3 esp32c6 esp32s3 esp32c2
+
+#![no_std]
+#![no_main]
+
+use core::time::Duration;
+
+use esp_backtrace as _;
+use esp_hal::{
+    delay::Delay,
+    gpio::{Input, InputConfig, Pull, WakeEvent},
+    main,
+    rtc_cntl::{
+        sleep::{GpioWakeupSource, TimerWakeupSource},
+        wakeup_cause,
+        Rtc,
+    },
+};
+use esp_println::println;
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let delay = Delay::new();
+    let mut rtc = Rtc::new(peripherals.LPWR);
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(feature = "esp32", feature = "esp32s3"))] {
+            let pin = peripherals.GPIO0;
+        } else {
+            let pin = peripherals.GPIO9;
+        }
+    }
+
+    let config = InputConfig::default().with_pull(Pull::Up);
+    let mut button = Input::new(pin, config);
+    button.wakeup_enable(true, WakeEvent::LowLevel).unwrap();
+
+    let gpio = GpioWakeupSource::new();
+    let timer = TimerWakeupSource::new(Duration::from_secs(10));
+
+    println!("up and runnning!");
+    loop {
+        println!("sleeping!");
+        delay.delay_millis(100);
+        rtc.sleep_light(&[&gpio, &timer]);
+
+        println!("wake reason: {:?}", wakeup_cause());
+
+        // Wait for the button to be released so the next sleep isn't ended
+        // right away.
+        while button.is_low() {}
+    }
+}
@@ -0,0 +1,80 @@
+//! Demonstrates light sleep with UART wakeup
+//!
+//! The following wiring is assumed:
+//! - External UART TX => GPIO4 (UART1 RX), 115200 baud 8N1
+//!
+//! To wake the chip up, send at least three `U` (0x55) characters, wait a few
+//! milliseconds, then send a line of text ending with `\n`. The characters
+//! that wake the chip up are lost, the line is echoed to the console.
+
+//% CHIPS: esp32 esp32c3 esp32c6 esp32s3 esp32c2
+
+#![no_std]
+#![no_main]
+
+use core::time::Duration;
+
+use esp_backtrace as _;
+use esp_hal::{
+    delay::Delay,
+    main,
+    rtc_cntl::{
+        sleep::{TimerWakeupSource, UartWakeupSource},
+        wakeup_cause,
+        Rtc,
+    },
+    time,
+    uart::{self, Uart},
+};
+use esp_println::println;
+
+#[main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let delay = Delay::new();
+    let mut rtc = Rtc::new(peripherals.LPWR);
+
+    let mut uart = Uart::new(peripherals.UART1, uart::Config::default())
+        .unwrap()
+        .with_rx(peripherals.GPIO4);
+    // A `U` (0x55) has 5 rising edges, counting the one into the stop bit, so
+    // the chip wakes up at the end of the third `U`.
+    uart.wakeup_enable(15).unwrap();
+
+    let wake_uart = UartWakeupSource::new();
+    let timer = TimerWakeupSource::new(Duration::from_secs(10));
+
+    println!("up and runnning!");
+    loop {
+        println!("sleeping!");
+        delay.delay_millis(100);
+        rtc.sleep_light(&[&wake_uart, &timer]);
+
+        println!("wake reason: {:?}", wakeup_cause());
+
+        // Skip what's left of the wake-up pattern.
+        let mut line = [0u8; 64];
+        let mut len = 0;
+        let mut byte = [0u8; 1];
+        let deadline = time::now() + time::Duration::secs(1);
+        while time::now() < deadline && len < line.len() {
+            if uart.read_buffered_bytes(&mut byte).unwrap_or(0) == 0 {
+                continue;
+            }
+            match byte[0] {
+                b'U' if len == 0 => {}
+                b'\n' => break,
+                b => {
+                    line[len] = b;
+                    len += 1;
+                }
+            }
+        }
+
+        println!(
+            "received: {:?}",
+            core::str::from_utf8(&line[..len]).unwrap_or("<invalid UTF-8>")
+        );
+    }
+}
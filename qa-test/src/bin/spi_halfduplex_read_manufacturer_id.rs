@@ -1,4 +1,4 @@
-//! SPI read manufacturer id from flash chip
+//! SPI read manufacturer id and JEDEC id from flash chip
 //!
 //! The following wiring is assumed:
 //! - SCLK        =>  GPIO0
@@ -80,6 +80,19 @@ fn main() -> ! {
     let delay = Delay::new();
 
     loop {
+        // READ JEDEC ID FROM FLASH CHIP
+        let mut data = [0u8; 3];
+        spi.half_duplex_read(
+            DataMode::SingleTwoDataLines,
+            Command::_8Bit(0x9F, DataMode::SingleTwoDataLines),
+            Address::None,
+            0,
+            &mut data,
+        )
+        .unwrap();
+        println!("JEDEC {:x?}", data);
+        delay.delay_millis(250);
+
         // READ MANUFACTURER ID FROM FLASH CHIP
         let mut data = [0u8; 2];
         spi.half_duplex_read(